@@ -3,10 +3,50 @@ title =
     Fish Folk
     Punchy
 start-game = Start Game
+new-game-plus = New Game Plus
+endless-mode = Endless Mode
+tutorial = Tutorial
+training = Training
+survival = Survival
+daily-challenge = Daily Challenge
+daily-challenge-attempted = Come back tomorrow for a new daily challenge
+high-scores = High Scores
+versus = Versus
+one-vs-one = 1 vs 1
+two-vs-two = 2 vs 2
 settings = Settings
 quit = Quit
 cancel = Cancel
 save = Save
+back = Back
+insert-coin = INSERT COIN
+press-any-button = Press any button to play
+
+# Extras
+extras = Extras
+concept-art = Concept Art
+music = Music
+enemy-bios = Enemy Bios
+no-extras = Nothing here yet
+locked = Locked
+unlock-campaign = Finish the campaign to unlock
+unlock-new-game-plus = Finish New Game Plus to unlock
+unlock-survival-score = Survival score needed to unlock
+play = Play
+now-playing = Now playing
+art-beach-sky = The Beach: Sky
+art-beach-dunes = The Beach: Dunes
+art-beach-shore = The Beach: Shore
+track-title-screen = Fishycuffs
+track-beach = Beach Mayhem
+track-deep-sea = Deep Sea
+track-atlantis = Atlantis
+track-the-unknown = The Unknown
+track-home-sweet-home = Home Sweet Home
+bio-bandit = A small-time crook who joined the gang for the snacks, and stayed for the fights.
+bio-slinger = Never gets close if a rock can do the job for it.
+bio-brute = Doesn't say much, and doesn't have to.
+bio-big-bass = The self-proclaimed ruler of the beach, who takes it very personally when you flop around on it.
 
 # Pause Menu
 paused = Paused
@@ -15,9 +55,48 @@ main-menu = Main Menu
 
 # Settings Menu
 controls = Controls
+video = Video
+accessibility = Accessibility
 sound = Sound
 reset = Reset
 
+# Video
+window-mode = Window Mode
+windowed = Windowed
+borderless = Borderless
+fullscreen = Fullscreen
+resolution = Resolution
+auto = Auto
+monitor = Monitor
+vsync = VSync
+frame-rate-limit = Frame Rate Limit
+uncapped = Uncapped
+dynamic-resolution = Dynamic Resolution
+pause-on-focus-loss = Pause When Unfocused
+mute-in-background = Mute When Unfocused
+reduce-background-frame-rate = Lower Frame Rate When Unfocused
+on = On
+off = Off
+
+# Accessibility
+reduced-damage = Reduced Damage
+slower-game-speed = Slower Game Speed
+auto-block = Auto Block
+extra-lives = Extra Lives
+assisted = Assisted
+played-with-assists = Played with assists
+high-contrast-flashes = High Contrast Hit Flashes
+menu-narration = Menu Narration
+player-outline = Player Outline
+enemy-outline = Enemy Outline
+hud-palette = HUD Colors
+default = Default
+blue = Blue
+orange = Orange
+yellow = Yellow
+white = White
+black = Black
+
 # Controls
 action = Action
 keyboard-1 = Keyboard 1
@@ -30,10 +109,127 @@ move-right = Move Right
 flop-attack = Flop Attack
 shoot = Shoot
 throwgrab = Throw/Grab
+block = Block
+dodge = Dodge
+special = Special
+pause = Pause
+hold-to-pause = Hold to Pause
 bind-input = Press an input or press Escape to cancel.
 
 # Debug Tools
+cheats-enabled = Cheats Enabled
 debug-tools = Debug Tools
 show-collision-shapes = Show Collision Shapes
 show-world-inspector = Show World Inspector
-show-ysort-lines = Show Y-Sort Lines
\ No newline at end of file
+show-ysort-lines = Show Y-Sort Lines
+missing-localization-keys = Missing Localization Keys
+rng-seed = RNG Seed
+debug-console = Debug Console
+pause-gameplay = Pause Gameplay
+step-gameplay = Step
+time-scale = Time Scale
+debug-console-hint = Enter a command, or `help` for the list of commands
+
+# Performance Overlay
+performance-overlay = Performance Overlay
+fps = FPS
+frame-time = Frame Time
+max = max
+entities = Entities
+archetypes = Archetypes
+stage-timings = Stage Timings
+
+# Frame Data Tuning
+frame-data-tuning = Frame Data Tuning
+fighter = Fighter
+pause-animation = Pause Animation
+animation = Animation
+frame = Frame
+attack = Attack
+startup-frame = Startup Frame
+active-frame = Active Frame
+recovery-frame = Recovery Frame
+hitbox-size = Hitbox Size
+hitbox-offset = Hitbox Offset
+save-to-file = Save to File
+
+# Captures
+screenshot-saved = Screenshot saved
+screenshot-failed = Could not save the screenshot
+bug-report-saved = Bug report saved
+bug-report-failed = Could not save the bug report
+crashed = The game crashed
+crash-report-saved = The last time the game was played, it crashed. A crash report that can be attached to an issue was saved to:
+
+# Training Mode
+dummy = Dummy
+start-training = Start Training
+dummy-stand = Stand
+dummy-block = Block
+dummy-random = Random
+hitboxes = Hitboxes
+hurtboxes = Hurtboxes
+grab-ranges = Grab Ranges
+attack-origins = Attack Origins
+last-hit-damage = Last Hit Damage
+combo = Combo
+frame-advantage = Frame Advantage
+input-history = Input History
+reset-positions = Reset Positions
+
+# Survival Mode
+wave = Wave
+waves = Waves
+score = Score
+your-score = Your Score
+no-high-scores = No high scores yet
+
+# Arcade Mode
+arcade = Arcade
+lives = Lives
+continues = Continues
+continue-question = Continue?
+press-confirm-to-continue = Press confirm to continue
+continues-left = Continues left
+
+# Versus Mode
+round = Round
+team = Team
+team-up = Team Up!
+wins-the-round = wins the round!
+wins-the-match = wins the match!
+draw = Draw!
+player = Player
+damage-dealt = Damage Dealt
+knockouts = Knockouts
+rematch = Rematch
+
+# Tutorial
+tutorial-move = Move around
+tutorial-attack = Attack
+tutorial-pick-up = Pick up the bottle
+tutorial-throw = Throw it
+
+# Blocking
+blocking = Blocking
+parry = Parry!
+
+# Interaction prompts
+interact-pick-up = Pick up
+interact-coat-weapon = Coat weapon
+
+# Enemy Nameplates
+boss = Boss
+enemy-bandit = Bandit
+enemy-veteran-bandit = Veteran Bandit
+enemy-slinger = Slinger
+enemy-veteran-slinger = Veteran Slinger
+enemy-brute = Brute
+enemy-veteran-brute = Veteran Brute
+enemy-grappler-brute = Grappler Brute
+enemy-big-bass = Big Bass
+
+# Dialogues
+dialogue-beach-boss-1 = Who dares to flop around on MY beach?
+dialogue-beach-boss-2 = We're just passing through, big fish.
+dialogue-beach-boss-3 = Then you'll be passing through my belly!
@@ -1,5 +1,6 @@
 # Main Menu
 start-game = Démarrer Jeu
+endless-mode = Mode Infini
 
 # Pause Menu
 paused = En Pause
@@ -0,0 +1,43 @@
+// Draws a tile of a spritesheet with its colors replaced by a row of a palette lookup image. The
+// first row of the lookup image holds the colors to replace.
+
+struct PaletteSwap {
+    // The color that the sprite is tinted with
+    color: vec4<f32>,
+    // The corners of the tile in the texture, in pixels
+    tile_min: vec2<f32>,
+    tile_max: vec2<f32>,
+    // 1.0 on the axes that the sprite is flipped on
+    flip: vec2<f32>,
+    // The row of the recolor in the lookup image
+    row: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> settings: PaletteSwap;
+@group(1) @binding(1)
+var sprite_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var palette_texture: texture_2d<f32>;
+
+@fragment
+fn fragment(
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+    // Read the texel of the tile directly, so that the colors are looked up exactly
+    let coords = mix(uv, vec2<f32>(1.0) - uv, settings.flip);
+    let last_texel = vec2<i32>(settings.tile_max) - vec2<i32>(1);
+    let texel = min(vec2<i32>(floor(mix(settings.tile_min, settings.tile_max, coords))), last_texel);
+    var color = textureLoad(sprite_texture, texel, 0);
+
+    let columns = i32(textureDimensions(palette_texture).x);
+    for (var column = 0; column < columns; column = column + 1) {
+        let key = textureLoad(palette_texture, vec2<i32>(column, 0), 0);
+        if (key.a > 0.0 && all(abs(key - color) < vec4<f32>(0.002))) {
+            color = textureLoad(palette_texture, vec2<i32>(column, i32(settings.row)), 0);
+            break;
+        }
+    }
+
+    return color * settings.color;
+}
@@ -0,0 +1,57 @@
+// Full-screen post-processing effects applied to the game camera, configured per level.
+
+struct PostProcessing {
+    // The color to grade the image towards, with the intensity in the alpha channel
+    color_grade: vec4<f32>,
+    crt: f32,
+    heat_haze: f32,
+    desaturation: f32,
+    time: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> settings: PostProcessing;
+@group(1) @binding(1)
+var source_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var source_sampler: sampler;
+
+@fragment
+fn fragment(
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+    var coords = uv;
+
+    // Heat haze: offset the sample position with a slowly moving wave
+    let haze = settings.heat_haze * 0.004;
+    coords.x = coords.x + sin(coords.y * 60.0 + settings.time * 3.0) * haze;
+    coords.y = coords.y + cos(coords.x * 40.0 + settings.time * 2.0) * haze * 0.5;
+
+    // CRT: barrel distortion around the center of the screen
+    let centered = coords * 2.0 - 1.0;
+    let distortion = settings.crt * 0.08;
+    coords = (centered * (1.0 + distortion * dot(centered, centered))) * 0.5 + 0.5;
+
+    if (coords.x < 0.0 || coords.x > 1.0 || coords.y < 0.0 || coords.y > 1.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    var color = textureSample(source_texture, source_sampler, coords).rgb;
+
+    // CRT: scanlines and vignette
+    let dimensions = vec2<f32>(textureDimensions(source_texture));
+    let scanline = 0.5 + 0.5 * sin(coords.y * dimensions.y * 3.14159);
+    color = color * mix(1.0, 0.75 + 0.25 * scanline, settings.crt);
+    let vignette = 1.0 - dot(centered, centered) * 0.25;
+    color = color * mix(1.0, vignette, settings.crt);
+
+    // Color grade: blend the luminance-preserving tint over the image
+    let luminance = dot(color, vec3<f32>(0.299, 0.587, 0.114));
+    color = mix(color, luminance * settings.color_grade.rgb * 1.5, settings.color_grade.a);
+
+    // Desaturation: drain the colors out of the image, for cinematics
+    let gray = dot(color, vec3<f32>(0.299, 0.587, 0.114));
+    color = mix(color, vec3<f32>(gray), settings.desaturation);
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,171 @@
+//! Benchmarks of the hot systems of the game, each run on a world filled with entities, without
+//! the rest of the game.
+//!
+//! Run them with `cargo bench`, or along with the scene benchmarks with `just bench`.
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    ecs::schedule::IntoSystemDescriptor,
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+    utils::HashMap,
+};
+use bevy_rapier2d::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use punchy::{
+    animation::{animation_cycling, Animation, Clip},
+    camera::{y_sort, YSort},
+    collision::{BodyLayers, PhysicsBundle},
+    consts,
+    hit_stop::HitStop,
+    item::Item,
+    metadata::ColliderMeta,
+    spatial_hash::{update_spatial_hash, SpatialHash},
+};
+
+/// The number of entities that the systems are run on
+const ENTITIES: usize = 10_000;
+
+/// The number of hurtboxes and attacks in the collision benchmark, which checks every attack
+/// against the hurtboxes around it
+const COLLIDERS: usize = 500;
+
+/// Benchmark a system on the world, calling `prepare` before every run without timing it
+fn bench_system<Params>(
+    c: &mut Criterion,
+    name: &str,
+    mut world: World,
+    system: impl IntoSystemDescriptor<Params>,
+    mut prepare: impl FnMut(&mut World),
+) {
+    ComputeTaskPool::init(TaskPool::default);
+    let mut stage = SystemStage::parallel().with_system(system);
+
+    c.bench_function(name, |b| {
+        b.iter_custom(|iterations| {
+            let mut total = Duration::ZERO;
+            for _ in 0..iterations {
+                prepare(&mut world);
+
+                let start = Instant::now();
+                stage.run(&mut world);
+                total += start.elapsed();
+            }
+            total
+        })
+    });
+}
+
+fn y_sort_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut world = World::new();
+    world.spawn_batch((0..ENTITIES).map(|_| {
+        (
+            Transform::from_xyz(0.0, rng.gen_range(consts::MIN_Y..consts::MAX_Y), 0.0),
+            YSort(consts::FIGHTERS_Z),
+        )
+    }));
+
+    // Move every entity before every run, so that none of them are skipped
+    bench_system(c, "y-sort", world, y_sort, |world| {
+        for mut transform in world.query::<&mut Transform>().iter_mut(world) {
+            transform.translation.y = -transform.translation.y;
+        }
+    });
+}
+
+fn animation_benchmark(c: &mut Criterion) {
+    let mut world = World::new();
+    world.init_resource::<HitStop>();
+    world.spawn_batch((0..ENTITIES).map(|i| {
+        let mut animations = HashMap::default();
+        animations.insert(
+            "idle".to_owned(),
+            Clip {
+                frames: 0..8,
+                repeat: true,
+                ..default()
+            },
+        );
+        let mut animation = Animation::new(1.0 / 12.0, animations);
+        animation.play("idle", true);
+        animation.current_frame = i % 8;
+
+        (TextureAtlasSprite::new(0), animation)
+    }));
+
+    bench_system(c, "animation", world, animation_cycling, |_| ());
+}
+
+fn collision_benchmark(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    // The broad phase of the gameplay systems that look for things in range
+    let mut world = World::new();
+    world.insert_resource(SpatialHash::new(64.0));
+    world.spawn_batch((0..ENTITIES).map(|_| {
+        (
+            Transform::from_xyz(
+                rng.gen_range(0.0..10_000.0),
+                rng.gen_range(consts::MIN_Y..consts::MAX_Y),
+                0.0,
+            ),
+            Item::default(),
+        )
+    }));
+    bench_system(c, "spatial-hash", world, update_spatial_hash, |_| ());
+
+    // The sensors of the attacks and the hurtboxes of the fighters, moving around every step
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default());
+
+    let mut random_position = || {
+        Vec3::new(
+            rng.gen_range(0.0..2_000.0),
+            rng.gen_range(consts::MIN_Y..consts::MAX_Y),
+            0.0,
+        )
+    };
+    let hurtbox = ColliderMeta {
+        size: Vec2::new(24.0, 48.0),
+        offset: Vec2::ZERO,
+    };
+    let attack = ColliderMeta {
+        size: Vec2::new(32.0, 16.0),
+        offset: Vec2::ZERO,
+    };
+    for i in 0..COLLIDERS * 2 {
+        let (meta, layers) = if i % 2 == 0 {
+            (&hurtbox, BodyLayers::ENEMY)
+        } else {
+            (&attack, BodyLayers::PLAYER_ATTACK)
+        };
+        app.world.spawn((
+            PhysicsBundle::new(meta, layers),
+            TransformBundle::from_transform(Transform::from_translation(random_position())),
+        ));
+    }
+
+    c.bench_function("collision", |b| {
+        b.iter(|| {
+            for mut transform in app.world.query::<&mut Transform>().iter_mut(&mut app.world) {
+                transform.translation.x = (transform.translation.x + 7.0) % 2_000.0;
+            }
+            app.update();
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    y_sort_benchmark,
+    animation_benchmark,
+    collision_benchmark
+);
+criterion_main!(benches);
@@ -8,13 +8,14 @@ is_release="$1"
 target=wasm32-unknown-unknown
 target_dir="web-target"
 
-release_arg=""
+profile_arg=""
 build_kind="debug"
 dist_dir="$target_dir/wasm-debug"
 
 if [ "$is_release" == "release" ]; then
-    release_arg="--release"
-    build_kind="release"
+    # Optimized for size, see the `wasm-release` profile in `Cargo.toml`
+    profile_arg="--profile wasm-release"
+    build_kind="wasm-release"
     dist_dir="$target_dir/wasm-release"
 fi
 
@@ -22,9 +23,24 @@ export CARGO_TARGET_DIR=$target_dir
 
 set -ex
 
-cargo build --target $target $release_arg
+cargo build --target $target $profile_arg
 rm -rf $dist_dir
 mkdir -p $dist_dir
 wasm-bindgen --out-dir $dist_dir --target web --no-typescript $target_dir/$target/$build_kind/punchy.wasm
 cp wasm_resources/index.html $dist_dir/index.html
-cp -r assets $dist_dir
+
+if [ "$is_release" == "release" ]; then
+    # Shrink the module further, if binaryen is installed
+    if command -v wasm-opt > /dev/null; then
+        wasm-opt -Os --strip-debug -o $dist_dir/punchy_bg.wasm $dist_dir/punchy_bg.wasm
+    fi
+
+    # Pack the assets into a compressed bundle that the page downloads in a single request. The
+    # native build is used to pack them, because the packing validates the assets first.
+    unset CARGO_TARGET_DIR
+    cargo run --release -- --pack-assets $dist_dir/assets.pack
+    # The uncompressed bundle is kept for browsers without `DecompressionStream`
+    gzip -9 -k $dist_dir/assets.pack
+else
+    cp -r assets $dist_dir
+fi
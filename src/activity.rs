@@ -0,0 +1,79 @@
+//! Putting enemies that are far away from the camera to sleep.
+//!
+//! In levels with an [`activity_distance`](LevelMeta::activity_distance), enemies that are further
+//! than that distance outside of the camera view get the [`Dormant`] component. Dormant enemies
+//! don't run their AI or animations, so large levels with many enemies stay cheap, and they wake up
+//! again when the camera gets close to them.
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    camera::camera_half_width,
+    enemy::Enemy,
+    metadata::{GameMeta, LevelMeta},
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// How much further than the activity distance enemies have to be to fall asleep, so that enemies
+/// right at the edge don't switch between waking and sleeping every step
+const DORMANT_MARGIN: f32 = 64.0;
+
+pub struct ActivityPlugin;
+
+impl Plugin for ActivityPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Dormant>()
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .with_system(update_dormant_enemies)
+                    .into(),
+            );
+    }
+}
+
+/// Component on enemies that are too far away from the camera to be updated
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Dormant;
+
+fn update_dormant_enemies(
+    mut commands: Commands,
+    level_meta: Res<LevelMeta>,
+    game_meta: Res<GameMeta>,
+    windows: Res<Windows>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    enemies: Query<(Entity, &Transform, Option<&Dormant>), With<Enemy>>,
+) {
+    let Some(activity_distance) = level_meta.activity_distance else {
+        return;
+    };
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+
+    let half_view_width = camera_half_width(&game_meta, windows.primary());
+    let view_left = camera_transform.translation.x - half_view_width;
+    let view_right = camera_transform.translation.x + half_view_width;
+
+    for (entity, transform, dormant) in &enemies {
+        let x = transform.translation.x;
+        let distance = (view_left - x).max(x - view_right).max(0.0);
+
+        match dormant {
+            Some(_) if distance <= activity_distance => {
+                commands.entity(entity).remove::<Dormant>();
+            }
+            None if distance > activity_distance + DORMANT_MARGIN => {
+                commands.entity(entity).insert(Dormant);
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Afterimages, the fading copies of its sprite that a fighter leaves behind while it does a fast
+//! move.
+//!
+//! A move leaves afterimages behind when its [`AttackMeta`](crate::metadata::AttackMeta) has
+//! [`AfterimageMeta`], and so do the team-up attack and the combo breaker when their own metadata
+//! has it. The fighter leaves them behind for as long as it plays the animation of the move, and
+//! other effects can leave them behind until they remove the [`Afterimages`] again. The copies are
+//! pooled, because fast fighters leave a lot of them behind.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    animation::Animation,
+    lifetime::Lifetime,
+    metadata::AfterimageMeta,
+    pool::{EntityPoolCommands, PoolKind},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct AfterimagePlugin;
+
+impl Plugin for AfterimagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system_set(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                .with_system(leave_afterimages)
+                .with_system(fade_afterimages)
+                .into(),
+        );
+    }
+}
+
+/// Component for a fighter that is leaving afterimages behind
+#[derive(Component)]
+pub struct Afterimages {
+    meta: AfterimageMeta,
+    /// The animation that the afterimages are left behind during, if they aren't left behind until
+    /// the component is removed
+    animation: Option<String>,
+    /// Whether the fighter has started playing the animation
+    has_started: bool,
+    timer: Timer,
+}
+
+impl Afterimages {
+    /// Leave afterimages behind until the component is removed
+    pub fn new(meta: &AfterimageMeta) -> Self {
+        Self {
+            meta: meta.clone(),
+            animation: None,
+            has_started: true,
+            timer: Timer::from_seconds(
+                meta.interval.max(GAMEPLAY_STEP.as_secs_f32()),
+                TimerMode::Repeating,
+            ),
+        }
+    }
+
+    /// Leave afterimages behind while the fighter plays `animation`, which it may only start
+    /// playing in a later step
+    pub fn during_animation(meta: &AfterimageMeta, animation: &str) -> Self {
+        Self {
+            animation: Some(animation.to_owned()),
+            has_started: false,
+            ..Self::new(meta)
+        }
+    }
+
+    /// Follow the animation that the fighter is playing, returning whether the fighter is done
+    /// leaving afterimages behind
+    fn update(&mut self, current_animation: Option<&str>) -> bool {
+        let Some(animation) = &self.animation else {
+            return false;
+        };
+        let is_playing = current_animation == Some(animation.as_str());
+
+        if self.has_started {
+            !is_playing
+        } else {
+            self.has_started = is_playing;
+            false
+        }
+    }
+}
+
+/// Leave afterimages behind the fighter during the move that uses the `animation`, if the move has
+/// any
+pub fn start_afterimages(
+    commands: &mut Commands,
+    entity: Entity,
+    meta: Option<&AfterimageMeta>,
+    animation: &str,
+) {
+    if let Some(meta) = meta {
+        commands
+            .entity(entity)
+            .insert(Afterimages::during_animation(meta, animation));
+    }
+}
+
+/// Component for a copy of a fighter's sprite that is fading away
+#[derive(Component)]
+pub struct Afterimage {
+    opacity: f32,
+}
+
+fn leave_afterimages(
+    mut commands: Commands,
+    mut pool: EntityPoolCommands,
+    mut fighters: Query<(
+        Entity,
+        &mut Afterimages,
+        &Animation,
+        &TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+        &Transform,
+    )>,
+) {
+    for (entity, mut afterimages, animation, sprite, texture_atlas, transform) in &mut fighters {
+        if afterimages.update(animation.current_animation.as_deref()) {
+            commands.entity(entity).remove::<Afterimages>();
+            continue;
+        }
+        if !afterimages.has_started || !afterimages.timer.tick(GAMEPLAY_STEP).just_finished() {
+            continue;
+        }
+
+        let meta = &afterimages.meta;
+        let opacity = meta.opacity.clamp(0.0, 1.0);
+        let mut color = match meta.tint {
+            Some([r, g, b]) => Color::rgb_u8(r, g, b),
+            None => Color::WHITE,
+        };
+        color.set_a(opacity);
+
+        // Afterimages are left behind the fighter
+        let mut transform = *transform;
+        transform.translation.z -= 0.1;
+
+        pool.spawn(
+            PoolKind::Afterimage,
+            (
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        color,
+                        index: sprite.index,
+                        flip_x: sprite.flip_x,
+                        flip_y: sprite.flip_y,
+                        custom_size: sprite.custom_size,
+                        anchor: sprite.anchor.clone(),
+                    },
+                    texture_atlas: texture_atlas.clone(),
+                    transform,
+                    ..default()
+                },
+                Lifetime(Timer::from_seconds(meta.lifetime.max(0.0), TimerMode::Once)),
+                Afterimage { opacity },
+                Name::new("Afterimage"),
+            ),
+        );
+    }
+}
+
+fn fade_afterimages(mut afterimages: Query<(&mut TextureAtlasSprite, &Afterimage, &Lifetime)>) {
+    for (mut sprite, afterimage, lifetime) in &mut afterimages {
+        sprite
+            .color
+            .set_a(afterimage.opacity * lifetime.percent_left());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn afterimages_last_as_long_as_their_animation() {
+        let meta = AfterimageMeta {
+            interval: 0.05,
+            lifetime: 0.2,
+            opacity: 0.5,
+            tint: None,
+        };
+
+        // The move may only start playing its animation in the next step
+        let mut afterimages = Afterimages::during_animation(&meta, "attacking");
+        assert!(!afterimages.update(Some("idle")));
+        assert!(!afterimages.has_started);
+        assert!(!afterimages.update(Some("attacking")));
+        assert!(afterimages.has_started);
+        assert!(afterimages.update(Some("idle")));
+
+        let mut afterimages = Afterimages::new(&meta);
+        assert!(!afterimages.update(Some("idle")));
+        assert!(!afterimages.update(None));
+    }
+}
@@ -1,13 +1,17 @@
-use std::ops::Range;
-
-use crate::GameState;
+use std::{ops::Range, time::Duration};
+
+use crate::{
+    activity::Dormant,
+    hit_stop::{HitStop, HitStopped},
+    metadata::ColliderMeta,
+    status_effect::Frozen,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
 use bevy::{
-    prelude::*,
-    sprite::TextureAtlasSprite,
-    time::{Time, Timer},
-    utils::HashMap,
+    prelude::*, reflect::FromReflect, sprite::TextureAtlasSprite, time::Timer, utils::HashMap,
 };
-use iyes_loopless::condition::ConditionSet;
+use iyes_loopless::prelude::*;
 use serde::{de::SeqAccess, Deserializer};
 
 pub struct AnimationPlugin;
@@ -17,13 +21,25 @@ impl Plugin for AnimationPlugin {
         app
             // Register reflect types
             .register_type::<Facing>()
+            .register_type::<Animation>()
+            .register_type::<Clip>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameTag>()
             // Add systems
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .with_system(animation_cycling)
+                    .with_system(send_animation_events.after(animation_cycling))
+                    .into(),
+            )
             .add_system_set_to_stage(
                 CoreStage::Last,
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
                     .with_system(animation_flipping)
-                    .with_system(animation_cycling)
                     .into(),
             );
     }
@@ -54,15 +70,54 @@ impl Facing {
     pub fn is_left(&self) -> bool {
         self == &Facing::Left
     }
+
+    /// The direction of the facing along the x axis, `-1` for left and `1` for right
+    pub fn sign(&self) -> f32 {
+        if self.is_left() {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Mirror an offset that is given for facing right, like the offset of a hitbox, of an
+    /// attachment or of the point that projectiles are thrown from, to this facing
+    pub fn mirror(&self, offset: Vec2) -> Vec2 {
+        Vec2::new(offset.x * self.sign(), offset.y)
+    }
 }
 
-#[derive(serde::Deserialize, Clone, Debug)]
+#[derive(serde::Deserialize, Clone, Debug, Default, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct Clip {
     #[serde(deserialize_with = "deserialize_range_from_array")]
     pub frames: Range<usize>,
     #[serde(default)]
     pub repeat: bool,
+    /// How hard the clip is to interrupt with [`Animation::interrupt`] before it has finished,
+    /// which only the clips with the same or a higher priority can do, like hit reactions
+    /// interrupting attacks
+    #[serde(default)]
+    pub priority: i32,
+    /// The clip that plays once this one has finished, if it doesn't repeat.
+    ///
+    /// The fighter states wait for their clips to finish, so they pick the clips that play next
+    /// themselves, and clips that they play shouldn't have one.
+    #[serde(default)]
+    pub next: Option<String>,
+    /// The tags of the frames of the clip, counted from its first frame, which are sent as
+    /// [`AnimationFrameTag`] events when the frames start, like `footstep` or `screen_shake`
+    #[serde(default)]
+    pub events: HashMap<usize, Vec<String>>,
+    /// The hitboxes of the fighter's attacks during the frames of the clip, counted from its first
+    /// frame, offset from the fighter like the hitboxes of the attacks. The frames without one use
+    /// the hitbox of the attack.
+    #[serde(default)]
+    pub hitboxes: HashMap<usize, ColliderMeta>,
+    /// The hurtboxes of the fighter during the frames of the clip, counted from its first frame and
+    /// offset from its usual hurtbox. The frames without one use the fighter's hurtbox.
+    #[serde(default)]
+    pub hurtboxes: HashMap<usize, ColliderMeta>,
 }
 
 fn deserialize_range_from_array<'de, D>(de: D) -> Result<Range<usize>, D::Error>
@@ -106,13 +161,35 @@ impl<'de> serde::de::Visitor<'de> for RangeVisitor {
     }
 }
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
 pub struct Animation {
     pub animations: HashMap<String, Clip>,
     pub current_frame: usize,
     pub current_animation: Option<String>,
     pub timer: Timer,
     pub played_once: bool,
+    /// The clip that has finished playing in the last step
+    finished_clip: Option<String>,
+    /// Whether a clip has started playing since the last step
+    started: bool,
+    /// Whether the current frame has started in the last step
+    entered_frame: bool,
+}
+
+/// Event sent when an entity has finished playing a clip that doesn't repeat
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub animation: String,
+}
+
+/// Event sent when an entity starts a frame of a clip that has a tag
+pub struct AnimationFrameTag {
+    pub entity: Entity,
+    pub animation: String,
+    /// The frame of the clip, counted from its first frame
+    pub frame: usize,
+    pub tag: String,
 }
 
 impl Animation {
@@ -123,6 +200,9 @@ impl Animation {
             current_animation: None,
             timer: Timer::from_seconds(fps, TimerMode::Once),
             played_once: false,
+            finished_clip: None,
+            started: false,
+            entered_frame: false,
         }
     }
 
@@ -138,6 +218,69 @@ impl Animation {
             TimerMode::Once
         });
         self.played_once = false;
+        self.started = true;
+    }
+
+    /// Start playing a new animation, unless the current clip hasn't finished and has a higher
+    /// priority, returning whether the new animation was started
+    pub fn interrupt(&mut self, name: &str, repeating: bool) -> bool {
+        let priority = |name: &str| self.animations.get(name).map_or(0, |clip| clip.priority);
+
+        if let Some(current) = &self.current_animation {
+            let is_playing = !self.is_finished() && !self.is_repeating();
+            if is_playing && priority(current) > priority(name) {
+                return false;
+            }
+        }
+
+        self.play(name, repeating);
+        true
+    }
+
+    /// Advance the animation, following the transition of the current clip once it has finished
+    pub fn tick(&mut self, delta: Duration) {
+        self.finished_clip = None;
+        self.entered_frame = false;
+        self.advance(delta);
+
+        // The clips that have just started playing start their first frame
+        self.entered_frame |= std::mem::take(&mut self.started);
+    }
+
+    fn advance(&mut self, delta: Duration) {
+        if self.is_finished() && !self.is_repeating() {
+            return;
+        }
+
+        self.timer.tick(delta);
+        if !self.timer.finished() {
+            return;
+        }
+        self.timer.reset();
+
+        if !self.is_last_frame() {
+            self.current_frame += 1;
+            self.entered_frame = true;
+            return;
+        }
+
+        // The clip has played once when its last frame is over
+        self.played_once = true;
+        if self.is_repeating() {
+            self.current_frame = 0;
+            self.entered_frame = true;
+            return;
+        }
+
+        self.finished_clip = self.current_animation.clone();
+        let next = self
+            .get_current_clip()
+            .and_then(|clip| clip.next.clone())
+            .filter(|next| self.animations.contains_key(next));
+        if let Some(next) = next {
+            let repeating = self.animations[&next].repeat;
+            self.play(&next, repeating);
+        }
     }
 
     pub fn is_finished(&self) -> bool {
@@ -164,15 +307,40 @@ impl Animation {
         false
     }
 
+    pub fn get_current_clip(&self) -> Option<&Clip> {
+        self.current_animation
+            .as_ref()
+            .and_then(|animation| self.animations.get(animation))
+    }
+
     pub fn get_current_indices(&self) -> Option<&Range<usize>> {
-        if let Some(animation) = &self.current_animation {
-            match self.animations.get(animation) {
-                Some(clip) => return Some(&clip.frames),
-                None => return None,
-            }
+        self.get_current_clip().map(|clip| &clip.frames)
+    }
+
+    /// The tags of the current frame, if it has just started
+    pub fn entered_frame_tags(&self) -> &[String] {
+        if !self.entered_frame {
+            return &[];
         }
 
-        None
+        self.get_current_clip()
+            .and_then(|clip| clip.events.get(&self.current_frame))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The hitbox that the current frame gives the attacks, if it has one
+    pub fn frame_hitbox(&self) -> Option<ColliderMeta> {
+        self.get_current_clip()
+            .and_then(|clip| clip.hitboxes.get(&self.current_frame))
+            .copied()
+    }
+
+    /// The hurtbox that the current frame gives the fighter, if it has one
+    pub fn frame_hurtbox(&self) -> Option<ColliderMeta> {
+        self.get_current_clip()
+            .and_then(|clip| clip.hurtboxes.get(&self.current_frame))
+            .copied()
     }
 
     pub fn get_current_index(&self) -> Option<usize> {
@@ -184,37 +352,205 @@ impl Animation {
     }
 }
 
-fn animation_cycling(mut query: Query<(&mut TextureAtlasSprite, &mut Animation)>, time: Res<Time>) {
-    //TODO: Add a tick method on Animation
-    for (mut texture_atlas_sprite, mut animation) in query.iter_mut() {
-        if animation.is_finished() && !animation.is_repeating() {
-            continue;
-        }
+/// The number of entities in each batch of the parallel animation systems
+const ANIMATION_BATCH_SIZE: usize = 32;
+
+/// Dormant enemies are skipped, since they are off-screen anyway, and the animations of frozen
+/// fighters stay on their frame.
+///
+/// The animations of the entities in a [hit-stop](crate::hit_stop) are ticked without advancing,
+/// so that the events of their last frame aren't sent again while they stand still.
+pub fn animation_cycling(
+    mut query: Query<
+        (&mut TextureAtlasSprite, &mut Animation, Option<&HitStopped>),
+        (Without<Dormant>, Without<Frozen>),
+    >,
+    hit_stop: Res<HitStop>,
+) {
+    let hit_stop = &*hit_stop;
+    query.par_for_each_mut(
+        ANIMATION_BATCH_SIZE,
+        |(mut texture_atlas_sprite, mut animation, hit_stopped)| {
+            if hit_stop.is_stopped(hit_stopped) {
+                animation.tick(Duration::ZERO);
+            } else {
+                animation.tick(GAMEPLAY_STEP);
+            }
 
-        animation.timer.tick(time.delta());
+            // Only update the sprite when the frame changes, so that it isn't marked as changed
+            if let Some(index) = animation.get_current_index() {
+                if texture_atlas_sprite.index != index {
+                    texture_atlas_sprite.index = index;
+                }
+            }
+        },
+    );
+}
 
-        if animation.timer.finished() {
-            animation.timer.reset();
+/// Send the events of the frame tags and of the clips that have finished playing, which can't be
+/// sent while the animations are cycled in parallel
+fn send_animation_events(
+    animations: Query<(Entity, &Animation), Without<Dormant>>,
+    mut finished_events: EventWriter<AnimationFinished>,
+    mut tag_events: EventWriter<AnimationFrameTag>,
+) {
+    for (entity, animation) in &animations {
+        if let Some(clip) = &animation.finished_clip {
+            finished_events.send(AnimationFinished {
+                entity,
+                animation: clip.clone(),
+            });
+        }
 
-            if animation.is_last_frame() {
-                animation.played_once = true; // Check if animation player here because we need to wait the last frame
+        for tag in animation.entered_frame_tags() {
+            tag_events.send(AnimationFrameTag {
+                entity,
+                animation: animation.current_animation.clone().unwrap_or_default(),
+                frame: animation.current_frame,
+                tag: tag.clone(),
+            });
+        }
+    }
+}
 
-                if animation.is_repeating() {
-                    animation.current_frame = 0;
-                }
-            } else {
-                animation.current_frame += 1;
+fn animation_flipping(
+    mut query: Query<
+        (&mut TextureAtlasSprite, &Facing),
+        Or<(Changed<Facing>, Changed<TextureAtlasSprite>)>,
+    >,
+) {
+    query.par_for_each_mut(
+        ANIMATION_BATCH_SIZE,
+        |(mut texture_atlas_sprite, facing)| {
+            if texture_atlas_sprite.flip_x != facing.is_left() {
+                texture_atlas_sprite.flip_x = facing.is_left();
             }
+        },
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn animation() -> Animation {
+        let clips = [
+            ("idle", 0..2, true, 0, None),
+            ("attacking", 2..4, false, 1, Some("idle")),
+            ("hitstun", 4..5, false, 2, None),
+        ];
+        let animations = clips
+            .into_iter()
+            .map(|(name, frames, repeat, priority, next)| {
+                let clip = Clip {
+                    frames,
+                    repeat,
+                    priority,
+                    next: next.map(str::to_owned),
+                    ..default()
+                };
+                (name.to_owned(), clip)
+            })
+            .collect();
+
+        Animation::new(0.1, animations)
+    }
+
+    #[test]
+    fn offsets_are_mirrored_when_facing_left() {
+        let offset = Vec2::new(12.0, -4.0);
+        assert_eq!(Facing::Right.mirror(offset), offset);
+        assert_eq!(Facing::Left.mirror(offset), Vec2::new(-12.0, -4.0));
+        assert_eq!(Facing::Left.mirror(Facing::Left.mirror(offset)), offset);
+    }
+
+    #[test]
+    fn clips_are_interrupted_by_priority() {
+        let mut animation = animation();
+        animation.play("attacking", false);
+        assert!(!animation.interrupt("idle", true));
+        assert!(animation.interrupt("hitstun", false));
+        assert!(!animation.interrupt("attacking", false));
+
+        // Finished clips can always be interrupted
+        animation.played_once = true;
+        assert!(animation.interrupt("attacking", false));
+    }
+
+    #[test]
+    fn finished_clips_transition_to_their_next_clip() {
+        let step = Duration::from_secs_f32(0.1);
+        let mut animation = animation();
+        animation.play("attacking", false);
+
+        // The clips play from their first frame to their last one
+        for _ in 0..2 {
+            animation.tick(step);
+            assert_eq!(animation.finished_clip, None);
         }
+        assert_eq!(animation.get_current_index(), Some(4));
+
+        animation.tick(step);
+        assert_eq!(animation.finished_clip.as_deref(), Some("attacking"));
+        assert_eq!(animation.current_animation.as_deref(), Some("idle"));
+        assert!(!animation.is_finished());
 
-        if let Some(index) = animation.get_current_index() {
-            texture_atlas_sprite.index = index;
+        animation.tick(step);
+        assert_eq!(animation.finished_clip, None);
+    }
+
+    #[test]
+    fn frame_tags_are_sent_once_when_their_frames_start() {
+        let mut animation = animation();
+        // Every frame lasts two steps
+        let step = animation.timer.duration() / 2 + Duration::from_nanos(1);
+        let idle = animation.animations.get_mut("idle").unwrap();
+        idle.events.insert(0, vec!["footstep".to_owned()]);
+        idle.events
+            .insert(2, vec!["footstep".to_owned(), "screen_shake".to_owned()]);
+        animation.play("idle", true);
+
+        let mut tags = Vec::new();
+        for _ in 0..8 {
+            animation.tick(step);
+            tags.push(animation.entered_frame_tags().join(" "));
         }
+
+        assert_eq!(
+            tags,
+            [
+                "footstep",
+                "",
+                "",
+                "footstep screen_shake",
+                "",
+                // The clip repeats from its first frame
+                "footstep",
+                "",
+                "",
+            ]
+        );
     }
-}
 
-fn animation_flipping(mut query: Query<(&mut TextureAtlasSprite, &Facing)>) {
-    for (mut texture_atlas_sprite, facing) in query.iter_mut() {
-        texture_atlas_sprite.flip_x = facing.is_left();
+    #[test]
+    fn frames_give_their_own_boxes() {
+        let mut animation = animation();
+        let hitbox = ColliderMeta {
+            size: Vec2::new(40.0, 16.0),
+            offset: Vec2::new(30.0, 8.0),
+        };
+        let attacking = animation.animations.get_mut("attacking").unwrap();
+        attacking.hitboxes.insert(1, hitbox);
+        animation.play("attacking", false);
+
+        assert_eq!(animation.frame_hitbox(), None);
+        animation.tick(animation.timer.duration());
+        assert_eq!(animation.frame_hitbox(), Some(hitbox));
+        assert_eq!(animation.frame_hurtbox(), None);
+
+        // Other clips don't have the boxes of the clip
+        animation.play("idle", true);
+        animation.tick(animation.timer.duration());
+        assert_eq!(animation.frame_hitbox(), None);
     }
 }
@@ -0,0 +1,327 @@
+//! Arcade mode, where the campaign is played for score with a fixed number of lives and continues.
+//!
+//! Arcade is started from the main menu, and plays the start level like the campaign, but without
+//! saving the campaign progress. Every player has the [lives](ArcadeMeta::lives) of the game's
+//! [`ArcadeMeta`], and comes back where the camera is while they have lives left. Once all of the
+//! players are out of lives, they can spend one of the continues to come back with all of their
+//! lives, or the run is over. Knocking out enemies and finishing the campaign scores points, and
+//! the score of the run is submitted to the arcade [`HighScores`], with the continues used.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    assist::Assists,
+    combat_events::Killed,
+    damage::Health,
+    enemy::{Boss, Enemy},
+    input::MenuAction,
+    menu_stack::MenuLayer,
+    metadata::{ArcadeMeta, GameMeta, LevelHandle, LevelMeta, Settings},
+    platform::Storage,
+    player::{Player, PlayerBundle, PlayerIndex},
+    survival::{HighScore, HighScoreTable, HighScores, RunResult},
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    utils::ResetController,
+    GameState,
+};
+
+/// How long the players have to continue once all of them are out of lives
+const CONTINUE_TIME: Duration = Duration::from_secs(10);
+
+/// How long the players get to enjoy finishing the campaign before the run is over
+const CLEAR_DELAY: Duration = Duration::from_secs(4);
+
+pub struct ArcadePlugin;
+
+impl Plugin for ArcadePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, finish_arcade)
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                use_lives
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<ArcadeRun>(),
+            )
+            .add_system(
+                score_knockouts
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<ArcadeRun>(),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<ArcadeRun>()
+                    .with_system(continue_or_game_over)
+                    .with_system(end_cleared_run)
+                    .into(),
+            );
+    }
+}
+
+/// Start arcade from the start level of the campaign
+pub fn start_arcade(commands: &mut Commands, game: &GameMeta, arcade: &ArcadeMeta) {
+    commands.insert_resource(LevelHandle(game.start_level_handle.clone()));
+    commands.insert_resource(ArcadeRun::new(arcade));
+    commands.remove_resource::<RunResult>();
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+/// The state of an arcade run, which exists while arcade is played
+#[derive(Resource)]
+pub struct ArcadeRun {
+    pub meta: ArcadeMeta,
+    pub score: u32,
+    /// The lives that each player has left, by their player index, not counting the one they are
+    /// playing with
+    pub lives_left: Vec<u32>,
+    pub continues_left: u32,
+    pub continues_used: u32,
+    /// Counts down the time left to continue, once all of the players are out of lives
+    pub continue_timer: Option<Timer>,
+    /// Counts down the time until the run is over, once the campaign has been finished
+    clear_timer: Option<Timer>,
+}
+
+impl ArcadeRun {
+    fn new(meta: &ArcadeMeta) -> Self {
+        Self {
+            meta: meta.clone(),
+            score: 0,
+            lives_left: Vec::new(),
+            continues_left: meta.continues,
+            continues_used: 0,
+            continue_timer: None,
+            clear_timer: None,
+        }
+    }
+
+    /// The lives that a player has left
+    pub fn lives_left(&self, player: usize) -> u32 {
+        self.lives_left
+            .get(player)
+            .copied()
+            .unwrap_or_else(|| self.meta.lives.saturating_sub(1))
+    }
+
+    /// Take a life of a player, returning whether they had one left
+    fn take_life(&mut self, player: usize) -> bool {
+        if self.lives_left.len() <= player {
+            let lives = self.meta.lives.saturating_sub(1);
+            self.lives_left.resize(player + 1, lives);
+        }
+
+        let lives = &mut self.lives_left[player];
+        if *lives == 0 {
+            return false;
+        }
+        *lives -= 1;
+        true
+    }
+
+    /// Spend a continue to give every player all of their lives back, returning whether there was
+    /// one left
+    fn use_continue(&mut self) -> bool {
+        if self.continues_left == 0 {
+            return false;
+        }
+        self.continues_left -= 1;
+        self.continues_used += 1;
+        self.lives_left.fill(self.meta.lives);
+        true
+    }
+
+    /// Score finishing the campaign, with the lives that are left
+    fn clear(&mut self, player_count: usize) {
+        let lives: u32 = (0..player_count)
+            .map(|player| self.lives_left(player))
+            .sum();
+        self.score += self.meta.clear_score + lives * self.meta.life_score;
+    }
+}
+
+/// Bring knocked out players back where the camera is, while they have lives left
+fn use_lives(
+    mut commands: Commands,
+    mut run: ResMut<ArcadeRun>,
+    players: Query<&PlayerIndex, With<Player>>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+
+    let mut settings = None;
+    for (i, player) in level.players.iter().enumerate() {
+        if players.iter().any(|player_index| player_index.0 == i) || !run.take_life(i) {
+            continue;
+        }
+
+        let settings =
+            settings.get_or_insert_with(|| storage.get::<Settings>(Settings::STORAGE_KEY));
+        let mut player = player.clone();
+        player.location.x = camera_transform.translation.x;
+        commands.spawn(PlayerBundle::new(&player, i, &game, settings.as_ref()));
+        info!(
+            player = i,
+            lives_left = run.lives_left(i),
+            "Player came back with a life"
+        );
+    }
+}
+
+/// Score the knocked out enemies, and finish the campaign once its last boss is knocked out
+fn score_knockouts(
+    mut run: ResMut<ArcadeRun>,
+    mut killed_events: EventReader<Killed>,
+    enemies: Query<Option<&Boss>, With<Enemy>>,
+    bosses: Query<&Health, With<Boss>>,
+    level: Res<LevelMeta>,
+) {
+    for event in killed_events.iter() {
+        let Ok(boss) = enemies.get(event.killed_entity) else {
+            continue;
+        };
+        if boss.is_none() {
+            run.score += run.meta.knockout_score;
+            continue;
+        }
+
+        run.score += run.meta.boss_score;
+        if run.clear_timer.is_none() && bosses.iter().all(|health| health.0 <= 0) {
+            run.clear(level.players.len());
+            run.clear_timer = Some(Timer::new(CLEAR_DELAY, TimerMode::Once));
+            info!(score = run.score, "Arcade campaign finished");
+        }
+    }
+}
+
+/// Count down the time to continue once all of the players are out of lives, and end the run if
+/// they don't
+fn continue_or_game_over(
+    mut commands: Commands,
+    mut run: ResMut<ArcadeRun>,
+    players: Query<(), With<Player>>,
+    level: Res<LevelMeta>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    time: Res<Time>,
+    reset_controller: ResetController,
+) {
+    let out_of_lives = (0..level.players.len()).all(|player| run.lives_left(player) == 0);
+    if !players.is_empty() || !out_of_lives {
+        return;
+    }
+    if run.continues_left == 0 {
+        game_over(&mut commands, reset_controller);
+        return;
+    }
+
+    let timer = run
+        .continue_timer
+        .get_or_insert_with(|| Timer::new(CONTINUE_TIME, TimerMode::Once));
+    timer.tick(time.delta());
+    let finished = timer.finished();
+
+    if menu_input
+        .iter()
+        .any(|input| input.just_pressed(MenuAction::Confirm))
+    {
+        run.continue_timer = None;
+        if run.lives_left.len() < level.players.len() {
+            run.lives_left.resize(level.players.len(), 0);
+        }
+        run.use_continue();
+        info!(continues_left = run.continues_left, "Arcade run continued");
+    } else if finished {
+        game_over(&mut commands, reset_controller);
+    }
+}
+
+/// End the run once the players have had the time to enjoy finishing the campaign
+fn end_cleared_run(
+    mut commands: Commands,
+    mut run: ResMut<ArcadeRun>,
+    time: Res<Time>,
+    reset_controller: ResetController,
+) {
+    let Some(timer) = &mut run.clear_timer else {
+        return;
+    };
+    if timer.tick(time.delta()).finished() {
+        game_over(&mut commands, reset_controller);
+    }
+}
+
+fn game_over(commands: &mut Commands, reset_controller: ResetController) {
+    commands.insert_resource(NextState(GameState::MainMenu));
+    reset_controller.reset_world();
+}
+
+/// Submit the score of the run to the arcade high scores once it's over
+fn finish_arcade(
+    mut commands: Commands,
+    run: Option<Res<ArcadeRun>>,
+    assists: Option<Res<Assists>>,
+    mut storage: ResMut<Storage>,
+) {
+    let Some(run) = run else {
+        return;
+    };
+    commands.remove_resource::<ArcadeRun>();
+
+    let score = HighScore {
+        score: run.score,
+        waves: 0,
+        assisted: assists.is_some(),
+        continues: run.continues_used,
+    };
+    info!(
+        score.score,
+        score.continues, score.assisted, "Arcade run over"
+    );
+
+    let table = HighScoreTable::Arcade;
+    let rank = HighScores::submit_to(&mut storage, table, score);
+    commands.insert_resource(RunResult { score, rank, table });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lives_and_continues_run_out() {
+        let mut run = ArcadeRun::new(&ArcadeMeta {
+            lives: 2,
+            continues: 1,
+            knockout_score: 100,
+            boss_score: 1000,
+            clear_score: 5000,
+            life_score: 500,
+        });
+
+        // The players start with one of their lives
+        assert_eq!(run.lives_left(1), 1);
+        assert!(run.take_life(1));
+        assert!(!run.take_life(1));
+        assert_eq!(run.lives_left(0), 1);
+
+        assert!(run.use_continue());
+        assert_eq!(run.lives_left(1), 2);
+        assert!(!run.use_continue());
+        assert_eq!(run.continues_used, 1);
+
+        run.clear(2);
+        assert_eq!(run.score, 5000 + 4 * 500);
+    }
+}
@@ -0,0 +1,871 @@
+//! Support for loading assets out of `.zip` content packs and packed asset bundles.
+//!
+//! Packs are mounted on top of the regular asset directory: a file is loaded from the first pack
+//! that contains it, falling back to the platform's default [`AssetIo`]. The root of each archive
+//! corresponds to the root of the asset directory.
+//!
+//! Asset bundles are a simple uncompressed archive format that we pack the game assets into for
+//! release builds. They are much faster to open and read from than zip archives, and the packer
+//! does some of the loading work ahead of time:
+//!
+//! - The metadata files are stored pre-parsed, as their YAML value tree in a binary encoding that
+//!   is much quicker to read than the YAML text. See [`read_preparsed_meta`].
+//! - The spritesheet images are also packed together into a few large atlas pages, so that the
+//!   fighters and the items don't each need textures of their own. The original images are still
+//!   in the bundle for everything else that uses them. See [`packed_image`].
+//!
+//! A bundle is laid out as:
+//!
+//! - The [`BUNDLE_MAGIC`] bytes and the `u32` format version
+//! - The `u32` number of files, followed by the index entry of every file: the `u32` length of the
+//!   path, the UTF-8 path, and the `u64` offset and length of the file in the data section
+//! - The `u32` number of packed images, followed by the entry of every image: the `u32` length of
+//!   the path, the UTF-8 path, the `u32` length of the path of its page, the UTF-8 path of the page,
+//!   and the `u32` width and height of the page and X and Y of the image in the page
+//! - The data section, containing the contents of all of the files, including the atlas pages
+//!
+//! All integers are little-endian. Bundles are only mounted by release builds, since the dev builds
+//! load the loose files to hot reload them.
+//!
+//! The browser can't open files, so the web build instead mounts a bundle that the page downloads
+//! before starting the game. See [`take_preloaded_bundle`].
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    fs::File,
+    io::{BufWriter, Write},
+};
+use std::{
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use bevy::{
+    asset::{AssetIo, AssetIoError, BoxedFuture, FileType, Metadata},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use once_cell::sync::OnceCell;
+use serde_yaml::{
+    value::{Tag, TaggedValue},
+    Mapping, Value,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use zip::ZipArchive;
+
+/// The file extension of packed asset bundles
+pub const BUNDLE_EXTENSION: &str = "pack";
+/// The bytes that every asset bundle starts with
+const BUNDLE_MAGIC: &[u8; 8] = b"PUNCHYPK";
+/// The version of the bundle format
+const BUNDLE_VERSION: u32 = 2;
+
+/// The bytes that every pre-parsed metadata file starts with, which no YAML file can start with
+const PREPARSED_MAGIC: &[u8; 8] = b"\0PUNCHYM";
+/// The kinds of metadata that the packer pre-parses, by the extension before `.yaml` or `.yml`
+#[cfg(not(target_arch = "wasm32"))]
+const PREPARSED_KINDS: &[&str] = &["game", "level", "endless", "fighter", "item", "demo"];
+
+/// The directory of the bundles that the atlas pages are packed into
+#[cfg(not(target_arch = "wasm32"))]
+const ATLAS_PAGE_DIR: &str = "packed-atlases";
+/// The width and height of the atlas pages, which all GPUs that we run on support
+#[cfg(not(target_arch = "wasm32"))]
+const ATLAS_PAGE_SIZE: u32 = 2048;
+/// The transparent pixels kept around the images in the atlas pages, so that they don't bleed
+/// into each other
+#[cfg(not(target_arch = "wasm32"))]
+const ATLAS_PADDING: u32 = 2;
+
+/// The spritesheet images that the mounted asset bundles packed into atlas pages, by their path
+static PACKED_IMAGES: OnceCell<HashMap<String, PackedImage>> = OnceCell::new();
+
+/// Where a spritesheet image was packed into an atlas page of an asset bundle
+#[derive(Clone, Debug)]
+pub struct PackedImage {
+    /// The path of the page, which is also a file of the bundle
+    pub page: String,
+    pub page_size: Vec2,
+    /// The position of the top left corner of the image in the page
+    pub position: Vec2,
+}
+
+/// The spritesheet image at `path`, if it was packed into an atlas page of a mounted asset bundle
+pub fn packed_image(path: &Path) -> Option<&'static PackedImage> {
+    PACKED_IMAGES.get()?.get(&archive_name(path))
+}
+
+/// An archive that assets can be loaded from.
+struct AssetPack {
+    path: PathBuf,
+    source: PackSource,
+    /// The paths of all of the files in the pack
+    files: HashSet<String>,
+    /// The spritesheet images that the pack packed into atlas pages, which only bundles do
+    packed_images: HashMap<String, PackedImage>,
+}
+
+enum PackSource {
+    #[cfg(not(target_arch = "wasm32"))]
+    Zip(Mutex<ZipArchive<File>>),
+    Bundle {
+        file: Mutex<Box<dyn BundleReader>>,
+        /// The offset of the data section in the bundle file
+        data_offset: u64,
+        /// The offset and length of every file, relative to the data section
+        index: HashMap<String, (u64, u64)>,
+    },
+}
+
+/// The file or the in-memory data that an asset bundle is read from
+trait BundleReader: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> BundleReader for T {}
+
+impl AssetPack {
+    /// Open a pack, which is read as an asset bundle if it has the `.pack` extension, or as a
+    /// zip archive otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+
+        if path.extension() == Some(OsStr::new(BUNDLE_EXTENSION)) {
+            return Self::from_bundle(path, Box::new(file));
+        }
+
+        let archive = ZipArchive::new(file)?;
+        let files = archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(String::from)
+            .collect();
+
+        Ok(Self {
+            path: path.to_owned(),
+            source: PackSource::Zip(Mutex::new(archive)),
+            files,
+            packed_images: default(),
+        })
+    }
+
+    /// Read an asset bundle from a file or from memory
+    fn from_bundle(path: &Path, mut file: Box<dyn BundleReader>) -> anyhow::Result<Self> {
+        let BundleIndex {
+            data_offset,
+            files: index,
+            packed_images,
+        } = read_bundle_index(&mut file)?;
+        let files = index.keys().cloned().collect();
+
+        Ok(Self {
+            path: path.to_owned(),
+            source: PackSource::Bundle {
+                file: Mutex::new(file),
+                data_offset,
+                index,
+            },
+            files,
+            packed_images,
+        })
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AssetIoError> {
+        let not_found = || AssetIoError::NotFound(name.into());
+
+        match &self.source {
+            #[cfg(not(target_arch = "wasm32"))]
+            PackSource::Zip(archive) => {
+                let mut archive = archive.lock().unwrap();
+                let mut file = archive.by_name(name).map_err(|_| not_found())?;
+
+                let mut bytes = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut bytes)?;
+
+                Ok(bytes)
+            }
+            PackSource::Bundle {
+                file,
+                data_offset,
+                index,
+            } => {
+                let (offset, len) = *index.get(name).ok_or_else(not_found)?;
+                let mut file = file.lock().unwrap();
+                file.seek(SeekFrom::Start(data_offset + offset))?;
+
+                let mut bytes = vec![0; len as usize];
+                file.read_exact(&mut bytes)?;
+
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn is_dir(&self, name: &str) -> bool {
+        name.is_empty() || self.files.iter().any(|x| is_in_dir(x, name))
+    }
+}
+
+/// [`AssetIo`] implementation that loads assets from `.zip` packs before falling back to the
+/// default [`AssetIo`].
+///
+/// Changes to assets in packs are not watched, so hot reloading only works for the loose files.
+pub struct PackAssetIo {
+    /// The mounted packs, in order of priority
+    packs: Vec<AssetPack>,
+    fallback: Box<dyn AssetIo>,
+}
+
+impl PackAssetIo {
+    /// Create the asset IO from the paths to the packs, in order of priority. Packs that can't be
+    /// opened are skipped with an error message.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(pack_paths: &[PathBuf], fallback: Box<dyn AssetIo>) -> Self {
+        let packs = pack_paths
+            .iter()
+            .filter_map(|path| match AssetPack::open(path) {
+                Ok(pack) => {
+                    info!(?path, "Mounted asset pack");
+                    Some(pack)
+                }
+                Err(e) => {
+                    error!(?path, "Could not open asset pack: {e}");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        publish_packed_images(&packs);
+
+        Self { packs, fallback }
+    }
+
+    /// Create the asset IO from an asset bundle that has been read into memory
+    pub fn from_bundle_bytes(
+        name: &str,
+        bytes: Vec<u8>,
+        fallback: Box<dyn AssetIo>,
+    ) -> anyhow::Result<Self> {
+        let pack = AssetPack::from_bundle(Path::new(name), Box::new(Cursor::new(bytes)))?;
+        info!(path = ?pack.path, files = pack.files.len(), "Mounted asset bundle");
+
+        let packs = vec![pack];
+        publish_packed_images(&packs);
+
+        Ok(Self { packs, fallback })
+    }
+
+    fn find_pack(&self, name: &str) -> Option<&AssetPack> {
+        self.packs.iter().find(|pack| pack.files.contains(name))
+    }
+}
+
+impl AssetIo for PackAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            let name = archive_name(path);
+
+            if let Some(pack) = self.find_pack(&name) {
+                trace!(?path, pack = ?pack.path, "Loading asset from pack");
+                pack.read(&name)
+            } else {
+                self.fallback.load_path(path).await
+            }
+        })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        let name = archive_name(path);
+
+        let mut entries = HashSet::default();
+        let mut found = false;
+        for pack in &self.packs {
+            if !pack.is_dir(&name) {
+                continue;
+            }
+            found = true;
+
+            for file in &pack.files {
+                if is_in_dir(file, &name) {
+                    // Only list the direct children of the directory
+                    let relative = file[name.len()..].trim_start_matches('/');
+                    let child = relative.split('/').next().unwrap_or(relative);
+                    entries.insert(path.join(child));
+                }
+            }
+        }
+
+        match self.fallback.read_directory(path) {
+            Ok(fallback_entries) => entries.extend(fallback_entries),
+            Err(e) if !found => return Err(e),
+            Err(_) => (),
+        }
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn get_metadata(&self, path: &Path) -> Result<Metadata, AssetIoError> {
+        let name = archive_name(path);
+
+        if self.find_pack(&name).is_some() {
+            Ok(Metadata::new(FileType::File))
+        } else if self.packs.iter().any(|pack| pack.is_dir(&name)) {
+            Ok(Metadata::new(FileType::Directory))
+        } else {
+            self.fallback.get_metadata(path)
+        }
+    }
+
+    fn watch_path_for_changes(&self, path: &Path) -> Result<(), AssetIoError> {
+        self.fallback.watch_path_for_changes(path)
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        self.fallback.watch_for_changes()
+    }
+}
+
+/// Make the images that the packs packed into atlas pages available to the asset loaders, through
+/// [`packed_image`]. The images that a pack of a higher priority replaces are loaded from it
+/// instead.
+fn publish_packed_images(packs: &[AssetPack]) {
+    let mut packed_images = HashMap::default();
+    for (priority, pack) in packs.iter().enumerate() {
+        for (path, image) in &pack.packed_images {
+            let replaced = packs[..priority]
+                .iter()
+                .any(|pack| pack.files.contains(path));
+            if !replaced {
+                packed_images
+                    .entry(path.clone())
+                    .or_insert_with(|| image.clone());
+            }
+        }
+    }
+
+    if PACKED_IMAGES.set(packed_images).is_err() {
+        warn!("Asset packs were mounted twice, keeping the first atlas pages");
+    }
+}
+
+/// The index of an asset bundle
+struct BundleIndex {
+    /// The offset of the data section in the bundle file
+    data_offset: u64,
+    /// The offset and length of every file, relative to the data section
+    files: HashMap<String, (u64, u64)>,
+    packed_images: HashMap<String, PackedImage>,
+}
+
+/// Read the index of an asset bundle
+fn read_bundle_index(file: &mut (impl Read + Seek)) -> anyhow::Result<BundleIndex> {
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0; BUNDLE_MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != BUNDLE_MAGIC {
+        anyhow::bail!("Not an asset bundle");
+    }
+    let version = read_u32(&mut reader)?;
+    if version != BUNDLE_VERSION {
+        anyhow::bail!("Unsupported asset bundle version: {version}");
+    }
+
+    let file_count = read_u32(&mut reader)?;
+    let mut index = HashMap::default();
+    for _ in 0..file_count {
+        let mut path = vec![0; read_u32(&mut reader)? as usize];
+        reader.read_exact(&mut path)?;
+        let offset = read_u64(&mut reader)?;
+        let len = read_u64(&mut reader)?;
+
+        index.insert(String::from_utf8(path)?, (offset, len));
+    }
+
+    let image_count = read_u32(&mut reader)?;
+    let mut packed_images = HashMap::default();
+    for _ in 0..image_count {
+        let path = read_string(&mut reader)?;
+        let page = read_string(&mut reader)?;
+        let page_size = UVec2::new(read_u32(&mut reader)?, read_u32(&mut reader)?);
+        let position = UVec2::new(read_u32(&mut reader)?, read_u32(&mut reader)?);
+
+        packed_images.insert(
+            path,
+            PackedImage {
+                page,
+                page_size: page_size.as_vec2(),
+                position: position.as_vec2(),
+            },
+        );
+    }
+
+    let data_offset = reader.stream_position()?;
+
+    Ok(BundleIndex {
+        data_offset,
+        files: index,
+        packed_images,
+    })
+}
+
+fn read_string(reader: &mut impl Read) -> anyhow::Result<String> {
+    let mut bytes = vec![0; read_u32(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// The kinds of the values of pre-parsed metadata, which each value starts with
+const VALUE_NULL: u8 = 0;
+const VALUE_FALSE: u8 = 1;
+const VALUE_TRUE: u8 = 2;
+const VALUE_UNSIGNED: u8 = 3;
+const VALUE_SIGNED: u8 = 4;
+const VALUE_FLOAT: u8 = 5;
+const VALUE_STRING: u8 = 6;
+const VALUE_SEQUENCE: u8 = 7;
+const VALUE_MAPPING: u8 = 8;
+const VALUE_TAGGED: u8 = 9;
+
+/// Read a metadata file that the packer pre-parsed into the YAML value tree of the file, or `None`
+/// if the file is still YAML, because it's a loose file or it comes from a zip pack.
+///
+/// The pre-parsed values are encoded depth-first, each value starting with its kind: the numbers
+/// follow as their `u64`, `i64`, or `f64`, and the strings as their `u32` length and UTF-8 bytes.
+/// The sequences follow with their `u32` length and their values, the mappings with their `u32`
+/// length and their keys and values, and the tagged values with their tag and their value.
+pub fn read_preparsed_meta(bytes: &[u8]) -> Option<anyhow::Result<Value>> {
+    let mut reader = bytes.strip_prefix(PREPARSED_MAGIC.as_slice())?;
+    Some(read_value(&mut reader))
+}
+
+fn read_value(reader: &mut &[u8]) -> anyhow::Result<Value> {
+    let mut kind = [0];
+    reader.read_exact(&mut kind)?;
+
+    Ok(match kind[0] {
+        VALUE_NULL => Value::Null,
+        VALUE_FALSE => Value::Bool(false),
+        VALUE_TRUE => Value::Bool(true),
+        VALUE_UNSIGNED => Value::Number(read_u64(reader)?.into()),
+        VALUE_SIGNED => Value::Number((read_u64(reader)? as i64).into()),
+        VALUE_FLOAT => Value::Number(f64::from_bits(read_u64(reader)?).into()),
+        VALUE_STRING => Value::String(read_string(reader)?),
+        VALUE_SEQUENCE => {
+            let len = read_u32(reader)?;
+            let sequence = (0..len)
+                .map(|_| read_value(reader))
+                .collect::<anyhow::Result<_>>()?;
+            Value::Sequence(sequence)
+        }
+        VALUE_MAPPING => {
+            let len = read_u32(reader)?;
+            let mut mapping = Mapping::with_capacity(len as usize);
+            for _ in 0..len {
+                let key = read_value(reader)?;
+                mapping.insert(key, read_value(reader)?);
+            }
+            Value::Mapping(mapping)
+        }
+        VALUE_TAGGED => {
+            let tag = Tag::new(read_string(reader)?);
+            let value = read_value(reader)?;
+            Value::Tagged(Box::new(TaggedValue { tag, value }))
+        }
+        kind => anyhow::bail!("Unknown kind of pre-parsed value: {kind}"),
+    })
+}
+
+/// Pre-parse a metadata file, encoding its YAML value tree for [`read_preparsed_meta`]
+#[cfg(not(target_arch = "wasm32"))]
+fn write_preparsed_meta(value: &Value) -> Vec<u8> {
+    let mut bytes = PREPARSED_MAGIC.to_vec();
+    write_value(value, &mut bytes);
+    bytes
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_value(value: &Value, bytes: &mut Vec<u8>) {
+    let write_str = |string: &str, bytes: &mut Vec<u8>| {
+        bytes.extend((string.len() as u32).to_le_bytes());
+        bytes.extend(string.as_bytes());
+    };
+
+    match value {
+        Value::Null => bytes.push(VALUE_NULL),
+        Value::Bool(false) => bytes.push(VALUE_FALSE),
+        Value::Bool(true) => bytes.push(VALUE_TRUE),
+        Value::Number(number) => write_number(number, bytes),
+        Value::String(string) => {
+            bytes.push(VALUE_STRING);
+            write_str(string, bytes);
+        }
+        Value::Sequence(sequence) => {
+            bytes.push(VALUE_SEQUENCE);
+            bytes.extend((sequence.len() as u32).to_le_bytes());
+            for value in sequence {
+                write_value(value, bytes);
+            }
+        }
+        Value::Mapping(mapping) => {
+            bytes.push(VALUE_MAPPING);
+            bytes.extend((mapping.len() as u32).to_le_bytes());
+            for (key, value) in mapping {
+                write_value(key, bytes);
+                write_value(value, bytes);
+            }
+        }
+        Value::Tagged(tagged) => {
+            bytes.push(VALUE_TAGGED);
+            write_str(&tagged.tag.to_string(), bytes);
+            write_value(&tagged.value, bytes);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_number(number: &serde_yaml::Number, bytes: &mut Vec<u8>) {
+    if let Some(number) = number.as_u64() {
+        bytes.push(VALUE_UNSIGNED);
+        bytes.extend(number.to_le_bytes());
+    } else if let Some(number) = number.as_i64() {
+        bytes.push(VALUE_SIGNED);
+        bytes.extend(number.to_le_bytes());
+    } else {
+        bytes.push(VALUE_FLOAT);
+        bytes.extend(number.as_f64().unwrap_or(f64::NAN).to_bits().to_le_bytes());
+    }
+}
+
+/// Take the asset bundle that the web page downloaded before starting the game, if it did.
+///
+/// The page stores the contents of the bundle in the `punchyAssetBundle` global, so that it can show
+/// the download progress and decompress the bundle while the WASM module is still loading. The
+/// global is cleared afterwards so that the browser can free its copy.
+#[cfg(target_arch = "wasm32")]
+pub fn take_preloaded_bundle() -> Option<Vec<u8>> {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let window = web_sys::window()?;
+    let key = JsValue::from_str("punchyAssetBundle");
+    let value = js_sys::Reflect::get(&window, &key).ok()?;
+    if !value.is_instance_of::<js_sys::Uint8Array>() {
+        return None;
+    }
+    js_sys::Reflect::delete_property(&window, &key).ok();
+
+    Some(js_sys::Uint8Array::new(&value).to_vec())
+}
+
+/// Pack all of the files in the asset directory into an asset bundle at `output`, returning the
+/// number of files packed.
+///
+/// The metadata is validated before packing, and packing fails if there are any validation errors,
+/// so that broken metadata never makes it into a release. The metadata is then pre-parsed, and the
+/// images of the spritesheets that it uses are packed into atlas pages.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_bundle(asset_dir: &Path, output: &Path) -> anyhow::Result<usize> {
+    let report = crate::validation::validate_assets(asset_dir);
+    if report.has_errors() {
+        report.print();
+        anyhow::bail!("Cannot pack assets with validation errors");
+    }
+
+    let mut files = crate::validation::collect_files(asset_dir)?;
+    files.sort();
+
+    // Read all the files, pre-parsing the metadata
+    let mut contents = Vec::with_capacity(files.len());
+    let mut spritesheet_images = BTreeSet::new();
+    for path in &files {
+        let mut bytes = std::fs::read(asset_dir.join(path))?;
+        if is_preparsed_kind(path) {
+            let value: Value = serde_yaml::from_slice(&bytes)?;
+            collect_spritesheet_images(&value, path, &mut spritesheet_images);
+            bytes = write_preparsed_meta(&value);
+        }
+        contents.push((archive_name(path), bytes));
+    }
+
+    // Pack the spritesheet images into atlas pages, which are files of the bundle too
+    let images = spritesheet_images
+        .into_iter()
+        .filter(|path| path.extension() == Some(OsStr::new("png")))
+        .filter(|path| asset_dir.join(path).is_file())
+        .map(|path| {
+            Ok((
+                archive_name(&path),
+                image::open(asset_dir.join(path))?.to_rgba8(),
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let sizes = images
+        .iter()
+        .map(|(_, image)| UVec2::new(image.width(), image.height()))
+        .collect::<Vec<_>>();
+    let (page_sizes, placements) = pack_atlas_pages(&sizes);
+
+    let mut pages = page_sizes
+        .iter()
+        .map(|size| image::RgbaImage::new(size.x, size.y))
+        .collect::<Vec<_>>();
+    let page_names = (0..pages.len())
+        .map(|index| format!("{ATLAS_PAGE_DIR}/page_{index}.png"))
+        .collect::<Vec<_>>();
+    let mut packed_images = Vec::new();
+    for ((name, image), placement) in images.iter().zip(&placements) {
+        let Some((page, position)) = *placement else {
+            continue;
+        };
+        image::imageops::replace(
+            &mut pages[page],
+            image,
+            position.x as i64,
+            position.y as i64,
+        );
+        packed_images.push((name, &page_names[page], page_sizes[page], position));
+    }
+    for (name, page) in page_names.iter().zip(pages) {
+        let mut bytes = Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(page)
+            .write_to(&mut bytes, image::ImageOutputFormat::Png)?;
+        contents.push((name.clone(), bytes.into_inner()));
+    }
+
+    // Build the index
+    let mut index = Vec::with_capacity(contents.len());
+    let mut offset = 0;
+    for (name, bytes) in &contents {
+        index.push((name, offset, bytes.len() as u64));
+        offset += bytes.len() as u64;
+    }
+
+    let write_str = |writer: &mut BufWriter<File>, string: &str| -> std::io::Result<()> {
+        writer.write_all(&(string.len() as u32).to_le_bytes())?;
+        writer.write_all(string.as_bytes())
+    };
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writer.write_all(BUNDLE_MAGIC)?;
+    writer.write_all(&BUNDLE_VERSION.to_le_bytes())?;
+    writer.write_all(&(index.len() as u32).to_le_bytes())?;
+    for (name, offset, len) in &index {
+        write_str(&mut writer, name)?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+    }
+    writer.write_all(&(packed_images.len() as u32).to_le_bytes())?;
+    for (name, page, page_size, position) in &packed_images {
+        write_str(&mut writer, name)?;
+        write_str(&mut writer, page)?;
+        for value in [page_size.x, page_size.y, position.x, position.y] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    for (_, bytes) in &contents {
+        writer.write_all(bytes)?;
+    }
+    writer.flush()?;
+
+    Ok(index.len())
+}
+
+/// Whether the file at `path` is a kind of metadata that the packer pre-parses
+#[cfg(not(target_arch = "wasm32"))]
+fn is_preparsed_kind(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(OsStr::to_str) else {
+        return false;
+    };
+    let Some(name) = name
+        .strip_suffix(".yaml")
+        .or_else(|| name.strip_suffix(".yml"))
+    else {
+        return false;
+    };
+
+    PREPARSED_KINDS
+        .iter()
+        .any(|kind| name.ends_with(&format!(".{kind}")))
+}
+
+/// Collect the images of the spritesheets in the metadata file at `path`, which are the mappings
+/// with an `image`, a `tile_size`, `columns`, and `rows`
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_spritesheet_images(value: &Value, path: &Path, images: &mut BTreeSet<PathBuf>) {
+    match value {
+        Value::Mapping(mapping) => {
+            let is_spritesheet = ["tile_size", "columns", "rows"]
+                .iter()
+                .all(|key| mapping.contains_key(*key));
+            if is_spritesheet {
+                let image_paths = match mapping.get("image") {
+                    Some(Value::String(image)) => vec![image],
+                    Some(Value::Sequence(sequence)) => {
+                        sequence.iter().filter_map(|x| x.as_str()).collect()
+                    }
+                    _ => Vec::new(),
+                };
+                for image in image_paths {
+                    images.insert(crate::assets::relative_asset_path(path, image));
+                }
+            }
+
+            for (_, value) in mapping {
+                collect_spritesheet_images(value, path, images);
+            }
+        }
+        Value::Sequence(sequence) => {
+            for value in sequence {
+                collect_spritesheet_images(value, path, images);
+            }
+        }
+        Value::Tagged(tagged) => collect_spritesheet_images(&tagged.value, path, images),
+        _ => (),
+    }
+}
+
+/// Pack images of the given sizes into as few atlas pages as they fit in, in rows from the tallest
+/// image down. Returns the sizes of the pages, and the page and the position of every image, or
+/// `None` for the images that are too large for a page.
+#[cfg(not(target_arch = "wasm32"))]
+fn pack_atlas_pages(sizes: &[UVec2]) -> (Vec<UVec2>, Vec<Option<(usize, UVec2)>>) {
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| std::cmp::Reverse((sizes[index].y, sizes[index].x)));
+
+    let mut page_sizes = Vec::<UVec2>::new();
+    let mut placements = vec![None; sizes.len()];
+    // The position that the next image of the current row goes at, and the height of the row
+    let mut cursor = UVec2::ZERO;
+    let mut row_height = 0;
+    for index in order {
+        let size = sizes[index] + UVec2::splat(ATLAS_PADDING * 2);
+        if size.x > ATLAS_PAGE_SIZE || size.y > ATLAS_PAGE_SIZE {
+            continue;
+        }
+
+        if page_sizes.is_empty() || cursor.x + size.x > ATLAS_PAGE_SIZE {
+            // Start a new row, or a new page once the rows fill the page up
+            cursor = UVec2::new(0, cursor.y + row_height);
+            row_height = 0;
+            if page_sizes.is_empty() || cursor.y + size.y > ATLAS_PAGE_SIZE {
+                page_sizes.push(UVec2::ZERO);
+                cursor = UVec2::ZERO;
+            }
+        }
+
+        let page = page_sizes.len() - 1;
+        placements[index] = Some((page, cursor + UVec2::splat(ATLAS_PADDING)));
+        row_height = row_height.max(size.y);
+        page_sizes[page] = page_sizes[page].max(cursor + size);
+        cursor.x += size.x;
+    }
+
+    (page_sizes, placements)
+}
+
+/// Convert an asset path to the name of the file in a zip archive, which always uses `/` as the
+/// separator.
+fn archive_name(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(x) => Some(x.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether the archive file `name` is inside of the archive directory `dir`
+fn is_in_dir(name: &str, dir: &str) -> bool {
+    dir.is_empty()
+        || name
+            .strip_prefix(dir)
+            .map(|rest| rest.starts_with('/'))
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_archive_name() {
+        assert_eq!(
+            archive_name(Path::new("fighters/fishy/fishy.fighter.yaml")),
+            "fighters/fishy/fishy.fighter.yaml"
+        );
+        assert_eq!(
+            archive_name(Path::new("./levels/level1.level.yaml")),
+            "levels/level1.level.yaml"
+        );
+    }
+
+    #[test]
+    fn test_preparsed_meta() {
+        let yaml = "
+            name: Fishy
+            stats: { max_health: 200, speed: -1.5 }
+            image: [a.png, b.png]
+            effects: { 0: hit.ogg }
+            kind: !Bomb { lifetime: 2 }
+            palette: ~
+        ";
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+
+        let bytes = write_preparsed_meta(&value);
+        assert_eq!(read_preparsed_meta(&bytes).unwrap().unwrap(), value);
+
+        // YAML is still read as YAML
+        assert!(read_preparsed_meta(yaml.as_bytes()).is_none());
+    }
+
+    #[test]
+    fn test_pack_atlas_pages() {
+        let sizes = [
+            UVec2::new(1000, 500),
+            UVec2::new(1000, 600),
+            UVec2::new(1000, 400),
+            UVec2::new(3000, 10),
+            UVec2::new(2000, 1000),
+            UVec2::new(1500, 300),
+        ];
+        let (page_sizes, placements) = pack_atlas_pages(&sizes);
+
+        // The tallest images go first, in rows that fill up the pages
+        assert_eq!(placements[4], Some((0, UVec2::new(2, 2))));
+        assert_eq!(placements[1], Some((0, UVec2::new(2, 1006))));
+        assert_eq!(placements[0], Some((0, UVec2::new(1006, 1006))));
+        assert_eq!(placements[2], Some((0, UVec2::new(2, 1610))));
+        assert_eq!(placements[5], Some((1, UVec2::new(2, 2))));
+        // Images too large for a page aren't packed
+        assert_eq!(placements[3], None);
+        assert_eq!(
+            page_sizes,
+            vec![UVec2::new(2008, 2012), UVec2::new(1504, 304)]
+        );
+    }
+
+    #[test]
+    fn test_is_in_dir() {
+        assert!(is_in_dir("fighters/fishy/fishy.png", "fighters"));
+        assert!(is_in_dir("fighters/fishy/fishy.png", ""));
+        assert!(!is_in_dir("fighters2/fishy.png", "fighters"));
+        assert!(!is_in_dir("fighters", "fighters"));
+    }
+}
@@ -8,26 +8,69 @@ use bevy::{
     utils::HashMap,
 };
 use bevy_egui::egui;
+use serde::de::DeserializeOwned;
 
-use crate::{consts::FOOT_PADDING, metadata::*};
+use crate::{
+    asset_io, config::ENGINE_CONFIG, consts::FOOT_PADDING, input::InputScript, metadata::*, palette,
+};
 
 /// Register game asset and loaders
 pub fn register(app: &mut bevy::prelude::App) {
     app.register_type::<TextureAtlasSprite>()
+        // Register the metadata types so that they can be inspected and edited by tools
+        .register_type::<LevelMeta>()
+        .register_type::<FighterSpawnMeta>()
+        .register_type::<ItemSpawnMeta>()
+        .register_type::<LevelStreamingMeta>()
+        .register_type::<LevelSectionMeta>()
+        .register_type::<ParallaxMeta>()
+        .register_type::<ParallaxLayerMeta>()
+        .register_type::<PostProcessingMeta>()
+        .register_type::<PostEffectsMeta>()
+        .register_type::<PostProcessingTriggerMeta>()
+        .register_type::<TutorialStepMeta>()
+        .register_type::<TutorialAction>()
+        .register_type::<LevelDialogueMeta>()
+        .register_type::<DialogueLineMeta>()
+        .register_type::<DirectorMeta>()
+        .register_type::<DirectorBounds>()
+        .register_type::<HazardMeta>()
+        .register_type::<HazardTriggerMeta>()
+        .register_type::<FighterMeta>()
+        .register_type::<FighterHudMeta>()
+        .register_type::<FighterSpritesheetMeta>()
+        .register_type::<FighterSoundsMeta>()
+        .register_type::<ThrownMeta>()
+        .register_type::<GrappleMeta>()
+        .register_type::<ShadowMeta>()
+        .register_type::<SocketMeta>()
+        .register_type::<SocketOffsets>()
+        .register_type::<AfterimageMeta>()
+        .register_type::<SoundVariationsMeta>()
+        .register_type::<AudioMeta>()
+        .register_type::<AttackMeta>()
+        .register_type::<ColliderMeta>()
+        .register_type::<ImageMeta>()
+        .register_type::<ItemMeta>()
+        .register_type::<ItemKind>()
+        .register_type::<Element>()
         .add_asset::<GameMeta>()
         .add_asset_loader(GameMetaLoader)
         .add_asset::<LevelMeta>()
         .add_asset_loader(LevelMetaLoader)
+        .add_asset_loader(EndlessLevelLoader)
         .add_asset::<FighterMeta>()
         .add_asset_loader(FighterLoader)
         .add_asset::<ItemMeta>()
         .add_asset_loader(ItemLoader)
+        .add_asset::<InputScript>()
+        .add_asset_loader(InputScriptLoader)
         .add_asset::<EguiFont>()
         .add_asset_loader(EguiFontLoader);
 }
 
 /// Calculate an asset's full path relative to another asset
-fn relative_asset_path(asset_path: &Path, relative_path: &str) -> PathBuf {
+pub(crate) fn relative_asset_path(asset_path: &Path, relative_path: &str) -> PathBuf {
     let is_relative = !relative_path.starts_with('/');
 
     if is_relative {
@@ -41,6 +84,14 @@ fn relative_asset_path(asset_path: &Path, relative_path: &str) -> PathBuf {
     }
 }
 
+/// Get the path of an asset as a string that can be passed to the [`AssetServer`]
+fn asset_path_string(path: &Path) -> String {
+    path.as_os_str()
+        .to_str()
+        .expect("utf8-filename")
+        .to_string()
+}
+
 /// Helper to get relative asset paths and handles
 fn get_relative_asset<T: Asset>(
     load_context: &bevy::asset::LoadContext,
@@ -54,6 +105,55 @@ fn get_relative_asset<T: Asset>(
     (asset_path, handle)
 }
 
+/// Deserialize a metadata file, which is read from its YAML, or from the value tree that the asset
+/// bundle pre-parsed it into
+pub(crate) fn deserialize_meta<T: DeserializeOwned>(bytes: &[u8]) -> anyhow::Result<T> {
+    match asset_io::read_preparsed_meta(bytes) {
+        Some(value) => Ok(serde_yaml::from_value(value?)?),
+        None => Ok(serde_yaml::from_slice(bytes)?),
+    }
+}
+
+/// The texture atlas of one of the images of a spritesheet, cut into the tiles of the spritesheet,
+/// and the path of the texture that it's cut out of. The images that the asset bundle packed into
+/// an atlas page are cut out of their page.
+fn spritesheet_atlas(
+    load_context: &bevy::asset::LoadContext,
+    spritesheet: &FighterSpritesheetMeta,
+    image: &str,
+) -> (AssetPath<'static>, TextureAtlas) {
+    let tile_size = spritesheet.tile_size.as_vec2();
+    let image_path = relative_asset_path(load_context.path(), image);
+    let Some(packed) = asset_io::packed_image(&image_path) else {
+        let (texture_path, texture_handle) =
+            get_relative_asset(load_context, load_context.path(), image);
+        let atlas = TextureAtlas::from_grid(
+            texture_handle,
+            tile_size,
+            spritesheet.columns,
+            spritesheet.rows,
+            None,
+            None,
+        );
+        return (texture_path, atlas);
+    };
+
+    // The tiles are in the same order as in a grid atlas, row by row
+    let page_path = AssetPath::new(PathBuf::from(&packed.page), None);
+    let mut atlas =
+        TextureAtlas::new_empty(load_context.get_handle(page_path.clone()), packed.page_size);
+    for row in 0..spritesheet.rows {
+        for column in 0..spritesheet.columns {
+            let min = packed.position + Vec2::new(column as f32, row as f32) * tile_size;
+            atlas.add_texture(Rect {
+                min,
+                max: min + tile_size,
+            });
+        }
+    }
+    (page_path, atlas)
+}
+
 #[derive(Default)]
 pub struct GameMetaLoader;
 
@@ -64,7 +164,7 @@ impl AssetLoader for GameMetaLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: GameMeta = serde_yaml::from_slice(bytes)?;
+            let mut meta: GameMeta = deserialize_meta(bytes)?;
             trace!(?meta, "Loaded game asset");
 
             let self_path = load_context.path().to_owned();
@@ -90,12 +190,147 @@ impl AssetLoader for GameMetaLoader {
                 meta.translations.locale_handles.push(handle);
             }
 
-            // Load the start level asset
+            // Load the start level asset, which may be replaced with the start level cheat. Its
+            // path is relative to the asset directory instead of the game file.
+            if let Some(start_level) = &ENGINE_CONFIG.cheats.start_level {
+                meta.start_level = format!("/{}", start_level.trim_start_matches('/'));
+            }
             let (start_level_path, start_level_handle) =
                 get_relative_asset(load_context, &self_path, &meta.start_level);
             meta.start_level_handle = start_level_handle;
             dependencies.push(start_level_path);
 
+            if let Some(endless_level) = &meta.endless_level {
+                let (endless_level_path, endless_level_handle) =
+                    get_relative_asset(load_context, &self_path, endless_level);
+                meta.endless_level_handle = Some(endless_level_handle);
+                dependencies.push(endless_level_path);
+            }
+
+            if let Some(tutorial_level) = &meta.tutorial_level {
+                let (tutorial_level_path, tutorial_level_handle) =
+                    get_relative_asset(load_context, &self_path, tutorial_level);
+                meta.tutorial_level_handle = Some(tutorial_level_handle);
+                dependencies.push(tutorial_level_path);
+            }
+
+            if let Some(training) = &mut meta.training {
+                let (training_level_path, training_level_handle) =
+                    get_relative_asset(load_context, &self_path, &training.level);
+                training.level_handle = training_level_handle;
+                dependencies.push(training_level_path);
+
+                for fighter in &training.fighters {
+                    let (fighter_path, fighter_handle) =
+                        get_relative_asset(load_context, &self_path, fighter);
+                    training.fighter_handles.push(fighter_handle);
+                    dependencies.push(fighter_path);
+                }
+            }
+
+            if let Some(survival) = &mut meta.survival {
+                let (survival_level_path, survival_level_handle) =
+                    get_relative_asset(load_context, &self_path, &survival.level);
+                survival.level_handle = survival_level_handle;
+                dependencies.push(survival_level_path);
+
+                for enemy in &survival.enemies {
+                    let (enemy_path, enemy_handle) =
+                        get_relative_asset(load_context, &self_path, enemy);
+                    survival.enemy_handles.push(enemy_handle);
+                    dependencies.push(enemy_path);
+                }
+            }
+
+            if let Some(versus) = &mut meta.versus {
+                let (versus_level_path, versus_level_handle) =
+                    get_relative_asset(load_context, &self_path, &versus.level);
+                versus.level_handle = versus_level_handle;
+                dependencies.push(versus_level_path);
+            }
+
+            if let Some(new_game_plus) = &mut meta.new_game_plus {
+                for enemy_variant in &mut new_game_plus.enemy_variants {
+                    let (fighter_path, fighter_handle) =
+                        get_relative_asset(load_context, &self_path, &enemy_variant.fighter);
+                    let (variant_path, variant_handle) =
+                        get_relative_asset(load_context, &self_path, &enemy_variant.variant);
+
+                    // Use the paths that the enemies of streamed level sections are spawned from
+                    enemy_variant.fighter = asset_path_string(fighter_path.path());
+                    enemy_variant.variant = asset_path_string(variant_path.path());
+                    enemy_variant.fighter_handle = fighter_handle;
+                    enemy_variant.variant_handle = variant_handle;
+                    dependencies.push(fighter_path);
+                    dependencies.push(variant_path);
+                }
+            }
+
+            if let Some(gallery) = &mut meta.gallery {
+                for art in &mut gallery.art {
+                    let (image_path, image_handle) =
+                        get_relative_asset(load_context, &self_path, &art.image.image);
+                    art.image.image_handle = image_handle;
+                    dependencies.push(image_path);
+                }
+                for track in &mut gallery.music {
+                    let (music_path, music_handle) =
+                        get_relative_asset(load_context, &self_path, &track.music);
+                    track.music_handle = music_handle;
+                    dependencies.push(music_path);
+                }
+                for bio in &mut gallery.bios {
+                    let (fighter_path, fighter_handle) =
+                        get_relative_asset(load_context, &self_path, &bio.fighter);
+                    bio.fighter_handle = fighter_handle;
+                    dependencies.push(fighter_path);
+                }
+            }
+
+            if let Some(spirits) = &mut meta.spirits {
+                let (image_path, image_handle) =
+                    get_relative_asset(load_context, &self_path, &spirits.image.image);
+                spirits.image.image_handle = image_handle;
+                dependencies.push(image_path);
+
+                let (pickup_path, pickup_handle) =
+                    get_relative_asset(load_context, &self_path, &spirits.pickup);
+                spirits.pickup_handle = pickup_handle;
+                dependencies.push(pickup_path);
+            }
+
+            if let Some(nameplates) = &mut meta.nameplates {
+                let (icon_path, icon_handle) =
+                    get_relative_asset(load_context, &self_path, &nameplates.threat_icon.image);
+                nameplates.threat_icon.image_handle = icon_handle;
+                dependencies.push(icon_path);
+            }
+
+            if let Some(effects) = &mut meta.effects {
+                let all_effects = [
+                    ("hit", &mut effects.hit),
+                    ("heavy_hit", &mut effects.heavy_hit),
+                    ("landing", &mut effects.landing),
+                    ("blood", &mut effects.blood),
+                ];
+                for (name, effect) in all_effects {
+                    let Some(effect) = effect else {
+                        continue;
+                    };
+                    let spritesheet = &mut effect.spritesheet;
+                    for (index, image) in spritesheet.image.iter().enumerate() {
+                        let (texture_path, atlas) =
+                            spritesheet_atlas(load_context, spritesheet, image);
+
+                        let atlas_handle = load_context.set_labeled_asset(
+                            format!("{name}_effect_atlas_{index}").as_str(),
+                            LoadedAsset::new(atlas).with_dependency(texture_path),
+                        );
+                        spritesheet.atlas_handle.push(atlas_handle);
+                    }
+                }
+            }
+
             // Load the main menu background
             let (main_menu_background_path, main_menu_background) = get_relative_asset(
                 load_context,
@@ -110,6 +345,13 @@ impl AssetLoader for GameMetaLoader {
                 let (path, handle) = get_relative_asset(load_context, &self_path, &border.image);
                 dependencies.push(path);
                 border.handle = handle;
+
+                for resolution in &mut border.resolutions {
+                    let (path, handle) =
+                        get_relative_asset(load_context, &self_path, &resolution.image);
+                    dependencies.push(path);
+                    resolution.handle = handle;
+                }
             };
             load_border_image(&mut meta.ui_theme.hud.portrait_frame);
             load_border_image(&mut meta.ui_theme.panel.border);
@@ -124,6 +366,12 @@ impl AssetLoader for GameMetaLoader {
                     load_border_image(border);
                 }
             }
+            for theme_override in meta.ui_theme.states.values_mut() {
+                theme_override
+                    .border_images_mut()
+                    .into_iter()
+                    .for_each(&mut load_border_image);
+            }
 
             // Load the music
             let (music_path, music_handle) =
@@ -143,6 +391,14 @@ impl AssetLoader for GameMetaLoader {
                 meta.main_menu.button_sound_handles.push(handle);
             }
 
+            // Load the attract mode demo
+            if let Some(attract_demo) = &meta.main_menu.attract_demo {
+                let (attract_demo_path, attract_demo_handle) =
+                    get_relative_asset(load_context, &self_path, attract_demo);
+                meta.main_menu.attract_demo_handle = Some(attract_demo_handle);
+                dependencies.push(attract_demo_path);
+            }
+
             // Load UI fonts
             for (font_name, font_relative_path) in &meta.ui_theme.font_families {
                 let (font_path, font_handle) =
@@ -154,6 +410,12 @@ impl AssetLoader for GameMetaLoader {
                     .font_handles
                     .insert(font_name.clone(), font_handle);
             }
+            for resolution in meta.ui_theme.font_resolutions.values_mut().flatten() {
+                let (font_path, font_handle) =
+                    get_relative_asset(load_context, &self_path, &resolution.font);
+                dependencies.push(font_path);
+                resolution.handle = font_handle;
+            }
 
             // Load the script handles
             for script_relative_path in &meta.scripts {
@@ -183,73 +445,163 @@ impl AssetLoader for LevelMetaLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: LevelMeta = serde_yaml::from_slice(bytes)?;
+            let meta: LevelMeta = deserialize_meta(bytes)?;
             trace!(?meta, "Loaded level asset");
 
-            let self_path = load_context.path();
+            load_level_meta(load_context, meta);
 
-            let mut dependencies = Vec::new();
+            Ok(())
+        })
+    }
 
-            // Load the players
-            for player in &mut meta.players {
-                let (player_fighter_path, player_fighter_handle) =
-                    get_relative_asset(load_context, self_path, &player.fighter);
-                dependencies.push(player_fighter_path);
+    fn extensions(&self) -> &[&str] {
+        &["level.yml", "level.yaml"]
+    }
+}
 
-                player.fighter_handle = player_fighter_handle;
-            }
+/// Generates a [`LevelMeta`] from an [`EndlessLevelMeta`], so that endless levels can be loaded
+/// just like any other level.
+pub struct EndlessLevelLoader;
+
+impl AssetLoader for EndlessLevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let endless: EndlessLevelMeta = deserialize_meta(bytes)?;
 
-            // Load the enemies
-            for enemy in &mut meta.enemies {
-                let (enemy_fighter_path, enemy_fighter_handle) =
-                    get_relative_asset(load_context, self_path, &enemy.fighter);
-                dependencies.push(enemy_fighter_path);
+            let seed = endless
+                .seed
+                .or(ENGINE_CONFIG.level_seed)
+                .or(ENGINE_CONFIG.seed)
+                .unwrap_or_else(rand::random);
+            info!(seed, path = ?load_context.path(), "Generating endless level");
 
-                enemy.fighter_handle = enemy_fighter_handle;
-            }
+            let meta = endless.generate(seed)?;
+            trace!(?meta, "Generated level asset");
 
-            // Load the items
-            for item in &mut meta.items {
-                let (item_path, item_handle) =
-                    get_relative_asset(load_context, self_path, &item.item);
+            load_level_meta(load_context, meta);
 
-                dependencies.push(item_path);
+            Ok(())
+        })
+    }
 
-                item.item_handle = item_handle;
-            }
+    fn extensions(&self) -> &[&str] {
+        &["endless.yml", "endless.yaml"]
+    }
+}
 
-            // Load parallax background layers
-            for layer in &mut meta.parallax_background.layers {
-                let (path, handle) = get_relative_asset(load_context, self_path, &layer.path);
+/// Load the assets referenced by a level and set it as the default asset of the load context
+fn load_level_meta(load_context: &mut bevy::asset::LoadContext, mut meta: LevelMeta) {
+    let self_path = load_context.path();
 
-                // Update the layer path to use an absolute path so that it matches the conventione
-                // used by the bevy_parallax_background plugin.
-                layer.path = path
-                    .path()
-                    .as_os_str()
-                    .to_str()
-                    .expect("utf8-filename")
-                    .to_string();
+    let mut dependencies = Vec::new();
 
-                layer.image_handle = handle;
-                dependencies.push(path);
+    // Load the players
+    for player in &mut meta.players {
+        let (player_fighter_path, player_fighter_handle) =
+            get_relative_asset(load_context, self_path, &player.fighter);
+        dependencies.push(player_fighter_path);
+
+        player.fighter_handle = player_fighter_handle;
+    }
+
+    // Move the spawns after the first section of a streamed level into their sections. Their
+    // assets aren't dependencies of the level, and are loaded once the camera gets close to them.
+    if let Some(streaming) = &mut meta.streaming {
+        streaming.split_sections(&mut meta.enemies, &mut meta.items);
+
+        for section in &mut streaming.sections {
+            for enemy in &mut section.enemies {
+                enemy.fighter = asset_path_string(&relative_asset_path(self_path, &enemy.fighter));
+            }
+            for item in &mut section.items {
+                item.item = asset_path_string(&relative_asset_path(self_path, &item.item));
             }
+        }
+    }
 
-            // Load the music
-            let (music_path, music_handle) =
-                get_relative_asset(load_context, self_path, &meta.music);
-            meta.music_handle = music_handle;
-            dependencies.push(music_path);
+    // Load the enemies
+    for enemy in &mut meta.enemies {
+        let (enemy_fighter_path, enemy_fighter_handle) =
+            get_relative_asset(load_context, self_path, &enemy.fighter);
+        dependencies.push(enemy_fighter_path);
 
-            load_context.set_default_asset(LoadedAsset::new(meta).with_dependencies(dependencies));
+        enemy.fighter_handle = enemy_fighter_handle;
+    }
 
-            Ok(())
-        })
+    // Load the enemies of the waves
+    for enemy in meta.waves.iter_mut().flat_map(|wave| &mut wave.enemies) {
+        let (enemy_fighter_path, enemy_fighter_handle) =
+            get_relative_asset(load_context, self_path, &enemy.fighter);
+        dependencies.push(enemy_fighter_path);
+
+        enemy.fighter_handle = enemy_fighter_handle;
     }
 
-    fn extensions(&self) -> &[&str] {
-        &["level.yml", "level.yaml"]
+    // Load the items
+    for item in &mut meta.items {
+        let (item_path, item_handle) = get_relative_asset(load_context, self_path, &item.item);
+
+        dependencies.push(item_path);
+
+        item.item_handle = item_handle;
+    }
+
+    // Load the speakers of the dialogues
+    for line in meta
+        .dialogues
+        .iter_mut()
+        .flat_map(|dialogue| &mut dialogue.lines)
+    {
+        let (speaker_path, speaker_handle) =
+            get_relative_asset(load_context, self_path, &line.speaker);
+        dependencies.push(speaker_path);
+
+        line.speaker_handle = speaker_handle;
+    }
+
+    // Load the item dropped by the enemies for the director
+    if let Some(director) = &mut meta.director {
+        if let Some(drop_item) = &director.drop_item {
+            let (item_path, item_handle) = get_relative_asset(load_context, self_path, drop_item);
+            dependencies.push(item_path);
+
+            director.drop_item_handle = Some(item_handle);
+        }
+    }
+
+    // Load the images of the hazards
+    for hazard in &mut meta.hazards {
+        for image in std::iter::once(&mut hazard.image).chain(&mut hazard.triggered_image) {
+            let (image_path, image_handle) =
+                get_relative_asset(load_context, self_path, &image.image);
+            dependencies.push(image_path);
+
+            image.image_handle = image_handle;
+        }
+    }
+
+    // Load parallax background layers
+    for layer in &mut meta.parallax_background.layers {
+        let (path, handle) = get_relative_asset(load_context, self_path, &layer.path);
+
+        // Update the layer path to use an absolute path so that it matches the conventione
+        // used by the bevy_parallax_background plugin.
+        layer.path = asset_path_string(path.path());
+
+        layer.image_handle = handle;
+        dependencies.push(path);
     }
+
+    // Load the music
+    let (music_path, music_handle) = get_relative_asset(load_context, self_path, &meta.music);
+    meta.music_handle = music_handle;
+    dependencies.push(music_path);
+
+    load_context.set_default_asset(LoadedAsset::new(meta).with_dependencies(dependencies));
 }
 
 pub struct FighterLoader;
@@ -261,10 +613,42 @@ impl AssetLoader for FighterLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: FighterMeta = serde_yaml::from_slice(bytes)?;
+            let self_path = load_context.path().to_owned();
+
+            // Resolve the chain of fighters that this fighter extends, if any
+            let mut meta_value: serde_yaml::Value = deserialize_meta(bytes)?;
+            let mut extended_path = self_path.clone();
+            let mut extended_paths = vec![self_path.clone()];
+            while let Some(extends) = take_yaml_key(&mut meta_value, "extends") {
+                let extends = extends
+                    .as_str()
+                    .ok_or_else(|| anyhow::format_err!("`extends` must be a path to a fighter"))?;
+                let base_path = relative_asset_path(&extended_path, extends);
+
+                if extended_paths.contains(&base_path) {
+                    anyhow::bail!(
+                        "Fighter {:?} extends itself through {:?}",
+                        self_path,
+                        base_path
+                    );
+                }
+
+                let base_bytes = load_context.read_asset_bytes(&base_path).await?;
+                let mut base_value: serde_yaml::Value = deserialize_meta(&base_bytes)?;
+
+                // Paths in the base fighter are relative to the base fighter, not to us
+                absolutize_fighter_paths(&mut base_value, &base_path);
+                merge_yaml(&mut base_value, meta_value);
+
+                meta_value = base_value;
+                extended_path = base_path.clone();
+                extended_paths.push(base_path);
+            }
+
+            let mut meta: FighterMeta = serde_yaml::from_value(meta_value)?;
             trace!(?meta, "Loaded fighter asset");
 
-            let self_path = load_context.path();
+            let self_path = self_path.as_path();
             let mut dependencies = Vec::new();
 
             for attack in &mut meta.attacks {
@@ -276,6 +660,23 @@ impl AssetLoader for FighterLoader {
 
                     attack.item_handle = item_handle;
                 }
+                if let Some(projectile) = &mut attack.projectile {
+                    let (image_path, image_handle) =
+                        get_relative_asset(load_context, self_path, &projectile.image.image);
+                    dependencies.push(image_path);
+                    projectile.image.image_handle = image_handle;
+                }
+            }
+
+            for minion in meta
+                .boss_phases
+                .iter_mut()
+                .flat_map(|phase| &mut phase.minions)
+            {
+                let (fighter_path, fighter_handle) =
+                    get_relative_asset(load_context, self_path, &minion.fighter);
+                dependencies.push(fighter_path);
+                minion.fighter_handle = fighter_handle;
             }
 
             let (portrait_path, portrait_handle) =
@@ -300,46 +701,41 @@ impl AssetLoader for FighterLoader {
                 }
             }
 
+            for sound in meta.sounds.iter_mut() {
+                for file in &sound.files {
+                    let (asset_path, handle) = get_relative_asset(load_context, self_path, file);
+                    dependencies.push(asset_path);
+                    sound.handles.push(handle);
+                }
+            }
+
             for (index, image) in meta.spritesheet.image.iter().enumerate() {
-                let (texture_path, texture_handle) =
-                    get_relative_asset(load_context, load_context.path(), image);
+                let (texture_path, atlas) =
+                    spritesheet_atlas(load_context, &meta.spritesheet, image);
 
                 let atlas_handle = load_context.set_labeled_asset(
                     format!("atlas_{index}").as_str(),
-                    LoadedAsset::new(TextureAtlas::from_grid(
-                        texture_handle,
-                        meta.spritesheet.tile_size.as_vec2(),
-                        meta.spritesheet.columns,
-                        meta.spritesheet.rows,
-                        None,
-                        None,
-                    ))
-                    .with_dependency(texture_path),
+                    LoadedAsset::new(atlas).with_dependency(texture_path),
                 );
                 meta.spritesheet.atlas_handle.push(atlas_handle);
                 meta.center_y = meta.spritesheet.tile_size.y as f32 / 2.;
                 meta.collision_offset = meta.center_y - FOOT_PADDING;
             }
 
+            dependencies.extend(palette::load_palette(load_context, &mut meta.spritesheet).await?);
+
             if let Some(ref mut attachment) = meta.attachment {
                 for (index, image) in attachment.image.iter().enumerate() {
-                    let (texture_path, texture_handle) =
-                        get_relative_asset(load_context, load_context.path(), image);
+                    let (texture_path, atlas) = spritesheet_atlas(load_context, attachment, image);
 
                     let atlas_handle = load_context.set_labeled_asset(
                         format!("atlas_{}", index + attachment.image.len()).as_str(),
-                        LoadedAsset::new(TextureAtlas::from_grid(
-                            texture_handle,
-                            attachment.tile_size.as_vec2(),
-                            attachment.columns,
-                            attachment.rows,
-                            None,
-                            None,
-                        ))
-                        .with_dependency(texture_path),
+                        LoadedAsset::new(atlas).with_dependency(texture_path),
                     );
                     attachment.atlas_handle.push(atlas_handle);
                 }
+
+                dependencies.extend(palette::load_palette(load_context, attachment).await?);
             }
 
             load_context.set_default_asset(LoadedAsset::new(meta).with_dependencies(dependencies));
@@ -353,6 +749,111 @@ impl AssetLoader for FighterLoader {
     }
 }
 
+/// Remove a key from a YAML mapping, returning its value if it was present.
+pub(crate) fn take_yaml_key(value: &mut serde_yaml::Value, key: &str) -> Option<serde_yaml::Value> {
+    value
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove(key))
+}
+
+/// Merge `overrides` into `base`.
+///
+/// Mappings are merged recursively, and any other value in `overrides` replaces the one in `base`.
+pub(crate) fn merge_yaml(base: &mut serde_yaml::Value, overrides: serde_yaml::Value) {
+    match (base, overrides) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                if let Some(base_value) = base.get_mut(&key) {
+                    merge_yaml(base_value, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Convert the relative asset paths in a raw fighter definition to absolute asset paths, so that
+/// they still point to the right files when the definition is inherited by a fighter in another
+/// directory.
+pub(crate) fn absolutize_fighter_paths(fighter: &mut serde_yaml::Value, fighter_path: &Path) {
+    let absolutize = |value: &mut serde_yaml::Value| {
+        if let Some(relative_path) = value.as_str() {
+            let path = relative_asset_path(fighter_path, relative_path);
+            *value = format!("/{}", path.to_string_lossy()).into();
+        }
+    };
+
+    for spritesheet in ["spritesheet", "attachment"] {
+        if let Some(spritesheet) = fighter.get_mut(spritesheet) {
+            if let Some(images) = spritesheet
+                .get_mut("image")
+                .and_then(|x| x.as_sequence_mut())
+            {
+                images.iter_mut().for_each(absolutize);
+            }
+
+            if let Some(palette) = spritesheet.get_mut("palette") {
+                absolutize(palette);
+            }
+        }
+    }
+
+    if let Some(portrait) = fighter
+        .get_mut("hud")
+        .and_then(|x| x.get_mut("portrait"))
+        .and_then(|x| x.get_mut("image"))
+    {
+        absolutize(portrait);
+    }
+
+    if let Some(effects) = fighter
+        .get_mut("audio")
+        .and_then(|x| x.get_mut("effects"))
+        .and_then(|x| x.as_mapping_mut())
+    {
+        for frames in effects.values_mut() {
+            if let Some(frames) = frames.as_mapping_mut() {
+                frames.values_mut().for_each(absolutize);
+            }
+        }
+    }
+
+    if let Some(sounds) = fighter.get_mut("sounds").and_then(|x| x.as_mapping_mut()) {
+        for files in sounds.values_mut().filter_map(|x| x.as_sequence_mut()) {
+            files.iter_mut().for_each(absolutize);
+        }
+    }
+
+    if let Some(attacks) = fighter.get_mut("attacks").and_then(|x| x.as_sequence_mut()) {
+        for item in attacks
+            .iter_mut()
+            .filter_map(|attack| attack.get_mut("item"))
+        {
+            absolutize(item);
+        }
+    }
+
+    if let Some(phases) = fighter
+        .get_mut("boss_phases")
+        .and_then(|x| x.as_sequence_mut())
+    {
+        for minions in phases
+            .iter_mut()
+            .filter_map(|phase| phase.get_mut("minions"))
+            .filter_map(|x| x.as_sequence_mut())
+        {
+            for fighter in minions
+                .iter_mut()
+                .filter_map(|minion| minion.get_mut("fighter"))
+            {
+                absolutize(fighter);
+            }
+        }
+    }
+}
+
 pub struct ItemLoader;
 
 impl AssetLoader for ItemLoader {
@@ -362,7 +863,7 @@ impl AssetLoader for ItemLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: ItemMeta = serde_yaml::from_slice(bytes)?;
+            let mut meta: ItemMeta = deserialize_meta(bytes)?;
             trace!(?meta, "Loaded item asset");
 
             let self_path = load_context.path();
@@ -414,20 +915,12 @@ impl AssetLoader for ItemLoader {
                     }
 
                     for (index, image) in spritesheet.image.iter().enumerate() {
-                        let (texture_path, texture_handle) =
-                            get_relative_asset(load_context, load_context.path(), image);
+                        let (texture_path, atlas) =
+                            spritesheet_atlas(load_context, spritesheet, image);
 
                         let atlas_handle = load_context.set_labeled_asset(
                             format!("atlas_{index}").as_str(),
-                            LoadedAsset::new(TextureAtlas::from_grid(
-                                texture_handle,
-                                spritesheet.tile_size.as_vec2(),
-                                spritesheet.columns,
-                                spritesheet.rows,
-                                None,
-                                None,
-                            ))
-                            .with_dependency(texture_path),
+                            LoadedAsset::new(atlas).with_dependency(texture_path),
                         );
                         spritesheet.atlas_handle.push(atlas_handle);
                     }
@@ -443,20 +936,12 @@ impl AssetLoader for ItemLoader {
                 }
                 ItemKind::Bomb { spritesheet, .. } => {
                     for (index, image) in spritesheet.image.iter().enumerate() {
-                        let (texture_path, texture_handle) =
-                            get_relative_asset(load_context, load_context.path(), image);
+                        let (texture_path, atlas) =
+                            spritesheet_atlas(load_context, spritesheet, image);
 
                         let atlas_handle = load_context.set_labeled_asset(
                             format!("atlas_{index}").as_str(),
-                            LoadedAsset::new(TextureAtlas::from_grid(
-                                texture_handle,
-                                spritesheet.tile_size.as_vec2(),
-                                spritesheet.columns,
-                                spritesheet.rows,
-                                None,
-                                None,
-                            ))
-                            .with_dependency(texture_path),
+                            LoadedAsset::new(atlas).with_dependency(texture_path),
                         );
                         spritesheet.atlas_handle.push(atlas_handle);
                     }
@@ -475,6 +960,39 @@ impl AssetLoader for ItemLoader {
     }
 }
 
+pub struct InputScriptLoader;
+
+impl AssetLoader for InputScriptLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let mut script: InputScript = deserialize_meta(bytes)?;
+            trace!(?script, "Loaded input script asset");
+
+            let self_path = load_context.path().to_owned();
+
+            // The level path of input scripts is relative to the asset directory
+            let (level_path, level_handle) = get_relative_asset(
+                load_context,
+                &self_path,
+                &format!("/{}", script.level.trim_start_matches('/')),
+            );
+            script.level_handle = level_handle;
+
+            load_context.set_default_asset(LoadedAsset::new(script).with_dependency(level_path));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["demo.yml", "demo.yaml"]
+    }
+}
+
 #[derive(Debug, Clone, TypeUuid)]
 #[uuid = "da277340-574f-4069-907c-7571b8756200"]
 pub struct EguiFont(pub egui::FontData);
@@ -511,3 +1029,52 @@ impl EguiFontDefinitions {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_yaml() {
+        let mut base: serde_yaml::Value = serde_yaml::from_str(
+            "name: Base\nstats:\n  max_health: 100\n  movement_speed: 150\nimage: [a.png]",
+        )
+        .unwrap();
+        let overrides: serde_yaml::Value =
+            serde_yaml::from_str("name: Variant\nstats:\n  max_health: 200\nimage: [b.png]")
+                .unwrap();
+
+        merge_yaml(&mut base, overrides);
+
+        assert_eq!(base["name"].as_str(), Some("Variant"));
+        assert_eq!(base["stats"]["max_health"].as_i64(), Some(200));
+        assert_eq!(base["stats"]["movement_speed"].as_i64(), Some(150));
+        assert_eq!(base["image"][0].as_str(), Some("b.png"));
+    }
+
+    #[test]
+    fn test_absolutize_fighter_paths() {
+        let mut fighter: serde_yaml::Value = serde_yaml::from_str(
+            "spritesheet:\n  image: [base.png, /shared.png]\nhud:\n  portrait:\n    image: portrait.png",
+        )
+        .unwrap();
+
+        absolutize_fighter_paths(
+            &mut fighter,
+            Path::new("fighters/bandit/bandit.fighter.yaml"),
+        );
+
+        assert_eq!(
+            fighter["spritesheet"]["image"][0].as_str(),
+            Some("/fighters/bandit/base.png")
+        );
+        assert_eq!(
+            fighter["spritesheet"]["image"][1].as_str(),
+            Some("/shared.png")
+        );
+        assert_eq!(
+            fighter["hud"]["portrait"]["image"].as_str(),
+            Some("/fighters/bandit/portrait.png")
+        );
+    }
+}
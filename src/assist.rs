@@ -0,0 +1,181 @@
+//! Assists, the accessibility options that make the game easier.
+//!
+//! The [`AssistSettings`] are read from the settings when a level is loaded, and are applied to the
+//! simulation until the next level is loaded. Runs played with any assists are flagged on the
+//! results screens.
+//!
+//! Assists aren't applied in the attract demo, or without a UI, so that recorded inputs play out
+//! the same way everywhere.
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    arcade::ArcadeRun,
+    damage::Damageable,
+    fighter_state::Idling,
+    metadata::{AssistSettings, GameMeta, LevelMeta, Settings},
+    platform::Storage,
+    player::{Player, PlayerBundle, PlayerIndex},
+    timestep::{gameplay_stage, TimeScale, GAMEPLAY_TIMESTEP},
+    ui::attract_mode::{self, AttractMode},
+    versus::VersusMatch,
+    GameState,
+};
+
+/// How much of the damage of a hit the players take with reduced damage
+const REDUCED_DAMAGE_FACTOR: f32 = 0.5;
+
+/// The speed of the game with the slower game speed
+const SLOWER_GAME_SPEED: f32 = 0.75;
+
+/// How many times the players can come back in a level with extra lives
+pub const EXTRA_LIVES: u32 = 3;
+
+pub struct AssistPlugin;
+
+impl Plugin for AssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_exit_system(GameState::LoadingLevel, start_assists)
+            .add_enter_system(GameState::MainMenu, end_assists)
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                auto_block
+                    .run_in_state(GameState::InGame)
+                    .run_if(assist_enabled(|assists| assists.auto_block)),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                use_extra_lives
+                    .run_in_state(GameState::InGame)
+                    .run_if(assist_enabled(|assists| assists.extra_lives))
+                    // Versus respawns the players for each round instead, and arcade has its own lives
+                    .run_unless_resource_exists::<VersusMatch>()
+                    .run_unless_resource_exists::<ArcadeRun>(),
+            );
+    }
+}
+
+/// The assists of the level being played, which exists while it's played with any assists
+#[derive(Resource)]
+pub struct Assists {
+    pub settings: AssistSettings,
+    /// The number of extra lives that are left in the level
+    pub lives_left: u32,
+}
+
+impl Assists {
+    /// The damage that a player takes from a hit
+    pub fn player_damage(&self, damage: i32) -> i32 {
+        if self.settings.reduced_damage {
+            (damage as f32 * REDUCED_DAMAGE_FACTOR).ceil() as i32
+        } else {
+            damage
+        }
+    }
+}
+
+/// Run condition that checks an assist of the level being played, i.e.
+/// `.run_if(assist_enabled(|assists| assists.auto_block))`.
+pub fn assist_enabled(
+    assist: fn(&AssistSettings) -> bool,
+) -> impl Fn(Option<Res<Assists>>) -> bool + Send + Sync + 'static {
+    move |assists| assists.map_or(false, |assists| assist(&assists.settings))
+}
+
+/// Read the assists from the settings for the level that has been loaded
+fn start_assists(
+    mut commands: Commands,
+    mut storage: ResMut<Storage>,
+    mut time_scale: ResMut<TimeScale>,
+    attract_mode: Option<Res<AttractMode>>,
+) {
+    let demo_playing = attract_mode.map_or(false, attract_mode::demo_playing);
+    let settings = storage
+        .get::<Settings>(Settings::STORAGE_KEY)
+        .map(|settings| settings.assist)
+        .filter(|_| !demo_playing)
+        .unwrap_or_default();
+
+    let game_speed = if settings.slower_game_speed {
+        SLOWER_GAME_SPEED
+    } else {
+        1.0
+    };
+    if time_scale.scale != game_speed {
+        time_scale.scale = game_speed;
+    }
+
+    if settings.any() {
+        info!(?settings, "Playing with assists");
+        commands.insert_resource(Assists {
+            settings,
+            lives_left: EXTRA_LIVES,
+        });
+    } else {
+        commands.remove_resource::<Assists>();
+    }
+}
+
+fn end_assists(
+    mut commands: Commands,
+    assists: Option<Res<Assists>>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    if assists.map_or(false, |assists| assists.settings.slower_game_speed) {
+        time_scale.scale = 1.0;
+    }
+    commands.remove_resource::<Assists>();
+}
+
+/// Keep the players from being damaged while they are standing still
+fn auto_block(mut players: Query<(&mut Damageable, Option<&Idling>), With<Player>>) {
+    for (mut damageable, idling) in &mut players {
+        let damageable_now = idling.is_none();
+        if **damageable != damageable_now {
+            **damageable = damageable_now;
+        }
+    }
+}
+
+/// Bring knocked out players back where the camera is, while there are extra lives left
+fn use_extra_lives(
+    mut commands: Commands,
+    mut assists: ResMut<Assists>,
+    players: Query<&PlayerIndex, With<Player>>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    if assists.lives_left == 0 {
+        return;
+    }
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+
+    let settings = storage.get(Settings::STORAGE_KEY);
+    for (i, player) in level.players.iter().enumerate() {
+        if assists.lives_left == 0 {
+            break;
+        }
+        if players.iter().any(|player_index| player_index.0 == i) {
+            continue;
+        }
+
+        let mut player = player.clone();
+        player.location.x = camera_transform.translation.x;
+        commands.spawn(PlayerBundle::new(&player, i, &game, settings.as_ref()));
+
+        assists.lives_left -= 1;
+        info!(
+            player = i,
+            lives_left = assists.lives_left,
+            "Player came back with an extra life"
+        );
+    }
+}
@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use bevy::{
     hierarchy::DespawnRecursiveExt,
     math::Vec2,
@@ -12,47 +10,75 @@ use iyes_loopless::prelude::*;
 use serde::Deserialize;
 
 use crate::{
-    animation::Animation,
-    damage::{DamageEvent, Damageable, Health},
+    animation::{Animation, Facing},
+    combat_events::{Blocked, HitLanded},
+    combo_breaker::ComboCounter,
+    damage::{Damageable, FighterDamage},
     enemy::Enemy,
-    fighter_state::MeleeWeapon,
+    fighter::Stats,
+    fighter_state::{Airborne, Blocking, Invulnerable, KnockedDown, MeleeWeapon, Thrown},
+    flying::{reaches_flying, Flying},
     item::{Drop, Explodable},
-    metadata::ColliderMeta,
+    metadata::{ColliderMeta, FighterMeta, GameMeta, StatusEffectMeta},
     player::Player,
+    pool::EntityPoolCommands,
+    rng::GameRng,
+    team::Teams,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
     GameState,
 };
 
 pub struct AttackPlugin;
 
+/// Label for the system that deals damage from attacks and sends the [`HitLanded`], [`Blocked`],
+/// and [`Killed`](crate::combat_events::Killed) events
+#[derive(Clone, SystemLabel)]
+pub struct AttackDamageSystem;
+
+/// Label for the system that breaks [`Breakable`]s and sends the [`BrokeEvent`]s
+#[derive(Clone, SystemLabel)]
+pub struct BreakableSystem;
+
 impl Plugin for AttackPlugin {
     fn build(&self, app: &mut App) {
         app
             // Register reflect types
             .register_type::<Attack>()
+            .register_type::<Breakable>()
+            .register_type::<AttackFrames>()
             // Add systems
-            .add_system_set(
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
                     .with_system(activate_hitbox)
                     .with_system(deactivate_hitbox)
+                    .with_system(apply_frame_boxes)
+                    .into(),
+            )
+            // Attack damage and breaking is run after rapier generates collision events
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                ConditionSet::new()
+                    .label(BreakableSystem)
+                    .run_in_state(GameState::InGame)
                     .with_system(breakable_system)
-                    .with_system(damage_flash)
                     .into(),
             )
-            // Attack damage is run in PostUpdate to make sure it runs after rapier generates collision events
-            .add_system_to_stage(CoreStage::PostUpdate, attack_damage_system)
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                attack_damage_system.label(AttackDamageSystem),
+            )
             // Event for when Breakable breaks
             .add_event::<BrokeEvent>();
     }
 }
 
-//Component to representing a timer to start and stop the flash upon damage impact
-#[derive(Component)]
-pub struct FlashingTimer {
-    pub timer: Timer,
-}
-
-/// A component representing an attack that can do damage to [`Damageable`]s with [`Health`].
+/// A component representing an attack that can do damage to [`Damageable`]s with
+/// [`Health`](crate::damage::Health).
 #[derive(Component, Clone, Copy, Default, Reflect)]
 #[reflect(Component)]
 pub struct Attack {
@@ -63,13 +89,25 @@ pub struct Attack {
     pub hitstun_duration: f32,
     /// add this for attacks that are not immediately active, used in activate_hitbox
     pub hitbox_meta: Option<ColliderMeta>,
+    /// The status effect that the attack puts on the fighters it hits
+    pub status_effect: Option<StatusEffectMeta>,
+    /// How high the attack launches the fighters it hits into the air
+    pub launch: f32,
+    /// Whether the attack reaches the flying enemies, even from the ground
+    pub anti_air: bool,
 }
 
 #[derive(Component)]
 pub struct Hurtbox;
 
+/// The box that the collider of an attack or a hurtbox has been given, which is changed by
+/// [`apply_frame_boxes`] when the frames of an animation give it another one
+#[derive(Component, Deref)]
+struct AppliedBox(ColliderMeta);
+
 /// A component that depawns an entity after collision.
 #[derive(Component, Clone, Copy, Default, Reflect)]
+#[reflect(Component)]
 pub struct Breakable {
     /// The number of collisions allowed before the entity is breakable.
     pub hit_tolerance: i32,
@@ -111,9 +149,12 @@ pub struct AttackFrames {
 /// adding a collider to the attack entity.
 //TODO: is there a way we can move the adding of collision layers here as well?
 fn activate_hitbox(
-    attack_query: Query<(Entity, &Attack, &AttackFrames, &Parent), Without<Collider>>,
+    mut attack_query: Query<
+        (Entity, &Attack, &AttackFrames, &Parent, &mut Transform),
+        Without<Collider>,
+    >,
     parent_query: Query<
-        &Animation,
+        (&Animation, Option<&Facing>),
         Or<(
             With<Player>,
             With<Enemy>,
@@ -123,12 +164,16 @@ fn activate_hitbox(
     >,
     mut commands: Commands,
 ) {
-    for (entity, attack, attack_frames, parent) in attack_query.iter() {
-        if let Ok(animation) = parent_query.get(**parent) {
+    for (entity, attack, attack_frames, parent, mut transform) in &mut attack_query {
+        if let Ok((animation, facing)) = parent_query.get(**parent) {
             if animation.current_frame >= attack_frames.startup
                 && animation.current_frame <= attack_frames.active
             {
-                if let Some(hitbox_meta) = attack.hitbox_meta {
+                if let Some(attack_hitbox) = attack.hitbox_meta {
+                    // The frame that the attack starts on can give it another hitbox
+                    let hitbox_meta = animation.frame_hitbox().unwrap_or(attack_hitbox);
+                    move_box(&mut transform, facing, attack_hitbox, hitbox_meta);
+
                     commands
                         .entity(entity)
                         .insert(Sensor)
@@ -139,13 +184,76 @@ fn activate_hitbox(
                         .insert(Collider::cuboid(
                             hitbox_meta.size.x / 2.,
                             hitbox_meta.size.y / 2.,
-                        ));
+                        ))
+                        .insert(AppliedBox(hitbox_meta));
                 }
             }
         }
     }
 }
 
+/// Reshape the active attacks and the hurtboxes of the fighters to the boxes of the frames of their
+/// animations, and back once the frames without boxes come
+fn apply_frame_boxes(
+    mut attacks: Query<(Entity, &Attack, &Parent, &mut Transform, &AppliedBox), Without<Hurtbox>>,
+    mut hurtboxes: Query<
+        (Entity, &Parent, &mut Transform, Option<&AppliedBox>),
+        (With<Hurtbox>, Without<Attack>),
+    >,
+    parents: Query<(&Animation, Option<&Facing>, Option<&Handle<FighterMeta>>)>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut commands: Commands,
+) {
+    for (entity, attack, parent, mut transform, applied) in &mut attacks {
+        let (Some(attack_hitbox), Ok((animation, facing, _))) =
+            (attack.hitbox_meta, parents.get(parent.get()))
+        else {
+            continue;
+        };
+
+        let hitbox = animation.frame_hitbox().unwrap_or(attack_hitbox);
+        if **applied != hitbox {
+            move_box(&mut transform, facing, **applied, hitbox);
+            commands.entity(entity).insert((
+                Collider::cuboid(hitbox.size.x / 2., hitbox.size.y / 2.),
+                AppliedBox(hitbox),
+            ));
+        }
+    }
+
+    for (entity, parent, mut transform, applied) in &mut hurtboxes {
+        let Ok((animation, facing, Some(meta_handle))) = parents.get(parent.get()) else {
+            continue;
+        };
+        let Some(fighter) = fighter_assets.get(meta_handle) else {
+            continue;
+        };
+
+        let current = applied.map_or(fighter.hurtbox, |applied| **applied);
+        let hurtbox = animation.frame_hurtbox().unwrap_or(fighter.hurtbox);
+        if current != hurtbox {
+            move_box(&mut transform, facing, current, hurtbox);
+            commands.entity(entity).insert((
+                Collider::cuboid(hurtbox.size.x / 2., hurtbox.size.y / 2.),
+                AppliedBox(hurtbox),
+            ));
+        }
+    }
+}
+
+/// Move an attack or a hurtbox from where the `from` box is to where the `to` box is, mirrored for
+/// the facing of its fighter
+fn move_box(
+    transform: &mut Transform,
+    facing: Option<&Facing>,
+    from: ColliderMeta,
+    to: ColliderMeta,
+) {
+    let offset = to.offset - from.offset;
+    let offset = facing.map_or(offset, |facing| facing.mirror(offset));
+    transform.translation += offset.extend(0.0);
+}
+
 /// Deactivate collisions for entities with [`AttackFrames`]
 fn deactivate_hitbox(
     query: Query<(Entity, &AttackFrames, &Parent), (With<Attack>, With<Collider>)>,
@@ -161,36 +269,30 @@ fn deactivate_hitbox(
     }
 }
 
-// flash component
-// changes an entity's sprite to white for a specified amount of time
-fn damage_flash(
-    mut commands: Commands,
-    mut flash_query: Query<(&mut FlashingTimer, Entity, &mut TextureAtlasSprite)>,
-    time: Res<Time>,
-) {
-    for (mut timer, timer_e, mut timer_sprite) in flash_query.iter_mut() {
-        //Set the color to white
-        timer_sprite.color = Color::rgb(255.0, 255.0, 255.0);
-
-        //run the timer
-        timer.timer.tick(time.delta());
-
-        //Reset the color back to normal and remove the flash component
-        if timer.timer.finished() {
-            timer_sprite.color = Color::rgba(1.0, 1.0, 1.0, 1.0);
-            commands.entity(timer_e).remove::<FlashingTimer>();
-        }
-    }
-}
-
 /// Depletes the health of damageables that have collided with attacks
 fn attack_damage_system(
-    mut commands: Commands,
     mut events: EventReader<CollisionEvent>,
-    mut damageables: Query<(&mut Health, &Damageable)>,
-    attacks: Query<&Attack>,
+    mut damageables: Query<(
+        &Damageable,
+        Option<&ComboCounter>,
+        Option<&mut Blocking>,
+        Option<&Handle<FighterMeta>>,
+        Option<&KnockedDown>,
+        Option<&Invulnerable>,
+        Option<&Facing>,
+        &GlobalTransform,
+        Option<&Flying>,
+    )>,
+    attacks: Query<(&Attack, Option<&Parent>)>,
+    attackers: Query<(&Stats, &GlobalTransform)>,
+    in_air: Query<(), Or<(With<Airborne>, With<Thrown>, With<Flying>)>>,
     hurtboxes: Query<&Parent, With<Hurtbox>>,
-    mut event_writer: EventWriter<DamageEvent>,
+    teams: Teams,
+    mut rng: ResMut<GameRng>,
+    game: Option<Res<GameMeta>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut fighter_damage: FighterDamage,
+    mut blocked_events: EventWriter<Blocked>,
 ) {
     for event in events.iter() {
         if let CollisionEvent::Started(e1, e2, _flags) = event {
@@ -203,29 +305,138 @@ fn attack_damage_system(
                     continue;
                 };
 
-            let attack = attacks.get(attack_entity).unwrap();
+            let (attack, parent) = attacks.get(attack_entity).unwrap();
             if let Ok(hurtbox_parent) = hurtboxes.get(hurtbox_entity) {
                 let hurtbox_parent_entity = hurtbox_parent.get();
-                let (mut health, damageable) = damageables.get_mut(hurtbox_parent_entity).unwrap();
 
-                //apply damage to target
-                if **damageable {
-                    **health -= attack.damage;
+                // Fighters can't hit themselves, even when their attacks hit other players too,
+                // whether they attack with their own attacks, their weapons or their projectiles.
+                // The thrown fighters are on the team of their hitboxes' thrower instead, but they
+                // don't hit themselves either.
+                let attacker = teams.attacker(attack_entity);
+                if attacker == Some(hurtbox_parent_entity)
+                    || parent.map(Parent::get) == Some(hurtbox_parent_entity)
+                {
+                    continue;
+                }
+                // Nor the fighters on their own team, unless the players have friendly fire
+                if teams.is_friendly(attack_entity, hurtbox_parent_entity) {
+                    continue;
+                }
+                let (
+                    damageable,
+                    combo,
+                    blocking,
+                    meta_handle,
+                    knocked_down,
+                    invulnerable,
+                    facing,
+                    transform,
+                    flying,
+                ) = damageables.get_mut(hurtbox_parent_entity).unwrap();
+                let fighter = meta_handle.and_then(|handle| fighter_assets.get(handle));
 
-                    //Damage flash of 100ms upon an entity taking damage
-                    commands
-                        .entity(hurtbox_parent_entity)
-                        .insert(FlashingTimer {
-                            timer: Timer::new(Duration::from_millis(100), TimerMode::Repeating),
+                // Flying enemies are only reached from the air, or by anti-air attacks
+                if flying.is_some() {
+                    let attacker_in_air =
+                        attacker.map_or(false, |attacker| in_air.contains(attacker));
+                    if !reaches_flying(attack, attacker_in_air) {
+                        continue;
+                    }
+                }
+
+                // Fighters can't be hit while they get up after being knocked down
+                if invulnerable.is_some() {
+                    continue;
+                }
+
+                // The fighters that are down only take a part of the damage, without being stunned
+                let down_damage_scale = knocked_down.map(|_| {
+                    fighter
+                        .and_then(|fighter| fighter.knockdown.as_ref())
+                        .map_or(0.0, |knockdown| knockdown.down_damage_scale)
+                });
+                if down_damage_scale.map_or(false, |scale| scale <= 0.0) {
+                    continue;
+                }
+
+                // Hits at the start of a block are parried, and the later ones only chip away at
+                // the fighter's health
+                let block = blocking
+                    .filter(|_| **damageable)
+                    .and_then(|blocking| Some(blocking).zip(fighter));
+                let parried = block.as_ref().map_or(false, |(blocking, fighter)| {
+                    blocking.is_parrying(&fighter.block)
+                });
+
+                // The stats of the fighter that made the attack vary its damage, and can make it
+                // critical, more likely so for the hits on the fighters that are facing away. The
+                // hits that don't deal any damage aren't rolled for, so that they don't change the
+                // rolls of the later hits.
+                let (mut damage, critical) = match teams
+                    .attacker(attack_entity)
+                    .filter(|_| **damageable && !parried)
+                    .and_then(|attacker| attackers.get(attacker).ok())
+                {
+                    Some((stats, attacker_transform)) => {
+                        let from_behind = facing.map_or(false, |facing| {
+                            let offset =
+                                attacker_transform.translation().x - transform.translation().x;
+                            facing.sign() * offset < 0.0
                         });
+                        stats.roll_damage(attack.damage, from_behind, &mut *rng)
+                    }
+                    None => (attack.damage, false),
+                };
+
+                // The longer the combo on a player, the less its hits hurt and stun
+                let mut hitstun_duration = attack.hitstun_duration;
+                let combo_scaling = game.as_ref().and_then(|game| game.combo_scaling.as_ref());
+                if let (Some(combo), Some(combo_scaling)) = (combo, combo_scaling) {
+                    damage = (damage as f32 * combo.damage_scale(combo_scaling)).ceil() as i32;
+                    hitstun_duration *= combo.stun_scale(combo_scaling);
+                }
+
+                if let Some(down_damage_scale) = down_damage_scale {
+                    damage = (damage as f32 * down_damage_scale).round() as i32;
+                    hitstun_duration = 0.0;
+                }
 
-                    event_writer.send(DamageEvent {
+                //apply damage to target
+                if let Some((mut blocking, fighter)) = block {
+                    let chip_damage = if parried {
+                        0
+                    } else {
+                        fighter.block.damage_taken(damage)
+                    };
+                    let chip_damage =
+                        fighter_damage.chip(attack_entity, hurtbox_parent_entity, chip_damage);
+                    blocking.parried |= parried;
+
+                    blocked_events.send(Blocked {
+                        attacking_entity: attack_entity,
+                        blocking_entity: hurtbox_parent_entity,
+                        damage,
+                        chip_damage,
+                        parried,
+                    });
+                } else if **damageable {
+                    fighter_damage.deal(HitLanded {
                         damageing_entity: attack_entity,
                         damage_velocity: attack.pushback,
-                        damage: attack.damage,
+                        damage,
                         damaged_entity: hurtbox_parent_entity,
-                        hitstun_duration: attack.hitstun_duration,
-                    })
+                        hitstun_duration,
+                        critical,
+                    });
+                } else {
+                    blocked_events.send(Blocked {
+                        attacking_entity: attack_entity,
+                        blocking_entity: hurtbox_parent_entity,
+                        damage,
+                        chip_damage: 0,
+                        parried: false,
+                    });
                 }
             }
         }
@@ -241,14 +452,26 @@ fn breakable_system(
         Option<&Parent>,
         Option<&Explodable>,
     )>,
-    mut commands: Commands,
+    hurtboxes: Query<&Parent, With<Hurtbox>>,
+    teams: Teams,
+    mut pool: EntityPoolCommands,
     mut event_writer: EventWriter<BrokeEvent>,
 ) {
     for ev in events.iter() {
         if let CollisionEvent::Started(e1, e2, _flags) = ev {
-            for e in [e1, e2].iter() {
+            for (e, other) in [(e1, e2), (e2, e1)] {
+                // Attacks pass through the fighters that made them, and the fighters on their own
+                // team, without breaking
+                let friendly = hurtboxes.get(*other).map_or(false, |fighter| {
+                    teams.attacker(*e) == Some(fighter.get())
+                        || teams.is_friendly(*e, fighter.get())
+                });
+                if friendly {
+                    continue;
+                }
+
                 if let Ok((mut breakable, drop, transform, parent, explodable)) =
-                    despawn_query.get_mut(**e)
+                    despawn_query.get_mut(*e)
                 {
                     if breakable.hit_count < breakable.hit_tolerance {
                         breakable.hit_count += 1;
@@ -258,11 +481,11 @@ fn breakable_system(
                             transform: transform.cloned(),
                             explodable: explodable.cloned(),
                         });
-                        commands.entity(**e).despawn_recursive();
+                        pool.despawn(*e);
 
                         if breakable.despawn_parent {
                             if let Some(parent) = parent {
-                                commands.entity(parent.get()).despawn_recursive()
+                                pool.despawn(parent.get());
                             }
                         }
                     }
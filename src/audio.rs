@@ -1,6 +1,6 @@
 // Multiple sounds can be played by one channel, but splitting music/effects is cleaner.
 // Also for cleanness (named channels have evident function), we don't use the default channel.
-use rand::{prelude::SliceRandom, thread_rng};
+use rand::{prelude::SliceRandom, thread_rng, Rng};
 
 use bevy::{prelude::*, utils::HashMap};
 use bevy_egui::{egui::output::OutputEvent, EguiContext};
@@ -8,9 +8,15 @@ use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioSource};
 use iyes_loopless::prelude::*;
 
 use crate::{
-    animation::Animation,
+    animation::{Animation, AnimationFrameTag},
+    attack::Attack,
+    combat_events::{HitLanded, Killed},
     config::ENGINE_CONFIG,
-    metadata::{GameMeta, LevelHandle, LevelMeta},
+    fighter_state::{Dying, Moving},
+    metadata::{
+        FighterMeta, FighterSoundsMeta, GameMeta, LevelHandle, LevelMeta, SoundVariationsMeta,
+    },
+    utils::ResetEvent,
     GameState,
 };
 
@@ -23,12 +29,15 @@ pub struct MusicChannel;
 #[derive(Resource)]
 pub struct EffectsChannel;
 
+/// The volume of the music and of the sound effects
+pub const CHANNEL_VOLUME: f64 = 0.5;
+
 pub fn set_audio_channels_volume(
     music_channel: Res<AudioChannel<MusicChannel>>,
     effects_channel: Res<AudioChannel<EffectsChannel>>,
 ) {
-    music_channel.set_volume(0.5);
-    effects_channel.set_volume(0.5);
+    music_channel.set_volume(CHANNEL_VOLUME);
+    effects_channel.set_volume(CHANNEL_VOLUME);
 }
 
 pub struct AudioPlugin;
@@ -41,9 +50,16 @@ impl Plugin for AudioPlugin {
             .add_startup_system(set_audio_channels_volume)
             .add_enter_system(GameState::InGame, play_level_music)
             .add_exit_system(GameState::InGame, stop_level_music)
-            .add_system_to_stage(
+            .add_system(stop_effects_on_reset)
+            .add_system_set_to_stage(
                 CoreStage::PostUpdate,
-                animation_audio_playback.run_in_state(GameState::InGame),
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .with_system(animation_audio_playback)
+                    .with_system(fighter_sounds)
+                    .with_system(fighter_footsteps)
+                    .with_system(tagged_footsteps)
+                    .into(),
             );
     }
 }
@@ -96,6 +112,140 @@ pub fn animation_audio_playback(
     }
 }
 
+/// Picks one of the sounds out of the fighter's sounds
+type SoundSelector = fn(&FighterSoundsMeta) -> &SoundVariationsMeta;
+
+/// Plays the [`FighterSoundsMeta`] sounds in response to attacks, damage, and knock outs
+fn fighter_sounds(
+    mut damage_events: EventReader<HitLanded>,
+    new_attacks: Query<(Entity, &Parent), Added<Attack>>,
+    removed_attacks: RemovedComponents<Attack>,
+    mut killed_events: EventReader<Killed>,
+    fighters: Query<&Handle<FighterMeta>>,
+    dying: Query<(), With<Dying>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+    // The fighter that each active attack belongs to, and whether the attack has hit anything
+    mut active_attacks: Local<HashMap<Entity, (Entity, bool)>>,
+) {
+    let play = |handle: &Handle<FighterMeta>, sound: SoundSelector| {
+        if let Some(fighter) = fighter_assets.get(handle) {
+            if let Some(audio_handle) = sound(&fighter.sounds).choose() {
+                effects_channel.play(audio_handle);
+            }
+        }
+    };
+    let play_for = |entity: Entity, sound: SoundSelector| {
+        if let Ok(handle) = fighters.get(entity) {
+            play(handle, sound);
+        }
+    };
+
+    for (attack, parent) in &new_attacks {
+        if let Ok(handle) = fighters.get(parent.get()) {
+            active_attacks.insert(attack, (parent.get(), false));
+
+            let voice_chance = fighter_assets
+                .get(handle)
+                .map(|fighter| fighter.sounds.voice_chance)
+                .unwrap_or_default();
+            if thread_rng().gen_bool(voice_chance.clamp(0.0, 1.0) as f64) {
+                play(handle, |sounds| &sounds.voice);
+            }
+        }
+    }
+
+    // Fighters that are knocked out play the KO sound instead of the hurt sound
+    let knocked_out: Vec<Entity> = killed_events
+        .iter()
+        .map(|event| event.killed_entity)
+        .collect();
+
+    for event in damage_events.iter() {
+        if let Some((fighter, hit)) = active_attacks.get_mut(&event.damageing_entity) {
+            *hit = true;
+            if event.critical {
+                play_for(*fighter, FighterSoundsMeta::critical_hit);
+            } else {
+                play_for(*fighter, |sounds| &sounds.hit);
+            }
+        }
+
+        // Neither do the fighters that were knocked out before
+        if !knocked_out.contains(&event.damaged_entity) && !dying.contains(event.damaged_entity) {
+            play_for(event.damaged_entity, |sounds| &sounds.hurt);
+        }
+    }
+
+    for attack in removed_attacks.iter() {
+        if let Some((fighter, false)) = active_attacks.remove(&attack) {
+            play_for(fighter, |sounds| &sounds.whiff);
+        }
+    }
+
+    for entity in knocked_out {
+        play_for(entity, |sounds| &sounds.ko);
+    }
+}
+
+/// Plays the footstep sounds of walking fighters
+fn fighter_footsteps(
+    fighters: Query<(Entity, &Animation, &Handle<FighterMeta>), With<Moving>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+    mut last_frames: Local<HashMap<Entity, usize>>,
+) {
+    let mut frames = HashMap::default();
+
+    for (entity, animation, handle) in &fighters {
+        if animation.current_animation.as_deref() != Some(Moving::ANIMATION) {
+            continue;
+        }
+
+        let frame = animation.current_frame;
+        frames.insert(entity, frame);
+
+        // Only play the sound once, when the animation reaches the frame
+        if last_frames.get(&entity) == Some(&frame) {
+            continue;
+        }
+        if let Some(fighter) = fighter_assets.get(handle) {
+            if fighter.sounds.footstep_frames.contains(&frame) {
+                if let Some(audio_handle) = fighter.sounds.footsteps.choose() {
+                    effects_channel.play(audio_handle);
+                }
+            }
+        }
+    }
+
+    *last_frames = frames;
+}
+
+/// Plays the footstep sounds of the fighters on the animation frames tagged with `footstep`
+fn tagged_footsteps(
+    mut tag_events: EventReader<AnimationFrameTag>,
+    fighters: Query<&Handle<FighterMeta>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+) {
+    for event in tag_events.iter() {
+        if event.tag != "footstep" {
+            continue;
+        }
+        let Some(fighter) = fighters
+            .get(event.entity)
+            .ok()
+            .and_then(|handle| fighter_assets.get(handle))
+        else {
+            continue;
+        };
+
+        if let Some(audio_handle) = fighter.sounds.footsteps.choose() {
+            effects_channel.play(audio_handle);
+        }
+    }
+}
+
 /// Plays main menu sounds
 pub fn main_menu_sounds(
     game: Res<GameMeta>,
@@ -111,7 +261,8 @@ pub fn main_menu_sounds(
                     //Play down_play_button
                     effects_channel.play(game.main_menu.play_button_sound_handle.clone_weak());
                 } else {
-                    //Play one of the down button audios, except down_play_button
+                    //Play one of the down button audios, except down_play_button. This doesn't
+                    //affect the game, so it doesn't use the seeded `GameRng`.
                     effects_channel.play(
                         game.main_menu
                             .button_sound_handles
@@ -148,6 +299,16 @@ pub fn play_level_music(
     }
 }
 
+/// Stop the sound effects of the level when it's reset, since the fighters that made them are gone
+fn stop_effects_on_reset(
+    mut reset_events: EventReader<ResetEvent>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+) {
+    if reset_events.iter().any(|reset| reset.scope.resets_level()) {
+        effects_channel.stop();
+    }
+}
+
 pub fn stop_level_music(music_channel: Res<AudioChannel<MusicChannel>>) {
     music_channel.stop();
 }
@@ -0,0 +1,222 @@
+//! Scene benchmarks for measuring the performance of the game.
+//!
+//! Run a scene with `--benchmark <name>`, or all of them with `just bench`. Scenes play the first
+//! level headless, with a lot of extra entities spawned into it, and report how long the frames
+//! took. Every frame runs exactly one gameplay step, using the [`SteppedClockPlugin`], and the
+//! players can't be defeated, so every run does the same work.
+//!
+//! The hot systems are benchmarked on their own with criterion, in the `benches` directory, which
+//! `just bench` runs too.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::Instant};
+use bevy_mod_js_scripting::ActiveScripts;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    damage::Health,
+    effects::{spawn_effect, EffectsPlugin},
+    enemy::EnemyBundle,
+    headless::SteppedClockPlugin,
+    item::ItemBundle,
+    metadata::{FighterMeta, GameMeta, ItemMeta, LevelMeta},
+    player::Player,
+    pool::EntityPoolCommands,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// The names of the scene benchmarks
+pub const SCENES: &[&str] = &["brawl", "items", "particles"];
+
+/// The number of gameplay steps that are played before the frames of a scene are timed, so that
+/// the assets have been uploaded and the pools have been filled
+const SCENE_WARMUP_STEPS: usize = 60;
+
+/// The number of timed frames of a scene
+const SCENE_STEPS: usize = 1200;
+
+/// The number of bursts of every effect that the particle scene plays every gameplay step
+const PARTICLE_BURSTS_PER_STEP: usize = 10;
+
+/// Print the mean, median, 95th percentile, and maximum of the timings
+fn print_timings(name: &str, unit: &str, timings: &[Duration]) {
+    let mut sorted = timings.to_vec();
+    sorted.sort_unstable();
+
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mean = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+    let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+
+    println!(
+        "{name}: {} {unit}s, mean {:.3} ms, median {:.3} ms, p95 {:.3} ms, max {:.3} ms",
+        sorted.len(),
+        ms(mean),
+        ms(percentile(0.5)),
+        ms(percentile(0.95)),
+        ms(sorted[sorted.len() - 1]),
+    );
+}
+
+/// A scene benchmark
+#[derive(Clone, Copy, Debug)]
+pub enum Scene {
+    /// 100 enemies fighting the players at once
+    Brawl,
+    /// 500 items lying around the start of the level
+    Items,
+    /// Bursts of every effect of the game played all over the start of the level, many times
+    /// every step
+    Particles,
+}
+
+impl Scene {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "brawl" => Some(Self::Brawl),
+            "items" => Some(Self::Items),
+            "particles" => Some(Self::Particles),
+            _ => None,
+        }
+    }
+}
+
+/// Plays a [`Scene`] headless and prints how long its frames took. Has to be added before the
+/// [`HeadlessPlugin`](crate::headless::HeadlessPlugin).
+pub struct SceneBenchmarkPlugin {
+    pub scene: Scene,
+}
+
+impl Plugin for SceneBenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(SteppedClockPlugin)
+            .insert_resource(SceneBenchmark {
+                scene: self.scene,
+                steps: 0,
+                last_frame: None,
+                frame_times: Vec::with_capacity(SCENE_STEPS),
+            })
+            .add_enter_system(GameState::InGame, spawn_scene)
+            .add_system_to_stage(
+                CoreStage::First,
+                time_scene_frames.run_in_state(GameState::InGame),
+            );
+
+        // The effects aren't played headless, so the particle scene brings them in
+        if let Scene::Particles = self.scene {
+            app.add_plugin(EffectsPlugin).add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                play_particles.run_in_state(GameState::InGame),
+            );
+        }
+    }
+}
+
+#[derive(Resource)]
+struct SceneBenchmark {
+    scene: Scene,
+    /// The number of frames played so far
+    steps: usize,
+    last_frame: Option<Instant>,
+    frame_times: Vec<Duration>,
+}
+
+fn spawn_scene(
+    mut commands: Commands,
+    benchmark: Res<SceneBenchmark>,
+    level: Res<LevelMeta>,
+    mut items_assets: ResMut<Assets<ItemMeta>>,
+    mut active_scripts: ResMut<ActiveScripts>,
+    mut players: Query<&mut Health, With<Player>>,
+) {
+    // Keep the players alive, so that the level isn't restarted in the middle of the benchmark
+    for mut health in &mut players {
+        health.0 = i32::MAX / 2;
+    }
+
+    match benchmark.scene {
+        Scene::Brawl => {
+            let enemies = level
+                .enemies
+                .iter()
+                .filter(|enemy| !enemy.boss)
+                .cycle()
+                .take(100);
+            for (i, enemy) in enemies.enumerate() {
+                let mut enemy = enemy.clone();
+                // Spawn locations are relative to the ground
+                enemy.location =
+                    Vec3::new(150.0 + (i % 20) as f32 * 40.0, (i / 20) as f32 * 15.0, 0.0);
+                enemy.trip_point_x = f32::MIN;
+
+                commands.spawn(EnemyBundle::new(&enemy));
+            }
+        }
+        Scene::Items => {
+            let items = level.items.iter().cycle().take(500);
+            for (i, item) in items.enumerate() {
+                let mut item = item.clone();
+                item.location =
+                    Vec3::new((i % 50) as f32 * 24.0, -80.0 + (i / 50) as f32 * 8.0, 0.0);
+
+                let item_commands = commands.spawn(ItemBundle::new(&item));
+                ItemBundle::spawn(item_commands, &item, &mut items_assets, &mut active_scripts);
+            }
+        }
+        // The bursts are played every step instead
+        Scene::Particles => (),
+    }
+}
+
+/// Play bursts of every effect of the game, spread over the start of the level
+fn play_particles(
+    mut pool: EntityPoolCommands,
+    benchmark: Res<SceneBenchmark>,
+    game: Res<GameMeta>,
+) {
+    let Some(effects) = &game.effects else {
+        return;
+    };
+    let all_effects = [
+        &effects.hit,
+        &effects.heavy_hit,
+        &effects.critical_hit,
+        &effects.landing,
+        &effects.blood,
+    ];
+
+    for (i, effect) in all_effects.into_iter().flatten().enumerate() {
+        for burst in 0..PARTICLE_BURSTS_PER_STEP {
+            // Move the bursts around from step to step, without any randomness
+            let n = benchmark.steps * PARTICLE_BURSTS_PER_STEP + burst + i * 7;
+            let position = Vec2::new((n * 37 % 800) as f32, -80.0 + (n * 13 % 120) as f32);
+            spawn_effect(&mut pool, effect, position);
+        }
+    }
+}
+
+/// Time the frames, and print the results and exit once enough have been played
+fn time_scene_frames(
+    mut benchmark: ResMut<SceneBenchmark>,
+    fighters: Query<(), With<Handle<FighterMeta>>>,
+) {
+    let now = Instant::now();
+    let last_frame = benchmark.last_frame.replace(now);
+    benchmark.steps += 1;
+
+    if benchmark.steps <= SCENE_WARMUP_STEPS {
+        return;
+    }
+    if let Some(last_frame) = last_frame {
+        benchmark.frame_times.push(now - last_frame);
+    }
+
+    if benchmark.frame_times.len() >= SCENE_STEPS {
+        let name = format!("{:?}", benchmark.scene).to_lowercase();
+        print_timings(&name, "frame", &benchmark.frame_times);
+        println!("{name}: {} fighters at the end", fighters.iter().count());
+        std::process::exit(0);
+    }
+}
@@ -0,0 +1,339 @@
+//! Bug reports that players can attach to the issues they file.
+//!
+//! Pressing F5 saves a zip to the `captures` directory next to the game's storage file, with:
+//!
+//! - `screenshot.png`: the game view, without the HUD and the menus
+//! - `inputs.yaml`: the inputs of the players in the last [`INPUT_HISTORY_DURATION`] of gameplay,
+//!   as an [`InputScript`]. When the level was loaded no longer ago than that, the script plays the
+//!   level up to the report, and can be checked with `--determinism-check`.
+//! - `state.yaml`: the game state, the level, and the positions of the fighters and items
+//! - `settings.yaml` and `engine_config.txt`: the settings and the command line options
+//! - `log.txt`: the last lines of the log
+//!
+//! The files are collected right away, and saved with the screenshot once it has been rendered.
+
+use std::{collections::VecDeque, io::Write, path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::Serialize;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    capture::{capture_path, ScreenshotTarget, TakeScreenshot},
+    config::ENGINE_CONFIG,
+    damage::Health,
+    enemy::Enemy,
+    input::{default_runs, InputScript, InputSegment, PlayerAction},
+    item::Item,
+    logging::LogTail,
+    metadata::{ItemMeta, LevelHandle, Settings},
+    player::{Player, PlayerIndex},
+    rng::GameRng,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// The key that saves a bug report
+const BUG_REPORT_KEY: KeyCode = KeyCode::F5;
+
+/// How much of the latest gameplay the inputs are saved for
+const INPUT_HISTORY_DURATION: Duration = Duration::from_secs(30);
+
+pub struct BugReportPlugin;
+
+impl Plugin for BugReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputHistory>()
+            .add_enter_system(GameState::LoadingLevel, clear_input_history)
+            // The input scripts are applied in the `PRE` stage
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::TRANSITIONS,
+                record_inputs.run_in_state(GameState::InGame),
+            )
+            .add_system(capture_bug_report);
+    }
+}
+
+/// The files of a bug report, which are saved along with its screenshot
+#[derive(Clone)]
+pub struct BugReport {
+    path: PathBuf,
+    files: Vec<(&'static str, Vec<u8>)>,
+}
+
+impl BugReport {
+    /// Write the zip of the report, returning the path it was saved to
+    pub fn save(self, screenshot: Vec<u8>) -> anyhow::Result<PathBuf> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut zip = ZipWriter::new(std::fs::File::create(&self.path)?);
+        // The PNG is compressed already
+        zip.start_file(
+            "screenshot.png",
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        zip.write_all(&screenshot)?;
+        for (name, contents) in &self.files {
+            zip.start_file(*name, FileOptions::default())?;
+            zip.write_all(contents)?;
+        }
+        zip.finish()?;
+
+        Ok(self.path)
+    }
+}
+
+/// The inputs of the players in the latest gameplay steps
+#[derive(Resource, Default)]
+struct InputHistory {
+    /// The inputs of every player in each step, oldest first
+    steps: VecDeque<Vec<InputSegment>>,
+    /// Whether steps have been dropped since the level was loaded
+    truncated: bool,
+}
+
+impl InputHistory {
+    fn max_steps() -> usize {
+        (INPUT_HISTORY_DURATION.as_nanos() / GAMEPLAY_STEP.as_nanos()) as usize
+    }
+
+    fn push(&mut self, inputs: Vec<InputSegment>) {
+        if self.steps.len() >= Self::max_steps() {
+            self.steps.pop_front();
+            self.truncated = true;
+        }
+        self.steps.push_back(inputs);
+    }
+
+    /// The inputs of each player, with the steps that have the same inputs merged into segments
+    fn player_segments(&self) -> Vec<Vec<InputSegment>> {
+        let player_count = self.steps.iter().map(Vec::len).max().unwrap_or(0);
+
+        (0..player_count)
+            .map(|player| {
+                let mut segments = Vec::<InputSegment>::new();
+                for step in &self.steps {
+                    let input = step.get(player).cloned().unwrap_or_default();
+                    match segments.last_mut() {
+                        Some(last)
+                            if InputSegment {
+                                steps: last.steps,
+                                ..input.clone()
+                            } == *last =>
+                        {
+                            last.steps += 1;
+                        }
+                        _ => segments.push(InputSegment { steps: 1, ..input }),
+                    }
+                }
+                segments
+            })
+            .collect()
+    }
+}
+
+/// The state of the game when a bug report was saved
+#[derive(Serialize)]
+struct GameSnapshot {
+    version: &'static str,
+    os: &'static str,
+    state: String,
+    level: Option<String>,
+    seed: u64,
+    fighters: Vec<FighterSnapshot>,
+    items: Vec<ItemSnapshot>,
+}
+
+#[derive(Serialize)]
+struct FighterSnapshot {
+    name: String,
+    player: Option<usize>,
+    position: Vec3,
+    health: i32,
+}
+
+#[derive(Serialize)]
+struct ItemSnapshot {
+    item: Option<String>,
+    position: Vec3,
+}
+
+fn clear_input_history(mut history: ResMut<InputHistory>) {
+    *history = InputHistory::default();
+}
+
+fn record_inputs(
+    mut history: ResMut<InputHistory>,
+    players: Query<(&PlayerIndex, &ActionState<PlayerAction>)>,
+) {
+    let mut inputs = Vec::new();
+    for (index, action_state) in &players {
+        if inputs.len() <= index.0 {
+            inputs.resize(index.0 + 1, InputSegment::default());
+        }
+        inputs[index.0] = InputSegment {
+            steps: 1,
+            movement: action_state
+                .axis_pair(PlayerAction::Move)
+                .map_or(Vec2::ZERO, |axis| axis.xy()),
+            attack: action_state.pressed(PlayerAction::Attack),
+            throw: action_state.pressed(PlayerAction::Throw),
+            shoot: action_state.pressed(PlayerAction::Shoot),
+            block: action_state.pressed(PlayerAction::Block),
+            dodge: action_state.pressed(PlayerAction::Dodge),
+        };
+    }
+
+    history.push(inputs);
+}
+
+fn capture_bug_report(
+    input: Res<Input<KeyCode>>,
+    mut screenshots: EventWriter<TakeScreenshot>,
+    history: Res<InputHistory>,
+    state: Res<CurrentState<GameState>>,
+    level_handle: Option<Res<LevelHandle>>,
+    asset_server: Res<AssetServer>,
+    rng: Res<GameRng>,
+    settings: Option<Res<Settings>>,
+    log_tail: Option<Res<LogTail>>,
+    fighters: Query<
+        (Option<&Name>, Option<&PlayerIndex>, &Transform, &Health),
+        Or<(With<Player>, With<Enemy>)>,
+    >,
+    items: Query<(Option<&Handle<ItemMeta>>, &Transform), With<Item>>,
+) {
+    if !input.just_pressed(BUG_REPORT_KEY) {
+        return;
+    }
+
+    let level = level_handle
+        .and_then(|level_handle| asset_server.get_handle_path(&level_handle.0))
+        .map(|path| path.path().to_string_lossy().replace('\\', "/"));
+    let snapshot = GameSnapshot {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        state: format!("{:?}", state.0),
+        level: level.clone(),
+        seed: rng.seed(),
+        fighters: fighters
+            .iter()
+            .map(|(name, player, transform, health)| FighterSnapshot {
+                name: name.map_or_else(String::new, |name| name.to_string()),
+                player: player.map(|player| player.0),
+                position: transform.translation,
+                health: health.0,
+            })
+            .collect(),
+        items: items
+            .iter()
+            .map(|(meta_handle, transform)| ItemSnapshot {
+                item: meta_handle
+                    .and_then(|handle| asset_server.get_handle_path(handle))
+                    .map(|path| path.path().to_string_lossy().replace('\\', "/")),
+                position: transform.translation,
+            })
+            .collect(),
+    };
+
+    let mut files = Vec::new();
+    let mut add_yaml = |name: &'static str, value: Result<String, serde_yaml::Error>| match value {
+        Ok(yaml) => files.push((name, yaml.into_bytes())),
+        Err(e) => warn!("Could not add {name} to the bug report: {e}"),
+    };
+    add_yaml("state.yaml", serde_yaml::to_string(&snapshot));
+    if let Some(level) = level.filter(|_| !history.steps.is_empty()) {
+        let script = InputScript {
+            level,
+            level_handle: default(),
+            seed: rng.seed(),
+            steps: history.steps.len() as u32,
+            runs: default_runs(),
+            checksum: None,
+            players: history.player_segments(),
+        };
+        add_yaml(
+            "inputs.yaml",
+            serde_yaml::to_string(&script).map(|yaml| {
+                if history.truncated {
+                    format!(
+                        "# These are the inputs of the last {} seconds, which don't start at the \
+                         start of the level\n{yaml}",
+                        INPUT_HISTORY_DURATION.as_secs()
+                    )
+                } else {
+                    yaml
+                }
+            }),
+        );
+    }
+    if let Some(settings) = settings {
+        add_yaml("settings.yaml", serde_yaml::to_string(&*settings));
+    }
+    files.push((
+        "engine_config.txt",
+        format!("{:#?}\n", *ENGINE_CONFIG).into_bytes(),
+    ));
+    if let Some(log_tail) = log_tail {
+        files.push(("log.txt", log_tail.text().into_bytes()));
+    }
+
+    info!("Saving bug report");
+    screenshots.send(TakeScreenshot(ScreenshotTarget::BugReport(BugReport {
+        path: capture_path("bug-report", "zip"),
+        files,
+    })));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn input_history_merges_steps_into_segments() {
+        let input = |movement, attack| InputSegment {
+            steps: 1,
+            movement,
+            attack,
+            ..default()
+        };
+
+        let mut history = InputHistory::default();
+        history.push(vec![input(Vec2::X, false)]);
+        history.push(vec![input(Vec2::X, false), input(Vec2::Y, false)]);
+        history.push(vec![input(Vec2::ZERO, true), input(Vec2::Y, false)]);
+
+        let segments = history.player_segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            segments[0],
+            [
+                InputSegment {
+                    steps: 2,
+                    ..input(Vec2::X, false)
+                },
+                input(Vec2::ZERO, true),
+            ]
+        );
+        // Players without inputs in a step don't press anything
+        assert_eq!(
+            segments[1],
+            [
+                InputSegment {
+                    steps: 1,
+                    ..default()
+                },
+                InputSegment {
+                    steps: 2,
+                    ..input(Vec2::Y, false)
+                },
+            ]
+        );
+        assert!(!history.truncated);
+    }
+}
@@ -1,8 +1,11 @@
-use bevy::prelude::*;
-use bevy_parallax::ParallaxMoveEvent;
+use bevy::{prelude::*, render::camera::CameraUpdateSystem, transform::TransformSystem};
+use bevy_parallax::{ParallaxCameraComponent, ParallaxMoveEvent};
 use iyes_loopless::prelude::*;
 
-use crate::{consts, metadata::GameMeta, movement::VelocitySystems, GameState, Player};
+use crate::{
+    consts, metadata::GameMeta, timestep::InterpolateTransformsSystem, wave::Waves, GameState,
+    Player,
+};
 
 pub struct CameraPlugin;
 
@@ -11,41 +14,152 @@ impl Plugin for CameraPlugin {
         app
             // Register reflect types
             .register_type::<YSort>()
+            .init_resource::<CameraOverride>()
             // Add systems
             .add_system_set_to_stage(
                 CoreStage::PostUpdate,
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
-                    .after(VelocitySystems)
+                    .after(InterpolateTransformsSystem)
                     .with_system(camera_follow_player)
                     .with_system(y_sort)
                     .into(),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_camera_override
+                    .after(TransformSystem::TransformPropagate)
+                    .before(CameraUpdateSystem),
             );
     }
 }
 
+/// Changes to the view of the game camera for cinematics, like the knockout slow motion.
+///
+/// Only the rendered view is changed, so the gameplay and the camera following the players still
+/// see the camera where it is.
+#[derive(Resource, Debug)]
+pub struct CameraOverride {
+    /// How far the view is zoomed in, where `1.0` doesn't zoom
+    pub zoom: f32,
+    /// The point that the view is zoomed in on, instead of the center of the view
+    pub focus: Option<Vec2>,
+}
+
+impl Default for CameraOverride {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            focus: None,
+        }
+    }
+}
+
+impl CameraOverride {
+    fn is_active(&self) -> bool {
+        self.zoom != 1.0
+    }
+
+    /// Get how far the view is moved from the camera at `center` to keep the focus in place on the
+    /// screen as it zooms in
+    fn offset(&self, center: Vec2) -> Vec2 {
+        match self.focus {
+            Some(focus) => (focus - center) * (1.0 - 1.0 / self.zoom),
+            None => Vec2::ZERO,
+        }
+    }
+}
+
+fn apply_camera_override(
+    camera_override: Res<CameraOverride>,
+    mut cameras: Query<
+        (
+            &Transform,
+            &mut GlobalTransform,
+            &mut OrthographicProjection,
+        ),
+        With<ParallaxCameraComponent>,
+    >,
+    mut was_active: Local<bool>,
+) {
+    let active = camera_override.is_active();
+    if !active && !*was_active {
+        return;
+    }
+    *was_active = active;
+
+    for (transform, mut global_transform, mut projection) in &mut cameras {
+        let scale = 1.0 / camera_override.zoom;
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+
+        // The global transform is only propagated from the transform when the camera moves, so it
+        // always has to be set from the transform
+        let mut view = *transform;
+        view.translation += camera_override
+            .offset(transform.translation.truncate())
+            .extend(0.0);
+        *global_transform = GlobalTransform::from(view);
+    }
+}
+
+/// Get half of the width of the area of the level that the camera shows
+pub fn camera_half_width(game_meta: &GameMeta, window: &Window) -> f32 {
+    game_meta.camera_height as f32 * window.width() / window.height() / 2.0
+}
+
 /// Component to sort entities by their y position.
 /// Takes in a base value usually the sprite default Z with possibly an height offset.
-/// this value could be tweaked to implement virtual Z for jumping
+/// The fighters juggled in the air add their virtual Z to it, so that they are sorted by the ground
+/// under them and not by how high up they are drawn.
+///
+/// Static props that never move don't need this component. Their Z position can be set once when
+/// they are spawned with [`YSort::z`], so that they don't have to be checked every frame.
 #[derive(Component, Default, Reflect)]
 #[reflect(Component)]
 pub struct YSort(pub f32);
 
-/// Applies the y-sorting to the entities Z position.
-pub fn y_sort(mut query: Query<(&mut Transform, &YSort)>) {
-    for (mut transform, ysort) in query.iter_mut() {
-        transform.translation.z = ysort.0 - transform.translation.y;
+impl YSort {
+    /// Get the Z position of an entity at the given Y position
+    pub fn z(&self, y: f32) -> f32 {
+        self.0 - y
     }
 }
 
+/// The number of entities in each batch of the parallel y-sort system
+const YSORT_BATCH_SIZE: usize = 64;
+
+/// Applies the y-sorting to the entities Z position.
+///
+/// Only entities that have moved are sorted, and their transforms are only written to when their Z
+/// position actually changes, so entities standing still don't cost anything.
+pub fn y_sort(
+    mut query: Query<(&mut Transform, &YSort), Or<(Changed<Transform>, Changed<YSort>)>>,
+) {
+    query.par_for_each_mut(YSORT_BATCH_SIZE, |(mut transform, ysort)| {
+        let z = ysort.z(transform.translation.y);
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    });
+}
+
 /// Moves the camera according to the RIGHT_BOUNDARY_DISTANCE. Note that this does not enforce
 /// limitations of any kind - that's up to the players movement logic (e.g. max distance).
+///
+/// The camera is held in place while the players fight a [wave](crate::wave).
 pub fn camera_follow_player(
     player_query: Query<&Transform, With<Player>>,
-    camera_query: Query<&Transform, (With<Camera>, Without<Player>)>,
+    camera_query: Query<&Transform, (With<ParallaxCameraComponent>, Without<Player>)>,
     mut move_event_writer: EventWriter<ParallaxMoveEvent>,
     game_meta: Res<GameMeta>,
+    waves: Res<Waves>,
 ) {
+    if waves.camera_lock().is_some() {
+        return;
+    }
+
     let max_player_x = player_query
         .iter()
         .map(|transform| transform.translation.x)
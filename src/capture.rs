@@ -0,0 +1,548 @@
+//! Saving screenshots of the game.
+//!
+//! Pressing the Print Screen key saves a PNG of the game view, without the HUD and the menus, to
+//! the `captures` directory next to the game's storage file, and shows a message with the path it
+//! was saved to.
+//!
+//! The window surface can't be read back, so a copy of the camera that renders to the window is
+//! spawned for one frame, rendering to an image instead. After that frame has been rendered, the
+//! render world copies the image into a buffer, and the PNG is encoded and written on the IO task
+//! pool.
+//!
+//! Other plugins take screenshots with the [`TakeScreenshot`] event, like the
+//! [bug reports](crate::bug_report), which are saved with their screenshot in a zip.
+
+use std::{
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_channel::{Receiver, Sender};
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssets,
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
+            ImageCopyBuffer, ImageDataLayout, MapMode, TextureDescriptor, TextureDimension,
+            TextureFormat, TextureUsages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        view::RenderLayers,
+        Extract, RenderApp, RenderStage,
+    },
+    tasks::IoTaskPool,
+    window::WindowId,
+};
+use bevy_egui::{egui, EguiContext};
+use bevy_fluent::Localization;
+
+use crate::{bug_report::BugReport, localization::LocalizationExt};
+
+/// How long the message about a saved screenshot is shown
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// The format of the images that screenshots are rendered to, which is the same as the format of
+/// the post-processing image, so the same pipelines can render to both
+const CAPTURE_FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+
+/// The rows of textures copied into buffers have to be aligned to this many bytes
+const BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        let (result_sender, result_receiver) = async_channel::unbounded();
+
+        app.add_event::<TakeScreenshot>()
+            .insert_resource(CaptureResults(result_receiver))
+            .add_system(take_screenshot)
+            .add_system(show_capture_toast);
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(ScreenshotReadbacks {
+                    requests: Vec::new(),
+                    copies: Vec::new(),
+                    result_sender,
+                })
+                .add_system_to_stage(RenderStage::Extract, extract_screenshots)
+                .add_system_to_stage(RenderStage::Cleanup, read_back_screenshots);
+        }
+    }
+}
+
+/// Event to take a screenshot of the game view
+pub struct TakeScreenshot(pub ScreenshotTarget);
+
+/// Where a screenshot is saved once it has been rendered
+#[derive(Clone)]
+pub enum ScreenshotTarget {
+    /// A PNG file
+    File(PathBuf),
+    /// The zip of a bug report, with the other files of the report
+    BugReport(BugReport),
+}
+
+impl ScreenshotTarget {
+    fn kind(&self) -> CaptureKind {
+        match self {
+            ScreenshotTarget::File(_) => CaptureKind::Screenshot,
+            ScreenshotTarget::BugReport(_) => CaptureKind::BugReport,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CaptureKind {
+    Screenshot,
+    BugReport,
+}
+
+impl CaptureKind {
+    /// The messages for when a capture has been saved, and when it couldn't be saved
+    fn messages(self) -> (&'static str, &'static str) {
+        match self {
+            CaptureKind::Screenshot => ("screenshot-saved", "screenshot-failed"),
+            CaptureKind::BugReport => ("bug-report-saved", "bug-report-failed"),
+        }
+    }
+}
+
+/// A camera that renders a screenshot. It's despawned in the frame after it was spawned in.
+#[derive(Component, Clone)]
+struct ScreenshotCamera {
+    image: Handle<Image>,
+    size: UVec2,
+    target: ScreenshotTarget,
+}
+
+/// The result of saving a capture, with the path it was saved to
+struct CaptureResult {
+    kind: CaptureKind,
+    result: Result<PathBuf, String>,
+}
+
+impl CaptureResult {
+    fn failed(kind: CaptureKind, error: &str) -> Self {
+        Self {
+            kind,
+            result: Err(error.to_owned()),
+        }
+    }
+}
+
+/// The results of saving the captures
+#[derive(Resource)]
+struct CaptureResults(Receiver<CaptureResult>);
+
+fn take_screenshot(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut requests: EventReader<TakeScreenshot>,
+    windows: Res<Windows>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<(
+        &Camera,
+        &Camera2d,
+        &OrthographicProjection,
+        &Transform,
+        &GlobalTransform,
+        Option<&RenderLayers>,
+    )>,
+    screenshot_cameras: Query<(Entity, &ScreenshotCamera)>,
+) {
+    // The screenshots of the last frame have been rendered
+    for (entity, screenshot) in &screenshot_cameras {
+        commands.entity(entity).despawn();
+        images.remove(&screenshot.image);
+    }
+
+    let mut targets = requests
+        .iter()
+        .map(|TakeScreenshot(target)| target.clone())
+        .collect::<Vec<_>>();
+    if input.just_pressed(KeyCode::Snapshot) {
+        targets.push(ScreenshotTarget::File(capture_path("screenshot", "png")));
+    }
+    if targets.is_empty() {
+        return;
+    }
+
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    // The camera that renders to the window last shows what the player sees, with the
+    // post-processing effects if there are any
+    let Some((camera, camera_2d, projection, transform, global_transform, render_layers)) = cameras
+        .iter()
+        .filter(|(camera, ..)| camera.target == RenderTarget::Window(WindowId::primary()))
+        .max_by_key(|(camera, ..)| camera.priority)
+    else {
+        return;
+    };
+
+    let size = UVec2::new(window.physical_width(), window.physical_height()).max(UVec2::ONE);
+    let extent = Extent3d {
+        width: size.x,
+        height: size.y,
+        ..default()
+    };
+    for target in targets {
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("screenshot"),
+                size: extent,
+                dimension: TextureDimension::D2,
+                format: CAPTURE_FORMAT,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::RENDER_ATTACHMENT,
+            },
+            ..default()
+        };
+        image.resize(extent);
+        let image = images.add(image);
+
+        let mut entity = commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(image.clone()),
+                    ..camera.clone()
+                },
+                camera_2d: camera_2d.clone(),
+                projection: projection.clone(),
+                transform: *transform,
+                global_transform: *global_transform,
+                ..default()
+            },
+            ScreenshotCamera {
+                image,
+                size,
+                target,
+            },
+            Name::new("Screenshot Camera"),
+        ));
+        if let Some(render_layers) = render_layers {
+            entity.insert(*render_layers);
+        }
+    }
+}
+
+/// Get a path for a new capture in the captures directory, named after the kind of capture and the
+/// current time, like `screenshot-2023-11-14_22-13-20.png`
+pub fn capture_path(kind: &str, extension: &str) -> PathBuf {
+    let dir = directories::ProjectDirs::from("org", "FishFolk", "Punchy")
+        .map(|project_dirs| project_dirs.data_dir().join("captures"))
+        .unwrap_or_else(|| PathBuf::from("captures"));
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let name = format!("{kind}-{}", format_timestamp(secs));
+
+    let mut path = dir.join(format!("{name}.{extension}"));
+    let mut count = 2;
+    while path.exists() {
+        path = dir.join(format!("{name}-{count}.{extension}"));
+        count += 1;
+    }
+
+    path
+}
+
+/// Format a Unix timestamp as a UTC date and time that can be used in file names, like
+/// `2023-11-14_22-13-20`
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // Convert the days since the Unix epoch to a date, using the algorithm from
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Shows a message when a capture has been saved, or couldn't be saved
+fn show_capture_toast(
+    mut toast: Local<Option<(CaptureResult, Timer)>>,
+    results: Res<CaptureResults>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+) {
+    if let Ok(result) = results.0.try_recv() {
+        match &result.result {
+            Ok(path) => info!(?path, kind = ?result.kind, "Saved capture"),
+            Err(e) => error!(kind = ?result.kind, "Could not save capture: {e}"),
+        }
+        *toast = Some((result, Timer::new(TOAST_DURATION, TimerMode::Once)));
+    }
+
+    let finished = toast
+        .as_mut()
+        .map_or(true, |(_, timer)| timer.tick(time.delta()).finished());
+    if finished {
+        *toast = None;
+        return;
+    }
+    let Some((result, _)) = &*toast else {
+        return;
+    };
+
+    let (saved, failed) = result.kind.messages();
+    let text = match &result.result {
+        Ok(path) => format!("{}: {}", localization.get(saved), path.display()),
+        Err(e) => format!("{}: {e}", localization.get(failed)),
+    };
+    egui::Area::new("capture_toast")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 16.0))
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(text);
+            });
+        });
+}
+
+/// The screenshots in the render world
+#[derive(Resource)]
+struct ScreenshotReadbacks {
+    /// The screenshots that are rendered this frame
+    requests: Vec<ScreenshotCamera>,
+    /// The screenshots that are being copied into buffers
+    copies: Vec<ScreenshotCopy>,
+    result_sender: Sender<CaptureResult>,
+}
+
+/// A screenshot that is being copied into a buffer
+struct ScreenshotCopy {
+    buffer: Buffer,
+    /// Receives whether the buffer could be mapped, once the copy has finished
+    mapped: Receiver<bool>,
+    padded_bytes_per_row: u32,
+    size: UVec2,
+    target: ScreenshotTarget,
+}
+
+fn extract_screenshots(
+    mut readbacks: ResMut<ScreenshotReadbacks>,
+    cameras: Extract<Query<&ScreenshotCamera>>,
+) {
+    readbacks.requests.extend(cameras.iter().cloned());
+}
+
+/// Copies the rendered screenshots into buffers, and saves the screenshots that have been copied
+fn read_back_screenshots(
+    mut readbacks: ResMut<ScreenshotReadbacks>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let ScreenshotReadbacks {
+        requests,
+        copies,
+        result_sender,
+    } = &mut *readbacks;
+
+    for request in requests.drain(..) {
+        let Some(gpu_image) = images.get(&request.image) else {
+            result_sender
+                .try_send(CaptureResult::failed(
+                    request.target.kind(),
+                    "The screenshot wasn't rendered",
+                ))
+                .ok();
+            continue;
+        };
+
+        let padded_bytes_per_row = padded_bytes_per_row(request.size.x);
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("screenshot_readback"),
+            size: (padded_bytes_per_row * request.size.y) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("screenshot_copy"),
+        });
+        encoder.copy_texture_to_buffer(
+            gpu_image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: request.size.x,
+                height: request.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        render_queue.submit([encoder.finish()]);
+
+        // The callback is called once the copy has finished, when the device is polled as the
+        // next frames are submitted
+        let (mapped_sender, mapped) = async_channel::bounded(1);
+        render_device.map_buffer(&buffer.slice(..), MapMode::Read, move |result| {
+            mapped_sender.try_send(result.is_ok()).ok();
+        });
+
+        copies.push(ScreenshotCopy {
+            buffer,
+            mapped,
+            padded_bytes_per_row,
+            size: request.size,
+            target: request.target,
+        });
+    }
+
+    copies.retain(|copy| {
+        let Ok(mapped) = copy.mapped.try_recv() else {
+            return true;
+        };
+        if !mapped {
+            result_sender
+                .try_send(CaptureResult::failed(
+                    copy.target.kind(),
+                    "The screenshot couldn't be read back",
+                ))
+                .ok();
+            return false;
+        }
+
+        let pixels = bgra_rows_to_rgba(
+            &copy.buffer.slice(..).get_mapped_range(),
+            copy.padded_bytes_per_row,
+            copy.size,
+        );
+        copy.buffer.unmap();
+
+        let result_sender = result_sender.clone();
+        let target = copy.target.clone();
+        let size = copy.size;
+        IoTaskPool::get()
+            .spawn(async move {
+                let kind = target.kind();
+                let result = match target {
+                    ScreenshotTarget::File(path) => save_png(&path, &pixels, size).map(|()| path),
+                    ScreenshotTarget::BugReport(report) => {
+                        encode_png(&pixels, size).and_then(|png| report.save(png))
+                    }
+                };
+                let result = result.map_err(|e| e.to_string());
+                result_sender
+                    .send(CaptureResult { kind, result })
+                    .await
+                    .ok();
+            })
+            .detach();
+
+        false
+    });
+}
+
+/// The number of bytes in a row of a copied image, including the padding for the alignment
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let bytes_per_row = width * 4;
+    let alignment = BYTES_PER_ROW_ALIGNMENT;
+    (bytes_per_row + alignment - 1) / alignment * alignment
+}
+
+/// Remove the padding from the rows of a copied BGRA image, and convert it to opaque RGBA
+fn bgra_rows_to_rgba(data: &[u8], padded_bytes_per_row: u32, size: UVec2) -> Vec<u8> {
+    let bytes_per_row = size.x as usize * 4;
+    let mut pixels = Vec::with_capacity(bytes_per_row * size.y as usize);
+
+    for row in data
+        .chunks(padded_bytes_per_row as usize)
+        .take(size.y as usize)
+    {
+        for bgra in row[..bytes_per_row].chunks_exact(4) {
+            pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], 255]);
+        }
+    }
+
+    pixels
+}
+
+fn save_png(path: &Path, pixels: &[u8], size: UVec2) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    image::save_buffer(path, pixels, size.x, size.y, image::ColorType::Rgba8)?;
+
+    Ok(())
+}
+
+fn encode_png(pixels: &[u8], size: UVec2) -> anyhow::Result<Vec<u8>> {
+    use image::ImageEncoder;
+
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png).write_image(
+        pixels,
+        size.x,
+        size.y,
+        image::ColorType::Rgba8,
+    )?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_formatted_as_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01_00-00-00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29_00-00-00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14_22-13-20");
+    }
+
+    #[test]
+    fn copied_rows_are_unpadded() {
+        let padded_bytes_per_row = padded_bytes_per_row(2);
+        assert_eq!(padded_bytes_per_row, 256);
+
+        let mut data = vec![0; padded_bytes_per_row as usize * 2];
+        data[..8].copy_from_slice(&[1, 2, 3, 0, 4, 5, 6, 0]);
+        data[256..264].copy_from_slice(&[7, 8, 9, 0, 10, 11, 12, 0]);
+
+        assert_eq!(
+            bgra_rows_to_rgba(&data, padded_bytes_per_row, UVec2::new(2, 2)),
+            [3, 2, 1, 255, 6, 5, 4, 255, 9, 8, 7, 255, 12, 11, 10, 255]
+        );
+    }
+}
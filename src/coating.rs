@@ -0,0 +1,114 @@
+//! Coatings, like fire oil and shock batteries, that fighters can pick up to give the melee weapon
+//! they are holding an [`Element`] for a while.
+//!
+//! The hits of a coated weapon deal the extra damage of the coating and have the effect of its
+//! element on the fighters they hit: fire sets them burning with a
+//! [status effect](crate::status_effect), and shock stuns them for longer. Coated weapons are
+//! tinted with the color of their coating and leave a trail behind them.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    lifetime::Lifetime,
+    metadata::Element,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    tint::{Tint, TintSource},
+    GameState,
+};
+
+/// The time between the sprites of the trail of a coated weapon, in seconds
+const TRAIL_INTERVAL: f32 = 0.08;
+
+/// How long the sprites of the trail of a coated weapon take to fade away, in seconds
+const TRAIL_LIFETIME: f32 = 0.3;
+
+/// The size of the sprites of the trail of a coated weapon
+const TRAIL_SIZE: f32 = 4.0;
+
+pub struct CoatingPlugin;
+
+impl Plugin for CoatingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system_set(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                .with_system(update_coatings)
+                .with_system(fade_trails)
+                .into(),
+        );
+    }
+}
+
+/// Component for a melee weapon that has been coated with an element
+#[derive(Component)]
+pub struct Coated {
+    pub element: Element,
+    /// The damage added to the hits of the weapon
+    pub bonus_damage: i32,
+    pub color: Color,
+    /// Counts down to the coating wearing off
+    timer: Timer,
+    trail_timer: Timer,
+}
+
+impl Coated {
+    pub fn new(element: Element, duration: f32, bonus_damage: i32, tint: [u8; 3]) -> Self {
+        Self {
+            element,
+            bonus_damage,
+            color: Color::rgb_u8(tint[0], tint[1], tint[2]),
+            timer: Timer::from_seconds(duration.max(0.0), TimerMode::Once),
+            trail_timer: Timer::from_seconds(TRAIL_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Component for the attacks of a [`Coated`] weapon
+#[derive(Component, Deref)]
+pub struct CoatedAttack(pub Element);
+
+/// Marker for the sprites of the trail of a coated weapon
+#[derive(Component)]
+struct Trail;
+
+/// Tint the coated weapons, leave their trails behind them, and remove coatings that have worn off
+fn update_coatings(
+    mut commands: Commands,
+    mut weapons: Query<(Entity, &mut Coated, &mut Tint, &GlobalTransform)>,
+) {
+    for (entity, mut coated, mut tint, transform) in &mut weapons {
+        if coated.timer.tick(GAMEPLAY_STEP).finished() {
+            tint.clear(TintSource::Coated);
+            commands.entity(entity).remove::<Coated>();
+            continue;
+        }
+        tint.set(TintSource::Coated, coated.color);
+
+        if coated.trail_timer.tick(GAMEPLAY_STEP).just_finished() {
+            let translation = transform.translation() - Vec3::Z * 0.1;
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: coated.color,
+                        custom_size: Some(Vec2::splat(TRAIL_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                },
+                Lifetime(Timer::from_seconds(TRAIL_LIFETIME, TimerMode::Once)),
+                Trail,
+                Name::new("Coating Trail"),
+            ));
+        }
+    }
+}
+
+fn fade_trails(mut trails: Query<(&mut Sprite, &Lifetime), With<Trail>>) {
+    for (mut sprite, lifetime) in &mut trails {
+        sprite.color.set_a(lifetime.percent_left());
+    }
+}
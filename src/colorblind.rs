@@ -0,0 +1,186 @@
+//! Applying the [`ColorSettings`], which make the fighters and the HUD easier to tell apart.
+//!
+//! The [`ColorSettings`] resource is kept up to date with the saved settings. Fighter outlines are
+//! child sprites that are drawn behind the fighters, using an outline of the fighter's texture
+//! atlas that is created the first time it's needed. The flashes and the HUD palette are applied
+//! where they are drawn.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    enemy::Enemy,
+    metadata::{ColorSettings, Settings},
+    palette,
+    player::Player,
+};
+
+/// How far behind the fighter its outline is drawn
+const OUTLINE_Z_OFFSET: f32 = -0.001;
+
+pub struct ColorblindPlugin;
+
+impl Plugin for ColorblindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColorSettings>()
+            .init_resource::<OutlineAtlases>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_color_settings.run_if_resource_exists::<Settings>(),
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, remove_outlines)
+            .add_system_to_stage(CoreStage::PostUpdate, add_outlines.after(remove_outlines))
+            .add_system_to_stage(CoreStage::PostUpdate, sync_outlines.after(add_outlines));
+    }
+}
+
+/// An outline sprite, drawn behind its parent fighter
+#[derive(Component)]
+struct Outline {
+    /// The texture atlas of the fighter that the outline was made from
+    source: Handle<TextureAtlas>,
+}
+
+/// A fighter that has been given an outline
+#[derive(Component)]
+struct Outlined;
+
+/// The outlines of the texture atlases that have been outlined so far
+#[derive(Resource, Default)]
+struct OutlineAtlases(HashMap<Handle<TextureAtlas>, Handle<TextureAtlas>>);
+
+/// Update the color settings when the settings have changed
+fn apply_color_settings(settings: Res<Settings>, mut color_settings: ResMut<ColorSettings>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let colors = settings.colors;
+
+    if *color_settings != colors {
+        debug!(?colors, "Setting color settings");
+        *color_settings = colors;
+    }
+}
+
+/// Remove the outlines that are out of date, so that they are added again
+fn remove_outlines(
+    mut commands: Commands,
+    color_settings: Res<ColorSettings>,
+    outlines: Query<(Entity, &Outline, &Parent)>,
+    fighters: Query<&Handle<TextureAtlas>>,
+    outlined: Query<(Entity, Option<&Children>), With<Outlined>>,
+) {
+    for (entity, outline, parent) in &outlines {
+        let atlas_changed = fighters
+            .get(parent.get())
+            .map_or(true, |atlas| *atlas != outline.source);
+
+        if color_settings.is_changed() || atlas_changed {
+            commands.entity(entity).despawn_recursive();
+            if let Some(mut parent) = commands.get_entity(parent.get()) {
+                parent.remove::<Outlined>();
+            }
+        }
+    }
+
+    // Pooled fighters lose their children when they are parked
+    for (entity, children) in &outlined {
+        let has_outline = children.map_or(false, |children| {
+            children.iter().any(|child| outlines.contains(*child))
+        });
+        if !has_outline {
+            commands.entity(entity).remove::<Outlined>();
+        }
+    }
+}
+
+/// Give the fighters the outline of their kind, if it is enabled
+fn add_outlines(
+    mut commands: Commands,
+    color_settings: Res<ColorSettings>,
+    mut outline_atlases: ResMut<OutlineAtlases>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    mut images: ResMut<Assets<Image>>,
+    fighters: Query<
+        (
+            Entity,
+            &Handle<TextureAtlas>,
+            &TextureAtlasSprite,
+            Option<&Player>,
+        ),
+        (Or<(With<Player>, With<Enemy>)>, Without<Outlined>),
+    >,
+) {
+    for (entity, atlas_handle, fighter_sprite, player) in &fighters {
+        let outline_color = if player.is_some() {
+            color_settings.player_outline
+        } else {
+            color_settings.enemy_outline
+        };
+        let Some(color) = outline_color.color() else {
+            continue;
+        };
+
+        let outline_atlas = match outline_atlases.0.get(atlas_handle) {
+            Some(outline_atlas) => outline_atlas.clone(),
+            None => {
+                // The atlas is outlined once its image has loaded
+                let Some(atlas) = atlases.get(atlas_handle) else {
+                    continue;
+                };
+                let Some(image) = images.get(&atlas.texture) else {
+                    continue;
+                };
+                let outline = palette::outline_image(image, &atlas.textures);
+
+                let outline_atlas = TextureAtlas {
+                    texture: images.add(outline),
+                    size: atlas.size,
+                    textures: atlas.textures.clone(),
+                    texture_handles: None,
+                };
+                let outline_atlas = atlases.add(outline_atlas);
+                outline_atlases
+                    .0
+                    .insert(atlas_handle.clone(), outline_atlas.clone());
+                outline_atlas
+            }
+        };
+
+        let outline = commands
+            .spawn((
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        color,
+                        anchor: fighter_sprite.anchor.clone(),
+                        ..default()
+                    },
+                    texture_atlas: outline_atlas,
+                    transform: Transform::from_xyz(0.0, 0.0, OUTLINE_Z_OFFSET),
+                    ..default()
+                },
+                Outline {
+                    source: atlas_handle.clone(),
+                },
+                Name::new("Outline"),
+            ))
+            .id();
+        commands.entity(entity).add_child(outline).insert(Outlined);
+    }
+}
+
+/// Keep the outlines on the same frame as their fighters
+fn sync_outlines(
+    mut outlines: Query<(&mut TextureAtlasSprite, &Parent), With<Outline>>,
+    fighters: Query<&TextureAtlasSprite, (With<Outlined>, Without<Outline>)>,
+) {
+    for (mut sprite, parent) in &mut outlines {
+        let Ok(fighter_sprite) = fighters.get(parent.get()) else {
+            continue;
+        };
+        if sprite.index != fighter_sprite.index || sprite.flip_x != fighter_sprite.flip_x {
+            sprite.index = fighter_sprite.index;
+            sprite.flip_x = fighter_sprite.flip_x;
+        }
+    }
+}
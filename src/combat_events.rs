@@ -0,0 +1,69 @@
+//! Events for what happens in the fights, so that the systems reacting to them, like the sounds,
+//! the effects, and the versus stats, don't have to work it out from the fighters themselves.
+//!
+//! The events are sent by the gameplay systems that decide them: the attacks and the status effects
+//! that hurt over time send [`HitLanded`], [`Blocked`], and [`Killed`] in
+//! [`gameplay_stage::COLLISIONS`], the slams and the thrown fighters send theirs through
+//! [`FighterDamage`], and the combo breaker sends [`ComboEnded`]. A new reaction to the fights only
+//! has to read the events, without changing the systems that send them.
+//!
+//! [`gameplay_stage::COLLISIONS`]: crate::timestep::gameplay_stage::COLLISIONS
+//! [`FighterDamage`]: crate::damage::FighterDamage
+
+use bevy::prelude::*;
+
+pub struct CombatEventsPlugin;
+
+impl Plugin for CombatEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HitLanded>()
+            .add_event::<Blocked>()
+            .add_event::<Killed>()
+            .add_event::<ComboEnded>();
+    }
+}
+
+/// Event emitted when an entity is damaged
+pub struct HitLanded {
+    pub damage_velocity: Vec2,
+    /// The attack that hit, the fighter that slammed the fighter into the ground or that a thrown
+    /// fighter was thrown into, or the fighter itself for the damage of a status effect, like the
+    /// fire it's burning in
+    pub damageing_entity: Entity,
+    pub damaged_entity: Entity,
+    pub damage: i32,
+    pub hitstun_duration: f32,
+    /// Whether the hit was critical, which multiplied its damage
+    pub critical: bool,
+}
+
+/// Event emitted when an attack hits a fighter that is blocking, so that it isn't damaged, or only
+/// by part of the damage
+pub struct Blocked {
+    pub attacking_entity: Entity,
+    pub blocking_entity: Entity,
+    /// The damage that the attack would have dealt
+    pub damage: i32,
+    /// The part of the damage that the blocking fighter took anyway, which the blocks that aren't
+    /// parried chip away at its health
+    pub chip_damage: i32,
+    /// Whether the hit was parried, which staggers the attacker
+    pub parried: bool,
+}
+
+/// Event emitted when a fighter is knocked out by running out of health
+pub struct Killed {
+    /// The attack that dealt the last hit, the fighter that a thrown fighter was thrown into, or
+    /// the fighter itself for the fire it's burning in
+    pub killing_entity: Entity,
+    pub killed_entity: Entity,
+}
+
+/// Event emitted when the combo on a player ends
+pub struct ComboEnded {
+    pub player: Entity,
+    /// The hits of the combo
+    pub hits: u32,
+    /// Whether the player burst out of the combo with the combo breaker, instead of recovering
+    pub broken: bool,
+}
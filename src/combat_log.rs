@@ -0,0 +1,115 @@
+//! Logs of the [combat events](crate::combat_events) of the fighters, for diagnosing gameplay bugs
+//! from the logs of player machines.
+//!
+//! The events are logged at the debug level with the [`GameplayStep`] they happened in, so they are
+//! only shown with `--log-module punchy::combat_log=debug`. Two runs of a level with the same
+//! inputs and seed have the same steps, so comparing their combat logs shows where a desync started.
+
+use bevy::{
+    prelude::*,
+    utils::tracing::{enabled, Level},
+};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    combat_events::{Blocked, ComboEnded, HitLanded, Killed},
+    timestep::{gameplay_stage, GameplayStep, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct CombatLogPlugin;
+
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system_set(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                .run_if(combat_log_enabled)
+                .with_system(log_hits)
+                .with_system(log_blocks)
+                .with_system(log_knockouts)
+                .with_system(log_combo_ends)
+                .into(),
+        );
+    }
+}
+
+fn combat_log_enabled() -> bool {
+    enabled!(Level::DEBUG)
+}
+
+fn log_hits(
+    step: Res<GameplayStep>,
+    mut damage_events: EventReader<HitLanded>,
+    names: Query<&Name>,
+) {
+    for event in damage_events.iter() {
+        debug!(
+            step = **step,
+            attacker = %entity_name(&names, event.damageing_entity),
+            target = %entity_name(&names, event.damaged_entity),
+            damage = event.damage,
+            hitstun = event.hitstun_duration,
+            "Hit"
+        );
+    }
+}
+
+fn log_blocks(
+    step: Res<GameplayStep>,
+    mut blocked_events: EventReader<Blocked>,
+    names: Query<&Name>,
+) {
+    for event in blocked_events.iter() {
+        debug!(
+            step = **step,
+            attacker = %entity_name(&names, event.attacking_entity),
+            target = %entity_name(&names, event.blocking_entity),
+            damage = event.damage,
+            chip_damage = event.chip_damage,
+            parried = event.parried,
+            "Blocked"
+        );
+    }
+}
+
+fn log_knockouts(
+    step: Res<GameplayStep>,
+    mut killed_events: EventReader<Killed>,
+    names: Query<&Name>,
+) {
+    for event in killed_events.iter() {
+        debug!(
+            step = **step,
+            attacker = %entity_name(&names, event.killing_entity),
+            fighter = %entity_name(&names, event.killed_entity),
+            "Knocked out"
+        );
+    }
+}
+
+fn log_combo_ends(
+    step: Res<GameplayStep>,
+    mut combo_ended_events: EventReader<ComboEnded>,
+    names: Query<&Name>,
+) {
+    for event in combo_ended_events.iter() {
+        debug!(
+            step = **step,
+            player = %entity_name(&names, event.player),
+            hits = event.hits,
+            broken = event.broken,
+            "Combo ended"
+        );
+    }
+}
+
+/// The name of an entity with its ID, like `Fishy (3v0)`, since many entities have the same name
+fn entity_name(names: &Query<&Name>, entity: Entity) -> String {
+    match names.get(entity) {
+        Ok(name) => format!("{name} ({entity:?})"),
+        Err(_) => format!("{entity:?}"),
+    }
+}
@@ -0,0 +1,303 @@
+//! Escape valves for players stuck in long combos, from enemies stunlocking them or from the other
+//! players in versus.
+//!
+//! The hits of a combo on a player are [scaled](ComboScalingMeta) down the longer the combo goes,
+//! so that they hurt less and run out of stun sooner. A combo lasts until the player has recovered
+//! from its stun for a moment. A stunned player can also press the special button to burst out of
+//! the combo with the [combo breaker](ComboBreakerMeta), using up part of their team-up meter to
+//! push the fighters around them away. Players held by an enemy's grab count as stunned.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::ActionState};
+
+use crate::{
+    afterimage::start_afterimages,
+    attack::{Attack, AttackDamageSystem},
+    combat_events::{ComboEnded, HitLanded},
+    fighter_state::{
+        ComboBreaking, FighterStateCollectSystems, Grappled, HitStun, StateTransition,
+        StateTransitionIntents,
+    },
+    input::PlayerAction,
+    lifetime::Lifetime,
+    menu_stack::MenuLayer,
+    metadata::{ComboBreakerMeta, ComboScalingMeta, GameMeta},
+    player::Player,
+    team::Team,
+    team_up::TeamUpMeter,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// How long a player has to be out of stun for the combo on them to end, in seconds
+const COMBO_RECOVERY_TIME: f32 = 0.3;
+
+pub struct ComboBreakerPlugin;
+
+impl Plugin for ComboBreakerPlugin {
+    fn build(&self, app: &mut App) {
+        // Special presses are collected every frame like the other player actions, so that none
+        // are missed
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            collect_break_presses
+                .run_in_state(GameState::InGame)
+                .run_in_state(MenuLayer::None)
+                .after(InputManagerSystem::Update),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::TRANSITIONS,
+            start_combo_breakers
+                .run_in_state(GameState::InGame)
+                .label(FighterStateCollectSystems),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::COLLISIONS,
+            count_combo_hits
+                .run_in_state(GameState::InGame)
+                .after(AttackDamageSystem),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            recover_from_combos.run_in_state(GameState::InGame),
+        );
+    }
+}
+
+/// The combo that a player is taking
+#[derive(Component, Default)]
+pub struct ComboCounter {
+    /// The hits of the combo so far
+    pub hits: u32,
+    /// How long the player has been out of stun, in seconds
+    recovered_for: f32,
+    /// Whether the player has pressed the special button while stunned
+    break_pressed: bool,
+}
+
+impl ComboCounter {
+    /// The factor that the damage of the next hit of the combo is scaled by
+    pub fn damage_scale(&self, meta: &ComboScalingMeta) -> f32 {
+        combo_scale(self.hits, meta.damage_scale_per_hit, meta.min_damage_scale)
+    }
+
+    /// The factor that the hitstun of the next hit of the combo is scaled by
+    pub fn stun_scale(&self, meta: &ComboScalingMeta) -> f32 {
+        combo_scale(self.hits, meta.stun_scale_per_hit, meta.min_stun_scale)
+    }
+
+    fn hit(&mut self) {
+        self.hits = self.hits.saturating_add(1);
+        self.recovered_for = 0.0;
+    }
+
+    /// Follow the recovery of the player, ending the combo once they have been out of stun for
+    /// long enough. Returns the hits of the combo when it ends.
+    fn update(&mut self, stunned: bool, delta: f32) -> Option<u32> {
+        if stunned {
+            self.recovered_for = 0.0;
+            return None;
+        }
+
+        self.recovered_for += delta;
+        if self.recovered_for >= COMBO_RECOVERY_TIME && self.hits != 0 {
+            return Some(std::mem::take(&mut self.hits));
+        }
+        None
+    }
+}
+
+fn combo_scale(hits: u32, per_hit: f32, min: f32) -> f32 {
+    (1.0 - per_hit * hits as f32).clamp(min.min(1.0), 1.0)
+}
+
+fn collect_break_presses(
+    mut players: Query<
+        (&ActionState<PlayerAction>, &mut ComboCounter),
+        Or<(With<HitStun>, With<Grappled>)>,
+    >,
+    game: Res<GameMeta>,
+) {
+    if game.combo_breaker.is_none() {
+        return;
+    }
+
+    for (action_state, mut counter) in &mut players {
+        if action_state.just_pressed(PlayerAction::Special) {
+            counter.break_pressed = true;
+        }
+    }
+}
+
+/// Count the hits that stun the players, which the fire of a coating doesn't
+fn count_combo_hits(
+    mut damage_events: EventReader<HitLanded>,
+    mut players: Query<&mut ComboCounter, With<Player>>,
+) {
+    for event in damage_events.iter() {
+        if event.hitstun_duration == 0.0 {
+            continue;
+        }
+        if let Ok(mut counter) = players.get_mut(event.damaged_entity) {
+            counter.hit();
+        }
+    }
+}
+
+fn recover_from_combos(
+    mut players: Query<
+        (
+            Entity,
+            &mut ComboCounter,
+            Option<&HitStun>,
+            Option<&Grappled>,
+        ),
+        With<Player>,
+    >,
+    mut combo_ended_events: EventWriter<ComboEnded>,
+) {
+    for (entity, mut counter, hitstun, grappled) in &mut players {
+        // Only the counters of players that have been hit are changed
+        if counter.hits != 0 {
+            let stunned = hitstun.is_some() || grappled.is_some();
+            if let Some(hits) = counter.update(stunned, GAMEPLAY_STEP.as_secs_f32()) {
+                combo_ended_events.send(ComboEnded {
+                    player: entity,
+                    hits,
+                    broken: false,
+                });
+            }
+        }
+    }
+}
+
+/// Burst the players that have pressed the special button while stunned out of their combos, if
+/// they have enough meter
+fn start_combo_breakers(
+    mut commands: Commands,
+    mut players: Query<
+        (
+            Entity,
+            &mut ComboCounter,
+            &mut TeamUpMeter,
+            &mut StateTransitionIntents,
+            Option<&HitStun>,
+            Option<&Grappled>,
+            &Team,
+        ),
+        With<Player>,
+    >,
+    game: Res<GameMeta>,
+    mut combo_ended_events: EventWriter<ComboEnded>,
+) {
+    let Some(meta) = &game.combo_breaker else {
+        return;
+    };
+
+    for (entity, mut counter, mut meter, mut transition_intents, hitstun, grappled, team) in
+        &mut players
+    {
+        if !counter.break_pressed {
+            continue;
+        }
+        counter.break_pressed = false;
+
+        // The player may have recovered since pressing the button
+        let stunned = hitstun.is_some() || grappled.is_some();
+        if !stunned || !meter.spend(meta.cost) {
+            continue;
+        }
+        combo_ended_events.send(ComboEnded {
+            player: entity,
+            hits: std::mem::take(&mut counter.hits),
+            broken: true,
+        });
+
+        transition_intents.push_back(StateTransition::new(
+            ComboBreaking::new(&meta.animation, meta.duration),
+            ComboBreaking::PRIORITY,
+            false,
+        ));
+        start_afterimages(
+            &mut commands,
+            entity,
+            meta.afterimages.as_ref(),
+            &meta.animation,
+        );
+
+        // Push the fighters on each side of the player away from them
+        let attacks =
+            [-1.0, 1.0].map(|direction| spawn_burst(&mut commands, meta, team, direction));
+        commands.entity(entity).push_children(&attacks);
+    }
+}
+
+/// Spawn the half of the burst on the side of the player in `direction`
+fn spawn_burst(
+    commands: &mut Commands,
+    meta: &ComboBreakerMeta,
+    team: &Team,
+    direction: f32,
+) -> Entity {
+    let half_size = meta.size / Vec2::new(4.0, 2.0);
+
+    commands
+        .spawn((
+            TransformBundle::from_transform(Transform::from_xyz(half_size.x * direction, 0.0, 0.0)),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+            team.attack_groups(),
+            Collider::cuboid(half_size.x, half_size.y),
+            Attack {
+                damage: meta.damage,
+                pushback: Vec2::new(meta.pushback * direction, 0.0),
+                hitstun_duration: meta.hitstun_duration,
+                hitbox_meta: None,
+                status_effect: None,
+                launch: 0.0,
+                anti_air: false,
+            },
+            Lifetime(Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once)),
+            Name::new("Combo Breaker"),
+        ))
+        .id()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn combos_scale_down_until_the_player_recovers() {
+        let meta = ComboScalingMeta {
+            damage_scale_per_hit: 0.25,
+            min_damage_scale: 0.5,
+            stun_scale_per_hit: 0.1,
+            min_stun_scale: 0.0,
+        };
+        let mut counter = ComboCounter::default();
+        assert_eq!(counter.damage_scale(&meta), 1.0);
+
+        counter.hit();
+        assert_eq!(counter.damage_scale(&meta), 0.75);
+        counter.hit();
+        counter.hit();
+        assert_eq!(counter.damage_scale(&meta), 0.5);
+        assert!((counter.stun_scale(&meta) - 0.7).abs() < 1e-4);
+
+        // Being out of stun for a moment between the hits doesn't end the combo
+        counter.update(false, COMBO_RECOVERY_TIME / 2.0);
+        counter.update(true, 0.1);
+        counter.update(false, COMBO_RECOVERY_TIME / 2.0);
+        assert_eq!(counter.hits, 3);
+
+        assert_eq!(counter.update(false, COMBO_RECOVERY_TIME), Some(3));
+        assert_eq!(counter.hits, 0);
+    }
+}
@@ -3,9 +3,25 @@ use structopt::StructOpt;
 
 const DEFAULT_LOG_LEVEL: &str = "info,wgpu=error,bevy_fluent=warn,symphonia_core=warn,symphonia_format_ogg=warn,symphonia_bundle_mp3=warn";
 
+/// The asset directory that Bevy uses when the `asset_dir` isn't specified
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_ASSET_DIR: &str = "assets";
+
+/// The environment variable that cheats can be enabled with, as a comma-separated list
+#[cfg(not(target_arch = "wasm32"))]
+const CHEATS_ENV_VAR: &str = "PUNCHY_CHEATS";
+
 pub static ENGINE_CONFIG: Lazy<EngineConfig> = Lazy::new(|| {
     #[cfg(not(target_arch = "wasm32"))]
-    return EngineConfig::from_args();
+    return {
+        let mut config = EngineConfig::from_args();
+        if let Ok(cheats) = std::env::var(CHEATS_ENV_VAR) {
+            if let Err(e) = config.cheats.enable_from_list(&cheats) {
+                eprintln!("warning: Could not parse {CHEATS_ENV_VAR}: {e}");
+            }
+        }
+        config
+    };
 
     #[cfg(target_arch = "wasm32")]
     return EngineConfig::from_web_params();
@@ -22,6 +38,13 @@ pub struct EngineConfig {
     #[structopt(short, long)]
     pub asset_dir: Option<String>,
 
+    /// Additional `.zip` asset packs to load assets from, such as mods
+    ///
+    /// Packs listed later take priority over the earlier ones, and all packs take priority over
+    /// the asset directory and the `assets.pack` or `assets.zip` pack of the base game.
+    #[structopt(short = "p", long = "asset-pack")]
+    pub asset_packs: Vec<String>,
+
     /// The .game.yaml asset to load at startup
     #[structopt(default_value = "default.game.yaml")]
     pub game_asset: String,
@@ -34,15 +57,178 @@ pub struct EngineConfig {
     #[structopt(short = "d", long)]
     pub debug_tools: bool,
 
+    /// Show the performance overlay at startup, which can be toggled by pressing F6
+    #[structopt(long)]
+    pub perf_overlay: bool,
+
+    /// Run the game simulation without a window, rendering, or UI, restarting the first level
+    /// whenever the game is over
+    ///
+    /// This is used to run the game on servers.
+    #[structopt(long)]
+    pub headless: bool,
+
+    /// The seed for the random number generator used by the game
+    ///
+    /// A random seed is used if this isn't set. The seed is logged and shown in the debug tools,
+    /// so that a run can be reproduced.
+    #[structopt(long)]
+    pub seed: Option<u64>,
+
+    /// The seed used to generate endless levels that don't specify their own seed
+    ///
+    /// Defaults to `--seed`, and a random seed is used if neither is set. The seed of every
+    /// generated level is logged, so that interesting levels can be played again.
+    #[structopt(long)]
+    pub level_seed: Option<u64>,
+
+    /// Load and validate all of the game metadata, print a report, and exit without starting the
+    /// game
+    ///
+    /// Exits with a non-zero status code if any errors were found.
+    #[structopt(long)]
+    pub validate_assets: bool,
+
+    /// Pack the asset directory into an asset bundle at the given path and exit without starting
+    /// the game
+    ///
+    /// The bundle stores the metadata pre-parsed and the spritesheets packed into atlas pages.
+    /// Release builds that don't hot reload load it from `assets.pack`, next to the asset
+    /// directory, if it exists.
+    #[structopt(long)]
+    pub pack_assets: Option<String>,
+
+    /// Play the input script at the given path headless, check that the simulation is
+    /// deterministic, and exit
+    ///
+    /// Exits with a non-zero status code if the runs of the script ended in different states, or
+    /// in a different state than the one recorded in the script.
+    #[structopt(long)]
+    pub determinism_check: Option<String>,
+
+    /// Run the benchmark with the given name, print the results, and exit
+    ///
+    /// The benchmarks `brawl`, `items`, and `particles` play the first level headless with many
+    /// enemies, items, or effects. The hot systems are benchmarked with `cargo bench` instead.
+    #[structopt(long)]
+    pub benchmark: Option<String>,
+
+    /// Host a netplay relay for online sessions on the given UDP address, like `0.0.0.0:7878`,
+    /// and play the levels of the session headless
+    #[structopt(long)]
+    pub relay: Option<String>,
+
+    /// Show every localized string accented, bracketed, and padded to be 30% longer
+    ///
+    /// This is used to find text that doesn't fit in its button or panel once it's translated,
+    /// and text that isn't localized at all, which is shown without the accents.
+    #[structopt(long)]
+    pub pseudo_locale: bool,
+
+    // Doc comments aren't allowed on flattened fields
+    #[structopt(flatten)]
+    pub cheats: CheatFlags,
+
     /// Set the log level
     ///
     /// May additionally specify log levels for specific modules as a comma-separated list of
     /// `module=level` items.
     #[structopt(short = "l", long, default_value = DEFAULT_LOG_LEVEL)]
     pub log_level: String,
+
+    /// Set the log level of a module, like `punchy::combat_log=debug`, on top of the log level
+    ///
+    /// Can be given multiple times. The hits and knockouts of the fighters are logged by
+    /// `punchy::combat_log` at the debug level, with the gameplay step that they happened in.
+    #[structopt(long = "log-module")]
+    pub log_modules: Vec<String>,
+
+    /// Also write the log to `logs/punchy.log` in the game's data directory
+    ///
+    /// The logs of the last runs are kept as `punchy.1.log` and so on, and the file is also moved
+    /// to `punchy.1.log` when it gets too big.
+    #[structopt(long)]
+    pub log_file: bool,
+}
+
+/// Developer flags that make it quicker to test the game. A watermark is shown while any of them
+/// are enabled.
+///
+/// Besides the command line, they can be enabled with a comma-separated list like
+/// `god_mode,start_level=levels/1_beach/beach.level.yaml` in the `PUNCHY_CHEATS` environment
+/// variable, or in the `cheats` URL parameter on the web.
+#[derive(Clone, Debug, Default, StructOpt)]
+pub struct CheatFlags {
+    /// Cheat: players don't lose health when they are hit
+    #[structopt(long)]
+    pub god_mode: bool,
+
+    /// Cheat: guns never run out of ammo
+    #[structopt(long)]
+    pub infinite_ammo: bool,
+
+    /// Cheat: defeat the enemies between the players and the next stop point, so that the players
+    /// can walk through the level
+    #[structopt(long)]
+    pub auto_win: bool,
+
+    /// Cheat: start the game at the level with the given asset path, instead of the first level
+    #[structopt(long)]
+    pub start_level: Option<String>,
+}
+
+impl CheatFlags {
+    /// Whether any of the cheats are enabled
+    pub fn any(&self) -> bool {
+        !self.names().is_empty()
+    }
+
+    /// The names of the enabled cheats
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.god_mode, "god_mode"),
+            (self.infinite_ammo, "infinite_ammo"),
+            (self.auto_win, "auto_win"),
+            (self.start_level.is_some(), "start_level"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect()
+    }
+
+    /// Enable the cheats in a comma-separated list, like `god_mode,start_level=<path>`
+    pub fn enable_from_list(&mut self, list: &str) -> Result<(), String> {
+        for cheat in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match cheat.split_once('=') {
+                None if cheat == "god_mode" => self.god_mode = true,
+                None if cheat == "infinite_ammo" => self.infinite_ammo = true,
+                None if cheat == "auto_win" => self.auto_win = true,
+                Some(("start_level", path)) => self.start_level = Some(path.to_owned()),
+                _ => return Err(format!("Unknown cheat `{cheat}`")),
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl EngineConfig {
+    /// The filter for the log, with the levels of the modules after the log level
+    pub fn log_filter(&self) -> String {
+        std::iter::once(self.log_level.as_str())
+            .chain(self.log_modules.iter().map(String::as_str))
+            .filter(|directive| !directive.is_empty())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Get the path to the asset directory on the file system
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn asset_dir_path(&self) -> std::path::PathBuf {
+        bevy::asset::FileAssetIo::get_base_path()
+            .join(self.asset_dir.as_deref().unwrap_or(DEFAULT_ASSET_DIR))
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn from_web_params() -> Self {
         if let Some(query) = web_sys::window().and_then(|w| w.location().search().ok()) {
@@ -68,10 +254,43 @@ impl EngineConfig {
                 config.debug_tools = debug_tools;
             }
 
+            if let Some(perf_overlay) =
+                parse_url_query_string(&query, "perf_overlay").and_then(|s| s.parse().ok())
+            {
+                config.perf_overlay = perf_overlay;
+            }
+
+            if let Some(seed) = parse_url_query_string(&query, "seed").and_then(|s| s.parse().ok())
+            {
+                config.seed = Some(seed);
+            }
+
+            if let Some(level_seed) =
+                parse_url_query_string(&query, "level_seed").and_then(|s| s.parse().ok())
+            {
+                config.level_seed = Some(level_seed);
+            }
+
+            if let Some(pseudo_locale) =
+                parse_url_query_string(&query, "pseudo_locale").and_then(|s| s.parse().ok())
+            {
+                config.pseudo_locale = pseudo_locale;
+            }
+
+            if let Some(cheats) = parse_url_query_string(&query, "cheats") {
+                if let Err(e) = config.cheats.enable_from_list(cheats) {
+                    web_sys::console::warn_1(&format!("Could not parse cheats: {e}").into());
+                }
+            }
+
             if let Some(log_level) = parse_url_query_string(&query, "log_level") {
                 config.log_level = log_level.into();
             }
 
+            if let Some(log_modules) = parse_url_query_string(&query, "log_modules") {
+                config.log_modules = log_modules.split(',').map(String::from).collect();
+            }
+
             config
         } else {
             Self::web_default()
@@ -85,10 +304,24 @@ impl EngineConfig {
         Self {
             hot_reload: false,
             asset_dir: None,
+            asset_packs: Vec::new(),
             game_asset: "default.game.yaml".into(),
             auto_start: false,
             debug_tools: false,
+            perf_overlay: false,
+            headless: false,
+            seed: None,
+            level_seed: None,
+            validate_assets: false,
+            pack_assets: None,
+            determinism_check: None,
+            benchmark: None,
+            relay: None,
+            pseudo_locale: false,
+            cheats: CheatFlags::default(),
             log_level: DEFAULT_LOG_LEVEL.into(),
+            log_modules: Vec::new(),
+            log_file: false,
         }
     }
 }
@@ -144,4 +377,38 @@ mod test {
             parse_url_query_string("?hello=world&foo=bar", "RUST_LOG")
         );
     }
+
+    #[test]
+    fn enable_cheats_from_list() {
+        let mut cheats = CheatFlags::default();
+        assert!(!cheats.any());
+
+        cheats
+            .enable_from_list("god_mode, start_level=levels/test.level.yaml")
+            .unwrap();
+        assert!(cheats.god_mode);
+        assert!(!cheats.auto_win);
+        assert_eq!(
+            cheats.start_level.as_deref(),
+            Some("levels/test.level.yaml")
+        );
+        assert_eq!(cheats.names(), ["god_mode", "start_level"]);
+
+        assert!(cheats.enable_from_list("fly").is_err());
+    }
+
+    #[test]
+    fn module_log_levels_follow_log_level() {
+        let mut config = EngineConfig::from_iter_safe(["punchy", "-l", "warn"]).unwrap();
+        assert_eq!(config.log_filter(), "warn");
+
+        config.log_modules = vec![
+            "punchy::combat_log=debug".to_owned(),
+            "wgpu=error".to_owned(),
+        ];
+        assert_eq!(
+            config.log_filter(),
+            "warn,punchy::combat_log=debug,wgpu=error"
+        );
+    }
 }
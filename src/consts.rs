@@ -28,15 +28,44 @@ pub const MIN_Y: f32 = -(GROUND_HEIGHT / 2.) + GROUND_Y - 50.;
 
 pub const ITEM_ATTACK_VELOCITY: f32 = 80.0;
 pub const HITSTUN_DURATION: f32 = 0.50;
+// How quickly knocked back fighters slow down, in pixels per second squared
+pub const KNOCKBACK_FRICTION: f32 = 160.;
+/// How quickly the fighters launched into the air fall back down, in pixels per second squared
+pub const JUGGLE_GRAVITY: f32 = 900.;
+/// How high the hits that don't launch pop the fighters that are already in the air
+pub const JUGGLE_HIT_HEIGHT: f32 = 20.;
+/// How much lower each further hit of a juggle pops the fighter, so that juggles come to an end
+pub const JUGGLE_DECAY: f32 = 0.8;
+/// How much of its falling speed a fighter bounces back up with when it first hits the ground
+pub const LANDING_BOUNCE: f32 = 0.35;
 
 pub const ITEM_LAYER: f32 = 100.;
+/// Under the fighters and the items lying on the ground
+pub const SHADOWS_Z: f32 = 99.;
 pub const ITEM_WIDTH: f32 = 30.;
 pub const ITEM_HEIGHT: f32 = 10.;
 
 pub const PROJECTILE_Z: f32 = 101.;
+/// In front of all of the fighters
+pub const EFFECTS_Z: f32 = 600.;
+/// In front of the effects, so that the spirits of the knocked out players are never hidden
+pub const SPIRITS_Z: f32 = 700.;
 pub const THROW_ITEM_OFFSET: Vec2 = Vec2::from_array([5.0, 30.0]);
 pub const THROW_ITEM_ROTATION_SPEED: f32 = -20.;
 
 pub const PICK_ITEM_RADIUS: f32 = 24.;
+pub const GRAB_FIGHTER_RADIUS: f32 = 40.;
+/// Where a grabbed fighter is carried, relative to the fighter carrying it, unless the fighter has
+/// its own [`carry_offset`](crate::metadata::FighterMeta::carry_offset)
+pub const HELD_FIGHTER_OFFSET: Vec2 = Vec2::from_array([0.0, 50.0]);
+/// How far in front of an enemy the player it has grabbed is held
+pub const GRAPPLED_FIGHTER_OFFSET: f32 = 24.;
+
+/// The radius around the feet of a fighter that the other fighters are pushed out of
+pub const FIGHTER_COLLISION_RADIUS: f32 = 10.;
+/// How fast allies push each other apart when they fully overlap
+pub const ALLY_SEPARATION_SPEED: f32 = 40.;
+/// How fast enemies push each other apart when they fully overlap
+pub const ENEMY_SEPARATION_SPEED: f32 = 120.;
 
 pub const FOOT_PADDING: f32 = 16.;
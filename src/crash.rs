@@ -0,0 +1,214 @@
+//! Crash reports, which are written when the game panics.
+//!
+//! The native builds install a panic hook that writes a report to the `crashes` directory next to
+//! the game's storage file, with the panic message, a backtrace, a summary of what the game was
+//! doing, and the tail of the log. The next time the game starts, the main menu shows where the
+//! report of the crash was saved, so that it can be attached to an issue. The web build shows
+//! panics in the browser console instead.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::CrashPlugin;
+
+/// Resource with the report of the crash of the last run, if the game crashed the last time it was
+/// played
+#[derive(Resource)]
+// The main menu shows the last crash on every platform, but it's only inserted in the native builds
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub struct LastCrash {
+    pub report: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::{
+        fmt::{Display, Write as _},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use bevy::prelude::*;
+    use iyes_loopless::prelude::*;
+
+    use crate::{
+        capture::format_timestamp, enemy::Enemy, logging::LogTail, metadata::LevelHandle,
+        player::Player, timestep::GameplayStep, GameState,
+    };
+
+    use super::LastCrash;
+
+    /// The file in the crash directory with the path of the last crash report, until it has been
+    /// shown in the next run
+    const LAST_CRASH_FILE: &str = "last-crash.txt";
+
+    /// What the game is doing, for the crash reports
+    #[derive(Resource, Clone, Default)]
+    struct CrashSummary(Arc<Mutex<GameSummary>>);
+
+    #[derive(Clone, Default, PartialEq)]
+    struct GameSummary {
+        state: Option<GameState>,
+        level: Option<String>,
+        step: u64,
+        players: usize,
+        enemies: usize,
+    }
+
+    pub struct CrashPlugin;
+
+    impl Plugin for CrashPlugin {
+        fn build(&self, app: &mut App) {
+            let dir = crash_dir();
+
+            if let Some(report) = take_last_crash(&dir) {
+                warn!(?report, "The game crashed the last time it was played");
+                app.insert_resource(LastCrash { report });
+            }
+
+            let summary = CrashSummary::default();
+            let log_tail = app.world.get_resource::<LogTail>().cloned();
+            let default_hook = std::panic::take_hook();
+            let hook_summary = summary.clone();
+            std::panic::set_hook(Box::new(move |info| {
+                default_hook(info);
+
+                match write_crash_report(&dir, info, &hook_summary, log_tail.as_ref()) {
+                    Ok(path) => eprintln!("The crash report was saved to {}", path.display()),
+                    Err(e) => eprintln!("Could not save the crash report: {e}"),
+                }
+            }));
+
+            app.insert_resource(summary)
+                .add_system_to_stage(CoreStage::Last, update_crash_summary);
+        }
+    }
+
+    fn crash_dir() -> PathBuf {
+        directories::ProjectDirs::from("org", "FishFolk", "Punchy")
+            .map(|project_dirs| project_dirs.data_dir().join("crashes"))
+            .unwrap_or_else(|| PathBuf::from("crashes"))
+    }
+
+    /// Get the path of the report of the last crash, if it hasn't been shown yet
+    fn take_last_crash(dir: &Path) -> Option<PathBuf> {
+        let last_crash_file = dir.join(LAST_CRASH_FILE);
+        let report = std::fs::read_to_string(&last_crash_file).ok()?;
+        std::fs::remove_file(&last_crash_file).ok();
+
+        Some(PathBuf::from(report.trim()))
+    }
+
+    fn update_crash_summary(
+        summary: Res<CrashSummary>,
+        state: Res<CurrentState<GameState>>,
+        level_handle: Option<Res<LevelHandle>>,
+        asset_server: Res<AssetServer>,
+        step: Res<GameplayStep>,
+        players: Query<(), With<Player>>,
+        enemies: Query<(), With<Enemy>>,
+    ) {
+        let level = level_handle
+            .and_then(|level_handle| asset_server.get_handle_path(&level_handle.0))
+            .map(|path| path.path().to_string_lossy().replace('\\', "/"));
+        let current = GameSummary {
+            state: Some(state.0),
+            level,
+            step: **step,
+            players: players.iter().count(),
+            enemies: enemies.iter().count(),
+        };
+
+        let mut summary = summary.0.lock().unwrap();
+        if *summary != current {
+            *summary = current;
+        }
+    }
+
+    fn write_crash_report(
+        dir: &Path,
+        panic: &dyn Display,
+        summary: &CrashSummary,
+        log_tail: Option<&LogTail>,
+    ) -> std::io::Result<PathBuf> {
+        let mut report = format!(
+            "Punchy {} crashed on {}\n\n{panic}\n\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS
+        );
+
+        // The panic may have happened while the summary was being updated
+        match summary.0.try_lock() {
+            Ok(summary) => report.push_str(&format_summary(&summary)),
+            Err(_) => report.push_str("The state of the game is unknown\n"),
+        }
+
+        writeln!(
+            report,
+            "\nBacktrace:\n{}",
+            std::backtrace::Backtrace::force_capture()
+        )
+        .ok();
+        if let Some(log_tail) = log_tail {
+            writeln!(report, "Log:\n{}", log_tail.text()).ok();
+        }
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("crash-{}.txt", format_timestamp(secs)));
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, report)?;
+        std::fs::write(dir.join(LAST_CRASH_FILE), path.to_string_lossy().as_bytes())?;
+
+        Ok(path)
+    }
+
+    fn format_summary(summary: &GameSummary) -> String {
+        let state = summary
+            .state
+            .map_or_else(|| "starting up".to_owned(), |state| format!("{state:?}"));
+
+        let mut text = String::new();
+        writeln!(text, "Game state: {state}").ok();
+        if let Some(level) = &summary.level {
+            writeln!(text, "Level: {level}").ok();
+        }
+        writeln!(text, "Gameplay step: {}", summary.step).ok();
+        writeln!(
+            text,
+            "Players: {}, enemies: {}",
+            summary.players, summary.enemies
+        )
+        .ok();
+        text
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn summary_lists_game_state() {
+            assert_eq!(
+                format_summary(&GameSummary::default()),
+                "Game state: starting up\nGameplay step: 0\nPlayers: 0, enemies: 0\n"
+            );
+            assert_eq!(
+                format_summary(&GameSummary {
+                    state: Some(GameState::InGame),
+                    level: Some("levels/1_beach/beach.level.yaml".to_owned()),
+                    step: 120,
+                    players: 2,
+                    enemies: 5,
+                }),
+                "Game state: InGame\nLevel: levels/1_beach/beach.level.yaml\nGameplay step: 120\n\
+                 Players: 2, enemies: 5\n"
+            );
+        }
+    }
+}
@@ -1,10 +1,18 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    assist::Assists,
+    combat_events::{HitLanded, Killed},
+    config::ENGINE_CONFIG,
+    player::Player,
+    tint::Tint,
+};
 
 pub struct DamagePlugin;
 
 impl Plugin for DamagePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<DamageEvent>().register_type::<Health>();
+        app.register_type::<Health>().register_type::<Damageable>();
     }
 }
 
@@ -16,7 +24,8 @@ pub struct Health(pub i32);
 /// A component that indicates whether an entity can be damaged.
 ///
 /// In other words, something that has [`Health`] but isn't [`Damageable`] is currently invincible.
-#[derive(Component, Deref, DerefMut)]
+#[derive(Reflect, Component, Deref, DerefMut)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Damageable(pub bool);
 
@@ -26,11 +35,77 @@ impl Default for Damageable {
     }
 }
 
-/// Event emitted when an entity is damaged
-pub struct DamageEvent {
-    pub damage_velocity: Vec2,
-    pub damageing_entity: Entity,
-    pub damaged_entity: Entity,
-    pub damage: i32,
-    pub hitstun_duration: f32,
+/// System parameter that hurts fighters, whether they are hit by attacks, slammed into the ground,
+/// thrown into the others or burning, all by the same rules: the players keep their health in god
+/// mode and take the damage of the assists, and the hits are sent as [`HitLanded`] and [`Killed`]
+/// events.
+#[derive(SystemParam)]
+pub struct FighterDamage<'w, 's> {
+    fighters: Query<
+        'w,
+        's,
+        (
+            &'static mut Health,
+            Option<&'static Damageable>,
+            Option<&'static Player>,
+            Option<&'static mut Tint>,
+        ),
+    >,
+    assists: Option<Res<'w, Assists>>,
+    hit_events: EventWriter<'w, 's, HitLanded>,
+    killed_events: EventWriter<'w, 's, Killed>,
+}
+
+impl<'w, 's> FighterDamage<'w, 's> {
+    /// Deal the damage of a hit to the fighter it landed on
+    pub fn deal(&mut self, mut hit: HitLanded) {
+        let Some(damage) = self.hurt(hit.damageing_entity, hit.damaged_entity, hit.damage, true)
+        else {
+            return;
+        };
+        hit.damage = damage;
+        self.hit_events.send(hit);
+    }
+
+    /// Deal the chip damage of a blocked hit to the fighter that blocked it, which isn't a hit
+    /// landed on it, returning the damage that it took
+    pub fn chip(&mut self, attacking_entity: Entity, blocking_entity: Entity, damage: i32) -> i32 {
+        self.hurt(attacking_entity, blocking_entity, damage, false)
+            .unwrap_or(0)
+    }
+
+    /// Take the damage from the health of the fighter, returning the damage that it took, or
+    /// `None` if it can't be damaged
+    fn hurt(
+        &mut self,
+        damageing_entity: Entity,
+        damaged_entity: Entity,
+        mut damage: i32,
+        flash: bool,
+    ) -> Option<i32> {
+        let (mut health, damageable, player, tint) = self.fighters.get_mut(damaged_entity).ok()?;
+        if !damageable.map_or(true, |damageable| **damageable) {
+            return None;
+        }
+
+        if let (Some(assists), Some(_)) = (&self.assists, player) {
+            damage = assists.player_damage(damage);
+        }
+        let was_alive = **health > 0;
+        // Players are still hit in god mode, they just don't lose any health
+        if !(ENGINE_CONFIG.cheats.god_mode && player.is_some()) {
+            **health -= damage;
+        }
+        if let Some(mut tint) = tint.filter(|_| flash) {
+            tint.flash();
+        }
+
+        if was_alive && **health <= 0 {
+            self.killed_events.send(Killed {
+                killing_entity: damageing_entity,
+                killed_entity: damaged_entity,
+            });
+        }
+        Some(damage)
+    }
 }
@@ -0,0 +1,208 @@
+//! Damage numbers, which float up from the fighters that are hit.
+//!
+//! Every hit that hurts, or heals, a fighter shows its damage where it lands, and so do the blocks
+//! that chip away at the health of the fighters. The critical hits and the heals stand out with
+//! fonts of their own. The numbers are drawn by the
+//! [HUD](crate::ui::hud::render_damage_numbers) in the style of the theme's
+//! [`DamageNumbersThemeMeta`](crate::metadata::DamageNumbersThemeMeta), and fade out as they float
+//! up.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::AttackDamageSystem,
+    combat_events::{Blocked, HitLanded},
+    effects::hit_position,
+    menu_stack::MenuLayer,
+    metadata::{DamageNumberKind, FighterMeta, GameMeta},
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// How long a damage number floats up for, in seconds of real time
+const FLOAT_DURATION: f32 = 0.8;
+
+/// How far a damage number floats up, in world units
+const FLOAT_HEIGHT: f32 = 24.0;
+
+/// The part of the float that a damage number is fully opaque for, before it fades out
+const OPAQUE_PART: f32 = 0.5;
+
+pub struct DamageNumbersPlugin;
+
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DamageNumbers>()
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                spawn_damage_numbers
+                    .run_in_state(GameState::InGame)
+                    .after(AttackDamageSystem),
+            )
+            .add_system(
+                float_damage_numbers
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None),
+            )
+            .add_exit_system(GameState::InGame, clear_damage_numbers);
+    }
+}
+
+/// A number floating up from a fighter that was hit
+#[derive(Clone, Debug)]
+pub struct DamageNumber {
+    /// Where the number started floating from
+    pub position: Vec2,
+    /// The damage of the hit, or the health given back by a heal
+    pub damage: i32,
+    pub kind: DamageNumberKind,
+    /// The real time that the number has been floating for, in seconds
+    age: f32,
+}
+
+impl DamageNumber {
+    /// The number for a hit of `damage`, or none for the hits that don't change the health
+    fn new(position: Vec2, damage: i32, critical: bool) -> Option<Self> {
+        let kind = match damage {
+            0 => return None,
+            _ if damage < 0 => DamageNumberKind::Heal,
+            _ if critical => DamageNumberKind::Critical,
+            _ => DamageNumberKind::Hit,
+        };
+        Some(Self {
+            position,
+            damage: damage.abs(),
+            kind,
+            age: 0.0,
+        })
+    }
+
+    /// The text of the number
+    pub fn text(&self) -> String {
+        match self.kind {
+            DamageNumberKind::Hit => self.damage.to_string(),
+            DamageNumberKind::Critical => format!("{}!", self.damage),
+            DamageNumberKind::Heal => format!("+{}", self.damage),
+        }
+    }
+
+    /// How far the number has floated up, and how opaque it is, from `0.0` to `1.0`
+    pub fn offset_and_alpha(&self) -> (f32, f32) {
+        float(self.age / FLOAT_DURATION)
+    }
+}
+
+/// How far a damage number has floated up, and how opaque it is, at `progress` through its float
+fn float(progress: f32) -> (f32, f32) {
+    let progress = progress.clamp(0.0, 1.0);
+    // The number slows down as it reaches the top
+    let offset = FLOAT_HEIGHT * (1.0 - (1.0 - progress).powi(2));
+    let alpha = ((1.0 - progress) / (1.0 - OPAQUE_PART)).min(1.0);
+    (offset, alpha)
+}
+
+/// The damage numbers that are floating up
+#[derive(Resource, Default)]
+pub struct DamageNumbers(Vec<DamageNumber>);
+
+impl DamageNumbers {
+    pub fn iter(&self) -> impl Iterator<Item = &DamageNumber> {
+        self.0.iter()
+    }
+}
+
+/// Show the damage of the hits, and of the blocks that chip away at the health, where they land
+fn spawn_damage_numbers(
+    mut damage_numbers: ResMut<DamageNumbers>,
+    mut damage_events: EventReader<HitLanded>,
+    mut blocked_events: EventReader<Blocked>,
+    targets: Query<(&GlobalTransform, Option<&Handle<FighterMeta>>)>,
+    attacks: Query<&GlobalTransform>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+) {
+    if game.ui_theme.hud.damage_numbers.is_none() {
+        damage_events.clear();
+        blocked_events.clear();
+        return;
+    }
+
+    let hits = damage_events
+        .iter()
+        .map(|event| {
+            (
+                event.damageing_entity,
+                event.damaged_entity,
+                event.damage,
+                event.critical,
+            )
+        })
+        .chain(blocked_events.iter().map(|event| {
+            (
+                event.attacking_entity,
+                event.blocking_entity,
+                event.chip_damage,
+                false,
+            )
+        }));
+    for (attack, target, damage, critical) in hits {
+        let Ok((transform, meta_handle)) = targets.get(target) else {
+            continue;
+        };
+
+        let fighter = meta_handle.and_then(|handle| fighter_assets.get(handle));
+        let position = hit_position(transform, fighter, attacks.get(attack).ok());
+        damage_numbers
+            .0
+            .extend(DamageNumber::new(position, damage, critical));
+    }
+}
+
+fn float_damage_numbers(mut damage_numbers: ResMut<DamageNumbers>, time: Res<Time>) {
+    if damage_numbers.0.is_empty() {
+        return;
+    }
+
+    let delta = time.delta_seconds();
+    damage_numbers.0.retain_mut(|number| {
+        number.age += delta;
+        number.age < FLOAT_DURATION
+    });
+}
+
+fn clear_damage_numbers(mut damage_numbers: ResMut<DamageNumbers>) {
+    damage_numbers.0.clear();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers_show_the_kind_of_hit() {
+        let number = |damage, critical| DamageNumber::new(Vec2::ZERO, damage, critical);
+
+        assert_eq!(number(12, false).unwrap().text(), "12");
+        assert_eq!(number(12, true).unwrap().text(), "12!");
+        let heal = number(-5, true).unwrap();
+        assert_eq!(
+            (heal.kind, heal.text()),
+            (DamageNumberKind::Heal, "+5".to_owned())
+        );
+        assert!(number(0, false).is_none());
+    }
+
+    #[test]
+    fn numbers_float_up_and_fade_out() {
+        assert_eq!(float(0.0), (0.0, 1.0));
+        assert_eq!(float(OPAQUE_PART).1, 1.0);
+        assert_eq!(float(1.0), (FLOAT_HEIGHT, 0.0));
+
+        let (low, _) = float(0.25);
+        let (high, alpha) = float(0.75);
+        assert!(0.0 < low && low < high && high < FLOAT_HEIGHT);
+        assert!(0.0 < alpha && alpha < 1.0);
+    }
+}
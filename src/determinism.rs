@@ -0,0 +1,251 @@
+//! Checking that the gameplay simulation is deterministic.
+//!
+//! Running the game with `--determinism-check <script>` plays the level of an [`InputScript`]
+//! headless, with the player inputs taken from the script instead of the input devices, and
+//! computes a checksum of the state of the fighters, the items, and the [`GameRng`] after the last
+//! step of the script. The level is played [`InputScript::runs`] times, and the check fails if the
+//! checksums of the runs differ from each other, or from the checksum recorded in the script.
+//!
+//! Every frame runs exactly one gameplay step, using the [`SteppedClockPlugin`], so the results
+//! don't depend on how fast the machine running the check is. Any difference between the runs means that a
+//! gameplay system depends on something other than the inputs and the seed, like the order that
+//! unordered systems happen to run in.
+//!
+//! Scripts that don't have a recorded checksum yet only compare the runs, and print the checksum so
+//! that it can be added to the script. Levels that stream their sections load them in the
+//! background, so they should not be used for checks.
+
+use std::{path::Path, time::Duration};
+
+use bevy::{prelude::*, utils::Instant};
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+use rand::RngCore;
+
+use crate::{
+    damage::Health,
+    enemy::Enemy,
+    headless::{HeadlessLevel, SteppedClockPlugin},
+    input::{disconnect_player_inputs, InputScript, PlayerAction},
+    item::Item,
+    player::{Player, PlayerIndex},
+    rng::GameRng,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    utils::ResetController,
+    GameState,
+};
+
+/// How long the whole check may take before it's considered stuck, like when an asset fails to load
+const TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+pub struct DeterminismPlugin {
+    script: InputScript,
+}
+
+impl DeterminismPlugin {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let script: InputScript = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+        anyhow::ensure!(script.runs > 0, "The script must be played at least once");
+
+        Ok(Self { script })
+    }
+}
+
+impl Plugin for DeterminismPlugin {
+    fn build(&self, app: &mut App) {
+        let level = app.world.resource::<AssetServer>().load(&self.script.level);
+
+        app.add_plugin(SteppedClockPlugin)
+            .insert_resource(GameRng::new(self.script.seed))
+            .insert_resource(HeadlessLevel(level))
+            .insert_resource(DeterminismCheck {
+                script: self.script.clone(),
+                step: 0,
+                checksums: Vec::new(),
+                started: Instant::now(),
+            })
+            .add_system(disconnect_player_inputs)
+            .add_system(check_timeout)
+            .add_enter_system(GameState::MainMenu, check_game_over)
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                play_input_script.run_in_state(GameState::InGame),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                finish_run.run_in_state(GameState::InGame),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct DeterminismCheck {
+    script: InputScript,
+    /// The step of the current run
+    step: u32,
+    /// The checksums of the finished runs
+    checksums: Vec<u64>,
+    started: Instant,
+}
+
+impl DeterminismCheck {
+    /// Print the results of the check, returning whether it passed
+    fn report(&self) -> bool {
+        let checksums = self
+            .checksums
+            .iter()
+            .map(|checksum| format!("{checksum:016x}"))
+            .collect::<Vec<_>>();
+
+        if checksums.iter().any(|checksum| checksum != &checksums[0]) {
+            eprintln!("error: The runs ended with different checksums: {checksums:?}");
+            return false;
+        }
+
+        match &self.script.checksum {
+            Some(expected) if expected != &checksums[0] => {
+                eprintln!(
+                    "error: The runs ended with checksum {}, but {expected} was expected",
+                    checksums[0]
+                );
+                false
+            }
+            Some(_) => {
+                println!(
+                    "All {} runs ended with the expected checksum",
+                    checksums.len()
+                );
+                true
+            }
+            None => {
+                println!(
+                    "All {} runs ended with checksum {}, which can be recorded in the script",
+                    checksums.len(),
+                    checksums[0]
+                );
+                true
+            }
+        }
+    }
+}
+
+fn play_input_script(
+    mut check: ResMut<DeterminismCheck>,
+    mut players: Query<(&PlayerIndex, &mut ActionState<PlayerAction>)>,
+) {
+    for (player_index, mut action_state) in &mut players {
+        check
+            .script
+            .input_at(player_index.0, check.step)
+            .apply(&mut action_state);
+    }
+
+    check.step += 1;
+}
+
+/// Compute the checksum after the last step of the script, and start the next run or finish the
+/// check
+fn finish_run(
+    mut commands: Commands,
+    mut check: ResMut<DeterminismCheck>,
+    mut rng: ResMut<GameRng>,
+    fighters: Query<(&Transform, &Health, Option<&Player>), Or<(With<Player>, With<Enemy>)>>,
+    items: Query<&Transform, With<Item>>,
+    reset_controller: ResetController,
+) {
+    if check.step < check.script.steps {
+        return;
+    }
+
+    // Sort the state, so that the checksum doesn't depend on the order of the entities
+    let mut fighter_states = fighters
+        .iter()
+        .map(|(transform, health, player)| {
+            let translation = transform.translation;
+            (
+                player.is_some(),
+                translation.x.to_bits(),
+                translation.y.to_bits(),
+                health.0,
+            )
+        })
+        .collect::<Vec<_>>();
+    fighter_states.sort_unstable();
+    let mut item_states = items
+        .iter()
+        .map(|transform| {
+            let translation = transform.translation;
+            (translation.x.to_bits(), translation.y.to_bits())
+        })
+        .collect::<Vec<_>>();
+    item_states.sort_unstable();
+
+    let mut checksum = Checksum::default();
+    for (is_player, x, y, health) in fighter_states {
+        checksum.write(is_player as u64);
+        checksum.write(x as u64);
+        checksum.write(y as u64);
+        checksum.write(health as u64);
+    }
+    for (x, y) in item_states {
+        checksum.write(x as u64);
+        checksum.write(y as u64);
+    }
+    // How much of the random sequence has been used
+    checksum.write(rng.next_u64());
+
+    let run = check.checksums.len() + 1;
+    info!(run, "Finished run with checksum {:016x}", checksum.0);
+    check.checksums.push(checksum.0);
+    check.step = 0;
+
+    if check.checksums.len() < check.script.runs {
+        reset_controller.reset_world();
+        commands.insert_resource(NextState(GameState::MainMenu));
+    } else {
+        std::process::exit(if check.report() { 0 } else { 1 });
+    }
+}
+
+/// The players are defeated if the game returns to the main menu in the middle of a run
+fn check_game_over(check: Res<DeterminismCheck>) {
+    if check.step > 0 {
+        eprintln!(
+            "error: The players were defeated after {} of {} steps, the script should be shorter",
+            check.step, check.script.steps
+        );
+        std::process::exit(1);
+    }
+}
+
+fn check_timeout(check: Res<DeterminismCheck>) {
+    if check.started.elapsed() > TIMEOUT {
+        eprintln!(
+            "error: The check timed out after {} runs and {} steps",
+            check.checksums.len(),
+            check.step
+        );
+        std::process::exit(1);
+    }
+}
+
+/// 64-bit FNV-1a hash, which unlike the standard library hashers is the same on every platform
+/// and version
+struct Checksum(u64);
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Checksum {
+    fn write(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Dialogues between the characters of the game, for the story beats between fights.
+//!
+//! A dialogue is played by inserting a [`Dialogue`] resource, which levels do when the players get
+//! to one of their [dialogues](crate::metadata::LevelMeta::dialogues). The lines are shown one at a
+//! time with the portrait of their speaker, and are typed out letter by letter. The confirm button
+//! or a player's attack button shows the rest of the line, or moves on to the next one. The
+//! gameplay is held until the dialogue is over.
+//!
+//! The levels don't play their dialogues in the attract demo, or without a UI.
+
+use bevy::prelude::*;
+use bevy_fluent::Localization;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    input::{MenuAction, PlayerAction},
+    localization::LocalizationExt,
+    menu_stack::MenuLayer,
+    metadata::{DialogueLineMeta, LevelMeta},
+    player::Player,
+    timestep::TimeScale,
+    ui::attract_mode,
+    utils::ResetEvent,
+    GameState,
+};
+
+/// How many characters of a line are typed out per second
+const CHARS_PER_SECOND: f32 = 40.0;
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, end_dialogue)
+            .add_system(hold_gameplay)
+            .add_system(end_dialogue_on_reset)
+            .add_system(
+                start_level_dialogues
+                    .run_in_state(GameState::InGame)
+                    .run_if_not(attract_mode::demo_playing),
+            )
+            .add_system(
+                advance_dialogue
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<Dialogue>(),
+            );
+    }
+}
+
+/// The dialogue being played
+#[derive(Resource)]
+pub struct Dialogue {
+    lines: Vec<DialogueLineMeta>,
+    /// The index of the line being shown
+    line: usize,
+    /// How long the current line has been typed out for, in seconds
+    typing_time: f32,
+}
+
+impl Dialogue {
+    pub fn new(lines: Vec<DialogueLineMeta>) -> Self {
+        Self {
+            lines,
+            line: 0,
+            typing_time: 0.0,
+        }
+    }
+
+    /// The line being shown, if the dialogue isn't over
+    pub fn current_line(&self) -> Option<&DialogueLineMeta> {
+        self.lines.get(self.line)
+    }
+
+    /// The number of characters of the current line that have been typed out so far
+    pub fn typed_chars(&self) -> usize {
+        (self.typing_time * CHARS_PER_SECOND) as usize
+    }
+
+    fn is_over(&self) -> bool {
+        self.line >= self.lines.len()
+    }
+
+    /// Show the rest of the current line, which is `line_chars` characters long, or move on to the
+    /// next line if it's all shown already
+    fn advance(&mut self, line_chars: usize) {
+        if self.typed_chars() < line_chars {
+            self.typing_time = line_chars as f32 / CHARS_PER_SECOND;
+        } else {
+            self.line += 1;
+            self.typing_time = 0.0;
+        }
+    }
+}
+
+fn end_dialogue(mut commands: Commands) {
+    commands.remove_resource::<Dialogue>();
+}
+
+/// End the dialogue when the level is reset, since its speakers may be gone
+fn end_dialogue_on_reset(mut commands: Commands, mut reset_events: EventReader<ResetEvent>) {
+    if reset_events.iter().any(|reset| reset.scope.resets_level()) {
+        commands.remove_resource::<Dialogue>();
+    }
+}
+
+/// Hold the gameplay while a dialogue is played
+fn hold_gameplay(dialogue: Option<Res<Dialogue>>, mut time_scale: ResMut<TimeScale>) {
+    let held = dialogue.is_some();
+    if time_scale.held != held {
+        time_scale.held = held;
+    }
+}
+
+/// Start the dialogues of the level as the players get to them
+fn start_level_dialogues(
+    mut commands: Commands,
+    mut next_dialogue: Local<usize>,
+    level: Res<LevelMeta>,
+    dialogue: Option<Res<Dialogue>>,
+    players: Query<&Transform, With<Player>>,
+) {
+    if level.is_changed() {
+        *next_dialogue = 0;
+    }
+    if dialogue.is_some() {
+        return;
+    }
+    let Some(level_dialogue) = level.dialogues.get(*next_dialogue) else {
+        return;
+    };
+
+    if players
+        .iter()
+        .any(|transform| transform.translation.x >= level_dialogue.trigger_x)
+    {
+        *next_dialogue += 1;
+        commands.insert_resource(Dialogue::new(level_dialogue.lines.clone()));
+    }
+}
+
+/// Type out the current line, and advance the dialogue when asked to
+fn advance_dialogue(
+    mut commands: Commands,
+    mut dialogue: ResMut<Dialogue>,
+    time: Res<Time>,
+    localization: Res<Localization>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    player_inputs: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let Some(line) = dialogue.current_line() else {
+        commands.remove_resource::<Dialogue>();
+        return;
+    };
+    let line_chars = localization.get(line.line.as_str()).chars().count();
+
+    dialogue.typing_time += time.delta_seconds();
+
+    let confirmed = menu_input
+        .iter()
+        .any(|input| input.just_pressed(MenuAction::Confirm))
+        || player_inputs
+            .iter()
+            .any(|input| input.just_pressed(PlayerAction::Attack));
+    if confirmed {
+        dialogue.advance(line_chars);
+        if dialogue.is_over() {
+            commands.remove_resource::<Dialogue>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn line(line: &str) -> DialogueLineMeta {
+        DialogueLineMeta {
+            speaker: String::new(),
+            speaker_handle: default(),
+            line: line.to_owned(),
+        }
+    }
+
+    #[test]
+    fn advancing_shows_the_whole_line_before_the_next_one() {
+        let mut dialogue = Dialogue::new(vec![line("first"), line("second")]);
+
+        dialogue.advance(10);
+        assert_eq!(dialogue.typed_chars(), 10);
+        assert_eq!(dialogue.current_line().unwrap().line, "first");
+
+        dialogue.advance(10);
+        assert_eq!(dialogue.typed_chars(), 0);
+        assert_eq!(dialogue.current_line().unwrap().line, "second");
+
+        dialogue.advance(0);
+        assert!(dialogue.is_over());
+    }
+}
@@ -0,0 +1,231 @@
+//! The pacing director, which keeps the fights of a level tense without being unfair.
+//!
+//! The director follows how well the players are doing, from their health and the damage they have
+//! taken recently, as an intensity between 0, while they are struggling, and 1, while they are
+//! doing well. The intensity picks the survival wave sizes, the number of enemies that may go after
+//! the players at once, and the chance of defeated enemies dropping an item, from within the
+//! [bounds](DirectorMeta) set by the level.
+//!
+//! The intensity changes slowly, so that a single hit doesn't change the pace of a fight.
+
+use bevy::prelude::*;
+use bevy_mod_js_scripting::ActiveScripts;
+use iyes_loopless::prelude::*;
+use rand::Rng;
+
+use crate::{
+    combat_events::HitLanded,
+    consts,
+    damage::Health,
+    enemy::Enemy,
+    fighter_state::Dying,
+    item::ItemBundle,
+    metadata::{DirectorMeta, ItemMeta, ItemSpawnMeta, LevelMeta},
+    player::Player,
+    rng::GameRng,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState, Stats,
+};
+
+/// The intensity of a level when it starts
+const START_INTENSITY: f32 = 0.5;
+
+/// How quickly the intensity moves towards how well the players are doing, per second
+const INTENSITY_CHANGE_RATE: f32 = 0.1;
+
+/// How long it takes for half of the recent damage to be forgotten, in seconds
+const RECENT_DAMAGE_HALF_LIFE: f32 = 5.0;
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_director.run_if_resource_exists::<LevelMeta>())
+            .add_enter_system(GameState::MainMenu, end_director)
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                update_director
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<Director>(),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                drop_items
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<Director>(),
+            );
+    }
+}
+
+/// The director of the level being played, which exists if the level has [`DirectorMeta`]
+#[derive(Resource)]
+pub struct Director {
+    pub meta: DirectorMeta,
+    /// How well the players are doing, from 0 while they are struggling to 1 while they are doing
+    /// well
+    pub intensity: f32,
+    /// The damage the players have taken recently, as a fraction of their max health
+    recent_damage: f32,
+}
+
+impl Director {
+    pub fn new(meta: DirectorMeta) -> Self {
+        Self {
+            meta,
+            intensity: START_INTENSITY,
+            recent_damage: 0.0,
+        }
+    }
+
+    /// The size of a survival wave that would be `size` enemies without the director
+    pub fn wave_size(&self, size: u32) -> u32 {
+        let scale = self.meta.wave_size_scale.at(self.intensity);
+        ((size as f32 * scale).round() as u32).max(1)
+    }
+
+    /// The number of enemies that may go after the players at once
+    pub fn attack_tokens(&self) -> usize {
+        (self.meta.attack_tokens.at(self.intensity).round() as usize).max(1)
+    }
+
+    /// The chance of a defeated enemy dropping the [drop item](DirectorMeta::drop_item)
+    pub fn drop_chance(&self) -> f32 {
+        self.meta.drop_chance.at(self.intensity)
+    }
+
+    /// Move the intensity towards how well the players are doing, given the fraction of health they
+    /// have left and the fraction of their max health they have just lost
+    fn update(&mut self, health: f32, damage: f32, delta: f32) {
+        self.recent_damage =
+            self.recent_damage * 0.5f32.powf(delta / RECENT_DAMAGE_HALF_LIFE) + damage;
+
+        let target = (health - self.recent_damage).clamp(0.0, 1.0);
+        let max_change = INTENSITY_CHANGE_RATE * delta;
+        self.intensity += (target - self.intensity).clamp(-max_change, max_change);
+    }
+}
+
+/// Start the director of a level when it's loaded, if it has one
+fn start_director(mut commands: Commands, level: Res<LevelMeta>) {
+    if !level.is_changed() {
+        return;
+    }
+
+    if let Some(director) = &level.director {
+        commands.insert_resource(Director::new(director.clone()));
+    } else {
+        commands.remove_resource::<Director>();
+    }
+}
+
+fn end_director(mut commands: Commands) {
+    commands.remove_resource::<Director>();
+}
+
+/// Follow how well the players are doing
+fn update_director(
+    mut director: ResMut<Director>,
+    mut damage_events: EventReader<HitLanded>,
+    players: Query<(&Health, &Stats), With<Player>>,
+) {
+    let max_health = |stats: &Stats| stats.max_health.max(1) as f32;
+
+    let damage = damage_events
+        .iter()
+        .filter_map(|event| players.get(event.damaged_entity).ok().map(|p| (event, p)))
+        .map(|(event, (_, stats))| event.damage as f32 / max_health(stats))
+        .sum::<f32>();
+
+    let player_count = players.iter().count();
+    if player_count == 0 {
+        return;
+    }
+    let health = players
+        .iter()
+        .map(|(health, stats)| (**health).max(0) as f32 / max_health(stats))
+        .sum::<f32>()
+        / player_count as f32;
+
+    director.update(
+        health,
+        damage / player_count as f32,
+        GAMEPLAY_STEP.as_secs_f32(),
+    );
+}
+
+/// Drop the director's item where enemies have been defeated, by chance
+fn drop_items(
+    mut commands: Commands,
+    director: Res<Director>,
+    defeated_enemies: Query<&Transform, (With<Enemy>, Added<Dying>)>,
+    mut items_assets: ResMut<Assets<ItemMeta>>,
+    mut active_scripts: ResMut<ActiveScripts>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Some(item_handle) = &director.meta.drop_item_handle else {
+        return;
+    };
+    if !items_assets.contains(item_handle) {
+        return;
+    }
+
+    let drop_chance = director.drop_chance().clamp(0.0, 1.0) as f64;
+    for transform in &defeated_enemies {
+        if !rng.gen_bool(drop_chance) {
+            continue;
+        }
+
+        let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
+        let item_spawn_meta = ItemSpawnMeta {
+            location: transform.translation - ground_offset,
+            item: String::new(),
+            item_handle: item_handle.clone(),
+        };
+        let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+        ItemBundle::spawn(
+            item_commands,
+            &item_spawn_meta,
+            &mut items_assets,
+            &mut active_scripts,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::DirectorBounds;
+
+    use super::*;
+
+    #[test]
+    fn intensity_follows_the_players_slowly() {
+        let bounds = |struggling, doing_well| DirectorBounds {
+            struggling,
+            doing_well,
+        };
+        let mut director = Director::new(DirectorMeta {
+            wave_size_scale: bounds(0.5, 1.5),
+            attack_tokens: bounds(1.0, 3.0),
+            drop_item: None,
+            drop_item_handle: None,
+            drop_chance: bounds(0.5, 0.0),
+        });
+        assert_eq!(director.wave_size(4), 4);
+        assert_eq!(director.attack_tokens(), 2);
+
+        // A big hit only moves the intensity a little at a time
+        director.update(0.6, 0.5, 1.0);
+        assert!((director.intensity - 0.4).abs() < 1e-4);
+
+        // Players at full health that haven't been hit in a while are doing well
+        for _ in 0..100 {
+            director.update(1.0, 0.0, 1.0);
+        }
+        assert!(director.intensity > 0.99);
+        assert_eq!(director.wave_size(4), 6);
+        assert_eq!(director.attack_tokens(), 3);
+        assert!(director.drop_chance() < 0.01);
+    }
+}
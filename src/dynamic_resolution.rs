@@ -0,0 +1,173 @@
+//! Dynamic resolution, which lowers the resolution that the levels are rendered at on hardware that
+//! can't keep up with the frame rate.
+//!
+//! When the [`DynamicResolution`](crate::metadata::DynamicResolution) setting has a target, the
+//! frame times are followed as a moving average. While they are slower than the target, the
+//! [`RenderScale`] of the post-processing pass is lowered one step at a time, and once there is
+//! enough headroom again it's raised back up.
+//! The menus are drawn over the window at its full resolution, so only the levels get blurrier.
+//!
+//! The frame rate limit and the lower frame rate in the background also slow the frames down, so
+//! the target is capped at the limit, and the scale is left as it is while the window isn't
+//! focused.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    focus::WindowFocus,
+    metadata::{Settings, VideoSettings},
+    post_processing::RenderScale,
+};
+
+/// The lowest part of the window's resolution that the levels are rendered at
+const MIN_SCALE: f32 = 0.5;
+
+/// How much the render scale changes at a time
+const SCALE_STEP: f32 = 0.1;
+
+/// How much of the newest frame time is mixed into the average frame time
+const FRAME_TIME_SMOOTHING: f32 = 0.05;
+
+/// How much slower than the target the average frame time has to be to lower the scale
+const SLOW_FRAME_MARGIN: f32 = 1.1;
+
+/// How much faster than the target the average frame time has to be to raise the scale again
+const HEADROOM_MARGIN: f32 = 0.75;
+
+/// How long to wait after changing the scale before changing it again, in seconds, so that the
+/// frame times have settled at the new scale
+const SCALE_CHANGE_DELAY: f32 = 1.0;
+
+pub struct DynamicResolutionPlugin;
+
+impl Plugin for DynamicResolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameTimes>().add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_render_scale.run_if_resource_exists::<Settings>(),
+        );
+    }
+}
+
+/// Follows the frame times to decide the render scale
+#[derive(Resource)]
+struct FrameTimes {
+    /// The target frame time in seconds, if the resolution is dynamic
+    target: Option<f32>,
+    /// The moving average of the frame times, in seconds
+    average: f32,
+    /// The time left until the scale may change again, in seconds
+    delay: f32,
+}
+
+impl Default for FrameTimes {
+    fn default() -> Self {
+        Self {
+            target: None,
+            average: 0.0,
+            delay: SCALE_CHANGE_DELAY,
+        }
+    }
+}
+
+impl FrameTimes {
+    /// Set the target frame rate from the video settings
+    fn set_target(&mut self, video: &VideoSettings) {
+        let target_fps = match (
+            video.dynamic_resolution.target_fps(),
+            video.frame_rate_limit.fps(),
+        ) {
+            (Some(target), Some(limit)) => Some(target.min(limit)),
+            (target, _) => target,
+        };
+        let target = target_fps.map(|fps| 1.0 / fps as f32);
+
+        if self.target != target {
+            debug!(?target_fps, "Setting dynamic resolution target");
+            self.target = target;
+            self.average = target.unwrap_or_default();
+            self.delay = SCALE_CHANGE_DELAY;
+        }
+    }
+
+    /// Add the time of a frame, and get the render scale that follows from the frame times
+    fn update(&mut self, frame_time: f32, scale: f32) -> f32 {
+        let Some(target) = self.target else {
+            return 1.0;
+        };
+
+        self.average += (frame_time - self.average) * FRAME_TIME_SMOOTHING;
+        self.delay -= frame_time;
+        if self.delay > 0.0 {
+            return scale;
+        }
+
+        let new_scale = if self.average > target * SLOW_FRAME_MARGIN {
+            (scale - SCALE_STEP).max(MIN_SCALE)
+        } else if self.average < target * HEADROOM_MARGIN {
+            (scale + SCALE_STEP).min(1.0)
+        } else {
+            scale
+        };
+        if new_scale != scale {
+            self.delay = SCALE_CHANGE_DELAY;
+        }
+        new_scale
+    }
+}
+
+fn update_render_scale(
+    mut frame_times: ResMut<FrameTimes>,
+    mut render_scale: ResMut<RenderScale>,
+    settings: Res<Settings>,
+    focus: Res<WindowFocus>,
+    time: Res<Time>,
+) {
+    if settings.is_changed() {
+        frame_times.set_target(&settings.video);
+    }
+    if !**focus {
+        return;
+    }
+
+    let scale = frame_times.update(time.delta_seconds(), **render_scale);
+    if **render_scale != scale {
+        debug!(scale, "Changing render scale");
+        **render_scale = scale;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::DynamicResolution;
+
+    use super::*;
+
+    #[test]
+    fn scale_follows_the_frame_times() {
+        let mut frame_times = FrameTimes::default();
+        let video = VideoSettings {
+            dynamic_resolution: DynamicResolution::Fps60,
+            ..default()
+        };
+        frame_times.set_target(&video);
+
+        // Slow frames lower the scale one step at a time, down to the lowest scale
+        let mut scale = 1.0;
+        for _ in 0..1000 {
+            scale = frame_times.update(1.0 / 30.0, scale);
+        }
+        assert_eq!(scale, MIN_SCALE);
+
+        // Fast frames raise it back up
+        for _ in 0..1000 {
+            scale = frame_times.update(1.0 / 120.0, scale);
+        }
+        assert_eq!(scale, 1.0);
+
+        // Without a target the levels are rendered at the full resolution
+        frame_times.set_target(&VideoSettings::default());
+        assert_eq!(frame_times.update(1.0 / 30.0, 0.5), 1.0);
+    }
+}
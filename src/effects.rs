@@ -0,0 +1,211 @@
+//! Visual effects played on combat events, like the sparks of hits and the dust of landings.
+//!
+//! Every effect of the game's [`EffectsMeta`](crate::metadata::EffectsMeta) is a burst of sprites
+//! from a spritesheet, which each play an animation once while flying away from the center of the
+//! burst. Heavy hits and critical hits can have their own sparks, and hits also spill blood when the
+//! `blood_effects` feature is enabled. Effects are spawned many times a second in busy fights, so
+//! their sprites are pooled.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    animation::{AnimatedSpriteSheetBundle, Animation},
+    attack::AttackDamageSystem,
+    combat_events::{Blocked, HitLanded},
+    consts,
+    fighter_state::Thrown,
+    lifetime::Lifetime,
+    metadata::{EffectMeta, FighterMeta, GameMeta},
+    movement::LinearVelocity,
+    pool::{EntityPoolCommands, PoolKind},
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::COLLISIONS,
+            play_hit_effects
+                .run_in_state(GameState::InGame)
+                .after(AttackDamageSystem),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            play_landing_effects.run_in_state(GameState::InGame),
+        );
+    }
+}
+
+/// Marker for the sprites of effects
+#[derive(Component)]
+pub struct Effect;
+
+/// Play the sparks, and the blood, of the hits where they land, and the sparks of the blocks that
+/// still chip away at the health of the fighters
+fn play_hit_effects(
+    mut pool: EntityPoolCommands,
+    mut damage_events: EventReader<HitLanded>,
+    mut blocked_events: EventReader<Blocked>,
+    targets: Query<(&GlobalTransform, Option<&Handle<FighterMeta>>)>,
+    attacks: Query<&GlobalTransform>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+) {
+    let Some(effects) = &game.effects else {
+        return;
+    };
+
+    for event in damage_events.iter() {
+        // Status effects like burning hurt the fighters themselves, without them being hit
+        if event.damageing_entity == event.damaged_entity {
+            continue;
+        }
+        let Ok((transform, meta_handle)) = targets.get(event.damaged_entity) else {
+            continue;
+        };
+
+        let fighter = meta_handle.and_then(|handle| fighter_assets.get(handle));
+        let position = hit_position(transform, fighter, attacks.get(event.damageing_entity).ok());
+
+        let spark = match (&effects.critical_hit, &effects.heavy_hit) {
+            (Some(critical_hit), _) if event.critical => Some(critical_hit),
+            (_, Some(heavy_hit)) if event.damage >= effects.heavy_hit_damage => Some(heavy_hit),
+            _ => effects.hit.as_ref(),
+        };
+        if let Some(spark) = spark {
+            spawn_effect(&mut pool, spark, position);
+        }
+        if let Some(blood) = &effects.blood {
+            if game.features.blood_effects && event.damage > 0 {
+                spawn_effect(&mut pool, blood, position);
+            }
+        }
+    }
+
+    let Some(spark) = &effects.hit else {
+        return;
+    };
+    for event in blocked_events.iter().filter(|event| event.chip_damage > 0) {
+        let Ok((transform, meta_handle)) = targets.get(event.blocking_entity) else {
+            continue;
+        };
+
+        let fighter = meta_handle.and_then(|handle| fighter_assets.get(handle));
+        let position = hit_position(transform, fighter, attacks.get(event.attacking_entity).ok());
+        spawn_effect(&mut pool, spark, position);
+    }
+}
+
+/// Where a hit lands on a fighter: on its hurtbox, on the side of the attack
+pub fn hit_position(
+    transform: &GlobalTransform,
+    fighter: Option<&FighterMeta>,
+    attack_transform: Option<&GlobalTransform>,
+) -> Vec2 {
+    let mut position = transform.translation().truncate();
+    let mut half_width = 0.0;
+    if let Some(fighter) = fighter {
+        position.y += fighter.collision_offset;
+        half_width = fighter.hurtbox.size.x / 2.0;
+    }
+    if let Some(attack_transform) = attack_transform {
+        position.x +=
+            (attack_transform.translation().x - position.x).clamp(-half_width, half_width);
+    }
+    position
+}
+
+/// Kick up dust at the feet of the thrown fighters as they land
+fn play_landing_effects(
+    mut pool: EntityPoolCommands,
+    fighters: Query<(&Transform, &Thrown)>,
+    game: Res<GameMeta>,
+) {
+    let Some(landing) = game
+        .effects
+        .as_ref()
+        .and_then(|effects| effects.landing.as_ref())
+    else {
+        return;
+    };
+
+    for (transform, thrown) in &fighters {
+        if thrown.timer.just_finished() {
+            spawn_effect(&mut pool, landing, transform.translation.truncate());
+        }
+    }
+}
+
+/// Play an effect, centered on the position
+pub fn spawn_effect(pool: &mut EntityPoolCommands, effect: &EffectMeta, position: Vec2) {
+    let spritesheet = &effect.spritesheet;
+    let Some(clip) = spritesheet.animations.get(&effect.animation) else {
+        return;
+    };
+    if spritesheet.atlas_handle.is_empty() {
+        return;
+    }
+    let seconds = (clip.frames.end - clip.frames.start) as f32 * spritesheet.animation_fps;
+
+    for (index, direction) in burst_directions(effect.count).enumerate() {
+        let mut animation =
+            Animation::new(spritesheet.animation_fps, spritesheet.animations.clone());
+        animation.play(&effect.animation, false);
+        let translation = (position + direction * effect.spread).extend(consts::EFFECTS_Z);
+
+        pool.spawn(
+            PoolKind::Effect,
+            (
+                AnimatedSpriteSheetBundle {
+                    sprite_sheet: SpriteSheetBundle {
+                        // Bursts with several images use all of them
+                        texture_atlas: spritesheet.atlas_handle
+                            [index % spritesheet.atlas_handle.len()]
+                        .clone(),
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                    animation,
+                },
+                LinearVelocity(direction * effect.speed),
+                Lifetime(Timer::from_seconds(seconds, TimerMode::Once)),
+                Effect,
+                Name::new("Effect"),
+            ),
+        );
+    }
+}
+
+/// The directions of the sprites of a burst, spread evenly around it starting from straight up
+fn burst_directions(count: usize) -> impl Iterator<Item = Vec2> {
+    (0..count).map(move |index| {
+        let angle = TAU * index as f32 / count as f32;
+        Vec2::new(angle.sin(), angle.cos())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bursts_are_spread_evenly() {
+        let directions = burst_directions(4).collect::<Vec<_>>();
+        let expected = [Vec2::Y, Vec2::X, -Vec2::Y, -Vec2::X];
+        assert_eq!(directions.len(), expected.len());
+        for (direction, expected) in directions.iter().zip(expected) {
+            assert!((*direction - expected).length() < 1e-4);
+        }
+
+        assert_eq!(burst_directions(1).collect::<Vec<_>>(), vec![Vec2::Y]);
+        assert_eq!(burst_directions(0).count(), 0);
+    }
+}
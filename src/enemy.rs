@@ -1,26 +1,133 @@
 use bevy::prelude::*;
 
 use crate::{
-    animation::Facing,
+    animation::{Animation, Facing},
     consts,
-    metadata::{FighterMeta, FighterSpawnMeta},
+    damage::Health,
+    fighter::{AvailableAttacks, Stats},
+    metadata::{BossPhaseMeta, FighterMeta, FighterSpawnMeta},
 };
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Enemy;
 
-/// Temporary marker component used to differentiate bosses from other fighters.
-#[derive(Component)]
-pub struct Boss;
+/// Component used to differentiate bosses from other fighters, with the phase of the fight that
+/// they are in.
+///
+/// Bosses start the [phases](BossPhaseMeta) of their fighter as their health runs low.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Boss {
+    /// The number of phases that the boss has started
+    pub phase: usize,
+    /// The names of the attacks that the boss takes turns with in its phase, if it has a pattern
+    pub pattern: Vec<String>,
+    /// The number of attacks of the pattern that the boss has gone after the players with
+    pub turn: usize,
+}
+
+impl Boss {
+    /// Pick the next attack of the boss's pattern as its current attack, if it has a pattern
+    pub fn take_turn(&mut self, available_attacks: &mut AvailableAttacks) {
+        if self.pattern.is_empty() {
+            return;
+        }
+        let name = &self.pattern[self.turn % self.pattern.len()];
+        self.turn += 1;
+
+        let attacks = &mut available_attacks.attacks;
+        if let Some(index) = attacks.iter().position(|attack| &attack.name == name) {
+            let attack = attacks.remove(index);
+            attacks.push(attack);
+        }
+    }
+}
 
 /// X coordinate of the level that requires to be trespassed in order for the enemies to move.
 /// For simplicy, once a given trip point is trespassed for the first time, it's set to f32::MIN.
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct TripPointX(pub f32);
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct SpawnLocationX(pub f32);
 
+/// The number of `phases` that a boss has started at `health`, out of `max_health`
+fn reached_phase(phases: &[BossPhaseMeta], health: i32, max_health: i32) -> usize {
+    phases
+        .iter()
+        .take_while(|phase| health as f32 <= phase.health * max_health as f32)
+        .count()
+}
+
+/// Start the phases of the bosses as their health runs low, spawning their minions
+pub fn start_boss_phases(
+    mut commands: Commands,
+    mut bosses: Query<(
+        &mut Boss,
+        &Health,
+        &mut Stats,
+        &mut Animation,
+        &Transform,
+        &Handle<FighterMeta>,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for (mut boss, health, mut stats, mut animation, transform, meta_handle) in &mut bosses {
+        // Bosses that are knocked out don't start a last stand
+        if **health <= 0 {
+            continue;
+        }
+        let Some(fighter) = fighter_assets.get(meta_handle) else {
+            continue;
+        };
+        let phase = reached_phase(&fighter.boss_phases, **health, stats.max_health);
+        if phase <= boss.phase {
+            continue;
+        }
+
+        // Big hits can start several phases at once, which each start in turn
+        for meta in &fighter.boss_phases[boss.phase..phase] {
+            debug!(boss = %fighter.name, health = meta.health, "Starting boss phase");
+
+            if !meta.animations.is_empty() {
+                animation.animations = fighter.spritesheet.animations.clone();
+                animation.animations.extend(meta.animations.clone());
+
+                // The clip being played might not have the same frames anymore
+                if let Some(current) = animation.current_animation.clone() {
+                    if meta.animations.contains_key(&current) {
+                        let repeating = animation.is_repeating();
+                        animation.play(&current, repeating);
+                    }
+                }
+            }
+            if !meta.attacks.is_empty() {
+                boss.pattern = meta.attacks.clone();
+                boss.turn = 0;
+            }
+            if let Some(movement_speed) = meta.movement_speed {
+                stats.movement_speed = movement_speed;
+            }
+
+            for minion in &meta.minions {
+                let ground_offset = Vec3::new(0.0, consts::GROUND_Y, 0.0);
+                commands.spawn(EnemyBundle::new(&FighterSpawnMeta {
+                    fighter: minion.fighter.clone(),
+                    fighter_handle: minion.fighter_handle.clone(),
+                    location: transform.translation - ground_offset + minion.offset.extend(0.0),
+                    // Minions join the fight right away
+                    trip_point_x: f32::MIN,
+                    boss: false,
+                }));
+            }
+        }
+        boss.phase = phase;
+    }
+}
+
 #[derive(Bundle)]
 pub struct EnemyBundle {
     enemy: Enemy,
@@ -52,3 +159,27 @@ impl EnemyBundle {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bosses_start_phases_as_their_health_runs_low() {
+        let phase = |health| BossPhaseMeta {
+            health,
+            animations: default(),
+            attacks: default(),
+            movement_speed: None,
+            minions: default(),
+        };
+        let phases = [phase(0.6), phase(0.3)];
+
+        assert_eq!(reached_phase(&phases, 100, 100), 0);
+        assert_eq!(reached_phase(&phases, 60, 100), 1);
+        assert_eq!(reached_phase(&phases, 45, 100), 1);
+        // A big hit starts both phases at once
+        assert_eq!(reached_phase(&phases, 10, 100), 2);
+        assert_eq!(reached_phase(&[], 10, 100), 0);
+    }
+}
@@ -4,21 +4,40 @@ use bevy::prelude::*;
 use rand::Rng;
 
 use crate::{
+    activity::Dormant,
     animation::Facing,
     consts::{self, ENEMY_MAX_ATTACK_DISTANCE, ENEMY_MIN_ATTACK_DISTANCE, ENEMY_TARGET_MAX_OFFSET},
-    enemy::{Boss, Enemy, TripPointX},
+    damage::Health,
+    director::Director,
+    enemy::{Boss, Enemy, SpawnLocationX, TripPointX},
     fighter::AvailableAttacks,
     fighter_state::{
-        BossBombThrow, Idling, Moving, ProjectileAttacking, Punching, StateTransition,
-        StateTransitionIntents,
+        BossBombThrow, Grappling, GroundSlam, Idling, Moving, ProjectileAttacking, Punching,
+        StateTransition, StateTransitionIntents,
     },
-    metadata::{ItemKind, ItemMeta},
+    flying::Flying,
+    metadata::{AttackMeta, ItemKind, ItemMeta, LevelMeta, RangedAttackMeta},
     player::Player,
+    rng::GameRng,
+    spirit::Distracted,
+    training::DummyStandingStill,
     Stats,
 };
 
 //maybe implement as plugin
 
+/// The attacks that the enemies make from a distance, and that can be
+/// [ranged](crate::metadata::AttackMeta::ranged) to keep it
+pub const RANGED_ATTACKS: [&str; 2] = ["projectile", "bomb_throw"];
+
+/// The range that an enemy keeps from the players with its attack, if it's a ranged attack
+fn keeps_distance(attack: &AttackMeta) -> Option<&RangedAttackMeta> {
+    attack
+        .ranged
+        .as_ref()
+        .filter(|_| RANGED_ATTACKS.contains(&attack.name.as_str()))
+}
+
 /// A place that an enemy fighter is going to move to, in an attempt to attack a player.
 ///
 /// The attack distance is for randomization purposes, and it's the distance that triggers the
@@ -33,6 +52,34 @@ pub struct WalkTarget {
     pub player_pos: Vec2,
 }
 
+/// Defeat the enemies between the players and the next stop point, or all of the remaining enemies
+/// after the last stop point, for the [`auto_win`](crate::config::CheatFlags::auto_win) cheat
+pub fn auto_win_fights(
+    level_meta: Res<LevelMeta>,
+    players: Query<&Transform, With<Player>>,
+    mut enemies: Query<(&SpawnLocationX, &mut Health), With<Enemy>>,
+) {
+    let Some(max_player_x) = players
+        .iter()
+        .map(|transform| transform.translation.x)
+        .max_by(f32::total_cmp)
+    else {
+        return;
+    };
+    let next_stop_point = level_meta
+        .stop_points
+        .iter()
+        .copied()
+        .find(|point_x| *point_x >= max_player_x)
+        .unwrap_or(f32::MAX);
+
+    for (SpawnLocationX(spawn_x), mut health) in &mut enemies {
+        if *spawn_x <= next_stop_point && **health > 0 {
+            **health = 0;
+        }
+    }
+}
+
 // For enemys without current target, pick a new spot near the player as target
 ///
 /// This is added to the [`crate::fighter_state::FighterStateCollectSystems`] to collect figher
@@ -40,13 +87,35 @@ pub struct WalkTarget {
 pub fn set_move_target_near_player(
     mut commands: Commands,
     mut enemies_query: Query<
-        (Entity, &mut TripPointX, &Transform, &AvailableAttacks),
-        (With<Enemy>, With<Idling>, Without<WalkTarget>),
+        (
+            Entity,
+            &mut TripPointX,
+            &Transform,
+            &mut AvailableAttacks,
+            Option<&mut Boss>,
+        ),
+        (
+            With<Enemy>,
+            With<Idling>,
+            Without<WalkTarget>,
+            Without<Dormant>,
+            Without<DummyStandingStill>,
+            Without<Distracted>,
+        ),
     >,
     player_query: Query<&Transform, With<Player>>,
+    approaching_enemies: Query<(), (With<Enemy>, With<WalkTarget>)>,
     items_assets: Res<Assets<ItemMeta>>,
+    director: Option<Res<Director>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    // The director limits how many enemies go after the players at once
+    let mut attack_tokens = director.map_or(usize::MAX, |director| {
+        director
+            .attack_tokens()
+            .saturating_sub(approaching_enemies.iter().count())
+    });
+
     let p_transforms = player_query.iter().collect::<Vec<_>>();
     let max_player_x = p_transforms
         .iter()
@@ -54,13 +123,23 @@ pub fn set_move_target_near_player(
         .max_by(f32::total_cmp);
 
     if let Some(max_player_x) = max_player_x {
-        for (e_entity, mut e_trip_point_x, e_transform, available_attacks) in
+        for (e_entity, mut e_trip_point_x, e_transform, mut available_attacks, boss) in
             enemies_query.iter_mut()
         {
             if let Some(p_transform) = choose_player(&p_transforms, e_transform) {
                 if max_player_x > e_trip_point_x.0 {
                     e_trip_point_x.0 = f32::MIN;
 
+                    if attack_tokens == 0 {
+                        continue;
+                    }
+                    attack_tokens -= 1;
+
+                    // Bosses take turns with the attacks of their phase
+                    if let Some(mut boss) = boss {
+                        boss.take_turn(&mut available_attacks);
+                    }
+
                     let mut x_offset =
                         rng.gen_range(-ENEMY_TARGET_MAX_OFFSET..ENEMY_TARGET_MAX_OFFSET);
                     let mut y_offset =
@@ -71,7 +150,24 @@ pub fn set_move_target_near_player(
 
                     match cur_attack.name.as_str() {
                         "projectile" | "bomb_throw" => {
-                            if let ItemKind::Throwable {
+                            let away_from_player =
+                                if p_transform.translation.x > e_transform.translation.x {
+                                    -1.
+                                } else {
+                                    1.
+                                };
+
+                            if let Some(ranged) = keeps_distance(cur_attack) {
+                                // Keep the distance from the player, on the side the enemy is on
+                                x_offset += ranged.range * away_from_player;
+                                y_offset = 0.0;
+                            } else if let Some(projectile) = &cur_attack.projectile {
+                                // Line up with the player to shoot it from halfway across the range
+                                // of the projectile
+                                x_offset +=
+                                    projectile.speed * projectile.lifetime * 0.5 * away_from_player;
+                                y_offset = 0.0;
+                            } else if let ItemKind::Throwable {
                                 lifetime,
                                 throw_velocity,
                                 gravity,
@@ -87,13 +183,7 @@ pub fn set_move_target_near_player(
                                 let t = lifetime * 0.65;
 
                                 //Change target offset to aim on player
-                                x_offset += throw_velocity.x
-                                    * t
-                                    * if p_transform.translation.x > e_transform.translation.x {
-                                        -1.
-                                    } else {
-                                        1.
-                                    };
+                                x_offset += throw_velocity.x * t * away_from_player;
 
                                 y_offset -= (throw_velocity.y * t) + (0.5 * -gravity * t.powi(2));
                             }
@@ -160,10 +250,18 @@ pub fn emit_enemy_intents(
             &mut StateTransitionIntents,
             Option<&Boss>,
             &AvailableAttacks,
+            Option<&Flying>,
+        ),
+        // All awake enemies that are either moving or idling
+        (
+            With<Enemy>,
+            Or<(With<Idling>, With<Moving>)>,
+            Without<Dormant>,
+            Without<DummyStandingStill>,
+            Without<Distracted>,
         ),
-        // All enemies that are either moving or idling
-        (With<Enemy>, Or<(With<Idling>, With<Moving>)>),
     >,
+    players: Query<&Transform, (With<Player>, Without<Enemy>)>,
     mut commands: Commands,
 ) {
     for (
@@ -175,9 +273,12 @@ pub fn emit_enemy_intents(
         mut intents,
         maybe_boss,
         available_attacks,
+        flying,
     ) in &mut query
     {
-        let position = transform.translation.truncate();
+        // Flying enemies move over the ground under them, like the others walk on it
+        let position =
+            transform.translation.truncate() - Vec2::Y * flying.map_or(0.0, |flying| flying.height);
         let velocity = (target.position - position).normalize() * stats.movement_speed;
 
         // If we're close to our target
@@ -204,11 +305,19 @@ pub fn emit_enemy_intents(
                     Facing::Left
                 };
 
-                intents.push_back(StateTransition::new(
-                    BossBombThrow::default(),
-                    BossBombThrow::PRIORITY,
-                    false,
-                ))
+                if available_attacks.current_attack().name == "ground_slam" {
+                    intents.push_back(StateTransition::new(
+                        GroundSlam::default(),
+                        GroundSlam::PRIORITY,
+                        false,
+                    ))
+                } else {
+                    intents.push_back(StateTransition::new(
+                        BossBombThrow::default(),
+                        BossBombThrow::PRIORITY,
+                        false,
+                    ))
+                }
             } else {
                 match available_attacks.current_attack().name.as_str() {
                     "punch" => intents.push_back(StateTransition::new(
@@ -216,6 +325,20 @@ pub fn emit_enemy_intents(
                         Punching::PRIORITY,
                         false,
                     )),
+                    "grab" => {
+                        // Face the player, who the grab has to reach
+                        *facing = if target.player_pos.x > position.x {
+                            Facing::Right
+                        } else {
+                            Facing::Left
+                        };
+
+                        intents.push_back(StateTransition::new(
+                            Grappling::default(),
+                            Grappling::PRIORITY,
+                            false,
+                        ));
+                    }
                     "projectile" => {
                         // Face the player
                         *facing = if target.player_pos.x > position.x {
@@ -233,6 +356,15 @@ pub fn emit_enemy_intents(
                     _ => {}
                 }
             }
+        // If a player has come too close for the spot that the enemy is walking to, pick a new spot
+        // at its range from them
+        } else if keeps_distance(available_attacks.current_attack()).map_or(false, |ranged| {
+            players.iter().any(|player| {
+                player.translation.truncate().distance(position)
+                    < ranged.range - ENEMY_TARGET_MAX_OFFSET
+            })
+        }) {
+            commands.entity(entity).remove::<WalkTarget>();
         // If we aren't near our target yet
         } else {
             // Face the direction we're moving
@@ -1,5 +1,5 @@
-use bevy::prelude::*;
-use rand::prelude::SliceRandom;
+use bevy::{prelude::*, reflect::FromReflect};
+use rand::Rng;
 use serde::Deserialize;
 
 use crate::attack::Hurtbox;
@@ -10,11 +10,16 @@ use crate::{
     camera::YSort,
     collision::{BodyLayers, PhysicsBundle},
     damage::{Damageable, Health},
-    enemy::Enemy,
+    enemy::{Boss, Enemy, SpawnLocationX, TripPointX},
     fighter_state::{Idling, StateTransitionIntents},
+    flying::Flying,
     metadata::{AttackMeta, FighterMeta},
     movement::LinearVelocity,
-    player::Player,
+    palette::PaletteSwap,
+    player::{Player, PlayerIndex},
+    rng::GameRng,
+    team::Team,
+    tint::Tint,
 };
 
 pub struct FighterPlugin;
@@ -22,6 +27,16 @@ pub struct FighterPlugin;
 impl Plugin for FighterPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<AvailableAttacks>()
+            .register_type::<Stats>()
+            .register_type::<WeightClass>()
+            .register_type::<DodgeStats>()
+            .register_type::<Inventory>()
+            .register_type::<Player>()
+            .register_type::<PlayerIndex>()
+            .register_type::<Enemy>()
+            .register_type::<Boss>()
+            .register_type::<TripPointX>()
+            .register_type::<SpawnLocationX>()
             .add_system_to_stage(CoreStage::PostUpdate, attachment_system);
     }
 }
@@ -44,6 +59,8 @@ pub struct ActiveFighterBundle {
     pub idling: Idling,
     pub velocity: LinearVelocity,
     pub available_attacks: AvailableAttacks,
+    pub tint: Tint,
+    pub team: Team,
 }
 
 /// Component that defines the currently available attacks on a fighter, modified at runtime when
@@ -58,20 +75,147 @@ impl AvailableAttacks {
     pub fn current_attack(&self) -> &AttackMeta {
         self.attacks.last().expect("No attacks available")
     }
+
+    /// The latest attack that shoots a projectile, if the fighter has one
+    pub fn projectile_attack(&self) -> Option<&AttackMeta> {
+        self.attacks
+            .iter()
+            .rev()
+            .find(|attack| attack.projectile.is_some())
+    }
 }
 
-#[derive(Component, Deserialize, Clone, Debug, Reflect)]
+#[derive(Component, Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[reflect(Component)]
 #[serde(deny_unknown_fields)]
 pub struct Stats {
     pub max_health: i32,
     pub movement_speed: f32,
+    #[serde(default)]
+    pub weight: WeightClass,
+    /// How heavy the fighter is when it's knocked back, which defaults to the mass of its
+    /// [weight class](WeightClass::mass)
+    #[serde(default)]
+    pub mass: Option<f32>,
+    /// How far and how often the fighter can dodge
+    #[serde(default)]
+    pub dodge: DodgeStats,
+    /// The chance of the fighter's hits being critical, from `0.0` to `1.0`
+    #[serde(default)]
+    pub crit_chance: f32,
+    /// How many times the damage of a hit the fighter's critical hits deal
+    #[serde(default = "default_crit_multiplier")]
+    pub crit_multiplier: f32,
+    /// The chance added to the `crit_chance` for the hits on fighters that are facing away
+    #[serde(default)]
+    pub back_crit_chance: f32,
+    /// How far the damage of the fighter's hits varies up or down, as a part of the damage
+    #[serde(default)]
+    pub damage_variance: f32,
+}
+
+fn default_crit_multiplier() -> f32 {
+    1.5
+}
+
+impl Stats {
+    /// The mass that the knockback impulses of the hits on the fighter are divided by
+    pub fn mass(&self) -> f32 {
+        self.mass.unwrap_or_else(|| self.weight.mass())
+    }
+
+    /// Roll the damage of a hit of the fighter, which varies with the `damage_variance` and is
+    /// multiplied if the hit is critical, and whether the hit is critical.
+    ///
+    /// The random numbers are only drawn for the fighters that have a crit chance or a damage
+    /// variance, so that the hits of the others don't change the sequence of the
+    /// [`GameRng`](crate::rng::GameRng).
+    pub fn roll_damage(&self, damage: i32, from_behind: bool, rng: &mut impl Rng) -> (i32, bool) {
+        if damage <= 0 {
+            return (damage, false);
+        }
+
+        let mut rolled = damage as f32;
+        if self.damage_variance > 0.0 {
+            let variance = self.damage_variance.min(1.0);
+            rolled *= rng.gen_range(1.0 - variance..=1.0 + variance);
+        }
+
+        let mut crit_chance = self.crit_chance;
+        if from_behind {
+            crit_chance += self.back_crit_chance;
+        }
+        let critical = crit_chance > 0.0 && rng.gen_bool(crit_chance.min(1.0) as f64);
+        if critical {
+            rolled *= self.crit_multiplier;
+        }
+
+        (rolled.round() as i32, critical)
+    }
+}
+
+/// How a player rolls out of the way with the dodge button
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct DodgeStats {
+    /// How far the fighter rolls
+    pub distance: f32,
+    /// How long the roll takes, in seconds
+    pub duration: f32,
+    /// How many gameplay steps at the start of the roll the fighter can't be hit for
+    pub invincible_frames: u32,
+    /// The time from the start of a dodge until the fighter can dodge again, in seconds
+    pub cooldown: f32,
+    /// The animation played while rolling
+    pub animation: String,
+}
+
+impl Default for DodgeStats {
+    fn default() -> Self {
+        Self {
+            distance: 72.0,
+            duration: 0.25,
+            invincible_frames: 9,
+            cooldown: 0.75,
+            animation: "running".into(),
+        }
+    }
+}
+
+/// How heavy a fighter is, which decides who can pick it up and throw it
+#[derive(
+    Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Reflect, FromReflect,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightClass {
+    Light,
+    #[default]
+    Medium,
+    Heavy,
+}
+
+impl WeightClass {
+    /// Whether a fighter of this weight class can throw a fighter of the `other` weight class, which
+    /// has to be lighter than it
+    pub fn can_throw(self, other: WeightClass) -> bool {
+        other < self
+    }
+
+    /// The mass of the fighters of this weight class, unless their stats give their own
+    pub fn mass(self) -> f32 {
+        match self {
+            WeightClass::Light => 0.6,
+            WeightClass::Medium => 1.0,
+            WeightClass::Heavy => 3.0,
+        }
+    }
 }
 
 /// The player inventory.
 ///
 /// A player may be holding one item
-#[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
+#[derive(Component, Clone, Debug, Default, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct Inventory(pub Option<ItemMeta>);
 
 impl Default for Stats {
@@ -79,6 +223,13 @@ impl Default for Stats {
         Stats {
             max_health: 100,
             movement_speed: 17000.,
+            weight: default(),
+            mass: None,
+            dodge: default(),
+            crit_chance: 0.0,
+            crit_multiplier: default_crit_multiplier(),
+            back_crit_chance: 0.0,
+            damage_variance: 0.0,
         }
     }
 }
@@ -92,6 +243,8 @@ impl ActiveFighterBundle {
         transform: &Transform,
         player: Option<&Player>,
         enemy: Option<&Enemy>,
+        palette: Option<usize>,
+        rng: &mut GameRng,
     ) {
         let body_layers = if player.is_some() {
             BodyLayers::PLAYER
@@ -101,6 +254,7 @@ impl ActiveFighterBundle {
             unreachable!();
         };
 
+        let (texture_atlas, palette_swap) = fighter.spritesheet.choose_atlas(palette, rng);
         let active_fighter_bundle = ActiveFighterBundle {
             name: Name::new(fighter.name.clone()),
             animated_spritesheet_bundle: AnimatedSpriteSheetBundle {
@@ -113,12 +267,7 @@ impl ActiveFighterBundle {
                         )),
                         ..default()
                     },
-                    texture_atlas: fighter
-                        .spritesheet
-                        .atlas_handle
-                        .choose(&mut rand::thread_rng())
-                        .unwrap()
-                        .clone(),
+                    texture_atlas,
                     transform: *transform,
                     ..Default::default()
                 },
@@ -140,6 +289,12 @@ impl ActiveFighterBundle {
             available_attacks: AvailableAttacks {
                 attacks: fighter.attacks.clone(),
             },
+            tint: default(),
+            team: if player.is_some() {
+                Team::players()
+            } else {
+                Team::enemies(fighter.team.as_deref())
+            },
         };
         let hurtbox = commands
             .spawn((
@@ -159,17 +314,21 @@ impl ActiveFighterBundle {
             .entity(entity)
             .insert(active_fighter_bundle)
             .push_children(&[hurtbox]);
+        if let Some(palette_swap) = palette_swap {
+            commands.entity(entity).insert(palette_swap);
+        }
+
+        if enemy.is_some() && fighter.flying.is_some() {
+            commands.entity(entity).insert(Flying::default());
+        }
 
         if let Some(attachment) = &fighter.attachment {
             //Clone fighter spritesheet
             let mut attachment_spritesheet = animated_spritesheet_bundle;
 
             //Change what's needed
-            attachment_spritesheet.sprite_sheet.texture_atlas = attachment
-                .atlas_handle
-                .choose(&mut rand::thread_rng())
-                .unwrap()
-                .clone();
+            let (texture_atlas, palette_swap) = attachment.choose_atlas(palette, rng);
+            attachment_spritesheet.sprite_sheet.texture_atlas = texture_atlas;
             attachment_spritesheet.animation =
                 Animation::new(attachment.animation_fps, attachment.animations.clone());
             attachment_spritesheet.sprite_sheet.transform = Transform::from_xyz(
@@ -189,6 +348,9 @@ impl ActiveFighterBundle {
                 })
                 .insert(Facing::default())
                 .id();
+            if let Some(palette_swap) = palette_swap {
+                commands.entity(attachment_ent).insert(palette_swap);
+            }
             commands.entity(entity).add_child(attachment_ent);
         }
     }
@@ -233,12 +395,34 @@ pub fn attachment_system(
 
             // Change position
             if attached.position_face {
-                transform.translation.x = if facing.is_left() {
-                    -transform.translation.x.abs()
-                } else {
-                    transform.translation.x.abs()
-                };
+                transform.translation.x = transform.translation.x.abs() * facing.sign();
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::rng::GameRng;
+
+    use super::*;
+
+    #[test]
+    fn critical_hits_deal_more_damage() {
+        let mut rng = GameRng::new(0);
+        let mut stats = Stats::default();
+        assert_eq!(stats.roll_damage(10, true, &mut rng), (10, false));
+
+        stats.back_crit_chance = 1.0;
+        assert_eq!(stats.roll_damage(10, false, &mut rng), (10, false));
+        assert_eq!(stats.roll_damage(10, true, &mut rng), (15, true));
+        // Hits without damage, like grabs, are never critical
+        assert_eq!(stats.roll_damage(0, true, &mut rng), (0, false));
+
+        stats.damage_variance = 0.2;
+        for _ in 0..100 {
+            let (damage, _) = stats.roll_damage(100, false, &mut rng);
+            assert!((80..=120).contains(&damage));
+        }
+    }
+}
@@ -2,30 +2,54 @@ use std::{collections::VecDeque, time::Duration};
 
 use bevy::{prelude::*, reflect::FromType, utils::HashSet};
 use bevy_mod_js_scripting::ActiveScripts;
-use bevy_rapier2d::prelude::CollisionGroups;
+use bevy_rapier2d::prelude::{ActiveCollisionTypes, ActiveEvents, CollisionGroups, Sensor};
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::{plugin::InputManagerSystem, prelude::ActionState};
 use rand::Rng;
 
 use crate::{
-    animation::{AnimatedSpriteSheetBundle, Animation, Facing},
-    attack::{Attack, Breakable},
+    afterimage::start_afterimages,
+    animation::{AnimatedSpriteSheetBundle, Animation, AnimationFinished, Facing},
+    attack::{Attack, AttackDamageSystem, Breakable, Hurtbox},
     audio::AnimationAudioPlayback,
+    camera::YSort,
+    coating::{Coated, CoatedAttack},
     collision::BodyLayers,
+    combat_events::{Blocked, HitLanded},
+    config::ENGINE_CONFIG,
     consts,
-    damage::{DamageEvent, Health},
-    enemy::{Boss, Enemy},
+    damage::{FighterDamage, Health},
+    enemy::{start_boss_phases, Boss, Enemy},
     enemy_ai,
-    fighter::{Attached, AvailableAttacks, Inventory},
+    fighter::{Attached, AvailableAttacks, DodgeStats, Inventory},
+    flying::Flying,
+    hit_stop::{HitStop, HitStopped},
     input::PlayerAction,
+    interaction::Interactable,
     item::{
-        AnimatedProjectile, Drop, Explodable, Item, ItemBundle, Projectile, ScriptItemGrabEvent,
-        ScriptItemThrowEvent,
+        AnimatedProjectile, Drop, Explodable, Item, ItemBundle, Projectile, ProjectileOwner,
+        ScriptItemGrabEvent, ScriptItemThrowEvent,
     },
     lifetime::Lifetime,
-    metadata::{AttackMeta, AudioMeta, FighterMeta, ItemKind, ItemMeta, ItemSpawnMeta},
-    movement::{AngularVelocity, Force, LinearVelocity},
+    menu_stack::MenuLayer,
+    metadata::{
+        AttackMeta, AudioMeta, BlockMeta, CancelWindow, FighterMeta, GameMeta, GrappleMeta,
+        ItemKind, ItemMeta, ItemSpawnMeta, KnockdownMeta, ThrownMeta,
+    },
+    movement::{
+        AngularVelocity, Force, Knockback, LinearVelocity, ThrowTrajectory, VelocitySystems,
+        VirtualZ,
+    },
     player::Player,
+    pool::{EntityPoolCommands, PoolKind},
+    projectile::ProjectileBundle,
+    rng::GameRng,
+    socket::{throw_offset, Socketed},
+    spatial_hash::SpatialHash,
+    sprite_atlas::ItemSprites,
+    team::{Team, Teams},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    tint::{Tint, TintSource, INVULNERABLE_COLOR},
     Collider, GameState, Stats,
 };
 
@@ -38,61 +62,167 @@ pub struct FighterStateCollectSystems;
 
 impl Plugin for FighterStatePlugin {
     fn build(&self, app: &mut App) {
-        app
-            // The collect systems
+        app.register_type::<Idling>()
+            .register_type::<Moving>()
+            .register_type::<Blocking>()
+            .register_type::<Dodging>()
+            .register_type::<Throwing>()
+            .register_type::<Slamming>()
+            .register_type::<Grabbing>()
+            .register_type::<Flopping>()
+            .register_type::<GroundSlam>()
+            .register_type::<BossBombThrow>()
+            .register_type::<Punching>()
+            .register_type::<Chaining>()
+            .register_type::<MeleeAttacking>()
+            .register_type::<Shooting>()
+            .register_type::<ProjectileAttacking>()
+            .register_type::<Holding>()
+            .register_type::<HitStun>()
+            .register_type::<Airborne>()
+            .register_type::<KnockedDown>()
+            .register_type::<GettingUp>()
+            .register_type::<Invulnerable>()
+            .register_type::<Grabbed>()
+            .register_type::<Grappling>()
+            .register_type::<Grappled>()
+            .register_type::<Thrown>()
+            .register_type::<TeamUpAttacking>()
+            .register_type::<ComboBreaking>()
+            .register_type::<Dying>()
+            // Player actions are collected every frame, even if there is no gameplay step in it, so
+            // that no button presses are missed. The intents are queued until the next step.
             .add_system_set_to_stage(
                 CoreStage::PreUpdate,
                 ConditionSet::new()
-                    .label(FighterStateCollectSystems)
                     .after(InputManagerSystem::Update)
                     .run_in_state(GameState::InGame)
-                    .with_system(collect_fighter_eliminations)
-                    .with_system(collect_hitstuns)
+                    // The buttons pressed in the menus aren't actions of the fighters
+                    .run_in_state(MenuLayer::None)
                     .with_system(collect_player_actions)
+                    .with_system(collect_escape_presses)
+                    .into(),
+            )
+            // The collect systems
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::TRANSITIONS,
+                ConditionSet::new()
+                    .label(FighterStateCollectSystems)
+                    .run_in_state(GameState::InGame)
+                    .with_system(collect_fighter_eliminations)
                     .with_system(
                         enemy_ai::set_move_target_near_player.pipe(enemy_ai::emit_enemy_intents),
                     )
                     .into(),
             )
+            // Hit stuns are collected right after the damage is dealt, so that the damage events
+            // are read in the same step that they are sent in.
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                ConditionSet::new()
+                    .after(AttackDamageSystem)
+                    .run_in_state(GameState::InGame)
+                    .with_system(collect_hitstuns)
+                    .with_system(collect_parries)
+                    .with_system(collect_thrown_hits)
+                    .with_system(wear_melee_weapons)
+                    .with_system(start_boss_phases)
+                    .into(),
+            )
             // The transition systems
-            .add_system_set_to_stage(
-                CoreStage::PreUpdate,
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::TRANSITIONS,
                 ConditionSet::new()
                     .after(FighterStateCollectSystems)
                     .run_in_state(GameState::InGame)
                     .with_system(transition_from_idle)
-                    .with_system(transition_from_chain)
+                    .with_system(transition_from_blocking)
+                    .with_system(transition_from_dodging)
                     .with_system(transition_from_flopping)
                     .with_system(transition_from_punching)
+                    .with_system(transition_from_chain)
                     .with_system(transition_from_ground_slam)
                     .with_system(transition_from_hitstun)
+                    .with_system(transition_from_airborne)
+                    .with_system(transition_from_knocked_down)
+                    .with_system(transition_from_getting_up)
                     .with_system(transition_from_melee_attacking)
                     .with_system(transition_from_shooting)
                     .with_system(transition_from_bomb_throw)
                     .with_system(transition_from_proj_attacking)
+                    .with_system(transition_from_grabbed)
+                    .with_system(transition_from_grappling)
+                    .with_system(transition_from_grappled)
+                    .with_system(transition_from_thrown)
+                    .with_system(transition_from_team_up_attacking)
+                    .with_system(transition_from_combo_breaking)
                     .into(),
             )
             // State handler systems
-            .add_system_set_to_stage(
-                CoreStage::Update,
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::STATES,
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
                     .with_system(idling)
-                    .with_system(chaining)
                     .with_system(flopping)
                     .with_system(punching)
+                    .with_system(chaining)
                     .with_system(ground_slam)
                     .with_system(moving)
+                    .with_system(blocking)
+                    .with_system(dodging)
+                    .with_system(update_dodge_cooldowns)
                     .with_system(throwing)
                     .with_system(grabbing)
+                    .with_system(slamming)
                     .with_system(hitstun)
+                    .with_system(airborne)
+                    .with_system(knocked_down)
+                    .with_system(drop_melee_weapons)
+                    .with_system(getting_up)
+                    .with_system(update_invulnerabilities)
                     .with_system(dying)
                     .with_system(melee_attacking)
                     .with_system(shooting)
                     .with_system(bomb_throw)
                     .with_system(projectile_attacking)
+                    .with_system(grabbed)
+                    .with_system(grappling)
+                    .with_system(grappled)
+                    .with_system(update_grab_immunities)
+                    .with_system(thrown)
+                    .with_system(team_up_attacking)
+                    .with_system(combo_breaking)
                     .into(),
+            )
+            // Grabbed fighters are carried after their holders have moved
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
+                carry_held_fighters
+                    .run_in_state(GameState::InGame)
+                    .after(VelocitySystems),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
+                hold_grappled_fighters
+                    .run_in_state(GameState::InGame)
+                    .after(VelocitySystems),
+            );
+
+        // Defeats the enemies before the fighter eliminations are collected
+        if ENGINE_CONFIG.cheats.auto_win {
+            app.add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                enemy_ai::auto_win_fights.run_in_state(GameState::InGame),
             );
+        }
     }
 }
 
@@ -195,6 +325,7 @@ impl StateTransitionIntents {
 
 /// Component indicating the player is idling
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Idling;
 impl Idling {
@@ -204,6 +335,7 @@ impl Idling {
 
 /// Component indicating the player is moving
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Moving {
     pub velocity: Vec2,
@@ -213,8 +345,60 @@ impl Moving {
     pub const ANIMATION: &'static str = "running";
 }
 
+/// Component indicating the player is holding the block button, which protects them from the hits
+/// they take until it's let go of
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Blocking {
+    /// How many gameplay steps the fighter has been blocking for
+    pub steps: u32,
+    /// Whether the fighter has parried a hit since it started blocking
+    pub parried: bool,
+}
+impl Blocking {
+    pub const PRIORITY: i32 = 12;
+
+    /// Whether a hit taken now is parried, which it is during the first
+    /// [`parry_frames`](BlockMeta::parry_frames) of the block
+    pub fn is_parrying(&self, meta: &BlockMeta) -> bool {
+        self.steps <= meta.parry_frames
+    }
+}
+
+/// Component indicating the player is dodging, rolling quickly in a direction with a few
+/// invincibility frames at the start of the roll
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Dodging {
+    /// The direction of the roll, which is the direction the player was holding, or the one they
+    /// were facing
+    pub direction: Vec2,
+    pub timer: Timer,
+    pub has_started: bool,
+}
+impl Dodging {
+    // Dodging cancels the attacks, but not the hits that land after its invincibility frames
+    pub const PRIORITY: i32 = 32;
+
+    pub fn new(direction: Vec2, meta: &DodgeStats) -> Self {
+        Self {
+            direction,
+            timer: Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once),
+            has_started: false,
+        }
+    }
+}
+
+/// Component on players that have dodged, which can't dodge again until its timer finishes
+#[derive(Component, Deref, DerefMut)]
+#[component(storage = "SparseSet")]
+pub struct DodgeCooldown(pub Timer);
+
 /// The player is throwing an item
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct Throwing;
 impl Throwing {
     pub const PRIORITY: i32 = 15;
@@ -222,13 +406,24 @@ impl Throwing {
 
 /// The player is grabbing an item ( or trying to)
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct Grabbing;
 impl Grabbing {
     pub const PRIORITY: i32 = Throwing::PRIORITY;
 }
 
+/// Component indicating the player is slamming the fighter they are carrying into the ground
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Slamming;
+impl Slamming {
+    pub const PRIORITY: i32 = Throwing::PRIORITY;
+}
+
 /// Component indicating the player is flopping
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Flopping {
     /// The initial y-height of the figther when starting the attack
@@ -244,6 +439,7 @@ impl Flopping {
 
 /// Component indicating the player is performing a groundslam
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct GroundSlam {
     /// The initial y-height of the figther when starting the attack
@@ -258,6 +454,7 @@ impl GroundSlam {
 }
 
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct BossBombThrow {
     pub has_started: bool,
@@ -270,6 +467,7 @@ impl BossBombThrow {
 }
 
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Punching {
     pub has_started: bool,
@@ -280,24 +478,48 @@ impl Punching {
     pub const ANIMATION: &'static str = "attacking";
 }
 
-#[derive(Component, Default, Reflect)]
+/// Component indicating the fighter is doing the steps of a `chain` attack, which are the
+/// [`combo`](AttackMeta::combo) steps of the attack
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Chaining {
+    /// The index of the current step in the [`combo`](AttackMeta::combo) of the attack
+    pub step: usize,
     pub has_started: bool,
+    pub is_finished: bool,
+    /// Whether attack has been pressed since the last gameplay step
+    pub pressed: bool,
+    /// Whether the next step follows once the cancel window of the current one opens
     pub continue_chain: bool,
-    pub can_extend: bool,
+    /// Whether the chain is finished with a flop, which follows the last step the same way as the
+    /// next step would
     pub transition_to_final: bool,
-    pub transition_to_idle: bool,
-    pub link: u32,
+    /// The attack entity of the current step
+    #[reflect(ignore)]
+    pub attack_entity: Option<Entity>,
 }
 impl Chaining {
     pub const PRIORITY: i32 = 30;
-    pub const ANIMATION: &'static str = "chaining";
-    pub const FOLLOWUP_ANIMATION: &'static str = "followup";
-    pub const LENGTH: u32 = 2;
+
+    /// Take the attack presses since the last step into account, and whether the chain continues
+    /// from the current `frame` of a step with the cancel `window`. Presses after the window has
+    /// closed are too late to continue the chain, and the ones before it opens wait for it.
+    fn continues(&mut self, window: Option<CancelWindow>, frame: usize) -> bool {
+        let Some(window) = window else {
+            return false;
+        };
+        if self.pressed && frame <= window.end {
+            self.continue_chain = true;
+        }
+        self.pressed = false;
+
+        self.continue_chain && frame >= window.start
+    }
 }
 
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct MeleeAttacking {
     pub has_started: bool,
@@ -309,6 +531,7 @@ impl MeleeAttacking {
 }
 
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Shooting {
     pub has_started: bool,
@@ -321,11 +544,14 @@ impl Shooting {
 }
 
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct ProjectileAttacking {
     pub has_started: bool,
     pub is_finished: bool,
     pub thrown: bool,
+    /// Whether the telegraph of a ranged attack has been played, before the attack itself
+    pub telegraphed: bool,
 }
 impl ProjectileAttacking {
     pub const PRIORITY: i32 = 30;
@@ -334,6 +560,7 @@ impl ProjectileAttacking {
 
 /// Component indicating the player is holding a item on it's head
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Holding;
 impl Holding {
@@ -342,9 +569,10 @@ impl Holding {
 
 /// Component indicating the player is in hitstun
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct HitStun {
-    //velocity > pushback?
+    /// The impulse of the hit, which knocks the fighter back depending on its [mass](Stats::mass)
     pub pushback: Vec2,
     pub timer: Timer,
 }
@@ -356,8 +584,234 @@ impl HitStun {
     pub const KNOCKED_RIGHT: &'static str = "knocked_right";
 }
 
+/// Component indicating the fighter has been launched into the air by a hit, where the follow-up
+/// hits keep it up until it falls back down on the ground
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Airborne {
+    pub z: VirtualZ,
+    /// The impulse of the latest hit, which knocks the fighter back depending on its
+    /// [mass](Stats::mass)
+    pub pushback: Vec2,
+    /// How many times the fighter has been hit since it was launched
+    pub juggles: u32,
+    pub has_started: bool,
+    pub landed: bool,
+}
+impl Airborne {
+    pub const PRIORITY: i32 = 42;
+
+    pub fn new(launch: f32, pushback: Vec2) -> Self {
+        let mut z = VirtualZ::default();
+        z.launch(launch);
+        Self {
+            z,
+            pushback,
+            ..default()
+        }
+    }
+
+    /// Keep the fighter in the air after being hit again, by `launch` for launchers and a small pop
+    /// for the other hits. Every hit pops the fighter a little lower than the one before, so that
+    /// it falls down eventually.
+    pub fn juggle(&mut self, launch: f32, pushback: Vec2) {
+        let height = launch.max(consts::JUGGLE_HIT_HEIGHT);
+        self.z
+            .launch(height * consts::JUGGLE_DECAY.powi(self.juggles as i32));
+        self.juggles += 1;
+        self.pushback = pushback;
+    }
+}
+
+/// Component indicating the fighter has been knocked down by a heavy hit, and is lying on the
+/// floor, where hits only hurt it by its [`down_damage_scale`](KnockdownMeta::down_damage_scale)
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct KnockedDown {
+    /// The impulse of the hit that knocked the fighter down
+    pub pushback: Vec2,
+    pub timer: Timer,
+    pub has_started: bool,
+}
+impl KnockedDown {
+    pub const PRIORITY: i32 = 46;
+
+    pub fn new(pushback: Vec2, meta: &KnockdownMeta) -> Self {
+        Self {
+            pushback,
+            timer: Timer::from_seconds(meta.down_time.max(0.0), TimerMode::Once),
+            has_started: false,
+        }
+    }
+}
+
+/// Component indicating the fighter is getting back up after being knocked down, while it's
+/// [`Invulnerable`]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct GettingUp {
+    pub timer: Timer,
+    pub has_started: bool,
+}
+impl GettingUp {
+    pub const PRIORITY: i32 = KnockedDown::PRIORITY;
+}
+
+/// Component on fighters that are getting up after being knocked down or are at the start of a
+/// dodge, which can't be hit until its timer finishes
+#[derive(Component, Reflect, Default, Deref, DerefMut)]
+#[reflect(Component)]
+pub struct Invulnerable(pub Timer);
+
+/// Component indicating the fighter has been grabbed, and is being carried by the fighter with the
+/// [`HeldFighter`]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Grabbed {
+    /// Where the fighter is carried, from the [`carry_offset`](FighterMeta::carry_offset) of its
+    /// holder
+    pub offset: Vec2,
+}
+impl Grabbed {
+    pub const PRIORITY: i32 = 45;
+    pub const ANIMATION: &'static str = HitStun::HITSTUN;
+}
+
+/// Component indicating the enemy is attacking with a grab, and is holding the player it caught in
+/// front of it once it's holding
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Grappling {
+    pub has_started: bool,
+    pub is_holding: bool,
+    pub hold_timer: Timer,
+}
+impl Grappling {
+    pub const PRIORITY: i32 = 30;
+    pub const ANIMATION: &'static str = "attacking";
+}
+
+/// The player that an enemy is holding with its grab attack
+#[derive(Component, Deref)]
+pub struct GrappledFighter(pub Entity);
+
+/// Component indicating the player is held by an enemy's grab attack, while the other enemies can
+/// hit them without stunning them out of it. Pressing attack enough times breaks free.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Grappled {
+    pub presses: u32,
+    pub escape_presses: u32,
+    /// How long the player can't be grabbed again after being let go
+    pub immunity_time: f32,
+    pub has_started: bool,
+}
+impl Grappled {
+    pub const PRIORITY: i32 = 44;
+    pub const ANIMATION: &'static str = HitStun::HITSTUN;
+
+    pub fn new(meta: &GrappleMeta) -> Self {
+        Self {
+            escape_presses: meta.escape_presses,
+            immunity_time: meta.immunity_time,
+            ..default()
+        }
+    }
+}
+
+/// Component on players that have just been let go of by an enemy's grab, who can't be grabbed
+/// again until its timer finishes
+#[derive(Component, Deref, DerefMut)]
+pub struct GrabImmunity(pub Timer);
+
+/// Component indicating the fighter has been thrown, and is flying into the fighters in its way
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct Thrown {
+    pub trajectory: ThrowTrajectory,
+    pub timer: Timer,
+    pub has_started: bool,
+    /// Whether the thrown fighter has hit another fighter, and taken its own damage for it
+    pub has_hit: bool,
+}
+impl Thrown {
+    pub const PRIORITY: i32 = 50;
+    /// How much slower than the fighter that hit them the fighters knocked down the chain fly
+    pub const CHAIN_SPEED_FACTOR: f32 = 0.6;
+    /// The speed below which the fighters that are hit are no longer knocked down the chain
+    pub const CHAIN_MIN_SPEED: f32 = 150.0;
+
+    pub fn new(trajectory: ThrowTrajectory, meta: &ThrownMeta) -> Self {
+        Self {
+            trajectory,
+            timer: Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once),
+            ..default()
+        }
+    }
+}
+
+/// The hitbox that a thrown fighter hits other fighters with
+#[derive(Component)]
+pub struct ThrownHitbox;
+
+/// The fighter that a fighter has grabbed and is carrying over its head
+#[derive(Component, Deref)]
+pub struct HeldFighter(pub Entity);
+
+/// Component indicating the player is bursting out of a combo, which can't be interrupted by the
+/// hits of the combo
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct ComboBreaking {
+    pub animation: String,
+    pub timer: Timer,
+    pub has_started: bool,
+}
+impl ComboBreaking {
+    pub const PRIORITY: i32 = 48;
+
+    pub fn new(animation: &str, duration: f32) -> Self {
+        Self {
+            animation: animation.to_owned(),
+            timer: Timer::from_seconds(duration.max(0.0), TimerMode::Once),
+            has_started: false,
+        }
+    }
+}
+
+/// Component indicating the player is playing its part in a team-up attack, which can't be
+/// interrupted by anything but dying
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+#[component(storage = "SparseSet")]
+pub struct TeamUpAttacking {
+    pub animation: String,
+    pub timer: Timer,
+    pub has_started: bool,
+}
+impl TeamUpAttacking {
+    pub const PRIORITY: i32 = 60;
+
+    pub fn new(animation: &str, duration: f32) -> Self {
+        Self {
+            animation: animation.to_owned(),
+            timer: Timer::from_seconds(duration.max(0.0), TimerMode::Once),
+            has_started: false,
+        }
+    }
+}
+
 /// Component indicating the player is dying
 #[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 #[component(storage = "SparseSet")]
 pub struct Dying;
 impl Dying {
@@ -372,6 +826,17 @@ pub struct BeingHeld;
 // Fighter input collector systems
 //
 
+/// Counts the attack presses of the grappled players trying to break free
+fn collect_escape_presses(
+    mut players: Query<(&ActionState<PlayerAction>, &mut Grappled), With<Player>>,
+) {
+    for (action_state, mut grappled) in &mut players {
+        if action_state.just_pressed(PlayerAction::Attack) {
+            grappled.presses += 1;
+        }
+    }
+}
+
 /// Emits state transitions based on fighter actions
 fn collect_player_actions(
     mut players: Query<
@@ -381,8 +846,11 @@ fn collect_player_actions(
             &Inventory,
             &Stats,
             Option<&Holding>,
+            Option<&HeldFighter>,
             Option<&mut Chaining>,
             &AvailableAttacks,
+            &Facing,
+            Option<&DodgeCooldown>,
         ),
         With<Player>,
     >,
@@ -393,17 +861,27 @@ fn collect_player_actions(
         inventory,
         stats,
         holding,
+        held_fighter,
         chaining,
         available_attacks,
+        facing,
+        dodge_cooldown,
     ) in &mut players
     {
+        // Slam the carried fighter into the ground instead of attacking
+        if action_state.just_pressed(PlayerAction::Attack) && held_fighter.is_some() {
+            transition_intents.push_back(StateTransition::new(Slamming, Slamming::PRIORITY, true));
+        }
+
         // Trigger attacks
         //TODO: can use flop attack again after input buffer/chaining
         if action_state.just_pressed(PlayerAction::Attack) && holding.is_none() {
-            if chaining.is_none() {
+            if let Some(mut chaining) = chaining {
+                // The chain decides whether the press was in time for its next step
+                chaining.pressed = true;
+            } else {
                 match available_attacks.current_attack().name.as_str() {
                     "chain" => transition_intents.push_back(StateTransition::new(
-                        //need to construct a chain with correct inputs
                         Chaining::default(),
                         Chaining::PRIORITY,
                         false,
@@ -430,18 +908,50 @@ fn collect_player_actions(
                     )),
                     _ => {}
                 }
-            //todo, change to pushing states and making it additive
-            //move variable setting/continue_chain to exit condition
-            } else if let Some(mut chaining) = chaining {
-                // if chaining.can_extend {
-                chaining.continue_chain = true;
-                // }
             }
         }
 
+        // Trigger blocking, which lasts until the button is let go of
+        if action_state.pressed(PlayerAction::Block) && holding.is_none() {
+            transition_intents.push_back(StateTransition::new(
+                Blocking::default(),
+                Blocking::PRIORITY,
+                false,
+            ));
+        }
+
+        // Trigger a dodge in the direction the player is holding, or the one they are facing
+        if action_state.just_pressed(PlayerAction::Dodge)
+            && holding.is_none()
+            && held_fighter.is_none()
+            && dodge_cooldown.is_none()
+        {
+            let direction = action_state
+                .clamped_axis_pair(PlayerAction::Move)
+                .and_then(|axis| axis.xy().try_normalize())
+                .unwrap_or_else(|| facing.mirror(Vec2::X));
+            transition_intents.push_back(StateTransition::new(
+                Dodging::new(direction, &stats.dodge),
+                Dodging::PRIORITY,
+                false,
+            ));
+        }
+
+        // Shoot the projectile of the fighter's attacks
+        if action_state.just_pressed(PlayerAction::Shoot)
+            && holding.is_none()
+            && available_attacks.projectile_attack().is_some()
+        {
+            transition_intents.push_back(StateTransition::new(
+                ProjectileAttacking::default(),
+                ProjectileAttacking::PRIORITY,
+                false,
+            ));
+        }
+
         // Trigger grab/throw
         if action_state.just_pressed(PlayerAction::Throw) {
-            if inventory.is_some() {
+            if inventory.is_some() || held_fighter.is_some() {
                 transition_intents.push_back(StateTransition::new(
                     Throwing,
                     Throwing::PRIORITY,
@@ -472,21 +982,66 @@ fn collect_player_actions(
     }
 }
 
-/// Look for attacks that have contacted a figher and queue a hitstun state transition.
+/// Look for attacks that have contacted a figher and queue a hitstun state transition, or a
+/// knockdown for the hits that deal enough damage. Launchers send the fighters into the air
+/// instead, where the hits keep juggling them.
 ///
 /// TODO: Not all attacks will have knockback. Maybe we should replace `damage_velocity` with
 /// `damage_impulse` including the knockback time so that it can be ignored by this system if it's
 /// velocity or time is zero.
 fn collect_hitstuns(
-    mut fighters: Query<&mut StateTransitionIntents, With<Handle<FighterMeta>>>,
-    mut damage_events: EventReader<DamageEvent>,
+    mut fighters: Query<(
+        &mut StateTransitionIntents,
+        &Handle<FighterMeta>,
+        Option<&mut Airborne>,
+    )>,
+    attacks: Query<&Attack>,
+    mut damage_events: EventReader<HitLanded>,
+    fighter_assets: Res<Assets<FighterMeta>>,
 ) {
     for event in damage_events.iter() {
         // If the damaged entity was a fighter
-        if let Ok(mut transition_intents) = fighters.get_mut(event.damaged_entity) {
+        if let Ok((mut transition_intents, meta_handle, airborne)) =
+            fighters.get_mut(event.damaged_entity)
+        {
+            // The damage of status effects isn't an attack, and doesn't knock anyone up
+            let launch = attacks
+                .get(event.damageing_entity)
+                .ok()
+                .map(|attack| attack.launch);
+            if let Some(mut airborne) = airborne {
+                if let Some(launch) = launch {
+                    airborne.juggle(launch, event.damage_velocity);
+                }
+                continue;
+            }
+            if let Some(launch) = launch.filter(|launch| *launch > 0.0) {
+                transition_intents.push_back(StateTransition::new(
+                    Airborne::new(launch, event.damage_velocity),
+                    Airborne::PRIORITY,
+                    false,
+                ));
+                continue;
+            }
+
+            // Status effect ticks and other hits without hitstun never stagger or knock down
             if event.hitstun_duration == 0.0 {
                 continue;
             }
+
+            let knockdown = fighter_assets
+                .get(meta_handle)
+                .and_then(|fighter| fighter.knockdown.as_ref())
+                .filter(|knockdown| event.damage >= knockdown.damage);
+            if let Some(knockdown) = knockdown {
+                transition_intents.push_back(StateTransition::new(
+                    KnockedDown::new(event.damage_velocity, knockdown),
+                    KnockedDown::PRIORITY,
+                    false,
+                ));
+                continue;
+            }
+
             // Trigger hit stun
             transition_intents.push_back(StateTransition::new(
                 HitStun {
@@ -501,9 +1056,113 @@ fn collect_hitstuns(
     }
 }
 
-/// Look for fighters with their health depleated and transition them to dying state
+/// Look for parried attacks and stagger the fighters that attacked with them
+fn collect_parries(
+    mut blocked_events: EventReader<Blocked>,
+    attacks: Query<&Parent, With<Attack>>,
+    blockers: Query<&Handle<FighterMeta>>,
+    mut fighters: Query<&mut StateTransitionIntents>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for event in blocked_events.iter().filter(|event| event.parried) {
+        // Projectiles and thrown items aren't attacks of a fighter, so they can't be staggered
+        let Ok(attacker) = attacks.get(event.attacking_entity) else {
+            continue;
+        };
+        let Some(blocker) = blockers
+            .get(event.blocking_entity)
+            .ok()
+            .and_then(|handle| fighter_assets.get(handle))
+        else {
+            continue;
+        };
+
+        if let Ok(mut transition_intents) = fighters.get_mut(attacker.get()) {
+            transition_intents.push_back(StateTransition::new(
+                HitStun {
+                    pushback: Vec2::ZERO,
+                    timer: Timer::from_seconds(
+                        blocker.block.parry_stagger.max(0.0),
+                        TimerMode::Once,
+                    ),
+                },
+                HitStun::PRIORITY,
+                false,
+            ));
+        }
+    }
+}
+
+/// Look for thrown fighters that have hit other fighters, damaging the thrown fighters and knocking
+/// the fighters they hit down the chain if they are light enough.
+fn collect_thrown_hits(
+    mut hits: ParamSet<(EventReader<HitLanded>, FighterDamage)>,
+    thrown_hitboxes: Query<&Parent, With<ThrownHitbox>>,
+    mut thrown_fighters: Query<(&mut Thrown, &Stats, &Handle<FighterMeta>)>,
+    mut fighters: Query<(&Stats, &Handle<FighterMeta>, &mut StateTransitionIntents), With<Enemy>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    let events = hits
+        .p0()
+        .iter()
+        .map(|event| (event.damageing_entity, event.damaged_entity))
+        .collect::<Vec<_>>();
+    for (damageing_entity, damaged_entity) in events {
+        let Ok(hitbox_parent) = thrown_hitboxes.get(damageing_entity) else {
+            continue;
+        };
+        let Ok((mut thrown, thrown_stats, meta_handle)) =
+            thrown_fighters.get_mut(hitbox_parent.get())
+        else {
+            continue;
+        };
+
+        // The thrown fighter is hurt by the first fighter it hits, which puts it down with its
+        // landing instead of stunning it
+        if !thrown.has_hit {
+            thrown.has_hit = true;
+            if let Some(fighter) = fighter_assets.get(meta_handle) {
+                hits.p1().deal(HitLanded {
+                    damage_velocity: Vec2::ZERO,
+                    damageing_entity: damaged_entity,
+                    damaged_entity: hitbox_parent.get(),
+                    damage: fighter.thrown.damage,
+                    hitstun_duration: 0.0,
+                    critical: false,
+                });
+            }
+        }
+
+        // Fighters that are no heavier than the thrown fighter are sent flying too, a little slower
+        let speed = thrown.trajectory.velocity.x * Thrown::CHAIN_SPEED_FACTOR;
+        if speed.abs() < Thrown::CHAIN_MIN_SPEED {
+            continue;
+        }
+        if let Ok((stats, meta_handle, mut transition_intents)) = fighters.get_mut(damaged_entity) {
+            if stats.weight > thrown_stats.weight {
+                continue;
+            }
+            if let Some(fighter) = fighter_assets.get(meta_handle) {
+                // They are knocked along the ground, without flying up
+                let meta = &fighter.thrown;
+                let trajectory = ThrowTrajectory::new(speed, 0.0, 0.0, meta.duration);
+                transition_intents.push_back(StateTransition::new(
+                    Thrown::new(trajectory, meta),
+                    Thrown::PRIORITY,
+                    false,
+                ));
+            }
+        }
+    }
+}
+
+/// Look for fighters with their health depleated and transition them to dying state, once they are
+/// back on the ground if they were knocked out in the air
 fn collect_fighter_eliminations(
-    mut fighters: Query<(&Health, &mut StateTransitionIntents), With<Handle<FighterMeta>>>,
+    mut fighters: Query<
+        (&Health, &mut StateTransitionIntents),
+        (With<Handle<FighterMeta>>, Without<Airborne>),
+    >,
 ) {
     for (health, mut transition_intents) in &mut fighters {
         // If the fighter health is depleted
@@ -533,17 +1192,17 @@ fn transition_from_idle(
     }
 }
 
-// Initiate any transitions from the flopping state
-fn transition_from_flopping(
+// Initiate any transitions from the blocking state
+fn transition_from_dodging(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Flopping)>,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Dodging)>,
 ) {
-    'entity: for (entity, mut transition_intents, flopping) in &mut fighters {
+    'entity: for (entity, mut transition_intents, dodging) in &mut fighters {
         // Transition to any higher priority states
         let current_state_removed = transition_intents
-            .transition_to_higher_priority_states::<Flopping>(
+            .transition_to_higher_priority_states::<Dodging>(
                 entity,
-                Flopping::PRIORITY,
+                Dodging::PRIORITY,
                 &mut commands,
             );
 
@@ -552,20 +1211,78 @@ fn transition_from_flopping(
             continue 'entity;
         }
 
-        // If we're done flopping
-        if flopping.is_finished {
-            // Go back to idle
-            commands.entity(entity).remove::<Flopping>().insert(Idling);
+        // Transition to idle once the roll is over
+        if dodging.has_started && dodging.timer.finished() {
+            commands.entity(entity).remove::<Dodging>().insert(Idling);
         }
     }
 }
 
-fn transition_from_punching(
+fn transition_from_blocking(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Punching)>,
-) {
-    'entity: for (entity, mut transition_intents, punching) in &mut fighters {
-        // Transition to any higher priority states
+    mut fighters: Query<
+        (
+            Entity,
+            &mut StateTransitionIntents,
+            &ActionState<PlayerAction>,
+        ),
+        With<Blocking>,
+    >,
+) {
+    'entity: for (entity, mut transition_intents, action_state) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Blocking>(
+                entity,
+                Blocking::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Go back to idle once the block button is let go of
+        if !action_state.pressed(PlayerAction::Block) {
+            commands.entity(entity).remove::<Blocking>().insert(Idling);
+        }
+    }
+}
+
+// Initiate any transitions from the flopping state
+fn transition_from_flopping(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Flopping)>,
+) {
+    'entity: for (entity, mut transition_intents, flopping) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Flopping>(
+                entity,
+                Flopping::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // If we're done flopping
+        if flopping.is_finished {
+            // Go back to idle
+            commands.entity(entity).remove::<Flopping>().insert(Idling);
+        }
+    }
+}
+
+fn transition_from_punching(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Punching)>,
+) {
+    'entity: for (entity, mut transition_intents, punching) in &mut fighters {
+        // Transition to any higher priority states
         let current_state_removed = transition_intents
             .transition_to_higher_priority_states::<Punching>(
                 entity,
@@ -588,7 +1305,7 @@ fn transition_from_punching(
 
 fn transition_from_chain(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut StateTransitionIntents, &mut Chaining)>,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Chaining)>,
 ) {
     'entity: for (entity, mut transition_intents, chain) in &mut fighters {
         // Transition to any higher priority states
@@ -604,14 +1321,13 @@ fn transition_from_chain(
             continue 'entity;
         }
 
-        // If we're done attacking
+        // Finish the chain with a flop, or go back to idle once it's done
         if chain.transition_to_final {
-            // Go back to idle
             commands
                 .entity(entity)
                 .remove::<Chaining>()
                 .insert(Flopping::default());
-        } else if chain.transition_to_idle {
+        } else if chain.is_finished {
             commands.entity(entity).remove::<Chaining>().insert(Idling);
         }
     }
@@ -701,6 +1417,66 @@ fn transition_from_hitstun(
     }
 }
 
+// Initiate any transitions from the airborne state
+fn transition_from_airborne(
+    mut commands: Commands,
+    mut fighters: Query<
+        (
+            Entity,
+            &mut StateTransitionIntents,
+            &Airborne,
+            &Handle<FighterMeta>,
+            &mut Transform,
+            &mut YSort,
+            &Children,
+        ),
+        Without<Hurtbox>,
+    >,
+    mut hurtboxes: Query<&mut Transform, With<Hurtbox>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    'entity: for (
+        entity,
+        mut transition_intents,
+        airborne,
+        meta_handle,
+        mut transform,
+        mut ysort,
+        children,
+    ) in &mut fighters
+    {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Airborne>(
+                entity,
+                Airborne::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, drop the fighter back on the ground and don't continue
+        // processing this fighter
+        if current_state_removed {
+            transform.translation.y -= airborne.z.height;
+            ysort.0 = consts::FIGHTERS_Z;
+            lower_hurtboxes(children, &mut hurtboxes, -airborne.z.height);
+            continue 'entity;
+        }
+
+        // Fall down on the floor after landing, if the fighter can be knocked down
+        if airborne.landed {
+            let mut fighter_commands = commands.entity(entity);
+            fighter_commands.remove::<Airborne>();
+            match fighter_assets
+                .get(meta_handle)
+                .and_then(|fighter| fighter.knockdown.as_ref())
+            {
+                Some(knockdown) => fighter_commands.insert(KnockedDown::new(Vec2::ZERO, knockdown)),
+                None => fighter_commands.insert(Idling),
+            };
+        }
+    }
+}
+
 fn transition_from_melee_attacking(
     mut commands: Commands,
     mut fighters: Query<(Entity, &mut StateTransitionIntents, &MeleeAttacking)>,
@@ -785,284 +1561,419 @@ fn transition_from_proj_attacking(
     }
 }
 
-//
-// Handle state systems
-//
-
-/// Handle fighter idle state
-fn idling(mut fighters: Query<(&mut Animation, &mut LinearVelocity), With<Idling>>) {
-    for (mut animation, mut velocity) in &mut fighters {
-        // If we aren't playing the idle animation
-        if animation.current_animation.as_deref() != Some(Idling::ANIMATION) {
-            // Start the idle animation from the beginning
-            animation.play(Idling::ANIMATION, true /* repeating */)
-        }
-
-        // Stop moving playe when we idle
-        **velocity = Vec2::ZERO;
-    }
-}
-
-/// Handle fighter attacking state
-///
-/// > **Note:** This system currently applies attacks for both enemies and players, doing a sort of
-/// > jumping "punch". In the future there will be different attacks, which will each have their own
-/// > state system, and we will trigger different attack states for different players and enemies,
-/// > based on the attacks available to that fighter.
-fn flopping(
+// Initiate any transitions from the grappling state
+fn transition_from_grappling(
     mut commands: Commands,
     mut fighters: Query<(
         Entity,
-        &mut Animation,
-        &mut Transform,
-        &mut LinearVelocity,
-        &Facing,
-        &Handle<FighterMeta>,
-        &AvailableAttacks,
-        &mut Flopping,
-        Option<&Player>,
-        Option<&Enemy>,
+        &mut StateTransitionIntents,
+        &Grappling,
+        &Animation,
+        Option<&GrappledFighter>,
     )>,
-    fighter_assets: Res<Assets<FighterMeta>>,
+    grappled: Query<(), With<Grappled>>,
 ) {
-    for (
-        entity,
-        mut animation,
-        mut transform,
-        mut velocity,
-        facing,
-        meta_handle,
-        available_attacks,
-        mut flopping,
-        player,
-        enemy,
-    ) in &mut fighters
+    'entity: for (entity, mut transition_intents, grappling, animation, grappled_fighter) in
+        &mut fighters
     {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
-
-        let attack = available_attacks.current_attack();
-        if let Some(fighter) = fighter_assets.get(meta_handle) {
-            // Start the attack
-            if !flopping.has_started {
-                flopping.has_started = true;
-                flopping.start_y = transform.translation.y;
-
-                // Start the attack  from the beginning
-                animation.play(Flopping::ANIMATION, false);
+        // Transition to any higher priority states, letting go of the player
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Grappling>(
+                entity,
+                Grappling::PRIORITY,
+                &mut commands,
+            );
 
-                let mut offset = attack.hitbox.offset;
-                if facing.is_left() {
-                    offset.x *= -1.0
-                }
-                offset.y += fighter.collision_offset;
-                let attack_frames = attack.frames;
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            commands.entity(entity).remove::<GrappledFighter>();
+            continue 'entity;
+        }
 
-                // Spawn the attack entity
-                let attack_entity = commands
-                    .spawn(TransformBundle::from_transform(
-                        Transform::from_translation(offset.extend(0.0)),
-                    ))
-                    .insert(CollisionGroups::new(
-                        if is_player {
-                            BodyLayers::PLAYER_ATTACK
-                        } else {
-                            BodyLayers::ENEMY_ATTACK
-                        },
-                        if is_player {
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                        } else {
-                            BodyLayers::PLAYER
-                        },
-                    ))
-                    .insert(Attack {
-                        damage: attack.damage,
-                        pushback: if facing.is_left() {
-                            Vec2::NEG_X
-                        } else {
-                            Vec2::X
-                        } * attack.velocity.unwrap_or(Vec2::ZERO),
-                        hitstun_duration: attack.hitstun_duration,
-                        hitbox_meta: Some(attack.hitbox),
-                    })
-                    .insert(attack_frames)
-                    .id();
-                commands.entity(entity).push_children(&[attack_entity]);
+        let is_finished = if grappling.is_holding {
+            // Let go once the hold is over, or once the player has broken free or has been taken
+            // out of the hold by something else. The player only transitions to the grappled state
+            // in the step after being caught.
+            let timer = &grappling.hold_timer;
+            let player_gone = grappled_fighter.map_or(true, |player| {
+                timer.elapsed_secs() > 0.0 && !grappled.contains(**player)
+            });
+            timer.finished() || player_gone
+        } else {
+            // Give up once the grab has missed
+            animation.is_finished()
+        };
+        if is_finished {
+            commands
+                .entity(entity)
+                .remove::<(Grappling, GrappledFighter)>()
+                .insert(Idling);
+        }
+    }
+}
 
-                // Play attack sound effect
-                if let Some(effects) = fighter.audio.effect_handles.get(Flopping::ANIMATION) {
-                    let fx_playback = AnimationAudioPlayback::new(
-                        Flopping::ANIMATION.to_owned(),
-                        effects.clone(),
-                    );
-                    commands.entity(entity).insert(fx_playback);
-                }
-            }
+// Initiate any transitions from the grappled state
+fn transition_from_grappled(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Transform, &Grappled)>,
+    mut holders: Query<
+        (
+            Entity,
+            &GrappledFighter,
+            &Transform,
+            &mut StateTransitionIntents,
+        ),
+        Without<Grappled>,
+    >,
+) {
+    'entity: for (entity, mut transition_intents, transform, grappled) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Grappled>(
+                entity,
+                Grappled::PRIORITY,
+                &mut commands,
+            );
 
-            // Reset velocity
-            **velocity = Vec2::ZERO;
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
 
-            // Do a forward jump thing
-            //TODO: Fix hacky way to get a forward jump
-            if animation.current_frame < attack.frames.recovery {
-                if facing.is_left() {
-                    velocity.x -= 200.0;
-                } else {
-                    velocity.x += 200.0;
-                }
+        let holder = holders.iter_mut().find(|(_, held, ..)| ***held == entity);
+        match holder {
+            Some(_) if grappled.presses < grappled.escape_presses => continue 'entity,
+            // Break free by knocking the holder back
+            Some((holder, _, holder_transform, mut holder_intents)) => {
+                let direction = (holder_transform.translation.x - transform.translation.x).signum();
+                holder_intents.push_back(StateTransition::new(
+                    HitStun {
+                        pushback: Vec2::new(direction * consts::ITEM_ATTACK_VELOCITY, 0.0),
+                        timer: Timer::from_seconds(consts::HITSTUN_DURATION, TimerMode::Once),
+                    },
+                    HitStun::PRIORITY,
+                    false,
+                ));
+                commands.entity(holder).remove::<GrappledFighter>();
             }
+            // The holder has let go
+            None => (),
+        }
 
-            if animation.current_frame < attack.frames.startup {
-                let v_per_frame = 200.0 / attack.frames.startup as f32;
-                velocity.y += v_per_frame;
-            } else if animation.current_frame < attack.frames.active {
-                let v_per_frame = 200.0 / (attack.frames.active - attack.frames.startup) as f32;
-                velocity.y -= v_per_frame;
-            }
+        commands
+            .entity(entity)
+            .remove::<Grappled>()
+            .insert(Idling)
+            .insert(GrabImmunity(Timer::from_seconds(
+                grappled.immunity_time.max(0.0),
+                TimerMode::Once,
+            )));
+    }
+}
 
-            if animation.is_finished() {
-                // Stop moving
-                **velocity = Vec2::ZERO;
+// Initiate any transitions from the grabbed state
+fn transition_from_grabbed(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut StateTransitionIntents,
+        &mut Transform,
+        &mut YSort,
+        &Grabbed,
+    )>,
+    holders: Query<&HeldFighter>,
+) {
+    'entity: for (entity, mut transition_intents, mut transform, mut ysort, grabbed) in
+        &mut fighters
+    {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Grabbed>(
+                entity,
+                Grabbed::PRIORITY,
+                &mut commands,
+            );
 
-                // Make sure we "land on the ground" ( i.e. the player y position hasn't changed )
-                transform.translation.y = flopping.start_y;
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
 
-                // Set flopping to finished
-                flopping.is_finished = true;
-            }
+        // Drop the fighter if its holder has let go of it, or is gone
+        if !holders.iter().any(|held| **held == entity) {
+            transform.translation.y -= grabbed.offset.y;
+            ysort.0 = consts::FIGHTERS_Z;
+            commands.entity(entity).remove::<Grabbed>().insert(Idling);
         }
     }
 }
 
-fn chaining(
+// Initiate any transitions from the knocked down state
+fn transition_from_knocked_down(
     mut commands: Commands,
-    mut fighters: Query<
-        (
-            Entity,
-            &mut Animation,
-            &mut LinearVelocity,
-            &Facing,
-            &Handle<FighterMeta>,
-            &AvailableAttacks,
-            &mut Chaining,
-        ),
-        With<Player>,
-    >,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &KnockedDown)>,
+) {
+    'entity: for (entity, mut transition_intents, knocked_down) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<KnockedDown>(
+                entity,
+                KnockedDown::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Get back up once the fighter has been down for long enough
+        if knocked_down.timer.finished() {
+            commands
+                .entity(entity)
+                .remove::<KnockedDown>()
+                .insert(GettingUp::default());
+        }
+    }
+}
+
+// Initiate any transitions from the getting up state
+fn transition_from_getting_up(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &GettingUp)>,
+) {
+    'entity: for (entity, mut transition_intents, getting_up) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<GettingUp>(
+                entity,
+                GettingUp::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Transition to idle once the fighter is back up
+        if getting_up.has_started && getting_up.timer.finished() {
+            commands.entity(entity).remove::<GettingUp>().insert(Idling);
+        }
+    }
+}
+
+// Initiate any transitions from the thrown state
+fn transition_from_thrown(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Thrown)>,
+) {
+    'entity: for (entity, mut transition_intents, thrown) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Thrown>(
+                entity,
+                Thrown::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Transition to idle when the fighter lands
+        if thrown.timer.finished() {
+            commands.entity(entity).remove::<Thrown>().insert(Idling);
+        }
+    }
+}
+
+// Initiate any transitions from the combo breaking state
+fn transition_from_combo_breaking(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &ComboBreaking)>,
+) {
+    'entity: for (entity, mut transition_intents, combo_breaking) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<ComboBreaking>(
+                entity,
+                ComboBreaking::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Transition to idle once the burst is over
+        if combo_breaking.timer.finished() {
+            commands
+                .entity(entity)
+                .remove::<ComboBreaking>()
+                .insert(Idling);
+        }
+    }
+}
+
+// Initiate any transitions from the team-up attacking state
+fn transition_from_team_up_attacking(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &TeamUpAttacking)>,
+) {
+    'entity: for (entity, mut transition_intents, team_up) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<TeamUpAttacking>(
+                entity,
+                TeamUpAttacking::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Transition to idle once the cinematic is over
+        if team_up.timer.finished() {
+            commands
+                .entity(entity)
+                .remove::<TeamUpAttacking>()
+                .insert(Idling);
+        }
+    }
+}
+
+//
+// Handle state systems
+//
+
+/// Handle fighter idle state
+fn idling(mut fighters: Query<(&mut Animation, &mut LinearVelocity), With<Idling>>) {
+    for (mut animation, mut velocity) in &mut fighters {
+        // If we aren't playing the idle animation
+        if animation.current_animation.as_deref() != Some(Idling::ANIMATION) {
+            // Start the idle animation from the beginning
+            animation.play(Idling::ANIMATION, true /* repeating */)
+        }
+
+        // Stop moving playe when we idle
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Handle fighter attacking state
+///
+/// > **Note:** This system currently applies attacks for both enemies and players, doing a sort of
+/// > jumping "punch". In the future there will be different attacks, which will each have their own
+/// > state system, and we will trigger different attack states for different players and enemies,
+/// > based on the attacks available to that fighter.
+fn flopping(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &mut Transform,
+        &mut LinearVelocity,
+        &Facing,
+        &Handle<FighterMeta>,
+        &AvailableAttacks,
+        &mut Flopping,
+        &Team,
+    )>,
     fighter_assets: Res<Assets<FighterMeta>>,
 ) {
     for (
         entity,
         mut animation,
+        mut transform,
         mut velocity,
         facing,
         meta_handle,
         available_attacks,
-        mut chaining,
+        mut flopping,
+        team,
     ) in &mut fighters
     {
-        // this seems... potentially panicky
-        if let Some(attack) = available_attacks
-            .attacks
-            .iter()
-            .filter(|a| a.name == *"chain")
-            .last()
-        {
-            if let Some(fighter) = fighter_assets.get(meta_handle) {
-                //if we havent started the chain yet or if we have input during chain window
-                if !chaining.has_started || chaining.continue_chain && chaining.can_extend {
-                    if !chaining.has_started {
-                        chaining.has_started = true;
-                        animation.play(Chaining::ANIMATION, false);
-                        // Play attack sound effect
-                        if let Some(effects) = fighter.audio.effect_handles.get(Chaining::ANIMATION)
-                        {
-                            let fx_playback = AnimationAudioPlayback::new(
-                                Chaining::ANIMATION.to_owned(),
-                                effects.clone(),
-                            );
-                            commands.entity(entity).insert(fx_playback);
-                        }
-                    }
-                    // Start the attack  from the beginning
-
-                    //if we are on chain followup, skip the first frame of the animation
-                    if chaining.continue_chain {
-                        animation.play(Chaining::FOLLOWUP_ANIMATION, false);
-                        animation.current_frame = 2;
-                        chaining.continue_chain = false;
-                        chaining.link += 1;
-                        if chaining.link >= Chaining::LENGTH {
-                            chaining.transition_to_final = true;
-                        }
-                        // Play attack sound effect
-                        if let Some(effects) = fighter
-                            .audio
-                            .effect_handles
-                            .get(Chaining::FOLLOWUP_ANIMATION)
-                        {
-                            let fx_playback = AnimationAudioPlayback::new(
-                                Chaining::FOLLOWUP_ANIMATION.to_owned(),
-                                effects.clone(),
-                            );
-                            commands.entity(entity).insert(fx_playback);
-                        }
-                    }
-                    chaining.can_extend = false;
+        let attack = available_attacks.current_attack();
+        if let Some(fighter) = fighter_assets.get(meta_handle) {
+            // Start the attack
+            if !flopping.has_started {
+                flopping.has_started = true;
+                flopping.start_y = transform.translation.y;
 
-                    let mut offset = attack.hitbox.offset;
-                    if facing.is_left() {
-                        offset.x *= -1.0
-                    }
-                    offset.y += fighter.collision_offset;
-                    // Spawn the attack entity
-                    let attack_entity = commands
-                        .spawn(TransformBundle::from_transform(
-                            Transform::from_translation(offset.extend(0.0)),
-                        ))
-                        .insert(CollisionGroups::new(
-                            BodyLayers::PLAYER_ATTACK,
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM,
-                        ))
-                        .insert(Attack {
-                            damage: attack.damage,
-                            pushback: if facing.is_left() {
-                                Vec2::NEG_X
-                            } else {
-                                Vec2::X
-                            } * attack.velocity.unwrap_or(Vec2::ZERO),
-                            hitstun_duration: attack.hitstun_duration,
-                            hitbox_meta: Some(attack.hitbox),
-                        })
-                        .insert(attack.frames)
-                        .id();
-                    commands.entity(entity).push_children(&[attack_entity]);
+                // Start the attack  from the beginning
+                animation.play(Flopping::ANIMATION, false);
+                start_afterimages(
+                    &mut commands,
+                    entity,
+                    attack.afterimages.as_ref(),
+                    Flopping::ANIMATION,
+                );
+
+                let mut offset = facing.mirror(attack.hitbox.offset);
+                offset.y += fighter.collision_offset;
+                let attack_frames = attack.frames;
+
+                // Spawn the attack entity
+                let attack_entity = commands
+                    .spawn(TransformBundle::from_transform(
+                        Transform::from_translation(offset.extend(0.0)),
+                    ))
+                    .insert(team.attack_groups())
+                    .insert(Attack {
+                        damage: attack.damage,
+                        pushback: Vec2::X * facing.sign() * attack.velocity.unwrap_or(Vec2::ZERO),
+                        hitstun_duration: attack.hitstun_duration,
+                        hitbox_meta: Some(attack.hitbox),
+                        status_effect: attack.status_effect,
+                        launch: attack.launch,
+                        anti_air: attack.anti_air,
+                    })
+                    .insert(attack_frames)
+                    .id();
+                commands.entity(entity).push_children(&[attack_entity]);
+
+                // Play attack sound effect
+                if let Some(effects) = fighter.audio.effect_handles.get(Flopping::ANIMATION) {
+                    let fx_playback = AnimationAudioPlayback::new(
+                        Flopping::ANIMATION.to_owned(),
+                        effects.clone(),
+                    );
+                    commands.entity(entity).insert(fx_playback);
                 }
             }
 
-            if animation.current_frame > attack.frames.active {
-                chaining.can_extend = true;
-            }
             // Reset velocity
             **velocity = Vec2::ZERO;
 
-            //move forward a bit during active frames
-            if animation.current_frame > attack.frames.startup
-                && animation.current_frame < attack.frames.recovery
-            {
-                if facing.is_left() {
-                    velocity.x -= 100.0;
-                } else {
-                    velocity.x += 100.0;
-                }
+            // Do a forward jump thing
+            //TODO: Fix hacky way to get a forward jump
+            if animation.current_frame < attack.frames.recovery {
+                velocity.x += 200.0 * facing.sign();
             }
-        }
 
-        if animation.is_finished() {
-            chaining.transition_to_idle = true;
+            if animation.current_frame < attack.frames.startup {
+                let v_per_frame = 200.0 / attack.frames.startup as f32;
+                velocity.y += v_per_frame;
+            } else if animation.current_frame < attack.frames.active {
+                let v_per_frame = 200.0 / (attack.frames.active - attack.frames.startup) as f32;
+                velocity.y -= v_per_frame;
+            }
+
+            if animation.is_finished() {
+                // Stop moving
+                **velocity = Vec2::ZERO;
+
+                // Make sure we "land on the ground" ( i.e. the player y position hasn't changed )
+                transform.translation.y = flopping.start_y;
+
+                // Set flopping to finished
+                flopping.is_finished = true;
+            }
         }
     }
 }
@@ -1077,8 +1988,7 @@ fn punching(
         &Handle<FighterMeta>,
         &AvailableAttacks,
         &mut Punching,
-        Option<&Player>,
-        Option<&Enemy>,
+        &Team,
     )>,
     fighter_assets: Res<Assets<FighterMeta>>,
 ) {
@@ -1090,17 +2000,9 @@ fn punching(
         meta_handle,
         available_attacks,
         mut punching,
-        player,
-        enemy,
+        team,
     ) in &mut fighters
     {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
-
         let attack = available_attacks.current_attack();
         if let Some(fighter) = fighter_assets.get(meta_handle) {
             if !punching.has_started {
@@ -1108,11 +2010,14 @@ fn punching(
 
                 // Start the attack  from the beginning
                 animation.play(Punching::ANIMATION, false);
-
-                let mut offset = attack.hitbox.offset;
-                if facing.is_left() {
-                    offset.x *= -1.0
-                }
+                start_afterimages(
+                    &mut commands,
+                    entity,
+                    attack.afterimages.as_ref(),
+                    Punching::ANIMATION,
+                );
+
+                let mut offset = facing.mirror(attack.hitbox.offset);
                 offset.y += fighter.collision_offset;
                 let attack_frames = attack.frames;
                 // Spawn the attack entity
@@ -1120,27 +2025,15 @@ fn punching(
                     .spawn(TransformBundle::from_transform(
                         Transform::from_translation(offset.extend(0.0)),
                     ))
-                    .insert(CollisionGroups::new(
-                        if is_player {
-                            BodyLayers::PLAYER_ATTACK
-                        } else {
-                            BodyLayers::ENEMY_ATTACK
-                        },
-                        if is_player {
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                        } else {
-                            BodyLayers::PLAYER
-                        },
-                    ))
+                    .insert(team.attack_groups())
                     .insert(Attack {
                         damage: attack.damage,
-                        pushback: if facing.is_left() {
-                            Vec2::NEG_X
-                        } else {
-                            Vec2::X
-                        } * attack.velocity.unwrap_or(Vec2::ZERO),
+                        pushback: Vec2::X * facing.sign() * attack.velocity.unwrap_or(Vec2::ZERO),
                         hitstun_duration: attack.hitstun_duration,
                         hitbox_meta: Some(attack.hitbox),
+                        status_effect: attack.status_effect,
+                        launch: attack.launch,
+                        anti_air: attack.anti_air,
                     })
                     .insert(attack_frames)
                     .id();
@@ -1165,44 +2058,220 @@ fn punching(
     }
 }
 
-fn projectile_attacking(
+/// Do the steps of the chain attacks. Pressing attack again before the cancel window of a step
+/// closes starts the next step as soon as the window opens, and with the experimental moves, the
+/// last step is followed by a flop the same way.
+fn chaining(
     mut commands: Commands,
-    mut fighters: Query<
-        (
-            &mut Animation,
-            &mut LinearVelocity,
-            &Facing,
-            &Transform,
-            &mut ProjectileAttacking,
-            &AvailableAttacks,
-        ),
-        With<Enemy>,
-    >,
-    item_assets: Res<Assets<ItemMeta>>,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &mut LinearVelocity,
+        &Facing,
+        &Handle<FighterMeta>,
+        &AvailableAttacks,
+        &mut Chaining,
+        &Team,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
 ) {
-    for (mut animation, mut velocity, facing, transform, mut proj_attacking, available_attacks) in
-        &mut fighters
+    for (
+        entity,
+        mut animation,
+        mut velocity,
+        facing,
+        meta_handle,
+        available_attacks,
+        mut chaining,
+        team,
+    ) in &mut fighters
     {
-        // Start the attack
+        **velocity = Vec2::ZERO;
+
         let attack = available_attacks.current_attack();
-        let item = item_assets
-            .get(&attack.item_handle)
-            .expect("Fighter has no item");
+        let (Some(fighter), Some(step)) = (
+            fighter_assets.get(meta_handle),
+            attack.combo.get(chaining.step),
+        ) else {
+            chaining.is_finished = true;
+            continue;
+        };
+
+        if !chaining.has_started {
+            chaining.has_started = true;
+            chaining.pressed = false;
+            chaining.continue_chain = false;
+
+            // The hit of the step before can't land anymore once the next step starts
+            if let Some(previous_attack) = chaining.attack_entity.take() {
+                if let Some(previous_attack) = commands.get_entity(previous_attack) {
+                    previous_attack.despawn_recursive();
+                }
+            }
+
+            animation.play(&step.animation, false);
+            start_afterimages(
+                &mut commands,
+                entity,
+                attack.afterimages.as_ref(),
+                &step.animation,
+            );
+
+            let hitbox = step.hitbox.unwrap_or(attack.hitbox);
+            let mut offset = facing.mirror(hitbox.offset);
+            offset.y += fighter.collision_offset;
+            // Spawn the attack entity
+            let attack_entity = commands
+                .spawn(TransformBundle::from_transform(
+                    Transform::from_translation(offset.extend(0.0)),
+                ))
+                .insert(team.attack_groups())
+                .insert(Attack {
+                    damage: step.damage.unwrap_or(attack.damage),
+                    pushback: Vec2::X
+                        * facing.sign()
+                        * step.velocity.or(attack.velocity).unwrap_or(Vec2::ZERO),
+                    hitstun_duration: step.hitstun_duration.unwrap_or(attack.hitstun_duration),
+                    hitbox_meta: Some(hitbox),
+                    status_effect: attack.status_effect,
+                    launch: step.launch.unwrap_or(attack.launch),
+                    anti_air: attack.anti_air,
+                })
+                .insert(step.frames.unwrap_or(attack.frames))
+                .id();
+            commands.entity(entity).push_children(&[attack_entity]);
+            chaining.attack_entity = Some(attack_entity);
+
+            // Play attack sound effect
+            if let Some(effects) = fighter.audio.effect_handles.get(&step.animation) {
+                let fx_playback =
+                    AnimationAudioPlayback::new(step.animation.clone(), effects.clone());
+                commands.entity(entity).insert(fx_playback);
+            }
+            continue;
+        }
+
+        if chaining.continues(step.cancel_window, animation.current_frame) {
+            if chaining.step + 1 < attack.combo.len() {
+                chaining.step += 1;
+                chaining.has_started = false;
+                continue;
+            }
+            if game.features.experimental_moves {
+                chaining.transition_to_final = true;
+                continue;
+            }
+        }
+
+        if animation.is_finished() {
+            chaining.is_finished = true;
+        }
+    }
+}
 
+fn projectile_attacking(
+    mut pool: EntityPoolCommands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &mut LinearVelocity,
+        &Facing,
+        &Transform,
+        &mut ProjectileAttacking,
+        &AvailableAttacks,
+        &Handle<FighterMeta>,
+        Option<&Enemy>,
+        &Team,
+    )>,
+    item_assets: Res<Assets<ItemMeta>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut item_sprites: ItemSprites,
+) {
+    for (
+        entity,
+        mut animation,
+        mut velocity,
+        facing,
+        transform,
+        mut proj_attacking,
+        available_attacks,
+        meta_handle,
+        enemy,
+        team,
+    ) in &mut fighters
+    {
+        // Start the attack, which shoots a projectile or throws the item of the current attack
+        let attack = available_attacks
+            .projectile_attack()
+            .unwrap_or_else(|| available_attacks.current_attack());
+
+        let telegraph = attack
+            .ranged
+            .as_ref()
+            .and_then(|ranged| ranged.telegraph.as_deref());
         if !proj_attacking.has_started {
             proj_attacking.has_started = true;
-            animation.play(ProjectileAttacking::ANIMATION, false);
+            match telegraph {
+                Some(telegraph) => animation.play(telegraph, false),
+                None => {
+                    proj_attacking.telegraphed = true;
+                    animation.play(ProjectileAttacking::ANIMATION, false);
+                }
+            }
+        }
+
+        // Ranged attackers warn the players before they shoot
+        if !proj_attacking.telegraphed {
+            if animation.is_finished() {
+                proj_attacking.telegraphed = true;
+                animation.play(ProjectileAttacking::ANIMATION, false);
+            }
+            **velocity = Vec2::ZERO;
+            continue;
         }
 
         if !animation.is_finished() {
             if animation.current_frame == attack.frames.startup && !proj_attacking.thrown {
+                let translation = transform.translation
+                    + facing
+                        .mirror(throw_offset(fighter_assets.get(meta_handle), &animation))
+                        .extend(0.0);
+                let owner = ProjectileOwner {
+                    fighter: entity,
+                    enemy: enemy.is_some(),
+                };
+
                 // Spawn projectile
-                commands.spawn(Projectile::from_thrown_item(
-                    transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
-                    item,
-                    facing,
-                    true,
-                ));
+                if let Some(projectile) = &attack.projectile {
+                    pool.spawn(
+                        PoolKind::Projectile,
+                        ProjectileBundle::new(
+                            translation,
+                            attack,
+                            projectile,
+                            facing,
+                            owner,
+                            team,
+                            &mut item_sprites,
+                        ),
+                    );
+                } else {
+                    let item = item_assets
+                        .get(&attack.item_handle)
+                        .expect("Fighter has no item");
+                    pool.spawn(
+                        PoolKind::ThrownItem,
+                        Projectile::from_thrown_item(
+                            translation,
+                            item,
+                            facing,
+                            owner,
+                            team,
+                            &mut item_sprites,
+                        ),
+                    );
+                }
 
                 proj_attacking.thrown = true;
             }
@@ -1228,6 +2297,7 @@ fn ground_slam(
             &Handle<FighterMeta>,
             &AvailableAttacks,
             &mut GroundSlam,
+            &Team,
         ),
         With<Boss>,
     >,
@@ -1242,15 +2312,13 @@ fn ground_slam(
         meta_handle,
         available_attacks,
         mut ground_slam,
+        team,
     ) in &mut fighters
     {
         // Start the attack
         let attack = available_attacks.current_attack();
         if let Some(fighter) = fighter_assets.get(meta_handle) {
-            let mut offset = attack.hitbox.offset;
-            if facing.is_left() {
-                offset.x *= -1.0
-            }
+            let mut offset = facing.mirror(attack.hitbox.offset);
             offset.y += fighter.collision_offset;
             let attack_frames = attack.frames;
             if !ground_slam.has_started {
@@ -1259,25 +2327,27 @@ fn ground_slam(
 
                 // Start the attack  from the beginning
                 animation.play(GroundSlam::ANIMATION, false);
+                start_afterimages(
+                    &mut commands,
+                    entity,
+                    attack.afterimages.as_ref(),
+                    GroundSlam::ANIMATION,
+                );
 
                 // Spawn the attack entity
                 let attack_entity = commands
                     .spawn(TransformBundle::from_transform(
                         Transform::from_translation(offset.extend(0.0)),
                     ))
-                    .insert(CollisionGroups::new(
-                        BodyLayers::ENEMY_ATTACK,
-                        BodyLayers::PLAYER,
-                    ))
+                    .insert(team.attack_groups())
                     .insert(Attack {
                         damage: attack.damage,
-                        pushback: if facing.is_left() {
-                            Vec2::NEG_X
-                        } else {
-                            Vec2::X
-                        } * attack.velocity.unwrap_or(Vec2::ZERO),
+                        pushback: Vec2::X * facing.sign() * attack.velocity.unwrap_or(Vec2::ZERO),
                         hitstun_duration: attack.hitstun_duration,
                         hitbox_meta: Some(attack.hitbox),
+                        status_effect: attack.status_effect,
+                        launch: attack.launch,
+                        anti_air: attack.anti_air,
                     })
                     .insert(attack_frames)
                     .id();
@@ -1303,11 +2373,7 @@ fn ground_slam(
 
                 // Control x movement
                 if animation.current_frame < attack_frames.startup {
-                    if facing.is_left() {
-                        velocity.x -= 50.0;
-                    } else {
-                        velocity.x += 50.0;
-                    }
+                    velocity.x += 50.0 * facing.sign();
                 }
 
                 // Control y movement
@@ -1341,7 +2407,7 @@ fn ground_slam(
 }
 
 fn bomb_throw(
-    mut commands: Commands,
+    mut pool: EntityPoolCommands,
     mut fighters: Query<
         (
             &mut Animation,
@@ -1351,11 +2417,13 @@ fn bomb_throw(
             &Handle<FighterMeta>,
             &mut BossBombThrow,
             &AvailableAttacks,
+            &Team,
         ),
         With<Boss>,
     >,
     fighter_assets: Res<Assets<FighterMeta>>,
     item_assets: Res<Assets<ItemMeta>>,
+    mut rng: ResMut<GameRng>,
 ) {
     for (
         mut animation,
@@ -1365,6 +2433,7 @@ fn bomb_throw(
         meta_handle,
         mut bomb_throw,
         available_attacks,
+        team,
     ) in &mut fighters
     {
         // Start the attack
@@ -1391,8 +2460,7 @@ fn bomb_throw(
 
             let mut translation = transform.translation;
             translation.z += 0.2; // Get above boss
-            translation.x +=
-                (spritesheet.tile_size.x / 3) as f32 * if facing.is_left() { -1. } else { 1. };
+            translation.x += (spritesheet.tile_size.x / 3) as f32 * facing.sign();
             translation.y += (spritesheet.tile_size.y / 2) as f32;
             let mut animated_sprite = AnimatedSpriteSheetBundle {
                 sprite_sheet: SpriteSheetBundle {
@@ -1407,10 +2475,7 @@ fn bomb_throw(
             };
             animated_sprite.animation.current_animation = Some("bomb".to_string());
 
-            let mut offset = attack.hitbox.offset;
-            if facing.is_left() {
-                offset.x *= -1.0
-            }
+            let mut offset = facing.mirror(attack.hitbox.offset);
             offset.y += fighter.collision_offset;
 
             if !bomb_throw.has_started {
@@ -1432,30 +2497,37 @@ fn bomb_throw(
                     };
 
                     // Spawn bomb
-                    commands
-                        .spawn(AnimatedProjectile::new(
-                            item,
-                            facing,
-                            animated_sprite.clone(),
-                        ))
-                        .insert(Explodable {
-                            attack: attack.clone(),
-                            timer: Timer::from_seconds(
-                                lifetime.expect("Bomb item not found."),
-                                TimerMode::Once,
-                            ),
-                            fusing: false,
-                            animated_sprite,
-                            explosion_frames: *attack_frames,
-                            attack_enemy: false,
-                        })
-                        .insert(ItemBundle {
-                            item: Item {
-                                spawn_sprite: false,
+                    let projectile = AnimatedProjectile::new(
+                        item,
+                        facing,
+                        team,
+                        animated_sprite.clone(),
+                        &mut rng,
+                    );
+                    pool.spawn(
+                        PoolKind::Bomb,
+                        (
+                            projectile,
+                            Explodable {
+                                attack: attack.clone(),
+                                timer: Timer::from_seconds(
+                                    lifetime.expect("Bomb item not found."),
+                                    TimerMode::Once,
+                                ),
+                                fusing: false,
+                                animated_sprite,
+                                explosion_frames: *attack_frames,
+                                attack_enemy: false,
+                            },
+                            ItemBundle {
+                                item: Item {
+                                    spawn_sprite: false,
+                                },
+                                item_meta_handle: attack.item_handle.clone(),
+                                name: Name::new("Bomb Item"),
                             },
-                            item_meta_handle: attack.item_handle.clone(),
-                            name: Name::new("Bomb Item"),
-                        });
+                        ),
+                    );
                     bomb_throw.thrown = !bomb_throw.thrown;
                 }
             } else if animation.is_finished() {
@@ -1502,35 +2574,633 @@ fn moving(
     }
 }
 
-/// Update hit stunned players
-fn hitstun(
-    mut fighters: Query<(&mut Animation, &Facing, &mut LinearVelocity, &mut HitStun)>,
-    time: Res<Time>,
+/// Hold the block standing still, counting the steps of its parry window
+fn blocking(
+    mut fighters: Query<(
+        &mut Animation,
+        &mut LinearVelocity,
+        &Handle<FighterMeta>,
+        &mut Blocking,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for (mut animation, mut velocity, meta_handle, mut blocking) in &mut fighters {
+        if blocking.steps == 0 {
+            if let Some(fighter) = fighter_assets.get(meta_handle) {
+                animation.play(&fighter.block.animation, false);
+            }
+        }
+
+        blocking.steps = blocking.steps.saturating_add(1);
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Roll the dodging players in the direction of their dodge, and keep them from being hit for the
+/// first frames of the roll
+fn dodging(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &Stats,
+        &mut LinearVelocity,
+        &mut Dodging,
+        Option<&mut Invulnerable>,
+        Option<&mut Tint>,
+    )>,
+) {
+    for (entity, mut animation, stats, mut velocity, mut dodging, invulnerable, tint) in
+        &mut fighters
+    {
+        let meta = &stats.dodge;
+        if !dodging.has_started {
+            dodging.has_started = true;
+            animation.play(&meta.animation, false);
+
+            // A dodge doesn't cut short the invulnerability of a fighter that has just gotten up
+            let invincible_time = GAMEPLAY_STEP * meta.invincible_frames;
+            match invulnerable {
+                Some(invulnerable) if invulnerable.remaining() >= invincible_time => (),
+                Some(mut invulnerable) => {
+                    **invulnerable = Timer::new(invincible_time, TimerMode::Once)
+                }
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(Invulnerable(Timer::new(invincible_time, TimerMode::Once)));
+                }
+            }
+            if let Some(mut tint) = tint {
+                tint.set(TintSource::Invulnerable, INVULNERABLE_COLOR);
+            }
+            commands
+                .entity(entity)
+                .insert(DodgeCooldown(Timer::from_seconds(
+                    meta.cooldown.max(0.0),
+                    TimerMode::Once,
+                )));
+        }
+
+        dodging.timer.tick(GAMEPLAY_STEP);
+
+        // The roll covers its distance at an even speed
+        **velocity = dodging.direction * meta.distance / meta.duration.max(f32::EPSILON);
+    }
+}
+
+/// Let the players dodge again once their cooldowns are over
+fn update_dodge_cooldowns(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut DodgeCooldown)>,
+) {
+    for (entity, mut cooldown) in &mut players {
+        if cooldown.tick(GAMEPLAY_STEP).finished() {
+            commands.entity(entity).remove::<DodgeCooldown>();
+        }
+    }
+}
+
+/// Update hit stunned players
+fn hitstun(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &Facing,
+        &Stats,
+        &mut LinearVelocity,
+        &mut HitStun,
+        Option<&HitStopped>,
+    )>,
+    hit_stop: Res<HitStop>,
+) {
+    for (entity, mut animation, facing, stats, mut velocity, mut hitstun, hit_stopped) in
+        &mut fighters
+    {
+        // If this is the start of the hit stun
+        if hitstun.timer.elapsed_secs() == 0.0 {
+            // Knock the fighter back with the pushback of the hit, which slows down by itself
+            if hitstun.pushback != Vec2::ZERO {
+                commands
+                    .entity(entity)
+                    .insert(Knockback::from_impulse(hitstun.pushback, stats.mass()));
+            }
+
+            // Calculate animation to use based on attack direction and fighter facing
+            let is_left = hitstun.pushback.x < 0.0;
+            //TODO: change knocked right and left to knocked front and back
+            let use_left_anim = if facing.is_left() { !is_left } else { is_left };
+            let animation_name = if hitstun.pushback == Vec2::ZERO {
+                HitStun::HITSTUN
+            } else if use_left_anim {
+                HitStun::KNOCKED_LEFT
+            } else {
+                HitStun::KNOCKED_RIGHT
+            };
+
+            // Play the animation, unless the fighter is playing a clip that hits don't interrupt
+            animation.interrupt(animation_name, false);
+        }
+
+        // Tick the hit stuntimer, which waits for the hit-stop after the first step, so that the
+        // stun isn't over any sooner than without the freeze
+        if hitstun.timer.elapsed_secs() == 0.0 || !hit_stop.is_stopped(hit_stopped) {
+            hitstun.timer.tick(GAMEPLAY_STEP);
+        }
+
+        // The fighter doesn't move by itself while it's stunned, only with its knockback
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Keep the launched fighters up in the air with the hits that juggle them, and let them fall back
+/// down with gravity until they land
+fn airborne(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &Stats,
+        &mut LinearVelocity,
+        &mut YSort,
+        &Children,
+        &mut Airborne,
+        Option<&HitStopped>,
+    )>,
+    mut hurtboxes: Query<&mut Transform, With<Hurtbox>>,
+    hit_stop: Res<HitStop>,
+) {
+    let dt = GAMEPLAY_STEP.as_secs_f32();
+    for (
+        entity,
+        mut animation,
+        stats,
+        mut velocity,
+        mut ysort,
+        children,
+        mut airborne,
+        hit_stopped,
+    ) in &mut fighters
+    {
+        if !airborne.has_started {
+            airborne.has_started = true;
+            animation.play(HitStun::HITSTUN, false);
+        }
+
+        // Knock the fighter back with the pushback of the latest hit, which slows down by itself
+        if airborne.pushback != Vec2::ZERO {
+            commands
+                .entity(entity)
+                .insert(Knockback::from_impulse(airborne.pushback, stats.mass()));
+            airborne.pushback = Vec2::ZERO;
+        }
+
+        // The fighter hangs in the air during the hit-stop, since it doesn't move with its velocity
+        if hit_stop.is_stopped(hit_stopped) {
+            continue;
+        }
+
+        let height = airborne.z.height;
+        airborne.landed = airborne.z.fall(dt);
+        let rise = airborne.z.height - height;
+
+        // The fighter is drawn higher up, but stays sorted and hit on the ground under it
+        ysort.0 = consts::FIGHTERS_Z + airborne.z.height;
+        lower_hurtboxes(children, &mut hurtboxes, rise);
+
+        // The fighter only moves up and down by itself, and sideways with its knockback
+        **velocity = Vec2::new(0.0, rise / dt);
+    }
+}
+
+/// Move the hurtboxes of a fighter that rises by `rise` back down by as much, so that they stay on
+/// the ground under the fighter
+pub fn lower_hurtboxes(
+    children: &Children,
+    hurtboxes: &mut Query<&mut Transform, With<Hurtbox>>,
+    rise: f32,
+) {
+    for child in children.iter() {
+        if let Ok(mut transform) = hurtboxes.get_mut(*child) {
+            transform.translation.y -= rise;
+        }
+    }
+}
+
+/// Knock the fighters down with the pushback of the hit that knocked them down, and keep them on
+/// the floor until they get back up
+fn knocked_down(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &Stats,
+        &Handle<FighterMeta>,
+        &mut LinearVelocity,
+        &mut KnockedDown,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for (entity, mut animation, stats, meta_handle, mut velocity, mut knocked_down) in &mut fighters
+    {
+        if !knocked_down.has_started {
+            let Some(knockdown) = fighter_assets
+                .get(meta_handle)
+                .and_then(|fighter| fighter.knockdown.as_ref())
+            else {
+                continue;
+            };
+            knocked_down.has_started = true;
+
+            if knocked_down.pushback != Vec2::ZERO {
+                commands
+                    .entity(entity)
+                    .insert(Knockback::from_impulse(knocked_down.pushback, stats.mass()));
+            }
+            animation.play(&knockdown.animation, false);
+        }
+
+        knocked_down.timer.tick(GAMEPLAY_STEP);
+
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Get the knocked down fighters back up, and keep them from being hit while they do and for a
+/// moment after
+fn getting_up(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &Handle<FighterMeta>,
+        &mut LinearVelocity,
+        &mut GettingUp,
+        Option<&mut Tint>,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for (entity, mut animation, meta_handle, mut velocity, mut getting_up, tint) in &mut fighters {
+        if !getting_up.has_started {
+            let Some(knockdown) = fighter_assets
+                .get(meta_handle)
+                .and_then(|fighter| fighter.knockdown.as_ref())
+            else {
+                continue;
+            };
+            getting_up.has_started = true;
+            getting_up.timer = Timer::from_seconds(knockdown.rise_time.max(0.0), TimerMode::Once);
+            animation.play(&knockdown.rise_animation, false);
+
+            let invulnerability = knockdown.rise_time + knockdown.invulnerability;
+            commands
+                .entity(entity)
+                .insert(Invulnerable(Timer::from_seconds(
+                    invulnerability.max(0.0),
+                    TimerMode::Once,
+                )));
+            if let Some(mut tint) = tint {
+                tint.set(TintSource::Invulnerable, INVULNERABLE_COLOR);
+            }
+        }
+
+        getting_up.timer.tick(GAMEPLAY_STEP);
+
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Let the fighters that have gotten up be hit again once their invulnerability is over
+fn update_invulnerabilities(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut Invulnerable, Option<&mut Tint>)>,
+) {
+    for (entity, mut invulnerable, tint) in &mut fighters {
+        if invulnerable.tick(GAMEPLAY_STEP).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+            if let Some(mut tint) = tint {
+                tint.clear(TintSource::Invulnerable);
+            }
+        }
+    }
+}
+
+/// Update grabbed fighters, which are moved by [`carry_held_fighters`]
+fn grabbed(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut Animation, &mut LinearVelocity), With<Grabbed>>,
+) {
+    for (entity, mut animation, mut velocity) in &mut fighters {
+        if animation.current_animation.as_deref() != Some(Grabbed::ANIMATION) {
+            animation.play(Grabbed::ANIMATION, false);
+            // The fighter is carried, so it can't be knocked away from its holder
+            commands.entity(entity).remove::<Knockback>();
+        }
+
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Carry the grabbed fighters over the heads of their holders, and let the holders go of the
+/// fighters that have been thrown, knocked away, or defeated
+fn carry_held_fighters(
+    mut commands: Commands,
+    holders: Query<(Entity, &Transform, &Facing, &HeldFighter)>,
+    mut held_fighters: Query<
+        (&mut Transform, &mut YSort, Option<&Grabbed>),
+        (Without<HeldFighter>, Without<Thrown>, Without<Dying>),
+    >,
+) {
+    for (holder, holder_transform, facing, held) in &holders {
+        let Ok((mut transform, mut ysort, grabbed)) = held_fighters.get_mut(**held) else {
+            commands.entity(holder).remove::<(HeldFighter, Holding)>();
+            continue;
+        };
+
+        // The fighter may not have transitioned to the grabbed state yet
+        let Some(grabbed) = grabbed else {
+            continue;
+        };
+
+        let translation = holder_transform.translation + facing.mirror(grabbed.offset).extend(0.0);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+
+        // Keep the fighter sorted in front of its holder, even though it's higher up
+        ysort.0 = consts::FIGHTERS_Z + grabbed.offset.y + 1.0;
+    }
+}
+
+/// Update grappling enemies, catching the first player inside the hitbox of their grab attack during
+/// its active frames
+fn grappling(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &mut LinearVelocity,
+        &Facing,
+        &Transform,
+        &Handle<FighterMeta>,
+        &AvailableAttacks,
+        &mut Grappling,
+    )>,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            &Handle<FighterMeta>,
+            &mut StateTransitionIntents,
+        ),
+        (
+            With<Player>,
+            Without<Grappled>,
+            Without<GrabImmunity>,
+            Without<Dying>,
+        ),
+    >,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    // Two enemies can't catch the same player
+    let mut caught_players = HashSet::new();
+
+    for (
+        entity,
+        mut animation,
+        mut velocity,
+        facing,
+        transform,
+        meta_handle,
+        available_attacks,
+        mut grappling,
+    ) in &mut fighters
+    {
+        **velocity = Vec2::ZERO;
+
+        if !grappling.has_started {
+            grappling.has_started = true;
+            animation.play(Grappling::ANIMATION, false);
+        }
+
+        if grappling.is_holding {
+            grappling.hold_timer.tick(GAMEPLAY_STEP);
+            continue;
+        }
+
+        let attack = available_attacks.current_attack();
+        if animation.current_frame < attack.frames.startup
+            || animation.current_frame > attack.frames.active
+        {
+            continue;
+        }
+        let Some(fighter) = fighter_assets.get(meta_handle) else {
+            continue;
+        };
+
+        let mut offset = facing.mirror(attack.hitbox.offset);
+        offset.y += fighter.collision_offset;
+        let grab_center = transform.translation.truncate() + offset;
+
+        let caught = players
+            .iter_mut()
+            .filter(|(player, ..)| !caught_players.contains(player))
+            .find(|(_, player_transform, player_meta_handle, _)| {
+                let Some(player_meta) = fighter_assets.get(player_meta_handle) else {
+                    return false;
+                };
+                let hurtbox_center = player_transform.translation.truncate()
+                    + Vec2::new(0.0, player_meta.collision_offset);
+                let max_offset = (attack.hitbox.size + player_meta.hurtbox.size) / 2.0;
+                (hurtbox_center - grab_center).abs().cmple(max_offset).all()
+            });
+        if let Some((player, _, _, mut player_intents)) = caught {
+            caught_players.insert(player);
+            player_intents.push_back(StateTransition::new(
+                Grappled::new(&fighter.grapple),
+                Grappled::PRIORITY,
+                false,
+            ));
+            commands.entity(entity).insert(GrappledFighter(player));
+            grappling.is_holding = true;
+            grappling.hold_timer =
+                Timer::from_seconds(fighter.grapple.hold_time.max(0.0), TimerMode::Once);
+        }
+    }
+}
+
+/// Update the players held by grappling enemies, which are moved by [`hold_grappled_fighters`]
+fn grappled(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut Animation, &mut LinearVelocity, &mut Grappled)>,
+) {
+    for (entity, mut animation, mut velocity, mut grappled) in &mut fighters {
+        if !grappled.has_started {
+            grappled.has_started = true;
+            animation.play(Grappled::ANIMATION, false);
+            commands.entity(entity).remove::<Knockback>();
+        }
+
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Hold the grappled players in front of the enemies that caught them
+fn hold_grappled_fighters(
+    holders: Query<(&Transform, &Facing, &GrappledFighter)>,
+    mut grappled: Query<&mut Transform, (With<Grappled>, Without<GrappledFighter>)>,
+) {
+    for (holder_transform, facing, held) in &holders {
+        let Ok(mut transform) = grappled.get_mut(**held) else {
+            continue;
+        };
+
+        let direction = facing.sign();
+        transform.translation.x =
+            holder_transform.translation.x + direction * consts::GRAPPLED_FIGHTER_OFFSET;
+        transform.translation.y = holder_transform.translation.y;
+    }
+}
+
+/// Let the players that have been let go of by a grab be grabbed again once their immunity is over
+fn update_grab_immunities(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut GrabImmunity)>,
+) {
+    for (entity, mut immunity) in &mut fighters {
+        if immunity.tick(GAMEPLAY_STEP).finished() {
+            commands.entity(entity).remove::<GrabImmunity>();
+        }
+    }
+}
+
+/// Update thrown fighters
+fn thrown(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &Facing,
+        &mut LinearVelocity,
+        &mut YSort,
+        &Handle<FighterMeta>,
+        &mut Thrown,
+        Option<&HitStopped>,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    hit_stop: Res<HitStop>,
+) {
+    for (
+        entity,
+        mut animation,
+        facing,
+        mut velocity,
+        mut ysort,
+        meta_handle,
+        mut thrown,
+        hit_stopped,
+    ) in &mut fighters
+    {
+        if !thrown.has_started {
+            let Some(fighter) = fighter_assets.get(meta_handle) else {
+                continue;
+            };
+            thrown.has_started = true;
+            ysort.0 = consts::FIGHTERS_Z;
+            commands.entity(entity).remove::<Knockback>();
+
+            // Fly tumbling backwards or forwards, the same way as when being knocked back
+            let is_left = thrown.trajectory.velocity.x < 0.0;
+            let use_left_anim = if facing.is_left() { !is_left } else { is_left };
+            animation.play(
+                if use_left_anim {
+                    HitStun::KNOCKED_LEFT
+                } else {
+                    HitStun::KNOCKED_RIGHT
+                },
+                false,
+            );
+
+            // Hit the enemies in the way for as long as the fighter flies. The fighter is thrown
+            // by a player, so it hits the enemies of its own team.
+            let meta = &fighter.thrown;
+            let team = Team::players();
+            let hitbox = commands
+                .spawn((
+                    TransformBundle::from_transform(Transform::from_xyz(
+                        0.0,
+                        fighter.collision_offset,
+                        0.0,
+                    )),
+                    Sensor,
+                    ActiveEvents::COLLISION_EVENTS,
+                    ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+                    team.attack_groups(),
+                    Collider::cuboid(fighter.hurtbox.size.x / 2., fighter.hurtbox.size.y / 2.),
+                    Attack {
+                        damage: meta.damage,
+                        pushback: Vec2::new(
+                            thrown.trajectory.velocity.x * Thrown::CHAIN_SPEED_FACTOR,
+                            0.0,
+                        ),
+                        hitstun_duration: meta.hitstun_duration,
+                        hitbox_meta: None,
+                        status_effect: None,
+                        launch: 0.0,
+                        anti_air: false,
+                    },
+                    Lifetime(thrown.timer.clone()),
+                    ThrownHitbox,
+                    team,
+                ))
+                .id();
+            commands.entity(entity).add_child(hitbox);
+        }
+
+        // The fighter stays on its trajectory by waiting out the hit-stop of the enemies it hits
+        if hit_stop.is_stopped(hit_stopped) {
+            continue;
+        }
+        thrown.timer.tick(GAMEPLAY_STEP);
+
+        **velocity = thrown.trajectory.velocity_at(thrown.timer.elapsed_secs());
+    }
+}
+
+/// Play the team-up animation standing still, while the cinematic plays out
+fn team_up_attacking(
+    mut fighters: Query<(&mut Animation, &mut LinearVelocity, &mut TeamUpAttacking)>,
+) {
+    for (mut animation, mut velocity, mut team_up) in &mut fighters {
+        if !team_up.has_started {
+            team_up.has_started = true;
+            animation.play(&team_up.animation, true /* repeating */);
+        }
+
+        team_up.timer.tick(GAMEPLAY_STEP);
+        **velocity = Vec2::ZERO;
+    }
+}
+
+/// Burst out of the combo standing still, without being knocked back any further
+fn combo_breaking(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &mut LinearVelocity,
+        &mut ComboBreaking,
+    )>,
 ) {
-    for (mut animation, facing, mut velocity, mut hitstun) in &mut fighters {
-        // If this is the start of the hit stun
-        if hitstun.timer.elapsed_secs() == 0.0 {
-            // Calculate animation to use based on attack direction and fighter facing
-            let is_left = hitstun.pushback.x < 0.0;
-            //TODO: change knocked right and left to knocked front and back
-            let use_left_anim = if facing.is_left() { !is_left } else { is_left };
-            let animation_name = if hitstun.pushback == Vec2::ZERO {
-                HitStun::HITSTUN
-            } else if use_left_anim {
-                HitStun::KNOCKED_LEFT
-            } else {
-                HitStun::KNOCKED_RIGHT
-            };
-
-            // Play the animation
-            animation.play(animation_name, false);
+    for (entity, mut animation, mut velocity, mut combo_breaking) in &mut fighters {
+        if !combo_breaking.has_started {
+            combo_breaking.has_started = true;
+            animation.play(&combo_breaking.animation, false);
+            commands.entity(entity).remove::<Knockback>();
         }
 
-        // Tick the hit stuntimer
-        hitstun.timer.tick(time.delta());
-
-        // Set our figher velocity to the hit stun velocity
-        **velocity = hitstun.pushback;
+        combo_breaking.timer.tick(GAMEPLAY_STEP);
+        **velocity = Vec2::ZERO;
     }
 }
 
@@ -1562,9 +3232,18 @@ fn throwing(
             &Facing,
             &mut Inventory,
             Option<&mut AvailableAttacks>,
+            Option<&HeldFighter>,
+            &Animation,
+            &Handle<FighterMeta>,
+            &Team,
         ),
         With<Throwing>,
     >,
+    mut grabbed_fighters: Query<
+        (&mut StateTransitionIntents, &Grabbed, &Handle<FighterMeta>),
+        Without<Throwing>,
+    >,
+    fighter_assets: Res<Assets<FighterMeta>>,
     mut being_held: Query<
         (
             Entity,
@@ -1580,8 +3259,51 @@ fn throwing(
     mut items_assets: ResMut<Assets<ItemMeta>>,
     mut active_scripts: ResMut<ActiveScripts>,
     mut script_item_throw_events: ResMut<Events<ScriptItemThrowEvent>>,
+    mut rng: ResMut<GameRng>,
+    mut pool: EntityPoolCommands,
+    mut item_sprites: ItemSprites,
 ) {
-    for (entity, fighter_transform, facing, mut inventory, available_attacks) in &mut fighters {
+    for (
+        entity,
+        fighter_transform,
+        facing,
+        mut inventory,
+        available_attacks,
+        held_fighter,
+        animation,
+        thrower_handle,
+        team,
+    ) in &mut fighters
+    {
+        let throw_origin = fighter_transform.translation
+            + facing
+                .mirror(throw_offset(fighter_assets.get(thrower_handle), animation))
+                .extend(0.0);
+
+        // If the player is carrying a fighter, throw it in the direction they are facing
+        if let Some(held) = held_fighter {
+            if let Ok((mut transition_intents, grabbed, meta_handle)) =
+                grabbed_fighters.get_mut(**held)
+            {
+                if let Some(fighter) = fighter_assets.get(meta_handle) {
+                    let meta = &fighter.thrown;
+                    // Fly in an arc from over the holder's head back down to the ground
+                    let trajectory = ThrowTrajectory::new(
+                        facing.sign() * meta.speed,
+                        grabbed.offset.y,
+                        meta.arc_height,
+                        meta.duration,
+                    );
+                    transition_intents.push_back(StateTransition::new(
+                        Thrown::new(trajectory, meta),
+                        Thrown::PRIORITY,
+                        false,
+                    ));
+                }
+            }
+            commands.entity(entity).remove::<(HeldFighter, Holding)>();
+        }
+
         // If the player has an item in their inventory
         if let Some(item_meta) = inventory.take() {
             // Check what kind of item this is.
@@ -1592,12 +3314,20 @@ fn throwing(
             match &item_meta.kind {
                 ItemKind::Throwable { .. } => {
                     // Throw the item!
-                    commands.spawn(Projectile::from_thrown_item(
-                        fighter_transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
-                        &item_meta,
-                        facing,
-                        false,
-                    ));
+                    pool.spawn(
+                        PoolKind::ThrownItem,
+                        Projectile::from_thrown_item(
+                            throw_origin,
+                            &item_meta,
+                            facing,
+                            ProjectileOwner {
+                                fighter: entity,
+                                enemy: false,
+                            },
+                            team,
+                            &mut item_sprites,
+                        ),
+                    );
                 }
                 ItemKind::Script { script_handle, .. } => {
                     script_item_throw_events.send(ScriptItemThrowEvent {
@@ -1608,19 +3338,28 @@ fn throwing(
                 ItemKind::BreakableBox {
                     ref item_handle, ..
                 } => {
-                    commands
-                        .spawn(Projectile::from_thrown_item(
-                            fighter_transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
-                            &item_meta,
-                            facing,
-                            false,
-                        ))
-                        .insert(Drop {
-                            item: items_assets
-                                .get(item_handle)
-                                .expect("Drop item not loaded!")
-                                .clone(),
-                        });
+                    pool.spawn(
+                        PoolKind::ThrownBox,
+                        (
+                            Projectile::from_thrown_item(
+                                throw_origin,
+                                &item_meta,
+                                facing,
+                                ProjectileOwner {
+                                    fighter: entity,
+                                    enemy: false,
+                                },
+                                team,
+                                &mut item_sprites,
+                            ),
+                            Drop {
+                                item: items_assets
+                                    .get(item_handle)
+                                    .expect("Drop item not loaded!")
+                                    .clone(),
+                            },
+                        ),
+                    );
 
                     // Despawn head sprite
                     for (head_ent, parent, ..) in being_held.iter() {
@@ -1631,18 +3370,12 @@ fn throwing(
                     commands.entity(entity).remove::<Holding>();
                 }
                 ItemKind::MeleeWeapon { .. } => {
-                    //Drop item
-                    let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
-
-                    let item_spawn_meta = ItemSpawnMeta {
-                        location: fighter_transform.translation - ground_offset,
-                        item: String::new(),
-                        item_handle: items_assets.add(item_meta.clone()),
-                    };
-                    let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
-                    ItemBundle::spawn(
-                        item_commands,
-                        &item_spawn_meta,
+                    drop_melee_weapon(
+                        &mut commands,
+                        entity,
+                        fighter_transform.translation,
+                        &item_meta,
+                        &weapon_held,
                         &mut items_assets,
                         &mut active_scripts,
                     );
@@ -1650,13 +3383,6 @@ fn throwing(
                     if let Some(mut available_attacks) = available_attacks {
                         available_attacks.attacks.pop();
                     }
-
-                    // Despawn weapon sprite
-                    for (weapon_ent, parent) in weapon_held.iter() {
-                        if parent.get() == entity {
-                            commands.entity(weapon_ent).despawn_recursive();
-                        }
-                    }
                 }
                 ItemKind::ProjectileWeapon { .. } => {
                     //Drop item
@@ -1700,12 +3426,6 @@ fn throwing(
                                 explodable.attack_enemy = true;
                             }
 
-                            let direction_mul = if facing.is_left() {
-                                Vec2::new(-1.0, 1.0)
-                            } else {
-                                Vec2::ONE
-                            };
-                            let mut rng = rand::thread_rng();
                             let item = items_assets.get(item_handle).expect("Bomb item not found.");
 
                             let (gravity, throw_velocity) = if let ItemKind::Bomb {
@@ -1722,12 +3442,12 @@ fn throwing(
 
                             commands.entity(head_ent).insert((
                                 LinearVelocity(
-                                    throw_velocity * direction_mul * rng.gen_range(0.8..1.2),
+                                    facing.mirror(throw_velocity) * rng.gen_range(0.8..1.2),
                                 ),
                                 Force(Vec2::new(0.0, -gravity)),
                                 AngularVelocity(
                                     consts::THROW_ITEM_ROTATION_SPEED
-                                        * direction_mul.x
+                                        * facing.sign()
                                         * rng.gen_range(0.8..1.2),
                                 ),
                                 CollisionGroups::new(
@@ -1742,6 +3462,7 @@ fn throwing(
                     }
                     commands.entity(entity).remove::<Holding>();
                 }
+                ItemKind::Coating { .. } => (),
             }
         }
 
@@ -1758,14 +3479,35 @@ fn grabbing(
         (
             Entity,
             &Transform,
+            &Stats,
             &mut Inventory,
             &mut StateTransitionIntents,
             Option<&mut AvailableAttacks>,
+            &Handle<FighterMeta>,
         ),
         With<Grabbing>,
     >,
-    items_query: Query<(Entity, &Transform, &Handle<ItemMeta>), With<Item>>,
+    // Only the enemies that are stunned can be grabbed
+    mut grabbable_fighters: Query<
+        (&Stats, &mut StateTransitionIntents),
+        (
+            With<Enemy>,
+            With<HitStun>,
+            Without<Grabbing>,
+            Without<Grabbed>,
+            Without<Thrown>,
+            Without<KnockedDown>,
+            Without<GettingUp>,
+            Without<Dying>,
+            // Flying enemies are out of reach
+            Without<Flying>,
+        ),
+    >,
+    items_query: Query<&Handle<ItemMeta>, With<Item>>,
     items_assets: Res<Assets<ItemMeta>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    melee_weapons: Query<(Entity, &Parent), With<MeleeWeapon>>,
+    spatial_hash: Res<SpatialHash>,
     mut script_item_grab_events: ResMut<Events<ScriptItemGrabEvent>>,
 ) {
     // We need to track the picked items, otherwise, in theory, two players could pick the same item.
@@ -1774,230 +3516,381 @@ fn grabbing(
     for (
         fighter_ent,
         fighter_transform,
+        fighter_stats,
         mut fighter_inventory,
         mut transition_intents,
         available_attacks,
+        meta_handle,
     ) in &mut fighters
     {
+        let mut grabbed_item = false;
+
         // If several items are at pick distance, an arbitrary one is picked.
-        for (item_ent, item_transform, item) in &items_query {
+        let nearby_items = spatial_hash.query(
+            fighter_transform.translation.truncate(),
+            consts::PICK_ITEM_RADIUS,
+        );
+        for (item_ent, _) in nearby_items {
+            let Ok(item) = items_query.get(item_ent) else {
+                continue;
+            };
+
             if !picked_item_ids.contains(&item_ent) {
-                // Get the distance the figher is from the item
-                let fighter_item_distance = fighter_transform
-                    .translation
-                    .truncate()
-                    .distance(item_transform.translation.truncate());
-
-                // If we are close enough
-                if fighter_item_distance <= consts::PICK_ITEM_RADIUS {
-                    // And our fighter isn't carrying another item
-                    if fighter_inventory.is_none() {
-                        match &items_assets.get(item).unwrap().kind {
-                            ItemKind::Script { script_handle, .. } => {
-                                script_item_grab_events.send(ScriptItemGrabEvent {
-                                    fighter: fighter_ent,
-                                    script_handle: script_handle.clone_weak(),
-                                });
-                                commands.entity(item_ent).despawn_recursive();
-                            }
-                            ItemKind::Throwable { damage: _, .. } => {
-                                // If its throwable, pick up the item
-                                picked_item_ids.insert(item_ent);
-                                **fighter_inventory =
-                                    Some(items_assets.get(item).expect("Item not loaded!").clone());
-                                commands.entity(item_ent).despawn_recursive();
-                            }
-                            ItemKind::BreakableBox { .. } | ItemKind::Bomb { .. } => {
-                                // Transition to holding state
-                                transition_intents.push_back(StateTransition::new(
-                                    Holding,
-                                    Holding::PRIORITY,
-                                    true,
-                                ));
-
-                                let image = items_assets
-                                    .get(item)
-                                    .expect("Item not loaded!")
-                                    .clone()
-                                    .image;
-
-                                commands.entity(item_ent).insert(Transform::from_xyz(
-                                    0.,
-                                    consts::THROW_ITEM_OFFSET.y + image.image_size.y,
-                                    consts::PROJECTILE_Z,
-                                ));
-
-                                picked_item_ids.insert(item_ent);
-                                **fighter_inventory =
-                                    Some(items_assets.get(item).expect("Item not loaded!").clone());
-                                commands.entity(item_ent).remove::<Item>().insert(BeingHeld);
-                                commands.entity(fighter_ent).add_child(item_ent);
+                // Coatings are picked up to coat the melee weapon the fighter is holding
+                if let ItemKind::Coating {
+                    element,
+                    duration,
+                    bonus_damage,
+                    tint,
+                } = &items_assets.get(item).unwrap().kind
+                {
+                    let Some((weapon_ent, _)) = melee_weapons
+                        .iter()
+                        .find(|(_, parent)| parent.get() == fighter_ent)
+                    else {
+                        continue;
+                    };
+
+                    picked_item_ids.insert(item_ent);
+                    commands.entity(weapon_ent).insert(Coated::new(
+                        *element,
+                        *duration,
+                        *bonus_damage,
+                        *tint,
+                    ));
+                    commands.entity(item_ent).despawn_recursive();
+                    grabbed_item = true;
+                    break;
+                }
+
+                // And our fighter isn't carrying another item
+                if fighter_inventory.is_none() {
+                    match &items_assets.get(item).unwrap().kind {
+                        ItemKind::Script { script_handle, .. } => {
+                            script_item_grab_events.send(ScriptItemGrabEvent {
+                                fighter: fighter_ent,
+                                script_handle: script_handle.clone_weak(),
+                            });
+                            commands.entity(item_ent).despawn_recursive();
+                        }
+                        ItemKind::Throwable { damage: _, .. } => {
+                            // If its throwable, pick up the item
+                            picked_item_ids.insert(item_ent);
+                            **fighter_inventory =
+                                Some(items_assets.get(item).expect("Item not loaded!").clone());
+                            commands.entity(item_ent).despawn_recursive();
+                        }
+                        ItemKind::BreakableBox { .. } | ItemKind::Bomb { .. } => {
+                            // Transition to holding state
+                            transition_intents.push_back(StateTransition::new(
+                                Holding,
+                                Holding::PRIORITY,
+                                true,
+                            ));
+
+                            let image = items_assets
+                                .get(item)
+                                .expect("Item not loaded!")
+                                .clone()
+                                .image;
+
+                            commands.entity(item_ent).insert(Transform::from_xyz(
+                                0.,
+                                consts::THROW_ITEM_OFFSET.y + image.image_size.y,
+                                consts::PROJECTILE_Z,
+                            ));
+
+                            picked_item_ids.insert(item_ent);
+                            **fighter_inventory =
+                                Some(items_assets.get(item).expect("Item not loaded!").clone());
+                            commands
+                                .entity(item_ent)
+                                .remove::<(Item, Interactable)>()
+                                .insert(BeingHeld);
+                            commands.entity(fighter_ent).add_child(item_ent);
+                        }
+                        ItemKind::MeleeWeapon {
+                            ref attack,
+                            ref spritesheet,
+                            ref audio,
+                            ref sprite_offset,
+                            ref swing_animation,
+                            ..
+                        } => {
+                            // If its throwable, pick up the item
+                            picked_item_ids.insert(item_ent);
+                            **fighter_inventory =
+                                Some(items_assets.get(item).expect("Item not loaded!").clone());
+                            commands.entity(item_ent).despawn_recursive();
+
+                            if let Some(mut available_attacks) = available_attacks {
+                                available_attacks.attacks.push(attack.clone())
                             }
-                            ItemKind::MeleeWeapon {
-                                ref attack,
-                                ref spritesheet,
-                                ref audio,
-                                ref sprite_offset,
-                            } => {
-                                // If its throwable, pick up the item
-                                picked_item_ids.insert(item_ent);
-                                **fighter_inventory =
-                                    Some(items_assets.get(item).expect("Item not loaded!").clone());
-                                commands.entity(item_ent).despawn_recursive();
-
-                                if let Some(mut available_attacks) = available_attacks {
-                                    available_attacks.attacks.push(attack.clone())
-                                }
-
-                                //Spawn weapon sprite on Player
-                                let mut animated_sprite = AnimatedSpriteSheetBundle {
-                                    sprite_sheet: SpriteSheetBundle {
-                                        texture_atlas: spritesheet.atlas_handle[0].clone(),
-                                        transform: Transform::from_xyz(
-                                            sprite_offset.x,
-                                            sprite_offset.y,
-                                            0.2,
-                                        ),
-                                        ..Default::default()
-                                    },
-                                    animation: Animation::new(
-                                        spritesheet.animation_fps,
-                                        spritesheet.animations.clone(),
+
+                            //Spawn weapon sprite on Player
+                            let mut animated_sprite = AnimatedSpriteSheetBundle {
+                                sprite_sheet: SpriteSheetBundle {
+                                    texture_atlas: spritesheet.atlas_handle[0].clone(),
+                                    transform: Transform::from_xyz(
+                                        sprite_offset.x,
+                                        sprite_offset.y,
+                                        0.2,
                                     ),
-                                };
-                                animated_sprite.animation.current_animation =
-                                    Some("idle".to_string());
-
-                                let weapon = commands
-                                    .spawn((
-                                        MeleeWeapon {
-                                            audio: audio.clone(),
-                                            attack: attack.clone(),
-                                        },
-                                        //need this because of hierarchy check in hitbox activation system,
-                                        //consider rearchitecting
-                                        AvailableAttacks {
-                                            attacks: vec![attack.clone()],
-                                        },
-                                        animated_sprite,
-                                        Attached {
-                                            position_face: true,
-                                            sync_facing: true,
-                                            sync_animation: false,
-                                        },
-                                        Facing::default(),
-                                    ))
-                                    .id();
-                                commands.entity(fighter_ent).add_child(weapon);
-                            }
-                            ItemKind::ProjectileWeapon {
-                                ref attack,
-                                ref spritesheet,
-                                ref sprite_offset,
-                                ref audio,
-                                ref bullet_velocity,
-                                ref bullet_lifetime,
-                                ref ammo,
-                                ref shoot_delay,
-                            } => {
-                                // If its throwable, pick up the item
-                                picked_item_ids.insert(item_ent);
-                                **fighter_inventory =
-                                    Some(items_assets.get(item).expect("Item not loaded!").clone());
-                                commands.entity(item_ent).despawn_recursive();
-
-                                if let Some(mut available_attacks) = available_attacks {
-                                    available_attacks.attacks.push(attack.clone())
-                                }
-
-                                //Spawn weapon sprite on Player
-                                let mut animated_sprite = AnimatedSpriteSheetBundle {
-                                    sprite_sheet: SpriteSheetBundle {
-                                        texture_atlas: spritesheet.atlas_handle[0].clone(),
-                                        transform: Transform::from_xyz(
-                                            sprite_offset.x,
-                                            sprite_offset.y,
-                                            0.2,
-                                        ),
-                                        ..Default::default()
+                                    ..Default::default()
+                                },
+                                animation: Animation::new(
+                                    spritesheet.animation_fps,
+                                    spritesheet.animations.clone(),
+                                ),
+                            };
+                            animated_sprite.animation.current_animation = Some("idle".to_string());
+
+                            let weapon = commands
+                                .spawn((
+                                    MeleeWeapon {
+                                        audio: audio.clone(),
+                                        attack: attack.clone(),
+                                        swing_animation: swing_animation.clone(),
                                     },
-                                    animation: Animation::new(
-                                        spritesheet.animation_fps,
-                                        spritesheet.animations.clone(),
-                                    ),
-                                };
-                                animated_sprite.animation.current_animation =
-                                    Some("idle".to_string());
-
-                                let mut shoot_timer =
-                                    Timer::from_seconds(*shoot_delay, TimerMode::Once);
-                                shoot_timer.set_elapsed(Duration::from_secs_f32(*shoot_delay));
-
-                                let weapon = commands
-                                    .spawn((
-                                        ProjectileWeapon {
-                                            attack: attack.clone(),
-                                            animated_sprite: animated_sprite.clone(),
-                                            audio: audio.clone(),
-                                            bullet_velocity: *bullet_velocity,
-                                            bullet_lifetime: *bullet_lifetime,
-                                            ammo: *ammo,
-                                            shoot_delay: shoot_timer,
-                                        },
-                                        animated_sprite,
-                                        Attached {
-                                            position_face: true,
-                                            sync_facing: true,
-                                            sync_animation: false,
-                                        },
-                                        Facing::default(),
-                                    ))
-                                    .id();
-                                commands.entity(fighter_ent).add_child(weapon);
+                                    //need this because of hierarchy check in hitbox activation system,
+                                    //consider rearchitecting
+                                    AvailableAttacks {
+                                        attacks: vec![attack.clone()],
+                                    },
+                                    animated_sprite,
+                                    // Held weapons are mirrored by the socket of the hand
+                                    Attached {
+                                        position_face: false,
+                                        sync_facing: true,
+                                        sync_animation: false,
+                                    },
+                                    Socketed::hand(*sprite_offset),
+                                    Facing::default(),
+                                    Tint::default(),
+                                ))
+                                .id();
+                            commands.entity(fighter_ent).add_child(weapon);
+                        }
+                        ItemKind::ProjectileWeapon {
+                            ref attack,
+                            ref spritesheet,
+                            ref sprite_offset,
+                            ref audio,
+                            ref bullet_velocity,
+                            ref bullet_lifetime,
+                            ref ammo,
+                            ref shoot_delay,
+                        } => {
+                            // If its throwable, pick up the item
+                            picked_item_ids.insert(item_ent);
+                            **fighter_inventory =
+                                Some(items_assets.get(item).expect("Item not loaded!").clone());
+                            commands.entity(item_ent).despawn_recursive();
+
+                            if let Some(mut available_attacks) = available_attacks {
+                                available_attacks.attacks.push(attack.clone())
                             }
+
+                            //Spawn weapon sprite on Player
+                            let mut animated_sprite = AnimatedSpriteSheetBundle {
+                                sprite_sheet: SpriteSheetBundle {
+                                    texture_atlas: spritesheet.atlas_handle[0].clone(),
+                                    transform: Transform::from_xyz(
+                                        sprite_offset.x,
+                                        sprite_offset.y,
+                                        0.2,
+                                    ),
+                                    ..Default::default()
+                                },
+                                animation: Animation::new(
+                                    spritesheet.animation_fps,
+                                    spritesheet.animations.clone(),
+                                ),
+                            };
+                            animated_sprite.animation.current_animation = Some("idle".to_string());
+
+                            let mut shoot_timer =
+                                Timer::from_seconds(*shoot_delay, TimerMode::Once);
+                            shoot_timer.set_elapsed(Duration::from_secs_f32(*shoot_delay));
+
+                            let weapon = commands
+                                .spawn((
+                                    ProjectileWeapon {
+                                        attack: attack.clone(),
+                                        animated_sprite: animated_sprite.clone(),
+                                        audio: audio.clone(),
+                                        bullet_velocity: *bullet_velocity,
+                                        bullet_lifetime: *bullet_lifetime,
+                                        ammo: *ammo,
+                                        shoot_delay: shoot_timer,
+                                    },
+                                    animated_sprite,
+                                    Attached {
+                                        position_face: false,
+                                        sync_facing: true,
+                                        sync_animation: false,
+                                    },
+                                    Socketed::hand(*sprite_offset),
+                                    Facing::default(),
+                                ))
+                                .id();
+                            commands.entity(fighter_ent).add_child(weapon);
                         }
+                        // Coatings are never held, they have been picked up above
+                        ItemKind::Coating { .. } => (),
                     }
-                    break;
                 }
+                grabbed_item = true;
+                break;
             }
         }
+
+        // Without an item nearby, grab a stunned enemy that is light enough to be thrown
+        let carry_offset = fighter_assets
+            .get(meta_handle)
+            .map_or(consts::HELD_FIGHTER_OFFSET, |fighter| fighter.carry_offset);
+        if !grabbed_item && fighter_inventory.is_none() {
+            let nearby_fighters = spatial_hash.query(
+                fighter_transform.translation.truncate(),
+                consts::GRAB_FIGHTER_RADIUS,
+            );
+            for (target_ent, _) in nearby_fighters {
+                if picked_item_ids.contains(&target_ent) {
+                    continue;
+                }
+                let Ok((target_stats, mut target_intents)) = grabbable_fighters.get_mut(target_ent)
+                else {
+                    continue;
+                };
+                if !fighter_stats.weight.can_throw(target_stats.weight) {
+                    continue;
+                }
+
+                picked_item_ids.insert(target_ent);
+                target_intents.push_back(StateTransition::new(
+                    Grabbed {
+                        offset: carry_offset,
+                    },
+                    Grabbed::PRIORITY,
+                    false,
+                ));
+                transition_intents.push_back(StateTransition::new(
+                    Holding,
+                    Holding::PRIORITY,
+                    true,
+                ));
+                commands.entity(fighter_ent).insert(HeldFighter(target_ent));
+                break;
+            }
+        }
+
         // Grabbing is an "instant" state, that is removed at the end of every frame. Eventually it
         // may not be and it might play a fighter animation.
         commands.entity(fighter_ent).remove::<Grabbing>();
     }
 }
 
+/// Slam the fighters that the players are carrying into the ground in front of them, knocking them
+/// down if they can be
+fn slamming(
+    mut commands: Commands,
+    holders: Query<(Entity, &Transform, &Facing, Option<&HeldFighter>), With<Slamming>>,
+    mut held_fighters: Query<
+        (&mut Transform, &mut YSort, &Handle<FighterMeta>),
+        (With<Grabbed>, Without<Slamming>),
+    >,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    teams: Teams,
+    mut damage: FighterDamage,
+) {
+    for (entity, holder_transform, facing, held) in &holders {
+        // Slamming is an "instant" state, like throwing
+        commands.entity(entity).remove::<Slamming>();
+
+        let Some(held) = held else {
+            continue;
+        };
+        let Ok((mut transform, mut ysort, meta_handle)) = held_fighters.get_mut(**held) else {
+            continue;
+        };
+        let Some(fighter) = fighter_assets.get(meta_handle) else {
+            continue;
+        };
+        commands.entity(entity).remove::<(HeldFighter, Holding)>();
+
+        // The fighter lands on the ground right in front of its holder
+        let offset = facing.mirror(Vec2::new(consts::FIGHTER_COLLISION_RADIUS * 2.0, 0.0));
+        transform.translation.x = holder_transform.translation.x + offset.x;
+        transform.translation.y = holder_transform.translation.y;
+        ysort.0 = consts::FIGHTERS_Z;
+
+        // The slam hits like an attack of the fighter slamming, unless they are on the same team.
+        // The hit doesn't stun the fighter, which is put in its new state below instead.
+        if !teams.is_friendly(entity, **held) {
+            damage.deal(HitLanded {
+                damage_velocity: Vec2::ZERO,
+                damageing_entity: entity,
+                damaged_entity: **held,
+                damage: fighter.thrown.slam_damage,
+                hitstun_duration: 0.0,
+                critical: false,
+            });
+        }
+
+        // Hits that are lower priority than being grabbed wouldn't get the fighter out of it, so
+        // it's put in its new state right away
+        let mut held_commands = commands.entity(**held);
+        held_commands.remove::<Grabbed>();
+        match &fighter.knockdown {
+            Some(knockdown) => held_commands.insert(KnockedDown::new(Vec2::ZERO, knockdown)),
+            None => held_commands.insert(HitStun {
+                pushback: Vec2::ZERO,
+                timer: Timer::from_seconds(fighter.thrown.hitstun_duration, TimerMode::Once),
+            }),
+        };
+    }
+}
+
 fn melee_attacking(
     mut commands: Commands,
-    mut fighters: Query<(
+    mut fighters: Query<
+        (
+            Entity,
+            Option<&mut MeleeAttacking>,
+            &Team,
+            &AvailableAttacks,
+            &mut LinearVelocity,
+            &Facing,
+            &mut Animation,
+        ),
+        Without<MeleeWeapon>,
+    >,
+    mut melee_weapons: Query<(
         Entity,
-        Option<&mut MeleeAttacking>,
-        Option<&Player>,
-        Option<&Enemy>,
-        &AvailableAttacks,
-        &mut LinearVelocity,
-        &Facing,
+        &Parent,
+        &mut Animation,
+        &MeleeWeapon,
+        Option<&Coated>,
     )>,
-    mut melee_weapons: Query<(Entity, &Parent, &mut Animation, &MeleeWeapon)>,
 ) {
-    for (entity, melee_attack, player, enemy, available_attacks, mut velocity, facing) in
-        &mut fighters
+    for (
+        entity,
+        melee_attack,
+        team,
+        available_attacks,
+        mut velocity,
+        facing,
+        mut fighter_animation,
+    ) in &mut fighters
     {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
-
         let mut melee_weapon = None;
-        for (weapon_ent, parent, animation, weapon) in &mut melee_weapons {
+        for (weapon_ent, parent, animation, weapon, coated) in &mut melee_weapons {
             if parent.get() == entity {
-                melee_weapon = Some((animation, weapon.audio.clone(), weapon_ent));
+                melee_weapon = Some((animation, weapon, weapon_ent, coated));
             }
         }
 
-        if let Some((mut animation, audio, weapon_ent)) = melee_weapon {
+        if let Some((mut animation, weapon, weapon_ent, coated)) = melee_weapon {
             //Check if it's attacking
             if let Some(mut melee_attack) = melee_attack {
                 if !melee_attack.has_started {
@@ -2005,44 +3898,50 @@ fn melee_attacking(
 
                     // Start the attack from the beginning
                     animation.play("slashing", false);
+                    if let Some(swing_animation) = weapon
+                        .swing_animation
+                        .as_deref()
+                        .filter(|name| fighter_animation.animations.contains_key(*name))
+                    {
+                        fighter_animation.play(swing_animation, false);
+                    }
 
                     let attack = available_attacks.current_attack();
 
-                    let offset = attack.hitbox.offset;
+                    // The hitbox is a child of the weapon, which is mirrored with the fighter
+                    let offset = facing.mirror(attack.hitbox.offset);
                     let attack_frames = attack.frames;
                     // Spawn the attack entity
                     let attack_entity = commands
                         .spawn(TransformBundle::from_transform(
                             Transform::from_translation(offset.extend(0.0)),
                         ))
-                        .insert(CollisionGroups::new(
-                            if is_player {
-                                BodyLayers::PLAYER_ATTACK
-                            } else {
-                                BodyLayers::ENEMY_ATTACK
-                            },
-                            if is_player {
-                                BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                            } else {
-                                BodyLayers::PLAYER
-                            },
-                        ))
+                        .insert(team.attack_groups())
                         .insert(Attack {
-                            damage: attack.damage,
-                            pushback: if facing.is_left() {
-                                Vec2::NEG_X
-                            } else {
-                                Vec2::X
-                            } * attack.velocity.unwrap_or(Vec2::ZERO),
-                            hitstun_duration: attack.hitstun_duration,
+                            damage: attack.damage + coated.map_or(0, |coated| coated.bonus_damage),
+                            pushback: Vec2::X
+                                * facing.sign()
+                                * attack.velocity.unwrap_or(Vec2::ZERO),
+                            hitstun_duration: attack.hitstun_duration
+                                + coated.map_or(0.0, |coated| coated.element.extra_stun_time()),
                             hitbox_meta: Some(attack.hitbox),
+                            status_effect: attack.status_effect,
+                            launch: attack.launch,
+                            anti_air: attack.anti_air,
                         })
                         .insert(attack_frames)
                         .id();
+                    if let Some(coated) = coated {
+                        commands
+                            .entity(attack_entity)
+                            .insert(CoatedAttack(coated.element));
+                    }
                     commands.entity(weapon_ent).push_children(&[attack_entity]);
 
                     // Play attack sound effect
-                    if let Some(effects) = audio.effect_handles.get(MeleeAttacking::ANIMATION) {
+                    if let Some(effects) =
+                        weapon.audio.effect_handles.get(MeleeAttacking::ANIMATION)
+                    {
                         let fx_playback = AnimationAudioPlayback::new(
                             MeleeAttacking::ANIMATION.to_owned(),
                             effects.clone(),
@@ -2056,6 +3955,9 @@ fn melee_attacking(
                     melee_attack.is_finished = true;
                 }
             }
+        } else if let Some(mut melee_attack) = melee_attack {
+            // The weapon broke in the middle of the swing, which ends it
+            melee_attack.is_finished = true;
         }
     }
 }
@@ -2065,8 +3967,7 @@ fn shooting(
     mut fighters: Query<(
         Entity,
         Option<&mut Shooting>,
-        Option<&Player>,
-        Option<&Enemy>,
+        &Team,
         &AvailableAttacks,
         &mut LinearVelocity,
         &Facing,
@@ -2078,18 +3979,11 @@ fn shooting(
         &mut ProjectileWeapon,
         &GlobalTransform,
     )>,
-    shooting_particles: Query<(&Animation, Entity, &Particle), Without<ProjectileWeapon>>,
-    time: Res<Time>,
+    shooting_particles: Query<(), With<Particle>>,
+    mut animation_events: EventReader<AnimationFinished>,
+    mut pool: EntityPoolCommands,
 ) {
-    for (entity, shooting, player, enemy, available_attacks, mut velocity, facing) in &mut fighters
-    {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
-
+    for (entity, shooting, team, available_attacks, mut velocity, facing) in &mut fighters {
         let mut projectile_weapon = None;
         for (weapon_ent, parent, animation, weapon, weapon_gtransform) in &mut projectile_weapons {
             if parent.get() == entity {
@@ -2100,7 +3994,7 @@ fn shooting(
         if let Some((mut animation, weapon_ent, weapon_gtransform, mut weapon)) = projectile_weapon
         {
             //Tick shoot delay
-            weapon.shoot_delay.tick(time.delta());
+            weapon.shoot_delay.tick(GAMEPLAY_STEP);
 
             //Check if it's attacking
             if let Some(mut shooting) = shooting {
@@ -2138,13 +4032,9 @@ fn shooting(
                 {
                     //Spawn bullet
                     shooting.spawned_bullet = true;
-                    weapon.ammo -= 1;
-
-                    let direction_mul = if facing.is_left() {
-                        Vec2::new(-1.0, 1.0)
-                    } else {
-                        Vec2::ONE
-                    };
+                    if !ENGINE_CONFIG.cheats.infinite_ammo {
+                        weapon.ammo -= 1;
+                    }
 
                     let mut animated_sprite = weapon.animated_sprite.clone();
                     animated_sprite.animation.play("bullet", false);
@@ -2157,18 +4047,18 @@ fn shooting(
                     let bullet_attack = commands
                         .spawn(TransformBundle::from_transform(
                             Transform::from_translation(
-                                (attack.hitbox.offset * direction_mul).extend(0.0),
+                                facing.mirror(attack.hitbox.offset).extend(0.0),
                             ),
                         ))
-                        .insert(CollisionGroups::new(
-                            BodyLayers::PLAYER_ATTACK,
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM,
-                        ))
+                        .insert(team.attack_groups())
                         .insert(Attack {
                             damage: attack.damage,
-                            pushback: attack.velocity.unwrap_or(Vec2::ZERO) * direction_mul,
+                            pushback: facing.mirror(attack.velocity.unwrap_or(Vec2::ZERO)),
                             hitstun_duration: attack.hitstun_duration,
                             hitbox_meta: None,
+                            status_effect: attack.status_effect,
+                            launch: attack.launch,
+                            anti_air: attack.anti_air,
                         })
                         .insert(Breakable::new(0, true))
                         .insert(Collider::cuboid(
@@ -2177,16 +4067,15 @@ fn shooting(
                         ))
                         .id();
 
-                    commands
-                        .spawn(animated_sprite)
-                        .insert(Lifetime(Timer::from_seconds(
-                            weapon.bullet_lifetime,
-                            TimerMode::Once,
-                        )))
-                        .insert(LinearVelocity(
-                            Vec2::new(weapon.bullet_velocity, 0.) * direction_mul,
-                        ))
-                        .add_child(bullet_attack);
+                    pool.spawn(
+                        PoolKind::Bullet,
+                        (
+                            animated_sprite,
+                            Lifetime(Timer::from_seconds(weapon.bullet_lifetime, TimerMode::Once)),
+                            LinearVelocity(facing.mirror(Vec2::new(weapon.bullet_velocity, 0.))),
+                        ),
+                    )
+                    .add_child(bullet_attack);
                 }
 
                 **velocity = Vec2::ZERO;
@@ -2199,10 +4088,10 @@ fn shooting(
         }
     }
 
-    //Check if particle is done
-    for (animation, particle_ent, _) in shooting_particles.iter() {
-        if animation.is_finished() {
-            commands.entity(particle_ent).despawn_recursive();
+    //Despawn the particles that are done
+    for event in animation_events.iter() {
+        if shooting_particles.contains(event.entity) {
+            commands.entity(event.entity).despawn_recursive();
         }
     }
 }
@@ -2211,6 +4100,135 @@ fn shooting(
 pub struct MeleeWeapon {
     pub audio: AudioMeta,
     pub attack: AttackMeta,
+    /// The animation that the fighter holding the weapon swings it with
+    pub swing_animation: Option<String>,
+}
+
+/// Drop a melee weapon on the ground under the fighter that was holding it, and take the weapon out
+/// of the fighter's hand
+fn drop_melee_weapon(
+    commands: &mut Commands,
+    fighter: Entity,
+    location: Vec3,
+    item_meta: &ItemMeta,
+    melee_weapons: &Query<(Entity, &Parent), With<MeleeWeapon>>,
+    items_assets: &mut ResMut<Assets<ItemMeta>>,
+    active_scripts: &mut ActiveScripts,
+) {
+    let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
+
+    // The dropped weapon keeps the durability it had left
+    let item_spawn_meta = ItemSpawnMeta {
+        location: location - ground_offset,
+        item: String::new(),
+        item_handle: items_assets.add(item_meta.clone()),
+    };
+    let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+    ItemBundle::spawn(
+        item_commands,
+        &item_spawn_meta,
+        items_assets,
+        active_scripts,
+    );
+
+    // Despawn weapon sprite
+    for (weapon_ent, parent) in melee_weapons.iter() {
+        if parent.get() == fighter {
+            commands.entity(weapon_ent).despawn_recursive();
+        }
+    }
+}
+
+/// Make the fighters that are knocked down drop the melee weapons they are holding
+fn drop_melee_weapons(
+    mut commands: Commands,
+    mut fighters: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Inventory,
+            Option<&mut AvailableAttacks>,
+        ),
+        With<KnockedDown>,
+    >,
+    melee_weapons: Query<(Entity, &Parent), With<MeleeWeapon>>,
+    mut items_assets: ResMut<Assets<ItemMeta>>,
+    mut active_scripts: ResMut<ActiveScripts>,
+) {
+    for (entity, transform, mut inventory, available_attacks) in &mut fighters {
+        let holding_weapon = matches!(
+            inventory.0.as_ref().map(|item| &item.kind),
+            Some(ItemKind::MeleeWeapon { .. })
+        );
+        if !holding_weapon {
+            continue;
+        }
+        let Some(item_meta) = inventory.take() else {
+            continue;
+        };
+
+        drop_melee_weapon(
+            &mut commands,
+            entity,
+            transform.translation,
+            &item_meta,
+            &melee_weapons,
+            &mut items_assets,
+            &mut active_scripts,
+        );
+        if let Some(mut available_attacks) = available_attacks {
+            available_attacks.attacks.pop();
+        }
+    }
+}
+
+/// Wear the melee weapons down with the hits they land, and break the ones that run out of
+/// durability in the hand of the fighter holding them
+fn wear_melee_weapons(
+    mut commands: Commands,
+    mut hit_events: EventReader<HitLanded>,
+    attacks: Query<&Parent, With<Attack>>,
+    melee_weapons: Query<&Parent, With<MeleeWeapon>>,
+    mut fighters: Query<(&mut Inventory, Option<&mut AvailableAttacks>)>,
+) {
+    for event in hit_events.iter() {
+        // The hitboxes of the melee attacks are children of the weapon they are swung with
+        let Ok(weapon_ent) = attacks
+            .get(event.damageing_entity)
+            .map(|parent| parent.get())
+        else {
+            continue;
+        };
+        let Ok(holder) = melee_weapons.get(weapon_ent).map(|parent| parent.get()) else {
+            continue;
+        };
+        let Ok((mut inventory, available_attacks)) = fighters.get_mut(holder) else {
+            continue;
+        };
+        let Some(ItemMeta {
+            kind:
+                ItemKind::MeleeWeapon {
+                    durability: Some(durability),
+                    ..
+                },
+            ..
+        }) = &mut inventory.0
+        else {
+            continue;
+        };
+
+        *durability = durability.saturating_sub(1);
+        if *durability > 0 {
+            continue;
+        }
+
+        debug!(?holder, "Melee weapon broke");
+        inventory.0 = None;
+        if let Some(mut available_attacks) = available_attacks {
+            available_attacks.attacks.pop();
+        }
+        commands.entity(weapon_ent).despawn_recursive();
+    }
 }
 
 #[derive(Component)]
@@ -2226,3 +4244,65 @@ pub struct ProjectileWeapon {
 
 #[derive(Component)]
 pub struct Particle;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn breaking_weapon_returns_fighter_to_idle() {
+        // The fighter is in the middle of a swing, but its weapon has broken and been despawned
+        let mut world = World::new();
+        let fighter = world
+            .spawn((
+                MeleeAttacking {
+                    has_started: true,
+                    is_finished: false,
+                },
+                Team::players(),
+                AvailableAttacks::default(),
+                LinearVelocity::default(),
+                Facing::default(),
+                Animation::new(1.0 / 12.0, default()),
+                StateTransitionIntents::default(),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded()
+            .with_system(melee_attacking)
+            .with_system(transition_from_melee_attacking.after(melee_attacking));
+        stage.run(&mut world);
+
+        assert!(world.get::<MeleeAttacking>(fighter).is_none());
+        assert!(world.get::<Idling>(fighter).is_some());
+    }
+
+    #[test]
+    fn chain_continues_in_cancel_window() {
+        let window = Some(CancelWindow { start: 3, end: 5 });
+
+        // A press before the window waits for it to open
+        let mut chaining = Chaining {
+            pressed: true,
+            ..default()
+        };
+        assert!(!chaining.continues(window, 1));
+        assert!(!chaining.continues(window, 2));
+        assert!(chaining.continues(window, 3));
+
+        // A press after the window has closed is too late
+        let mut chaining = Chaining {
+            pressed: true,
+            ..default()
+        };
+        assert!(!chaining.continues(window, 6));
+        assert!(!chaining.continues(window, 7));
+
+        // Steps without a window end the chain
+        let mut chaining = Chaining {
+            pressed: true,
+            ..default()
+        };
+        assert!(!chaining.continues(None, 3));
+    }
+}
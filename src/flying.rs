@@ -0,0 +1,133 @@
+//! Flying enemies, which hover above the ground out of the reach of most attacks.
+//!
+//! The enemies with a [`flying`](crate::metadata::FighterMeta::flying) fighter hover at its height,
+//! bobbing up and down, and fly over the ground instead of walking on it. Like the fighters that
+//! are launched into the air they are drawn higher up, but sorted and hit on the ground under them.
+//! Only the attacks made from the air and the [anti-air](crate::metadata::AttackMeta::anti_air)
+//! attacks reach them there.
+//!
+//! The bob is counted in gameplay steps, so that recorded inputs play out the same way, and it
+//! stands still along with the flying enemy during a hit-stop.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::{Attack, Hurtbox},
+    camera::YSort,
+    consts,
+    fighter_state::{lower_hurtboxes, Airborne},
+    hit_stop::{HitStop, HitStopped},
+    metadata::{FighterMeta, FlyingMeta},
+    movement::VelocitySystems,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct FlyingPlugin;
+
+impl Plugin for FlyingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Flying>().add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::MOVEMENT,
+            hover.run_in_state(GameState::InGame).after(VelocitySystems),
+        );
+    }
+}
+
+/// Component on the enemies that fly above the ground
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Flying {
+    /// How high above the ground the enemy is drawn
+    pub height: f32,
+    /// The gameplay steps that the enemy has been hovering for, which its bob follows
+    steps: u32,
+}
+
+/// How high above the ground a flying fighter hovers after hovering for `steps` gameplay steps
+fn hover_height(meta: &FlyingMeta, steps: u32) -> f32 {
+    if meta.bob_period <= 0.0 {
+        return meta.height;
+    }
+    let time = steps as f32 * GAMEPLAY_STEP.as_secs_f32();
+    meta.height + meta.bob_height * (TAU * time / meta.bob_period).sin()
+}
+
+/// Whether an attack reaches the flying fighters, which it does if it's an anti-air attack, or if
+/// the fighter that made it is in the air too
+pub fn reaches_flying(attack: &Attack, attacker_in_air: bool) -> bool {
+    attack.anti_air || attacker_in_air
+}
+
+/// Keep the flying enemies up in the air, bobbing up and down. The enemies that are launched by an
+/// anti-air hit rise and fall from their height instead, and hover again once they are back at it.
+fn hover(
+    mut fighters: Query<
+        (
+            &mut Flying,
+            &Handle<FighterMeta>,
+            &mut Transform,
+            &mut YSort,
+            &Children,
+            Option<&HitStopped>,
+        ),
+        (Without<Airborne>, Without<Hurtbox>),
+    >,
+    mut hurtboxes: Query<&mut Transform, With<Hurtbox>>,
+    hit_stop: Res<HitStop>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for (mut flying, meta_handle, mut transform, mut ysort, children, hit_stopped) in &mut fighters
+    {
+        let Some(meta) = fighter_assets
+            .get(meta_handle)
+            .and_then(|fighter| fighter.flying.as_ref())
+        else {
+            continue;
+        };
+        if hit_stop.is_stopped(hit_stopped) {
+            continue;
+        }
+
+        flying.steps = flying.steps.wrapping_add(1);
+        let height = hover_height(meta, flying.steps);
+        let rise = height - flying.height;
+        flying.height = height;
+
+        // The enemy is drawn higher up, but stays sorted and hit on the ground under it
+        transform.translation.y += rise;
+        ysort.0 = consts::FIGHTERS_Z + height;
+        lower_hurtboxes(children, &mut hurtboxes, rise);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flying_fighters_bob_around_their_height() {
+        let meta = FlyingMeta {
+            height: 40.0,
+            bob_height: 6.0,
+            bob_period: 1.0,
+        };
+        let steps_per_second = (1.0 / GAMEPLAY_STEP.as_secs_f32()).round() as u32;
+
+        assert_eq!(hover_height(&meta, 0), 40.0);
+        assert!((hover_height(&meta, steps_per_second / 4) - 46.0).abs() < 1e-3);
+        assert!((hover_height(&meta, steps_per_second * 3 / 4) - 34.0).abs() < 1e-3);
+        assert!((hover_height(&meta, steps_per_second) - 40.0).abs() < 1e-3);
+
+        // Without a period, the fighter holds still at its height
+        let still = FlyingMeta {
+            bob_period: 0.0,
+            ..meta
+        };
+        assert_eq!(hover_height(&still, steps_per_second / 4), 40.0);
+    }
+}
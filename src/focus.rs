@@ -0,0 +1,112 @@
+//! What the game does while its window isn't focused, following the [`BackgroundSettings`].
+//!
+//! The [`BackgroundSettings`] resource is kept up to date with the saved settings, and the
+//! [`WindowFocus`] resource with the focus events of the primary window. Single-player games are
+//! paused when the window loses focus, so that the player doesn't come back to a lost fight, but
+//! games with more players keep going, because the other players may still be playing. The audio
+//! can be muted while the window is in the background, and the [video](crate::video) module draws
+//! fewer frames then.
+
+use bevy::{
+    prelude::*,
+    window::{WindowFocused, WindowId},
+};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    audio::{EffectsChannel, MusicChannel, CHANNEL_VOLUME},
+    menu_stack::{MenuLayer, MenuStack},
+    metadata::{BackgroundSettings, Settings},
+    player::Player,
+    ui::attract_mode,
+    GameState,
+};
+
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BackgroundSettings>()
+            .init_resource::<WindowFocus>()
+            .add_system_to_stage(CoreStage::PreUpdate, track_window_focus)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_background_settings.run_if_resource_exists::<Settings>(),
+            )
+            .add_system(
+                pause_on_focus_loss
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_not(attract_mode::demo_playing),
+            )
+            .add_system(mute_in_background);
+    }
+}
+
+/// Whether the primary window is focused
+#[derive(Resource, Deref)]
+pub struct WindowFocus(bool);
+
+impl Default for WindowFocus {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+fn track_window_focus(mut events: EventReader<WindowFocused>, mut focus: ResMut<WindowFocus>) {
+    for event in events.iter() {
+        // Only changes of the focus count as changes of the resource
+        if event.id == WindowId::primary() && **focus != event.focused {
+            focus.0 = event.focused;
+        }
+    }
+}
+
+/// Update the background settings when the settings have changed
+fn apply_background_settings(
+    settings: Res<Settings>,
+    mut background_settings: ResMut<BackgroundSettings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let background = settings.background;
+
+    if *background_settings != background {
+        debug!(?background, "Setting background settings");
+        *background_settings = background;
+    }
+}
+
+fn pause_on_focus_loss(
+    mut menu_stack: ResMut<MenuStack>,
+    focus: Res<WindowFocus>,
+    background_settings: Res<BackgroundSettings>,
+    players: Query<(), With<Player>>,
+) {
+    if focus.is_changed()
+        && !**focus
+        && background_settings.pause_on_focus_loss
+        && players.iter().count() == 1
+    {
+        menu_stack.push(MenuLayer::Paused);
+    }
+}
+
+fn mute_in_background(
+    focus: Res<WindowFocus>,
+    background_settings: Res<BackgroundSettings>,
+    music_channel: Res<AudioChannel<MusicChannel>>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+) {
+    if !focus.is_changed() && !background_settings.is_changed() {
+        return;
+    }
+
+    let muted = background_settings.mute_in_background && !**focus;
+    let volume = if muted { 0.0 } else { CHANNEL_VOLUME };
+    music_channel.set_volume(volume);
+    effects_channel.set_volume(volume);
+}
@@ -0,0 +1,71 @@
+//! The unlocks of the extras menu, where the concept art, music, and enemy bios of the game's
+//! [`GalleryMeta`](crate::metadata::GalleryMeta) are shown.
+//!
+//! The entries are unlocked by their [`UnlockCondition`], which is checked against the progress
+//! that the game already keeps in the [`Storage`]: the [`CampaignProgress`] and the survival
+//! [`HighScores`]. So unlocking an entry doesn't have to be saved by itself, and entries added to
+//! the game later are unlocked for players who already did what they ask for.
+
+use crate::{
+    metadata::UnlockCondition, new_game_plus::CampaignProgress, platform::Storage,
+    survival::HighScores,
+};
+
+/// The progress that the entries of the extras menu are unlocked by
+#[derive(Default, Clone, Debug)]
+pub struct Unlocks {
+    pub campaign: CampaignProgress,
+    /// The best score of a survival run, not counting the daily challenges
+    pub best_survival_score: u32,
+}
+
+impl Unlocks {
+    /// Get the progress saved in the storage
+    pub fn load(storage: &mut Storage) -> Self {
+        let campaign = storage
+            .get::<CampaignProgress>(CampaignProgress::STORAGE_KEY)
+            .unwrap_or_default();
+        let best_survival_score = storage
+            .get::<HighScores>(HighScores::STORAGE_KEY)
+            .unwrap_or_default()
+            .0
+            .iter()
+            .map(|high_score| high_score.score)
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            campaign,
+            best_survival_score,
+        }
+    }
+
+    pub fn is_unlocked(&self, condition: UnlockCondition) -> bool {
+        match condition {
+            UnlockCondition::Always => true,
+            UnlockCondition::CampaignCompleted => self.campaign.completed,
+            UnlockCondition::NewGamePlusCompleted => self.campaign.new_game_plus_completions > 0,
+            UnlockCondition::SurvivalScore(score) => self.best_survival_score >= score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn entries_are_unlocked_by_progress() {
+        let mut unlocks = Unlocks::default();
+        assert!(unlocks.is_unlocked(UnlockCondition::Always));
+        assert!(!unlocks.is_unlocked(UnlockCondition::CampaignCompleted));
+        assert!(!unlocks.is_unlocked(UnlockCondition::SurvivalScore(1000)));
+
+        unlocks.campaign.completed = true;
+        unlocks.best_survival_score = 1000;
+        assert!(unlocks.is_unlocked(UnlockCondition::CampaignCompleted));
+        assert!(!unlocks.is_unlocked(UnlockCondition::NewGamePlusCompleted));
+        assert!(unlocks.is_unlocked(UnlockCondition::SurvivalScore(1000)));
+        assert!(!unlocks.is_unlocked(UnlockCondition::SurvivalScore(1001)));
+    }
+}
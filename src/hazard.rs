@@ -0,0 +1,306 @@
+//! Hazards in the background of a level, like hanging crates and electrical panels, that players
+//! can attack to drop or set off onto the enemies around them.
+//!
+//! A hazard goes off once it has been hit [enough times](HazardTriggerMeta::hits) and there are
+//! [enough enemies](HazardTriggerMeta::min_enemies) in its damage area, waiting for the enemies if
+//! it has to. It then hurts the enemies in the area for a moment, and stays triggered until its
+//! cooldown is over, or for the rest of the level if it doesn't have one.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::Attack,
+    camera::YSort,
+    collision::{BodyLayers, PhysicsBundle},
+    consts,
+    enemy::Enemy,
+    fighter_state::Dying,
+    lifetime::Lifetime,
+    metadata::{HazardMeta, HazardTriggerMeta},
+    sprite_atlas::ItemSprites,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(load_hazards.run_in_state(GameState::InGame))
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::STATES,
+                update_hazards.run_in_state(GameState::InGame),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                hit_hazards.run_in_state(GameState::InGame),
+            );
+    }
+}
+
+/// A hazard of the level
+#[derive(Component)]
+pub struct Hazard {
+    pub meta: HazardMeta,
+    /// How many times the hazard has been hit since it was last ready
+    hits: u32,
+    /// Counts down to the hazard being ready again, while it's triggered
+    cooldown: Option<Timer>,
+    triggered: bool,
+}
+
+impl Hazard {
+    pub fn new(meta: HazardMeta) -> Self {
+        Self {
+            meta,
+            hits: 0,
+            cooldown: None,
+            triggered: false,
+        }
+    }
+
+    fn trigger_meta(&self) -> &HazardTriggerMeta {
+        &self.meta.trigger
+    }
+
+    /// Count a hit, which doesn't do anything while the hazard is triggered
+    fn hit(&mut self) {
+        if !self.triggered {
+            self.hits = self.hits.saturating_add(1);
+        }
+    }
+
+    /// Whether the hazard goes off, with the given number of enemies in its damage area
+    fn should_trigger(&self, enemies_in_area: usize) -> bool {
+        !self.triggered
+            && self.hits >= self.trigger_meta().hits
+            && enemies_in_area >= self.trigger_meta().min_enemies
+    }
+
+    fn trigger(&mut self) {
+        self.triggered = true;
+        self.hits = 0;
+        self.cooldown = self
+            .trigger_meta()
+            .cooldown
+            .map(|cooldown| Timer::from_seconds(cooldown.max(0.0), TimerMode::Once));
+    }
+
+    /// Count down the cooldown, returning whether the hazard just became ready again
+    fn tick(&mut self, delta: std::time::Duration) -> bool {
+        let Some(cooldown) = &mut self.cooldown else {
+            return false;
+        };
+        if cooldown.tick(delta).finished() {
+            self.cooldown = None;
+            self.triggered = false;
+            return true;
+        }
+        false
+    }
+
+    /// The center and the half size of the damage area of a hazard at `translation`
+    fn damage_area(&self, translation: Vec2) -> (Vec2, Vec2) {
+        let area = &self.meta.damage_area;
+        (translation + area.offset, area.size / 2.0)
+    }
+}
+
+/// Bundle for the stub of a hazard, which gets its sprite once its image has loaded
+#[derive(Bundle)]
+pub struct HazardBundle {
+    pub hazard: Hazard,
+    #[bundle]
+    pub transform_bundle: TransformBundle,
+    pub name: Name,
+}
+
+impl HazardBundle {
+    pub fn new(meta: &HazardMeta) -> Self {
+        // Hazards stand in the level like the breakable boxes, so they are sorted with the fighters
+        let mut translation = meta.location + Vec3::new(0.0, consts::GROUND_Y, 0.0);
+        translation.z = YSort(consts::FIGHTERS_Z).z(translation.y);
+
+        Self {
+            hazard: Hazard::new(meta.clone()),
+            transform_bundle: TransformBundle::from_transform(Transform::from_translation(
+                translation,
+            )),
+            name: Name::new("Hazard"),
+        }
+    }
+}
+
+/// Give the hazard stubs their sprites and the hurtboxes that attacks hit them on
+fn load_hazards(
+    mut commands: Commands,
+    hazards: Query<(Entity, &Transform, &Hazard), Without<TextureAtlasSprite>>,
+    mut item_sprites: ItemSprites,
+) {
+    for (entity, transform, hazard) in &hazards {
+        let (texture_atlas, sprite) = item_sprites.get(&hazard.meta.image);
+
+        let mut physics_bundle =
+            PhysicsBundle::new(&hazard.meta.hurtbox, BodyLayers::BREAKABLE_ITEM);
+        physics_bundle.collision_groups.filters = BodyLayers::PLAYER_ATTACK;
+        let hurtbox = commands
+            .spawn((
+                physics_bundle,
+                TransformBundle::from_transform(Transform::from_translation(
+                    hazard.meta.hurtbox.offset.extend(0.0),
+                )),
+            ))
+            .id();
+
+        commands
+            .entity(entity)
+            .insert(SpriteSheetBundle {
+                texture_atlas,
+                sprite,
+                transform: *transform,
+                ..default()
+            })
+            .add_child(hurtbox);
+    }
+}
+
+/// Count the attacks that hit the hazards
+fn hit_hazards(
+    mut events: EventReader<CollisionEvent>,
+    attacks: Query<(), With<Attack>>,
+    hurtboxes: Query<&Parent, Without<Attack>>,
+    mut hazards: Query<&mut Hazard>,
+) {
+    for event in events.iter() {
+        let CollisionEvent::Started(e1, e2, _) = event else {
+            continue;
+        };
+        let hurtbox = if attacks.contains(*e1) {
+            *e2
+        } else if attacks.contains(*e2) {
+            *e1
+        } else {
+            continue;
+        };
+
+        if let Ok(mut hazard) = hurtboxes
+            .get(hurtbox)
+            .and_then(|parent| hazards.get_mut(parent.get()))
+        {
+            hazard.hit();
+        }
+    }
+}
+
+/// Set off the hazards that are ready to go off, and get the triggered ones ready again
+fn update_hazards(
+    mut commands: Commands,
+    mut hazards: Query<(
+        &mut Hazard,
+        &Transform,
+        &mut Handle<TextureAtlas>,
+        &mut TextureAtlasSprite,
+    )>,
+    enemies: Query<&Transform, (With<Enemy>, Without<Dying>, Without<Hazard>)>,
+    mut item_sprites: ItemSprites,
+) {
+    for (mut hazard, transform, mut texture_atlas, mut sprite) in &mut hazards {
+        if hazard.tick(GAMEPLAY_STEP) {
+            (*texture_atlas, *sprite) = item_sprites.get(&hazard.meta.image);
+        }
+
+        let (center, half_size) = hazard.damage_area(transform.translation.truncate());
+        let enemies_in_area = enemies
+            .iter()
+            .filter(|enemy| {
+                let offset = enemy.translation.truncate() - center;
+                offset.abs().cmple(half_size).all()
+            })
+            .count();
+        if !hazard.should_trigger(enemies_in_area) {
+            continue;
+        }
+        hazard.trigger();
+
+        if let Some(image) = &hazard.meta.triggered_image {
+            (*texture_atlas, *sprite) = item_sprites.get(image);
+        }
+
+        // Hurt the enemies in the damage area for a moment
+        let meta = &hazard.meta;
+        commands.spawn((
+            TransformBundle::from_transform(Transform::from_translation(center.extend(0.0))),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+            CollisionGroups::new(BodyLayers::PLAYER_ATTACK, BodyLayers::ENEMY),
+            Collider::cuboid(half_size.x, half_size.y),
+            Attack {
+                damage: meta.damage,
+                pushback: meta.pushback,
+                hitstun_duration: meta.hitstun_duration,
+                hitbox_meta: None,
+                status_effect: None,
+                launch: 0.0,
+                anti_air: false,
+            },
+            Lifetime(Timer::from_seconds(
+                meta.active_time.max(0.0),
+                TimerMode::Once,
+            )),
+            Name::new("Hazard Attack"),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::metadata::{ColliderMeta, ImageMeta};
+
+    use super::*;
+
+    #[test]
+    fn hazard_waits_for_hits_and_enemies() {
+        let mut hazard = Hazard::new(HazardMeta {
+            location: Vec3::ZERO,
+            image: ImageMeta {
+                image: String::new(),
+                image_size: Vec2::ONE,
+                image_handle: default(),
+            },
+            triggered_image: None,
+            hurtbox: ColliderMeta::default(),
+            trigger: HazardTriggerMeta {
+                hits: 2,
+                min_enemies: 1,
+                cooldown: Some(1.0),
+            },
+            damage_area: ColliderMeta::default(),
+            damage: 10,
+            pushback: Vec2::ZERO,
+            hitstun_duration: 0.5,
+            active_time: 0.2,
+        });
+
+        hazard.hit();
+        assert!(!hazard.should_trigger(3));
+        hazard.hit();
+        assert!(!hazard.should_trigger(0));
+        assert!(hazard.should_trigger(1));
+
+        // Hits don't count while the hazard is cooling down
+        hazard.trigger();
+        hazard.hit();
+        hazard.hit();
+        assert!(!hazard.should_trigger(1));
+        assert!(!hazard.tick(Duration::from_millis(500)));
+        assert!(hazard.tick(Duration::from_millis(500)));
+        assert!(!hazard.should_trigger(1));
+    }
+}
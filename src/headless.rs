@@ -0,0 +1,109 @@
+//! Running the game without a window, for servers.
+//!
+//! In headless mode, enabled with `--headless`, the game is run without the windowing, rendering,
+//! and UI plugins. The simulation is updated by Bevy's schedule runner at the rate of the gameplay
+//! timestep, and the first level is started as soon as the game has loaded, and started again
+//! whenever the game is over. The [`HeadlessLevel`] resource may be inserted to play a different
+//! level.
+//!
+//! Servers that host online sessions also run the [netplay relay](crate::relay) with `--relay`,
+//! which plays the level with the inputs of the players of the session.
+//!
+//! The gameplay systems still use the primary window to work out how far the camera can see, so a
+//! virtual window with the default size is added in place of a real one.
+
+use std::time::Duration;
+
+use bevy::{
+    app::{ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    prelude::*,
+    time::{create_time_channels, TimeSender},
+    utils::Instant,
+    window::WindowId,
+};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    metadata::{GameMeta, LevelHandle, LevelMeta},
+    timestep::GAMEPLAY_STEP,
+    GameState,
+};
+
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let descriptor = WindowDescriptor::default();
+        let window = Window::new(
+            WindowId::primary(),
+            &descriptor,
+            descriptor.width as u32,
+            descriptor.height as u32,
+            1.0,
+            None,
+            None,
+        );
+        app.world.resource_mut::<Windows>().add(window);
+
+        // Update at the rate of the gameplay steps, unless another rate has been set already
+        if !app.world.contains_resource::<ScheduleRunnerSettings>() {
+            app.insert_resource(ScheduleRunnerSettings::run_loop(GAMEPLAY_STEP));
+        }
+
+        app.add_plugin(ScheduleRunnerPlugin)
+            .add_enter_system(GameState::MainMenu, start_level);
+    }
+}
+
+/// Makes every frame run exactly one gameplay step, as fast as possible.
+///
+/// This replaces the system clock with one that is advanced by [`GAMEPLAY_STEP`] at the end of
+/// every frame, so that the simulation doesn't depend on how fast the machine running it is. It
+/// has to be added before the [`HeadlessPlugin`].
+pub struct SteppedClockPlugin;
+
+impl Plugin for SteppedClockPlugin {
+    fn build(&self, app: &mut App) {
+        let (time_receiver, time_sender) = create_time_channels();
+
+        app.insert_resource(time_receiver)
+            .insert_resource(SteppedClock {
+                sender: time_sender,
+                now: Instant::now(),
+            })
+            .insert_resource(ScheduleRunnerSettings::run_loop(Duration::ZERO))
+            .add_system_to_stage(CoreStage::Last, advance_clock);
+    }
+}
+
+/// The clock that the game's [`Time`] is updated from
+#[derive(Resource)]
+struct SteppedClock {
+    sender: TimeSender,
+    now: Instant,
+}
+
+fn advance_clock(mut clock: ResMut<SteppedClock>) {
+    clock.now += GAMEPLAY_STEP;
+    let now = clock.now;
+    clock.sender.0.send(now).ok();
+}
+
+/// The level to play in headless mode, instead of the first level of the game
+#[derive(Resource)]
+pub struct HeadlessLevel(pub Handle<LevelMeta>);
+
+/// Start the level in place of showing the main menu
+fn start_level(
+    mut commands: Commands,
+    game: Res<GameMeta>,
+    headless_level: Option<Res<HeadlessLevel>>,
+) {
+    info!("Starting level");
+    let level = match headless_level {
+        Some(level) => level.0.clone(),
+        None => game.start_level_handle.clone(),
+    };
+    commands.insert_resource(LevelHandle(level));
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
@@ -0,0 +1,198 @@
+//! Hit-stop, the few frames that the fight freezes for when an attack connects, which gives the
+//! hits their weight.
+//!
+//! Every hit freezes for a duration that grows with its damage, up to the longest freeze of the
+//! game's [`HitStopMeta`]. A global hit-stop freezes the whole fight through the [`HitStop`]
+//! resource, and otherwise only the fighter that is hit and the one that hit it are frozen, with
+//! the [`HitStopped`] component. The animations and the movement stand still while frozen, and
+//! carry on where they were once the freeze is over.
+//!
+//! Only the timers of the states that the hits put fighters in wait for the freeze: hitstun, being
+//! airborne, being thrown, and the bobbing of flying fighters. The other states are not held up,
+//! so a fighter that is dodging or firing a volley of projectiles keeps counting down its timers
+//! while its animation stands still. Only fighters are frozen on their own, so the projectiles,
+//! which have no fighter at their root, aren't.
+//!
+//! The freezes are counted in gameplay steps instead of in real time, so that recorded inputs play
+//! out the same way, and so that they are slowed down along with the rest of the gameplay.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::AttackDamageSystem,
+    combat_events::HitLanded,
+    damage::Health,
+    metadata::{GameMeta, HitStopMeta},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    utils::ResetEvent,
+    GameState,
+};
+
+pub struct HitStopPlugin;
+
+impl Plugin for HitStopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HitStop>()
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                tick_hit_stop.run_in_state(GameState::InGame),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                start_hit_stop
+                    .run_in_state(GameState::InGame)
+                    .after(tick_hit_stop)
+                    .after(AttackDamageSystem),
+            )
+            .add_exit_system(GameState::InGame, clear_hit_stop)
+            .add_system(clear_hit_stop_on_reset);
+    }
+}
+
+/// The global hit-stop, which freezes the animations and the movement of every entity while it
+/// lasts
+#[derive(Resource, Default, Debug)]
+pub struct HitStop {
+    /// The gameplay steps left until the freeze is over
+    steps: u32,
+}
+
+impl HitStop {
+    /// Whether the fight is frozen in the current step
+    pub fn is_active(&self) -> bool {
+        self.steps > 0
+    }
+
+    /// Whether an entity, which might have a hit-stop of its own, is frozen in the current step
+    pub fn is_stopped(&self, hit_stopped: Option<&HitStopped>) -> bool {
+        self.is_active() || hit_stopped.is_some()
+    }
+}
+
+/// Component on the entities that are frozen by a hit-stop of their own
+#[derive(Component, Debug)]
+pub struct HitStopped {
+    /// The gameplay steps left until the freeze is over
+    steps: u32,
+}
+
+/// The number of gameplay steps that a hit of `damage` freezes for
+fn hit_stop_steps(meta: &HitStopMeta, damage: i32) -> u32 {
+    let duration = (meta.duration + meta.duration_per_damage * damage.max(0) as f32)
+        .min(meta.max_duration)
+        .max(0.0);
+    (duration / GAMEPLAY_STEP.as_secs_f32()).round() as u32
+}
+
+fn tick_hit_stop(
+    mut commands: Commands,
+    mut hit_stop: ResMut<HitStop>,
+    mut hit_stopped: Query<(Entity, &mut HitStopped)>,
+) {
+    if hit_stop.steps > 0 {
+        hit_stop.steps -= 1;
+    }
+    for (entity, mut stopped) in &mut hit_stopped {
+        stopped.steps = stopped.steps.saturating_sub(1);
+        if stopped.steps == 0 {
+            commands.entity(entity).remove::<HitStopped>();
+        }
+    }
+}
+
+/// Freeze the fight, or the fighters of the hit, when an attack connects
+fn start_hit_stop(
+    mut commands: Commands,
+    mut damage_events: EventReader<HitLanded>,
+    mut hit_stop: ResMut<HitStop>,
+    hit_stopped: Query<&HitStopped>,
+    parents: Query<&Parent>,
+    fighters: Query<(), With<Health>>,
+    game: Res<GameMeta>,
+) {
+    let Some(meta) = &game.hit_stop else {
+        damage_events.clear();
+        return;
+    };
+
+    for event in damage_events.iter() {
+        // The damage of status effects like burning isn't a hit that connects
+        if event.damageing_entity == event.damaged_entity {
+            continue;
+        }
+        let steps = hit_stop_steps(meta, event.damage);
+        if steps == 0 {
+            continue;
+        }
+
+        if meta.global {
+            hit_stop.steps = hit_stop.steps.max(steps);
+            continue;
+        }
+
+        // Attacks are spawned as children of the fighters, or of the weapons that they hold, so
+        // the one that hit is at the root of the attack
+        let mut attacker = event.damageing_entity;
+        while let Ok(parent) = parents.get(attacker) {
+            attacker = parent.get();
+        }
+        // Projectiles are at the root of their own attacks, and are parked in their pool once they
+        // have hit, so only the fighters are frozen
+        for entity in [event.damaged_entity, attacker]
+            .into_iter()
+            .filter(|entity| fighters.contains(*entity))
+        {
+            let steps = hit_stopped
+                .get(entity)
+                .map_or(steps, |stopped| stopped.steps.max(steps));
+            commands.entity(entity).insert(HitStopped { steps });
+        }
+    }
+}
+
+fn clear_hit_stop(
+    mut commands: Commands,
+    mut hit_stop: ResMut<HitStop>,
+    hit_stopped: Query<Entity, With<HitStopped>>,
+) {
+    hit_stop.steps = 0;
+    for entity in &hit_stopped {
+        commands.entity(entity).remove::<HitStopped>();
+    }
+}
+
+/// Clear the hit-stop when the level is reset, so that the next one doesn't start frozen
+fn clear_hit_stop_on_reset(
+    mut reset_events: EventReader<ResetEvent>,
+    commands: Commands,
+    hit_stop: ResMut<HitStop>,
+    hit_stopped: Query<Entity, With<HitStopped>>,
+) {
+    if reset_events.iter().any(|reset| reset.scope.resets_level()) {
+        clear_hit_stop(commands, hit_stop, hit_stopped);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stronger_hits_freeze_for_longer() {
+        let meta = HitStopMeta {
+            duration: 2.0 / 60.0,
+            duration_per_damage: 1.0 / 60.0,
+            max_duration: 8.0 / 60.0,
+            global: false,
+        };
+
+        assert_eq!(hit_stop_steps(&meta, 0), 2);
+        assert_eq!(hit_stop_steps(&meta, 3), 5);
+        // The freeze is capped, and healing doesn't shorten it
+        assert_eq!(hit_stop_steps(&meta, 100), 8);
+        assert_eq!(hit_stop_steps(&meta, -10), 2);
+    }
+}
@@ -1,5 +1,11 @@
-use leafwing_input_manager::Actionlike;
-use serde::Deserialize;
+use bevy::{input::gamepad::GamepadEventType, prelude::*, reflect::TypeUuid, utils::HashMap};
+use leafwing_input_manager::{prelude::*, Actionlike};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    metadata::LevelMeta,
+    player::{Player, PlayerIndex},
+};
 
 #[derive(Debug, Copy, Clone, Actionlike, Deserialize, Eq, PartialEq, Hash)]
 pub enum PlayerAction {
@@ -8,6 +14,13 @@ pub enum PlayerAction {
     Attack,
     Throw,
     Shoot,
+    /// Blocks the hits taken while it's held, and parries the ones right after it's pressed
+    Block,
+    /// Rolls out of the way in the direction that is held, with a few invincibility frames
+    Dodge,
+    /// Starts a team-up attack with another player
+    Special,
+    Pause,
 }
 
 #[derive(Debug, Copy, Clone, Actionlike, Deserialize, Eq, PartialEq, Hash)]
@@ -18,6 +31,192 @@ pub enum MenuAction {
     Right,
     Confirm,
     Back,
-    Pause,
     ToggleFullscreen,
 }
+
+/// The kind of device that a player is controlled with, which the buttons shown to them are for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputDevice {
+    Keyboard,
+    Gamepad,
+}
+
+/// The device that each player used last
+#[derive(Resource, Default, Debug)]
+pub struct InputDevices(HashMap<usize, InputDevice>);
+
+impl InputDevices {
+    /// Get the device that the player with the given index used last
+    pub fn get(&self, player_idx: usize) -> InputDevice {
+        self.0.get(&player_idx).copied().unwrap_or(
+            // Only the first two players have keyboard controls
+            if player_idx < 2 {
+                InputDevice::Keyboard
+            } else {
+                InputDevice::Gamepad
+            },
+        )
+    }
+}
+
+/// Keep track of the device that each player used last
+pub fn track_input_devices(
+    mut devices: ResMut<InputDevices>,
+    keys: Res<Input<KeyCode>>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    players: Query<(&PlayerIndex, &ActionState<PlayerAction>), With<Player>>,
+) {
+    // Every player is given the gamepad with their index
+    let mut gamepads_used = Vec::new();
+    for event in gamepad_events.iter() {
+        let used = match event.event_type {
+            GamepadEventType::ButtonChanged(_, value) => value > 0.5,
+            GamepadEventType::AxisChanged(_, value) => value.abs() > 0.5,
+            _ => false,
+        };
+        if used {
+            gamepads_used.push(event.gamepad.id);
+        }
+    }
+    for gamepad in gamepads_used.iter().copied() {
+        if devices.get(gamepad) != InputDevice::Gamepad {
+            devices.0.insert(gamepad, InputDevice::Gamepad);
+        }
+    }
+
+    // The keyboard is shared by the first two players, so a key press is given to the players that
+    // it performed an action for
+    if keys.get_just_pressed().next().is_none() {
+        return;
+    }
+    for (player_index, action_state) in &players {
+        if player_index.0 < 2
+            && !gamepads_used.contains(&player_index.0)
+            && !action_state.get_just_pressed().is_empty()
+            && devices.get(player_index.0) != InputDevice::Keyboard
+        {
+            devices.0.insert(player_index.0, InputDevice::Keyboard);
+        }
+    }
+}
+
+/// Recorded player inputs for playing a level, loaded from a YAML file.
+///
+/// Input scripts are played by the determinism check, and by the attract mode of the main menu,
+/// which loads them as assets with the `.demo.yaml` extension. Bug reports save the last inputs of
+/// the players as an input script.
+#[derive(TypeUuid, Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[uuid = "d1d8ebf5-1bf4-4616-9ec1-440ec60a8dc1"]
+pub struct InputScript {
+    /// The asset path of the level to play, relative to the asset directory
+    pub level: String,
+    #[serde(skip)]
+    pub level_handle: Handle<LevelMeta>,
+    /// The seed of the [`GameRng`](crate::rng::GameRng)
+    pub seed: u64,
+    /// The number of gameplay steps to play
+    pub steps: u32,
+    /// How many times the determinism check plays the level
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    /// The checksum that the runs of the determinism check must end with, if it has been recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// The inputs of every player, as a list of segments that are played one after the other.
+    /// Players have no inputs after their last segment.
+    pub players: Vec<Vec<InputSegment>>,
+}
+
+pub fn default_runs() -> usize {
+    2
+}
+
+/// Inputs that are held for a number of steps
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct InputSegment {
+    pub steps: u32,
+    #[serde(rename = "move")]
+    pub movement: Vec2,
+    pub attack: bool,
+    pub throw: bool,
+    pub shoot: bool,
+    pub block: bool,
+    pub dodge: bool,
+}
+
+impl InputScript {
+    /// Get the inputs of a player in the given step
+    pub fn input_at(&self, player: usize, step: u32) -> InputSegment {
+        let mut start = 0;
+        for segment in self.players.get(player).into_iter().flatten() {
+            if step < start + segment.steps {
+                return segment.clone();
+            }
+            start += segment.steps;
+        }
+
+        InputSegment::default()
+    }
+}
+
+impl InputSegment {
+    /// Press and release the actions of a player to match these inputs
+    pub fn apply(&self, action_state: &mut ActionState<PlayerAction>) {
+        let buttons = [
+            (PlayerAction::Attack, self.attack),
+            (PlayerAction::Throw, self.throw),
+            (PlayerAction::Shoot, self.shoot),
+            (PlayerAction::Block, self.block),
+            (PlayerAction::Dodge, self.dodge),
+            (PlayerAction::Move, self.movement != Vec2::ZERO),
+        ];
+        for (action, pressed) in buttons {
+            match (pressed, action_state.pressed(action)) {
+                (true, false) => action_state.press(action),
+                (false, true) => action_state.release(action),
+                _ => (),
+            }
+        }
+        action_state.action_data_mut(PlayerAction::Move).axis_pair =
+            Some(DualAxisData::new(self.movement.x, self.movement.y));
+    }
+}
+
+/// Stop the input devices from controlling the players, so that only an input script does
+pub fn disconnect_player_inputs(
+    mut commands: Commands,
+    players: Query<Entity, (With<Player>, With<InputMap<PlayerAction>>)>,
+) {
+    for entity in &players {
+        commands.entity(entity).remove::<InputMap<PlayerAction>>();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn input_script_plays_segments_in_order() {
+        let script: InputScript = serde_yaml::from_str(
+            "
+            level: levels/test.level.yaml
+            seed: 1
+            steps: 10
+            players:
+              - - { steps: 2, move: [1.0, 0.0] }
+                - { steps: 1, attack: true }
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(script.runs, 2);
+        assert_eq!(script.input_at(0, 0).movement, Vec2::X);
+        assert_eq!(script.input_at(0, 1).movement, Vec2::X);
+        assert!(script.input_at(0, 2).attack);
+        assert_eq!(script.input_at(0, 3), InputSegment::default());
+        assert_eq!(script.input_at(1, 0), InputSegment::default());
+    }
+}
@@ -0,0 +1,121 @@
+//! Things in the level that the players can interact with, like the items that are picked up.
+//!
+//! An [`Interactable`] is interacted with by pressing its action while within its radius. The
+//! [interaction prompt](crate::ui::interaction_prompt) shows every player the nearest thing they
+//! can interact with, with the buttons of the device they used last, while the interactions
+//! themselves are performed by the systems of the things, like the grabbing state for the items.
+
+use bevy::prelude::*;
+
+use crate::{
+    fighter::Inventory,
+    input::{track_input_devices, InputDevices, PlayerAction},
+    metadata::ItemKind,
+};
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputDevices>()
+            .add_system(track_input_devices);
+    }
+}
+
+/// Something that the players can interact with by pressing an action near it
+#[derive(Component, Clone, Debug)]
+pub struct Interactable {
+    /// How close the players have to be to interact
+    pub radius: f32,
+    /// The action that interacts
+    pub action: PlayerAction,
+    /// The localization key of the prompt, like `interact-pick-up`
+    pub prompt: String,
+    pub condition: InteractionCondition,
+}
+
+/// What a player needs for an [`Interactable`] to be interacted with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InteractionCondition {
+    #[default]
+    Always,
+    /// The player isn't holding an item
+    EmptyHands,
+    /// The player is holding a melee weapon, like for coating it
+    HoldingMeleeWeapon,
+}
+
+impl Interactable {
+    pub fn new(radius: f32, action: PlayerAction, prompt: impl Into<String>) -> Self {
+        Self {
+            radius,
+            action,
+            prompt: prompt.into(),
+            condition: InteractionCondition::Always,
+        }
+    }
+
+    pub fn with_condition(mut self, condition: InteractionCondition) -> Self {
+        self.condition = condition;
+        self
+    }
+
+    /// Whether a player holding the items in the inventory can interact with this
+    pub fn available_to(&self, inventory: &Inventory) -> bool {
+        match self.condition {
+            InteractionCondition::Always => true,
+            InteractionCondition::EmptyHands => inventory.is_none(),
+            InteractionCondition::HoldingMeleeWeapon => matches!(
+                inventory.0.as_ref().map(|item| &item.kind),
+                Some(ItemKind::MeleeWeapon { .. })
+            ),
+        }
+    }
+}
+
+/// Find the nearest of the interactables that a player at the position is within the radius of
+pub fn nearest_interactable<'a>(
+    position: Vec2,
+    interactables: impl IntoIterator<Item = (Entity, Vec2, &'a Interactable)>,
+) -> Option<(Entity, &'a Interactable)> {
+    interactables
+        .into_iter()
+        .map(|(entity, interactable_position, interactable)| {
+            (
+                entity,
+                interactable,
+                position.distance_squared(interactable_position),
+            )
+        })
+        .filter(|(_, interactable, distance_squared)| {
+            *distance_squared <= interactable.radius * interactable.radius
+        })
+        .min_by(|(.., a), (.., b)| a.total_cmp(b))
+        .map(|(entity, interactable, _)| (entity, interactable))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nearest_interactable_in_reach_is_found() {
+        let near = Interactable::new(10.0, PlayerAction::Throw, "interact-pick-up");
+        let far = Interactable::new(100.0, PlayerAction::Throw, "interact-pick-up");
+        let interactables = [
+            (Entity::from_raw(0), Vec2::new(20.0, 0.0), &near),
+            (Entity::from_raw(1), Vec2::new(50.0, 0.0), &far),
+        ];
+
+        // The nearer one is out of its reach
+        assert_eq!(
+            nearest_interactable(Vec2::ZERO, interactables).map(|(entity, _)| entity),
+            Some(Entity::from_raw(1))
+        );
+        assert_eq!(
+            nearest_interactable(Vec2::new(15.0, 0.0), interactables).map(|(entity, _)| entity),
+            Some(Entity::from_raw(0))
+        );
+        assert!(nearest_interactable(Vec2::new(200.0, 0.0), interactables).is_none());
+    }
+}
@@ -1,25 +1,42 @@
 use bevy::{ecs::system::EntityCommands, prelude::*};
 use bevy_mod_js_scripting::{ActiveScripts, JsScript};
 use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
 use rand::Rng;
 
 use crate::{
     animation::{AnimatedSpriteSheetBundle, Animation, Facing},
-    attack::{Attack, AttackFrames, Breakable, BrokeEvent},
+    attack::{Attack, AttackFrames, Breakable, BreakableSystem, BrokeEvent},
+    camera::YSort,
     collision::{BodyLayers, PhysicsBundle},
     consts,
     fighter::Inventory,
+    input::PlayerAction,
+    interaction::{Interactable, InteractionCondition},
     lifetime::{Lifetime, LifetimeExpired},
     metadata::{AttackMeta, ItemKind, ItemMeta, ItemSpawnMeta},
     movement::{AngularVelocity, Force, LinearVelocity},
+    pool::{EntityPoolCommands, PoolKind},
+    rng::GameRng,
+    sprite_atlas::ItemSprites,
+    team::Team,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
 };
 
 pub struct ItemPlugin;
 
 impl Plugin for ItemPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(drop_system)
-            .add_system(explodable_system)
+        app.register_type::<Item>()
+            .register_type::<Drop>()
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                SystemSet::new()
+                    .after(BreakableSystem)
+                    .with_system(drop_system)
+                    .with_system(explodable_system),
+            )
             .add_event::<ScriptItemThrowEvent>()
             .add_event::<ScriptItemGrabEvent>();
     }
@@ -37,7 +54,8 @@ pub struct ScriptItemGrabEvent {
     pub script_handle: Handle<JsScript>,
 }
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Item {
     /// Prevent the spawning of a Sprite component by load_items by setting this to false
     pub spawn_sprite: bool,
@@ -77,6 +95,23 @@ impl ItemBundle {
         let item_meta = items_assets
             .get_mut(&item_spawn_meta.item_handle)
             .expect("Item not loaded!");
+
+        // Items are picked up with the throw button by the grabbing state
+        commands.insert(match &item_meta.kind {
+            ItemKind::Coating { .. } => Interactable::new(
+                consts::PICK_ITEM_RADIUS,
+                PlayerAction::Throw,
+                "interact-coat-weapon",
+            )
+            .with_condition(InteractionCondition::HoldingMeleeWeapon),
+            _ => Interactable::new(
+                consts::PICK_ITEM_RADIUS,
+                PlayerAction::Throw,
+                "interact-pick-up",
+            )
+            .with_condition(InteractionCondition::EmptyHands),
+        });
+
         match &item_meta.kind {
             ItemKind::BreakableBox {
                 hurtbox,
@@ -89,7 +124,16 @@ impl ItemBundle {
                 let mut physics_bundle = PhysicsBundle::new(hurtbox, BodyLayers::BREAKABLE_ITEM);
                 physics_bundle.collision_groups.filters = BodyLayers::PLAYER_ATTACK;
 
-                commands.insert((physics_bundle, Breakable::new(*hits, false)));
+                // Boxes stand up like the fighters, so sort them with the fighters. They never
+                // move, so their Z position only has to be set once.
+                let mut translation = item_spawn_meta.location + ground_offset;
+                translation.z = YSort(consts::FIGHTERS_Z).z(translation.y);
+
+                commands.insert((
+                    TransformBundle::from_transform(Transform::from_translation(translation)),
+                    physics_bundle,
+                    Breakable::new(*hits, false),
+                ));
             }
             ItemKind::Script { script_handle, .. } => {
                 active_scripts.insert(script_handle.clone());
@@ -108,7 +152,7 @@ impl ItemBundle {
 #[derive(Bundle)]
 pub struct Projectile {
     #[bundle]
-    sprite_bundle: SpriteBundle,
+    sprite_bundle: SpriteSheetBundle,
     velocity: LinearVelocity,
     angular_velocity: AngularVelocity,
     force: Force,
@@ -120,6 +164,15 @@ pub struct Projectile {
     attack: Attack,
     lifetime: Lifetime,
     breakable: Breakable,
+    owner: ProjectileOwner,
+}
+
+/// The fighter that threw a projectile, and whether it's an enemy, which decides who the projectile
+/// homes in on
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ProjectileOwner {
+    pub fighter: Entity,
+    pub enemy: bool,
 }
 
 impl Projectile {
@@ -127,14 +180,10 @@ impl Projectile {
         translation: Vec3,
         item_meta: &ItemMeta,
         facing: &Facing,
-        enemy: bool,
+        owner: ProjectileOwner,
+        team: &Team,
+        item_sprites: &mut ItemSprites,
     ) -> Self {
-        let direction_mul = if facing.is_left() {
-            Vec2::new(-1.0, 1.0)
-        } else {
-            Vec2::ONE
-        };
-
         let item_vars = match item_meta.kind {
             crate::metadata::ItemKind::Throwable {
                 damage,
@@ -164,49 +213,47 @@ impl Projectile {
             _ => None,
         }
         .expect("Non throwable item");
+        let status_effect = match item_meta.kind {
+            crate::metadata::ItemKind::Throwable { status_effect, .. } => status_effect,
+            _ => None,
+        };
+
+        let (texture_atlas, sprite) = item_sprites.get(&item_meta.image);
 
         Self {
-            sprite_bundle: SpriteBundle {
-                texture: item_meta.image.image_handle.clone(),
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas,
+                sprite,
                 transform: Transform::from_xyz(translation.x, translation.y, consts::PROJECTILE_Z),
                 ..default()
             },
             attack: Attack {
                 damage: item_vars.0,
-                pushback: Vec2::new(item_vars.4, 0.0) * direction_mul,
+                pushback: facing.mirror(Vec2::new(item_vars.4, 0.0)),
                 hitstun_duration: item_vars.5,
                 hitbox_meta: None,
+                status_effect,
+                launch: 0.0,
+                anti_air: false,
             },
-            velocity: LinearVelocity(item_vars.2 * direction_mul),
+            velocity: LinearVelocity(facing.mirror(item_vars.2)),
             // Gravity
             force: Force(Vec2::new(0.0, -item_vars.1)),
-            angular_velocity: AngularVelocity(consts::THROW_ITEM_ROTATION_SPEED * direction_mul.x),
+            angular_velocity: AngularVelocity(consts::THROW_ITEM_ROTATION_SPEED * facing.sign()),
             collider: Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
             sensor: Sensor,
             events: ActiveEvents::COLLISION_EVENTS,
             collision_types: ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
-            //TODO: define collision layer based on the fighter shooting projectile, load for asset
-            //files of fighter which "team" they are on
-            collision_groups: CollisionGroups::new(
-                if enemy {
-                    BodyLayers::ENEMY_ATTACK
-                } else {
-                    BodyLayers::PLAYER_ATTACK
-                },
-                if enemy {
-                    BodyLayers::PLAYER
-                } else {
-                    BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                },
-            ),
+            collision_groups: team.attack_groups(),
             lifetime: Lifetime(Timer::from_seconds(item_vars.3, TimerMode::Once)),
             breakable: Breakable::new(0, false),
+            owner,
         }
     }
 }
 
 /// A component that with Breakable, drops a item when broke.
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect)]
 pub struct Drop {
     /// Item data
     pub item: ItemMeta,
@@ -274,8 +321,8 @@ fn explodable_system(
         Entity,
         Option<&Parent>,
     )>,
-    time: Res<Time>,
     mut inventory: Query<&mut Inventory>,
+    mut pool: EntityPoolCommands,
 ) {
     let mut explosions = Vec::new();
 
@@ -297,7 +344,11 @@ fn explodable_system(
         parent,
     ) in &mut explodables
     {
-        explodable.timer.tick(time.delta());
+        if pool.is_parked(entity) {
+            continue;
+        }
+
+        explodable.timer.tick(GAMEPLAY_STEP);
 
         if !explodable.fusing && explodable.timer.finished() {
             // Stop bomb and start fusing
@@ -320,7 +371,7 @@ fn explodable_system(
                 }
             }
 
-            commands.entity(entity).despawn_recursive();
+            pool.despawn(entity);
         }
     }
 
@@ -362,17 +413,24 @@ fn explodable_system(
                     pushback: attack.velocity.unwrap_or(Vec2::ZERO),
                     hitstun_duration: attack.hitstun_duration,
                     hitbox_meta: Some(explodable.attack.hitbox),
+                    status_effect: attack.status_effect,
+                    launch: attack.launch,
+                    anti_air: attack.anti_air,
                 },
                 explodable.explosion_frames,
                 transform,
             ))
             .id();
 
-        commands
-            .spawn(animated_sprite)
-            .insert(Lifetime(Timer::from_seconds(seconds, TimerMode::Once)))
-            .insert(explodable)
-            .push_children(&[attack_ent]);
+        pool.spawn(
+            PoolKind::Explosion,
+            (
+                animated_sprite,
+                Lifetime(Timer::from_seconds(seconds, TimerMode::Once)),
+                explodable,
+            ),
+        )
+        .push_children(&[attack_ent]);
     }
 }
 
@@ -396,15 +454,10 @@ impl AnimatedProjectile {
     pub fn new(
         item_meta: &ItemMeta,
         facing: &Facing,
+        team: &Team,
         animated_sprite: AnimatedSpriteSheetBundle,
+        rng: &mut GameRng,
     ) -> Self {
-        let direction_mul = if facing.is_left() {
-            Vec2::new(-1.0, 1.0)
-        } else {
-            Vec2::ONE
-        };
-        let mut rng = rand::thread_rng();
-
         let item_vars = match item_meta.kind {
             crate::metadata::ItemKind::Bomb {
                 damage,
@@ -420,23 +473,24 @@ impl AnimatedProjectile {
             sprite_bundle: animated_sprite,
             attack: Attack {
                 damage: item_vars.0,
-                pushback: Vec2::new(consts::ITEM_ATTACK_VELOCITY, 0.0) * direction_mul,
+                pushback: facing.mirror(Vec2::new(consts::ITEM_ATTACK_VELOCITY, 0.0)),
                 hitstun_duration: consts::HITSTUN_DURATION,
                 hitbox_meta: None,
+                status_effect: None,
+                launch: 0.0,
+                anti_air: false,
             },
-            velocity: LinearVelocity(item_vars.2 * direction_mul * rng.gen_range(0.8..1.2)),
+            velocity: LinearVelocity(facing.mirror(item_vars.2) * rng.gen_range(0.8..1.2)),
             // Gravity
             force: Force(Vec2::new(0.0, -item_vars.1)),
             angular_velocity: AngularVelocity(
-                consts::THROW_ITEM_ROTATION_SPEED * direction_mul.x * rng.gen_range(0.8..1.2),
+                consts::THROW_ITEM_ROTATION_SPEED * facing.sign() * rng.gen_range(0.8..1.2),
             ),
             collider: Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
             sensor: Sensor,
             events: ActiveEvents::COLLISION_EVENTS,
             collision_types: ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
-            //TODO: define collision layer based on the fighter shooting projectile, load for asset
-            //files of fighter which "team" they are on
-            collision_groups: CollisionGroups::new(BodyLayers::ENEMY_ATTACK, BodyLayers::PLAYER),
+            collision_groups: team.attack_groups(),
             breakable: Breakable::new(0, false),
         }
     }
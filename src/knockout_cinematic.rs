@@ -0,0 +1,218 @@
+//! The slow motion played when the last enemy of a wave or a boss is knocked out.
+//!
+//! The cinematic is put together from the overrides of the other modules: the
+//! [slow motion](crate::timestep::TimeScale::slow_motion) of the gameplay steps, the
+//! [zoom and focus](CameraOverride) of the camera, and the [desaturation](PostEffectsOverride) of
+//! the post-processing. It eases into the effects on the finishing blow, holds them, and eases back
+//! out of them before the fight resumes. It's only played if the game has a
+//! [`KnockoutCinematicMeta`].
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    camera::CameraOverride,
+    combat_events::Killed,
+    damage::Health,
+    enemy::{Boss, Enemy, SpawnLocationX},
+    menu_stack::MenuLayer,
+    metadata::{GameMeta, KnockoutCinematicMeta, LevelMeta},
+    post_processing::PostEffectsOverride,
+    timestep::TimeScale,
+    utils::ResetEvent,
+    GameState,
+};
+
+/// The part of the cinematic spent easing into the effects
+const EASE_IN: f32 = 0.15;
+
+/// The part of the cinematic spent easing out of the effects
+const EASE_OUT: f32 = 0.3;
+
+pub struct KnockoutCinematicPlugin;
+
+impl Plugin for KnockoutCinematicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_cinematic.run_in_state(GameState::InGame))
+            .add_system(
+                update_cinematic
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<KnockoutCinematic>()
+                    .after(start_cinematic),
+            )
+            .add_exit_system(GameState::InGame, end_cinematic)
+            .add_system(end_cinematic_on_reset);
+    }
+}
+
+/// The knockout cinematic that is playing
+#[derive(Resource)]
+struct KnockoutCinematic {
+    meta: KnockoutCinematicMeta,
+    /// Counts down the real time of the cinematic, which isn't slowed down with the gameplay
+    timer: Timer,
+    /// Where the finishing blow landed
+    focus: Vec2,
+}
+
+/// Start the cinematic when the last enemy of a wave or a boss is knocked out
+fn start_cinematic(
+    mut commands: Commands,
+    mut killed_events: EventReader<Killed>,
+    killed_enemies: Query<(&GlobalTransform, &SpawnLocationX, Option<&Boss>), With<Enemy>>,
+    enemies: Query<(&SpawnLocationX, &Health), With<Enemy>>,
+    level: Option<Res<LevelMeta>>,
+    game: Res<GameMeta>,
+    cinematic: Option<Res<KnockoutCinematic>>,
+) {
+    let Some(meta) = &game.knockout_cinematic else {
+        killed_events.clear();
+        return;
+    };
+    if cinematic.is_some() {
+        killed_events.clear();
+        return;
+    }
+
+    let stop_points = level
+        .as_ref()
+        .map(|level| level.stop_points.as_slice())
+        .unwrap_or_default();
+    for event in killed_events.iter() {
+        let Ok((transform, spawn_x, boss)) = killed_enemies.get(event.killed_entity) else {
+            continue;
+        };
+        let remaining = enemies
+            .iter()
+            .filter(|(_, health)| health.0 > 0)
+            .map(|(SpawnLocationX(x), _)| *x);
+        if boss.is_some() || is_last_of_wave(spawn_x.0, stop_points, remaining) {
+            commands.insert_resource(KnockoutCinematic {
+                meta: meta.clone(),
+                timer: Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once),
+                focus: transform.translation().truncate(),
+            });
+            break;
+        }
+    }
+}
+
+/// Whether none of the enemies that are still standing belong to the wave of an enemy spawned at
+/// `spawn_x`.
+///
+/// The enemies of a wave are the ones that hold the players at the same stop point, so the wave
+/// is made of the enemies spawned before the first stop point after `spawn_x`, or of all the
+/// enemies if there are no stop points after it, like in the survival arena.
+fn is_last_of_wave(
+    spawn_x: f32,
+    stop_points: &[f32],
+    remaining_spawn_xs: impl IntoIterator<Item = f32>,
+) -> bool {
+    let wave_end = stop_points
+        .iter()
+        .copied()
+        .filter(|stop_point| *stop_point >= spawn_x)
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(f32::INFINITY);
+
+    remaining_spawn_xs
+        .into_iter()
+        .all(|remaining_x| remaining_x > wave_end)
+}
+
+/// Get how strong the effects are at `progress` through the cinematic, from `0.0` to `1.0`
+fn intensity(progress: f32) -> f32 {
+    let progress = progress.clamp(0.0, 1.0);
+    let ease = |x: f32| x * x * (3.0 - 2.0 * x);
+
+    if progress < EASE_IN {
+        ease(progress / EASE_IN)
+    } else if progress > 1.0 - EASE_OUT {
+        ease((1.0 - progress) / EASE_OUT)
+    } else {
+        1.0
+    }
+}
+
+fn update_cinematic(
+    mut commands: Commands,
+    mut cinematic: ResMut<KnockoutCinematic>,
+    mut time_scale: ResMut<TimeScale>,
+    mut camera_override: ResMut<CameraOverride>,
+    mut effects_override: ResMut<PostEffectsOverride>,
+    time: Res<Time>,
+) {
+    cinematic.timer.tick(time.delta());
+    if cinematic.timer.finished() {
+        commands.remove_resource::<KnockoutCinematic>();
+        clear_overrides(&mut time_scale, &mut camera_override, &mut effects_override);
+        return;
+    }
+
+    let k = intensity(cinematic.timer.percent());
+    let meta = &cinematic.meta;
+    time_scale.slow_motion = 1.0 + (meta.time_scale - 1.0) * k;
+    camera_override.zoom = 1.0 + (meta.zoom - 1.0) * k;
+    camera_override.focus = Some(cinematic.focus);
+    effects_override.desaturation = meta.desaturation * k;
+}
+
+fn clear_overrides(
+    time_scale: &mut TimeScale,
+    camera_override: &mut CameraOverride,
+    effects_override: &mut PostEffectsOverride,
+) {
+    time_scale.slow_motion = 1.0;
+    *camera_override = CameraOverride::default();
+    *effects_override = PostEffectsOverride::default();
+}
+
+fn end_cinematic(
+    mut commands: Commands,
+    mut time_scale: ResMut<TimeScale>,
+    mut camera_override: ResMut<CameraOverride>,
+    mut effects_override: ResMut<PostEffectsOverride>,
+) {
+    commands.remove_resource::<KnockoutCinematic>();
+    clear_overrides(&mut time_scale, &mut camera_override, &mut effects_override);
+}
+
+/// End the cinematic when the level is reset, so that the next one doesn't start in slow motion
+fn end_cinematic_on_reset(
+    mut reset_events: EventReader<ResetEvent>,
+    commands: Commands,
+    time_scale: ResMut<TimeScale>,
+    camera_override: ResMut<CameraOverride>,
+    effects_override: ResMut<PostEffectsOverride>,
+) {
+    if reset_events.iter().any(|reset| reset.scope.resets_level()) {
+        end_cinematic(commands, time_scale, camera_override, effects_override);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn last_enemy_of_wave() {
+        let stop_points = [100.0, 200.0];
+
+        // An enemy of the next wave is still standing
+        assert!(is_last_of_wave(50.0, &stop_points, [150.0]));
+        // An enemy of the same wave is still standing
+        assert!(!is_last_of_wave(50.0, &stop_points, [80.0, 150.0]));
+        // Past the last stop point, every enemy is part of the wave
+        assert!(!is_last_of_wave(250.0, &stop_points, [1000.0]));
+        assert!(is_last_of_wave(250.0, &[], []));
+    }
+
+    #[test]
+    fn intensity_eases_in_and_out() {
+        assert_eq!(intensity(0.0), 0.0);
+        assert_eq!(intensity(0.5), 1.0);
+        assert_eq!(intensity(1.0), 0.0);
+        assert!(intensity(EASE_IN / 2.0) > 0.0 && intensity(EASE_IN / 2.0) < 1.0);
+    }
+}
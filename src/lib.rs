@@ -0,0 +1,560 @@
+#![allow(clippy::type_complexity)]
+#![allow(clippy::forget_non_drop)]
+#![allow(clippy::too_many_arguments)]
+
+use bevy::prelude::*;
+use bevy_parallax::{ParallaxPlugin, ParallaxResource};
+use bevy_rapier2d::prelude::*;
+use fighter::Stats;
+use input::MenuAction;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+use player::*;
+
+use bevy_inspector_egui::{WorldInspectorParams, WorldInspectorPlugin};
+use bevy_inspector_egui_rapier::InspectableRapierPlugin;
+
+pub mod activity;
+pub mod afterimage;
+pub mod animation;
+pub mod arcade;
+pub mod asset_io;
+pub mod assets;
+pub mod assist;
+pub mod attack;
+pub mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bug_report;
+pub mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capture;
+pub mod coating;
+pub mod collision;
+pub mod colorblind;
+pub mod combat_events;
+pub mod combat_log;
+pub mod combo_breaker;
+pub mod config;
+pub mod consts;
+pub mod crash;
+pub mod damage;
+pub mod damage_numbers;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod determinism;
+pub mod dialogue;
+pub mod director;
+pub mod dynamic_resolution;
+pub mod effects;
+pub mod enemy;
+pub mod enemy_ai;
+pub mod fighter;
+pub mod fighter_state;
+pub mod flying;
+pub mod focus;
+pub mod gallery;
+pub mod hazard;
+pub mod headless;
+pub mod hit_stop;
+pub mod input;
+pub mod interaction;
+pub mod item;
+pub mod knockout_cinematic;
+pub mod lifetime;
+pub mod loading;
+pub mod localization;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod logging;
+pub mod menu_stack;
+pub mod metadata;
+pub mod movement;
+pub mod nameplate;
+pub mod narration;
+pub mod new_game_plus;
+pub mod palette;
+pub mod platform;
+pub mod player;
+pub mod pool;
+pub mod post_processing;
+pub mod projectile;
+pub mod reflection;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod relay;
+pub mod rng;
+pub mod scripting;
+pub mod shadow;
+pub mod socket;
+pub mod spatial_hash;
+pub mod spirit;
+pub mod sprite_atlas;
+pub mod status_effect;
+pub mod streaming;
+pub mod survival;
+pub mod team;
+pub mod team_up;
+pub mod timestep;
+pub mod tint;
+pub mod training;
+pub mod tutorial;
+pub mod ui;
+pub mod utils;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod validation;
+pub mod versus;
+pub mod video;
+pub mod wave;
+
+use animation::*;
+use attack::AttackPlugin;
+use audio::*;
+use camera::*;
+use enemy_ai::WalkTarget;
+use metadata::GameMeta;
+use ui::UIPlugin;
+use utils::{ResetController, ResetEvent};
+use versus::VersusMatch;
+
+use crate::{
+    activity::ActivityPlugin,
+    afterimage::AfterimagePlugin,
+    arcade::{ArcadePlugin, ArcadeRun},
+    assist::AssistPlugin,
+    coating::CoatingPlugin,
+    colorblind::ColorblindPlugin,
+    combat_events::CombatEventsPlugin,
+    combat_log::CombatLogPlugin,
+    combo_breaker::ComboBreakerPlugin,
+    damage::DamagePlugin,
+    damage_numbers::DamageNumbersPlugin,
+    dialogue::DialoguePlugin,
+    director::DirectorPlugin,
+    dynamic_resolution::DynamicResolutionPlugin,
+    effects::EffectsPlugin,
+    fighter::FighterPlugin,
+    fighter_state::FighterStatePlugin,
+    flying::FlyingPlugin,
+    focus::FocusPlugin,
+    hazard::HazardPlugin,
+    headless::HeadlessPlugin,
+    hit_stop::HitStopPlugin,
+    input::PlayerAction,
+    interaction::InteractionPlugin,
+    item::ItemPlugin,
+    knockout_cinematic::KnockoutCinematicPlugin,
+    lifetime::LifetimePlugin,
+    loading::LoadingPlugin,
+    localization::LocalizationPlugin,
+    menu_stack::MenuStackPlugin,
+    metadata::GameHandle,
+    movement::MovementPlugin,
+    nameplate::NameplatePlugin,
+    narration::NarrationPlugin,
+    new_game_plus::NewGamePlusPlugin,
+    palette::PalettePlugin,
+    platform::PlatformPlugin,
+    pool::PoolPlugin,
+    post_processing::PostProcessingPlugin,
+    projectile::ProjectilePlugin,
+    reflection::ReflectionPlugin,
+    rng::RngPlugin,
+    scripting::ScriptingPlugin,
+    shadow::ShadowPlugin,
+    socket::SocketPlugin,
+    spatial_hash::SpatialHashPlugin,
+    spirit::SpiritPlugin,
+    sprite_atlas::SpriteAtlasPlugin,
+    status_effect::StatusEffectPlugin,
+    streaming::StreamingPlugin,
+    survival::SurvivalPlugin,
+    team::TeamPlugin,
+    team_up::TeamUpPlugin,
+    timestep::TimestepPlugin,
+    tint::TintPlugin,
+    training::TrainingPlugin,
+    tutorial::TutorialPlugin,
+    ui::debug_tools::YSortDebugPlugin,
+    versus::VersusPlugin,
+    video::VideoPlugin,
+    wave::WavePlugin,
+};
+
+/// What the game is doing. The menus opened over it, like the pause menu, are in the
+/// [`MenuStack`](menu_stack::MenuStack).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    LoadingStorage,
+    LoadingGame,
+    MainMenu,
+    LoadingLevel,
+    InGame,
+    //Editor,
+}
+
+/// Run the game, as configured by the [`ENGINE_CONFIG`](config::ENGINE_CONFIG)
+pub fn run() {
+    // Load engine config. This will parse CLI arguments or web query string so we want to do it
+    // before we create the app to make sure everything is in order.
+    let engine_config = &*config::ENGINE_CONFIG;
+
+    // Validate the game assets and exit without starting the game, if requested
+    #[cfg(not(target_arch = "wasm32"))]
+    if engine_config.validate_assets {
+        let asset_dir = engine_config.asset_dir_path();
+        let report = validation::validate_assets(&asset_dir);
+        report.print();
+
+        std::process::exit(if report.has_errors() { 1 } else { 0 });
+    }
+
+    // Pack the game assets into a bundle and exit without starting the game, if requested
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(output) = &engine_config.pack_assets {
+        let asset_dir = engine_config.asset_dir_path();
+
+        match asset_io::write_bundle(&asset_dir, std::path::Path::new(output)) {
+            Ok(count) => {
+                println!("Packed {count} files into {output}");
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("error: Could not pack assets: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // The determinism check, the benchmarks, and the relay run the game headless too
+    let headless = engine_config.headless
+        || engine_config.determinism_check.is_some()
+        || engine_config.benchmark.is_some()
+        || engine_config.relay.is_some();
+
+    let mut app = App::new();
+
+    // Configure asset server
+    let mut asset_plugin = AssetPlugin {
+        watch_for_changes: engine_config.hot_reload,
+        ..default()
+    };
+    if let Some(asset_folder) = &engine_config.asset_dir {
+        asset_plugin.asset_folder = asset_folder.clone();
+    }
+
+    // Mount the asset packs, if there are any. The asset plugin will use the asset server we insert
+    // here instead of creating its own.
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // The base pack shadows the asset folder, so it's only used by release builds that don't
+        // hot reload, where the assets aren't being edited
+        let base_path = bevy::asset::FileAssetIo::get_base_path();
+        let base_pack = [asset_io::BUNDLE_EXTENSION, "zip"]
+            .iter()
+            .map(|ext| base_path.join(format!("{}.{ext}", asset_plugin.asset_folder)))
+            .find(|path| path.is_file())
+            .filter(|_| !cfg!(debug_assertions) && !engine_config.hot_reload);
+
+        let mut packs = engine_config
+            .asset_packs
+            .iter()
+            .rev()
+            .map(std::path::PathBuf::from)
+            .collect::<Vec<_>>();
+        packs.extend(base_pack);
+
+        // Only replace the default asset IO when needed, because hot reloading depends on it
+        if !packs.is_empty() {
+            let asset_io =
+                asset_io::PackAssetIo::new(&packs, asset_plugin.create_platform_default_asset_io());
+            app.insert_resource(AssetServer::new(asset_io));
+        }
+    }
+
+    // Mount the asset bundle that the web page downloaded, so assets don't have to be fetched one
+    // request at a time
+    #[cfg(target_arch = "wasm32")]
+    if let Some(bytes) = asset_io::take_preloaded_bundle() {
+        let name = format!(
+            "{}.{}",
+            asset_plugin.asset_folder,
+            asset_io::BUNDLE_EXTENSION
+        );
+        match asset_io::PackAssetIo::from_bundle_bytes(
+            &name,
+            bytes,
+            asset_plugin.create_platform_default_asset_io(),
+        ) {
+            Ok(asset_io) => {
+                app.insert_resource(AssetServer::new(asset_io));
+            }
+            Err(e) => error!("Could not mount preloaded asset bundle: {e}"),
+        }
+    }
+
+    // Don't create a renderer when running headless
+    if headless {
+        app.insert_resource(bevy::render::settings::WgpuSettings {
+            backends: None,
+            ..default()
+        });
+    }
+
+    app.add_plugins({
+        let mut builder = DefaultPlugins.build();
+
+        // Configure Window
+        builder = builder
+            .set(WindowPlugin {
+                window: WindowDescriptor {
+                    title: "Fish Folk Punchy".to_string(),
+                    scale_factor_override: Some(1.0),
+                    ..default()
+                },
+                // The headless plugin adds a virtual window instead
+                add_primary_window: !headless,
+                exit_on_all_closed: !headless,
+                ..default()
+            })
+            .set(ImagePlugin::default_nearest());
+
+        if headless {
+            builder = builder.disable::<bevy::winit::WinitPlugin>();
+        }
+
+        builder = builder.set(asset_plugin);
+
+        // Configure logging. The native builds replace Bevy's log plugin with one that keeps the
+        // tail of the log for bug reports, and can write the log to a file.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            builder = builder.disable::<bevy::log::LogPlugin>();
+
+            #[cfg(not(feature = "schedule_graph"))]
+            {
+                builder = builder.add_before::<bevy::log::LogPlugin, _>(logging::LogPlugin {
+                    filter: engine_config.log_filter(),
+                    file: engine_config.log_file,
+                });
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            builder = builder.set(bevy::log::LogPlugin {
+                filter: engine_config.log_filter(),
+                ..default()
+            });
+        }
+
+        #[cfg(feature = "schedule_graph")]
+        {
+            builder.disable::<bevy::log::LogPlugin>()
+        }
+
+        #[cfg(not(feature = "schedule_graph"))]
+        builder
+    });
+
+    // Write crash reports from early on, so that the crashes while loading the game are reported too
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_plugin(crash::CrashPlugin);
+
+    // Add other systems and resources
+    app.insert_resource(ClearColor(Color::BLACK))
+        .add_loopless_state(GameState::LoadingStorage)
+        .add_plugin(MenuStackPlugin)
+        .add_event::<ResetEvent>()
+        .add_plugin(RngPlugin)
+        .add_plugin(ScriptingPlugin)
+        .add_plugin(PlatformPlugin)
+        .add_plugin(LocalizationPlugin)
+        .add_plugin(LoadingPlugin)
+        .add_plugin(StreamingPlugin)
+        // The physics systems are added to the gameplay timestep by the timestep plugin
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false))
+        .add_plugin(TimestepPlugin)
+        .add_plugin(SpatialHashPlugin)
+        .add_plugin(PoolPlugin)
+        .add_plugin(SpriteAtlasPlugin)
+        .add_plugin(ActivityPlugin)
+        .add_plugin(InputManagerPlugin::<PlayerAction>::default())
+        .add_plugin(InputManagerPlugin::<MenuAction>::default())
+        .add_plugin(AttackPlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(ParallaxPlugin)
+        .add_plugin(FighterStatePlugin)
+        .add_plugin(MovementPlugin)
+        .add_plugin(FlyingPlugin)
+        .add_plugin(AudioPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(CombatEventsPlugin)
+        .add_plugin(CombatLogPlugin)
+        .add_plugin(LifetimePlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(ItemPlugin)
+        .add_plugin(InteractionPlugin)
+        .add_plugin(CoatingPlugin)
+        .add_plugin(StatusEffectPlugin)
+        .add_plugin(TintPlugin)
+        .add_plugin(FighterPlugin)
+        .add_plugin(SocketPlugin)
+        .add_plugin(TrainingPlugin)
+        .add_plugin(TutorialPlugin)
+        .add_plugin(SurvivalPlugin)
+        .add_plugin(ArcadePlugin)
+        .add_plugin(DirectorPlugin)
+        .add_plugin(WavePlugin)
+        .add_plugin(TeamPlugin)
+        .add_plugin(TeamUpPlugin)
+        .add_plugin(ComboBreakerPlugin)
+        .add_plugin(HitStopPlugin)
+        .add_plugin(ProjectilePlugin)
+        .add_plugin(ReflectionPlugin)
+        .add_plugin(HazardPlugin)
+        .add_plugin(VersusPlugin)
+        .add_plugin(NewGamePlusPlugin)
+        .insert_resource(ParallaxResource::default())
+        .add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                // Versus respawns the players for each round instead, and arcade once they continue
+                .with_system(
+                    game_over_on_players_death
+                        .run_unless_resource_exists::<VersusMatch>()
+                        .run_unless_resource_exists::<ArcadeRun>(),
+                )
+                .into(),
+        );
+
+    if headless {
+        // Added before the headless plugin, which keeps the update rate set by it
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(script) = &engine_config.determinism_check {
+            match determinism::DeterminismPlugin::load(std::path::Path::new(script)) {
+                Ok(plugin) => {
+                    app.add_plugin(plugin);
+                }
+                Err(e) => {
+                    eprintln!("error: Could not load input script: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(name) = &engine_config.benchmark {
+            match benchmark::Scene::from_name(name) {
+                Some(scene) => {
+                    app.add_plugin(benchmark::SceneBenchmarkPlugin { scene });
+                }
+                None => {
+                    eprintln!(
+                        "error: Unknown benchmark `{name}`, expected one of {:?}",
+                        benchmark::SCENES
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(address) = &engine_config.relay {
+            match relay::RelayPlugin::bind(address) {
+                Ok(plugin) => {
+                    app.add_plugin(plugin);
+                }
+                Err(e) => {
+                    eprintln!("error: Could not host the relay on {address}: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        app.add_plugin(HeadlessPlugin);
+    } else {
+        app.add_plugin(UIPlugin)
+            // Dialogues wait for the players to read them, so they aren't played without a UI
+            .add_plugin(DialoguePlugin)
+            // Neither are assists, which would change how recorded inputs play out
+            .add_plugin(AssistPlugin)
+            // Nor are the spirits of knocked out players, whose inputs aren't recorded
+            .add_plugin(SpiritPlugin)
+            .add_plugin(ColorblindPlugin)
+            .add_plugin(PalettePlugin)
+            .add_plugin(EffectsPlugin)
+            .add_plugin(AfterimagePlugin)
+            .add_plugin(ShadowPlugin)
+            .add_plugin(PostProcessingPlugin)
+            .add_plugin(KnockoutCinematicPlugin)
+            .add_plugin(NameplatePlugin)
+            .add_plugin(DamageNumbersPlugin)
+            .add_plugin(VideoPlugin)
+            .add_plugin(DynamicResolutionPlugin)
+            .add_plugin(FocusPlugin)
+            .add_plugin(NarrationPlugin)
+            //this should be moved to AudioPlugin, it also causes a panic in egui_inspector when
+            //using the color picker widget currently
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                main_menu_sounds
+                    .run_if_resource_exists::<GameMeta>()
+                    .run_if(ui::in_menu)
+                    .before(bevy_egui::EguiSystem::ProcessOutput),
+            );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugin(capture::CapturePlugin)
+            .add_plugin(bug_report::BugReportPlugin);
+    }
+
+    // Register reflect types that don't come from plugins
+    app.register_type::<Stats>().register_type::<WalkTarget>();
+
+    // Add debug plugins if enabled
+    if engine_config.debug_tools && !headless {
+        app.insert_resource(DebugRenderContext {
+            enabled: false,
+            ..default()
+        })
+        .add_plugin(YSortDebugPlugin)
+        .add_plugin(InspectableRapierPlugin)
+        .insert_resource(WorldInspectorParams {
+            enabled: false,
+            ..default()
+        })
+        .add_plugin(WorldInspectorPlugin::new());
+    }
+
+    // Register assets and loaders
+    assets::register(&mut app);
+
+    debug!(?engine_config, "Starting game");
+
+    // Get the game handle
+    let asset_server = app.world.get_resource::<AssetServer>().unwrap();
+    let game_asset = &engine_config.game_asset;
+    let game_handle: Handle<GameMeta> = asset_server.load(game_asset);
+
+    // Insert game handle resource
+    app.world.insert_resource(GameHandle(game_handle));
+
+    // Print the graphviz schedule graph
+    #[cfg(feature = "schedule_graph")]
+    bevy_mod_debugdump::print_schedule(&mut app);
+
+    app.run();
+}
+
+/// Transition back to main menu and reset world when all players have died
+fn game_over_on_players_death(
+    mut commands: Commands,
+    query: Query<(), With<Player>>,
+    reset_controller: ResetController,
+) {
+    if query.is_empty() {
+        commands.insert_resource(NextState(GameState::MainMenu));
+
+        reset_controller.reset_world();
+    }
+}
@@ -1,36 +1,42 @@
 use bevy::prelude::*;
+use iyes_loopless::prelude::*;
 
-use crate::item::Drop;
+use crate::{
+    item::Drop,
+    pool::EntityPoolCommands,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+};
 
 pub struct LifetimePlugin;
 
 impl Plugin for LifetimePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_to_stage(CoreStage::Last, lifetime_system)
+        app.register_type::<Lifetime>()
+            .add_fixed_timestep_system(GAMEPLAY_TIMESTEP, gameplay_stage::MOVEMENT, lifetime_system)
             .add_event::<LifetimeExpired>();
     }
 }
 
 /// Component added to entities that should despawn after a timer.
-#[derive(Component, Deref, DerefMut, Debug, Clone)]
+#[derive(Component, Deref, DerefMut, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
 pub struct Lifetime(pub Timer);
 
 /// Despawn entities who's lifetime has expired
 fn lifetime_system(
-    mut commands: Commands,
+    mut pool: EntityPoolCommands,
     mut entities: Query<(Entity, &mut Lifetime, Option<&Drop>, Option<&Transform>)>,
-    time: Res<Time>,
     mut event_writer: EventWriter<LifetimeExpired>,
 ) {
     for (entity, mut lifetime, drop, transform) in &mut entities {
-        lifetime.tick(time.delta());
+        lifetime.tick(GAMEPLAY_STEP);
 
         if lifetime.finished() {
             event_writer.send(LifetimeExpired {
                 drop: drop.cloned(),
                 transform: transform.cloned(),
             });
-            commands.entity(entity).despawn_recursive();
+            pool.despawn(entity);
         }
     }
 }
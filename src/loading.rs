@@ -3,22 +3,27 @@ use bevy_mod_js_scripting::ActiveScripts;
 use bevy_parallax::ParallaxResource;
 use iyes_loopless::{prelude::*, state::NextState};
 
-use rand::seq::SliceRandom;
-
 use crate::{
     animation::Animation,
-    assets::EguiFontDefinitions,
+    assets::{EguiFont, EguiFontDefinitions},
     config::ENGINE_CONFIG,
+    consts,
     enemy::{Boss, Enemy, EnemyBundle},
     fighter::ActiveFighterBundle,
-    input::MenuAction,
+    hazard::HazardBundle,
+    input::{MenuAction, PlayerAction},
     item::{Item, ItemBundle},
     metadata::{
         BorderImageMeta, FighterMeta, GameHandle, GameMeta, ItemMeta, LevelHandle, LevelMeta,
         Settings,
     },
+    palette::PaletteSwap,
     platform::Storage,
-    player::{Player, PlayerBundle},
+    player::{Player, PlayerBundle, PlayerIndex},
+    rng::GameRng,
+    sprite_atlas::ItemSprites,
+    streaming::LevelStreaming,
+    timestep::TransformInterpolation,
     GameState, Stats,
 };
 
@@ -32,7 +37,7 @@ use leafwing_input_manager::{
     InputManagerBundle,
 };
 
-use progress::{HasLoadProgress, LoadingResources};
+use progress::{HasLoadProgress, LoadProgress, LoadingResources};
 
 pub mod progress;
 
@@ -63,6 +68,7 @@ impl Plugin for LoadingPlugin {
                         .run_in_state(GameState::InGame)
                         .with_system(hot_reload_level)
                         .with_system(hot_reload_fighters)
+                        .with_system(hot_reload_player_controls)
                         .into(),
                 );
         }
@@ -95,11 +101,13 @@ fn game_assets_loaded(
 #[derive(SystemParam)]
 pub struct GameLoader<'w, 's> {
     skip_next_asset_update_event: Local<'s, bool>,
-    camera: Query<'w, 's, Entity, With<Camera>>,
+    camera: Query<'w, 's, Entity, With<ParallaxCameraComponent>>,
     commands: Commands<'w, 's>,
     game_handle: Res<'w, GameHandle>,
     assets: ResMut<'w, Assets<GameMeta>>,
-    egui_ctx: ResMut<'w, EguiContext>,
+    /// Missing when running headless
+    egui_ctx: Option<ResMut<'w, EguiContext>>,
+    font_assets: Res<'w, Assets<EguiFont>>,
     events: EventReader<'w, 's, AssetEvent<GameMeta>>,
     active_scripts: ResMut<'w, ActiveScripts>,
 }
@@ -124,6 +132,7 @@ impl<'w, 's> GameLoader<'w, 's> {
             game_handle,
             mut assets,
             mut egui_ctx,
+            font_assets,
             mut active_scripts,
             ..
         } = self;
@@ -145,21 +154,35 @@ impl<'w, 's> GameLoader<'w, 's> {
 
                 // One-time initialization
             } else {
-                // Initialize empty fonts for all game fonts.
-                //
-                // This makes sure Egui will not panic if we try to use a font that is still loading.
-                let mut egui_fonts = egui::FontDefinitions::default();
-                for font_name in game.ui_theme.font_families.keys() {
-                    let font_family = egui::FontFamily::Name(font_name.clone().into());
-                    egui_fonts.families.insert(font_family, vec![]);
-                }
-                egui_ctx.ctx_mut().set_fonts(egui_fonts.clone());
-                commands.insert_resource(EguiFontDefinitions(egui_fonts));
-
                 // Transition to the main menu when we are done
                 commands.insert_resource(NextState(GameState::MainMenu));
             }
 
+            // Initialize the fonts for all game fonts.
+            //
+            // Fonts that are still loading are given an empty family, which makes sure Egui will
+            // not panic if we try to use them. They will be filled in by the `update_egui_fonts`
+            // system when they finish loading. On hot reload this also picks up any font families
+            // that were added, removed, or pointed to a different file.
+            let mut egui_fonts = egui::FontDefinitions::default();
+            for (font_name, font_handle) in &game.ui_theme.font_handles {
+                let font_family = egui::FontFamily::Name(font_name.clone().into());
+                let mut family_fonts = vec![];
+
+                if let Some(font) = font_assets.get(font_handle) {
+                    egui_fonts
+                        .font_data
+                        .insert(font_name.clone(), font.0.clone());
+                    family_fonts.push(font_name.clone());
+                }
+
+                egui_fonts.families.insert(font_family, family_fonts);
+            }
+            if let Some(egui_ctx) = &mut egui_ctx {
+                egui_ctx.ctx_mut().set_fonts(egui_fonts.clone());
+            }
+            commands.insert_resource(EguiFontDefinitions(egui_fonts));
+
             // Set the locale resource
             let translations = &game.translations;
             commands.insert_resource(
@@ -183,7 +206,13 @@ impl<'w, 's> GameLoader<'w, 's> {
 
             // Helper to load border images
             let mut load_border_image = |border: &mut BorderImageMeta| {
-                border.egui_texture = egui_ctx.add_image(border.handle.clone_weak());
+                if let Some(egui_ctx) = &mut egui_ctx {
+                    border.egui_texture = egui_ctx.add_image(border.handle.clone_weak());
+                    for resolution in &mut border.resolutions {
+                        resolution.egui_texture =
+                            egui_ctx.add_image(resolution.handle.clone_weak());
+                    }
+                }
             };
 
             // Add Border images to egui context
@@ -200,6 +229,13 @@ impl<'w, 's> GameLoader<'w, 's> {
                     load_border_image(border);
                 }
             }
+            for theme_override in game.ui_theme.states.values_mut() {
+                theme_override
+                    .border_images_mut()
+                    .into_iter()
+                    .for_each(&mut load_border_image);
+            }
+            game.ui_theme.build_state_themes();
 
             // Set the active scripts
             for script_handle in &game.script_handles {
@@ -302,9 +338,6 @@ fn menu_input_map() -> InputMap<MenuAction> {
         // Toggle Fullscreen
         .insert(KeyCode::F11, MenuAction::ToggleFullscreen)
         .insert(GamepadButtonType::Mode, MenuAction::ToggleFullscreen)
-        // Pause
-        .insert(KeyCode::Escape, MenuAction::Pause)
-        .insert(GamepadButtonType::Start, MenuAction::Pause)
         .build()
 }
 
@@ -335,10 +368,29 @@ fn load_level(
     mut storage: ResMut<Storage>,
     loading_resources: LoadingResources,
     mut active_scripts: ResMut<ActiveScripts>,
+    mut rng: ResMut<GameRng>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut kept_players: Query<
+        (
+            &PlayerIndex,
+            &mut Transform,
+            Option<&mut TransformInterpolation>,
+        ),
+        With<Player>,
+    >,
 ) {
     if let Some(level) = assets.get(&level_handle) {
-        // Track load progress
-        let load_progress = level.load_progress(&loading_resources);
+        // Track load progress, including the sounds of the fighters, which are only known once the
+        // fighter assets have loaded
+        let fighter_sounds = level
+            .players
+            .iter()
+            .chain(&level.enemies)
+            .filter_map(|spawn| fighter_assets.get(&spawn.fighter_handle))
+            .map(|fighter| fighter.sounds.load_progress(&loading_resources));
+        let load_progress = LoadProgress::merged(
+            std::iter::once(level.load_progress(&loading_resources)).chain(fighter_sounds),
+        );
         debug!(
             %load_progress,
             "Loading level assets: {:.2}% ",
@@ -350,6 +402,9 @@ fn load_level(
             return;
         }
 
+        // Restart the RNG so that playing the level again with the same inputs plays out the same
+        rng.restart();
+
         let window = windows.primary();
 
         // Setup the parallax background
@@ -360,8 +415,19 @@ fn load_level(
         // Set the clear color
         commands.insert_resource(ClearColor(level.background_color()));
 
-        // Spawn the players
+        // Spawn the players, or move the players that were kept by a level reset to the start
         for (i, player) in level.players.iter().enumerate() {
+            if let Some((_, mut transform, interpolation)) = kept_players
+                .iter_mut()
+                .find(|(player_index, ..)| player_index.0 == i)
+            {
+                transform.translation = player.location + Vec3::new(0.0, consts::GROUND_Y, 0.0);
+                if let Some(mut interpolation) = interpolation {
+                    *interpolation = TransformInterpolation::new(transform.translation.truncate());
+                }
+                continue;
+            }
+
             commands.spawn(PlayerBundle::new(
                 player,
                 i,
@@ -375,7 +441,7 @@ fn load_level(
             let mut ec = commands.spawn(EnemyBundle::new(enemy));
 
             if enemy.boss {
-                ec.insert(Boss);
+                ec.insert(Boss::default());
             }
         }
 
@@ -390,6 +456,17 @@ fn load_level(
             )
         }
 
+        // Spawn the hazards
+        for hazard_meta in &level.hazards {
+            commands.spawn(HazardBundle::new(hazard_meta));
+        }
+
+        // The sections after the first are spawned while the level is played
+        match &level.streaming {
+            Some(streaming) => commands.insert_resource(LevelStreaming::new(streaming)),
+            None => commands.remove_resource::<LevelStreaming>(),
+        }
+
         commands.insert_resource(level.clone());
         commands.insert_resource(NextState(GameState::InGame));
     } else {
@@ -427,8 +504,12 @@ fn hot_reload_level(
 
 fn load_items(
     mut commands: Commands,
-    item_spawns: Query<(Entity, &Transform, &Handle<ItemMeta>, Option<&Item>), Without<Sprite>>,
+    item_spawns: Query<
+        (Entity, &Transform, &Handle<ItemMeta>, Option<&Item>),
+        Without<TextureAtlasSprite>,
+    >,
     item_assets: Res<Assets<ItemMeta>>,
+    mut item_sprites: ItemSprites,
 ) {
     for (entity, transform, item_handle, item) in item_spawns.iter() {
         if let Some(item) = item {
@@ -438,8 +519,10 @@ fn load_items(
         }
 
         if let Some(item_meta) = item_assets.get(item_handle) {
-            commands.entity(entity).insert(SpriteBundle {
-                texture: item_meta.image.image_handle.clone(),
+            let (texture_atlas, sprite) = item_sprites.get(&item_meta.image);
+            commands.entity(entity).insert(SpriteSheetBundle {
+                texture_atlas,
+                sprite,
                 transform: *transform,
                 ..default()
             });
@@ -461,13 +544,15 @@ fn load_fighters(
             &Transform,
             &Handle<FighterMeta>,
             Option<&Player>,
+            Option<&PlayerIndex>,
             Option<&Enemy>,
         ),
         Without<Stats>,
     >,
     fighter_assets: Res<Assets<FighterMeta>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    for (entity, transform, fighter_handle, player, enemy) in fighters.iter() {
+    for (entity, transform, fighter_handle, player, player_index, enemy) in fighters.iter() {
         if let Some(fighter) = fighter_assets.get(fighter_handle) {
             ActiveFighterBundle::activate_fighter_stub(
                 &mut commands,
@@ -476,6 +561,10 @@ fn load_fighters(
                 transform,
                 player,
                 enemy,
+                // Give each player a different palette, so that players with the same fighter
+                // can be told apart
+                player_index.map(|index| index.0),
+                &mut rng,
             );
         }
     }
@@ -483,31 +572,44 @@ fn load_fighters(
 
 /// Hot reload fighter data when fighter assets are updated.
 fn hot_reload_fighters(
+    mut commands: Commands,
     mut fighters: Query<(
+        Entity,
         &Handle<FighterMeta>,
         &mut Name,
         &mut Handle<TextureAtlas>,
         &mut Animation,
         &mut Stats,
+        Option<&PlayerIndex>,
     )>,
     mut events: EventReader<AssetEvent<FighterMeta>>,
     assets: Res<Assets<FighterMeta>>,
+    mut rng: ResMut<GameRng>,
 ) {
     for event in events.iter() {
         if let AssetEvent::Modified { handle } = event {
-            for (fighter_handle, mut name, mut atlas_handle, mut animation, mut stats) in
-                fighters.iter_mut()
+            for (
+                entity,
+                fighter_handle,
+                mut name,
+                mut atlas_handle,
+                mut animation,
+                mut stats,
+                player_index,
+            ) in fighters.iter_mut()
             {
                 if fighter_handle == handle {
                     let fighter = assets.get(fighter_handle).unwrap();
 
                     *name = Name::new(fighter.name.clone());
-                    *atlas_handle = fighter
+                    let (atlas, palette_swap) = fighter
                         .spritesheet
-                        .atlas_handle
-                        .choose(&mut rand::thread_rng())
-                        .unwrap()
-                        .clone();
+                        .choose_atlas(player_index.map(|index| index.0), &mut *rng);
+                    *atlas_handle = atlas;
+                    match palette_swap {
+                        Some(palette_swap) => commands.entity(entity).insert(palette_swap),
+                        None => commands.entity(entity).remove::<PaletteSwap>(),
+                    };
                     *animation = Animation::new(
                         fighter.spritesheet.animation_fps,
                         fighter.spritesheet.animations.clone(),
@@ -518,3 +620,25 @@ fn hot_reload_fighters(
         }
     }
 }
+
+/// Re-apply player input maps when the game's default settings are hot reloaded.
+///
+/// Settings saved to [`Storage`] take precedence over the defaults, so this only has a visible
+/// effect for players that haven't customized their controls.
+fn hot_reload_player_controls(
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut players: Query<(&PlayerIndex, &mut InputMap<PlayerAction>), With<Player>>,
+) {
+    if !game.is_changed() || game.is_added() {
+        return;
+    }
+
+    let settings = storage
+        .get(Settings::STORAGE_KEY)
+        .unwrap_or_else(|| game.default_settings.clone());
+
+    for (player_index, mut input_map) in &mut players {
+        *input_map = settings.player_controls.get_input_map(player_index.0);
+    }
+}
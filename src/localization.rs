@@ -1,9 +1,18 @@
-use std::borrow::Borrow;
+use std::{borrow::Borrow, sync::Mutex};
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_fluent::{BundleAsset, FluentPlugin, Locale, Localization};
 use fluent::FluentArgs;
 use fluent_content::{Content, Request};
+use once_cell::sync::Lazy;
+
+/// The ids of all of the messages that have been requested through [`LocalizationExt::get`].
+///
+/// This is global because the extension trait only has access to the [`Localization`].
+static REQUESTED_MESSAGES: Lazy<Mutex<HashSet<String>>> = Lazy::new(default);
 
 /// Plugin for initializing and loading the [`Localization`] resource.
 pub struct LocalizationPlugin;
@@ -12,12 +21,36 @@ impl Plugin for LocalizationPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(FluentPlugin)
             .init_resource::<Locale>()
-            .insert_resource(Localization::new());
+            .insert_resource(Localization::new())
+            .init_resource::<MissingLocalizationKeys>();
+
+        app.add_system(load_locales)
+            .add_system(report_missing_localization_keys.after(load_locales));
 
-        app.add_system(load_locales);
+        #[cfg(not(target_arch = "wasm32"))]
+        if crate::config::ENGINE_CONFIG.hot_reload {
+            app.add_system(hot_reload::reload_modified_locales.before(load_locales));
+        }
     }
 }
 
+/// The message ids that have been requested by the game, but that are missing from the bundle of
+/// the active locale.
+///
+/// These messages are either displayed in a fallback locale or not at all. They are listed in the
+/// debug tools window so that translators can find the gaps in their translations.
+#[derive(Resource, Default, Debug)]
+pub struct MissingLocalizationKeys {
+    /// The locale that the keys are missing from
+    pub locale: Option<unic_langid::LanguageIdentifier>,
+    /// The missing message ids, sorted alphabetically
+    pub keys: Vec<String>,
+}
+
+/// How much longer the strings are made by [`pseudolocalize`], since translations are often
+/// longer than the English text
+const PSEUDO_LOCALE_EXPANSION: f32 = 0.3;
+
 /// Extension trait to reduce boilerplate when getting values from a [`Localization`].
 pub trait LocalizationExt<'a, T: Into<Request<'a, U>>, U: Borrow<FluentArgs<'a>>> {
     /// Request message content and get an empty string if it doesn't exist.
@@ -31,10 +64,60 @@ where
 {
     /// Request message content and get an empty string if it doesn't exist.
     fn get(&self, request: T) -> String {
-        self.content(request).unwrap_or_default()
+        // Record the requested message id for the missing key report
+        let id = Into::<Request<'a, U>>::into(request).id;
+        let mut requested = REQUESTED_MESSAGES.lock().unwrap();
+        if !requested.contains(id) {
+            requested.insert(id.to_string());
+        }
+        drop(requested);
+
+        let content = self.content(request).unwrap_or_default();
+        if crate::config::ENGINE_CONFIG.pseudo_locale {
+            pseudolocalize(&content)
+        } else {
+            content
+        }
     }
 }
 
+/// Turn the text into the pseudo-locale enabled with `--pseudo-locale`: the letters are accented,
+/// the text is padded to be longer, and it's put in brackets, so that text that is cut off is
+/// missing its closing bracket.
+///
+/// Missing messages stay empty, so that they still show up in the missing key report.
+pub fn pseudolocalize(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let accented = text
+        .chars()
+        .map(|c| match c {
+            'a' => 'á',
+            'e' => 'é',
+            'i' => 'í',
+            'o' => 'ö',
+            'u' => 'ü',
+            'c' => 'ç',
+            'n' => 'ñ',
+            'y' => 'ý',
+            'A' => 'Á',
+            'E' => 'É',
+            'I' => 'Í',
+            'O' => 'Ö',
+            'U' => 'Ü',
+            'C' => 'Ç',
+            'N' => 'Ñ',
+            'Y' => 'Ý',
+            c => c,
+        })
+        .collect::<String>();
+    let padding = (text.chars().count() as f32 * PSEUDO_LOCALE_EXPANSION).ceil() as usize;
+
+    format!("[{accented}{}]", "~".repeat(padding))
+}
+
 /// Watch for locale [`BundleAsset`] load events and add any new bundles to the [`Localization`]
 /// resource.
 fn load_locales(
@@ -103,3 +186,150 @@ fn load_locales(
         *localization = new_localization;
     }
 }
+
+/// Check the requested message ids against the bundle of the active locale and update the
+/// [`MissingLocalizationKeys`] resource, logging a warning for every newly missing key.
+fn report_missing_localization_keys(
+    locale: Res<Locale>,
+    localization: Res<Localization>,
+    assets: Res<Assets<BundleAsset>>,
+    mut missing: ResMut<MissingLocalizationKeys>,
+    mut checked_count: Local<usize>,
+) {
+    let requested = REQUESTED_MESSAGES.lock().unwrap();
+
+    // Only re-check if new messages have been requested or the localization was rebuilt
+    if requested.len() == *checked_count && !localization.is_changed() {
+        return;
+    }
+    *checked_count = requested.len();
+
+    // Find the bundle for the active locale, which is first in the fallback chain
+    let bundles = assets
+        .iter()
+        .map(|(_, asset)| (&asset.locales[0], asset))
+        .collect::<HashMap<_, _>>();
+    let Some(active_locale) = locale
+        .fallback_chain(bundles.keys().cloned())
+        .into_iter()
+        .next()
+    else {
+        return;
+    };
+    let bundle = bundles[active_locale];
+
+    let mut keys = requested
+        .iter()
+        .filter(|id| !bundle.has_message(id))
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    let locale_changed = missing.locale.as_ref() != Some(active_locale);
+    for key in &keys {
+        if locale_changed || missing.keys.binary_search(key).is_err() {
+            warn!(%active_locale, "Missing localization key: {key}");
+        }
+    }
+
+    *missing = MissingLocalizationKeys {
+        locale: Some(active_locale.clone()),
+        keys,
+    };
+}
+
+/// Hot reloading for the Fluent resources of the locale bundles.
+///
+/// The bundle loader reads the `.ftl` resources directly, so the asset server only watches the
+/// bundle files themselves for changes. To pick up changes to the translations, we poll for
+/// modifications to the resources and reload the bundles that include them.
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload {
+    use std::{
+        path::{Path, PathBuf},
+        time::SystemTime,
+    };
+
+    use bevy::{prelude::*, utils::HashMap};
+    use bevy_fluent::BundleAsset;
+    use serde::Deserialize;
+
+    use crate::config::ENGINE_CONFIG;
+
+    /// How often to check the Fluent resources for changes, in seconds
+    const POLL_INTERVAL: f32 = 0.5;
+
+    /// The part of the bundle file that we need to find the resources
+    #[derive(Deserialize)]
+    struct BundleFile {
+        resources: Vec<String>,
+    }
+
+    pub fn reload_modified_locales(
+        time: Res<Time>,
+        asset_server: Res<AssetServer>,
+        assets: Res<Assets<BundleAsset>>,
+        mut modified_times: Local<HashMap<PathBuf, SystemTime>>,
+        mut timer: Local<Option<Timer>>,
+    ) {
+        let timer =
+            timer.get_or_insert_with(|| Timer::from_seconds(POLL_INTERVAL, TimerMode::Repeating));
+        if !timer.tick(time.delta()).just_finished() {
+            return;
+        }
+
+        let asset_dir = ENGINE_CONFIG.asset_dir_path();
+
+        for (handle_id, _) in assets.iter() {
+            let Some(bundle_path) = asset_server.get_handle_path(handle_id) else {
+                continue;
+            };
+            let bundle_path = bundle_path.path();
+
+            let mut modified = false;
+            for resource_path in bundle_resources(&asset_dir, bundle_path) {
+                let Ok(time) = std::fs::metadata(&resource_path).and_then(|x| x.modified()) else {
+                    continue;
+                };
+
+                if let Some(previous) = modified_times.insert(resource_path, time) {
+                    modified |= previous != time;
+                }
+            }
+
+            if modified {
+                info!(?bundle_path, "Locale resource modified, reloading bundle");
+                asset_server.reload_asset(bundle_path);
+            }
+        }
+    }
+
+    /// Get the file system paths to the Fluent resources included in a bundle
+    fn bundle_resources(asset_dir: &Path, bundle_path: &Path) -> Vec<PathBuf> {
+        let bundle_path = asset_dir.join(bundle_path);
+        let bundle_dir = bundle_path.parent().unwrap_or(asset_dir);
+
+        std::fs::read(&bundle_path)
+            .ok()
+            .and_then(|bytes| serde_yaml::from_slice::<BundleFile>(&bytes).ok())
+            .map(|bundle| {
+                bundle
+                    .resources
+                    .iter()
+                    .map(|resource| bundle_dir.join(resource))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pseudolocalized_text_is_accented_and_longer() {
+        assert_eq!(pseudolocalize("Start Game"), "[Stárt Gámé~~~]");
+        assert_eq!(pseudolocalize(""), "");
+    }
+}
@@ -0,0 +1,262 @@
+//! Logging for the native builds, which keeps the last lines of the log for bug reports, and can
+//! write the log to a file.
+//!
+//! Bevy's `LogPlugin` sets up a subscriber that no layers can be added to, so the native builds
+//! disable it and set up the same filtered terminal output with this plugin instead, along with a
+//! layer that keeps the [`LogTail`]. The web build still uses Bevy's plugin, which logs to the
+//! browser console.
+//!
+//! With `--log-file`, the log is also written to `logs/punchy.log` in the game's data directory.
+//! The file is rolled over to `punchy.1.log` at startup and whenever it gets bigger than
+//! [`MAX_LOG_FILE_SIZE`], so the logs of the last few runs are kept without filling up the disk.
+
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Write as _},
+    fs::File,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use bevy::{
+    prelude::*,
+    utils::{
+        tracing::{
+            field::{Field, Visit},
+            Event, Subscriber,
+        },
+        Instant,
+    },
+};
+use tracing_subscriber::{layer::Context, prelude::*, EnvFilter, Layer, Registry};
+
+/// How many lines of the log are kept in the [`LogTail`]
+const LOG_TAIL_LINES: usize = 1000;
+
+/// The size that the log file is rolled over at
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How many of the older log files are kept
+const OLD_LOG_FILES: usize = 4;
+
+pub struct LogPlugin {
+    /// The filter for the log, like `info,wgpu=error`, unless the `RUST_LOG` environment variable
+    /// is set
+    pub filter: String,
+    /// Whether to also write the log to the log file
+    pub file: bool,
+}
+
+impl Plugin for LogPlugin {
+    fn build(&self, app: &mut App) {
+        let filter = EnvFilter::try_from_default_env()
+            .or_else(|_| EnvFilter::try_new(&self.filter))
+            .unwrap_or_else(|e| {
+                eprintln!("warning: Invalid log filter `{}`: {e}", self.filter);
+                EnvFilter::new("info")
+            });
+        let log_tail = LogTail::new(LOG_TAIL_LINES);
+
+        let file_path = self.file.then(log_file_path);
+        let file = file_path
+            .as_ref()
+            .and_then(|path| match RollingFile::open(path.clone()) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    eprintln!(
+                        "warning: Could not open the log file {}: {e}",
+                        path.display()
+                    );
+                    None
+                }
+            });
+
+        let subscriber = Registry::default()
+            .with(filter)
+            .with(tracing_subscriber::fmt::Layer::default())
+            .with(file.map(|file| {
+                tracing_subscriber::fmt::Layer::default()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file))
+            }))
+            .with(LogTailLayer(log_tail.clone()));
+        if subscriber.try_init().is_err() {
+            eprintln!("warning: Could not set the global logger, as it has already been set");
+        }
+        if let Some(path) = file_path {
+            info!(?path, "Writing the log to a file");
+        }
+
+        app.insert_resource(log_tail);
+    }
+}
+
+/// The last lines of the log, oldest first
+#[derive(Resource, Clone)]
+pub struct LogTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+    started: Instant,
+}
+
+impl LogTail {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            started: Instant::now(),
+        }
+    }
+
+    fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The lines joined into a single text
+    pub fn text(&self) -> String {
+        // Crash reports include the log, even when the panic happened while a line was added
+        let lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut text = String::new();
+        for line in lines.iter() {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// The path of the log file in the game's data directory
+fn log_file_path() -> PathBuf {
+    directories::ProjectDirs::from("org", "FishFolk", "Punchy")
+        .map(|project_dirs| project_dirs.data_dir().join("logs"))
+        .unwrap_or_else(|| PathBuf::from("logs"))
+        .join("punchy.log")
+}
+
+/// The path of an older log file, like `punchy.2.log`
+fn old_log_file_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("{index}.log"))
+}
+
+/// A log file that is rolled over to the older log files when it gets too big
+struct RollingFile {
+    path: PathBuf,
+    /// The open log file, which is only closed while it's rolled over, since open files can't be
+    /// moved on Windows
+    file: Option<File>,
+    size: u64,
+}
+
+impl RollingFile {
+    /// Roll over the log file of the last run, and create a new one
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        roll_over(&path);
+
+        Ok(Self {
+            file: Some(File::create(&path)?),
+            path,
+            size: 0,
+        })
+    }
+}
+
+impl io::Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let file = match self.file.take() {
+            Some(file) if self.size > 0 && self.size + buf.len() as u64 > MAX_LOG_FILE_SIZE => {
+                drop(file);
+                roll_over(&self.path);
+                self.size = 0;
+                self.file.insert(File::create(&self.path)?)
+            }
+            Some(file) => self.file.insert(file),
+            // Creating the file again failed the last time it was rolled over
+            None => self.file.insert(File::create(&self.path)?),
+        };
+
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Move the log file to `punchy.1.log`, and the older log files one further, dropping the oldest
+fn roll_over(path: &Path) {
+    // The files that don't exist yet can't be moved, which is fine
+    for index in (1..OLD_LOG_FILES).rev() {
+        std::fs::rename(
+            old_log_file_path(path, index),
+            old_log_file_path(path, index + 1),
+        )
+        .ok();
+    }
+    std::fs::rename(path, old_log_file_path(path, 1)).ok();
+}
+
+/// Formats the logged events into lines of the [`LogTail`]
+struct LogTailLayer(LogTail);
+
+impl<S: Subscriber> Layer<S> for LogTailLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = format!(
+            "{:>10.3} {:>5} {}:",
+            self.0.started.elapsed().as_secs_f32(),
+            metadata.level(),
+            metadata.target()
+        );
+        event.record(&mut FieldVisitor(&mut line));
+        self.0.push(line);
+    }
+}
+
+/// Writes the fields of an event after its message, like `message key=value`
+struct FieldVisitor<'a>(&'a mut String);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            write!(self.0, " {value:?}").ok();
+        } else {
+            write!(self.0, " {}={value:?}", field.name()).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn old_log_files_are_numbered() {
+        assert_eq!(
+            old_log_file_path(Path::new("logs/punchy.log"), 2),
+            Path::new("logs/punchy.2.log")
+        );
+    }
+
+    #[test]
+    fn log_tail_keeps_last_lines() {
+        let log_tail = LogTail::new(2);
+        for line in ["first", "second", "third"] {
+            log_tail.push(line.to_owned());
+        }
+
+        assert_eq!(log_tail.text(), "second\nthird\n");
+    }
+}
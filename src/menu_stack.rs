@@ -0,0 +1,122 @@
+//! The menus that are open over the [`GameState`](crate::GameState).
+//!
+//! The game state says what the game is doing, like playing a level or showing the main menu, and
+//! the menus that are opened over it are kept in the [`MenuStack`]. Pausing opens the pause menu
+//! over the level, and the settings can be opened over the pause menu or the main menu, so closing
+//! a menu goes back to the one below it without having to know where it was opened from.
+//!
+//! The menu on top of the stack is the [`MenuLayer`] state, so systems run on their layer with
+//! `run_in_state`: the menus run in their own layer, and the gameplay systems that read the
+//! inputs of the players run in [`MenuLayer::None`]. The gameplay timestep is held while a menu
+//! is open, so the systems in it don't need to check the layer.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::GameState;
+
+pub struct MenuStackPlugin;
+
+impl Plugin for MenuStackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_loopless_state(MenuLayer::None)
+            .init_resource::<MenuStack>()
+            .add_system_to_stage(CoreStage::PostUpdate, close_menus_on_state_change)
+            // The layer is changed at the start of the next frame, after all of the menus of this
+            // frame have been shown
+            .add_system_to_stage(
+                CoreStage::Last,
+                sync_menu_layer.after(close_menus_on_state_change),
+            );
+    }
+}
+
+/// The menu that is open on top of the [`GameState`](crate::GameState)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MenuLayer {
+    /// No menu is open, so the game state is played
+    #[default]
+    None,
+    Paused,
+    Settings,
+}
+
+/// The menus that are open, from the bottom one to the top one.
+///
+/// The [`MenuLayer`] state follows the top of the stack at the start of the next frame.
+#[derive(Resource, Default, Debug)]
+pub struct MenuStack(Vec<MenuLayer>);
+
+impl MenuStack {
+    /// Open a menu over the open ones
+    pub fn push(&mut self, layer: MenuLayer) {
+        if layer != MenuLayer::None && self.top() != layer {
+            self.0.push(layer);
+        }
+    }
+
+    /// Close the menu on top, going back to the one below it
+    pub fn pop(&mut self) -> Option<MenuLayer> {
+        self.0.pop()
+    }
+
+    /// Close all of the menus
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// The menu on top, or [`MenuLayer::None`] if no menu is open
+    pub fn top(&self) -> MenuLayer {
+        self.0.last().copied().unwrap_or_default()
+    }
+
+    /// Whether the menu is open, even if other menus are open over it
+    pub fn contains(&self, layer: MenuLayer) -> bool {
+        self.0.contains(&layer)
+    }
+}
+
+/// Close the menus when the game state changes, since they were opened over the last state
+fn close_menus_on_state_change(
+    state: Res<CurrentState<GameState>>,
+    mut menu_stack: ResMut<MenuStack>,
+) {
+    if state.is_changed() && !menu_stack.0.is_empty() {
+        menu_stack.clear();
+    }
+}
+
+fn sync_menu_layer(
+    mut commands: Commands,
+    menu_stack: Res<MenuStack>,
+    layer: Res<CurrentState<MenuLayer>>,
+) {
+    let top = menu_stack.top();
+    if layer.0 != top {
+        commands.insert_resource(NextState(top));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn closing_menus_goes_back_to_the_one_below() {
+        let mut menu_stack = MenuStack::default();
+        assert_eq!(menu_stack.top(), MenuLayer::None);
+
+        menu_stack.push(MenuLayer::Paused);
+        menu_stack.push(MenuLayer::Settings);
+        // Opening the menu on top again doesn't need closing it twice
+        menu_stack.push(MenuLayer::Settings);
+        assert_eq!(menu_stack.top(), MenuLayer::Settings);
+        assert!(menu_stack.contains(MenuLayer::Paused));
+
+        assert_eq!(menu_stack.pop(), Some(MenuLayer::Settings));
+        assert_eq!(menu_stack.top(), MenuLayer::Paused);
+        assert_eq!(menu_stack.pop(), Some(MenuLayer::Paused));
+        assert_eq!(menu_stack.top(), MenuLayer::None);
+        assert_eq!(menu_stack.pop(), None);
+    }
+}
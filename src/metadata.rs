@@ -1,6 +1,6 @@
 use bevy::{
     math::{UVec2, Vec2, Vec3},
-    prelude::{Color, Component, Deref, DerefMut, Handle, Image, Resource},
+    prelude::{default, Color, Component, Deref, DerefMut, Handle, Image, Resource},
     reflect::{FromReflect, Reflect, TypeUuid},
     sprite::TextureAtlas,
     utils::HashMap,
@@ -10,9 +10,13 @@ use bevy_kira_audio::AudioSource;
 use bevy_mod_js_scripting::JsScript;
 use bevy_parallax::{LayerData, ParallaxResource};
 use punchy_macros::HasLoadProgress;
+use rand::{seq::SliceRandom, Rng};
 use serde::Deserialize;
 
-use crate::{animation::Clip, assets::EguiFont, attack::AttackFrames, fighter::Stats};
+use crate::{
+    animation::Clip, assets::EguiFont, attack::AttackFrames, consts, fighter::Stats,
+    input::InputScript, palette::PaletteSwap,
+};
 
 pub mod settings;
 pub use settings::*;
@@ -23,6 +27,12 @@ pub mod ui;
 pub mod localization;
 pub use localization::TranslationsMeta;
 
+pub mod features;
+pub use features::*;
+
+pub mod endless;
+pub use endless::*;
+
 #[derive(Resource, Deref, DerefMut)]
 pub struct GameHandle(pub Handle<GameMeta>);
 
@@ -33,6 +43,74 @@ pub struct GameMeta {
     pub start_level: String,
     #[serde(skip)]
     pub start_level_handle: Handle<LevelMeta>,
+    /// The optional `.endless.yaml` level started by the endless mode button in the main menu
+    #[serde(default)]
+    pub endless_level: Option<String>,
+    #[serde(skip)]
+    pub endless_level_handle: Option<Handle<LevelMeta>>,
+    /// The optional level started by the tutorial button in the main menu
+    #[serde(default)]
+    pub tutorial_level: Option<String>,
+    #[serde(skip)]
+    pub tutorial_level_handle: Option<Handle<LevelMeta>>,
+    /// The optional training mode, started by the training button in the main menu
+    #[serde(default)]
+    pub training: Option<TrainingMeta>,
+    /// The optional survival mode, started by the survival button in the main menu
+    #[serde(default)]
+    pub survival: Option<SurvivalMeta>,
+    /// The optional arcade mode, the campaign played for score with limited lives and continues
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub arcade: Option<ArcadeMeta>,
+    /// The optional versus mode, started by the versus button in the main menu
+    #[serde(default)]
+    pub versus: Option<VersusMeta>,
+    /// The optional modifiers of New Game Plus, which is unlocked by finishing the start level
+    #[serde(default)]
+    pub new_game_plus: Option<NewGamePlusMeta>,
+    /// The optional extras menu, with the concept art, music, and enemy bios that are unlocked
+    /// by playing
+    #[serde(default)]
+    pub gallery: Option<GalleryMeta>,
+    /// The optional team-up attack of two players with full meters
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub team_up: Option<TeamUpMeta>,
+    /// Whether the attacks of the players hurt the other players in co-op, like they do in versus
+    #[serde(default)]
+    pub friendly_fire: bool,
+    /// The optional scaling of the damage and the stun of long combos on the players
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub combo_scaling: Option<ComboScalingMeta>,
+    /// The optional combo breaker, that lets stunned players escape combos with their meter
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub combo_breaker: Option<ComboBreakerMeta>,
+    /// The optional reflection of enemy projectiles by well-timed attacks
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub reflection: Option<ReflectionMeta>,
+    /// The optional sparks, dust, and blood played on the hits and landings of the fighters
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub effects: Option<EffectsMeta>,
+    /// The optional slow motion played when the last enemy of a wave or a boss is knocked out
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub knockout_cinematic: Option<KnockoutCinematicMeta>,
+    /// The optional freeze of the fight for a few frames when an attack connects
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub hit_stop: Option<HitStopMeta>,
+    /// The optional spirits that the players who are out of a co-op fight control until they come
+    /// back
+    #[serde(default)]
+    pub spirits: Option<SpiritMeta>,
+    /// The optional nameplates that introduce each kind of enemy the first time it appears
+    #[serde(default)]
+    pub nameplates: Option<NameplatesMeta>,
     pub main_menu: MainMenuMeta,
     pub ui_theme: UIThemeMeta,
     pub camera_height: u32,
@@ -41,11 +119,15 @@ pub struct GameMeta {
     pub default_settings: Settings,
     pub translations: TranslationsMeta,
     #[serde(default)]
+    pub features: FeatureFlags,
+    #[serde(default)]
     pub scripts: Vec<String>,
     #[serde(skip)]
     pub script_handles: Vec<Handle<JsScript>>,
 }
 
+/// In the web build, the game doesn't wait for the sounds and music to load, because decoding them
+/// blocks the browser for a long time. They start playing once they are ready instead.
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct MainMenuMeta {
@@ -53,17 +135,369 @@ pub struct MainMenuMeta {
     pub background_image: ImageMeta,
     pub music: String,
     #[serde(skip)]
+    #[cfg_attr(target_arch = "wasm32", has_load_progress(none))]
     pub music_handle: Handle<AudioSource>,
     pub play_button_sound: String,
     #[serde(skip)]
+    #[cfg_attr(target_arch = "wasm32", has_load_progress(none))]
     pub play_button_sound_handle: Handle<AudioSource>,
     pub button_sounds: Vec<String>,
     #[serde(skip)]
+    #[cfg_attr(target_arch = "wasm32", has_load_progress(none))]
     pub button_sound_handles: Vec<Handle<AudioSource>>,
+    /// The demo that is played after idling on the main menu
+    #[serde(default)]
+    pub attract_demo: Option<String>,
+    #[serde(skip)]
+    pub attract_demo_handle: Option<Handle<InputScript>>,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TrainingMeta {
+    /// The level that training is played in. The player and the dummy are spawned where its first
+    /// player and its first enemy are.
+    pub level: String,
+    #[serde(skip)]
+    pub level_handle: Handle<LevelMeta>,
+    /// The fighters that can be picked for the player and the dummy
+    pub fighters: Vec<String>,
+    #[serde(skip)]
+    pub fighter_handles: Vec<Handle<FighterMeta>>,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SurvivalMeta {
+    /// The arena that survival is played in. Its players are spawned as usual, but its enemies are
+    /// replaced by the waves.
+    pub level: String,
+    #[serde(skip)]
+    pub level_handle: Handle<LevelMeta>,
+    /// The fighters that the waves are made of, from the weakest to the strongest. Each wave can
+    /// pick from one more of them than the wave before it.
+    pub enemies: Vec<String>,
+    #[serde(skip)]
+    pub enemy_handles: Vec<Handle<FighterMeta>>,
+    /// The number of enemies in the first wave
+    pub first_wave_size: u32,
+    /// How many more enemies each wave has than the wave before it
+    pub wave_size_increase: u32,
+    /// The most enemies that a wave can have
+    pub max_wave_size: u32,
+    /// The score of defeating an enemy, which is multiplied by the number of the wave
+    pub enemy_score: u32,
+    /// The score of clearing a wave, which is multiplied by the number of the wave
+    pub wave_score: u32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ArcadeMeta {
+    /// The lives of each player, including the one they start with
+    pub lives: u32,
+    /// How many times the players can continue once all of them are out of lives
+    pub continues: u32,
+    /// The score of knocking out an enemy
+    pub knockout_score: u32,
+    /// The score of knocking out a boss
+    pub boss_score: u32,
+    /// The score of finishing the campaign
+    pub clear_score: u32,
+    /// The score of every life that is left when the campaign is finished
+    pub life_score: u32,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct VersusMeta {
+    /// The arena that versus is played in. Its first two players fight each other in 1v1, and all
+    /// four of them in 2v2, where the first and the third player are on the first team. Its enemies
+    /// are left out.
+    pub level: String,
+    #[serde(skip)]
+    pub level_handle: Handle<LevelMeta>,
+    /// The width of the arena, which is centered on the start of the level
+    pub arena_width: f32,
+    /// How long a round lasts, in seconds
+    pub round_time: f32,
+    /// How many rounds a team has to win to win the match
+    pub rounds_to_win: u32,
+}
+
+/// A joint attack of two players standing next to each other, that hits every enemy on the screen
+/// after a short cinematic
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TeamUpMeta {
+    /// The damage a player has to deal to the enemies to fill their meter
+    pub meter: f32,
+    /// How close to each other the players have to stand
+    pub range: f32,
+    /// How far apart the players may press their special buttons, in seconds
+    pub press_window: f32,
+    /// The animations played during the cinematic by the player on the left and by the player on
+    /// the right
+    pub animations: [String; 2],
+    /// How long the cinematic lasts before the attack hits, in seconds
+    pub cinematic_time: f32,
+    pub damage: i32,
+    #[serde(default)]
+    pub pushback: Vec2,
+    pub hitstun_duration: f32,
+    /// The afterimages that the players leave behind during the cinematic
+    #[serde(default)]
+    pub afterimages: Option<AfterimageMeta>,
+}
+
+/// How much less the hits of a combo on a player hurt and stun, the more hits the combo has. Each
+/// scale goes down by its `per_hit` step for every hit before it, but never below its `min`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ComboScalingMeta {
+    pub damage_scale_per_hit: f32,
+    pub min_damage_scale: f32,
+    /// Shortening the stun works like the gravity scaling of fighting games, where the longer a
+    /// player is juggled, the sooner they fall out of the combo
+    pub stun_scale_per_hit: f32,
+    pub min_stun_scale: f32,
+}
+
+/// A burst that stunned players can use to break out of a combo, pushing the fighters around them
+/// away
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ComboBreakerMeta {
+    /// The part of the team-up meter that the combo breaker uses up
+    pub cost: f32,
+    pub animation: String,
+    /// How long the burst lasts, in seconds
+    pub duration: f32,
+    /// The size of the area around the player that the burst hits
+    pub size: Vec2,
+    #[serde(default)]
+    pub damage: i32,
+    /// The speed that the burst pushes the fighters it hits away with
+    pub pushback: f32,
+    pub hitstun_duration: f32,
+    /// The afterimages that the player leaves behind during the burst
+    #[serde(default)]
+    pub afterimages: Option<AfterimageMeta>,
+}
+
+/// How the attacks of the players reflect the projectiles of the enemies back at them
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ReflectionMeta {
+    /// How long after the hitbox of an attack comes out it can reflect projectiles, in seconds
+    pub window: f32,
+    /// The factor that the speed of a reflected projectile is scaled by
+    pub speed_scale: f32,
+    /// The factor that the damage of a reflected projectile is scaled by
+    pub damage_scale: f32,
+}
+
+/// A brief slow motion zoomed in on the finishing blow, with the colors drained out of the screen
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct KnockoutCinematicMeta {
+    /// How long the cinematic lasts, in seconds of real time
+    pub duration: f32,
+    /// The speed of the gameplay at the height of the cinematic, relative to normal speed
+    pub time_scale: f32,
+    /// How far the camera zooms in on the finishing blow, where `1.0` doesn't zoom
+    pub zoom: f32,
+    /// How much of the colors are drained out of the screen, from `0.0` to `1.0`
+    #[serde(default)]
+    pub desaturation: f32,
+}
+
+/// The few frames that the fight freezes for when an attack connects, longer for stronger hits
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HitStopMeta {
+    /// How long every hit freezes for, in seconds
+    pub duration: f32,
+    /// How much longer a hit freezes for with every point of its damage, in seconds
+    #[serde(default)]
+    pub duration_per_damage: f32,
+    /// The longest that a hit freezes for, in seconds
+    pub max_duration: f32,
+    /// Whether the whole fight freezes, instead of only the fighter that is hit and the one that
+    /// hit it
+    #[serde(default)]
+    pub global: bool,
+}
+
+/// The cursor that a player who is out of a co-op fight moves around the screen, to drop pickups for
+/// the other players and to distract the enemies
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpiritMeta {
+    pub image: ImageMeta,
+    /// How fast the cursor moves
+    pub speed: f32,
+    /// How long a spirit has to wait after dropping a pickup or distracting the enemies, in seconds
+    pub cooldown: f32,
+    /// The minor item dropped under the cursor with the throw button
+    pub pickup: String,
+    #[serde(skip)]
+    pub pickup_handle: Handle<ItemMeta>,
+    /// How close to the cursor the enemies distracted with the attack button have to be
+    pub distract_radius: f32,
+    /// How long the distracted enemies stop going after the players, in seconds
+    pub distract_time: f32,
+}
+
+/// How the nameplates of the enemies with a [`FighterNameplateMeta`] are shown
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NameplatesMeta {
+    /// The icon shown once for every level of threat of an enemy
+    pub threat_icon: ImageMeta,
+    /// How long a nameplate is shown, in seconds
+    pub duration: f32,
+}
+
+/// The visual effects played on combat events. Every effect is optional.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EffectsMeta {
+    /// Played where a hit lands
+    #[serde(default)]
+    pub hit: Option<EffectMeta>,
+    /// Played instead of `hit` for the hits that deal at least `heavy_hit_damage`
+    #[serde(default)]
+    pub heavy_hit: Option<EffectMeta>,
+    #[serde(default)]
+    pub heavy_hit_damage: i32,
+    /// Played instead of `hit` and `heavy_hit` for the critical hits
+    #[serde(default)]
+    pub critical_hit: Option<EffectMeta>,
+    /// Played at the feet of the fighters that land after being thrown
+    #[serde(default)]
+    pub landing: Option<EffectMeta>,
+    /// Played along with the sparks of the hits when the `blood_effects` feature is enabled
+    #[serde(default)]
+    pub blood: Option<EffectMeta>,
+}
+
+/// A burst of sprites that each play an animation once, flying away from the center of the burst
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EffectMeta {
+    pub spritesheet: FighterSpritesheetMeta,
+    pub animation: String,
+    /// The number of sprites in the burst, which are spread evenly around its center
+    #[serde(default = "default_effect_count")]
+    pub count: usize,
+    /// How far from the center of the burst the sprites start
+    #[serde(default)]
+    pub spread: f32,
+    /// How fast the sprites fly away from the center of the burst
+    #[serde(default)]
+    pub speed: f32,
+}
+
+fn default_effect_count() -> usize {
+    1
+}
+
+/// The modifiers applied over the levels of the campaign when it's played as New Game Plus
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NewGamePlusMeta {
+    /// The enemies that are replaced by tougher variants
+    #[serde(default)]
+    pub enemy_variants: Vec<EnemyVariantMeta>,
+    /// Whether the enemies that aren't bosses are shuffled between their spawn points, so that the
+    /// fights don't play out the same way
+    #[serde(default)]
+    pub remix_enemies: bool,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyVariantMeta {
+    /// The fighter to replace. Its path is made relative to the asset folder by the loader.
+    pub fighter: String,
+    #[serde(skip)]
+    pub fighter_handle: Handle<FighterMeta>,
+    /// The fighter to replace it with. Its path is made relative to the asset folder by the loader.
+    pub variant: String,
+    #[serde(skip)]
+    pub variant_handle: Handle<FighterMeta>,
 }
 
+/// The entries of the extras menu, in the order that they are shown
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
+pub struct GalleryMeta {
+    #[serde(default)]
+    pub art: Vec<GalleryArtMeta>,
+    #[serde(default)]
+    pub music: Vec<GalleryMusicMeta>,
+    #[serde(default)]
+    pub bios: Vec<GalleryBioMeta>,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GalleryArtMeta {
+    /// The localization key of the title of the art
+    pub title: String,
+    pub image: ImageMeta,
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub unlock: UnlockCondition,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GalleryMusicMeta {
+    /// The localization key of the title of the track
+    pub title: String,
+    pub music: String,
+    #[serde(skip)]
+    #[cfg_attr(target_arch = "wasm32", has_load_progress(none))]
+    pub music_handle: Handle<AudioSource>,
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub unlock: UnlockCondition,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GalleryBioMeta {
+    /// The fighter that the bio is about, whose name and portrait are shown with it
+    pub fighter: String,
+    #[serde(skip)]
+    pub fighter_handle: Handle<FighterMeta>,
+    /// The localization key of the text of the bio
+    pub bio: String,
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub unlock: UnlockCondition,
+}
+
+/// What the players have to do to unlock an entry of the extras menu, which is checked against
+/// the progress kept in the storage
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnlockCondition {
+    /// The entry is always unlocked
+    #[default]
+    Always,
+    /// Finish the campaign
+    CampaignCompleted,
+    /// Finish the campaign as New Game Plus
+    NewGamePlusCompleted,
+    /// Score at least this much in survival mode
+    SurvivalScore(u32),
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
 pub struct ImageMeta {
     pub image: String,
     pub image_size: Vec2,
@@ -74,7 +508,7 @@ pub struct ImageMeta {
 #[derive(Resource, Deref, DerefMut)]
 pub struct LevelHandle(pub Handle<LevelMeta>);
 
-#[derive(Resource, HasLoadProgress, TypeUuid, Deserialize, Clone, Debug)]
+#[derive(Resource, HasLoadProgress, TypeUuid, Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 #[uuid = "32111f6e-bb9a-4ea7-8988-1220b923a059"]
 pub struct LevelMeta {
@@ -88,8 +522,77 @@ pub struct LevelMeta {
     pub items: Vec<ItemSpawnMeta>,
     pub music: String,
     #[serde(skip)]
+    #[cfg_attr(target_arch = "wasm32", has_load_progress(none))]
     pub music_handle: Handle<AudioSource>,
     pub stop_points: Vec<f32>,
+    /// Optional full-screen effects applied to the camera while playing the level
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub post_processing: Option<PostProcessingMeta>,
+    /// If set, only the enemies and items in the first section of the level are loaded before it
+    /// starts, and the rest are loaded as the camera gets close to them.
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub streaming: Option<LevelStreamingMeta>,
+    /// How far outside of the camera view enemies are still updated, in pixels. Enemies that are
+    /// further away are dormant until the camera gets closer. If unset, enemies are always updated.
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub activity_distance: Option<f32>,
+    /// The steps of the tutorial that the level teaches, in the order they are taught
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub tutorial: Vec<TutorialStepMeta>,
+    /// The dialogues played as the players move through the level, sorted from left to right
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub dialogues: Vec<LevelDialogueMeta>,
+    /// The bounds of the pacing [director](crate::director), if it steers the fights of the level
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub director: Option<DirectorMeta>,
+    /// The objects in the background that players can attack to drop or set off onto the enemies
+    #[serde(default)]
+    pub hazards: Vec<HazardMeta>,
+    /// The [waves](crate::wave) of enemies that ambush the players as the camera gets to them,
+    /// sorted from left to right
+    #[serde(default)]
+    pub waves: Vec<WaveMeta>,
+}
+
+/// A wave of enemies that comes in from the edges of the screen once the camera gets to it, and
+/// holds the camera in place until it's defeated
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct WaveMeta {
+    /// How far the camera has to go for the wave to start
+    pub camera_x: f32,
+    pub enemies: Vec<WaveEnemyMeta>,
+}
+
+/// An enemy of a wave, which is spawned just off the screen
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct WaveEnemyMeta {
+    pub fighter: String,
+    #[serde(skip)]
+    pub fighter_handle: Handle<FighterMeta>,
+    /// The edge of the screen that the enemy comes in from
+    #[has_load_progress(none)]
+    pub side: WaveSide,
+    /// How far up the ground the enemy comes in, like the `y` of the `location` of the enemies
+    #[serde(default)]
+    pub y: f32,
+    #[serde(default)]
+    pub boss: bool,
+}
+
+/// An edge of the screen
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Reflect, FromReflect)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveSide {
+    Left,
+    Right,
 }
 
 impl LevelMeta {
@@ -99,7 +602,240 @@ impl LevelMeta {
     }
 }
 
-#[derive(TypeUuid, Deserialize, Clone, Debug, Component)]
+/// An object in the background of a level, like a hanging crate or an electrical panel, that players
+/// can attack to drop it or set it off onto the enemies around it
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct HazardMeta {
+    /// Where the hazard is, relative to the ground like the items
+    pub location: Vec3,
+    pub image: ImageMeta,
+    /// The image shown while the hazard has been triggered, if it looks different then
+    #[serde(default)]
+    pub triggered_image: Option<ImageMeta>,
+    /// Where the hazard is hit by attacks, relative to its location
+    pub hurtbox: ColliderMeta,
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub trigger: HazardTriggerMeta,
+    /// Where the hazard damages the enemies when it's triggered, relative to its location
+    pub damage_area: ColliderMeta,
+    pub damage: i32,
+    #[serde(default)]
+    pub pushback: Vec2,
+    pub hitstun_duration: f32,
+    /// How long the damage area hurts the enemies in it, in seconds
+    #[serde(default = "default_hazard_active_time")]
+    pub active_time: f32,
+}
+
+fn default_hazard_active_time() -> f32 {
+    0.2
+}
+
+/// When a [`HazardMeta`] is triggered
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct HazardTriggerMeta {
+    /// How many times the hazard has to be hit to trigger it
+    pub hits: u32,
+    /// How many enemies have to be in the damage area for the hazard to go off. A hazard that has
+    /// been hit enough times waits for them.
+    pub min_enemies: usize,
+    /// How long it takes for the hazard to be ready again after it went off, in seconds. Hazards
+    /// without a cooldown only go off once.
+    pub cooldown: Option<f32>,
+}
+
+impl Default for HazardTriggerMeta {
+    fn default() -> Self {
+        Self {
+            hits: 1,
+            min_enemies: 0,
+            cooldown: None,
+        }
+    }
+}
+
+/// How the spawns of a long level are split up into sections that are loaded while it's played
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct LevelStreamingMeta {
+    /// The width of a section in pixels
+    pub section_width: f32,
+    /// How far past the right edge of the screen a section starts loading, in pixels
+    #[serde(default = "default_preload_distance")]
+    pub preload_distance: f32,
+    /// The sections after the first one, filled in by the level loader
+    #[serde(skip)]
+    pub sections: Vec<LevelSectionMeta>,
+}
+
+fn default_preload_distance() -> f32 {
+    600.0
+}
+
+/// The enemies and items of a streamed level section.
+///
+/// The section's asset paths are relative to the asset folder, and their handles aren't set, so
+/// that the assets aren't loaded until the section is.
+#[derive(Clone, Debug, Default, Reflect, FromReflect)]
+pub struct LevelSectionMeta {
+    pub start_x: f32,
+    pub end_x: f32,
+    pub enemies: Vec<FighterSpawnMeta>,
+    pub items: Vec<ItemSpawnMeta>,
+}
+
+/// A step of a tutorial level, which prompts the players to perform an action once they get to it,
+/// and holds them back until one of them has
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct TutorialStepMeta {
+    /// How far the players have to go for the prompt to be shown
+    pub trigger_x: f32,
+    /// How far the players can go before the action has been performed
+    pub gate_x: f32,
+    pub action: TutorialAction,
+    /// The localization key of the prompt, which is shown next to the buttons of the action
+    pub prompt: String,
+}
+
+/// An action taught by a [`TutorialStepMeta`]
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Reflect, FromReflect)]
+#[serde(rename_all = "snake_case")]
+pub enum TutorialAction {
+    Move,
+    Attack,
+    /// Picking up an item with the throw button
+    PickUp,
+    /// Throwing a picked up item
+    Throw,
+}
+
+/// A dialogue that is played once the players get to a point in the level
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct LevelDialogueMeta {
+    /// How far the players have to go for the dialogue to start
+    pub trigger_x: f32,
+    pub lines: Vec<DialogueLineMeta>,
+}
+
+/// A line of a [dialogue](crate::dialogue)
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct DialogueLineMeta {
+    /// The fighter saying the line, whose name and portrait are shown with it
+    pub speaker: String,
+    #[serde(skip)]
+    pub speaker_handle: Handle<FighterMeta>,
+    /// The localization key of the line
+    pub line: String,
+}
+
+/// The bounds that the pacing [director](crate::director) keeps the fights of a level within
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct DirectorMeta {
+    /// How much the size of the survival waves is scaled
+    #[serde(default = "DirectorBounds::one")]
+    pub wave_size_scale: DirectorBounds,
+    /// How many enemies may go after the players at once
+    pub attack_tokens: DirectorBounds,
+    /// The item that defeated enemies may drop
+    #[serde(default)]
+    pub drop_item: Option<String>,
+    #[serde(skip)]
+    pub drop_item_handle: Option<Handle<ItemMeta>>,
+    /// The chance of a defeated enemy dropping the item
+    #[serde(default = "DirectorBounds::zero")]
+    pub drop_chance: DirectorBounds,
+}
+
+/// A value that the director moves between, depending on how the players are doing
+#[derive(Deserialize, Clone, Copy, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct DirectorBounds {
+    /// The value while the players are struggling
+    pub struggling: f32,
+    /// The value while the players are doing well
+    pub doing_well: f32,
+}
+
+impl DirectorBounds {
+    fn one() -> Self {
+        Self {
+            struggling: 1.0,
+            doing_well: 1.0,
+        }
+    }
+
+    fn zero() -> Self {
+        Self {
+            struggling: 0.0,
+            doing_well: 0.0,
+        }
+    }
+
+    /// The value for an intensity between 0, while the players are struggling, and 1, while they
+    /// are doing well
+    pub fn at(&self, intensity: f32) -> f32 {
+        self.struggling + (self.doing_well - self.struggling) * intensity.clamp(0.0, 1.0)
+    }
+}
+
+impl LevelStreamingMeta {
+    /// Move the enemies and items that aren't in the first section of the level into the
+    /// [`sections`](Self::sections).
+    pub fn split_sections(
+        &mut self,
+        enemies: &mut Vec<FighterSpawnMeta>,
+        items: &mut Vec<ItemSpawnMeta>,
+    ) {
+        let width = self.section_width;
+        if width <= 0.0 || !width.is_finite() {
+            self.sections.clear();
+            return;
+        }
+        let section_index = |x: f32| (x / width).floor().max(0.0) as usize;
+
+        let section_count = enemies
+            .iter()
+            .map(|enemy| enemy.location.x)
+            .chain(items.iter().map(|item| item.location.x))
+            .map(section_index)
+            .max()
+            .unwrap_or(0);
+        self.sections = (1..=section_count)
+            .map(|i| LevelSectionMeta {
+                start_x: i as f32 * width,
+                end_x: (i + 1) as f32 * width,
+                ..default()
+            })
+            .collect();
+
+        for enemy in std::mem::take(enemies) {
+            match section_index(enemy.location.x) {
+                0 => enemies.push(enemy),
+                i => self.sections[i - 1].enemies.push(enemy),
+            }
+        }
+        for item in std::mem::take(items) {
+            match section_index(item.location.x) {
+                0 => items.push(item),
+                i => self.sections[i - 1].items.push(item),
+            }
+        }
+    }
+}
+
+/// The definition of a fighter, loaded from a `.fighter.yaml` file.
+///
+/// A fighter file may start with `extends: path/to/base.fighter.yaml` to inherit all of the fields
+/// of another fighter, only overriding the fields that it specifies. Nested maps, such as `stats`,
+/// are merged, so a variant may override a single stat and keep the rest.
+#[derive(TypeUuid, Deserialize, Clone, Debug, Component, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 #[uuid = "d5e040c4-3de7-4b8a-b6c2-27f82f58d8f0"]
 pub struct FighterMeta {
@@ -112,9 +848,288 @@ pub struct FighterMeta {
     pub hud: FighterHudMeta,
     pub spritesheet: FighterSpritesheetMeta,
     pub audio: AudioMeta,
+    #[serde(default)]
+    pub sounds: FighterSoundsMeta,
     pub hurtbox: ColliderMeta,
     pub attacks: Vec<AttackMeta>,
     pub attachment: Option<FighterSpritesheetMeta>,
+    /// How the fighter flies when it's thrown, if it's light enough to be thrown
+    #[serde(default)]
+    pub thrown: ThrownMeta,
+    /// Where the fighter carries the fighters it grabs, relative to its feet while facing right
+    #[serde(default = "default_carry_offset")]
+    pub carry_offset: Vec2,
+    /// How the fighter holds the players it catches with a `grab` attack
+    #[serde(default)]
+    pub grapple: GrappleMeta,
+    /// How much the fighter's block protects it, and how long its parry window is
+    #[serde(default)]
+    pub block: BlockMeta,
+    /// How the fighter is knocked down by heavy hits and gets back up, if it can be knocked down
+    #[serde(default)]
+    pub knockdown: Option<KnockdownMeta>,
+    /// The shadow under the fighter, which is sized after its hurtbox if it isn't given
+    #[serde(default)]
+    pub shadow: Option<ShadowMeta>,
+    /// The named points on the fighter's sprite, like its `hand`, that attachments follow
+    #[serde(default)]
+    pub sockets: HashMap<String, SocketMeta>,
+    /// The nameplate that introduces the fighter the first time it appears as an enemy
+    #[serde(default)]
+    pub nameplate: Option<FighterNameplateMeta>,
+    /// The faction that the fighter fights for as an enemy, which fights the other enemies too. The
+    /// enemies without one are on the same team.
+    #[serde(default)]
+    pub team: Option<String>,
+    /// The phases that the fighter goes through as a boss, in order, as its health runs low
+    #[serde(default)]
+    pub boss_phases: Vec<BossPhaseMeta>,
+    /// How the fighter flies above the ground as an enemy, if it's a flying enemy. Only the attacks
+    /// made from the air and the [anti-air](AttackMeta::anti_air) attacks reach it there.
+    #[serde(default)]
+    pub flying: Option<FlyingMeta>,
+}
+
+/// A phase of a boss fight, which changes how the boss fights from the moment it starts until the
+/// next phase does
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct BossPhaseMeta {
+    /// The part of its max health, from `0.0` to `1.0`, that the boss starts the phase at
+    pub health: f32,
+    /// The clips that replace the ones of the boss's spritesheet during the phase, from the same
+    /// images
+    #[serde(default)]
+    pub animations: HashMap<String, Clip>,
+    /// The names of the boss's attacks that it uses during the phase, the last one first. The boss
+    /// keeps the attacks of the phase before if it's empty.
+    #[serde(default)]
+    pub attacks: Vec<String>,
+    /// The movement speed of the boss during the phase, if it changes
+    #[serde(default)]
+    pub movement_speed: Option<f32>,
+    /// The enemies that join the fight when the phase starts
+    #[serde(default)]
+    pub minions: Vec<BossMinionMeta>,
+}
+
+/// An enemy spawned by a boss when it starts a phase
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct BossMinionMeta {
+    pub fighter: String,
+    #[serde(skip)]
+    pub fighter_handle: Handle<FighterMeta>,
+    /// Where the enemy is spawned, relative to the feet of the boss
+    #[serde(default)]
+    pub offset: Vec2,
+}
+
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct FighterNameplateMeta {
+    /// The localization key of the name of the enemy. Enemies with the same name are introduced
+    /// only once.
+    pub name: String,
+    /// How dangerous the enemy is, shown with as many threat icons
+    #[serde(default)]
+    pub threat: u32,
+}
+
+/// The shadow that a fighter casts on the ground under it
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct ShadowMeta {
+    /// The width and height of the shadow while the fighter is on the ground
+    pub size: Vec2,
+}
+
+/// How a flying enemy hovers above the ground
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct FlyingMeta {
+    /// How high above the ground the enemy hovers
+    pub height: f32,
+    /// How far up and down from its height the enemy bobs
+    #[serde(default)]
+    pub bob_height: f32,
+    /// How long a bob up and down takes, in seconds. The enemy holds still at its height without
+    /// one.
+    #[serde(default)]
+    pub bob_period: f32,
+}
+
+/// A named point on a fighter's sprite that attachments follow while it animates, relative to the
+/// feet of the fighter facing right
+#[derive(Deserialize, Clone, Debug, Default, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct SocketMeta {
+    /// The position of the socket during the animations that don't give one
+    #[serde(default)]
+    pub offset: Vec2,
+    /// The positions of the socket during animations
+    #[serde(default)]
+    pub animations: HashMap<String, SocketOffsets>,
+}
+
+impl SocketMeta {
+    /// The position of the socket during the `frame` of the `animation`
+    pub fn offset(&self, animation: Option<&str>, frame: usize) -> Vec2 {
+        match animation.and_then(|animation| self.animations.get(animation)) {
+            Some(SocketOffsets::Clip(offset)) => *offset,
+            // Clips that are longer than their positions stay at the last one
+            Some(SocketOffsets::Frames(offsets)) => offsets
+                .get(frame)
+                .or_else(|| offsets.last())
+                .copied()
+                .unwrap_or(self.offset),
+            None => self.offset,
+        }
+    }
+}
+
+/// The positions of a socket during an animation
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(untagged)]
+pub enum SocketOffsets {
+    /// One position for the whole clip
+    Clip(Vec2),
+    /// One position for each frame of the clip
+    Frames(Vec<Vec2>),
+}
+
+/// How a fighter flies when it's picked up and thrown by another fighter
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct ThrownMeta {
+    /// The damage dealt to the fighters that the thrown fighter hits, and to the thrown fighter
+    /// itself when it hits the first one
+    pub damage: i32,
+    /// The speed that the fighter is thrown at
+    pub speed: f32,
+    /// How long the fighter flies for, in seconds
+    pub duration: f32,
+    /// How much higher than where it's thrown from the fighter flies, before falling to the ground
+    pub arc_height: f32,
+    /// The hitstun duration of the fighters that are hit
+    pub hitstun_duration: f32,
+    /// The damage that the fighter takes when it's slammed into the ground instead of thrown
+    pub slam_damage: i32,
+}
+
+impl Default for ThrownMeta {
+    fn default() -> Self {
+        Self {
+            damage: 20,
+            speed: 400.0,
+            duration: 0.5,
+            arc_height: 16.0,
+            hitstun_duration: 0.5,
+            slam_damage: 30,
+        }
+    }
+}
+
+fn default_carry_offset() -> Vec2 {
+    consts::HELD_FIGHTER_OFFSET
+}
+
+/// How a fighter holds a player in front of it after catching them with its grab attack, while the
+/// other enemies hit them
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct GrappleMeta {
+    /// How long the fighter holds on to the player for, in seconds
+    pub hold_time: f32,
+    /// How many times the player has to press attack to break free
+    pub escape_presses: u32,
+    /// How long the player can't be grabbed again after being let go, in seconds
+    pub immunity_time: f32,
+}
+
+impl Default for GrappleMeta {
+    fn default() -> Self {
+        Self {
+            hold_time: 2.0,
+            escape_presses: 6,
+            immunity_time: 1.5,
+        }
+    }
+}
+
+/// How a player's block protects them from the hits they take while holding the block button
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct BlockMeta {
+    /// The part of the damage of a blocked hit that the fighter doesn't take, where `1.0` blocks
+    /// all of it
+    pub damage_reduction: f32,
+    /// How many gameplay steps after starting to block a hit is parried, taking no damage and
+    /// staggering the attacker
+    pub parry_frames: u32,
+    /// How long the attackers are staggered for when their hits are parried, in seconds
+    pub parry_stagger: f32,
+    /// The animation played while blocking
+    pub animation: String,
+}
+
+impl Default for BlockMeta {
+    fn default() -> Self {
+        Self {
+            damage_reduction: 0.75,
+            parry_frames: 6,
+            parry_stagger: 0.6,
+            // The fighters brace themselves with the start of their hit stun
+            animation: "hitstun".into(),
+        }
+    }
+}
+
+impl BlockMeta {
+    /// The damage that the fighter takes from a blocked hit that isn't parried
+    pub fn damage_taken(&self, damage: i32) -> i32 {
+        (damage as f32 * (1.0 - self.damage_reduction)).round() as i32
+    }
+}
+
+/// How a fighter is knocked down by the hits that deal enough damage, and gets back up
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct KnockdownMeta {
+    /// The damage of a single hit that knocks the fighter down instead of stunning it
+    pub damage: i32,
+    /// How long the fighter lies on the floor, in seconds
+    #[serde(default = "default_down_time")]
+    pub down_time: f32,
+    /// The part of the damage of the hits that the fighter takes while it's down, where `0.0`
+    /// can't hit it at all
+    #[serde(default)]
+    pub down_damage_scale: f32,
+    /// How long it takes the fighter to get back up, in seconds
+    #[serde(default)]
+    pub rise_time: f32,
+    /// How long the fighter can't be hit for after it's back up, in seconds
+    #[serde(default)]
+    pub invulnerability: f32,
+    /// The animation played while the fighter is down
+    #[serde(default = "default_down_animation")]
+    pub animation: String,
+    /// The animation played while the fighter gets back up
+    #[serde(default = "default_rise_animation")]
+    pub rise_animation: String,
+}
+
+fn default_down_time() -> f32 {
+    1.0
+}
+
+fn default_down_animation() -> String {
+    // The fighters fall over the same way as when they are knocked out
+    "dying".into()
+}
+
+fn default_rise_animation() -> String {
+    "idle".into()
 }
 
 #[derive(TypeUuid, Deserialize, Clone, Debug, Component, Reflect, FromReflect)]
@@ -130,9 +1145,134 @@ pub struct AttackMeta {
     pub item: Option<String>,
     #[serde(skip)]
     pub item_handle: Handle<ItemMeta>,
+    /// The afterimages that the fighter leaves behind while it does the move
+    #[serde(default)]
+    pub afterimages: Option<AfterimageMeta>,
+    /// The steps of a `chain` attack, which pressing attack again chains through one after the
+    /// other
+    #[serde(default)]
+    pub combo: Vec<ComboStepMeta>,
+    /// The status effect that the attack puts on the fighters it hits
+    #[serde(default)]
+    pub status_effect: Option<StatusEffectMeta>,
+    /// How high the attack launches the fighters it hits into the air, where the follow-up hits
+    /// juggle them. Attacks that don't launch leave the fighters on the ground.
+    #[serde(default)]
+    pub launch: f32,
+    /// Whether the attack reaches the flying enemies even when it's made from the ground, like an
+    /// uppercut or a shot aimed up at the sky
+    #[serde(default)]
+    pub anti_air: bool,
+    /// The projectile that the attack shoots, which hits with the damage and the effects of the
+    /// attack. Players shoot it with the shoot button.
+    #[serde(default)]
+    pub projectile: Option<ProjectileMeta>,
+    /// How an enemy fights from a distance with its `projectile` or `bomb_throw` attack, instead of
+    /// walking up to the players to shoot or throw it at them
+    #[serde(default)]
+    pub ranged: Option<RangedAttackMeta>,
+}
+
+/// How an enemy keeps its distance from the players while it shoots or throws at them
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct RangedAttackMeta {
+    /// How far from the players the enemy lines up to shoot or throw at them. The enemies that the
+    /// players get closer to back away to this distance, even on their way to their spot.
+    pub range: f32,
+    /// The clip played before the attack, which warns the players of the shot coming
+    #[serde(default)]
+    pub telegraph: Option<String>,
+}
+
+/// A projectile shot by an attack of a fighter
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectileMeta {
+    pub image: ImageMeta,
+    /// How fast the projectile is shot
+    pub speed: f32,
+    #[serde(default)]
+    pub trajectory: ProjectileTrajectory,
+    /// How long the projectile flies before it's gone, in seconds
+    pub lifetime: f32,
+    /// How many fighters the projectile flies through, before it's gone with the next one it hits
+    #[serde(default)]
+    pub piercing: i32,
+    /// How fast the projectile spins while it flies, in radians per second
+    #[serde(default)]
+    pub spin: f32,
+}
+
+/// How a projectile flies once it's shot
+#[derive(Deserialize, Clone, Copy, Debug, Default, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub enum ProjectileTrajectory {
+    /// Flies straight ahead
+    #[default]
+    Straight,
+    /// Is shot up at `angle` degrees, and falls back down with `gravity`
+    Arcing { angle: f32, gravity: f32 },
+    /// Turns towards the closest fighter that it can hit, by up to `turn_rate` degrees a second
+    Homing { turn_rate: f32 },
+}
+
+/// A step of a `chain` attack. The hit of the step is the one of its attack, except for what the
+/// step changes.
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct ComboStepMeta {
+    /// The animation of the step, from the fighter's spritesheet animations
+    pub animation: String,
+    #[serde(default)]
+    pub damage: Option<i32>,
+    /// The knockback of the step's hit
+    #[serde(default)]
+    pub velocity: Option<Vec2>,
+    #[serde(default)]
+    pub hitstun_duration: Option<f32>,
+    #[serde(default)]
+    pub frames: Option<AttackFrames>,
+    #[serde(default)]
+    pub hitbox: Option<ColliderMeta>,
+    /// The frames of the animation during which the step is canceled into the next one, if attack
+    /// has been pressed again by then. Steps without a cancel window end the chain.
+    #[serde(default)]
+    pub cancel_window: Option<CancelWindow>,
+    /// How high the step's hit launches the fighters into the air, like a closing uppercut
+    #[serde(default)]
+    pub launch: Option<f32>,
+}
+
+/// A range of animation frames, from `start` to `end` including both
+#[derive(Deserialize, Clone, Copy, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct CancelWindow {
+    pub start: usize,
+    pub end: usize,
 }
 
-#[derive(TypeUuid, Deserialize, Clone, Debug, Component)]
+/// The fading copies of its sprite that a fighter leaves behind while it does a fast move
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct AfterimageMeta {
+    /// The time between two afterimages, in seconds
+    pub interval: f32,
+    /// How long an afterimage takes to fade away, in seconds
+    pub lifetime: f32,
+    /// How opaque an afterimage is when it's left behind, from 0 to 1
+    #[serde(default = "default_afterimage_opacity")]
+    pub opacity: f32,
+    /// The color the afterimages are tinted with
+    #[serde(default)]
+    pub tint: Option<[u8; 3]>,
+}
+
+fn default_afterimage_opacity() -> f32 {
+    0.5
+}
+
+#[derive(TypeUuid, Deserialize, Clone, Debug, Component, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 #[uuid = "5e2db270-ec2e-013a-92a8-2cf05d71216b"]
 pub struct ItemMeta {
@@ -141,7 +1281,7 @@ pub struct ItemMeta {
     pub kind: ItemKind,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub enum ItemKind {
     Throwable {
@@ -151,6 +1291,9 @@ pub enum ItemKind {
         lifetime: f32,
         pushback: f32,
         hitstun_duration: f32,
+        /// The status effect that the item puts on the fighters it hits
+        #[serde(default)]
+        status_effect: Option<StatusEffectMeta>,
     },
     BreakableBox {
         damage: i32,
@@ -168,13 +1311,21 @@ pub enum ItemKind {
     MeleeWeapon {
         attack: AttackMeta,
         audio: AudioMeta,
-        spritesheet: Box<FighterSpritesheetMeta>,
+        spritesheet: FighterSpritesheetMeta,
         sprite_offset: Vec2,
+        /// The animation from the fighter's spritesheet that the fighter holding the weapon swings
+        /// it with. Fighters that don't have it keep playing the animation they were playing.
+        #[serde(default)]
+        swing_animation: Option<String>,
+        /// How many hits the weapon lands before it breaks. Weapons without a durability never
+        /// break.
+        #[serde(default)]
+        durability: Option<u32>,
     },
     ProjectileWeapon {
         attack: AttackMeta,
         audio: AudioMeta,
-        spritesheet: Box<FighterSpritesheetMeta>,
+        spritesheet: FighterSpritesheetMeta,
         sprite_offset: Vec2,
         bullet_velocity: f32,
         bullet_lifetime: f32,
@@ -195,15 +1346,150 @@ pub enum ItemKind {
         throw_velocity: Vec2,
         lifetime: f32,
     },
+    /// Coats the melee weapon held by the fighter that picks it up with an element for a while.
+    /// Fighters that aren't holding a melee weapon can't pick it up.
+    Coating {
+        element: Element,
+        /// How long the weapon stays coated, in seconds
+        duration: f32,
+        /// The damage added to the hits of the coated weapon
+        #[serde(default)]
+        bonus_damage: i32,
+        /// The color of the coated weapon and of the trail it leaves behind
+        tint: [u8; 3],
+    },
 }
 
-#[derive(Deserialize, Clone, Debug)]
+/// The element of a [coating](ItemKind::Coating), and the effect it has on the fighters that are
+/// hit with the coated weapon
+#[derive(Deserialize, Clone, Copy, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub enum Element {
+    /// Sets the fighters on fire, hurting them every second while they burn
+    Fire {
+        damage_per_second: i32,
+        /// How long the fighters burn for, in seconds
+        burn_time: f32,
+    },
+    /// Shocks the fighters, stunning them for longer than the weapon alone would
+    Shock {
+        /// The time added to the hitstun of the weapon's hits, in seconds
+        extra_stun_time: f32,
+    },
+}
+
+impl Element {
+    /// The time added to the hitstun of the hits of a weapon coated with this element
+    pub fn extra_stun_time(&self) -> f32 {
+        match self {
+            Element::Shock { extra_stun_time } => *extra_stun_time,
+            Element::Fire { .. } => 0.0,
+        }
+    }
+
+    /// The status effect that the hits of a weapon coated with this element put on the fighters
+    pub fn status_effect(&self) -> Option<StatusEffectMeta> {
+        match *self {
+            Element::Fire {
+                damage_per_second,
+                burn_time,
+            } => Some(StatusEffectMeta {
+                kind: StatusEffectKind::Burn { damage_per_second },
+                duration: burn_time,
+            }),
+            Element::Shock { .. } => None,
+        }
+    }
+}
+
+/// A status effect that a hit puts on the fighter it hits, lasting for a while
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct StatusEffectMeta {
+    pub kind: StatusEffectKind,
+    /// How long the effect lasts, in seconds
+    pub duration: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub enum StatusEffectKind {
+    /// Sets the fighter on fire, hurting it every second
+    Burn { damage_per_second: i32 },
+    /// Hurts the fighter every second, and slows it down
+    Poison {
+        damage_per_second: i32,
+        /// The part of the fighter's speed that it loses, from `0.0` to `1.0`
+        slowdown: f32,
+    },
+    /// Stops the fighter in place, with its animation frozen
+    Freeze,
+    /// Keeps the fighter stunned
+    Stun,
+}
+
+/// The post-processing effects of a level
+#[derive(Deserialize, Clone, Debug, Default, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct PostProcessingMeta {
+    /// The effects at the start of the level
+    #[serde(default)]
+    pub effects: PostEffectsMeta,
+    /// Changes to the effects that are triggered as the camera moves through the level, sorted
+    /// from left to right
+    #[serde(default)]
+    pub triggers: Vec<PostProcessingTriggerMeta>,
+}
+
+/// The intensities of the post-processing effects, from `0.0` ( off ) to `1.0`
+#[derive(Deserialize, Clone, Debug, Default, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct PostEffectsMeta {
+    /// Scanlines, vignette, and screen curvature
+    pub crt: f32,
+    /// Wavy distortion, like the air above hot sand
+    pub heat_haze: f32,
+    /// The color to grade the image towards
+    pub color_grade: [u8; 3],
+    /// How much to grade the image towards the `color_grade`
+    pub color_grade_intensity: f32,
+}
+
+impl PostEffectsMeta {
+    /// Interpolate between two sets of effects
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let color = |i: usize| lerp(self.color_grade[i] as f32, other.color_grade[i] as f32);
+
+        Self {
+            crt: lerp(self.crt, other.crt),
+            heat_haze: lerp(self.heat_haze, other.heat_haze),
+            color_grade: [color(0) as u8, color(1) as u8, color(2) as u8],
+            color_grade_intensity: lerp(self.color_grade_intensity, other.color_grade_intensity),
+        }
+    }
+}
+
+/// A change in the post-processing effects when the camera reaches a point in the level
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct PostProcessingTriggerMeta {
+    /// The x position the camera has to reach to trigger the change
+    pub x: f32,
+    /// The effects to transition to
+    pub effects: PostEffectsMeta,
+    /// The time it takes to transition to the new effects, in seconds
+    #[serde(default)]
+    pub transition: f32,
+}
+
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct FighterHudMeta {
     pub portrait: ImageMeta,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct FighterSpritesheetMeta {
     pub image: Vec<String>,
@@ -214,9 +1500,46 @@ pub struct FighterSpritesheetMeta {
     pub rows: usize,
     pub animation_fps: f32,
     pub animations: HashMap<String, Clip>,
+    /// Optional palette lookup image used to recolor the spritesheet.
+    ///
+    /// See the [`palette`][crate::palette] module for the format of the image.
+    #[serde(default)]
+    pub palette: Option<String>,
+    #[serde(skip)]
+    pub palette_handle: Handle<Image>,
+    /// The number of recolors in the palette lookup image
+    #[serde(skip)]
+    pub palette_count: usize,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+impl FighterSpritesheetMeta {
+    /// Pick a texture atlas and a recolor for a fighter using this spritesheet.
+    ///
+    /// Palette `0` is the original spritesheet and palette `n` is the `n`th recolor, wrapping
+    /// around if there aren't enough palettes. If no palette is given, a random one is picked.
+    pub fn choose_atlas(
+        &self,
+        palette: Option<usize>,
+        rng: &mut impl Rng,
+    ) -> (Handle<TextureAtlas>, Option<PaletteSwap>) {
+        let palette_count = self.palette_count + 1;
+        let palette = palette.unwrap_or_else(|| rng.gen_range(0..palette_count)) % palette_count;
+
+        let atlas = self
+            .atlas_handle
+            .choose(rng)
+            .expect("Spritesheet has no images")
+            .clone();
+        let palette_swap = (palette != 0).then(|| PaletteSwap {
+            palette: self.palette_handle.clone(),
+            row: palette as u32,
+        });
+
+        (atlas, palette_swap)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct AudioMeta {
     pub effects: HashMap<String, HashMap<usize, String>>,
@@ -224,7 +1547,105 @@ pub struct AudioMeta {
     pub effect_handles: HashMap<String, HashMap<usize, Handle<AudioSource>>>,
 }
 
-#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+/// Sounds played in response to things that happen to a fighter, as opposed to the
+/// [`AudioMeta`] effects which are played on specific animation frames.
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields, default)]
+pub struct FighterSoundsMeta {
+    /// Played when one of the fighter's attacks hits something
+    pub hit: SoundVariationsMeta,
+    /// Played instead of the `hit` sound when one of the fighter's attacks is a critical hit
+    pub critical_hit: SoundVariationsMeta,
+    /// Played when one of the fighter's attacks ends without hitting anything
+    pub whiff: SoundVariationsMeta,
+    /// Played when the fighter is damaged
+    pub hurt: SoundVariationsMeta,
+    /// Played when the fighter is knocked out
+    pub ko: SoundVariationsMeta,
+    /// Played on the `footstep_frames` of the walking animation, and on the frames of any animation
+    /// that are tagged with `footstep`
+    pub footsteps: SoundVariationsMeta,
+    pub footstep_frames: Vec<usize>,
+    /// Played when the fighter starts an attack, with a `voice_chance` chance
+    pub voice: SoundVariationsMeta,
+    pub voice_chance: f32,
+}
+
+impl Default for FighterSoundsMeta {
+    fn default() -> Self {
+        Self {
+            hit: default(),
+            critical_hit: default(),
+            whiff: default(),
+            hurt: default(),
+            ko: default(),
+            footsteps: default(),
+            footstep_frames: default(),
+            voice: default(),
+            voice_chance: 0.3,
+        }
+    }
+}
+
+impl FighterSoundsMeta {
+    /// The sound of the critical hits, which is the `hit` sound if the fighter doesn't have one
+    pub fn critical_hit(&self) -> &SoundVariationsMeta {
+        if self.critical_hit.files.is_empty() {
+            &self.hit
+        } else {
+            &self.critical_hit
+        }
+    }
+
+    /// Iterate over all of the sounds
+    pub fn iter(&self) -> impl Iterator<Item = &SoundVariationsMeta> {
+        [
+            &self.hit,
+            &self.critical_hit,
+            &self.whiff,
+            &self.hurt,
+            &self.ko,
+            &self.footsteps,
+            &self.voice,
+        ]
+        .into_iter()
+    }
+
+    /// Iterate mutably over all of the sounds, to load their handles
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut SoundVariationsMeta> {
+        [
+            &mut self.hit,
+            &mut self.critical_hit,
+            &mut self.whiff,
+            &mut self.hurt,
+            &mut self.ko,
+            &mut self.footsteps,
+            &mut self.voice,
+        ]
+        .into_iter()
+    }
+}
+
+/// A list of sound files, one of which is picked at random every time the sound is played
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Default, Reflect, FromReflect)]
+#[serde(transparent)]
+pub struct SoundVariationsMeta {
+    pub files: Vec<String>,
+    #[serde(skip)]
+    #[cfg_attr(target_arch = "wasm32", has_load_progress(none))]
+    pub handles: Vec<Handle<AudioSource>>,
+}
+
+impl SoundVariationsMeta {
+    /// Pick one of the sounds at random, if there are any.
+    ///
+    /// Sounds are cosmetic, so this doesn't use the seeded [`GameRng`][crate::rng::GameRng].
+    pub fn choose(&self) -> Option<Handle<AudioSource>> {
+        self.handles.choose(&mut rand::thread_rng()).cloned()
+    }
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct FighterSpawnMeta {
     pub fighter: String,
@@ -242,7 +1663,7 @@ fn default_f32_min() -> f32 {
     f32::MIN
 }
 
-#[derive(HasLoadProgress, TypeUuid, Deserialize, Clone, Debug)]
+#[derive(HasLoadProgress, TypeUuid, Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 #[uuid = "f5092550-ec30-013a-92a9-2cf05d71216b"]
 pub struct ItemSpawnMeta {
@@ -252,7 +1673,7 @@ pub struct ItemSpawnMeta {
     pub location: Vec3,
 }
 
-#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct ParallaxMeta {
     pub layers: Vec<ParallaxLayerMeta>,
@@ -264,7 +1685,7 @@ impl ParallaxMeta {
     }
 }
 
-#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 pub struct ParallaxLayerMeta {
     pub speed: f32,
@@ -297,7 +1718,9 @@ impl From<ParallaxLayerMeta> for LayerData {
     }
 }
 
-#[derive(HasLoadProgress, Deserialize, Default, Copy, Clone, Debug, Reflect, FromReflect)]
+#[derive(
+    HasLoadProgress, Deserialize, Default, Copy, Clone, Debug, PartialEq, Reflect, FromReflect,
+)]
 #[serde(deny_unknown_fields)]
 pub struct ColliderMeta {
     //TODO: Add type of collider with different properties.
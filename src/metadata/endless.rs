@@ -0,0 +1,280 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::Deserialize;
+
+use super::{
+    FighterSpawnMeta, ItemSpawnMeta, LevelMeta, LevelStreamingMeta, ParallaxMeta,
+    PostProcessingMeta,
+};
+
+/// The definition of an endless-mode level, loaded from an `.endless.yaml` file.
+///
+/// Instead of describing a single level, the file describes a set of level "chunks" that are
+/// picked at random and placed one after another to generate a [`LevelMeta`]. The generated level
+/// is loaded exactly like a hand-made `.level.yaml` file.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EndlessLevelMeta {
+    /// The seed used to generate the level.
+    ///
+    /// If this isn't set, the `--level-seed` or `--seed` argument is used, and if neither is set, a
+    /// new random seed is used every time the level is loaded.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The number of chunks to generate after the `start` chunk
+    pub length: usize,
+    pub players: Vec<FighterSpawnMeta>,
+    /// The visual themes of the level, one of which is picked for every generated level
+    pub themes: Vec<LevelThemeMeta>,
+    /// The chunk the players start in, which is always placed first
+    pub start: LevelChunkMeta,
+    /// The chunks that may be placed after the `start` chunk
+    pub chunks: Vec<LevelChunkMeta>,
+    /// Load the generated level in sections, like [`LevelMeta::streaming`]
+    #[serde(default)]
+    pub streaming: Option<LevelStreamingMeta>,
+    /// How far outside of the camera view enemies are still updated, like
+    /// [`LevelMeta::activity_distance`]
+    #[serde(default)]
+    pub activity_distance: Option<f32>,
+}
+
+/// The background, music, and effects of a generated level
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LevelThemeMeta {
+    pub name: String,
+    pub background_color: [u8; 3],
+    pub parallax_background: ParallaxMeta,
+    pub music: String,
+    #[serde(default)]
+    pub post_processing: Option<PostProcessingMeta>,
+}
+
+/// A section of a generated level.
+///
+/// The locations of the enemies and items are relative to the left edge of the chunk.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LevelChunkMeta {
+    /// The width of the chunk in pixels
+    pub width: f32,
+    /// How likely this chunk is to be picked, relative to the other chunks
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    /// The names of the themes this chunk may be used with. Empty means all themes.
+    #[serde(default)]
+    pub themes: Vec<String>,
+    #[serde(default)]
+    pub enemies: Vec<FighterSpawnMeta>,
+    #[serde(default)]
+    pub items: Vec<ItemSpawnMeta>,
+    /// Whether the camera should stop at the end of this chunk until the enemies are defeated.
+    ///
+    /// Defaults to `true` if the chunk has any enemies.
+    #[serde(default)]
+    pub stop_point: Option<bool>,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl LevelChunkMeta {
+    fn fits_theme(&self, theme: &LevelThemeMeta) -> bool {
+        self.themes.is_empty() || self.themes.contains(&theme.name)
+    }
+}
+
+impl EndlessLevelMeta {
+    /// Generate a level from the chunks. The same seed always generates the same level.
+    ///
+    /// The asset paths in the returned level are the same as in the endless level file, and their
+    /// handles still have to be loaded.
+    pub fn generate(&self, seed: u64) -> anyhow::Result<LevelMeta> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let theme = self
+            .themes
+            .choose(&mut rng)
+            .ok_or_else(|| anyhow::format_err!("Endless level doesn't have any themes"))?;
+        let chunks = self
+            .chunks
+            .iter()
+            .filter(|chunk| chunk.fits_theme(theme))
+            .collect::<Vec<_>>();
+        if chunks.is_empty() && self.length > 0 {
+            anyhow::bail!(
+                "Endless level doesn't have any chunks for theme: {}",
+                theme.name
+            );
+        }
+
+        let mut level = LevelMeta {
+            background_color: theme.background_color,
+            parallax_background: theme.parallax_background.clone(),
+            players: self.players.clone(),
+            enemies: Vec::new(),
+            items: Vec::new(),
+            music: theme.music.clone(),
+            music_handle: Default::default(),
+            stop_points: Vec::new(),
+            post_processing: theme.post_processing.clone(),
+            streaming: self.streaming.clone(),
+            activity_distance: self.activity_distance,
+            tutorial: Vec::new(),
+            dialogues: Vec::new(),
+            director: None,
+            hazards: Vec::new(),
+            waves: Vec::new(),
+        };
+
+        let mut chunk_x = 0.0;
+        let mut place_chunk = |chunk: &LevelChunkMeta| {
+            for enemy in &chunk.enemies {
+                let mut enemy = enemy.clone();
+                enemy.location.x += chunk_x;
+                // Enemies with the default trip point are always active, so leave them alone
+                if enemy.trip_point_x != f32::MIN {
+                    enemy.trip_point_x += chunk_x;
+                }
+                level.enemies.push(enemy);
+            }
+            for item in &chunk.items {
+                let mut item = item.clone();
+                item.location.x += chunk_x;
+                level.items.push(item);
+            }
+
+            chunk_x += chunk.width;
+            if chunk.stop_point.unwrap_or(!chunk.enemies.is_empty()) {
+                level.stop_points.push(chunk_x);
+            }
+        };
+
+        place_chunk(&self.start);
+        for _ in 0..self.length {
+            let chunk = chunks
+                .choose_weighted(&mut rng, |chunk| chunk.weight)
+                .map_err(|e| anyhow::format_err!("Invalid chunk weights: {e}"))?;
+            place_chunk(chunk);
+        }
+
+        Ok(level)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ENDLESS_LEVEL: &str = r#"
+length: 8
+players:
+  - fighter: player.fighter.yaml
+    location: [0, 0, 0]
+themes:
+  - name: beach
+    background_color: [0, 0, 0]
+    parallax_background:
+      layers: []
+    music: beach.mp3
+  - name: cave
+    background_color: [0, 0, 0]
+    parallax_background:
+      layers: []
+    music: cave.mp3
+start:
+  width: 100
+chunks:
+  - width: 200
+    enemies:
+      - fighter: enemy.fighter.yaml
+        location: [50, 0, 0]
+        trip_point_x: 10
+  - width: 300
+    themes: [cave]
+    items:
+      - item: box.item.yaml
+        location: [20, 0, 0]
+"#;
+
+    fn spawns(level: &LevelMeta) -> Vec<(f32, f32)> {
+        level
+            .enemies
+            .iter()
+            .map(|x| (x.location.x, x.trip_point_x))
+            .chain(level.items.iter().map(|x| (x.location.x, 0.0)))
+            .collect()
+    }
+
+    #[test]
+    fn generate_is_reproducible() {
+        let meta: EndlessLevelMeta = serde_yaml::from_str(ENDLESS_LEVEL).unwrap();
+
+        for seed in 0..16 {
+            let a = meta.generate(seed).unwrap();
+            let b = meta.generate(seed).unwrap();
+            assert_eq!(a.music, b.music);
+            assert_eq!(a.stop_points, b.stop_points);
+            assert_eq!(spawns(&a), spawns(&b));
+        }
+    }
+
+    #[test]
+    fn generate_offsets_chunks() {
+        let meta: EndlessLevelMeta = serde_yaml::from_str(ENDLESS_LEVEL).unwrap();
+
+        for seed in 0..16 {
+            let level = meta.generate(seed).unwrap();
+
+            // Only the cave theme can use the item chunk
+            if level.music == "beach.mp3" {
+                assert!(level.items.is_empty());
+                assert_eq!(level.enemies.len(), 8);
+            }
+
+            // Every enemy chunk ends in a stop point, right after its enemy
+            for (enemy, stop_point) in level.enemies.iter().zip(&level.stop_points) {
+                assert_eq!(stop_point - enemy.location.x, 150.0);
+                assert_eq!(enemy.location.x - enemy.trip_point_x, 40.0);
+            }
+            for item in &level.items {
+                assert!(item.location.x >= 100.0);
+            }
+        }
+    }
+
+    #[test]
+    fn streaming_splits_sections() {
+        let meta: EndlessLevelMeta = serde_yaml::from_str(ENDLESS_LEVEL).unwrap();
+        let mut level = meta.generate(0).unwrap();
+        let spawn_count = level.enemies.len() + level.items.len();
+
+        let mut streaming = LevelStreamingMeta {
+            section_width: 250.0,
+            preload_distance: 0.0,
+            sections: Vec::new(),
+        };
+        streaming.split_sections(&mut level.enemies, &mut level.items);
+
+        assert!(level.enemies.iter().all(|x| x.location.x < 250.0));
+        assert!(level.items.iter().all(|x| x.location.x < 250.0));
+        let mut streamed_count = 0;
+        for section in &streaming.sections {
+            assert_eq!(section.end_x - section.start_x, 250.0);
+            for x in section
+                .enemies
+                .iter()
+                .map(|x| x.location.x)
+                .chain(section.items.iter().map(|x| x.location.x))
+            {
+                assert!(section.start_x <= x && x < section.end_x);
+                streamed_count += 1;
+            }
+        }
+        assert_eq!(
+            level.enemies.len() + level.items.len() + streamed_count,
+            spawn_count
+        );
+    }
+}
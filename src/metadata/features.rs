@@ -0,0 +1,45 @@
+use bevy::prelude::Res;
+use punchy_macros::HasLoadProgress;
+use serde::Deserialize;
+
+use super::GameMeta;
+
+/// Optional subsystems that can be enabled or disabled by the game, so that the same binary can
+/// ship different content configurations.
+///
+/// Features are checked by the plugins that implement them, usually with the [`feature_enabled`]
+/// run condition.
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct FeatureFlags {
+    /// Show blood effects when fighters are damaged
+    pub blood_effects: bool,
+    /// Show prompts explaining the controls to new players
+    pub tutorial_prompts: bool,
+    /// Show the buttons for interacting with the nearest item or other interactable to each player
+    pub interaction_prompts: bool,
+    /// Enable moves that are still being worked on, such as the flop that finishes the chain attack
+    pub experimental_moves: bool,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self {
+            blood_effects: false,
+            tutorial_prompts: true,
+            interaction_prompts: true,
+            experimental_moves: false,
+        }
+    }
+}
+
+/// Run condition that checks a feature flag in the [`GameMeta`], i.e.
+/// `.run_if(feature_enabled(|features| features.blood_effects))`.
+///
+/// Features are never enabled before the game has been loaded.
+pub fn feature_enabled(
+    feature: fn(&FeatureFlags) -> bool,
+) -> impl Fn(Option<Res<GameMeta>>) -> bool + Send + Sync + 'static {
+    move |game| game.map(|game| feature(&game.features)).unwrap_or(false)
+}
@@ -1,16 +1,37 @@
-use bevy::prelude::Gamepad;
+use std::time::Duration;
+
+use bevy::{
+    prelude::{Color, Gamepad, GamepadButtonType, KeyCode, Resource},
+    window::{PresentMode, WindowMode},
+};
 use leafwing_input_manager::{axislike::VirtualDPad, prelude::InputMap, user_input::InputKind};
 use punchy_macros::HasLoadProgress;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::input::PlayerAction;
+use crate::input::{InputDevice, PlayerAction};
 
-/// Global settings, stored and accessed through [`crate::platform::Storage`]
-#[derive(HasLoadProgress, Deserialize, Serialize, Debug, Clone)]
+/// Global settings, stored and accessed through [`crate::platform::Storage`]. The settings that are
+/// in use are also kept as a resource, which is updated whenever they are saved.
+#[derive(HasLoadProgress, Resource, Deserialize, Serialize, Debug, Clone)]
 #[has_load_progress(none)]
 pub struct Settings {
     // The player controller bindings
     pub player_controls: PlayerControlMethods,
+    /// The display settings
+    #[serde(default)]
+    pub video: VideoSettings,
+    /// The assist options
+    #[serde(default)]
+    pub assist: AssistSettings,
+    /// The colorblind-friendly color options
+    #[serde(default)]
+    pub colors: ColorSettings,
+    /// What the game does while its window isn't focused
+    #[serde(default)]
+    pub background: BackgroundSettings,
+    /// The narration of the menus
+    #[serde(default)]
+    pub narration: NarrationSettings,
 }
 
 impl Settings {
@@ -18,6 +39,369 @@ impl Settings {
     pub const STORAGE_KEY: &'static str = "settings";
 }
 
+/// Settings for how the game is shown on the displays, and how often frames are drawn
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub window_mode: WindowModeSetting,
+    /// The size of the window, or the video mode in fullscreen. If it isn't set, the window keeps
+    /// its size, and fullscreen uses the resolution of the monitor.
+    pub resolution: Option<Resolution>,
+    /// The index of the monitor to show the game on
+    pub monitor: usize,
+    /// Wait for the display to refresh before showing each frame
+    pub vsync: bool,
+    pub frame_rate_limit: FrameRateLimit,
+    /// Lower the resolution that the levels are rendered at while the frame rate is below a target
+    pub dynamic_resolution: DynamicResolution,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            window_mode: WindowModeSetting::Windowed,
+            resolution: None,
+            monitor: 0,
+            vsync: true,
+            frame_rate_limit: FrameRateLimit::Uncapped,
+            dynamic_resolution: DynamicResolution::Off,
+        }
+    }
+}
+
+impl VideoSettings {
+    /// Get the present mode of the window for the vsync setting
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+}
+
+/// What the game does while its window isn't focused.
+///
+/// The settings that are in use are kept in a resource, see the [`focus`][crate::focus] module.
+#[derive(Resource, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct BackgroundSettings {
+    /// Pause single-player games when the window loses focus
+    pub pause_on_focus_loss: bool,
+    /// Mute the music and the sound effects while the window isn't focused
+    pub mute_in_background: bool,
+    /// Draw fewer frames while the window isn't focused
+    pub reduce_frame_rate: bool,
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            pause_on_focus_loss: true,
+            mute_in_background: false,
+            reduce_frame_rate: true,
+        }
+    }
+}
+
+/// Settings for speaking the menus out loud, for players that can't see them.
+///
+/// The settings that are in use are kept in a resource, see the [`narration`][crate::narration]
+/// module.
+#[derive(Resource, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct NarrationSettings {
+    /// Speak the focused menu items, and changes like the game pausing
+    pub enabled: bool,
+}
+
+/// Whether the game is shown in a window or fills the monitor
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowModeSetting {
+    Windowed,
+    /// A window without borders that covers the monitor
+    Borderless,
+    /// Exclusive fullscreen, which changes the video mode of the monitor
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    /// All of the modes, in the order they are cycled through in the settings menu
+    pub const ALL: [Self; 3] = [Self::Windowed, Self::Borderless, Self::Fullscreen];
+
+    /// The mode of the window, for the resolution setting
+    pub fn window_mode(&self, resolution: Option<Resolution>) -> WindowMode {
+        match self {
+            Self::Windowed => WindowMode::Windowed,
+            Self::Borderless => WindowMode::BorderlessFullscreen,
+            // Use the video mode that is closest to the resolution of the window
+            Self::Fullscreen if resolution.is_some() => WindowMode::SizedFullscreen,
+            Self::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+
+    /// The localization key of the name of the mode
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Windowed => "windowed",
+            Self::Borderless => "borderless",
+            Self::Fullscreen => "fullscreen",
+        }
+    }
+
+    /// Get the next mode in [`WindowModeSetting::ALL`], wrapping around at the end
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The mode that the fullscreen toggle switches to
+    pub fn toggled(&self) -> Self {
+        match self {
+            Self::Windowed => Self::Borderless,
+            Self::Borderless | Self::Fullscreen => Self::Windowed,
+        }
+    }
+}
+
+/// A size of the window or video mode of a monitor, in pixels
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl std::fmt::Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Options that make the game easier, so that more players can finish the campaign. Runs played
+/// with any of them are flagged on the results screens.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct AssistSettings {
+    /// The players lose half as much health from hits
+    pub reduced_damage: bool,
+    /// The game is played at a slower speed
+    pub slower_game_speed: bool,
+    /// The players block the hits they take while standing still
+    pub auto_block: bool,
+    /// Knocked out players come back a few times in each level
+    pub extra_lives: bool,
+}
+
+impl AssistSettings {
+    /// Whether any of the assists are enabled
+    pub fn any(&self) -> bool {
+        self.reduced_damage || self.slower_game_speed || self.auto_block || self.extra_lives
+    }
+}
+
+/// Color options that make the fighters and the HUD easier to tell apart, regardless of how colors
+/// are seen.
+///
+/// The settings that are in use are kept in a resource, see the [`colorblind`][crate::colorblind]
+/// module.
+#[derive(Resource, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ColorSettings {
+    /// The color of the outline drawn around the players
+    pub player_outline: OutlineColor,
+    /// The color of the outline drawn around the enemies
+    pub enemy_outline: OutlineColor,
+    /// Flash hit fighters black and white, instead of only white
+    pub high_contrast_flashes: bool,
+    pub hud_palette: HudPalette,
+}
+
+/// The color of a fighter outline, from a palette that stays distinct with all kinds of color
+/// blindness
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlineColor {
+    #[default]
+    Off,
+    Blue,
+    Orange,
+    Yellow,
+    White,
+    Black,
+}
+
+impl OutlineColor {
+    /// All of the colors, in the order they are cycled through in the settings menu
+    pub const ALL: [Self; 6] = [
+        Self::Off,
+        Self::Blue,
+        Self::Orange,
+        Self::Yellow,
+        Self::White,
+        Self::Black,
+    ];
+
+    /// The color of the outline, or `None` if no outline is drawn
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Self::Off => None,
+            Self::Blue => Some(Color::rgb_u8(0, 114, 178)),
+            Self::Orange => Some(Color::rgb_u8(230, 159, 0)),
+            Self::Yellow => Some(Color::rgb_u8(240, 228, 66)),
+            Self::White => Some(Color::WHITE),
+            Self::Black => Some(Color::BLACK),
+        }
+    }
+
+    /// The localization key of the name of the color
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Blue => "blue",
+            Self::Orange => "orange",
+            Self::Yellow => "yellow",
+            Self::White => "white",
+            Self::Black => "black",
+        }
+    }
+
+    /// Get the next color in [`OutlineColor::ALL`], wrapping around at the end
+    pub fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|color| color == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// The colors of the HUD's lifebars
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HudPalette {
+    /// The colors of the UI theme
+    #[default]
+    Default,
+    Blue,
+    Yellow,
+    White,
+}
+
+impl HudPalette {
+    /// All of the palettes, in the order they are cycled through in the settings menu
+    pub const ALL: [Self; 4] = [Self::Default, Self::Blue, Self::Yellow, Self::White];
+
+    /// The color of the lifebars, or `None` to use the lifebar image of the UI theme
+    pub fn lifebar_color(&self) -> Option<Color> {
+        match self {
+            Self::Default => None,
+            Self::Blue => Some(Color::rgb_u8(86, 180, 233)),
+            Self::Yellow => Some(Color::rgb_u8(240, 228, 66)),
+            Self::White => Some(Color::WHITE),
+        }
+    }
+
+    /// The localization key of the name of the palette
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Blue => "blue",
+            Self::Yellow => "yellow",
+            Self::White => "white",
+        }
+    }
+
+    /// Get the next palette in [`HudPalette::ALL`], wrapping around at the end
+    pub fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|palette| palette == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// The maximum number of frames to draw per second
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameRateLimit {
+    Fps30,
+    Fps60,
+    Fps120,
+    Uncapped,
+}
+
+impl FrameRateLimit {
+    /// All of the limits, in the order they are cycled through in the settings menu
+    pub const ALL: [Self; 4] = [Self::Fps30, Self::Fps60, Self::Fps120, Self::Uncapped];
+
+    /// The number of frames per second, or `None` if the frame rate isn't limited
+    pub fn fps(&self) -> Option<u32> {
+        match self {
+            Self::Fps30 => Some(30),
+            Self::Fps60 => Some(60),
+            Self::Fps120 => Some(120),
+            Self::Uncapped => None,
+        }
+    }
+
+    /// The shortest time that a frame may take
+    pub fn frame_duration(&self) -> Option<Duration> {
+        self.fps()
+            .map(|fps| Duration::from_secs_f64(1.0 / fps as f64))
+    }
+
+    /// Get the next limit in [`FrameRateLimit::ALL`], wrapping around at the end
+    pub fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|limit| limit == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// The frame rate that the render resolution is lowered to keep up with, if it's dynamic
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DynamicResolution {
+    Off,
+    Fps30,
+    Fps60,
+    Fps120,
+}
+
+impl DynamicResolution {
+    /// All of the targets, in the order they are cycled through in the settings menu
+    pub const ALL: [Self; 4] = [Self::Off, Self::Fps30, Self::Fps60, Self::Fps120];
+
+    /// The target number of frames per second, or `None` if the resolution isn't dynamic
+    pub fn target_fps(&self) -> Option<u32> {
+        match self {
+            Self::Off => None,
+            Self::Fps30 => Some(30),
+            Self::Fps60 => Some(60),
+            Self::Fps120 => Some(120),
+        }
+    }
+
+    /// Get the next target in [`DynamicResolution::ALL`], wrapping around at the end
+    pub fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|target| target == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct PlayerControlMethods {
     /// Controls for game remotes
@@ -26,6 +410,30 @@ pub struct PlayerControlMethods {
     pub keyboard1: PlayerControls,
     /// Controls for keyboard player 2
     pub keyboard2: PlayerControls,
+    /// The pause buttons of each of the control methods
+    #[serde(
+        default = "ButtonControls::default_pause",
+        deserialize_with = "ButtonControls::deserialize_pause"
+    )]
+    pub pause: ButtonControls,
+    /// The special buttons of each of the control methods
+    #[serde(
+        default = "ButtonControls::default_special",
+        deserialize_with = "ButtonControls::deserialize_special"
+    )]
+    pub special: ButtonControls,
+    /// The block buttons of each of the control methods
+    #[serde(
+        default = "ButtonControls::default_block",
+        deserialize_with = "ButtonControls::deserialize_block"
+    )]
+    pub block: ButtonControls,
+    /// The dodge buttons of each of the control methods
+    #[serde(
+        default = "ButtonControls::default_dodge",
+        deserialize_with = "ButtonControls::deserialize_dodge"
+    )]
+    pub dodge: ButtonControls,
 }
 
 impl PlayerControlMethods {
@@ -43,15 +451,173 @@ impl PlayerControlMethods {
         };
 
         add_controls(&self.gamepad);
-
         match player_idx {
             0 => add_controls(&self.keyboard1),
             1 => add_controls(&self.keyboard2),
             _ => (),
         }
 
+        for (action, buttons) in self.button_sets() {
+            let devices = [InputDevice::Gamepad, InputDevice::Keyboard];
+            for button in devices
+                .into_iter()
+                .filter_map(|device| buttons.get(player_idx, device))
+            {
+                input_map.insert(button, action);
+            }
+        }
+
         input_map
     }
+
+    /// Get the buttons that perform an action for the given player index on a device, which are
+    /// none if the player can't be controlled with the device
+    pub fn buttons(
+        &self,
+        player_idx: usize,
+        device: InputDevice,
+        action: PlayerAction,
+    ) -> Vec<InputKind> {
+        let controls = match (device, player_idx) {
+            (InputDevice::Gamepad, _) => &self.gamepad,
+            (InputDevice::Keyboard, 0) => &self.keyboard1,
+            (InputDevice::Keyboard, 1) => &self.keyboard2,
+            (InputDevice::Keyboard, _) => return Vec::new(),
+        };
+
+        match action {
+            PlayerAction::Move => {
+                let dpad = &controls.movement;
+                vec![dpad.up, dpad.down, dpad.left, dpad.right]
+            }
+            PlayerAction::Attack => vec![controls.flop_attack],
+            PlayerAction::Throw => vec![controls.throw],
+            PlayerAction::Shoot => vec![controls.shoot],
+            _ => self
+                .button_sets()
+                .into_iter()
+                .filter(|(button_action, _)| *button_action == action)
+                .filter_map(|(_, buttons)| buttons.get(player_idx, device))
+                .collect(),
+        }
+    }
+
+    /// The single-button actions, along with the buttons that perform them
+    fn button_sets(&self) -> [(PlayerAction, ButtonControls); 4] {
+        [
+            (PlayerAction::Pause, self.pause),
+            (PlayerAction::Special, self.special),
+            (PlayerAction::Block, self.block),
+            (PlayerAction::Dodge, self.dodge),
+        ]
+    }
+}
+
+/// The buttons that perform a single action with each of the control methods. They are kept apart
+/// from the [`PlayerControls`] so that the controls saved before they were added still load, and
+/// the buttons missing from the saved controls are the defaults of their action.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct ButtonControls {
+    pub gamepad: InputKind,
+    pub keyboard1: InputKind,
+    pub keyboard2: InputKind,
+    /// Require the button to be held for a moment before it is pressed, so that it isn't pressed
+    /// by accident. Only the pause buttons can be held.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hold_to_pause: bool,
+}
+
+/// The [`ButtonControls`] as they are saved, which may miss any of the buttons
+#[derive(Deserialize)]
+struct SavedButtonControls {
+    #[serde(default)]
+    gamepad: Option<InputKind>,
+    #[serde(default)]
+    keyboard1: Option<InputKind>,
+    #[serde(default)]
+    keyboard2: Option<InputKind>,
+    #[serde(default)]
+    hold_to_pause: Option<bool>,
+}
+
+impl ButtonControls {
+    /// The buttons that pause the game, which are separate from the menu's back button
+    pub fn default_pause() -> Self {
+        Self {
+            gamepad: InputKind::GamepadButton(GamepadButtonType::Start),
+            keyboard1: InputKind::Keyboard(KeyCode::Escape),
+            keyboard2: InputKind::Keyboard(KeyCode::Back),
+            hold_to_pause: false,
+        }
+    }
+
+    /// The buttons that start team-up attacks
+    pub fn default_special() -> Self {
+        Self {
+            gamepad: InputKind::GamepadButton(GamepadButtonType::North),
+            keyboard1: InputKind::Keyboard(KeyCode::B),
+            keyboard2: InputKind::Keyboard(KeyCode::Slash),
+            hold_to_pause: false,
+        }
+    }
+
+    /// The buttons that block
+    pub fn default_block() -> Self {
+        Self {
+            gamepad: InputKind::GamepadButton(GamepadButtonType::RightTrigger),
+            keyboard1: InputKind::Keyboard(KeyCode::X),
+            keyboard2: InputKind::Keyboard(KeyCode::M),
+            hold_to_pause: false,
+        }
+    }
+
+    /// The buttons that dodge
+    pub fn default_dodge() -> Self {
+        Self {
+            gamepad: InputKind::GamepadButton(GamepadButtonType::LeftTrigger),
+            keyboard1: InputKind::Keyboard(KeyCode::Z),
+            keyboard2: InputKind::Keyboard(KeyCode::N),
+            hold_to_pause: false,
+        }
+    }
+
+    fn deserialize_pause<'de, D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        Self::deserialize_or(de, Self::default_pause())
+    }
+
+    fn deserialize_special<'de, D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        Self::deserialize_or(de, Self::default_special())
+    }
+
+    fn deserialize_block<'de, D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        Self::deserialize_or(de, Self::default_block())
+    }
+
+    fn deserialize_dodge<'de, D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        Self::deserialize_or(de, Self::default_dodge())
+    }
+
+    /// Deserialize the saved buttons, filling in the missing ones from the `defaults`
+    fn deserialize_or<'de, D: Deserializer<'de>>(de: D, defaults: Self) -> Result<Self, D::Error> {
+        let saved = SavedButtonControls::deserialize(de)?;
+        Ok(Self {
+            gamepad: saved.gamepad.unwrap_or(defaults.gamepad),
+            keyboard1: saved.keyboard1.unwrap_or(defaults.keyboard1),
+            keyboard2: saved.keyboard2.unwrap_or(defaults.keyboard2),
+            hold_to_pause: saved.hold_to_pause.unwrap_or(defaults.hold_to_pause),
+        })
+    }
+
+    /// Get the button of the control method of the given player index on a device, which is
+    /// `None` if the player can't be controlled with the device
+    pub fn get(&self, player_idx: usize, device: InputDevice) -> Option<InputKind> {
+        match (device, player_idx) {
+            (InputDevice::Gamepad, _) => Some(self.gamepad),
+            (InputDevice::Keyboard, 0) => Some(self.keyboard1),
+            (InputDevice::Keyboard, 1) => Some(self.keyboard2),
+            (InputDevice::Keyboard, _) => None,
+        }
+    }
 }
 
 /// Binds inputs to player actions
@@ -62,3 +628,20 @@ pub struct PlayerControls {
     pub throw: InputKind,
     pub shoot: InputKind,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn missing_buttons_are_defaults_of_their_action() {
+        let saved = serde_yaml::Deserializer::from_str("keyboard1: !Keyboard Q");
+        let block = ButtonControls::deserialize_block(saved).unwrap();
+
+        let defaults = ButtonControls::default_block();
+        assert_eq!(block.keyboard1, InputKind::Keyboard(KeyCode::Q));
+        assert_eq!(block.keyboard2, defaults.keyboard2);
+        assert_eq!(block.gamepad, defaults.gamepad);
+        assert!(!block.hold_to_pause);
+    }
+}
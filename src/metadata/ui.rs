@@ -8,11 +8,138 @@ pub struct UIThemeMeta {
     pub font_families: HashMap<String, String>,
     #[serde(skip)]
     pub font_handles: HashMap<String, Handle<EguiFont>>,
+    /// Other resolutions of the fonts in `font_families`, such as bigger pixel fonts to use when
+    /// the UI is scaled up on big screens
+    #[serde(default)]
+    pub font_resolutions: HashMap<String, Vec<FontResolutionMeta>>,
     pub font_styles: HashMap<FontStyle, FontMeta>,
     // pub font_sizes: HashMap<FontSize, f32>,
     pub hud: HudThemeMeta,
     pub panel: PanelThemeMeta,
     pub button_styles: HashMap<ButtonStyle, ButtonThemeMeta>,
+    /// Changes to the theme that only apply in specific game states, such as a darker panel for
+    /// the pause menu
+    #[serde(default)]
+    pub states: HashMap<UIThemeState, UIThemeOverrideMeta>,
+    /// The theme of each state in `states`, with the overrides applied
+    #[serde(skip)]
+    #[has_load_progress(none)]
+    pub state_themes: HashMap<UIThemeState, UIThemeMeta>,
+}
+
+/// The states of the game that the [`UIThemeMeta`] can be changed in
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum UIThemeState {
+    MainMenu,
+    InGame,
+    /// The pause menu, which is shown over the game
+    Paused,
+}
+
+impl UIThemeMeta {
+    /// Get the handle of the font to use for the font family when a point of the UI is
+    /// `pixels_per_point` pixels on the screen
+    pub fn font_handle(&self, family: &str, pixels_per_point: f32) -> Option<&Handle<EguiFont>> {
+        let handle = self.font_handles.get(family)?;
+        let resolutions = self.font_resolutions.get(family).into_iter().flatten();
+
+        Some(select_resolution(
+            handle,
+            resolutions.map(|resolution| (resolution.scale, &resolution.handle)),
+            pixels_per_point,
+        ))
+    }
+
+    /// Get the theme to use in the given game state
+    pub fn for_state(&self, state: UIThemeState) -> &UIThemeMeta {
+        self.state_themes.get(&state).unwrap_or(self)
+    }
+
+    /// Apply the overrides in `states` to create the `state_themes`.
+    ///
+    /// Must be called after the border images of the overrides have been loaded, because the
+    /// images are copied into the state themes.
+    pub fn build_state_themes(&mut self) {
+        let mut base = self.clone();
+        base.states.clear();
+        base.state_themes.clear();
+
+        self.state_themes = self
+            .states
+            .iter()
+            .map(|(state, theme_override)| {
+                let mut theme = base.clone();
+                if let Some(hud) = &theme_override.hud {
+                    theme.hud = hud.clone();
+                }
+                if let Some(panel) = &theme_override.panel {
+                    theme.panel = panel.clone();
+                }
+                theme.font_styles.extend(theme_override.font_styles.clone());
+                theme
+                    .button_styles
+                    .extend(theme_override.button_styles.clone());
+
+                (*state, theme)
+            })
+            .collect();
+    }
+}
+
+/// Parts of the [`UIThemeMeta`] to replace in a specific game state.
+///
+/// The font and button styles are merged with the styles of the base theme, so only the styles
+/// that change need to be specified.
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct UIThemeOverrideMeta {
+    pub font_styles: HashMap<FontStyle, FontMeta>,
+    pub hud: Option<HudThemeMeta>,
+    pub panel: Option<PanelThemeMeta>,
+    pub button_styles: HashMap<ButtonStyle, ButtonThemeMeta>,
+}
+
+impl UIThemeOverrideMeta {
+    /// Get all of the border images used by the override
+    pub fn border_images(&self) -> Vec<&BorderImageMeta> {
+        let mut borders = Vec::new();
+        if let Some(hud) = &self.hud {
+            borders.push(&hud.portrait_frame);
+            borders.push(&hud.lifebar.background_image);
+            borders.push(&hud.lifebar.progress_image);
+        }
+        if let Some(panel) = &self.panel {
+            borders.push(&panel.border);
+        }
+        for button in self.button_styles.values() {
+            borders.push(&button.borders.default);
+            borders.extend(button.borders.focused.iter());
+            borders.extend(button.borders.clicked.iter());
+        }
+
+        borders
+    }
+
+    /// Mutable version of [`border_images`][Self::border_images], used to load the images
+    pub fn border_images_mut(&mut self) -> Vec<&mut BorderImageMeta> {
+        let mut borders = Vec::new();
+        if let Some(hud) = &mut self.hud {
+            borders.push(&mut hud.portrait_frame);
+            borders.push(&mut hud.lifebar.background_image);
+            borders.push(&mut hud.lifebar.progress_image);
+        }
+        if let Some(panel) = &mut self.panel {
+            borders.push(&mut panel.border);
+        }
+        for button in self.button_styles.values_mut() {
+            borders.push(&mut button.borders.default);
+            borders.extend(button.borders.focused.iter_mut());
+            borders.extend(button.borders.clicked.iter_mut());
+        }
+
+        borders
+    }
 }
 
 #[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -45,6 +172,12 @@ impl TryFrom<String> for FontStyle {
 #[serde(from = "String")]
 pub struct FontFamily(Arc<str>);
 
+impl FontFamily {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl From<String> for FontFamily {
     fn from(s: String) -> Self {
         Self(Arc::from(s))
@@ -106,6 +239,45 @@ pub struct HudThemeMeta {
     pub portrait_frame: BorderImageMeta,
     pub font: FontMeta,
     pub lifebar: ProgressBarMeta,
+    /// The numbers that float up from the fighters that are hit, which aren't shown if it's unset
+    #[serde(default)]
+    pub damage_numbers: Option<DamageNumbersThemeMeta>,
+}
+
+/// The style of the numbers that float up from the fighters that are hit
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct DamageNumbersThemeMeta {
+    /// The font of the damage of the hits, which is the `critical` font if it's unset
+    #[serde(default)]
+    pub font: Option<FontMeta>,
+    /// The font of the damage of the critical hits
+    pub critical: FontMeta,
+    /// The font of the health given back by the attacks that heal, instead of hurting, which is the
+    /// font of the hits if it's unset
+    #[serde(default)]
+    pub heal: Option<FontMeta>,
+}
+
+impl DamageNumbersThemeMeta {
+    /// The font of a damage number of the given kind
+    pub fn font(&self, kind: DamageNumberKind) -> &FontMeta {
+        let hit = self.font.as_ref().unwrap_or(&self.critical);
+        match kind {
+            DamageNumberKind::Hit => hit,
+            DamageNumberKind::Critical => &self.critical,
+            DamageNumberKind::Heal => self.heal.as_ref().unwrap_or(hit),
+        }
+    }
+}
+
+/// The kinds of damage numbers, which are each shown in a font of their own
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageNumberKind {
+    Hit,
+    Critical,
+    Heal,
 }
 
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
@@ -136,6 +308,23 @@ pub struct BorderImageMeta {
     pub border_size: MarginMeta,
     #[serde(default = "f32_one")]
     pub scale: f32,
+    /// Other resolutions of the image, with the same layout as `image`, that are used instead of it
+    /// when the UI is scaled
+    #[serde(default)]
+    pub resolutions: Vec<BorderImageResolutionMeta>,
+
+    #[serde(skip)]
+    pub handle: Handle<Image>,
+    #[serde(skip)]
+    pub egui_texture: egui::TextureId,
+}
+
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BorderImageResolutionMeta {
+    /// How many times bigger the image is than the base image of the border
+    pub scale: f32,
+    pub image: String,
 
     #[serde(skip)]
     pub handle: Handle<Image>,
@@ -143,6 +332,44 @@ pub struct BorderImageMeta {
     pub egui_texture: egui::TextureId,
 }
 
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FontResolutionMeta {
+    /// The UI scale that the font is made for, compared to the base font of the family
+    pub scale: f32,
+    pub font: String,
+
+    #[serde(skip)]
+    pub handle: Handle<EguiFont>,
+}
+
+/// Select the resolution of a UI asset to use when a point of the UI is `pixels_per_point` pixels
+/// on the screen, where the `base` resolution has a scale of 1.
+///
+/// The smallest resolution that is at least as big as the UI is on the screen is used, so that the
+/// asset is only ever scaled down, unless all of the resolutions are too small.
+pub fn select_resolution<T: Copy>(
+    base: T,
+    resolutions: impl Iterator<Item = (f32, T)>,
+    pixels_per_point: f32,
+) -> T {
+    let base = (1.0, base);
+    let mut smallest_fitting = (base.0 >= pixels_per_point).then_some(base);
+    let mut largest = base;
+
+    for (scale, resolution) in resolutions {
+        let fits = scale >= pixels_per_point;
+        if fits && smallest_fitting.map_or(true, |(smallest, _)| scale < smallest) {
+            smallest_fitting = Some((scale, resolution));
+        }
+        if scale > largest.0 {
+            largest = (scale, resolution);
+        }
+    }
+
+    smallest_fitting.unwrap_or(largest).1
+}
+
 fn f32_one() -> f32 {
     1.0
 }
@@ -200,3 +427,36 @@ impl From<MarginMeta> for bevy_egui::egui::style::Margin {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolutions_are_only_scaled_down() {
+        let resolutions = [(0.5, "small"), (4.0, "huge"), (2.0, "big")];
+        let select = |pixels_per_point| {
+            select_resolution("base", resolutions.iter().copied(), pixels_per_point)
+        };
+
+        assert_eq!(select(0.4), "small");
+        assert_eq!(select(0.75), "base");
+        assert_eq!(select(1.0), "base");
+        assert_eq!(select(1.5), "big");
+        assert_eq!(select(3.0), "huge");
+        // When no resolution is big enough, the biggest one is scaled up
+        assert_eq!(select(6.0), "huge");
+        assert_eq!(select_resolution("base", std::iter::empty(), 3.0), "base");
+    }
+
+    #[test]
+    fn damage_numbers_fall_back_to_critical_font() {
+        // Themes from before the other fonts were added only have the critical one
+        let theme: DamageNumbersThemeMeta =
+            serde_yaml::from_str("critical: { family: ark, size: 19 }").unwrap();
+
+        for kind in [DamageNumberKind::Hit, DamageNumberKind::Heal] {
+            assert_eq!(theme.font(kind).size, 19.0);
+        }
+    }
+}
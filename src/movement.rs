@@ -1,14 +1,23 @@
+use std::ops::RangeInclusive;
+
 use bevy::{
     math::{Quat, Vec2},
     prelude::*,
-    time::Time,
 };
 use iyes_loopless::prelude::*;
 
 use crate::{
     consts::{self, LEFT_BOUNDARY_MAX_DISTANCE},
     enemy::SpawnLocationX,
-    metadata::{GameMeta, LevelMeta},
+    fighter_state::{Airborne, Dodging, Dying, Grabbed, Grappled, Thrown},
+    flying::Flying,
+    hit_stop::{HitStop, HitStopped},
+    metadata::{FighterMeta, GameMeta, LevelMeta},
+    player::PlayerIndex,
+    spatial_hash::SpatialHash,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    versus::{self, VersusMatch},
+    wave::Waves,
     GameState, Player,
 };
 
@@ -29,21 +38,34 @@ impl Plugin for MovementPlugin {
             .register_type::<AngularVelocity>()
             .register_type::<Force>()
             .register_type::<Torque>()
+            .register_type::<Knockback>()
             // Init resources
             .init_resource::<LeftMovementBoundary>()
             // Add systems that modify velocity based on forces
-            .add_system_set_to_stage(
-                CoreStage::PostUpdate,
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
                 ConditionSet::new()
                     .label(ForceSystems)
                     .run_in_state(GameState::InGame)
                     .with_system(force_system)
                     .with_system(torque_system)
+                    .with_system(knockback_system)
                     .into(),
             )
+            // Keep the fighters from walking through each other once their velocities are known
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
+                soft_collision_system
+                    .run_in_state(GameState::InGame)
+                    .after(ForceSystems)
+                    .before(VelocitySystems),
+            )
             // Add systems that modify translation and rotation based on velocity
-            .add_system_set_to_stage(
-                CoreStage::PostUpdate,
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
                 ConditionSet::new()
                     .label(VelocitySystems)
                     .after(ForceSystems)
@@ -71,9 +93,18 @@ impl Plugin for MovementPlugin {
 pub struct LinearVelocity(pub Vec2);
 
 /// System that updates translations based on entity velocities.
-pub fn velocity_system(mut query: Query<(&mut Transform, &LinearVelocity)>, time: Res<Time>) {
+///
+/// The entities in a [hit-stop](crate::hit_stop) keep their velocities, but don't move until it's
+/// over. The same goes for their rotations, forces, and knockbacks.
+pub fn velocity_system(
+    mut query: Query<(&mut Transform, &LinearVelocity), Without<HitStopped>>,
+    hit_stop: Res<HitStop>,
+) {
+    if hit_stop.is_active() {
+        return;
+    }
     for (mut transform, dir) in &mut query.iter_mut() {
-        transform.translation += dir.0.extend(0.) * time.delta_seconds();
+        transform.translation += dir.0.extend(0.) * GAMEPLAY_STEP.as_secs_f32();
     }
 }
 
@@ -93,11 +124,14 @@ impl AngularVelocity {
 
 /// System that applies rotations based on entity torques.
 pub fn angular_velocity_system(
-    mut query: Query<(&mut Transform, &AngularVelocity)>,
-    time: Res<Time>,
+    mut query: Query<(&mut Transform, &AngularVelocity), Without<HitStopped>>,
+    hit_stop: Res<HitStop>,
 ) {
+    if hit_stop.is_active() {
+        return;
+    }
     for (mut transform, torque) in &mut query.iter_mut() {
-        transform.rotation *= Quat::from_rotation_z(**torque * time.delta_seconds());
+        transform.rotation *= Quat::from_rotation_z(**torque * GAMEPLAY_STEP.as_secs_f32());
     }
 }
 
@@ -107,9 +141,15 @@ pub fn angular_velocity_system(
 pub struct Force(pub Vec2);
 
 // Applies forces to linear velocities
-pub fn force_system(mut query: Query<(&mut LinearVelocity, &Force)>, time: Res<Time>) {
+pub fn force_system(
+    mut query: Query<(&mut LinearVelocity, &Force), Without<HitStopped>>,
+    hit_stop: Res<HitStop>,
+) {
+    if hit_stop.is_active() {
+        return;
+    }
     for (mut velocity, force) in &mut query.iter_mut() {
-        **velocity += **force * time.delta_seconds();
+        **velocity += **force * GAMEPLAY_STEP.as_secs_f32();
     }
 }
 
@@ -119,9 +159,237 @@ pub fn force_system(mut query: Query<(&mut LinearVelocity, &Force)>, time: Res<T
 pub struct Torque(pub f32);
 
 // Applies torques to angular velocities
-pub fn torque_system(mut query: Query<(&mut AngularVelocity, &Torque)>, time: Res<Time>) {
+pub fn torque_system(
+    mut query: Query<(&mut AngularVelocity, &Torque), Without<HitStopped>>,
+    hit_stop: Res<HitStop>,
+) {
+    if hit_stop.is_active() {
+        return;
+    }
     for (mut velocity, torque) in &mut query.iter_mut() {
-        **velocity += **torque * time.delta_seconds();
+        **velocity += **torque * GAMEPLAY_STEP.as_secs_f32();
+    }
+}
+
+/// The velocity an entity has been knocked back with, which is added to the velocity the entity moves
+/// with by itself, and slows down with friction until it stops.
+#[derive(Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Knockback {
+    pub velocity: Vec2,
+}
+
+impl Knockback {
+    /// The knockback of an entity of the given `mass` hit with `impulse`, so that heavy entities
+    /// barely budge while light ones fly
+    pub fn from_impulse(impulse: Vec2, mass: f32) -> Self {
+        Self {
+            velocity: impulse / mass.max(f32::EPSILON),
+        }
+    }
+
+    /// Slow the knockback down with `friction` over `delta` seconds, without reversing it
+    fn apply_friction(&mut self, friction: f32, delta: f32) {
+        let speed = self.velocity.length();
+        let new_speed = (speed - friction * delta).max(0.0);
+        self.velocity = self.velocity.clamp_length_max(new_speed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.velocity == Vec2::ZERO
+    }
+}
+
+/// Adds knockbacks to linear velocities, and removes them once they have stopped
+pub fn knockback_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut LinearVelocity, &mut Knockback), Without<HitStopped>>,
+    hit_stop: Res<HitStop>,
+) {
+    if hit_stop.is_active() {
+        return;
+    }
+    for (entity, mut velocity, mut knockback) in &mut query {
+        **velocity += knockback.velocity;
+
+        knockback.apply_friction(consts::KNOCKBACK_FRICTION, GAMEPLAY_STEP.as_secs_f32());
+        if knockback.is_stopped() {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+/// The arc that a thrown fighter flies along, from where it's thrown from back down to the ground
+#[derive(Clone, Copy, Default, Debug, Reflect)]
+pub struct ThrowTrajectory {
+    /// The velocity that the fighter is thrown with
+    pub velocity: Vec2,
+    /// How fast the fighter falls back down
+    pub gravity: f32,
+    /// How high above the ground the fighter is thrown from
+    pub height: f32,
+}
+
+impl ThrowTrajectory {
+    /// The trajectory of a fighter thrown with `speed` from `height` above the ground, that rises
+    /// `arc_height` higher before it falls and lands on the ground after `duration` seconds
+    pub fn new(speed: f32, height: f32, arc_height: f32, duration: f32) -> Self {
+        let height = height.max(0.0);
+        let arc_height = arc_height.max(0.0);
+        let duration = duration.max(f32::EPSILON);
+
+        // Landing on the ground after `duration` is a quadratic equation in the square root of the
+        // gravity, once the rise of the arc is set by the speed that the fighter is thrown up with
+        let gravity_sqrt =
+            ((2.0 * arc_height).sqrt() + (2.0 * (arc_height + height)).sqrt()) / duration;
+        Self {
+            velocity: Vec2::new(speed, gravity_sqrt * (2.0 * arc_height).sqrt()),
+            gravity: gravity_sqrt * gravity_sqrt,
+            height,
+        }
+    }
+
+    /// The velocity of the fighter `elapsed` seconds after it's thrown
+    pub fn velocity_at(&self, elapsed: f32) -> Vec2 {
+        Vec2::new(self.velocity.x, self.velocity.y - self.gravity * elapsed)
+    }
+
+    /// How high above the ground the fighter is `elapsed` seconds after it's thrown
+    pub fn height_at(&self, elapsed: f32) -> f32 {
+        let height =
+            self.height + self.velocity.y * elapsed - self.gravity * elapsed * elapsed / 2.0;
+        height.max(0.0)
+    }
+}
+
+/// How high above the ground a fighter that has been launched into the air is, which is its virtual
+/// Z. The fighter is drawn that much higher up, but it's still sorted and hit on the ground under
+/// it.
+#[derive(Clone, Copy, Default, Debug, Reflect)]
+pub struct VirtualZ {
+    pub height: f32,
+    /// How fast the fighter rises, or falls when it's negative
+    pub vertical_speed: f32,
+    /// Whether the fighter has already bounced off the ground once
+    pub bounced: bool,
+}
+
+impl VirtualZ {
+    /// The lowest speed that a fighter lands with that still bounces it back up
+    const MIN_BOUNCE_SPEED: f32 = 60.0;
+
+    /// Pop the fighter up so that it rises `height` higher than it is before it falls again
+    pub fn launch(&mut self, height: f32) {
+        self.vertical_speed = (2.0 * consts::JUGGLE_GRAVITY * height.max(0.0)).sqrt();
+        self.bounced = false;
+    }
+
+    /// Rise or fall with gravity for `delta` seconds, and return whether the fighter has landed.
+    /// The first time that the fighter hits the ground hard enough it bounces back up a little.
+    pub fn fall(&mut self, delta: f32) -> bool {
+        self.vertical_speed -= consts::JUGGLE_GRAVITY * delta;
+        self.height += self.vertical_speed * delta;
+        if self.height > 0.0 {
+            return false;
+        }
+
+        self.height = 0.0;
+        if !self.bounced && -self.vertical_speed >= Self::MIN_BOUNCE_SPEED {
+            self.bounced = true;
+            self.vertical_speed = -self.vertical_speed * consts::LANDING_BOUNCE;
+            return false;
+        }
+        self.vertical_speed = 0.0;
+        true
+    }
+}
+
+/// The fighters that the other fighters can't walk through, which are all of them except for the
+/// ones that are thrown around, juggled or flying in the air, held by another fighter, dodging
+/// through the others, or knocked out
+type SolidFighter = (
+    With<Handle<FighterMeta>>,
+    Without<Thrown>,
+    Without<Airborne>,
+    Without<Flying>,
+    Without<Grabbed>,
+    Without<Grappled>,
+    Without<Dodging>,
+    Without<Dying>,
+);
+
+/// Pushes overlapping fighters apart: allies gently, so that they can still crowd around an enemy,
+/// and enemies firmly, so that they block each other's way.
+pub fn soft_collision_system(
+    mut fighters: Query<
+        (
+            Entity,
+            &Transform,
+            &mut LinearVelocity,
+            Option<&PlayerIndex>,
+        ),
+        SolidFighter,
+    >,
+    others: Query<Option<&PlayerIndex>, SolidFighter>,
+    spatial_hash: Res<SpatialHash>,
+    versus: Option<Res<VersusMatch>>,
+) {
+    // The enemies are all on the same side, while in versus each team of players is on its own
+    let side = |player_index: Option<&PlayerIndex>| match (player_index, &versus) {
+        (Some(player_index), Some(_)) => Some(versus::team(player_index.0)),
+        (Some(_), None) => Some(0),
+        (None, _) => None,
+    };
+
+    for (entity, transform, mut velocity, player_index) in &mut fighters {
+        let position = transform.translation.truncate();
+        let fighter_side = side(player_index);
+
+        for (other, other_position) in
+            spatial_hash.query(position, consts::FIGHTER_COLLISION_RADIUS * 2.0)
+        {
+            if other == entity {
+                continue;
+            }
+            let Ok(other_player_index) = others.get(other) else {
+                continue;
+            };
+
+            // Fighters standing on the exact same spot are pushed apart in opposite directions
+            let fallback_direction = if entity < other { -Vec2::X } else { Vec2::X };
+            let blocking = side(other_player_index) != fighter_side;
+            **velocity = separated_velocity(
+                **velocity,
+                other_position - position,
+                fallback_direction,
+                blocking,
+            );
+        }
+    }
+}
+
+/// The velocity of a fighter that has another fighter `offset` away from it, pushing it away from the
+/// other fighter more the more they overlap. When `blocking`, the fighter also can't walk any further
+/// into the other fighter, but can still walk around it.
+fn separated_velocity(
+    velocity: Vec2,
+    offset: Vec2,
+    fallback_direction: Vec2,
+    blocking: bool,
+) -> Vec2 {
+    let min_distance = consts::FIGHTER_COLLISION_RADIUS * 2.0;
+    let distance = offset.length();
+    if distance >= min_distance {
+        return velocity;
+    }
+
+    let away = (-offset).try_normalize().unwrap_or(fallback_direction);
+    let overlap = 1.0 - distance / min_distance;
+    if blocking {
+        let towards = velocity.dot(-away).max(0.0);
+        velocity + away * (towards + overlap * consts::ENEMY_SEPARATION_SPEED)
+    } else {
+        velocity + away * overlap * consts::ALLY_SEPARATION_SPEED
     }
 }
 
@@ -159,22 +427,22 @@ fn constrain_player_movement(
     level_meta: Res<LevelMeta>,
     game_meta: Res<GameMeta>,
     left_movement_boundary: Res<LeftMovementBoundary>,
-    mut players: Query<(&Transform, &mut LinearVelocity), With<Player>>,
-    time: Res<Time>,
+    waves: Res<Waves>,
+    mut players: Query<(&Transform, &mut LinearVelocity, Option<&Airborne>), With<Player>>,
 ) {
-    let dt = time.delta_seconds();
+    let dt = GAMEPLAY_STEP.as_secs_f32();
 
     // Collect player positions and velocities
     let mut player_velocities = players
         .iter_mut()
-        .map(|(transform, vel)| (transform.translation, vel))
+        .map(|(transform, vel, airborne)| (transform.translation, vel, airborne.is_some()))
         .collect::<Vec<_>>();
 
     // Identify the current stop point
     let current_stop_point = level_meta.stop_points.iter().find(|point_x| {
         player_velocities
             .iter()
-            .any(|(location, dir)| location.x < **point_x && **point_x <= location.x + dir.x)
+            .any(|(location, dir, _)| location.x < **point_x && **point_x <= location.x + dir.x)
     });
 
     // If there is a current stop point
@@ -186,15 +454,26 @@ fn constrain_player_movement(
         // Prevent movement beyond the stop point if there are enemies not yet defeated behind the
         // stop point.
         if any_enemy_behind_stop_point {
-            for (location, velocity) in player_velocities.iter_mut() {
-                // Can be simplified, but it's harder to understand.
-                if location.x + velocity.x * dt > *current_stop_point {
-                    velocity.x = 0.;
-                }
+            for (location, velocity, _) in player_velocities.iter_mut() {
+                velocity.x = velocity_within_bounds(
+                    location.x,
+                    velocity.x,
+                    f32::NEG_INFINITY..=*current_stop_point,
+                    dt,
+                );
             }
         }
     }
 
+    // The players are held back while they fight a wave, so that the camera doesn't follow them
+    if let Some(camera_x) = waves.camera_lock() {
+        let wave_edge = camera_x + game_meta.camera_move_right_boundary;
+        for (location, velocity, _) in player_velocities.iter_mut() {
+            velocity.x =
+                velocity_within_bounds(location.x, velocity.x, f32::NEG_INFINITY..=wave_edge, dt);
+        }
+    }
+
     // Then, we perform the absolute clamping (screen top/left/bottom), and we collect the data
     // required for the relative clamping.
 
@@ -203,18 +482,23 @@ fn constrain_player_movement(
     #[allow(clippy::needless_collect)] // False alarm
     let velocities = player_velocities
         .into_iter()
-        .map(|(location, mut velocity)| {
+        .map(|(location, mut velocity, airborne)| {
+            velocity.x = velocity_within_bounds(
+                location.x,
+                velocity.x,
+                left_movement_boundary.0..=f32::INFINITY,
+                dt,
+            );
             let new_x = location.x + velocity.x * dt;
 
-            if new_x < left_movement_boundary.0 {
-                velocity.x = 0.;
-            }
-
-            //Restrict player to the ground
-            let new_y = location.y + velocity.y * dt + consts::GROUND_OFFSET;
-
-            if new_y >= consts::MAX_Y || new_y <= consts::MIN_Y {
-                velocity.y = 0.;
+            //Restrict player to the ground, which the players in the air only rise and fall above
+            if !airborne {
+                velocity.y = velocity_within_bounds(
+                    location.y + consts::GROUND_OFFSET,
+                    velocity.y,
+                    consts::MIN_Y..=consts::MAX_Y,
+                    dt,
+                );
             }
 
             let new_velocity = (velocity, new_x);
@@ -236,3 +520,132 @@ fn constrain_player_movement(
             }
         });
 }
+
+/// The velocity along an axis that keeps an entity at `position` within `bounds` over the next
+/// `dt` seconds. Moves that would cross a bound stop right at it instead of short of it, so that
+/// fast moves like dodges end against the edges of the level.
+fn velocity_within_bounds(
+    position: f32,
+    velocity: f32,
+    bounds: RangeInclusive<f32>,
+    dt: f32,
+) -> f32 {
+    let new_position = position + velocity * dt;
+    if new_position < *bounds.start() {
+        ((bounds.start() - position) / dt).min(0.0)
+    } else if new_position > *bounds.end() {
+        ((bounds.end() - position) / dt).max(0.0)
+    } else {
+        velocity
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn knockback_depends_on_mass_and_slows_down() {
+        let light = Knockback::from_impulse(Vec2::new(80.0, 0.0), 0.5);
+        let mut heavy = Knockback::from_impulse(Vec2::new(80.0, 0.0), 4.0);
+        assert_eq!(light.velocity, Vec2::new(160.0, 0.0));
+        assert_eq!(heavy.velocity, Vec2::new(20.0, 0.0));
+
+        heavy.apply_friction(100.0, 0.1);
+        assert!((heavy.velocity.x - 10.0).abs() < 1e-4);
+        heavy.apply_friction(100.0, 0.5);
+        assert!(heavy.is_stopped());
+    }
+
+    #[test]
+    fn thrown_fighters_land_on_time() {
+        let trajectory = ThrowTrajectory::new(400.0, 50.0, 16.0, 0.5);
+        assert_eq!(trajectory.height_at(0.0), 50.0);
+        assert!(trajectory.velocity.y > 0.0);
+        assert!(trajectory.height_at(0.5).abs() < 1e-3);
+        assert_eq!(trajectory.velocity_at(0.25).x, 400.0);
+
+        // The arc peaks `arc_height` above where the fighter is thrown from
+        let peak = trajectory.velocity.y / trajectory.gravity;
+        assert!((trajectory.height_at(peak) - 66.0).abs() < 1e-3);
+
+        // Fighters knocked along the ground stay on it
+        let trajectory = ThrowTrajectory::new(240.0, 0.0, 0.0, 0.5);
+        assert_eq!(trajectory.velocity_at(0.3), Vec2::new(240.0, 0.0));
+    }
+
+    #[test]
+    fn launched_fighters_bounce_once_and_land() {
+        let dt = 1.0 / 60.0;
+        let mut z = VirtualZ::default();
+        z.launch(50.0);
+
+        let mut peak: f32 = 0.0;
+        let mut steps = 0;
+        while !z.fall(dt) {
+            peak = peak.max(z.height);
+            steps += 1;
+            assert!(steps < 600, "The fighter never landed");
+        }
+        assert!((peak - 50.0).abs() < 3.0);
+        assert!(z.bounced);
+        assert_eq!(z.height, 0.0);
+
+        // Fighters that barely fall land right away
+        let mut z = VirtualZ::default();
+        assert!(z.fall(dt));
+        assert!(!z.bounced);
+    }
+
+    #[test]
+    fn dodges_stop_at_the_level_bounds() {
+        let dt = 0.5;
+        let dodge_speed = 300.0;
+
+        // A dodge that would cross a bound stops right at it
+        let velocity = velocity_within_bounds(10.0, -dodge_speed, 0.0..=400.0, dt);
+        assert_eq!(velocity, -20.0);
+        let velocity = velocity_within_bounds(390.0, dodge_speed, 0.0..=400.0, dt);
+        assert_eq!(velocity, 20.0);
+        assert_eq!(
+            velocity_within_bounds(200.0, dodge_speed, 0.0..=400.0, dt),
+            dodge_speed
+        );
+
+        // Fighters that are already past a bound can't go any further, but aren't pulled back
+        assert_eq!(
+            velocity_within_bounds(-5.0, -dodge_speed, 0.0..=400.0, dt),
+            0.0
+        );
+        assert_eq!(velocity_within_bounds(-5.0, 4.0, 0.0..=400.0, dt), 0.0);
+    }
+
+    #[test]
+    fn overlapping_fighters_are_pushed_apart() {
+        let min_distance = consts::FIGHTER_COLLISION_RADIUS * 2.0;
+        let walking = Vec2::new(50.0, 10.0);
+
+        // Fighters that don't overlap don't affect each other
+        let far = Vec2::new(min_distance, 0.0);
+        assert_eq!(separated_velocity(walking, far, Vec2::X, true), walking);
+
+        // Allies are only nudged apart
+        let half_overlapping = Vec2::new(min_distance / 2.0, 0.0);
+        let velocity = separated_velocity(walking, half_overlapping, Vec2::X, false);
+        assert_eq!(
+            velocity,
+            walking - Vec2::new(consts::ALLY_SEPARATION_SPEED / 2.0, 0.0)
+        );
+
+        // Enemies can't walk into each other, but can still walk around each other
+        let velocity = separated_velocity(walking, half_overlapping, Vec2::X, true);
+        assert_eq!(
+            velocity,
+            Vec2::new(-consts::ENEMY_SEPARATION_SPEED / 2.0, 10.0)
+        );
+
+        // Fighters on the same spot are pushed in the fallback direction
+        let velocity = separated_velocity(Vec2::ZERO, Vec2::ZERO, -Vec2::X, false);
+        assert_eq!(velocity, Vec2::new(-consts::ALLY_SEPARATION_SPEED, 0.0));
+    }
+}
@@ -0,0 +1,153 @@
+//! Nameplates, which introduce the enemies the first time they appear.
+//!
+//! The first time an enemy with a [`FighterNameplateMeta`] comes into view, its name and threat are
+//! shown for a moment, so that the players learn the enemies they will be fighting. The enemies
+//! that have been introduced are kept in the [`Storage`], so a nameplate is only shown once for
+//! every save of the game, and not again on the next playthrough. Nameplates of enemies that appear
+//! together are shown one after the other.
+//!
+//! Nameplates are only shown if the game has [`NameplatesMeta`](crate::metadata::NameplatesMeta),
+//! and not in the attract demo.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    activity::Dormant,
+    camera::camera_half_width,
+    enemy::{Boss, Enemy},
+    menu_stack::MenuLayer,
+    metadata::{FighterMeta, FighterNameplateMeta, GameMeta},
+    platform::Storage,
+    ui::attract_mode,
+    GameState,
+};
+
+pub struct NameplatePlugin;
+
+impl Plugin for NameplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Nameplates>()
+            .add_system(
+                introduce_enemies
+                    .run_in_state(GameState::InGame)
+                    .run_if_not(attract_mode::demo_playing),
+            )
+            .add_system(
+                update_nameplates
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .after(introduce_enemies),
+            )
+            .add_exit_system(GameState::InGame, clear_nameplates);
+    }
+}
+
+/// The enemies that have been introduced by their nameplates, by their names
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct IntroducedEnemies(pub BTreeSet<String>);
+
+impl IntroducedEnemies {
+    /// The key used to store the introduced enemies in the [`Storage`].
+    pub const STORAGE_KEY: &'static str = "introduced-enemies";
+}
+
+/// A nameplate waiting to be shown
+#[derive(Clone, Debug)]
+pub struct Nameplate {
+    pub meta: FighterNameplateMeta,
+    pub boss: bool,
+}
+
+/// The nameplates that are shown one after the other, starting with the one being shown
+#[derive(Resource, Default)]
+pub struct Nameplates {
+    queue: VecDeque<Nameplate>,
+    /// Counts down the real time that the current nameplate has left
+    timer: Timer,
+}
+
+impl Nameplates {
+    /// The nameplate being shown
+    pub fn current(&self) -> Option<&Nameplate> {
+        self.queue.front()
+    }
+}
+
+/// Queue the nameplates of the enemies that come into view for the first time
+fn introduce_enemies(
+    mut nameplates: ResMut<Nameplates>,
+    enemies: Query<
+        (&Transform, &Handle<FighterMeta>, Option<&Boss>),
+        (With<Enemy>, Without<Dormant>),
+    >,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    fighters: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+    windows: Res<Windows>,
+    mut storage: ResMut<Storage>,
+    mut introduced: Local<Option<IntroducedEnemies>>,
+) {
+    let Some(meta) = &game.nameplates else {
+        return;
+    };
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let half_view_width = camera_half_width(&game, windows.primary());
+
+    let introduced = introduced.get_or_insert_with(|| {
+        storage
+            .get::<IntroducedEnemies>(IntroducedEnemies::STORAGE_KEY)
+            .unwrap_or_default()
+    });
+    let mut changed = false;
+    for (transform, fighter_handle, boss) in &enemies {
+        if (transform.translation.x - camera_transform.translation.x).abs() > half_view_width {
+            continue;
+        }
+        let Some(nameplate) = fighters
+            .get(fighter_handle)
+            .and_then(|fighter| fighter.nameplate.as_ref())
+        else {
+            continue;
+        };
+        if !introduced.0.insert(nameplate.name.clone()) {
+            continue;
+        }
+
+        if nameplates.queue.is_empty() {
+            nameplates.timer = Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once);
+        }
+        nameplates.queue.push_back(Nameplate {
+            meta: nameplate.clone(),
+            boss: boss.is_some(),
+        });
+        changed = true;
+    }
+
+    if changed {
+        storage.set(IntroducedEnemies::STORAGE_KEY, &*introduced);
+        storage.save();
+    }
+}
+
+/// Move on to the next nameplate once the current one has been shown for long enough
+fn update_nameplates(mut nameplates: ResMut<Nameplates>, game: Res<GameMeta>, time: Res<Time>) {
+    if nameplates.queue.is_empty() || !nameplates.timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    nameplates.queue.pop_front();
+    if let Some(meta) = &game.nameplates {
+        nameplates.timer = Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once);
+    }
+}
+
+fn clear_nameplates(mut nameplates: ResMut<Nameplates>) {
+    nameplates.queue.clear();
+}
@@ -0,0 +1,277 @@
+//! Narration of the menus, which speaks them out loud for players that can't see them.
+//!
+//! While [narration](NarrationSettings) is enabled, the menu widgets are spoken as they get focused
+//! and as their values change, using the localized strings that the widgets show, or their
+//! [descriptions](crate::ui::widgets::bordered_button::BorderedButton::description). Changes like
+//! the game pausing are announced with [`Narrate`] events. All of the texts of a frame are spoken
+//! together, and stop the texts before them from being spoken.
+//!
+//! The texts are spoken by the text to speech of the platform: the `say` command on macOS,
+//! PowerShell's speech synthesizer on Windows, `spd-say` from speech-dispatcher elsewhere, and the
+//! speech synthesis API of the browser in the web build.
+
+use bevy::prelude::*;
+use bevy_egui::{egui::output::OutputEvent, EguiContext, EguiSystem};
+use bevy_fluent::Localization;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    localization::LocalizationExt,
+    menu_stack::MenuLayer,
+    metadata::{NarrationSettings, Settings},
+    GameState,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use native as backend;
+
+#[cfg(target_arch = "wasm32")]
+use wasm as backend;
+
+pub struct NarrationPlugin;
+
+impl Plugin for NarrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NarrationSettings>()
+            .init_resource::<Narrator>()
+            .add_event::<Narrate>()
+            .add_system(narrate_state_changes)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_narration_settings.run_if_resource_exists::<Settings>(),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                // The output of egui is cleared when it's processed
+                narrate_widgets.before(EguiSystem::ProcessOutput),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                speak_narration
+                    .after(apply_narration_settings)
+                    .after(narrate_widgets),
+            );
+    }
+}
+
+/// Event to speak a text, if narration is enabled
+pub struct Narrate(pub String);
+
+/// The text to speech that the narration is spoken with
+#[derive(Resource, Default)]
+struct Narrator {
+    speech: backend::Speech,
+}
+
+/// Update the narration settings when the settings have changed
+fn apply_narration_settings(
+    settings: Res<Settings>,
+    mut narration_settings: ResMut<NarrationSettings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let narration = settings.narration;
+
+    if *narration_settings != narration {
+        debug!(?narration, "Setting narration settings");
+        *narration_settings = narration;
+    }
+}
+
+/// Announce the menus that the game goes to
+fn narrate_state_changes(
+    state: Res<CurrentState<GameState>>,
+    menu_layer: Res<CurrentState<MenuLayer>>,
+    localization: Res<Localization>,
+    mut narrate: EventWriter<Narrate>,
+) {
+    // The menus opened over the main menu are announced instead of it
+    let message = match (state.0, menu_layer.0) {
+        (_, MenuLayer::Paused) if menu_layer.is_changed() => "paused",
+        (_, MenuLayer::Settings) if menu_layer.is_changed() => "settings",
+        (GameState::MainMenu, MenuLayer::None) if state.is_changed() => "main-menu",
+        _ => return,
+    };
+    narrate.send(Narrate(localization.get(message)));
+}
+
+/// Narrate the widgets that have been focused, or whose values have changed
+fn narrate_widgets(
+    mut context: ResMut<EguiContext>,
+    narration_settings: Res<NarrationSettings>,
+    mut narrate: EventWriter<Narrate>,
+) {
+    if !narration_settings.enabled {
+        return;
+    }
+
+    for event in &context.ctx_mut().output().events {
+        let (OutputEvent::FocusGained(info) | OutputEvent::ValueChanged(info)) = event else {
+            continue;
+        };
+        if let Some(label) = info.label.as_ref().filter(|label| !label.is_empty()) {
+            narrate.send(Narrate(label.clone()));
+        }
+    }
+}
+
+fn speak_narration(
+    mut events: EventReader<Narrate>,
+    narration_settings: Res<NarrationSettings>,
+    mut narrator: ResMut<Narrator>,
+) {
+    narrator.speech.reap();
+
+    let texts = events
+        .iter()
+        .map(|Narrate(text)| text.as_str())
+        .collect::<Vec<_>>();
+
+    if !narration_settings.enabled {
+        // Stop speaking right away when narration is disabled
+        if narration_settings.is_changed() {
+            narrator.speech.stop();
+        }
+        return;
+    }
+    if !texts.is_empty() {
+        narrator.speech.speak(&texts.join(", "));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::process::{Child, Command, Stdio};
+
+    use bevy::prelude::*;
+
+    /// Speaks with the text to speech command of the platform. The commands are never waited on,
+    /// so that the game doesn't freeze while they start or stop speaking.
+    #[derive(Default)]
+    pub struct Speech {
+        /// The command that is speaking, if it hasn't been stopped yet
+        child: Option<Child>,
+        /// The commands that have been stopped or cancel the speech, but haven't exited yet
+        exiting: Vec<Child>,
+    }
+
+    impl Speech {
+        pub fn speak(&mut self, text: &str) {
+            self.kill();
+
+            let spawned = speak_command(text)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            match spawned {
+                Ok(child) => self.child = Some(child),
+                Err(error) => warn!(%error, "Could not start the text to speech command"),
+            }
+        }
+
+        pub fn stop(&mut self) {
+            self.kill();
+
+            // Speech dispatcher keeps speaking the texts after the command has exited
+            #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+            if let Ok(cancel) = Command::new("spd-say")
+                .arg("--cancel")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                self.exiting.push(cancel);
+            }
+        }
+
+        /// Stop the command that is speaking, without waiting for it to exit
+        fn kill(&mut self) {
+            if let Some(mut child) = self.child.take() {
+                // The command may have finished speaking already, which makes killing it fail
+                child.kill().ok();
+                self.exiting.push(child);
+            }
+        }
+
+        /// Clean up after the commands that have exited
+        pub fn reap(&mut self) {
+            self.exiting
+                .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn speak_command(text: &str) -> Command {
+        let mut command = Command::new("say");
+        command.arg("--").arg(text);
+        command
+    }
+
+    #[cfg(target_os = "windows")]
+    fn speak_command(text: &str) -> Command {
+        use std::os::windows::process::CommandExt;
+
+        /// Keeps PowerShell from opening a console window
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+        let mut command = Command::new("powershell");
+        command
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Add-Type -AssemblyName System.Speech; \
+                 (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:PUNCHY_NARRATION)",
+            ])
+            // The text is passed through the environment so that it doesn't have to be quoted
+            .env("PUNCHY_NARRATION", text)
+            .creation_flags(CREATE_NO_WINDOW);
+        command
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    fn speak_command(text: &str) -> Command {
+        // Speech dispatcher keeps speaking the texts after their command has been killed, so they
+        // are canceled by the next one
+        let mut command = Command::new("spd-say");
+        command.arg("--cancel").arg("--").arg(text);
+        command
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use web_sys::{SpeechSynthesis, SpeechSynthesisUtterance};
+
+    /// Speaks with the speech synthesis of the browser
+    #[derive(Default)]
+    pub struct Speech;
+
+    impl Speech {
+        pub fn speak(&mut self, text: &str) {
+            let Some(synthesis) = speech_synthesis() else {
+                return;
+            };
+            synthesis.cancel();
+            if let Ok(utterance) = SpeechSynthesisUtterance::new_with_text(text) {
+                synthesis.speak(&utterance);
+            }
+        }
+
+        pub fn stop(&mut self) {
+            if let Some(synthesis) = speech_synthesis() {
+                synthesis.cancel();
+            }
+        }
+
+        /// The browser doesn't leave anything to clean up after speaking
+        pub fn reap(&mut self) {}
+    }
+
+    fn speech_synthesis() -> Option<SpeechSynthesis> {
+        web_sys::window()?.speech_synthesis().ok()
+    }
+}
@@ -0,0 +1,226 @@
+//! New Game Plus, where the campaign is played again with the modifiers of the game's
+//! [`NewGamePlusMeta`].
+//!
+//! The campaign is finished by defeating the boss of the start level, which is saved in the
+//! [`CampaignProgress`] and unlocks the New Game Plus button of the main menu. New Game Plus plays
+//! the start level with the modifiers applied over its metadata when it's started, so the level
+//! asset itself is left as it is.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    enemy::Boss,
+    metadata::{FighterSpawnMeta, GameMeta, LevelHandle, LevelMeta, NewGamePlusMeta},
+    platform::Storage,
+    GameState,
+};
+
+pub struct NewGamePlusPlugin;
+
+impl Plugin for NewGamePlusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, end_campaign)
+            .add_system(
+                record_completion
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<Campaign>(),
+            );
+    }
+}
+
+/// The campaign being played, which exists while it's played from the main menu
+#[derive(Resource)]
+pub struct Campaign {
+    pub new_game_plus: bool,
+}
+
+/// How far the players have gotten through the campaign, which is kept in the [`Storage`]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct CampaignProgress {
+    /// Whether the campaign has been finished, which unlocks New Game Plus
+    pub completed: bool,
+    /// The number of times the campaign has been finished as New Game Plus
+    pub new_game_plus_completions: u32,
+}
+
+impl CampaignProgress {
+    /// The key used to store the campaign progress in the [`Storage`].
+    pub const STORAGE_KEY: &'static str = "campaign-progress";
+}
+
+/// Start the campaign from the start level
+pub fn start_campaign(commands: &mut Commands, game: &GameMeta) {
+    commands.insert_resource(LevelHandle(game.start_level_handle.clone()));
+    commands.insert_resource(Campaign {
+        new_game_plus: false,
+    });
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+/// Start the campaign as New Game Plus, with the modifiers applied over the start level
+pub fn start_new_game_plus(
+    commands: &mut Commands,
+    levels: &mut Assets<LevelMeta>,
+    game: &GameMeta,
+    new_game_plus: &NewGamePlusMeta,
+) {
+    let Some(level) = levels.get(&game.start_level_handle) else {
+        warn!("The start level hasn't loaded");
+        return;
+    };
+    let mut level = level.clone();
+    apply_modifiers(&mut level, new_game_plus, &mut rand::thread_rng());
+
+    commands.insert_resource(LevelHandle(levels.add(level)));
+    commands.insert_resource(Campaign {
+        new_game_plus: true,
+    });
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+/// Apply the New Game Plus modifiers over a level
+fn apply_modifiers(level: &mut LevelMeta, new_game_plus: &NewGamePlusMeta, rng: &mut impl Rng) {
+    modify_enemies(&mut level.enemies, new_game_plus, rng);
+
+    // The enemies of the streamed sections are spawned from their paths, and aren't remixed
+    if let Some(streaming) = &mut level.streaming {
+        for enemy in streaming
+            .sections
+            .iter_mut()
+            .flat_map(|section| &mut section.enemies)
+        {
+            if let Some(enemy_variant) = new_game_plus
+                .enemy_variants
+                .iter()
+                .find(|enemy_variant| enemy_variant.fighter == enemy.fighter)
+            {
+                enemy.fighter = enemy_variant.variant.clone();
+            }
+        }
+    }
+}
+
+/// Replace the enemies by their variants, and remix them if enabled
+fn modify_enemies(
+    enemies: &mut [FighterSpawnMeta],
+    new_game_plus: &NewGamePlusMeta,
+    rng: &mut impl Rng,
+) {
+    for enemy in enemies.iter_mut() {
+        if let Some(enemy_variant) = new_game_plus
+            .enemy_variants
+            .iter()
+            .find(|enemy_variant| enemy_variant.fighter_handle == enemy.fighter_handle)
+        {
+            enemy.fighter = enemy_variant.variant.clone();
+            enemy.fighter_handle = enemy_variant.variant_handle.clone();
+        }
+    }
+
+    if new_game_plus.remix_enemies {
+        let mut fighters = enemies
+            .iter()
+            .filter(|enemy| !enemy.boss)
+            .map(|enemy| (enemy.fighter.clone(), enemy.fighter_handle.clone()))
+            .collect::<Vec<_>>();
+        fighters.shuffle(rng);
+
+        for (enemy, (fighter, fighter_handle)) in
+            enemies.iter_mut().filter(|enemy| !enemy.boss).zip(fighters)
+        {
+            enemy.fighter = fighter;
+            enemy.fighter_handle = fighter_handle;
+        }
+    }
+}
+
+fn end_campaign(mut commands: Commands) {
+    commands.remove_resource::<Campaign>();
+}
+
+/// Save that the campaign has been finished once the boss of the level has been defeated
+fn record_completion(
+    campaign: Res<Campaign>,
+    level: Res<LevelMeta>,
+    bosses: Query<(), With<Boss>>,
+    mut boss_seen: Local<bool>,
+    mut storage: ResMut<Storage>,
+) {
+    if level.is_changed() {
+        *boss_seen = false;
+    }
+    if !bosses.is_empty() {
+        *boss_seen = true;
+        return;
+    }
+    if !std::mem::take(&mut *boss_seen) {
+        return;
+    }
+
+    let mut progress = storage
+        .get::<CampaignProgress>(CampaignProgress::STORAGE_KEY)
+        .unwrap_or_default();
+    if campaign.new_game_plus {
+        progress.new_game_plus_completions += 1;
+    }
+    progress.completed = true;
+    info!(campaign.new_game_plus, "Campaign finished");
+
+    storage.set(CampaignProgress::STORAGE_KEY, &progress);
+    storage.save();
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::asset::HandleId;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::metadata::{EnemyVariantMeta, FighterMeta};
+
+    use super::*;
+
+    fn enemy(fighter_handle: &Handle<FighterMeta>, boss: bool) -> FighterSpawnMeta {
+        FighterSpawnMeta {
+            fighter: String::new(),
+            fighter_handle: fighter_handle.clone(),
+            location: Vec3::ZERO,
+            trip_point_x: f32::MIN,
+            boss,
+        }
+    }
+
+    #[test]
+    fn enemies_are_replaced_and_remixed_around_bosses() {
+        let [slinger, veteran, brute, boss] =
+            [(); 4].map(|_| Handle::weak(HandleId::random::<FighterMeta>()));
+        let new_game_plus = NewGamePlusMeta {
+            enemy_variants: vec![EnemyVariantMeta {
+                fighter: String::new(),
+                fighter_handle: slinger.clone(),
+                variant: String::new(),
+                variant_handle: veteran.clone(),
+            }],
+            remix_enemies: true,
+        };
+        let mut enemies = [
+            enemy(&slinger, false),
+            enemy(&boss, true),
+            enemy(&brute, false),
+            enemy(&slinger, false),
+        ];
+
+        modify_enemies(&mut enemies, &new_game_plus, &mut StdRng::seed_from_u64(0));
+
+        assert_eq!(enemies[1].fighter_handle, boss);
+        let count = |handle: &Handle<FighterMeta>| {
+            enemies
+                .iter()
+                .filter(|enemy| enemy.fighter_handle == *handle)
+                .count()
+        };
+        assert_eq!((count(&veteran), count(&brute), count(&slinger)), (2, 1, 0));
+    }
+}
@@ -0,0 +1,361 @@
+//! Palette swapping for spritesheets.
+//!
+//! Instead of duplicating a spritesheet for every color variant of a fighter, a spritesheet can
+//! reference a palette lookup image. The first row of the lookup image contains the colors to
+//! replace, and every other row is a palette, containing the replacement for the color in the
+//! same column of the first row. Fully transparent colors in the first row are ignored.
+//!
+//! Palettes are applied on the GPU. A fighter wearing a recolor has a [`PaletteSwap`], and is drawn
+//! with a [`PaletteMaterial`] instead of its sprite, which looks the colors of the sprite up in the
+//! lookup image. The sprite stays the source of the tile, color and flip that are drawn, so the
+//! animation and tint systems don't need to know about palettes.
+//!
+//! Outlines are a palette of their own, which turns every transparent pixel bordering the sprite of
+//! a tile white, and every other pixel transparent. The white is tinted to the outline's color by
+//! the sprite's color.
+
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::VertexAttributeValues,
+        render_resource::{AsBindGroup, ShaderRef, ShaderType, TextureFormat},
+        texture::{CompressedImageFormats, ImageType},
+        Extract, RenderApp, RenderStage,
+    },
+    sprite::{ExtractedSprites, Material2d, Material2dPlugin, Mesh2dHandle, SpriteSystem},
+    utils::HashMap,
+};
+
+use crate::{assets::relative_asset_path, metadata::FighterSpritesheetMeta};
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<PaletteMaterial>::default())
+            .add_system_to_stage(CoreStage::PostUpdate, remove_palette_materials)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_palette_materials.after(remove_palette_materials),
+            );
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system_to_stage(
+                RenderStage::Extract,
+                extract_palette_swaps.after(SpriteSystem::ExtractSprites),
+            );
+        }
+    }
+}
+
+/// A mapping from original colors to replacement colors
+pub type Palette = HashMap<[u8; 4], [u8; 4]>;
+
+/// A recolor of the spritesheet that a sprite is cut out of
+#[derive(Component, Clone, Debug)]
+pub struct PaletteSwap {
+    /// The palette lookup image of the spritesheet
+    pub palette: Handle<Image>,
+    /// The row of the recolor in the lookup image, starting at `1`
+    pub row: u32,
+}
+
+/// The material that palette swapped sprites are drawn with
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "3f0c5a52-7d0e-4b8a-9a55-2c61e0b7d4a9"]
+pub struct PaletteMaterial {
+    #[uniform(0)]
+    settings: PaletteSettings,
+    #[texture(1)]
+    texture: Handle<Image>,
+    #[texture(2)]
+    palette: Handle<Image>,
+}
+
+impl Material2d for PaletteMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/palette.wgsl".into()
+    }
+}
+
+/// The uniform data for the palette shader
+#[derive(ShaderType, Clone, Default, Debug)]
+struct PaletteSettings {
+    /// The color that the sprite is tinted with, in linear RGBA
+    color: Vec4,
+    /// The corners of the sprite's tile in the texture, in pixels
+    tile_min: Vec2,
+    tile_max: Vec2,
+    /// `1.0` on the axes that the sprite is flipped on
+    flip: Vec2,
+    /// The row of the recolor in the lookup image
+    row: u32,
+}
+
+impl PaletteSettings {
+    fn new(sprite: &TextureAtlasSprite, tile: Rect, palette_swap: &PaletteSwap) -> Self {
+        Self {
+            color: Vec4::from(sprite.color.as_linear_rgba_f32()),
+            tile_min: tile.min,
+            tile_max: tile.max,
+            flip: Vec2::new(
+                if sprite.flip_x { 1.0 } else { 0.0 },
+                if sprite.flip_y { 1.0 } else { 0.0 },
+            ),
+            row: palette_swap.row,
+        }
+    }
+}
+
+/// Remove the palette materials of the sprites that have lost their recolor
+fn remove_palette_materials(mut commands: Commands, removed: RemovedComponents<PaletteSwap>) {
+    for entity in removed.iter() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<(Mesh2dHandle, Handle<PaletteMaterial>)>();
+        }
+    }
+}
+
+/// Give the palette swapped sprites their material once their atlas has loaded, and keep it up to
+/// date with their sprite
+#[allow(clippy::type_complexity)]
+fn update_palette_materials(
+    mut commands: Commands,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PaletteMaterial>>,
+    sprites: Query<
+        (
+            Entity,
+            &PaletteSwap,
+            &TextureAtlasSprite,
+            &Handle<TextureAtlas>,
+            Option<&Handle<PaletteMaterial>>,
+        ),
+        Or<(
+            Changed<PaletteSwap>,
+            Changed<TextureAtlasSprite>,
+            Changed<Handle<TextureAtlas>>,
+            Without<Handle<PaletteMaterial>>,
+        )>,
+    >,
+) {
+    for (entity, palette_swap, sprite, atlas_handle, material_handle) in &sprites {
+        let Some(atlas) = atlases.get(atlas_handle) else {
+            continue;
+        };
+        let Some(&tile) = atlas.textures.get(sprite.index) else {
+            continue;
+        };
+
+        let material = PaletteMaterial {
+            settings: PaletteSettings::new(sprite, tile, palette_swap),
+            texture: atlas.texture.clone(),
+            palette: palette_swap.palette.clone(),
+        };
+        match material_handle.and_then(|handle| materials.get_mut(handle)) {
+            Some(current) => *current = material,
+            None => {
+                // The tiles of a spritesheet all have the same size, so the quad is made once
+                let quad = tile_quad(tile.size(), sprite.anchor.as_vec());
+                commands
+                    .entity(entity)
+                    .insert((Mesh2dHandle(meshes.add(quad)), materials.add(material)));
+            }
+        }
+    }
+}
+
+/// A quad the size of a tile, placed around the origin according to the sprite's anchor
+fn tile_quad(size: Vec2, anchor: Vec2) -> Mesh {
+    let mut quad = Mesh::from(shape::Quad::new(size));
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        quad.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        for position in positions {
+            position[0] -= anchor.x * size.x;
+            position[1] -= anchor.y * size.y;
+        }
+    }
+    quad
+}
+
+/// Leave the palette swapped sprites out of the sprite batches, because they are drawn with their
+/// material instead
+fn extract_palette_swaps(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    palette_swaps: Extract<Query<(), (With<PaletteSwap>, With<Handle<PaletteMaterial>>)>>,
+) {
+    extracted_sprites
+        .sprites
+        .retain(|sprite| !palette_swaps.contains(sprite.entity));
+}
+
+/// Load the palette lookup image of a spritesheet, if it has one, returning the path of the image
+/// so that the spritesheet can depend on it
+pub async fn load_palette(
+    load_context: &mut LoadContext<'_>,
+    spritesheet: &mut FighterSpritesheetMeta,
+) -> anyhow::Result<Option<AssetPath<'static>>> {
+    let Some(palette_path) = &spritesheet.palette else {
+        return Ok(None);
+    };
+    let palette_path = relative_asset_path(load_context.path(), palette_path);
+    let palettes = read_palettes(&load_image(load_context, &palette_path).await?)?;
+
+    let asset_path = AssetPath::new(palette_path, None);
+    spritesheet.palette_handle = load_context.get_handle(asset_path.clone());
+    spritesheet.palette_count = palettes.len();
+
+    Ok(Some(asset_path))
+}
+
+/// Read and decode an image
+async fn load_image(
+    load_context: &LoadContext<'_>,
+    path: &std::path::Path,
+) -> anyhow::Result<Image> {
+    let bytes = load_context.read_asset_bytes(path).await?;
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default();
+
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension(extension),
+        CompressedImageFormats::NONE,
+        true,
+    )?;
+
+    if !matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm
+    ) {
+        anyhow::bail!(
+            "Unsupported image format for palette swapping in {path:?}: {:?}",
+            image.texture_descriptor.format
+        );
+    }
+
+    Ok(image)
+}
+
+/// Read the palettes out of an RGBA8 palette lookup image
+pub fn read_palettes(image: &Image) -> anyhow::Result<Vec<Palette>> {
+    let width = image.texture_descriptor.size.width as usize;
+    let height = image.texture_descriptor.size.height as usize;
+    if height < 2 {
+        anyhow::bail!("Palette image must have at least two rows");
+    }
+
+    let pixel = |x: usize, y: usize| -> [u8; 4] {
+        let i = (y * width + x) * 4;
+        [
+            image.data[i],
+            image.data[i + 1],
+            image.data[i + 2],
+            image.data[i + 3],
+        ]
+    };
+
+    let palettes = (1..height)
+        .map(|row| {
+            (0..width)
+                .filter(|&column| pixel(column, 0)[3] != 0)
+                .map(|column| (pixel(column, 0), pixel(column, row)))
+                .collect()
+        })
+        .collect();
+
+    Ok(palettes)
+}
+
+/// Create an outline of every tile of an RGBA8 image.
+///
+/// The outline of a tile stays in the tile, so that it doesn't bleed into the tiles next to it.
+pub fn outline_image(image: &Image, tiles: &[Rect]) -> Image {
+    const OUTLINE: [u8; 4] = [255, 255, 255, 255];
+
+    let width = image.texture_descriptor.size.width as usize;
+    let height = image.texture_descriptor.size.height as usize;
+    let opaque = |x: usize, y: usize| image.data[(y * width + x) * 4 + 3] != 0;
+
+    let mut outline = image.clone();
+    outline.data.fill(0);
+
+    for tile in tiles {
+        let min_x = (tile.min.x as usize).min(width);
+        let min_y = (tile.min.y as usize).min(height);
+        let max_x = (tile.max.x as usize).min(width);
+        let max_y = (tile.max.y as usize).min(height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                if opaque(x, y) {
+                    continue;
+                }
+                let borders_sprite = (x > min_x && opaque(x - 1, y))
+                    || (x + 1 < max_x && opaque(x + 1, y))
+                    || (y > min_y && opaque(x, y - 1))
+                    || (y + 1 < max_y && opaque(x, y + 1));
+                if borders_sprite {
+                    let i = (y * width + x) * 4;
+                    outline.data[i..i + 4].copy_from_slice(&OUTLINE);
+                }
+            }
+        }
+    }
+
+    outline
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::render::render_resource::{Extent3d, TextureDimension};
+
+    use super::*;
+
+    fn image(width: u32, height: u32, data: &[[u8; 4]]) -> Image {
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data.concat(),
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    #[test]
+    fn test_palette_swap() {
+        const RED: [u8; 4] = [255, 0, 0, 255];
+        const GREEN: [u8; 4] = [0, 255, 0, 255];
+        const BLUE: [u8; 4] = [0, 0, 255, 255];
+        const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+        // Replace red with blue, and ignore the transparent column
+        let palettes = read_palettes(&image(2, 2, &[RED, CLEAR, BLUE, GREEN])).unwrap();
+        assert_eq!(palettes.len(), 1);
+        assert_eq!(palettes[0].len(), 1);
+        assert_eq!(palettes[0].get(&RED), Some(&BLUE));
+    }
+
+    #[test]
+    fn outlines_stay_in_their_tiles() {
+        const RED: [u8; 4] = [255, 0, 0, 255];
+        const WHITE: [u8; 4] = [255, 255, 255, 255];
+        const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+        // Two 2x1 tiles, with a sprite pixel on the right of the first one
+        let sheet = image(4, 1, &[CLEAR, RED, CLEAR, CLEAR]);
+        let tiles = [Rect::new(0.0, 0.0, 2.0, 1.0), Rect::new(2.0, 0.0, 4.0, 1.0)];
+
+        let outline = outline_image(&sheet, &tiles);
+        assert_eq!(outline.data, [WHITE, CLEAR, CLEAR, CLEAR].concat());
+    }
+}
@@ -13,7 +13,10 @@ use native as backend;
 #[cfg(target_arch = "wasm32")]
 use wasm as backend;
 
-use crate::GameState;
+use crate::{
+    metadata::{GameMeta, Settings},
+    GameState,
+};
 
 pub struct PlatformPlugin;
 
@@ -23,7 +26,11 @@ impl Plugin for PlatformPlugin {
         app.add_system(wasm::update_canvas_size);
 
         app.init_resource::<Storage>()
-            .add_system(load_storage.run_in_state(GameState::LoadingStorage));
+            .add_system(load_storage.run_in_state(GameState::LoadingStorage))
+            .add_system_to_stage(
+                CoreStage::First,
+                update_settings.run_if_resource_exists::<GameMeta>(),
+            );
     }
 }
 
@@ -51,6 +58,22 @@ pub fn load_storage(
     }
 }
 
+/// Update the [`Settings`] resource when the settings have been saved, or when the game has been
+/// loaded with the default settings, so that the systems applying them only have to check whether
+/// the resource has changed
+fn update_settings(mut commands: Commands, game: Res<GameMeta>, mut storage: ResMut<Storage>) {
+    if !storage.is_changed() && !game.is_changed() {
+        return;
+    }
+
+    // Reading from the storage needs mutable access, which shouldn't count as a change
+    let settings = storage
+        .bypass_change_detection()
+        .get::<Settings>(Settings::STORAGE_KEY)
+        .unwrap_or_else(|| game.default_settings.clone());
+    commands.insert_resource(settings);
+}
+
 /// The type of the inner data in [`Storage`]
 type StorageData = HashMap<String, serde_yaml::Value>;
 
@@ -267,6 +290,12 @@ impl SaveTask {
     }
 }
 
+/// The number of the current day since the Unix epoch, in UTC, which is the same for every player
+/// on the same date
+pub fn current_day() -> u64 {
+    backend::unix_time().as_secs() / (60 * 60 * 24)
+}
+
 enum StorageRequest {
     Load {
         result_sender: Sender<StorageData>,
@@ -283,6 +312,7 @@ mod native {
     use std::{
         fs,
         io::{Read, Write},
+        time::{Duration, SystemTime, UNIX_EPOCH},
     };
 
     use async_channel::Sender;
@@ -294,6 +324,13 @@ mod native {
 
     use super::StorageRequest;
 
+    /// The time since the Unix epoch
+    pub(super) fn unix_time() -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
     pub(super) fn init_storage(io_task_pool: &IoTaskPool) -> Sender<StorageRequest> {
         trace!("Initialize platform storage backend");
 
@@ -370,6 +407,8 @@ mod native {
 /// WASM platform support
 #[cfg(target_arch = "wasm32")]
 mod wasm {
+    use std::time::Duration;
+
     use async_channel::Sender;
     use bevy::{prelude::*, tasks::IoTaskPool};
 
@@ -377,6 +416,11 @@ mod wasm {
 
     const BROWSER_LOCAL_STORAGE_KEY: &str = "punchy-platform-storage";
 
+    /// The time since the Unix epoch. The standard library can't tell the time in the browser.
+    pub(super) fn unix_time() -> Duration {
+        Duration::from_millis(js_sys::Date::now() as u64)
+    }
+
     /// System to update the canvas size to match the size of the browser window
     pub fn update_canvas_size(mut windows: ResMut<Windows>) {
         // Get the browser window size
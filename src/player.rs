@@ -3,16 +3,20 @@ use leafwing_input_manager::InputManagerBundle;
 
 use crate::{
     animation::Facing,
+    combo_breaker::ComboCounter,
     consts,
     fighter::Inventory,
     input::PlayerAction,
     metadata::{FighterMeta, FighterSpawnMeta, GameMeta, Settings},
+    team_up::TeamUpMeter,
 };
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Player;
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct PlayerIndex(pub usize);
 
 #[derive(Bundle)]
@@ -21,6 +25,8 @@ pub struct PlayerBundle {
     index: PlayerIndex,
     facing: Facing,
     inventory: Inventory,
+    team_up_meter: TeamUpMeter,
+    combo_counter: ComboCounter,
     #[bundle]
     transform_bundle: TransformBundle,
     fighter_handle: Handle<FighterMeta>,
@@ -59,6 +65,8 @@ impl PlayerBundle {
             fighter_handle,
             input_manager_bundle,
             inventory: Inventory(None),
+            team_up_meter: default(),
+            combo_counter: default(),
         }
     }
 }
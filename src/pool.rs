@@ -0,0 +1,206 @@
+//! Pooling of short-lived entities like projectiles and effects.
+//!
+//! Bullets, thrown items, and explosions are spawned and despawned many times a second in busy
+//! fights. Instead of being despawned, pooled entities are parked: they are hidden, stopped, and
+//! taken out of collisions, but they keep their components so that the next spawn of the same
+//! [`PoolKind`] can re-use them without moving them between archetypes.
+//!
+//! Use [`EntityPoolCommands`] to spawn pooled entities and to despawn entities that may be pooled.
+
+use bevy::{
+    ecs::{
+        entity::Entities,
+        system::{Command, EntityCommands, SystemParam},
+    },
+    hierarchy::despawn_with_children_recursive,
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    lifetime::Lifetime,
+    movement::{AngularVelocity, Force, LinearVelocity},
+    timestep::TransformInterpolation,
+};
+
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPool>();
+    }
+}
+
+/// The kinds of pooled entities.
+///
+/// Entities are only re-used for spawns of the same kind, so every spawn of a kind must insert the
+/// same set of components.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PoolKind {
+    Bullet,
+    Bomb,
+    ThrownItem,
+    /// A thrown item that drops another item when it breaks
+    ThrownBox,
+    Explosion,
+    /// A sprite of a hit spark or another [effect](crate::effects)
+    Effect,
+    /// A fading copy of a fighter's sprite, left behind by a fast move
+    Afterimage,
+    /// A projectile shot by an attack
+    Projectile,
+}
+
+/// Component on entities that belong to the [`EntityPool`]
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Pooled {
+    pub kind: PoolKind,
+    /// Whether the entity is currently parked in the pool, waiting to be re-used
+    pub parked: bool,
+}
+
+/// The parked entities, waiting to be re-used
+#[derive(Resource, Default)]
+pub struct EntityPool {
+    parked: HashMap<PoolKind, Vec<Entity>>,
+}
+
+/// System parameter used to spawn pooled entities and return entities to the pool.
+#[derive(SystemParam)]
+pub struct EntityPoolCommands<'w, 's> {
+    commands: Commands<'w, 's>,
+    pool: ResMut<'w, EntityPool>,
+    pooled: Query<'w, 's, &'static Pooled>,
+    entities: &'w Entities,
+}
+
+impl<'w, 's> EntityPoolCommands<'w, 's> {
+    /// Spawn an entity of the given kind with the bundle, re-using a parked entity if there is one
+    pub fn spawn<'a, B: Bundle>(
+        &'a mut self,
+        kind: PoolKind,
+        bundle: B,
+    ) -> EntityCommands<'w, 's, 'a> {
+        // Parked entities can still be despawned directly, for instance when the world is reset
+        let parked = self.pool.parked.entry(kind).or_default();
+        let mut reusable = None;
+        while let Some(entity) = parked.pop() {
+            if self.entities.contains(entity) {
+                reusable = Some(entity);
+                break;
+            }
+        }
+
+        let pooled = Pooled {
+            kind,
+            parked: false,
+        };
+        match reusable {
+            Some(entity) => {
+                self.commands.entity(entity).insert((bundle, pooled));
+                self.commands.add(ResetInterpolation(entity));
+                self.commands.entity(entity)
+            }
+            None => self.commands.spawn((bundle, pooled)),
+        }
+    }
+
+    /// Return the entity to the pool if it is pooled, or despawn it recursively if it isn't
+    pub fn despawn(&mut self, entity: Entity) {
+        if self.pooled.contains(entity) {
+            self.commands.add(ParkEntity(entity));
+        } else {
+            self.commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    /// Whether the entity is parked in the pool. Systems that act on pooled entities, other than
+    /// moving them, should skip parked entities.
+    pub fn is_parked(&self, entity: Entity) -> bool {
+        self.pooled
+            .get(entity)
+            .map(|pooled| pooled.parked)
+            .unwrap_or(false)
+    }
+}
+
+/// Command that deactivates a pooled entity and adds it to the pool
+struct ParkEntity(Entity);
+
+impl Command for ParkEntity {
+    fn write(self, world: &mut World) {
+        let entity = self.0;
+        let Some(pooled) = world.get::<Pooled>(entity).copied() else {
+            return;
+        };
+        if pooled.parked {
+            return;
+        }
+
+        // Entities that have been attached to another entity, like bombs that have been picked up,
+        // have different components than a fresh spawn, so they can't be re-used
+        if world.get::<Parent>(entity).is_some() {
+            despawn_with_children_recursive(world, entity);
+            return;
+        }
+
+        // Children, like attack hitboxes, are spawned again with their parents, so they aren't
+        // pooled
+        if let Some(children) = world.get::<Children>(entity) {
+            for child in children.to_vec() {
+                despawn_with_children_recursive(world, child);
+            }
+        }
+
+        let mut entity_mut = world.entity_mut(entity);
+        entity_mut.insert(Pooled {
+            parked: true,
+            ..pooled
+        });
+        if let Some(mut visibility) = entity_mut.get_mut::<Visibility>() {
+            visibility.is_visible = false;
+        }
+        if let Some(mut velocity) = entity_mut.get_mut::<LinearVelocity>() {
+            **velocity = Vec2::ZERO;
+        }
+        if let Some(mut velocity) = entity_mut.get_mut::<AngularVelocity>() {
+            **velocity = 0.0;
+        }
+        if let Some(mut force) = entity_mut.get_mut::<Force>() {
+            **force = Vec2::ZERO;
+        }
+        if let Some(mut collision_groups) = entity_mut.get_mut::<CollisionGroups>() {
+            *collision_groups = CollisionGroups::new(Group::NONE, Group::NONE);
+        }
+        // Make sure the lifetime doesn't expire again while the entity is parked
+        if let Some(mut lifetime) = entity_mut.get_mut::<Lifetime>() {
+            lifetime.reset();
+            lifetime.pause();
+        }
+
+        world
+            .resource_mut::<EntityPool>()
+            .parked
+            .entry(pooled.kind)
+            .or_default()
+            .push(entity);
+    }
+}
+
+/// Command that moves the interpolated translation of a re-used entity to its new translation
+struct ResetInterpolation(Entity);
+
+impl Command for ResetInterpolation {
+    fn write(self, world: &mut World) {
+        let Some(mut entity_mut) = world.get_entity_mut(self.0) else {
+            return;
+        };
+        let Some(translation) = entity_mut.get::<Transform>().map(|x| x.translation) else {
+            return;
+        };
+        if let Some(mut interpolation) = entity_mut.get_mut::<TransformInterpolation>() {
+            *interpolation = TransformInterpolation::new(translation.truncate());
+        }
+    }
+}
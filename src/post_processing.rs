@@ -0,0 +1,346 @@
+//! Full-screen post-processing effects for levels.
+//!
+//! When a level has post-processing effects, the game camera renders to an image instead of the
+//! window. A second camera then renders that image to the window on a full-screen quad, using the
+//! [`PostProcessingMaterial`] to apply the effects.
+//!
+//! Cinematics can also drain the colors out of the screen with the [`PostEffectsOverride`], which
+//! sets up the pass for levels without effects of their own while it's active. The pass is also
+//! set up while the [`RenderScale`] is lowered, to render the levels to a smaller image that is
+//! stretched over the window.
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDescriptor, TextureDimension,
+            TextureFormat, TextureUsages,
+        },
+        view::RenderLayers,
+    },
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
+    window::WindowId,
+};
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    metadata::{LevelMeta, PostEffectsMeta, PostProcessingMeta},
+    GameState,
+};
+
+/// The render layer used by the post-processing pass, so that the game camera doesn't render the
+/// full-screen quad.
+const POST_PROCESSING_LAYER: u8 = (RenderLayers::TOTAL_LAYERS - 1) as u8;
+
+pub struct PostProcessingPlugin;
+
+impl Plugin for PostProcessingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<PostProcessingMaterial>::default())
+            .init_resource::<PostEffectsOverride>()
+            .init_resource::<RenderScale>()
+            .add_system_to_stage(CoreStage::PostUpdate, update_post_processing);
+    }
+}
+
+/// Marker component for the entities of the post-processing pass.
+///
+/// These entities are managed by the post-processing systems and should be left alone when
+/// resetting the world.
+#[derive(Component)]
+pub struct PostProcessingEntity;
+
+/// Effects applied on top of the effects of the level, for cinematics like the knockout slow
+/// motion
+#[derive(Resource, Default, Debug)]
+pub struct PostEffectsOverride {
+    /// How much of the colors are drained out of the screen, from `0.0` to `1.0`
+    pub desaturation: f32,
+}
+
+/// The part of the window's resolution that the levels are rendered at, from `0.0` to `1.0`
+#[derive(Resource, Deref, DerefMut, Debug)]
+pub struct RenderScale(pub f32);
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// The material that applies the post-processing effects to the image rendered by the game
+/// camera.
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "a7b8c364-1a3f-4e8e-9a6c-8c7e8c1b3f52"]
+pub struct PostProcessingMaterial {
+    #[uniform(0)]
+    settings: PostProcessingSettings,
+    #[texture(1)]
+    #[sampler(2)]
+    source_image: Handle<Image>,
+}
+
+impl Material2d for PostProcessingMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/post_processing.wgsl".into()
+    }
+}
+
+/// The uniform data for the post-processing shader
+#[derive(ShaderType, Clone, Default, Debug)]
+struct PostProcessingSettings {
+    color_grade: Vec4,
+    crt: f32,
+    heat_haze: f32,
+    desaturation: f32,
+    time: f32,
+}
+
+impl PostProcessingSettings {
+    fn new(effects: &PostEffectsMeta, effects_override: &PostEffectsOverride, time: f32) -> Self {
+        let [r, g, b] = effects.color_grade;
+        let color = Color::rgb_u8(r, g, b);
+
+        Self {
+            color_grade: Vec4::new(
+                color.r(),
+                color.g(),
+                color.b(),
+                effects.color_grade_intensity,
+            ),
+            crt: effects.crt,
+            heat_haze: effects.heat_haze,
+            desaturation: effects_override.desaturation,
+            time,
+        }
+    }
+}
+
+/// Resource that exists while the post-processing pass is active
+#[derive(Resource)]
+struct PostProcessingPass {
+    image: Handle<Image>,
+    material: Handle<PostProcessingMaterial>,
+    quad: Entity,
+    camera: Entity,
+    /// The physical size of the image
+    size: UVec2,
+    /// The current intensity of the effects
+    effects: PostEffectsMeta,
+    /// The transition to the effects of the last reached trigger, if one is in progress
+    transition: Option<(PostEffectsMeta, PostEffectsMeta, Timer)>,
+    /// The index of the next trigger in the level
+    next_trigger: usize,
+}
+
+/// Sets up, updates, and tears down the post-processing pass depending on the current level.
+fn update_post_processing(
+    mut commands: Commands,
+    level: Option<Res<LevelMeta>>,
+    state: Res<CurrentState<GameState>>,
+    pass: Option<ResMut<PostProcessingPass>>,
+    windows: Res<Windows>,
+    time: Res<Time>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PostProcessingMaterial>>,
+    mut game_camera: Query<(&mut Camera, &Transform), With<ParallaxCameraComponent>>,
+    effects_override: Res<PostEffectsOverride>,
+    render_scale: Res<RenderScale>,
+) {
+    let in_level = state.0 == GameState::InGame;
+    let scaled = **render_scale < 1.0;
+    // The override and the render scale need the pass even if the level has no effects
+    let no_effects = PostProcessingMeta::default();
+    let post_processing = level
+        .as_ref()
+        .filter(|_| in_level)
+        .and_then(|level| level.post_processing.as_ref())
+        .or_else(|| {
+            (in_level && (effects_override.desaturation > 0.0 || scaled)).then_some(&no_effects)
+        });
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let window_size = UVec2::new(window.physical_width(), window.physical_height()).max(UVec2::ONE);
+    let image_size = scaled_size(window_size, **render_scale);
+
+    match (post_processing, pass) {
+        (None, None) => (),
+
+        // Remove the post-processing pass when leaving the level
+        (None, Some(pass)) => {
+            for (mut camera, _) in &mut game_camera {
+                camera.target = RenderTarget::Window(WindowId::primary());
+            }
+            for entity in [pass.quad, pass.camera] {
+                if let Some(entity) = commands.get_entity(entity) {
+                    entity.despawn_recursive();
+                }
+            }
+            images.remove(&pass.image);
+            materials.remove(&pass.material);
+            commands.remove_resource::<PostProcessingPass>();
+        }
+
+        // Create the post-processing pass when entering a level that has effects
+        (Some(post_processing), None) => {
+            let size = Extent3d {
+                width: image_size.x,
+                height: image_size.y,
+                ..default()
+            };
+            let mut image = Image {
+                texture_descriptor: TextureDescriptor {
+                    label: Some("post_processing_source"),
+                    size,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::Bgra8UnormSrgb,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    usage: TextureUsages::TEXTURE_BINDING
+                        | TextureUsages::COPY_DST
+                        | TextureUsages::RENDER_ATTACHMENT,
+                },
+                ..default()
+            };
+            image.resize(size);
+            let image = images.add(image);
+
+            let effects = post_processing.effects.clone();
+            let material = materials.add(PostProcessingMaterial {
+                settings: PostProcessingSettings::new(
+                    &effects,
+                    &effects_override,
+                    time.elapsed_seconds(),
+                ),
+                source_image: image.clone(),
+            });
+
+            let quad = commands
+                .spawn((
+                    MaterialMesh2dBundle {
+                        mesh: Mesh2dHandle(meshes.add(fullscreen_quad(window))),
+                        material: material.clone(),
+                        ..default()
+                    },
+                    RenderLayers::layer(POST_PROCESSING_LAYER),
+                    PostProcessingEntity,
+                    Name::new("Post Processing Quad"),
+                ))
+                .id();
+            let camera = commands
+                .spawn((
+                    Camera2dBundle {
+                        camera: Camera {
+                            // Render after the game camera
+                            priority: 1,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    RenderLayers::layer(POST_PROCESSING_LAYER),
+                    PostProcessingEntity,
+                    Name::new("Post Processing Camera"),
+                ))
+                .id();
+
+            for (mut camera, _) in &mut game_camera {
+                camera.target = RenderTarget::Image(image.clone());
+            }
+
+            commands.insert_resource(PostProcessingPass {
+                image,
+                material,
+                quad,
+                camera,
+                size: image_size,
+                effects,
+                transition: None,
+                next_trigger: 0,
+            });
+        }
+
+        // Update the effects
+        (Some(post_processing), Some(mut pass)) => {
+            // Restart the effects if a new level has been loaded
+            if level.as_ref().map(|x| x.is_changed()).unwrap_or(false) {
+                pass.effects = post_processing.effects.clone();
+                pass.transition = None;
+                pass.next_trigger = 0;
+            }
+
+            // Resize the image and the quad with the window and the render scale
+            if pass.size != image_size {
+                pass.size = image_size;
+                if let Some(image) = images.get_mut(&pass.image) {
+                    image.resize(Extent3d {
+                        width: image_size.x,
+                        height: image_size.y,
+                        ..default()
+                    });
+                }
+                commands
+                    .entity(pass.quad)
+                    .insert(Mesh2dHandle(meshes.add(fullscreen_quad(window))));
+            }
+
+            let mut camera_x = None;
+            for (mut camera, transform) in &mut game_camera {
+                camera_x = Some(transform.translation.x);
+
+                // The game camera is re-spawned when the game is hot reloaded
+                if camera.target != RenderTarget::Image(pass.image.clone()) {
+                    camera.target = RenderTarget::Image(pass.image.clone());
+                }
+            }
+
+            // Start the transition for any triggers the camera has reached
+            if let Some(camera_x) = camera_x {
+                while let Some(trigger) = post_processing.triggers.get(pass.next_trigger) {
+                    if camera_x < trigger.x {
+                        break;
+                    }
+
+                    let timer = Timer::from_seconds(trigger.transition, TimerMode::Once);
+                    pass.transition = Some((pass.effects.clone(), trigger.effects.clone(), timer));
+                    pass.next_trigger += 1;
+                }
+            }
+
+            if let Some((from, to, mut timer)) = pass.transition.take() {
+                timer.tick(time.delta());
+                pass.effects = from.lerp(&to, timer.percent());
+
+                if !timer.finished() {
+                    pass.transition = Some((from, to, timer));
+                }
+            }
+
+            if let Some(material) = materials.get_mut(&pass.material) {
+                material.settings = PostProcessingSettings::new(
+                    &pass.effects,
+                    &effects_override,
+                    time.elapsed_seconds(),
+                );
+            }
+        }
+    }
+}
+
+/// Get the size of the image rendered at the `scale` of the window's physical size
+fn scaled_size(window_size: UVec2, scale: f32) -> UVec2 {
+    let scale = scale.clamp(0.0, 1.0);
+    (window_size.as_vec2() * scale)
+        .round()
+        .as_uvec2()
+        .max(UVec2::ONE)
+}
+
+/// Create a quad that covers the whole window
+fn fullscreen_quad(window: &Window) -> Mesh {
+    Mesh::from(shape::Quad::new(Vec2::new(window.width(), window.height())))
+}
@@ -0,0 +1,218 @@
+//! Projectiles, which the attacks of the fighters shoot at each other.
+//!
+//! An attack with a [`ProjectileMeta`] shoots it instead of hitting with a hitbox of its own. The
+//! projectile hits with the damage and the effects of the attack, like its status effect and how
+//! high it launches the fighters, and flies along its [`ProjectileTrajectory`] until its lifetime
+//! is over or it hits one more fighter than it pierces through. Homing projectiles keep turning
+//! towards the closest fighter that they can hit, so a reflected one goes after the enemies.
+//!
+//! Players shoot the projectile of their attacks with the shoot button, and enemies with their
+//! `projectile` attack.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    animation::Facing,
+    attack::{Attack, Breakable},
+    consts,
+    enemy::Enemy,
+    fighter_state::Dying,
+    item::ProjectileOwner,
+    lifetime::Lifetime,
+    metadata::{AttackMeta, ProjectileMeta, ProjectileTrajectory},
+    movement::{AngularVelocity, Force, ForceSystems, LinearVelocity, VelocitySystems},
+    player::Player,
+    sprite_atlas::ItemSprites,
+    team::Team,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::MOVEMENT,
+            home_projectiles
+                .run_in_state(GameState::InGame)
+                .after(ForceSystems)
+                .before(VelocitySystems),
+        );
+    }
+}
+
+/// Component on projectiles shot by attacks, with the trajectory that they fly along
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Flight(pub ProjectileTrajectory);
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    velocity: LinearVelocity,
+    angular_velocity: AngularVelocity,
+    force: Force,
+    collider: Collider,
+    sensor: Sensor,
+    events: ActiveEvents,
+    collision_types: ActiveCollisionTypes,
+    collision_groups: CollisionGroups,
+    attack: Attack,
+    lifetime: Lifetime,
+    breakable: Breakable,
+    owner: ProjectileOwner,
+    flight: Flight,
+}
+
+impl ProjectileBundle {
+    /// The projectile of `attack` shot from `translation` in the direction the owner is facing
+    pub fn new(
+        translation: Vec3,
+        attack: &AttackMeta,
+        meta: &ProjectileMeta,
+        facing: &Facing,
+        owner: ProjectileOwner,
+        team: &Team,
+        item_sprites: &mut ItemSprites,
+    ) -> Self {
+        let (texture_atlas, sprite) = item_sprites.get(&meta.image);
+        let gravity = match meta.trajectory {
+            ProjectileTrajectory::Arcing { gravity, .. } => gravity,
+            _ => 0.0,
+        };
+
+        Self {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas,
+                sprite,
+                transform: Transform::from_xyz(translation.x, translation.y, consts::PROJECTILE_Z),
+                ..default()
+            },
+            velocity: LinearVelocity(facing.mirror(launch_velocity(meta.trajectory, meta.speed))),
+            angular_velocity: AngularVelocity(meta.spin * facing.sign()),
+            force: Force(Vec2::new(0.0, -gravity)),
+            collider: Collider::cuboid(attack.hitbox.size.x / 2., attack.hitbox.size.y / 2.),
+            sensor: Sensor,
+            events: ActiveEvents::COLLISION_EVENTS,
+            collision_types: ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+            collision_groups: team.attack_groups(),
+            attack: Attack {
+                damage: attack.damage,
+                pushback: facing.mirror(attack.velocity.unwrap_or(Vec2::ZERO)),
+                hitstun_duration: attack.hitstun_duration,
+                hitbox_meta: None,
+                status_effect: attack.status_effect,
+                launch: attack.launch,
+                anti_air: attack.anti_air,
+            },
+            lifetime: Lifetime(Timer::from_seconds(meta.lifetime.max(0.0), TimerMode::Once)),
+            breakable: Breakable::new(meta.piercing.max(0), false),
+            owner,
+            flight: Flight(meta.trajectory),
+        }
+    }
+}
+
+/// The velocity that a projectile is shot forward with, before it's mirrored with the facing of
+/// its owner
+fn launch_velocity(trajectory: ProjectileTrajectory, speed: f32) -> Vec2 {
+    let direction = match trajectory {
+        ProjectileTrajectory::Arcing { angle, .. } => Vec2::from_angle(angle.to_radians()),
+        _ => Vec2::X,
+    };
+    direction * speed
+}
+
+/// Turn the homing projectiles towards the closest fighter that they can hit
+fn home_projectiles(
+    mut projectiles: Query<(&Transform, &mut LinearVelocity, &ProjectileOwner, &Flight)>,
+    players: Query<&Transform, (With<Player>, Without<Dying>, Without<Flight>)>,
+    enemies: Query<&Transform, (With<Enemy>, Without<Dying>, Without<Flight>)>,
+) {
+    for (transform, mut velocity, owner, flight) in &mut projectiles {
+        let ProjectileTrajectory::Homing { turn_rate } = flight.0 else {
+            continue;
+        };
+
+        let position = transform.translation.truncate();
+        let target = if owner.enemy {
+            closest_target(position, players.iter())
+        } else {
+            closest_target(position, enemies.iter())
+        };
+        let Some(target) = target else {
+            continue;
+        };
+
+        let max_turn = turn_rate.to_radians() * GAMEPLAY_STEP.as_secs_f32();
+        **velocity = steer(**velocity, target - position, max_turn);
+    }
+}
+
+/// The position of the fighter closest to `position` that a projectile aims for, which is the
+/// middle of the fighter instead of its feet
+fn closest_target<'a>(
+    position: Vec2,
+    fighters: impl Iterator<Item = &'a Transform>,
+) -> Option<Vec2> {
+    fighters
+        .map(|fighter| fighter.translation.truncate() + consts::THROW_ITEM_OFFSET)
+        .min_by(|a, b| {
+            a.distance_squared(position)
+                .total_cmp(&b.distance_squared(position))
+        })
+}
+
+/// The velocity turned towards `direction` by at most `max_turn` radians, at the same speed
+fn steer(velocity: Vec2, direction: Vec2, max_turn: f32) -> Vec2 {
+    let (Some(heading), Some(direction)) = (velocity.try_normalize(), direction.try_normalize())
+    else {
+        return velocity;
+    };
+
+    let turn = heading.angle_between(direction).clamp(-max_turn, max_turn);
+    Vec2::from_angle(turn).rotate(velocity)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn homing_projectiles_turn_towards_their_target() {
+        let velocity = Vec2::new(100.0, 0.0);
+
+        // The turn is limited by the turn rate, without slowing the projectile down
+        let turned = steer(velocity, Vec2::Y, 0.1);
+        assert!((turned.length() - 100.0).abs() < 1e-3);
+        assert!((turned.angle_between(velocity) + 0.1).abs() < 1e-4);
+        assert!(turned.y > 0.0);
+
+        // Projectiles that can turn far enough fly straight at the target
+        let turned = steer(velocity, Vec2::new(0.0, -5.0), PI);
+        assert!(turned.abs_diff_eq(Vec2::new(0.0, -100.0), 1e-3));
+
+        // Projectiles on top of their target keep flying the way they were
+        assert_eq!(steer(velocity, Vec2::ZERO, 0.1), velocity);
+    }
+
+    #[test]
+    fn arcing_projectiles_are_shot_up() {
+        let straight = launch_velocity(ProjectileTrajectory::Straight, 200.0);
+        assert_eq!(straight, Vec2::new(200.0, 0.0));
+
+        let arcing = ProjectileTrajectory::Arcing {
+            angle: 45.0,
+            gravity: 300.0,
+        };
+        let velocity = launch_velocity(arcing, 200.0);
+        assert!((velocity.x - velocity.y).abs() < 1e-3);
+        assert!((velocity.length() - 200.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,219 @@
+//! Reflecting the projectiles of the enemies back at them with well-timed attacks.
+//!
+//! An attack of a player reflects an enemy projectile that is inside its hitbox during the
+//! [reflection window](ReflectionMeta::window), right after the hitbox comes out. The projectile
+//! then flies straight back at the enemy that threw it, faster and hurting more, and belongs to the
+//! player that reflected it.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::{Attack, AttackFrames},
+    collision::BodyLayers,
+    consts,
+    item::ProjectileOwner,
+    lifetime::Lifetime,
+    metadata::{GameMeta, ReflectionMeta},
+    movement::{AngularVelocity, Force, LinearVelocity, VelocitySystems},
+    player::Player,
+    team::Team,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct ReflectionPlugin;
+
+impl Plugin for ReflectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::MOVEMENT,
+            reflect_projectiles
+                .run_in_state(GameState::InGame)
+                .after(VelocitySystems),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            open_reflection_windows.run_in_state(GameState::InGame),
+        );
+    }
+}
+
+/// Component on the hitbox of an attack that can still reflect projectiles, until its timer runs
+/// out
+#[derive(Component, Deref, DerefMut)]
+pub struct ReflectionWindow(pub Timer);
+
+/// Open the reflection windows of the hitboxes of the players' attacks as they come out
+fn open_reflection_windows(
+    mut commands: Commands,
+    hitboxes: Query<(Entity, &CollisionGroups), (With<AttackFrames>, Added<Collider>)>,
+    game: Res<GameMeta>,
+) {
+    let Some(meta) = &game.reflection else {
+        return;
+    };
+
+    for (entity, collision_groups) in &hitboxes {
+        if collision_groups
+            .memberships
+            .contains(BodyLayers::PLAYER_ATTACK)
+        {
+            commands
+                .entity(entity)
+                .insert(ReflectionWindow(Timer::from_seconds(
+                    meta.window.max(0.0),
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+/// Send the enemy projectiles inside of open reflection windows back at their owners
+fn reflect_projectiles(
+    mut hitboxes: Query<(&mut ReflectionWindow, &GlobalTransform, &Collider, &Parent)>,
+    mut projectiles: Query<
+        (
+            &Transform,
+            &Collider,
+            &mut ProjectileOwner,
+            &mut Attack,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            &mut Force,
+            &mut CollisionGroups,
+            &mut Lifetime,
+        ),
+        Without<ReflectionWindow>,
+    >,
+    fighters: Query<&Transform, Without<ProjectileOwner>>,
+    parents: Query<&Parent>,
+    players: Query<&Team, With<Player>>,
+    game: Res<GameMeta>,
+) {
+    let Some(meta) = &game.reflection else {
+        return;
+    };
+
+    for (mut window, hitbox_transform, hitbox_collider, hitbox_parent) in &mut hitboxes {
+        if window.tick(GAMEPLAY_STEP).finished() {
+            continue;
+        }
+        let Some(hitbox_half_size) = half_size(hitbox_collider) else {
+            continue;
+        };
+
+        // The attacks of the weapons are children of the weapons, which are children of the players
+        let parent = hitbox_parent.get();
+        let grandparent = parents.get(parent).ok().map(Parent::get);
+        let Some((reflector, team)) = [Some(parent), grandparent]
+            .into_iter()
+            .flatten()
+            .find_map(|entity| Some(entity).zip(players.get(entity).ok()))
+        else {
+            continue;
+        };
+
+        for (
+            transform,
+            collider,
+            mut owner,
+            mut attack,
+            mut velocity,
+            mut angular_velocity,
+            mut force,
+            mut collision_groups,
+            mut lifetime,
+        ) in &mut projectiles
+        {
+            // Parked projectiles are taken out of collisions, so they aren't enemy attacks anymore
+            if !owner.enemy
+                || !collision_groups
+                    .memberships
+                    .contains(BodyLayers::ENEMY_ATTACK)
+            {
+                continue;
+            }
+            let Some(projectile_half_size) = half_size(collider) else {
+                continue;
+            };
+            let offset =
+                transform.translation.truncate() - hitbox_transform.translation().truncate();
+            if !offset
+                .abs()
+                .cmple(hitbox_half_size + projectile_half_size)
+                .all()
+            {
+                continue;
+            }
+
+            // Aim at the height that the owner threw the projectile from, or straight back if the
+            // owner is gone
+            let target = fighters
+                .get(owner.fighter)
+                .ok()
+                .map(|fighter| fighter.translation.truncate() + consts::THROW_ITEM_OFFSET);
+            **velocity =
+                reflected_velocity(**velocity, transform.translation.truncate(), target, meta);
+            **angular_velocity = -**angular_velocity;
+            // Reflected projectiles fly straight, so that they don't fall short of their target
+            **force = Vec2::ZERO;
+            lifetime.reset();
+
+            attack.damage = (attack.damage as f32 * meta.damage_scale).round() as i32;
+            attack.pushback = Vec2::new(attack.pushback.length() * velocity.x.signum(), 0.0);
+            *collision_groups = team.attack_groups();
+            *owner = ProjectileOwner {
+                fighter: reflector,
+                enemy: false,
+            };
+        }
+    }
+}
+
+fn half_size(collider: &Collider) -> Option<Vec2> {
+    collider.as_cuboid().map(|cuboid| cuboid.half_extents())
+}
+
+/// The velocity of a projectile at `position` reflected towards `target`, or back the way it came
+/// if there is no target
+fn reflected_velocity(
+    velocity: Vec2,
+    position: Vec2,
+    target: Option<Vec2>,
+    meta: &ReflectionMeta,
+) -> Vec2 {
+    let speed = velocity.length() * meta.speed_scale;
+    let direction = target
+        .and_then(|target| (target - position).try_normalize())
+        .unwrap_or_else(|| (-velocity).normalize_or_zero());
+    direction * speed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn projectiles_are_reflected_at_their_owners() {
+        let meta = ReflectionMeta {
+            window: 0.1,
+            speed_scale: 2.0,
+            damage_scale: 1.0,
+        };
+
+        let velocity = reflected_velocity(
+            Vec2::new(-30.0, 40.0),
+            Vec2::ZERO,
+            Some(Vec2::new(100.0, 0.0)),
+            &meta,
+        );
+        assert!((velocity - Vec2::new(100.0, 0.0)).length() < 1e-4);
+
+        let velocity = reflected_velocity(Vec2::new(-50.0, 0.0), Vec2::ZERO, None, &meta);
+        assert!((velocity - Vec2::new(100.0, 0.0)).length() < 1e-4);
+    }
+}
@@ -0,0 +1,488 @@
+//! The netplay relay that hosts online sessions.
+//!
+//! Running the game with `--relay <address>` runs it headless, with a relay listening for the
+//! players of an online session on the UDP address. The relay plays the level itself, with the
+//! inputs that the players send it in place of input devices, and after every gameplay step it
+//! sends the inputs that it played the step with back to all of the players. Because the
+//! simulation is deterministic, every player that plays the steps with the same inputs and seed
+//! ends up in the same state as the relay.
+//!
+//! The relay never waits for late inputs. A player whose input for a step hasn't arrived in time
+//! keeps their input of the step before, and since the players play the inputs that the relay
+//! sends them rather than their own, they stay in sync with it all the same.
+//!
+//! Every datagram holds one [`Message`]:
+//!
+//! - A player joins with [`Message::Join`], and is answered with [`Message::Welcome`] and their
+//!   player index, or [`Message::Full`].
+//! - [`Message::Start`] is sent to everyone whenever the level starts over, with the seed of the
+//!   [`GameRng`]. The level starts over when the game is over, and when a player joins in the
+//!   middle of it, so that everyone plays it from the first step.
+//! - The players send their inputs for the coming steps with [`Message::Input`], which also keeps
+//!   them from timing out.
+//! - The relay sends the inputs of the last few steps with [`Message::Steps`] after every step, so
+//!   that a lost datagram doesn't leave a gap in the steps of a player.
+//! - A player leaves with [`Message::Leave`], or by not sending anything for [`PEER_TIMEOUT`].
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::Instant};
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    input::{disconnect_player_inputs, InputSegment, PlayerAction},
+    player::PlayerIndex,
+    rng::GameRng,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    utils::ResetController,
+    GameState,
+};
+
+/// The most players that can join a session
+pub const MAX_PLAYERS: usize = 4;
+
+/// How long a player can go without sending anything before they are dropped from the session
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many of the last steps are sent with every step
+const RESENT_STEPS: usize = 4;
+
+/// How far ahead of the relay the players may send inputs. Inputs for later steps are dropped.
+const MAX_INPUT_LEAD: u32 = 120;
+
+/// The size of the largest message, the inputs of [`RESENT_STEPS`] steps
+const MAX_MESSAGE_SIZE: usize = 6 + RESENT_STEPS * MAX_PLAYERS * INPUT_SIZE;
+
+/// The size of an encoded input: the movement axes and the buttons
+const INPUT_SIZE: usize = 9;
+
+pub struct RelayPlugin {
+    socket: UdpSocket,
+}
+
+impl RelayPlugin {
+    /// Bind the socket of the relay to the address
+    pub fn bind(address: &str) -> anyhow::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        info!("Relay listening on {}", socket.local_addr()?);
+
+        Ok(Self { socket })
+    }
+}
+
+impl Plugin for RelayPlugin {
+    fn build(&self, app: &mut App) {
+        let socket = self
+            .socket
+            .try_clone()
+            .expect("Could not share the relay socket");
+
+        app.insert_resource(Relay {
+            socket,
+            peers: Default::default(),
+            step: 0,
+            history: VecDeque::new(),
+        })
+        .add_system(disconnect_player_inputs)
+        .add_system(receive_messages)
+        .add_enter_system(GameState::MainMenu, start_session)
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::PRE,
+            relay_step.run_in_state(GameState::InGame),
+        );
+    }
+}
+
+/// A message between the relay and a player
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// Sent by a player to join the session
+    Join,
+    /// Sent by the relay to a player that joined, with the index of their player
+    Welcome { player: u8 },
+    /// Sent by the relay to a player that can't join because the session is full
+    Full,
+    /// Sent by the relay when the level starts over, with the seed of the [`GameRng`]
+    Start { seed: u64 },
+    /// Sent by a player with their input for a step
+    Input { step: u32, input: InputSegment },
+    /// Sent by the relay after a step, with the inputs of the players in the last few steps,
+    /// starting with `first_step`. Every step has an input for each of the [`MAX_PLAYERS`]
+    /// players, in the order of their indices.
+    Steps {
+        first_step: u32,
+        steps: Vec<[InputSegment; MAX_PLAYERS]>,
+    },
+    /// Sent by a player to leave the session
+    Leave,
+}
+
+impl Message {
+    const JOIN: u8 = 0;
+    const WELCOME: u8 = 1;
+    const FULL: u8 = 2;
+    const START: u8 = 3;
+    const INPUT: u8 = 4;
+    const STEPS: u8 = 5;
+    const LEAVE: u8 = 6;
+
+    /// Encode the message into the bytes of a datagram
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAX_MESSAGE_SIZE);
+        match self {
+            Message::Join => bytes.push(Self::JOIN),
+            Message::Welcome { player } => bytes.extend([Self::WELCOME, *player]),
+            Message::Full => bytes.push(Self::FULL),
+            Message::Start { seed } => {
+                bytes.push(Self::START);
+                bytes.extend(seed.to_le_bytes());
+            }
+            Message::Input { step, input } => {
+                bytes.push(Self::INPUT);
+                bytes.extend(step.to_le_bytes());
+                write_input(&mut bytes, input);
+            }
+            Message::Steps { first_step, steps } => {
+                bytes.push(Self::STEPS);
+                bytes.extend(first_step.to_le_bytes());
+                bytes.push(steps.len() as u8);
+                for input in steps.iter().flatten() {
+                    write_input(&mut bytes, input);
+                }
+            }
+            Message::Leave => bytes.push(Self::LEAVE),
+        }
+        bytes
+    }
+
+    /// Decode the message in a datagram, if it is a valid message
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&kind, mut rest) = bytes.split_first()?;
+        let message = match kind {
+            Self::JOIN => Message::Join,
+            Self::WELCOME => Message::Welcome {
+                player: take(&mut rest, 1)?[0],
+            },
+            Self::FULL => Message::Full,
+            Self::START => Message::Start {
+                seed: u64::from_le_bytes(take(&mut rest, 8)?.try_into().ok()?),
+            },
+            Self::INPUT => Message::Input {
+                step: u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?),
+                input: read_input(&mut rest)?,
+            },
+            Self::STEPS => {
+                let first_step = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+                let count = take(&mut rest, 1)?[0] as usize;
+                let mut steps = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let mut inputs: [InputSegment; MAX_PLAYERS] = Default::default();
+                    for input in &mut inputs {
+                        *input = read_input(&mut rest)?;
+                    }
+                    steps.push(inputs);
+                }
+                Message::Steps { first_step, steps }
+            }
+            Self::LEAVE => Message::Leave,
+            _ => return None,
+        };
+
+        // Trailing bytes mean that the datagram isn't one of our messages
+        rest.is_empty().then_some(message)
+    }
+}
+
+/// Take the next `len` bytes
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len {
+        return None;
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Some(taken)
+}
+
+/// Write the movement axes of an input, followed by a bit for each of its buttons
+fn write_input(bytes: &mut Vec<u8>, input: &InputSegment) {
+    bytes.extend(input.movement.x.to_le_bytes());
+    bytes.extend(input.movement.y.to_le_bytes());
+    let buttons = [
+        input.attack,
+        input.throw,
+        input.shoot,
+        input.block,
+        input.dodge,
+    ];
+    let bits = buttons
+        .iter()
+        .enumerate()
+        .fold(0u8, |bits, (i, &pressed)| bits | ((pressed as u8) << i));
+    bytes.push(bits);
+}
+
+fn read_input(bytes: &mut &[u8]) -> Option<InputSegment> {
+    let x = f32::from_le_bytes(take(bytes, 4)?.try_into().ok()?);
+    let y = f32::from_le_bytes(take(bytes, 4)?.try_into().ok()?);
+    let bits = take(bytes, 1)?[0];
+    let pressed = |i: usize| bits & (1 << i) != 0;
+
+    Some(InputSegment {
+        // Each input is for a single step
+        steps: 1,
+        movement: Vec2::new(x, y),
+        attack: pressed(0),
+        throw: pressed(1),
+        shoot: pressed(2),
+        block: pressed(3),
+        dodge: pressed(4),
+    })
+}
+
+/// A player in the session
+struct Peer {
+    address: SocketAddr,
+    /// The inputs that the player has sent for the coming steps
+    inputs: BTreeMap<u32, InputSegment>,
+    /// The input that the player was given in the last step
+    last_input: InputSegment,
+    /// When the player last sent a message
+    last_heard: Instant,
+}
+
+#[derive(Resource)]
+struct Relay {
+    socket: UdpSocket,
+    /// The players in the session, by player index
+    peers: [Option<Peer>; MAX_PLAYERS],
+    /// The step of the level that is played next
+    step: u32,
+    /// The inputs of the last [`RESENT_STEPS`] steps
+    history: VecDeque<[InputSegment; MAX_PLAYERS]>,
+}
+
+impl Relay {
+    fn send(&self, address: SocketAddr, message: &Message) {
+        if let Err(e) = self.socket.send_to(&message.encode(), address) {
+            warn!(%address, "Could not send to player: {e}");
+        }
+    }
+
+    fn broadcast(&self, message: &Message) {
+        for peer in self.peers.iter().flatten() {
+            self.send(peer.address, message);
+        }
+    }
+
+    fn player_index(&self, address: SocketAddr) -> Option<usize> {
+        self.peers
+            .iter()
+            .position(|peer| peer.as_ref().map_or(false, |peer| peer.address == address))
+    }
+}
+
+/// Handle the messages of the players, and drop the players that timed out
+fn receive_messages(
+    mut commands: Commands,
+    mut relay: ResMut<Relay>,
+    rng: Res<GameRng>,
+    reset_controller: ResetController,
+) {
+    let mut buffer = [0; MAX_MESSAGE_SIZE];
+    let mut restart = false;
+
+    loop {
+        let (len, address) = match relay.socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            // Windows reports datagrams that couldn't be delivered to a player as errors
+            Err(e) => {
+                debug!("Could not receive from player: {e}");
+                continue;
+            }
+        };
+        let Some(message) = Message::decode(&buffer[..len]) else {
+            debug!(%address, "Dropped invalid message");
+            continue;
+        };
+
+        let player = relay.player_index(address);
+        if let Some(peer) = player.and_then(|player| relay.peers[player].as_mut()) {
+            peer.last_heard = Instant::now();
+        }
+
+        match (message, player) {
+            // The welcome may have been lost
+            (Message::Join, Some(player)) => {
+                relay.send(
+                    address,
+                    &Message::Welcome {
+                        player: player as u8,
+                    },
+                );
+            }
+            (Message::Join, None) => {
+                let Some(player) = relay.peers.iter().position(Option::is_none) else {
+                    relay.send(address, &Message::Full);
+                    continue;
+                };
+                info!(%address, player, "Player joined");
+                relay.peers[player] = Some(Peer {
+                    address,
+                    inputs: BTreeMap::new(),
+                    last_input: default(),
+                    last_heard: Instant::now(),
+                });
+                relay.send(
+                    address,
+                    &Message::Welcome {
+                        player: player as u8,
+                    },
+                );
+
+                // Everyone plays the level from the start, with the new player
+                if relay.step > 0 {
+                    restart = true;
+                } else {
+                    relay.send(address, &Message::Start { seed: rng.seed() });
+                }
+            }
+            (Message::Input { step, input }, Some(player)) => {
+                let next_step = relay.step;
+                if (next_step..next_step + MAX_INPUT_LEAD).contains(&step) {
+                    if let Some(peer) = relay.peers[player].as_mut() {
+                        peer.inputs.insert(step, input);
+                    }
+                }
+            }
+            (Message::Leave, Some(player)) => {
+                info!(%address, player, "Player left");
+                relay.peers[player] = None;
+            }
+            // Only players send the other messages, and only the joined players may send inputs
+            _ => debug!(%address, "Dropped unexpected message"),
+        }
+    }
+
+    for (player, peer) in relay.peers.iter_mut().enumerate() {
+        let timed_out = peer
+            .as_ref()
+            .map_or(false, |peer| peer.last_heard.elapsed() > PEER_TIMEOUT);
+        if timed_out {
+            info!(player, "Player timed out");
+            *peer = None;
+        }
+    }
+
+    if restart {
+        reset_controller.reset_world();
+        commands.insert_resource(NextState(GameState::MainMenu));
+    }
+}
+
+/// Start the level over for every player
+fn start_session(mut relay: ResMut<Relay>, rng: Res<GameRng>) {
+    relay.step = 0;
+    relay.history.clear();
+    for peer in relay.peers.iter_mut().flatten() {
+        peer.inputs.clear();
+        peer.last_input = default();
+    }
+
+    relay.broadcast(&Message::Start { seed: rng.seed() });
+}
+
+/// Play the step with the inputs of the players, and send the inputs to all of them
+fn relay_step(
+    mut relay: ResMut<Relay>,
+    mut players: Query<(&PlayerIndex, &mut ActionState<PlayerAction>)>,
+) {
+    let relay = &mut *relay;
+    let step = relay.step;
+
+    let mut inputs: [InputSegment; MAX_PLAYERS] = Default::default();
+    for (peer, input) in relay.peers.iter_mut().zip(&mut inputs) {
+        let Some(peer) = peer else {
+            continue;
+        };
+        // Inputs that arrived too late are of no use anymore
+        peer.inputs = peer.inputs.split_off(&step);
+        if let Some(sent) = peer.inputs.remove(&step) {
+            peer.last_input = sent;
+        }
+        *input = peer.last_input.clone();
+    }
+
+    for (player_index, mut action_state) in &mut players {
+        if let Some(input) = inputs.get(player_index.0) {
+            input.apply(&mut action_state);
+        }
+    }
+
+    if relay.history.len() == RESENT_STEPS {
+        relay.history.pop_front();
+    }
+    relay.history.push_back(inputs);
+    relay.step += 1;
+
+    relay.broadcast(&Message::Steps {
+        first_step: relay.step - relay.history.len() as u32,
+        steps: relay.history.iter().cloned().collect(),
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages_survive_encoding() {
+        let input = InputSegment {
+            steps: 1,
+            movement: Vec2::new(-1.0, 0.5),
+            attack: true,
+            dodge: true,
+            ..default()
+        };
+        let messages = [
+            Message::Join,
+            Message::Welcome { player: 3 },
+            Message::Full,
+            Message::Start { seed: 0xdead_beef },
+            Message::Input {
+                step: 42,
+                input: input.clone(),
+            },
+            Message::Steps {
+                first_step: 7,
+                steps: vec![
+                    Default::default(),
+                    [input.clone(), default(), default(), input],
+                ],
+            },
+            Message::Leave,
+        ];
+
+        for message in messages {
+            let bytes = message.encode();
+            assert!(bytes.len() <= MAX_MESSAGE_SIZE);
+            assert_eq!(Message::decode(&bytes), Some(message));
+        }
+    }
+
+    #[test]
+    fn invalid_messages_are_dropped() {
+        assert_eq!(Message::decode(&[]), None);
+        assert_eq!(Message::decode(&[Message::LEAVE, 0]), None);
+        assert_eq!(Message::decode(&[Message::START, 1, 2]), None);
+        assert_eq!(Message::decode(&[255]), None);
+    }
+}
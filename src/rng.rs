@@ -0,0 +1,95 @@
+//! The random number generator used by the gameplay systems.
+//!
+//! All randomness that affects the game, such as enemy targets, throw velocities, and fighter
+//! palettes, comes from the [`GameRng`] resource instead of `rand::thread_rng()`. The generator is
+//! reset to its seed every time a level is loaded, so a run can be reproduced from the seed and
+//! the player inputs.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+use crate::config::ENGINE_CONFIG;
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = configured_seed();
+        info!(seed, "Initialized game RNG");
+
+        app.insert_resource(GameRng::new(seed));
+    }
+}
+
+/// The seed set in the engine config, or a random one
+pub fn configured_seed() -> u64 {
+    ENGINE_CONFIG.seed.unwrap_or_else(rand::random)
+}
+
+/// The seeded random number generator for the game.
+///
+/// Implements [`RngCore`], so it can be used with all of the [`rand::Rng`] methods.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed the generator was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reset the generator to the start of its sequence
+    pub fn restart(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn restart_repeats_sequence() {
+        let mut rng = GameRng::new(42);
+        let first = (0..8).map(|_| rng.gen::<u32>()).collect::<Vec<_>>();
+
+        rng.restart();
+        let second = (0..8).map(|_| rng.gen::<u32>()).collect::<Vec<_>>();
+
+        assert_eq!(first, second);
+        assert_eq!(first, {
+            let mut other = GameRng::new(42);
+            (0..8).map(|_| other.gen::<u32>()).collect::<Vec<_>>()
+        });
+    }
+}
@@ -0,0 +1,235 @@
+//! Shadows that the fighters cast on the ground under them.
+//!
+//! Fighters rise by moving up the screen, so their ground position isn't their position while they
+//! jump, or while they are carried over the head of another fighter. Their shadows stay where the
+//! fighters would be standing, and shrink and fade the higher the fighters rise, which makes it
+//! easier to tell where a fighter in the air is going to land. A shadow is sized after the hurtbox
+//! of its fighter, unless its [`ShadowMeta`](crate::metadata::ShadowMeta) gives it a size.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    transform::TransformSystem,
+};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    camera::YSort,
+    consts,
+    fighter::Stats,
+    fighter_state::{Airborne, Flopping, Grabbed, GroundSlam, Thrown},
+    flying::Flying,
+    metadata::FighterMeta,
+    timestep::InterpolateTransformsSystem,
+    GameState,
+};
+
+/// How opaque the shadow of a fighter on the ground is
+const SHADOW_OPACITY: f32 = 0.35;
+
+/// The height at which the shadows of the fighters stop shrinking
+const SHADOW_MIN_SCALE_HEIGHT: f32 = 150.;
+
+/// How small the shadow of a fighter gets when it's high up
+const SHADOW_MIN_SCALE: f32 = 0.5;
+
+/// The width of the shadow texture, which is twice as wide as it's high
+const SHADOW_TEXTURE_WIDTH: u32 = 64;
+
+pub struct ShadowPlugin;
+
+impl Plugin for ShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(create_shadow_texture)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    // The shadows follow the interpolated transforms of their fighters
+                    .after(InterpolateTransformsSystem)
+                    .before(TransformSystem::TransformPropagate)
+                    .with_system(add_shadows)
+                    .with_system(update_shadows)
+                    .into(),
+            );
+    }
+}
+
+/// The soft ellipse that the shadows are drawn with
+#[derive(Resource, Deref)]
+struct ShadowTexture(Handle<Image>);
+
+/// Component for the shadow of a fighter, which is a child of the fighter
+#[derive(Component)]
+pub struct Shadow {
+    size: Vec2,
+}
+
+fn create_shadow_texture(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let width = SHADOW_TEXTURE_WIDTH;
+    let height = SHADOW_TEXTURE_WIDTH / 2;
+
+    let data = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            // The position of the pixel in the ellipse, from -1 to 1
+            let position = Vec2::new(
+                (x as f32 + 0.5) / width as f32 * 2.0 - 1.0,
+                (y as f32 + 0.5) / height as f32 * 2.0 - 1.0,
+            );
+            let alpha = shadow_alpha(position.length());
+            [0, 0, 0, (alpha * 255.0).round() as u8]
+        })
+        .collect();
+
+    let mut image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    // Keep the edges of the shadows soft when they are scaled
+    image.sampler_descriptor = ImageSampler::linear();
+
+    commands.insert_resource(ShadowTexture(images.add(image)));
+}
+
+/// The opacity of the shadow texture at `distance` from its center, where its edge is at 1
+fn shadow_alpha(distance: f32) -> f32 {
+    // Fade out over the outer half of the ellipse
+    let t = ((distance - 0.5) / 0.5).clamp(0.0, 1.0);
+    1.0 - t * t * (3.0 - 2.0 * t)
+}
+
+/// Give the fighters that have just been activated their shadows
+fn add_shadows(
+    mut commands: Commands,
+    fighters: Query<(Entity, &Handle<FighterMeta>), Added<Stats>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    shadow_texture: Res<ShadowTexture>,
+) {
+    for (entity, meta_handle) in &fighters {
+        let Some(fighter) = fighter_assets.get(meta_handle) else {
+            continue;
+        };
+        let size = fighter.shadow.as_ref().map_or_else(
+            || Vec2::new(fighter.hurtbox.size.x * 1.2, fighter.hurtbox.size.x * 0.4),
+            |shadow| shadow.size,
+        );
+
+        let shadow = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1.0, 1.0, 1.0, SHADOW_OPACITY),
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    texture: shadow_texture.clone(),
+                    ..default()
+                },
+                Shadow { size },
+                Name::new("Shadow"),
+            ))
+            .id();
+        commands.entity(entity).add_child(shadow);
+    }
+}
+
+/// Keep the shadows on the ground under their fighters
+fn update_shadows(
+    mut shadows: Query<(&mut Transform, &mut Sprite, &Shadow, &Parent)>,
+    fighters: Query<
+        (
+            &Transform,
+            &YSort,
+            Option<&Flopping>,
+            Option<&GroundSlam>,
+            Option<&Grabbed>,
+            Option<&Thrown>,
+            Option<&Airborne>,
+            Option<&Flying>,
+        ),
+        Without<Shadow>,
+    >,
+) {
+    for (mut transform, mut sprite, shadow, parent) in &mut shadows {
+        let Ok((
+            fighter_transform,
+            ysort,
+            flopping,
+            ground_slam,
+            grabbed,
+            thrown,
+            airborne,
+            flying,
+        )) = fighters.get(parent.get())
+        else {
+            continue;
+        };
+        let y = fighter_transform.translation.y;
+
+        // The fighters that jump up land back where they started
+        let ground_y = match (flopping, ground_slam) {
+            (Some(flopping), _) if flopping.has_started => Some(flopping.start_y),
+            (_, Some(ground_slam)) if ground_slam.has_started => Some(ground_slam.start_y),
+            _ => None,
+        };
+        let height = match (ground_y, grabbed, thrown, airborne) {
+            (Some(ground_y), _, _, _) => y - ground_y,
+            (None, Some(grabbed), _, _) => grabbed.offset.y,
+            // Thrown fighters fly in an arc above where they will land
+            (None, None, Some(thrown), _) => {
+                thrown.trajectory.height_at(thrown.timer.elapsed_secs())
+            }
+            (None, None, None, Some(airborne)) => airborne.z.height,
+            _ => 0.0,
+        };
+        // Flying fighters do all of that above the height they hover at
+        let height = height + flying.map_or(0.0, |flying| flying.height);
+
+        // The shadows are sorted under all of the fighters, whatever the Z of their fighter is
+        let z = consts::SHADOWS_Z - ysort.z(y);
+        transform.translation = Vec3::new(0.0, -height, z);
+
+        let scale = shadow_scale(height);
+        let size = shadow.size * scale;
+        if sprite.custom_size != Some(size) {
+            sprite.custom_size = Some(size);
+            sprite.color.set_a(SHADOW_OPACITY * scale);
+        }
+    }
+}
+
+/// How big the shadow of a fighter at `height` above the ground is, compared to on the ground
+fn shadow_scale(height: f32) -> f32 {
+    let t = (height / SHADOW_MIN_SCALE_HEIGHT).clamp(0.0, 1.0);
+    1.0 - t * (1.0 - SHADOW_MIN_SCALE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shadows_shrink_as_fighters_rise() {
+        assert_eq!(shadow_scale(0.0), 1.0);
+        assert_eq!(shadow_scale(-10.0), 1.0);
+        assert!((shadow_scale(SHADOW_MIN_SCALE_HEIGHT / 2.0) - 0.75).abs() < 1e-4);
+        assert_eq!(
+            shadow_scale(SHADOW_MIN_SCALE_HEIGHT * 2.0),
+            SHADOW_MIN_SCALE
+        );
+
+        assert_eq!(shadow_alpha(0.0), 1.0);
+        assert_eq!(shadow_alpha(1.0), 0.0);
+        assert!(shadow_alpha(0.75) > 0.0 && shadow_alpha(0.75) < 1.0);
+    }
+}
@@ -0,0 +1,120 @@
+//! Sockets, the named points on the sprites of the fighters that attachments follow, like their
+//! hands.
+//!
+//! The [sockets](crate::metadata::SocketMeta) of a fighter are given in its metadata, for all of
+//! its animations, for whole clips, or for each frame of a clip. The entities with a [`Socketed`]
+//! component are kept at a socket of their parent fighter while it animates and turns around, like
+//! the weapons that the fighters hold. Fighters without the socket keep the attachment at its
+//! offset from their feet, and throw items from [`THROW_ITEM_OFFSET`](consts::THROW_ITEM_OFFSET)
+//! when they don't have a hand.
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    animation::{Animation, Facing},
+    consts,
+    metadata::FighterMeta,
+};
+
+/// The socket for the weapons that the fighters hold, and that they throw items from
+pub const HAND_SOCKET: &str = "hand";
+
+pub struct SocketPlugin;
+
+impl Plugin for SocketPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            follow_sockets.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Component for an attachment that follows a socket of its parent fighter
+#[derive(Component)]
+pub struct Socketed {
+    pub socket: String,
+    /// The offset of the attachment from the socket, for the fighter facing right
+    pub offset: Vec2,
+}
+
+impl Socketed {
+    pub fn hand(offset: Vec2) -> Self {
+        Self {
+            socket: HAND_SOCKET.to_owned(),
+            offset,
+        }
+    }
+}
+
+/// The position of a socket of the fighter during its current animation, for the fighter facing
+/// right, if the fighter has the socket
+pub fn socket_offset(fighter: &FighterMeta, socket: &str, animation: &Animation) -> Option<Vec2> {
+    fighter.sockets.get(socket).map(|socket| {
+        socket.offset(
+            animation.current_animation.as_deref(),
+            animation.current_frame,
+        )
+    })
+}
+
+/// The offset that the fighter throws items from, for the fighter facing right
+pub fn throw_offset(fighter: Option<&FighterMeta>, animation: &Animation) -> Vec2 {
+    fighter
+        .and_then(|fighter| socket_offset(fighter, HAND_SOCKET, animation))
+        .unwrap_or(consts::THROW_ITEM_OFFSET)
+}
+
+fn follow_sockets(
+    mut attachments: Query<(&Socketed, &Parent, &mut Transform)>,
+    fighters: Query<(&Handle<FighterMeta>, &Animation, &Facing)>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    for (socketed, parent, mut transform) in &mut attachments {
+        let Ok((meta_handle, animation, facing)) = fighters.get(parent.get()) else {
+            continue;
+        };
+        let Some(fighter) = fighter_assets.get(meta_handle) else {
+            continue;
+        };
+
+        let socket = socket_offset(fighter, &socketed.socket, animation).unwrap_or_default();
+        let translation = facing.mirror(socket + socketed.offset);
+        // Only write to the transforms that move, so that they aren't marked as changed
+        if transform.translation.truncate() != translation {
+            transform.translation.x = translation.x;
+            transform.translation.y = translation.y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::{SocketMeta, SocketOffsets};
+
+    use super::*;
+
+    #[test]
+    fn sockets_follow_clips_and_frames() {
+        let mut socket = SocketMeta {
+            offset: Vec2::new(10.0, 20.0),
+            ..default()
+        };
+        socket.animations.insert(
+            "running".to_owned(),
+            SocketOffsets::Clip(Vec2::new(12.0, 18.0)),
+        );
+        socket.animations.insert(
+            "attacking".to_owned(),
+            SocketOffsets::Frames(vec![Vec2::new(8.0, 20.0), Vec2::new(20.0, 24.0)]),
+        );
+
+        assert_eq!(socket.offset(None, 0), Vec2::new(10.0, 20.0));
+        assert_eq!(socket.offset(Some("idle"), 3), Vec2::new(10.0, 20.0));
+        assert_eq!(socket.offset(Some("running"), 3), Vec2::new(12.0, 18.0));
+        assert_eq!(socket.offset(Some("attacking"), 0), Vec2::new(8.0, 20.0));
+        assert_eq!(socket.offset(Some("attacking"), 1), Vec2::new(20.0, 24.0));
+        // The frames after the last position stay at it
+        assert_eq!(socket.offset(Some("attacking"), 5), Vec2::new(20.0, 24.0));
+    }
+}
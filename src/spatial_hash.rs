@@ -0,0 +1,134 @@
+//! Broad-phase spatial hash for finding gameplay entities near a position.
+//!
+//! Collisions between attacks and hurtboxes are already broad-phased by rapier, but gameplay
+//! systems that look for things in range, like picking up items, would otherwise have to check
+//! every fighter against every item. The [`SpatialHash`] is rebuilt once every gameplay step, so
+//! those systems can look up only the entities in the grid cells around a position instead.
+
+use bevy::{prelude::*, utils::HashMap};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    item::Item,
+    metadata::FighterMeta,
+    pool::Pooled,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// The size of the spatial hash grid cells, in pixels
+const CELL_SIZE: f32 = 64.0;
+
+pub struct SpatialHashPlugin;
+
+impl Plugin for SpatialHashPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SpatialHash::new(CELL_SIZE))
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::TRANSITIONS,
+                ConditionSet::new()
+                    .label(SpatialHashSystem)
+                    .run_in_state(GameState::InGame)
+                    .with_system(update_spatial_hash)
+                    .into(),
+            );
+    }
+}
+
+/// Label for the system that rebuilds the [`SpatialHash`]
+#[derive(Clone, SystemLabel)]
+pub struct SpatialHashSystem;
+
+/// A uniform grid of the fighters and items in the level, indexed by their translations at the start
+/// of the current gameplay step.
+///
+/// Entities may have been despawned since the hash was built, so the entities returned by its
+/// queries should still be looked up with a [`Query`].
+#[derive(Resource)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: default(),
+        }
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Remove all of the entities, keeping the allocated cells around for the next rebuild
+    pub fn clear(&mut self) {
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+
+    /// Iterate over the entities within `radius` of `position`, along with their positions
+    pub fn query(&self, position: Vec2, radius: f32) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let min = self.cell(position - Vec2::splat(radius));
+        let max = self.cell(position + Vec2::splat(radius));
+
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |(_, other)| other.distance(position) <= radius)
+    }
+}
+
+pub fn update_spatial_hash(
+    mut spatial_hash: ResMut<SpatialHash>,
+    entities: Query<
+        (Entity, &Transform, Option<&Pooled>),
+        Or<(With<Item>, With<Handle<FighterMeta>>)>,
+    >,
+) {
+    spatial_hash.clear();
+    for (entity, transform, pooled) in &entities {
+        // Skip items that are parked in the entity pool
+        if pooled.map(|pooled| pooled.parked).unwrap_or(false) {
+            continue;
+        }
+
+        spatial_hash.insert(entity, transform.translation.truncate());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn query_finds_entities_in_radius() {
+        let mut spatial_hash = SpatialHash::new(10.0);
+        let near = Entity::from_raw(0);
+        let across_cells = Entity::from_raw(1);
+        let far = Entity::from_raw(2);
+        spatial_hash.insert(near, Vec2::new(1.0, 1.0));
+        spatial_hash.insert(across_cells, Vec2::new(-4.0, 2.0));
+        spatial_hash.insert(far, Vec2::new(30.0, 0.0));
+
+        let mut found = spatial_hash
+            .query(Vec2::new(2.0, 0.0), 8.0)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, vec![near, across_cells]);
+
+        spatial_hash.clear();
+        assert_eq!(spatial_hash.query(Vec2::new(2.0, 0.0), 8.0).count(), 0);
+    }
+}
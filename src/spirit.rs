@@ -0,0 +1,295 @@
+//! Spirits, the cursors of the players who are out of a co-op fight.
+//!
+//! While a player is knocked out and the other players are still fighting, they move a small spirit
+//! around the screen with their own controls, until they come back with a life or the fight is
+//! over. A spirit can drop the minor [pickup](SpiritMeta::pickup) of the game's [`SpiritMeta`]
+//! under it with the throw button, or distract the enemies around it with the attack button, so
+//! that they stop going after the players for a moment. Both share a cooldown.
+//!
+//! Spirits are only played with a UI and outside of the attract demo, because they aren't part of
+//! the recorded inputs.
+
+use bevy::prelude::*;
+use bevy_mod_js_scripting::ActiveScripts;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    animation::Facing,
+    camera::camera_half_width,
+    consts,
+    enemy::Enemy,
+    enemy_ai::WalkTarget,
+    fighter_state::Dying,
+    input::PlayerAction,
+    item::ItemBundle,
+    menu_stack::MenuLayer,
+    metadata::{GameMeta, ItemMeta, ItemSpawnMeta, LevelMeta, Settings},
+    platform::Storage,
+    player::{Player, PlayerIndex},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    ui::attract_mode::{self, AttractMode},
+    versus::VersusMatch,
+    GameState,
+};
+
+/// How transparent a spirit is while it's waiting for its cooldown
+const COOLDOWN_ALPHA: f32 = 0.35;
+
+pub struct SpiritPlugin;
+
+impl Plugin for SpiritPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            update_spirits
+                .run_in_state(GameState::InGame)
+                // Versus respawns the players for each round instead
+                .run_unless_resource_exists::<VersusMatch>(),
+        )
+        .add_system(
+            control_spirits
+                .run_in_state(GameState::InGame)
+                .run_in_state(MenuLayer::None)
+                .after(update_spirits),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::PRE,
+            end_distractions.run_in_state(GameState::InGame),
+        );
+    }
+}
+
+/// The spirit of a player who is out of the fight
+#[derive(Component)]
+pub struct Spirit {
+    /// The index of the player that the spirit belongs to
+    pub player: usize,
+    /// Counts down the time until the spirit can drop a pickup or distract the enemies again
+    cooldown: Timer,
+}
+
+/// Keeps the enemy AI from going after the players, while an enemy is distracted by a spirit
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Distracted(Timer);
+
+/// Spawn the spirits of the players who are out of the fight, and despawn them once they are back
+/// or the fight is over
+fn update_spirits(
+    mut commands: Commands,
+    players: Query<&PlayerIndex, With<Player>>,
+    spirits: Query<(Entity, &Spirit)>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    attract_mode: Option<Res<AttractMode>>,
+) {
+    let demo_playing = attract_mode.map_or(false, attract_mode::demo_playing);
+    let meta = match &game.spirits {
+        Some(meta) if !demo_playing && !players.is_empty() => meta,
+        // Nobody is left to help once all of the players are out
+        _ => {
+            for (entity, _) in &spirits {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+    };
+
+    for (entity, spirit) in &spirits {
+        if players.iter().any(|player| player.0 == spirit.player) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let mut settings = None;
+    for i in 0..level.players.len() {
+        let player_out = !players.iter().any(|player| player.0 == i);
+        if !player_out || spirits.iter().any(|(_, spirit)| spirit.player == i) {
+            continue;
+        }
+
+        let settings =
+            settings.get_or_insert_with(|| storage.get::<Settings>(Settings::STORAGE_KEY));
+        let input_map = settings
+            .as_ref()
+            .unwrap_or(&game.default_settings)
+            .player_controls
+            .get_input_map(i);
+
+        let mut cooldown = Timer::from_seconds(meta.cooldown.max(0.0), TimerMode::Once);
+        cooldown.tick(cooldown.duration());
+
+        let position = camera_transform.translation.truncate();
+        commands.spawn((
+            Name::new(format!("Spirit {i}")),
+            Spirit {
+                player: i,
+                cooldown,
+            },
+            SpriteBundle {
+                texture: meta.image.image_handle.clone(),
+                sprite: Sprite {
+                    custom_size: Some(meta.image.image_size),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(consts::SPIRITS_Z)),
+                ..default()
+            },
+            InputManagerBundle {
+                input_map,
+                ..default()
+            },
+        ));
+        debug!(player = i, "Player is out of the fight as a spirit");
+    }
+}
+
+/// Move the spirits around the screen, and drop pickups or distract the enemies with them
+fn control_spirits(
+    mut commands: Commands,
+    mut spirits: Query<(
+        &mut Spirit,
+        &ActionState<PlayerAction>,
+        &mut Transform,
+        &mut Sprite,
+    )>,
+    mut enemies: Query<
+        (Entity, &Transform, &mut Facing),
+        (With<Enemy>, Without<Dying>, Without<Spirit>),
+    >,
+    camera: Query<
+        &Transform,
+        (
+            With<ParallaxCameraComponent>,
+            Without<Spirit>,
+            Without<Enemy>,
+        ),
+    >,
+    game: Res<GameMeta>,
+    windows: Res<Windows>,
+    mut items_assets: ResMut<Assets<ItemMeta>>,
+    mut active_scripts: ResMut<ActiveScripts>,
+    time: Res<Time>,
+) {
+    let Some(meta) = &game.spirits else {
+        return;
+    };
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let half_view = Vec2::new(
+        camera_half_width(&game, windows.primary()),
+        game.camera_height as f32 / 2.0,
+    );
+
+    for (mut spirit, action_state, mut transform, mut sprite) in &mut spirits {
+        let movement = action_state
+            .clamped_axis_pair(PlayerAction::Move)
+            .map_or(Vec2::ZERO, |axis| axis.xy());
+        let position =
+            transform.translation.truncate() + movement * meta.speed * time.delta_seconds();
+        let position = clamp_to_view(
+            position,
+            camera_transform.translation.truncate(),
+            half_view - meta.image.image_size / 2.0,
+        );
+        transform.translation = position.extend(consts::SPIRITS_Z);
+
+        spirit.cooldown.tick(time.delta());
+        let ready = spirit.cooldown.finished();
+        sprite.color.set_a(if ready { 1.0 } else { COOLDOWN_ALPHA });
+        if !ready {
+            continue;
+        }
+
+        if action_state.just_pressed(PlayerAction::Throw)
+            && items_assets.contains(&meta.pickup_handle)
+        {
+            // The pickup is dropped on the floor under the spirit
+            let floor_y = position.y.clamp(consts::MIN_Y, consts::MAX_Y);
+            let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
+            let item_spawn_meta = ItemSpawnMeta {
+                location: Vec3::new(position.x, floor_y, consts::ITEM_LAYER) - ground_offset,
+                item: String::new(),
+                item_handle: meta.pickup_handle.clone(),
+            };
+            let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+            ItemBundle::spawn(
+                item_commands,
+                &item_spawn_meta,
+                &mut items_assets,
+                &mut active_scripts,
+            );
+            spirit.cooldown.reset();
+        } else if action_state.just_pressed(PlayerAction::Attack) {
+            for (entity, enemy_transform, mut facing) in &mut enemies {
+                let enemy_position = enemy_transform.translation.truncate();
+                if enemy_position.distance(position) > meta.distract_radius {
+                    continue;
+                }
+
+                // The enemies turn to look at the spirit instead of going after the players
+                *facing = if position.x < enemy_position.x {
+                    Facing::Left
+                } else {
+                    Facing::Right
+                };
+                commands
+                    .entity(entity)
+                    .remove::<WalkTarget>()
+                    .insert(Distracted(Timer::from_seconds(
+                        meta.distract_time.max(0.0),
+                        TimerMode::Once,
+                    )));
+            }
+            spirit.cooldown.reset();
+        }
+    }
+}
+
+/// Get the position of a spirit kept inside of the view of the camera, where `half_size` is half
+/// of the area that the spirit can move in
+fn clamp_to_view(position: Vec2, camera_position: Vec2, half_size: Vec2) -> Vec2 {
+    let half_size = half_size.max(Vec2::ZERO);
+    position.clamp(camera_position - half_size, camera_position + half_size)
+}
+
+fn end_distractions(mut commands: Commands, mut enemies: Query<(Entity, &mut Distracted)>) {
+    for (entity, mut distracted) in &mut enemies {
+        if distracted.0.tick(GAMEPLAY_STEP).finished() {
+            commands.entity(entity).remove::<Distracted>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spirits_stay_in_view() {
+        let camera = Vec2::new(100.0, 0.0);
+        let half_size = Vec2::new(50.0, 20.0);
+
+        assert_eq!(
+            clamp_to_view(Vec2::new(120.0, 10.0), camera, half_size),
+            Vec2::new(120.0, 10.0)
+        );
+        assert_eq!(
+            clamp_to_view(Vec2::new(0.0, 30.0), camera, half_size),
+            Vec2::new(50.0, 20.0)
+        );
+        // A view smaller than the spirit keeps it at the center
+        assert_eq!(
+            clamp_to_view(Vec2::new(0.0, 0.0), camera, Vec2::splat(-5.0)),
+            camera
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! The shared texture atlas that item sprites are packed into.
+//!
+//! Bevy draws consecutive sprites that use the same texture in a single batch, but sprites are
+//! drawn in depth order, so items with different images spread around the level break the batches
+//! up. The item images are packed into one shared atlas as they are loaded, so that all of the item
+//! sprites on screen can be drawn together, however they are sorted.
+//!
+//! Use [`ItemSprites`] to get the atlas and sprite for an item image.
+
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::HandleId,
+    ecs::system::SystemParam,
+    math::Rect,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    sprite::DynamicTextureAtlasBuilder,
+    utils::HashMap,
+};
+
+use crate::metadata::{ImageMeta, ItemMeta};
+
+/// The width and height of the shared atlas texture
+const ATLAS_SIZE: u32 = 1024;
+
+/// The texture format of the shared atlas. Images in other formats aren't packed into it.
+const ATLAS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// The space between the packed images, so that neighboring images don't bleed into each other
+const ATLAS_PADDING: i32 = 1;
+
+pub struct SpriteAtlasPlugin;
+
+impl Plugin for SpriteAtlasPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ItemAtlas>()
+            .add_system_to_stage(CoreStage::PreUpdate, pack_item_images);
+    }
+}
+
+/// The shared atlas for item images
+#[derive(Resource)]
+pub struct ItemAtlas {
+    atlas: Handle<TextureAtlas>,
+    builder: DynamicTextureAtlasBuilder,
+    /// The atlas and index of every image that has been looked up, by image handle.
+    ///
+    /// Images that couldn't be packed into the shared atlas get an atlas of their own.
+    sprites: HashMap<HandleId, (Handle<TextureAtlas>, usize)>,
+}
+
+impl FromWorld for ItemAtlas {
+    fn from_world(world: &mut World) -> Self {
+        let size = Extent3d {
+            width: ATLAS_SIZE,
+            height: ATLAS_SIZE,
+            depth_or_array_layers: 1,
+        };
+        let image = Image::new_fill(size, TextureDimension::D2, &[0, 0, 0, 0], ATLAS_FORMAT);
+        let image = world.resource_mut::<Assets<Image>>().add(image);
+
+        let atlas = TextureAtlas::new_empty(image, Vec2::splat(ATLAS_SIZE as f32));
+        let atlas = world.resource_mut::<Assets<TextureAtlas>>().add(atlas);
+
+        Self {
+            atlas,
+            builder: DynamicTextureAtlasBuilder::new(Vec2::splat(ATLAS_SIZE as f32), ATLAS_PADDING),
+            sprites: default(),
+        }
+    }
+}
+
+/// System parameter used to get the sprites for item images from the [`ItemAtlas`]
+#[derive(SystemParam)]
+pub struct ItemSprites<'w, 's> {
+    item_atlas: ResMut<'w, ItemAtlas>,
+    texture_atlases: ResMut<'w, Assets<TextureAtlas>>,
+    images: ResMut<'w, Assets<Image>>,
+    #[system_param(ignore)]
+    _phantom: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ItemSprites<'w, 's> {
+    /// Get the atlas and the sprite to draw the image with, packing the image into the shared atlas
+    /// if it hasn't been yet
+    pub fn get(&mut self, image: &ImageMeta) -> (Handle<TextureAtlas>, TextureAtlasSprite) {
+        let id = image.image_handle.id;
+        if let Some((atlas, index)) = self.item_atlas.sprites.get(&id) {
+            return (atlas.clone(), TextureAtlasSprite::new(*index));
+        }
+
+        // The image hasn't loaded yet, so draw it from an atlas of its own for now
+        let Some(texture) = self.images.get(&image.image_handle) else {
+            return (self.single_image_atlas(image), TextureAtlasSprite::new(0));
+        };
+
+        let texture = texture.clone();
+        let shared_atlas = self.item_atlas.atlas.clone();
+        let packed_index = if texture.texture_descriptor.format == ATLAS_FORMAT {
+            let atlas = self
+                .texture_atlases
+                .get_mut(&shared_atlas)
+                .expect("Shared item atlas missing");
+            self.item_atlas
+                .builder
+                .add_texture(atlas, &mut self.images, &texture)
+        } else {
+            None
+        };
+
+        let sprite = match packed_index {
+            Some(index) => (shared_atlas, index),
+            None => {
+                warn!(image = ?image.image, "Could not pack item image into the shared atlas");
+                (self.single_image_atlas(image), 0)
+            }
+        };
+        self.item_atlas.sprites.insert(id, sprite.clone());
+
+        (sprite.0, TextureAtlasSprite::new(sprite.1))
+    }
+
+    fn single_image_atlas(&mut self, image: &ImageMeta) -> Handle<TextureAtlas> {
+        let mut atlas = TextureAtlas::new_empty(image.image_handle.clone(), image.image_size);
+        atlas.add_texture(Rect {
+            min: Vec2::ZERO,
+            max: image.image_size,
+        });
+        self.texture_atlases.add(atlas)
+    }
+}
+
+/// Pack the images of items into the shared atlas as soon as they load, before they are spawned
+fn pack_item_images(
+    mut pending: Local<Vec<Handle<ItemMeta>>>,
+    mut events: EventReader<AssetEvent<ItemMeta>>,
+    items_assets: Res<Assets<ItemMeta>>,
+    mut item_sprites: ItemSprites,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } = event {
+            pending.push(handle.clone_weak());
+        }
+    }
+
+    pending.retain(|handle| {
+        let Some(item) = items_assets.get(handle) else {
+            return false;
+        };
+        if !item_sprites.images.contains(&item.image.image_handle) {
+            return true;
+        }
+
+        item_sprites.get(&item.image);
+        false
+    });
+}
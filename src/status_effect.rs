@@ -0,0 +1,379 @@
+//! Status effects, like burning or being frozen, that hits leave on the fighters they hit for a
+//! while.
+//!
+//! The attacks and thrown items with a [`StatusEffectMeta`] put their effect on the players and
+//! enemies they hit, as do the weapons coated with fire. Burns and poison hurt the fighter every
+//! second, and poison slows it down as well. Stuns keep the fighter in hit stun until they wear
+//! off, and freezing also stops it in place with its animation frozen. Being hit with an effect
+//! that the fighter already has starts it over.
+//!
+//! The effects tint the fighters they are on, and the HUD shows the effects on the players under
+//! their life bars, in the colors of the effects.
+
+use std::{mem, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::{Attack, AttackDamageSystem},
+    coating::CoatedAttack,
+    combat_events::HitLanded,
+    damage::{FighterDamage, Health},
+    fighter_state::{Dying, HitStun, StateTransition, StateTransitionIntents},
+    metadata::{StatusEffectKind, StatusEffectMeta},
+    movement::{ForceSystems, LinearVelocity},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    tint::{Tint, TintSource, BURNING_COLOR, FROZEN_COLOR, POISONED_COLOR},
+    GameState,
+};
+
+/// The color of the HUD icon of a stun, which doesn't tint the fighter
+const STUNNED_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+
+/// The tints of the effects that tint the fighters they are on
+const EFFECT_TINTS: [(TintSource, Color); 3] = [
+    (TintSource::Burning, BURNING_COLOR),
+    (TintSource::Poisoned, POISONED_COLOR),
+    (TintSource::Frozen, FROZEN_COLOR),
+];
+
+pub struct StatusEffectPlugin;
+
+impl Plugin for StatusEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::PRE,
+            stun_fighters.run_in_state(GameState::InGame),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::MOVEMENT,
+            slow_fighters
+                .run_in_state(GameState::InGame)
+                .before(ForceSystems),
+        )
+        .add_fixed_timestep_system_set(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::COLLISIONS,
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                .after(AttackDamageSystem)
+                .with_system(apply_status_effects)
+                .with_system(update_status_effects.after(apply_status_effects))
+                .into(),
+        );
+    }
+}
+
+/// Component for a fighter with status effects on it
+#[derive(Component, Default)]
+pub struct StatusEffects(Vec<StatusEffect>);
+
+/// Marker for the fighters that are frozen, whose animations don't play
+#[derive(Component)]
+pub struct Frozen;
+
+/// A status effect on a fighter, which lasts until its timer finishes
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    timer: Timer,
+    damage_timer: Timer,
+}
+
+impl StatusEffect {
+    pub fn new(meta: &StatusEffectMeta) -> Self {
+        Self {
+            kind: meta.kind,
+            timer: Timer::from_seconds(meta.duration.max(0.0), TimerMode::Once),
+            damage_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        }
+    }
+
+    /// Last for a little longer, returning the damage the effect has just dealt
+    fn tick(&mut self, delta: Duration) -> i32 {
+        self.timer.tick(delta);
+        self.damage_timer.tick(delta);
+        let damage_per_second = match self.kind {
+            StatusEffectKind::Burn { damage_per_second }
+            | StatusEffectKind::Poison {
+                damage_per_second, ..
+            } => damage_per_second,
+            StatusEffectKind::Freeze | StatusEffectKind::Stun => 0,
+        };
+        self.damage_timer.times_finished_this_tick() as i32 * damage_per_second
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.timer.finished()
+    }
+
+    /// The color that the effect tints the fighter with, and that its HUD icon is shown in
+    pub fn color(&self) -> Color {
+        match self.kind {
+            StatusEffectKind::Burn { .. } => BURNING_COLOR,
+            StatusEffectKind::Poison { .. } => POISONED_COLOR,
+            StatusEffectKind::Freeze => FROZEN_COLOR,
+            StatusEffectKind::Stun => STUNNED_COLOR,
+        }
+    }
+
+    fn tint_source(&self) -> Option<TintSource> {
+        match self.kind {
+            StatusEffectKind::Burn { .. } => Some(TintSource::Burning),
+            StatusEffectKind::Poison { .. } => Some(TintSource::Poisoned),
+            StatusEffectKind::Freeze => Some(TintSource::Frozen),
+            StatusEffectKind::Stun => None,
+        }
+    }
+}
+
+impl StatusEffects {
+    /// Put an effect on the fighter, starting it over if the fighter already has an effect of the
+    /// same kind
+    pub fn apply(&mut self, meta: &StatusEffectMeta) {
+        let effect = StatusEffect::new(meta);
+        let kind = mem::discriminant(&meta.kind);
+        match self
+            .0
+            .iter_mut()
+            .find(|other| mem::discriminant(&other.kind) == kind)
+        {
+            Some(other) => *other = effect,
+            None => self.0.push(effect),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// How fast the effects let the fighter move, from `0.0` when it can't move to `1.0` at its
+    /// full speed
+    pub fn speed_scale(&self) -> f32 {
+        self.iter()
+            .map(|effect| match effect.kind {
+                StatusEffectKind::Poison { slowdown, .. } => 1.0 - slowdown.clamp(0.0, 1.0),
+                StatusEffectKind::Freeze => 0.0,
+                StatusEffectKind::Burn { .. } | StatusEffectKind::Stun => 1.0,
+            })
+            .product()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.iter()
+            .any(|effect| matches!(effect.kind, StatusEffectKind::Freeze))
+    }
+
+    /// The time left until the fighter isn't stunned or frozen anymore, in seconds, if it is
+    fn stun_time_left(&self) -> Option<f32> {
+        self.iter()
+            .filter(|effect| {
+                matches!(
+                    effect.kind,
+                    StatusEffectKind::Freeze | StatusEffectKind::Stun
+                )
+            })
+            .map(|effect| effect.timer.remaining_secs())
+            .reduce(f32::max)
+    }
+
+    /// Make the effects last for a little longer, returning the damage they have just dealt, and
+    /// remove the ones that have worn off
+    fn tick(&mut self, delta: Duration) -> i32 {
+        let damage = self.0.iter_mut().map(|effect| effect.tick(delta)).sum();
+        self.0.retain(|effect| !effect.is_finished());
+        damage
+    }
+}
+
+/// Put the status effects of the attacks, and the fire of the coated weapons, on the fighters they
+/// hit
+fn apply_status_effects(
+    mut commands: Commands,
+    mut damage_events: EventReader<HitLanded>,
+    attacks: Query<(Option<&Attack>, Option<&CoatedAttack>)>,
+    mut fighters: Query<Option<&mut StatusEffects>, (With<Health>, Without<Dying>)>,
+) {
+    // The fighters that didn't have any effects yet get theirs once all of the hits are known
+    let mut new_effects = HashMap::<Entity, StatusEffects>::default();
+    for event in damage_events.iter() {
+        let Ok((attack, coated)) = attacks.get(event.damageing_entity) else {
+            continue;
+        };
+        let Ok(mut effects) = fighters.get_mut(event.damaged_entity) else {
+            continue;
+        };
+
+        let attack_effect = attack.and_then(|attack| attack.status_effect);
+        let coating_effect = coated.and_then(|coated| coated.status_effect());
+        for meta in [attack_effect, coating_effect].iter().flatten() {
+            match &mut effects {
+                Some(effects) => effects.apply(meta),
+                None => new_effects
+                    .entry(event.damaged_entity)
+                    .or_default()
+                    .apply(meta),
+            }
+        }
+    }
+
+    for (entity, effects) in new_effects {
+        commands.entity(entity).insert(effects);
+    }
+}
+
+/// Hurt the fighters with the effects that hurt over time, tint them with their effects, and remove
+/// the effects once they wear off
+fn update_status_effects(
+    mut commands: Commands,
+    mut fighters: ParamSet<(
+        Query<(
+            Entity,
+            &mut StatusEffects,
+            Option<&mut Tint>,
+            Option<&Dying>,
+            Option<&Frozen>,
+        )>,
+        FighterDamage,
+    )>,
+) {
+    let mut hits = Vec::new();
+    for (entity, mut effects, mut tint, dying, frozen) in &mut fighters.p0() {
+        let damage = effects.tick(GAMEPLAY_STEP);
+        // The effects end with the fight of the fighter
+        if dying.is_some() {
+            effects.0.clear();
+        }
+
+        if let Some(tint) = &mut tint {
+            for (source, color) in EFFECT_TINTS {
+                if effects
+                    .iter()
+                    .any(|effect| effect.tint_source() == Some(source))
+                {
+                    tint.set(source, color);
+                } else {
+                    tint.clear(source);
+                }
+            }
+        }
+        match (effects.is_frozen(), frozen.is_some()) {
+            (true, false) => {
+                commands.entity(entity).insert(Frozen);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<Frozen>();
+            }
+            _ => (),
+        }
+        if effects.is_empty() {
+            commands.entity(entity).remove::<StatusEffects>();
+        }
+        if damage == 0 || dying.is_some() {
+            continue;
+        }
+
+        // The effects that hurt over time don't stun the fighters, they only hurt them
+        hits.push(HitLanded {
+            damage_velocity: Vec2::ZERO,
+            damageing_entity: entity,
+            damaged_entity: entity,
+            damage,
+            hitstun_duration: 0.0,
+            critical: false,
+        });
+    }
+
+    let mut fighter_damage = fighters.p1();
+    for hit in hits {
+        fighter_damage.deal(hit);
+    }
+}
+
+/// Keep the stunned and frozen fighters in hit stun until their effects wear off
+fn stun_fighters(
+    mut fighters: Query<
+        (&StatusEffects, &mut StateTransitionIntents),
+        (Without<HitStun>, Without<Dying>),
+    >,
+) {
+    for (effects, mut intents) in &mut fighters {
+        let Some(time_left) = effects.stun_time_left() else {
+            continue;
+        };
+        intents.push_back(StateTransition::new(
+            HitStun {
+                pushback: Vec2::ZERO,
+                timer: Timer::from_seconds(time_left, TimerMode::Once),
+            },
+            HitStun::PRIORITY,
+            false,
+        ));
+    }
+}
+
+/// Slow down the poisoned fighters, and stop the frozen ones
+fn slow_fighters(mut fighters: Query<(&StatusEffects, &mut LinearVelocity)>) {
+    for (effects, mut velocity) in &mut fighters {
+        **velocity *= effects.speed_scale();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn burning_hurts_every_second_until_it_goes_out() {
+        let mut effects = StatusEffects::default();
+        let burn = StatusEffectMeta {
+            kind: StatusEffectKind::Burn {
+                damage_per_second: 4,
+            },
+            duration: 2.5,
+        };
+        effects.apply(&burn);
+
+        let damage = (0..10)
+            .map(|_| effects.tick(Duration::from_millis(200)))
+            .sum::<i32>();
+        assert_eq!(damage, 8);
+        assert!(!effects.is_empty());
+
+        // Being set on fire again starts the fire over, instead of burning twice as hot
+        effects.apply(&burn);
+        assert_eq!(effects.iter().count(), 1);
+        effects.tick(Duration::from_millis(2000));
+        assert!(!effects.is_empty());
+        effects.tick(Duration::from_millis(500));
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn poison_and_freezing_slow_fighters_down() {
+        let mut effects = StatusEffects::default();
+        effects.apply(&StatusEffectMeta {
+            kind: StatusEffectKind::Poison {
+                damage_per_second: 1,
+                slowdown: 0.25,
+            },
+            duration: 2.0,
+        });
+        assert_eq!(effects.speed_scale(), 0.75);
+        assert_eq!(effects.stun_time_left(), None);
+
+        effects.apply(&StatusEffectMeta {
+            kind: StatusEffectKind::Freeze,
+            duration: 1.0,
+        });
+        assert!(effects.is_frozen());
+        assert_eq!(effects.speed_scale(), 0.0);
+        assert_eq!(effects.stun_time_left(), Some(1.0));
+    }
+}
@@ -0,0 +1,196 @@
+//! Loading the sections of long levels while they are played.
+//!
+//! Levels with [`LevelMeta::streaming`](crate::metadata::LevelMeta::streaming) set only load the
+//! enemies and items in their first section before they start. The rest of the sections are loaded
+//! when the camera gets close to them, and their assets are released again once the camera has
+//! passed them and their enemies have been defeated, so that long levels start quickly and don't
+//! keep every fighter in memory.
+
+use bevy::{asset::LoadState, prelude::*};
+use bevy_mod_js_scripting::ActiveScripts;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    camera::camera_half_width,
+    enemy::{Boss, Enemy, EnemyBundle},
+    item::ItemBundle,
+    metadata::{FighterMeta, GameMeta, ItemKind, ItemMeta, LevelSectionMeta, LevelStreamingMeta},
+    GameState,
+};
+
+pub struct StreamingPlugin;
+
+impl Plugin for StreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                .run_if_resource_exists::<LevelStreaming>()
+                .with_system(stream_level_sections)
+                .into(),
+        );
+    }
+}
+
+/// The sections of the current level that are loaded while it's played.
+///
+/// This is inserted when a level with streaming enabled is loaded, and holds the handles to the
+/// assets of the loaded sections.
+#[derive(Resource)]
+pub struct LevelStreaming {
+    preload_distance: f32,
+    sections: Vec<StreamedSection>,
+}
+
+impl LevelStreaming {
+    pub fn new(meta: &LevelStreamingMeta) -> Self {
+        Self {
+            preload_distance: meta.preload_distance,
+            sections: meta
+                .sections
+                .iter()
+                .map(|section| StreamedSection {
+                    meta: section.clone(),
+                    state: SectionState::Pending,
+                    enemies: Vec::new(),
+                    fighters: Vec::new(),
+                    items: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+struct StreamedSection {
+    meta: LevelSectionMeta,
+    state: SectionState,
+    /// The spawned enemies of the section
+    enemies: Vec<Entity>,
+    fighters: Vec<Handle<FighterMeta>>,
+    items: Vec<Handle<ItemMeta>>,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum SectionState {
+    /// The camera hasn't gotten close to the section yet
+    Pending,
+    /// The assets of the section are loading.
+    ///
+    /// The enemies are spawned as soon as the section starts loading, so that they hold the players
+    /// at the section's stop points, and become active once their fighters have loaded. The items
+    /// are spawned once all of them have loaded.
+    Loading,
+    /// The items of the section have been spawned
+    Loaded,
+    /// The camera has passed the section and its enemies have been defeated, so its handles have
+    /// been dropped
+    Unloaded,
+}
+
+fn stream_level_sections(
+    mut commands: Commands,
+    mut streaming: ResMut<LevelStreaming>,
+    asset_server: Res<AssetServer>,
+    mut items_assets: ResMut<Assets<ItemMeta>>,
+    mut active_scripts: ResMut<ActiveScripts>,
+    game: Res<GameMeta>,
+    windows: Res<Windows>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let half_view_width = camera_half_width(&game, windows.primary());
+    let view_left = camera_transform.translation.x - half_view_width;
+    let load_edge = camera_transform.translation.x + half_view_width + streaming.preload_distance;
+
+    for section in &mut streaming.sections {
+        match section.state {
+            SectionState::Pending => {
+                if section.meta.start_x > load_edge {
+                    continue;
+                }
+                debug!(start_x = section.meta.start_x, "Loading level section");
+
+                for enemy in &section.meta.enemies {
+                    let fighter_handle = asset_server.load(&enemy.fighter);
+                    let mut enemy = enemy.clone();
+                    enemy.fighter_handle = fighter_handle.clone();
+
+                    let mut ec = commands.spawn(EnemyBundle::new(&enemy));
+                    if enemy.boss {
+                        ec.insert(Boss::default());
+                    }
+
+                    section.enemies.push(ec.id());
+                    section.fighters.push(fighter_handle);
+                }
+                section.items = section
+                    .meta
+                    .items
+                    .iter()
+                    .map(|item| asset_server.load(&item.item))
+                    .collect();
+
+                section.state = SectionState::Loading;
+            }
+            SectionState::Loading => {
+                // Items with missing assets are skipped instead of holding up the rest
+                let loaded = section.items.iter().all(|handle| {
+                    asset_server.get_load_state(handle) == LoadState::Failed
+                        || item_loaded(&items_assets, handle)
+                });
+                if !loaded {
+                    continue;
+                }
+
+                for (item_spawn_meta, item_handle) in section.meta.items.iter().zip(&section.items)
+                {
+                    if !item_loaded(&items_assets, item_handle) {
+                        warn!(item = ?item_spawn_meta.item, "Could not load streamed item");
+                        continue;
+                    }
+
+                    let mut item_spawn_meta = item_spawn_meta.clone();
+                    item_spawn_meta.item_handle = item_handle.clone();
+
+                    let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+                    ItemBundle::spawn(
+                        item_commands,
+                        &item_spawn_meta,
+                        &mut items_assets,
+                        &mut active_scripts,
+                    );
+                }
+
+                section.state = SectionState::Loaded;
+            }
+            SectionState::Loaded => {
+                let passed = view_left > section.meta.end_x;
+                if !passed || section.enemies.iter().any(|&enemy| enemies.contains(enemy)) {
+                    continue;
+                }
+                debug!(start_x = section.meta.start_x, "Unloading level section");
+
+                // The assets are freed once nothing else, like items that are still lying on the
+                // ground, uses them
+                section.enemies.clear();
+                section.fighters.clear();
+                section.items.clear();
+                section.state = SectionState::Unloaded;
+            }
+            SectionState::Unloaded => (),
+        }
+    }
+}
+
+/// Whether the item, and the item that it drops if it's a box, have loaded
+fn item_loaded(items_assets: &Assets<ItemMeta>, handle: &Handle<ItemMeta>) -> bool {
+    match items_assets.get(handle).map(|item| &item.kind) {
+        Some(ItemKind::BreakableBox { item_handle, .. }) => items_assets.contains(item_handle),
+        Some(_) => true,
+        None => false,
+    }
+}
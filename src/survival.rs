@@ -0,0 +1,404 @@
+//! Survival mode, where the players fight escalating waves of enemies for as long as they can.
+//!
+//! Survival is started from the main menu, and is played in the arena of the game's
+//! [`SurvivalMeta`]. Once a wave has been defeated, the next one is spawned at the edges of the
+//! screen, with more enemies that are picked from more of the [survival
+//! enemies](SurvivalMeta::enemies). Defeating enemies and clearing waves scores points, and once
+//! the players have been defeated, the score is submitted to the [`HighScores`].
+//!
+//! The daily challenge is a survival run with a seed that is picked from the date, so that it
+//! plays out the same for every player on that day. It can be attempted once a day, and has its
+//! own high scores.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assist::Assists,
+    camera::camera_half_width,
+    consts,
+    director::Director,
+    enemy::{Enemy, EnemyBundle},
+    metadata::{FighterSpawnMeta, GameMeta, LevelHandle, LevelMeta, SurvivalMeta},
+    platform::{self, Storage},
+    rng::{self, GameRng},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// How long the players get to catch their breath before the next wave
+const WAVE_DELAY: Duration = Duration::from_secs(3);
+
+/// How far outside of the screen the enemies of a wave are spawned
+const SPAWN_MARGIN: f32 = 40.0;
+
+/// The number of scores kept in the [`HighScores`]
+pub const MAX_HIGH_SCORES: usize = 10;
+
+pub struct SurvivalPlugin;
+
+impl Plugin for SurvivalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, finish_survival)
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                spawn_waves
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<SurvivalRun>(),
+            );
+    }
+}
+
+/// Start survival in the arena of the game's survival mode
+pub fn start_survival(
+    commands: &mut Commands,
+    levels: &mut Assets<LevelMeta>,
+    survival: &SurvivalMeta,
+) {
+    let Some(level) = levels.get(&survival.level_handle) else {
+        warn!("The survival level hasn't loaded");
+        return;
+    };
+    let mut level = level.clone();
+
+    // The enemies only come in waves
+    level.enemies.clear();
+    level.waves.clear();
+    level.streaming = None;
+    level.stop_points.clear();
+
+    commands.insert_resource(LevelHandle(levels.add(level)));
+    commands.insert_resource(SurvivalRun::default());
+    commands.remove_resource::<RunResult>();
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+/// Start the daily challenge, if it hasn't been attempted today yet.
+///
+/// The attempt is used up as soon as the run starts, so that it can't be retried by quitting.
+pub fn start_daily_challenge(
+    commands: &mut Commands,
+    levels: &mut Assets<LevelMeta>,
+    survival: &SurvivalMeta,
+    storage: &mut Storage,
+) {
+    let day = platform::current_day();
+    let mut daily_challenge = storage
+        .get::<DailyChallenge>(DailyChallenge::STORAGE_KEY)
+        .unwrap_or_default();
+    if daily_challenge.attempted_on(day) {
+        return;
+    }
+    daily_challenge.last_attempt = Some(day);
+    storage.set(DailyChallenge::STORAGE_KEY, &daily_challenge);
+    storage.save();
+
+    start_survival(commands, levels, survival);
+    commands.insert_resource(SurvivalRun {
+        daily_challenge: Some(day),
+        ..default()
+    });
+    commands.insert_resource(GameRng::new(DailyChallenge::seed(day)));
+}
+
+/// The state of a survival run, which exists while survival is played
+#[derive(Resource)]
+pub struct SurvivalRun {
+    /// The number of the current wave, starting at 1, or 0 before the first wave
+    pub wave: u32,
+    /// The number of waves that have been cleared
+    pub waves_survived: u32,
+    pub score: u32,
+    /// The day of the daily challenge, if the run is one
+    pub daily_challenge: Option<u64>,
+    /// The enemies of the current wave that haven't been defeated yet
+    wave_enemies: Vec<Entity>,
+    /// The time until the next wave is spawned
+    wave_timer: Timer,
+}
+
+impl Default for SurvivalRun {
+    fn default() -> Self {
+        Self {
+            wave: 0,
+            waves_survived: 0,
+            score: 0,
+            daily_challenge: None,
+            wave_enemies: Vec::new(),
+            wave_timer: Timer::new(WAVE_DELAY, TimerMode::Once),
+        }
+    }
+}
+
+/// The result of the last survival or arcade run, shown in the high scores of the main menu
+#[derive(Resource)]
+pub struct RunResult {
+    pub score: HighScore,
+    /// The place of the score in the [`HighScores`], if it made it in
+    pub rank: Option<usize>,
+    /// The high scores that the score was submitted to
+    pub table: HighScoreTable,
+}
+
+/// The daily challenge attempts of the player, which are kept in the [`Storage`]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct DailyChallenge {
+    /// The day that the daily challenge was last attempted on, as given by
+    /// [`platform::current_day`]
+    pub last_attempt: Option<u64>,
+}
+
+impl DailyChallenge {
+    /// The key used to store the daily challenge attempts in the [`Storage`].
+    pub const STORAGE_KEY: &'static str = "daily-challenge";
+
+    /// Whether the daily challenge of a day has already been attempted
+    pub fn attempted_on(&self, day: u64) -> bool {
+        self.last_attempt == Some(day)
+    }
+
+    /// The seed of the daily challenge of a day, which is the same for every player
+    pub fn seed(day: u64) -> u64 {
+        // Spread consecutive days far apart, so that their seeds don't look alike
+        day.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+}
+
+/// The best survival scores, which are kept in the [`Storage`]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct HighScores(pub Vec<HighScore>);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HighScore {
+    pub score: u32,
+    /// The waves survived in survival, which arcade runs don't have
+    pub waves: u32,
+    /// Whether the run was played with any assists
+    #[serde(default)]
+    pub assisted: bool,
+    /// The continues used in an arcade run
+    #[serde(default)]
+    pub continues: u32,
+}
+
+/// The tables of high scores, which are kept apart in the [`Storage`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HighScoreTable {
+    Survival,
+    DailyChallenge,
+    Arcade,
+}
+
+impl HighScoreTable {
+    /// The key used to store the high scores of the table in the [`Storage`].
+    pub fn storage_key(self) -> &'static str {
+        match self {
+            Self::Survival => HighScores::STORAGE_KEY,
+            Self::DailyChallenge => HighScores::DAILY_CHALLENGE_STORAGE_KEY,
+            Self::Arcade => HighScores::ARCADE_STORAGE_KEY,
+        }
+    }
+
+    /// The localization key of the name of the table
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Survival => "survival",
+            Self::DailyChallenge => "daily-challenge",
+            Self::Arcade => "arcade",
+        }
+    }
+}
+
+impl HighScores {
+    /// The key used to store the high scores in the [`Storage`].
+    pub const STORAGE_KEY: &'static str = "survival-high-scores";
+    /// The key used to store the daily challenge high scores in the [`Storage`].
+    pub const DAILY_CHALLENGE_STORAGE_KEY: &'static str = "daily-challenge-high-scores";
+    /// The key used to store the arcade high scores in the [`Storage`].
+    pub const ARCADE_STORAGE_KEY: &'static str = "arcade-high-scores";
+
+    /// Submit a score to the high scores of a table in the storage, returning its place if it was
+    /// good enough to be kept
+    pub fn submit_to(
+        storage: &mut Storage,
+        table: HighScoreTable,
+        score: HighScore,
+    ) -> Option<usize> {
+        let storage_key = table.storage_key();
+        let mut high_scores = storage.get::<HighScores>(storage_key).unwrap_or_default();
+        let rank = high_scores.submit(score);
+        if rank.is_some() {
+            storage.set(storage_key, &high_scores);
+            storage.save();
+        }
+        rank
+    }
+
+    /// Add a score to the high scores, returning its place if it was good enough to be kept.
+    ///
+    /// A score that ties an older one is placed after it.
+    pub fn submit(&mut self, score: HighScore) -> Option<usize> {
+        let rank = self
+            .0
+            .iter()
+            .position(|other| score.score > other.score)
+            .unwrap_or(self.0.len());
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+
+        self.0.insert(rank, score);
+        self.0.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+}
+
+/// Score the defeated enemies, and spawn the next wave once the current one has been cleared
+fn spawn_waves(
+    mut commands: Commands,
+    mut run: ResMut<SurvivalRun>,
+    mut rng: ResMut<GameRng>,
+    game: Res<GameMeta>,
+    windows: Res<Windows>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    enemies: Query<(), With<Enemy>>,
+    director: Option<Res<Director>>,
+) {
+    let Some(survival) = &game.survival else {
+        return;
+    };
+
+    let wave_size = run.wave_enemies.len();
+    run.wave_enemies.retain(|&enemy| enemies.contains(enemy));
+    let defeated = (wave_size - run.wave_enemies.len()) as u32;
+    run.score += defeated * survival.enemy_score * run.wave;
+
+    if !run.wave_enemies.is_empty() {
+        return;
+    }
+    if defeated > 0 {
+        info!(wave = run.wave, score = run.score, "Survival wave cleared");
+        run.waves_survived = run.wave;
+        run.score += survival.wave_score * run.wave;
+        run.wave_timer.reset();
+    }
+
+    run.wave_timer.tick(GAMEPLAY_STEP);
+    if !run.wave_timer.finished() {
+        return;
+    }
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+
+    run.wave += 1;
+    let size = survival
+        .max_wave_size
+        .min(survival.first_wave_size + survival.wave_size_increase * (run.wave - 1));
+    let size = director.map_or(size, |director| {
+        director.wave_size(size).min(survival.max_wave_size)
+    });
+    let fighters = &survival.enemy_handles[..survival.enemy_handles.len().min(run.wave as usize)];
+    let spawn_distance = camera_half_width(&game, windows.primary()) + SPAWN_MARGIN;
+    debug!(wave = run.wave, size, "Spawning survival wave");
+
+    for _ in 0..size {
+        let Some(fighter_handle) = fighters.choose(&mut *rng) else {
+            return;
+        };
+
+        // Enemies come from both sides of the screen, anywhere on the ground
+        let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+        let location = Vec3::new(
+            camera_transform.translation.x + side * spawn_distance,
+            rng.gen_range(consts::MIN_Y..consts::MAX_Y) - consts::GROUND_Y,
+            0.0,
+        );
+
+        let enemy = commands
+            .spawn(EnemyBundle::new(&FighterSpawnMeta {
+                fighter: default(),
+                fighter_handle: fighter_handle.clone(),
+                location,
+                trip_point_x: f32::MIN,
+                boss: false,
+            }))
+            .id();
+        run.wave_enemies.push(enemy);
+    }
+}
+
+/// Submit the score of the run to the high scores once it's over
+fn finish_survival(
+    mut commands: Commands,
+    run: Option<Res<SurvivalRun>>,
+    assists: Option<Res<Assists>>,
+    mut storage: ResMut<Storage>,
+) {
+    let Some(run) = run else {
+        return;
+    };
+    commands.remove_resource::<SurvivalRun>();
+
+    let score = HighScore {
+        score: run.score,
+        waves: run.waves_survived,
+        assisted: assists.is_some(),
+        continues: 0,
+    };
+    let daily_challenge = run.daily_challenge.is_some();
+    info!(
+        score.score,
+        score.waves, score.assisted, daily_challenge, "Survival run over"
+    );
+
+    let table = if daily_challenge {
+        HighScoreTable::DailyChallenge
+    } else {
+        HighScoreTable::Survival
+    };
+    let rank = HighScores::submit_to(&mut storage, table, score);
+
+    // The daily seed is only for the daily challenge
+    if daily_challenge {
+        commands.insert_resource(GameRng::new(rng::configured_seed()));
+    }
+
+    commands.insert_resource(RunResult { score, rank, table });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn score(score: u32) -> HighScore {
+        HighScore {
+            score,
+            waves: 0,
+            assisted: false,
+            continues: 0,
+        }
+    }
+
+    #[test]
+    fn high_scores_are_kept_in_order() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.submit(score(10)), Some(0));
+        assert_eq!(high_scores.submit(score(30)), Some(0));
+        assert_eq!(high_scores.submit(score(10)), Some(2));
+        assert_eq!(high_scores.submit(score(20)), Some(1));
+        assert_eq!(high_scores.0, [score(30), score(20), score(10), score(10)]);
+
+        for _ in 0..MAX_HIGH_SCORES {
+            high_scores.submit(score(40));
+        }
+        assert_eq!(high_scores.0.len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.submit(score(5)), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Damage teams, which decide whose attacks hurt whom.
+//!
+//! Every fighter is on a [`Team`]: the players are on the [`PLAYERS`](Team::PLAYERS) team, and the
+//! enemies are on the [`ENEMIES`](Team::ENEMIES) team, unless their [`FighterMeta`] puts them in a
+//! faction of their own. The attacks of a fighter, including the projectiles it shoots and throws,
+//! are on its team, and don't hurt the fighters on the same team unless there is friendly fire.
+//! Attacks that don't belong to a fighter, like the explosions of items, hurt everyone.
+//!
+//! The players hit each other in versus, and in co-op if the game has
+//! [`friendly_fire`](crate::metadata::GameMeta::friendly_fire) enabled. The enemies of different
+//! factions always hit each other.
+//!
+//! The [collision groups](Team::attack_groups) of the attacks let them collide with every fighter,
+//! and [`Teams::is_friendly`] decides which of the fighters they hurt.
+//!
+//! [`FighterMeta`]: crate::metadata::FighterMeta
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::prelude::CollisionGroups;
+
+use crate::{
+    collision::BodyLayers, item::ProjectileOwner, metadata::GameMeta, versus::VersusMatch,
+};
+
+pub struct TeamPlugin;
+
+impl Plugin for TeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Team>();
+    }
+}
+
+/// The team that a fighter fights for
+#[derive(Component, Reflect, Default, Clone, PartialEq, Eq, Debug)]
+#[reflect(Component)]
+pub struct Team(pub String);
+
+impl Team {
+    /// The team of the players
+    pub const PLAYERS: &'static str = "players";
+    /// The team of the enemies that aren't in a faction of their own
+    pub const ENEMIES: &'static str = "enemies";
+
+    /// The team of a player
+    pub fn players() -> Self {
+        Self(Self::PLAYERS.to_owned())
+    }
+
+    /// The team of an enemy, which is in the given faction if it has one
+    pub fn enemies(faction: Option<&str>) -> Self {
+        Self(faction.unwrap_or(Self::ENEMIES).to_owned())
+    }
+
+    /// Whether the team is a faction of enemies other than the usual one
+    pub fn is_faction(&self) -> bool {
+        self.0 != Self::PLAYERS && self.0 != Self::ENEMIES
+    }
+
+    /// The collision groups of the attacks made by the fighters on the team, which collide with
+    /// the fighters of every team and leave it to [`Teams::is_friendly`] whom they hurt. Only the
+    /// players' attacks break the items.
+    pub fn attack_groups(&self) -> CollisionGroups {
+        if self.0 == Self::PLAYERS {
+            CollisionGroups::new(
+                BodyLayers::PLAYER_ATTACK,
+                BodyLayers::PLAYER | BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM,
+            )
+        } else {
+            CollisionGroups::new(
+                BodyLayers::ENEMY_ATTACK,
+                BodyLayers::PLAYER | BodyLayers::ENEMY,
+            )
+        }
+    }
+}
+
+/// System parameter that looks up the teams of the attacks and the fighters
+#[derive(SystemParam)]
+pub struct Teams<'w, 's> {
+    teams: Query<'w, 's, &'static Team>,
+    parents: Query<'w, 's, &'static Parent>,
+    owners: Query<'w, 's, &'static ProjectileOwner>,
+    game: Option<Res<'w, GameMeta>>,
+    versus: Option<Res<'w, VersusMatch>>,
+}
+
+impl<'w, 's> Teams<'w, 's> {
+    /// Whether the players' attacks hurt the other players
+    pub fn friendly_fire(&self) -> bool {
+        self.versus.is_some() || self.game.as_ref().map_or(false, |game| game.friendly_fire)
+    }
+
+    /// The fighter that made an attack, or the attack itself if it's on a team of its own.
+    ///
+    /// Melee attacks are spawned as children of the fighters, or of the weapons that they hold, and
+    /// projectiles know the fighter that shot them.
+    pub fn attacker(&self, attack: Entity) -> Option<Entity> {
+        let mut entity = attack;
+        loop {
+            if self.teams.contains(entity) {
+                return Some(entity);
+            }
+            if let Ok(owner) = self.owners.get(entity) {
+                return Some(owner.fighter);
+            }
+            entity = self.parents.get(entity).ok()?.get();
+        }
+    }
+
+    /// The team of an attack, which is the team of the fighter that made it
+    pub fn attack_team(&self, attack: Entity) -> Option<&Team> {
+        self.teams.get(self.attacker(attack)?).ok()
+    }
+
+    /// Whether an attack is on the same team as the fighter it hits, so that it doesn't hurt it
+    pub fn is_friendly(&self, attack: Entity, fighter: Entity) -> bool {
+        let (Some(attack_team), Ok(team)) = (self.attack_team(attack), self.teams.get(fighter))
+        else {
+            return false;
+        };
+        is_friendly(attack_team, team, self.friendly_fire())
+    }
+}
+
+/// Whether an attack of `attack_team` is kept from hurting a fighter of `team`
+fn is_friendly(attack_team: &Team, team: &Team, friendly_fire: bool) -> bool {
+    attack_team == team && !(friendly_fire && team.0 == Team::PLAYERS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn attacks_only_hurt_other_teams() {
+        let players = Team::players();
+        let enemies = Team::enemies(None);
+        let pirates = Team::enemies(Some("pirates"));
+
+        assert!(!is_friendly(&players, &enemies, false));
+        assert!(!is_friendly(&pirates, &enemies, false));
+        assert!(is_friendly(&enemies, &enemies, false));
+        assert!(is_friendly(&players, &players, false));
+
+        // Friendly fire only lets the players hurt each other
+        assert!(!is_friendly(&players, &players, true));
+        assert!(is_friendly(&pirates, &pirates, true));
+        assert!(pirates.is_faction() && !enemies.is_faction() && !players.is_faction());
+    }
+}
@@ -0,0 +1,356 @@
+//! The team-up attack, a joint attack of two players that hits every enemy on the screen.
+//!
+//! Players fill their [meter](TeamUpMeter) by dealing damage to the enemies. Once two players with
+//! full meters stand next to each other and press their special buttons at nearly the same time,
+//! both meters are used up and a short cinematic plays: the players play their [team-up
+//! animations](TeamUpMeta::animations) while the enemies are stunned, and then the attack hits the
+//! whole screen.
+//!
+//! Part of the meter can also be spent on the [combo breaker](crate::combo_breaker).
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::ActionState};
+
+use crate::{
+    afterimage::start_afterimages,
+    attack::{Attack, AttackDamageSystem},
+    camera::camera_half_width,
+    collision::BodyLayers,
+    combat_events::HitLanded,
+    enemy::Enemy,
+    fighter_state::{
+        Dying, FighterStateCollectSystems, HitStun, StateTransition, StateTransitionIntents,
+        TeamUpAttacking,
+    },
+    input::PlayerAction,
+    lifetime::Lifetime,
+    menu_stack::MenuLayer,
+    metadata::{GameMeta, TeamUpMeta},
+    player::{Player, PlayerIndex},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    utils::ResetEvent,
+    GameState,
+};
+
+/// How long the attack at the end of the cinematic hurts the enemies, in seconds
+const ATTACK_TIME: f32 = 0.1;
+
+pub struct TeamUpPlugin;
+
+impl Plugin for TeamUpPlugin {
+    fn build(&self, app: &mut App) {
+        // Special presses are collected every frame like the other player actions, so that none
+        // are missed
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            collect_special_presses
+                .run_in_state(GameState::InGame)
+                .run_in_state(MenuLayer::None)
+                .after(InputManagerSystem::Update),
+        )
+        .add_enter_system(GameState::MainMenu, end_cinematic)
+        .add_system(end_cinematic_on_reset)
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::TRANSITIONS,
+            start_team_ups
+                .run_in_state(GameState::InGame)
+                .label(FighterStateCollectSystems),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            update_cinematic
+                .run_in_state(GameState::InGame)
+                .run_if_resource_exists::<TeamUpCinematic>(),
+        )
+        .add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::COLLISIONS,
+            fill_meters
+                .run_in_state(GameState::InGame)
+                .after(AttackDamageSystem),
+        );
+    }
+}
+
+/// The team-up meter of a player
+#[derive(Component, Default)]
+pub struct TeamUpMeter {
+    /// The damage the player has dealt to the enemies since their last team-up
+    pub value: f32,
+    /// Counts down the time that the player waits for a partner after pressing the special button
+    press_timer: Option<Timer>,
+}
+
+impl TeamUpMeter {
+    /// How full the meter is, from 0 to 1
+    pub fn fraction(&self, meta: &TeamUpMeta) -> f32 {
+        if meta.meter <= 0.0 {
+            return 1.0;
+        }
+        (self.value / meta.meter).clamp(0.0, 1.0)
+    }
+
+    pub fn is_full(&self, meta: &TeamUpMeta) -> bool {
+        self.value >= meta.meter
+    }
+
+    /// Use up `cost` of the meter if it's filled that far, which stops the player from waiting for a
+    /// partner. Returns whether the meter has been used.
+    pub fn spend(&mut self, cost: f32) -> bool {
+        if self.value < cost {
+            return false;
+        }
+        self.value -= cost;
+        self.press_timer = None;
+        true
+    }
+
+    /// Whether the player has pressed the special button with a full meter, and is waiting for a
+    /// partner
+    fn is_waiting(&self) -> bool {
+        self.press_timer.is_some()
+    }
+
+    /// Stop waiting for a partner once the press window is over
+    fn tick(&mut self, delta: Duration) {
+        if let Some(timer) = &mut self.press_timer {
+            if timer.tick(delta).finished() {
+                self.press_timer = None;
+            }
+        }
+    }
+}
+
+/// The cinematic of a team-up attack that is playing out
+#[derive(Resource)]
+pub struct TeamUpCinematic {
+    pub meta: TeamUpMeta,
+    timer: Timer,
+}
+
+fn collect_special_presses(
+    mut players: Query<(&ActionState<PlayerAction>, &mut TeamUpMeter), With<Player>>,
+    game: Res<GameMeta>,
+) {
+    let Some(meta) = &game.team_up else {
+        return;
+    };
+
+    for (action_state, mut meter) in &mut players {
+        if action_state.just_pressed(PlayerAction::Special) && meter.is_full(meta) {
+            meter.press_timer = Some(Timer::from_seconds(
+                meta.press_window.max(0.0),
+                TimerMode::Once,
+            ));
+        }
+    }
+}
+
+/// Fill the meters of the players with the damage they deal to the enemies, or to the other players
+/// in versus, with their own attacks and with the attacks of the weapons they are holding
+fn fill_meters(
+    mut damage_events: EventReader<HitLanded>,
+    mut players: Query<&mut TeamUpMeter, With<Player>>,
+    parents: Query<&Parent>,
+    enemies: Query<(), With<Enemy>>,
+    game: Res<GameMeta>,
+) {
+    let Some(meta) = &game.team_up else {
+        return;
+    };
+
+    for event in damage_events.iter() {
+        if !enemies.contains(event.damaged_entity) && !players.contains(event.damaged_entity) {
+            continue;
+        }
+
+        // The attacks of the weapons are children of the weapons, which are children of the players
+        let parent = parents.get(event.damageing_entity).ok().map(Parent::get);
+        let grandparent = parent
+            .and_then(|parent| parents.get(parent).ok())
+            .map(Parent::get);
+        let Some(attacker) = [parent, grandparent]
+            .into_iter()
+            .flatten()
+            .find(|&entity| players.contains(entity))
+        else {
+            continue;
+        };
+        let mut meter = players.get_mut(attacker).unwrap();
+        meter.value = (meter.value + event.damage.max(0) as f32).min(meta.meter);
+    }
+}
+
+/// Start the team-ups of the players that have pressed their special buttons next to each other
+fn start_team_ups(
+    mut commands: Commands,
+    mut players: Query<
+        (
+            Entity,
+            &PlayerIndex,
+            &Transform,
+            &mut TeamUpMeter,
+            &mut StateTransitionIntents,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
+    mut enemies: Query<&mut StateTransitionIntents, (With<Enemy>, Without<Dying>, Without<Player>)>,
+    cinematic: Option<Res<TeamUpCinematic>>,
+    game: Res<GameMeta>,
+) {
+    let Some(meta) = &game.team_up else {
+        return;
+    };
+
+    // Only the meters of the waiting players are changed, so that the HUD isn't updated every step
+    for (.., mut meter, _) in &mut players {
+        if meter.is_waiting() {
+            meter.tick(GAMEPLAY_STEP);
+        }
+    }
+    if cinematic.is_some() {
+        return;
+    }
+
+    let mut waiting = players
+        .iter()
+        .filter(|(.., meter, _)| meter.is_waiting())
+        .map(|(entity, index, transform, ..)| (entity, index.0, transform.translation.truncate()))
+        .collect::<Vec<_>>();
+    waiting.sort_by_key(|(_, index, _)| *index);
+    let positions = waiting
+        .iter()
+        .map(|(_, _, position)| *position)
+        .collect::<Vec<_>>();
+    let Some((first, second)) = find_partners(&positions, meta.range) else {
+        return;
+    };
+
+    // The player on the left plays the first animation of the pair
+    let (left, right) = if positions[first].x <= positions[second].x {
+        (waiting[first].0, waiting[second].0)
+    } else {
+        (waiting[second].0, waiting[first].0)
+    };
+    for (entity, animation) in [(left, &meta.animations[0]), (right, &meta.animations[1])] {
+        let (.., mut meter, mut transition_intents) = players.get_mut(entity).unwrap();
+        meter.value = 0.0;
+        meter.press_timer = None;
+        transition_intents.push_back(StateTransition::new(
+            TeamUpAttacking::new(animation, meta.cinematic_time),
+            TeamUpAttacking::PRIORITY,
+            false,
+        ));
+        start_afterimages(&mut commands, entity, meta.afterimages.as_ref(), animation);
+    }
+
+    // The enemies are stunned while the cinematic plays out
+    for mut transition_intents in &mut enemies {
+        transition_intents.push_back(StateTransition::new(
+            HitStun {
+                pushback: Vec2::ZERO,
+                timer: Timer::from_seconds(meta.cinematic_time.max(0.0), TimerMode::Once),
+            },
+            HitStun::PRIORITY,
+            false,
+        ));
+    }
+
+    commands.insert_resource(TeamUpCinematic {
+        meta: meta.clone(),
+        timer: Timer::from_seconds(meta.cinematic_time.max(0.0), TimerMode::Once),
+    });
+}
+
+/// Find the first two players, in the order they are given in, that stand within `range` of each
+/// other
+fn find_partners(positions: &[Vec2], range: f32) -> Option<(usize, usize)> {
+    (0..positions.len()).find_map(|first| {
+        (first + 1..positions.len())
+            .find(|&second| positions[first].distance(positions[second]) <= range)
+            .map(|second| (first, second))
+    })
+}
+
+/// Hit the whole screen once the cinematic is over
+fn update_cinematic(
+    mut commands: Commands,
+    mut cinematic: ResMut<TeamUpCinematic>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    windows: Res<Windows>,
+    game: Res<GameMeta>,
+) {
+    if !cinematic.timer.tick(GAMEPLAY_STEP).finished() {
+        return;
+    }
+    commands.remove_resource::<TeamUpCinematic>();
+
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let half_size = Vec2::new(
+        camera_half_width(&game, windows.primary()),
+        game.camera_height as f32 / 2.0,
+    );
+
+    let meta = &cinematic.meta;
+    commands.spawn((
+        TransformBundle::from_transform(Transform::from_translation(
+            camera_transform.translation.truncate().extend(0.0),
+        )),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+        CollisionGroups::new(
+            BodyLayers::PLAYER_ATTACK,
+            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM,
+        ),
+        Collider::cuboid(half_size.x, half_size.y),
+        Attack {
+            damage: meta.damage,
+            pushback: meta.pushback,
+            hitstun_duration: meta.hitstun_duration,
+            hitbox_meta: None,
+            status_effect: None,
+            launch: 0.0,
+            anti_air: false,
+        },
+        Lifetime(Timer::from_seconds(ATTACK_TIME, TimerMode::Once)),
+        Name::new("Team-Up Attack"),
+    ));
+}
+
+fn end_cinematic(mut commands: Commands) {
+    commands.remove_resource::<TeamUpCinematic>();
+}
+
+/// End the cinematic when the level is reset, since its banner shouldn't be shown over the next one
+fn end_cinematic_on_reset(mut commands: Commands, mut reset_events: EventReader<ResetEvent>) {
+    if reset_events.iter().any(|reset| reset.scope.resets_level()) {
+        commands.remove_resource::<TeamUpCinematic>();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partners_have_to_stand_next_to_each_other() {
+        let positions = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(200.0, 0.0),
+            Vec2::new(250.0, 30.0),
+        ];
+        assert_eq!(find_partners(&positions, 80.0), Some((1, 2)));
+        assert_eq!(find_partners(&positions, 40.0), None);
+        assert_eq!(find_partners(&positions[..1], 1000.0), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! The fixed timestep that the gameplay systems run in.
+//!
+//! Movement, attacks, AI, physics, and animations are updated in steps of [`GAMEPLAY_STEP`], so the
+//! speed of the game doesn't depend on the frame rate. Because the steps don't line up with the
+//! frames, the translations of moving entities are interpolated between the last two steps before
+//! they are rendered.
+//!
+//! Player input is still collected every frame, so that button presses that happen in frames
+//! without a gameplay step aren't missed.
+//!
+//! The [`GameplayStep`] resource counts the steps, so that the logs can tell when things happened.
+//!
+//! The [`TimeScale`] resource slows down, speeds up, or pauses the gameplay steps, without changing
+//! how much time passes in each of them. The steps are held while a [menu](crate::menu_stack) is
+//! open over the gameplay, and slowed down further by the [slow motion](TimeScale::slow_motion) of
+//! cinematics.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, reflect::FromReflect};
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{menu_stack::MenuLayer, movement::LinearVelocity, GameState};
+
+/// The label of the gameplay fixed timestep
+pub const GAMEPLAY_TIMESTEP: &str = "gameplay_timestep";
+
+/// The duration of a single gameplay step
+pub const GAMEPLAY_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The sub-stages of the gameplay timestep, in the order that they run every step.
+///
+/// Commands are applied between the sub-stages, so systems that need to see the entities and
+/// components added by systems in another stage have to run in a later stage.
+pub mod gameplay_stage {
+    /// Moves entities back to their translations from the last step
+    pub const PRE: usize = 0;
+    /// Collects the fighter state transition intents and performs the transitions
+    pub const TRANSITIONS: usize = 1;
+    /// Runs the fighter state systems
+    pub const STATES: usize = 2;
+    /// Applies forces and velocities, ticks animations, and enables attack hitboxes
+    pub const MOVEMENT: usize = 3;
+    /// Sends the moved colliders to the physics engine
+    pub const SYNC_PHYSICS: usize = 4;
+    pub const SYNC_PHYSICS_FLUSH: usize = 5;
+    /// Detects collisions
+    pub const STEP_PHYSICS: usize = 6;
+    /// Handles the collisions, like dealing damage and breaking items
+    pub const COLLISIONS: usize = 7;
+    /// Records the translations used to interpolate transforms
+    pub const POST: usize = 8;
+}
+
+pub struct TimestepPlugin;
+
+impl Plugin for TimestepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep(GAMEPLAY_STEP, GAMEPLAY_TIMESTEP);
+        for _ in gameplay_stage::TRANSITIONS..=gameplay_stage::POST {
+            app.add_fixed_timestep_child_stage(GAMEPLAY_TIMESTEP);
+        }
+
+        app.register_type::<TransformInterpolation>()
+            .init_resource::<TimeScale>()
+            .init_resource::<GameplayStep>()
+            .add_system_to_stage(CoreStage::PreUpdate, apply_time_scale)
+            // Step the physics in the gameplay timestep so that the collision events are read in
+            // the same steps that they are generated in. This requires the rapier plugin to be
+            // added with its default system setup disabled, and this plugin has to be added after it.
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt: GAMEPLAY_STEP.as_secs_f32(),
+                    substeps: 1,
+                },
+                ..default()
+            })
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::SYNC_PHYSICS,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::SyncBackend),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::SYNC_PHYSICS_FLUSH,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::SyncBackendFlush),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::STEP_PHYSICS,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::StepSimulation),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::Writeback),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Last,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::DetectDespawn),
+            )
+            .add_fixed_timestep_system(GAMEPLAY_TIMESTEP, gameplay_stage::PRE, count_steps)
+            // Transform interpolation
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .with_system(restore_transforms)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .with_system(record_transforms)
+                    .with_system(add_transform_interpolation)
+                    .into(),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                ConditionSet::new()
+                    .label(InterpolateTransformsSystem)
+                    .before(bevy::transform::TransformSystem::TransformPropagate)
+                    .run_in_state(GameState::InGame)
+                    .with_system(interpolate_transforms)
+                    .into(),
+            );
+    }
+}
+
+/// How fast the gameplay steps are played, used by the debug tools to look at the gameplay in slow
+/// motion or step by step
+#[derive(Resource)]
+pub struct TimeScale {
+    /// The speed of the gameplay relative to real time
+    pub scale: f32,
+    /// The factor that cinematics like the knockout slow motion scale the speed by, on top of the
+    /// [`scale`](Self::scale) set by the assists and the debug tools
+    pub slow_motion: f32,
+    pub paused: bool,
+    /// Whether the gameplay is held while something plays out over it, like a dialogue. Unlike
+    /// [`paused`](Self::paused), it can't be stepped through.
+    pub held: bool,
+    /// Whether to play a single step in the next frame while paused
+    step_once: bool,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            slow_motion: 1.0,
+            paused: false,
+            held: false,
+            step_once: false,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Play a single gameplay step in the next frame, if the gameplay is paused
+    pub fn step_once(&mut self) {
+        self.step_once = true;
+    }
+
+    /// How much real time passes between two gameplay steps
+    fn step_duration(&self) -> Duration {
+        // Not dividing by one keeps the duration exact, so that it matches the headless clock
+        let scale = self.scale * self.slow_motion;
+        if scale == 1.0 {
+            GAMEPLAY_STEP
+        } else {
+            GAMEPLAY_STEP.div_f32(scale)
+        }
+    }
+}
+
+/// The number of gameplay steps that have been played since the game started, counting the current
+/// step
+#[derive(Resource, Default, Deref)]
+pub struct GameplayStep(u64);
+
+fn apply_time_scale(
+    mut time_scale: ResMut<TimeScale>,
+    mut timesteps: ResMut<FixedTimesteps>,
+    menu_layer: Res<CurrentState<MenuLayer>>,
+) {
+    let Some(timestep) = timesteps.get_mut(GAMEPLAY_TIMESTEP) else {
+        return;
+    };
+    let step = time_scale.step_duration();
+    // The gameplay is held while a menu like the pause menu is open over it
+    let held = time_scale.held || menu_layer.0 != MenuLayer::None;
+
+    if !time_scale.paused && !held {
+        timestep.unpause();
+        timestep.step = step;
+    } else if !held && std::mem::take(&mut time_scale.step_once) {
+        // Make a step due, with a step duration so long that it's only played once however long
+        // the frame takes
+        timestep.unpause();
+        timestep.step = Duration::MAX / 4;
+        timestep.accumulator = timestep.step;
+    } else {
+        timestep.pause();
+        timestep.step = step;
+        // Show the entities where they were after the last step, instead of in between steps
+        timestep.accumulator = step;
+    }
+}
+
+/// Label for the system that interpolates the transforms every frame.
+///
+/// Systems that need the rendered translations of the entities, like the camera, should run after
+/// it.
+#[derive(Clone, SystemLabel)]
+pub struct InterpolateTransformsSystem;
+
+/// The translations of an entity after the last two gameplay steps, used to render it in between
+/// them.
+///
+/// This is added automatically to every entity with a [`LinearVelocity`].
+#[derive(Component, Reflect, FromReflect, Default, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct TransformInterpolation {
+    pub previous: Vec2,
+    pub current: Vec2,
+}
+
+impl TransformInterpolation {
+    /// Create an interpolation that starts at the given translation
+    pub fn new(translation: Vec2) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+        }
+    }
+
+    /// Get the translation the given fraction of the way from the previous to the current step
+    pub fn lerp(&self, fraction: f32) -> Vec2 {
+        self.previous.lerp(self.current, fraction)
+    }
+}
+
+fn add_transform_interpolation(
+    mut commands: Commands,
+    entities: Query<(Entity, &Transform), (With<LinearVelocity>, Without<TransformInterpolation>)>,
+) {
+    for (entity, transform) in &entities {
+        commands.entity(entity).insert(TransformInterpolation::new(
+            transform.translation.truncate(),
+        ));
+    }
+}
+
+/// Undo the interpolation so that the gameplay systems work with the translations of the last step
+fn restore_transforms(mut entities: Query<(&mut Transform, &mut TransformInterpolation)>) {
+    for (mut transform, mut interpolation) in &mut entities {
+        let current = interpolation.current;
+        if interpolation.previous != current {
+            interpolation.previous = current;
+        }
+        set_translation(&mut transform, current);
+    }
+}
+
+fn count_steps(mut step: ResMut<GameplayStep>) {
+    step.0 += 1;
+}
+
+fn record_transforms(
+    mut entities: Query<(&Transform, &mut TransformInterpolation), Changed<Transform>>,
+) {
+    for (transform, mut interpolation) in &mut entities {
+        interpolation.current = transform.translation.truncate();
+    }
+}
+
+/// Move entities in between their translations from the last two steps, depending on how much
+/// time has passed since the last step.
+fn interpolate_transforms(
+    timesteps: Res<FixedTimesteps>,
+    mut entities: Query<(&mut Transform, &TransformInterpolation)>,
+) {
+    let Some(timestep) = timesteps.get(GAMEPLAY_TIMESTEP) else {
+        return;
+    };
+    let fraction = timestep.overstep_percentage().clamp(0.0, 1.0) as f32;
+
+    for (mut transform, interpolation) in &mut entities {
+        set_translation(&mut transform, interpolation.lerp(fraction));
+    }
+}
+
+/// Set the X and Y translation, without marking the transform as changed if it's the same already,
+/// so that systems using change detection skip entities that are standing still.
+fn set_translation(transform: &mut Mut<Transform>, translation: Vec2) {
+    if transform.translation.truncate() != translation {
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interpolation_lerps_between_steps() {
+        let mut interpolation = TransformInterpolation::new(Vec2::new(10.0, 0.0));
+        assert_eq!(interpolation.lerp(0.5), Vec2::new(10.0, 0.0));
+
+        interpolation.current = Vec2::new(20.0, -4.0);
+        assert_eq!(interpolation.lerp(0.0), Vec2::new(10.0, 0.0));
+        assert_eq!(interpolation.lerp(0.5), Vec2::new(15.0, -2.0));
+        assert_eq!(interpolation.lerp(1.0), Vec2::new(20.0, -4.0));
+    }
+}
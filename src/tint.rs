@@ -0,0 +1,169 @@
+//! Color feedback on the sprites of the fighters and of their weapons.
+//!
+//! The systems that give feedback with the color of a sprite go through its [`Tint`] instead of
+//! setting the color of the sprite themselves, so that they don't undo each other's colors. Hits
+//! flash the sprite white, and lasting effects tint it for as long as they last, like red while
+//! burning, blue while frozen, yellow while armored, or see-through while invulnerable. When
+//! several effects tint a sprite at once, the tint of the highest [`TintSource`] shows, and a
+//! flash shows over all of them.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    damage::Damageable,
+    metadata::ColorSettings,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// How long the flash of a hit lasts
+const FLASH_TIME: Duration = Duration::from_millis(100);
+
+/// The tint of the fighters that are burning
+pub const BURNING_COLOR: Color = Color::rgb(1.0, 0.45, 0.35);
+
+/// The tint of the fighters that are poisoned
+pub const POISONED_COLOR: Color = Color::rgb(0.6, 1.0, 0.45);
+
+/// The tint of the fighters that are frozen
+pub const FROZEN_COLOR: Color = Color::rgb(0.55, 0.8, 1.0);
+
+/// The tint of the fighters that can't be hit while they get up after being knocked down
+pub const INVULNERABLE_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.6);
+
+/// The tint of the fighters that can't be damaged, like players that are blocking
+pub const ARMORED_COLOR: Color = Color::rgb(1.0, 0.9, 0.3);
+
+pub struct TintPlugin;
+
+impl Plugin for TintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_timestep_system(
+            GAMEPLAY_TIMESTEP,
+            gameplay_stage::POST,
+            tint_armored_fighters.run_in_state(GameState::InGame),
+        )
+        // The tints are only visual, so they are updated every frame
+        .add_system(update_tints.run_in_state(GameState::InGame));
+    }
+}
+
+/// The effects that tint sprites for as long as they last, from the lowest priority to the highest
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum TintSource {
+    /// The color of the coating of a weapon
+    Coated,
+    Burning,
+    Poisoned,
+    Frozen,
+    Invulnerable,
+    Armored,
+}
+
+/// Component that controls the color of a sprite
+#[derive(Component, Default)]
+pub struct Tint {
+    tints: Vec<(TintSource, Color)>,
+    flash: Option<Timer>,
+}
+
+impl Tint {
+    /// Flash the sprite white, like when it's hit
+    pub fn flash(&mut self) {
+        self.flash = Some(Timer::new(FLASH_TIME, TimerMode::Once));
+    }
+
+    /// Tint the sprite with `color` until the tint of the `source` is cleared
+    pub fn set(&mut self, source: TintSource, color: Color) {
+        match self.tints.iter_mut().find(|(other, _)| *other == source) {
+            Some((_, tint)) => *tint = color,
+            None => self.tints.push((source, color)),
+        }
+    }
+
+    pub fn clear(&mut self, source: TintSource) {
+        self.tints.retain(|(other, _)| *other != source);
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        if let Some(timer) = &mut self.flash {
+            if timer.tick(delta).finished() {
+                self.flash = None;
+            }
+        }
+    }
+
+    /// The color of the sprite. High contrast flashes start black before turning white.
+    fn color(&self, high_contrast: bool) -> Color {
+        if let Some(timer) = &self.flash {
+            return if high_contrast && timer.percent() < 0.5 {
+                Color::BLACK
+            } else {
+                Color::rgb(255.0, 255.0, 255.0)
+            };
+        }
+
+        self.tints
+            .iter()
+            .max_by_key(|(source, _)| *source)
+            .map_or(Color::WHITE, |(_, color)| *color)
+    }
+}
+
+fn update_tints(
+    mut sprites: Query<(&mut Tint, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+    color_settings: Option<Res<ColorSettings>>,
+) {
+    let high_contrast = color_settings.map_or(false, |colors| colors.high_contrast_flashes);
+
+    for (mut tint, mut sprite) in &mut sprites {
+        if tint.flash.is_some() {
+            tint.tick(time.delta());
+        }
+
+        let color = tint.color(high_contrast);
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// Tint the fighters that can't be damaged while they can't be
+fn tint_armored_fighters(mut fighters: Query<(&Damageable, &mut Tint), Changed<Damageable>>) {
+    for (damageable, mut tint) in &mut fighters {
+        if **damageable {
+            tint.clear(TintSource::Armored);
+        } else {
+            tint.set(TintSource::Armored, ARMORED_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flashes_show_over_the_highest_tint() {
+        let mut tint = Tint::default();
+        assert_eq!(tint.color(false), Color::WHITE);
+
+        tint.set(TintSource::Armored, ARMORED_COLOR);
+        tint.set(TintSource::Burning, BURNING_COLOR);
+        assert_eq!(tint.color(false), ARMORED_COLOR);
+
+        tint.flash();
+        assert_eq!(tint.color(true), Color::BLACK);
+        tint.tick(FLASH_TIME / 2);
+        assert_eq!(tint.color(true), Color::rgb(255.0, 255.0, 255.0));
+        tint.tick(FLASH_TIME);
+        assert_eq!(tint.color(false), ARMORED_COLOR);
+
+        tint.clear(TintSource::Armored);
+        assert_eq!(tint.color(false), BURNING_COLOR);
+    }
+}
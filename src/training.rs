@@ -0,0 +1,475 @@
+//! Training mode, for practicing attacks on a dummy.
+//!
+//! Training is started from the main menu, with the fighters of the player and the dummy picked
+//! from the game's [`TrainingMeta`]. It's played in the training level, where neither of them runs
+//! out of health, and the [training panel](crate::ui::training_panel) shows:
+//!
+//! - how much damage the last hit and the current combo did
+//! - the frame advantage of the last hit, which is how many steps before the dummy the player can
+//!   act again, negative if the dummy recovers first
+//! - the recent inputs of the player in numpad notation, with how many steps they were held for
+//!
+//! The panel also has the settings for how the dummy behaves, and resets the player and the dummy
+//! to where they were spawned, which can also be done with [`RESET_KEY`].
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use rand::Rng;
+
+use crate::{
+    attack::AttackDamageSystem,
+    combat_events::HitLanded,
+    consts,
+    damage::{Damageable, Health},
+    enemy::Enemy,
+    fighter::Stats,
+    fighter_state::{HitStun, Idling, Moving},
+    input::PlayerAction,
+    menu_stack::MenuLayer,
+    metadata::{FighterMeta, LevelHandle, LevelMeta, TrainingMeta},
+    player::{Player, PlayerIndex},
+    rng::GameRng,
+    timestep::{gameplay_stage, TransformInterpolation, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// The key that resets the positions of the player and the dummy
+pub const RESET_KEY: KeyCode = KeyCode::Back;
+
+/// How many of the player's inputs are kept in the input history
+const INPUT_HISTORY_LEN: usize = 16;
+
+/// How far the movement axis has to be pushed to count as a direction in the input history
+const DIRECTION_THRESHOLD: f32 = 0.3;
+
+/// How long a randomly behaving dummy keeps standing still or fighting back, in seconds
+const RANDOM_BEHAVIOR_SECS: std::ops::Range<f32> = 1.0..3.0;
+
+pub struct TrainingPlugin;
+
+impl Plugin for TrainingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, end_training)
+            .add_system(
+                request_reset_on_key
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<TrainingSession>(),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<TrainingSession>()
+                    .with_system(mark_training_dummy)
+                    .with_system(update_dummy_behavior)
+                    .with_system(reset_positions)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                ConditionSet::new()
+                    .after(AttackDamageSystem)
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<TrainingSession>()
+                    .with_system(record_hits)
+                    .with_system(refill_health.after(record_hits))
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<TrainingSession>()
+                    .with_system(track_recovery)
+                    .with_system(record_input_history)
+                    .into(),
+            );
+    }
+}
+
+/// Start training in the training level, with the given fighters for the player and the dummy
+pub fn start_training(
+    commands: &mut Commands,
+    levels: &mut Assets<LevelMeta>,
+    training: &TrainingMeta,
+    fighter: &Handle<FighterMeta>,
+    dummy: &Handle<FighterMeta>,
+) {
+    let Some(level) = levels.get(&training.level_handle) else {
+        warn!("The training level hasn't loaded");
+        return;
+    };
+    let mut level = level.clone();
+
+    level.players.truncate(1);
+    let Some(player) = level.players.first_mut() else {
+        warn!("The training level doesn't have a player");
+        return;
+    };
+    player.fighter_handle = fighter.clone();
+
+    // The dummy is spawned where the first enemy is, or in front of the player
+    let mut dummy_spawn = level.enemies.first().cloned().unwrap_or_else(|| {
+        let mut spawn = player.clone();
+        spawn.location.x += 150.0;
+        spawn
+    });
+    dummy_spawn.fighter_handle = dummy.clone();
+    dummy_spawn.trip_point_x = f32::MIN;
+    dummy_spawn.boss = false;
+    level.enemies = vec![dummy_spawn];
+
+    commands.insert_resource(LevelHandle(levels.add(level)));
+    commands.insert_resource(TrainingSession::default());
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+/// How the training dummy behaves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DummyBehavior {
+    /// Stands still and takes every hit
+    Stand,
+    /// Stands still and isn't damaged by any hit, like a player blocking everything without ever
+    /// letting go
+    Block,
+    /// Switches between standing still and fighting back like an enemy every few seconds
+    Random,
+}
+
+impl DummyBehavior {
+    pub const ALL: [Self; 3] = [Self::Stand, Self::Block, Self::Random];
+
+    /// The localization key of the behavior's name
+    pub fn localization_key(self) -> &'static str {
+        match self {
+            Self::Stand => "dummy-stand",
+            Self::Block => "dummy-block",
+            Self::Random => "dummy-random",
+        }
+    }
+}
+
+/// The state of the training mode, which exists while training
+#[derive(Resource)]
+pub struct TrainingSession {
+    pub dummy_behavior: DummyBehavior,
+    /// The number of gameplay steps played since training started
+    step: u32,
+    /// The damage that the last hit on the dummy did
+    pub last_hit_damage: Option<i32>,
+    /// The number of hits and the damage of the current combo on the dummy
+    pub combo_hits: u32,
+    pub combo_damage: i32,
+    /// The frame advantage of the last hit that both fighters have recovered from
+    pub frame_advantage: Option<i32>,
+    recovery: Option<Recovery>,
+    /// The recent inputs of the player, latest first
+    pub input_history: VecDeque<InputHistoryEntry>,
+    reset_requested: bool,
+    /// When a randomly behaving dummy changes between standing still and fighting back
+    random_behavior_timer: Timer,
+    random_standing_still: bool,
+}
+
+impl Default for TrainingSession {
+    fn default() -> Self {
+        Self {
+            dummy_behavior: DummyBehavior::Stand,
+            step: 0,
+            last_hit_damage: None,
+            combo_hits: 0,
+            combo_damage: 0,
+            frame_advantage: None,
+            recovery: None,
+            input_history: VecDeque::with_capacity(INPUT_HISTORY_LEN),
+            reset_requested: false,
+            random_behavior_timer: Timer::from_seconds(RANDOM_BEHAVIOR_SECS.start, TimerMode::Once),
+            random_standing_still: true,
+        }
+    }
+}
+
+impl TrainingSession {
+    /// Move the player and the dummy back to where they were spawned in the next step
+    pub fn request_reset(&mut self) {
+        self.reset_requested = true;
+    }
+}
+
+/// The steps that the fighters of the last hit recovered in
+struct Recovery {
+    attacker: Entity,
+    attacker_recovered: Option<u32>,
+    dummy_recovered: Option<u32>,
+}
+
+/// An input of the player in the input history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputHistoryEntry {
+    /// The direction in numpad notation, where 5 is neutral, 6 is forward, and 8 is up
+    pub direction: u8,
+    pub attack: bool,
+    pub throw: bool,
+    pub shoot: bool,
+    pub block: bool,
+    pub dodge: bool,
+    /// How many steps the input was held for
+    pub steps: u32,
+}
+
+impl InputHistoryEntry {
+    fn new(movement: Vec2, action_state: &ActionState<PlayerAction>) -> Self {
+        Self {
+            direction: numpad_direction(movement),
+            attack: action_state.pressed(PlayerAction::Attack),
+            throw: action_state.pressed(PlayerAction::Throw),
+            shoot: action_state.pressed(PlayerAction::Shoot),
+            block: action_state.pressed(PlayerAction::Block),
+            dodge: action_state.pressed(PlayerAction::Dodge),
+            steps: 1,
+        }
+    }
+
+    /// Whether both entries have the same inputs, regardless of how long they were held
+    fn same_inputs(&self, other: &Self) -> bool {
+        Self { steps: 0, ..*self } == Self { steps: 0, ..*other }
+    }
+}
+
+/// Get the direction of a movement in numpad notation
+fn numpad_direction(movement: Vec2) -> u8 {
+    let axis = |value: f32| {
+        if value > DIRECTION_THRESHOLD {
+            1
+        } else if value < -DIRECTION_THRESHOLD {
+            -1
+        } else {
+            0
+        }
+    };
+
+    (5 + axis(movement.x) + 3 * axis(movement.y)) as u8
+}
+
+/// Marks the dummy of the training level
+#[derive(Component)]
+pub struct TrainingDummy;
+
+/// Keeps the enemy AI from controlling the dummy
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct DummyStandingStill;
+
+fn end_training(mut commands: Commands) {
+    commands.remove_resource::<TrainingSession>();
+}
+
+fn request_reset_on_key(mut session: ResMut<TrainingSession>, input: Res<Input<KeyCode>>) {
+    if input.just_pressed(RESET_KEY) {
+        session.request_reset();
+    }
+}
+
+fn mark_training_dummy(
+    mut commands: Commands,
+    enemies: Query<Entity, (With<Enemy>, Without<TrainingDummy>)>,
+) {
+    for entity in &enemies {
+        commands
+            .entity(entity)
+            .insert((TrainingDummy, DummyStandingStill));
+    }
+}
+
+fn update_dummy_behavior(
+    mut commands: Commands,
+    mut session: ResMut<TrainingSession>,
+    mut rng: ResMut<GameRng>,
+    mut dummies: Query<(Entity, &mut Damageable, Option<&DummyStandingStill>), With<TrainingDummy>>,
+) {
+    let standing_still = match session.dummy_behavior {
+        DummyBehavior::Stand | DummyBehavior::Block => true,
+        DummyBehavior::Random => {
+            session.random_behavior_timer.tick(GAMEPLAY_STEP);
+            if session.random_behavior_timer.finished() {
+                session.random_standing_still = !session.random_standing_still;
+                session.random_behavior_timer =
+                    Timer::from_seconds(rng.gen_range(RANDOM_BEHAVIOR_SECS), TimerMode::Once);
+            }
+
+            session.random_standing_still
+        }
+    };
+
+    for (entity, mut damageable, standing) in &mut dummies {
+        let damageable_now = session.dummy_behavior != DummyBehavior::Block;
+        if **damageable != damageable_now {
+            **damageable = damageable_now;
+        }
+
+        match (standing.is_some(), standing_still) {
+            (false, true) => {
+                commands.entity(entity).insert(DummyStandingStill);
+            }
+            (true, false) => {
+                commands.entity(entity).remove::<DummyStandingStill>();
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Move the player and the dummy back to where they were spawned
+fn reset_positions(
+    mut session: ResMut<TrainingSession>,
+    level: Res<LevelMeta>,
+    mut fighters: Query<
+        (
+            &mut Transform,
+            Option<&mut TransformInterpolation>,
+            Option<&PlayerIndex>,
+        ),
+        Or<(With<Player>, With<TrainingDummy>)>,
+    >,
+) {
+    if !std::mem::take(&mut session.reset_requested) {
+        return;
+    }
+
+    let ground_offset = Vec3::new(0.0, consts::GROUND_Y, 0.0);
+    for (mut transform, interpolation, player_index) in &mut fighters {
+        let spawn = match player_index {
+            Some(player_index) => level.players.get(player_index.0),
+            None => level.enemies.first(),
+        };
+        let Some(spawn) = spawn else {
+            continue;
+        };
+
+        let translation = spawn.location + ground_offset;
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        if let Some(mut interpolation) = interpolation {
+            *interpolation = TransformInterpolation::new(translation.truncate());
+        }
+    }
+
+    session.combo_hits = 0;
+    session.combo_damage = 0;
+    session.recovery = None;
+}
+
+/// Record the damage of the hits on the dummy, and start tracking the frame advantage of each hit
+fn record_hits(
+    mut session: ResMut<TrainingSession>,
+    mut damage_events: EventReader<HitLanded>,
+    dummies: Query<Option<&HitStun>, With<TrainingDummy>>,
+    parents: Query<&Parent>,
+    players: Query<(), With<Player>>,
+) {
+    for event in damage_events.iter() {
+        let Ok(hitstun) = dummies.get(event.damaged_entity) else {
+            continue;
+        };
+
+        // The combo continues for as long as the dummy is still stunned by the last hit
+        if hitstun.is_none() {
+            session.combo_hits = 0;
+            session.combo_damage = 0;
+        }
+        session.combo_hits += 1;
+        session.combo_damage += event.damage;
+        session.last_hit_damage = Some(event.damage);
+
+        // Melee attacks are children of the fighter making them
+        let attacker = parents
+            .get(event.damageing_entity)
+            .map(|parent| parent.get())
+            .ok()
+            .filter(|attacker| players.contains(*attacker));
+        session.recovery = attacker.map(|attacker| Recovery {
+            attacker,
+            attacker_recovered: None,
+            dummy_recovered: None,
+        });
+    }
+}
+
+/// Keep the player and the dummy at full health
+fn refill_health(
+    mut fighters: Query<(&mut Health, &Stats), Or<(With<Player>, With<TrainingDummy>)>>,
+) {
+    for (mut health, stats) in &mut fighters {
+        if **health != stats.max_health {
+            **health = stats.max_health;
+        }
+    }
+}
+
+/// Work out the frame advantage of the last hit, once both fighters have recovered from it
+fn track_recovery(
+    mut session: ResMut<TrainingSession>,
+    actionable: Query<(), Or<(With<Idling>, With<Moving>)>>,
+    dummies: Query<Option<&HitStun>, With<TrainingDummy>>,
+) {
+    session.step += 1;
+    let step = session.step;
+
+    let Some(recovery) = &mut session.recovery else {
+        return;
+    };
+    if recovery.attacker_recovered.is_none() && actionable.contains(recovery.attacker) {
+        recovery.attacker_recovered = Some(step);
+    }
+    if recovery.dummy_recovered.is_none() && dummies.iter().all(|hitstun| hitstun.is_none()) {
+        recovery.dummy_recovered = Some(step);
+    }
+
+    if let (Some(attacker), Some(dummy)) = (recovery.attacker_recovered, recovery.dummy_recovered) {
+        session.frame_advantage = Some(dummy as i32 - attacker as i32);
+        session.recovery = None;
+    }
+}
+
+fn record_input_history(
+    mut session: ResMut<TrainingSession>,
+    players: Query<(&PlayerIndex, &ActionState<PlayerAction>)>,
+) {
+    let Some((_, action_state)) = players.iter().find(|(index, _)| index.0 == 0) else {
+        return;
+    };
+    let movement = action_state
+        .axis_pair(PlayerAction::Move)
+        .map_or(Vec2::ZERO, |axis| axis.xy());
+    let entry = InputHistoryEntry::new(movement, action_state);
+
+    match session.input_history.front_mut() {
+        Some(last) if last.same_inputs(&entry) => last.steps += 1,
+        _ => {
+            session.input_history.truncate(INPUT_HISTORY_LEN - 1);
+            session.input_history.push_front(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn directions_use_numpad_notation() {
+        assert_eq!(numpad_direction(Vec2::ZERO), 5);
+        assert_eq!(numpad_direction(Vec2::new(1.0, 0.0)), 6);
+        assert_eq!(numpad_direction(Vec2::new(-1.0, 0.0)), 4);
+        assert_eq!(numpad_direction(Vec2::new(0.0, 1.0)), 8);
+        assert_eq!(numpad_direction(Vec2::new(0.7, -0.7)), 3);
+        assert_eq!(numpad_direction(Vec2::new(0.1, -0.2)), 5);
+    }
+}
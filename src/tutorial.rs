@@ -0,0 +1,162 @@
+//! Tutorial levels, which teach the controls one step at a time.
+//!
+//! Once the players get to a [tutorial step](crate::metadata::TutorialStepMeta) of the level, its
+//! prompt is shown with the buttons of its action, and the players are held back at the gate of
+//! the step until one of them has performed the action. The buttons are shown for the keyboard or
+//! the gamepad, whichever the first player used last according to the
+//! [`InputDevices`](crate::input::InputDevices).
+//!
+//! Tutorials are only run if the `tutorial_prompts` feature is enabled.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::{prelude::ActionState, user_input::InputKind};
+
+use crate::{
+    fighter::Inventory,
+    fighter_state::Throwing,
+    input::{InputDevice, PlayerAction},
+    metadata::{GameMeta, LevelMeta, PlayerControlMethods, Settings, TutorialAction},
+    movement::{ForceSystems, LinearVelocity, VelocitySystems},
+    platform::Storage,
+    player::Player,
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, end_tutorial)
+            .add_system(start_tutorial.run_in_state(GameState::InGame))
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
+                hold_players_at_gate
+                    .after(ForceSystems)
+                    .before(VelocitySystems)
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<Tutorial>(),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                advance_tutorial
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<Tutorial>(),
+            );
+    }
+}
+
+/// The progress through the tutorial of the current level, which exists while it's played
+#[derive(Resource)]
+pub struct Tutorial {
+    /// The index of the current step in the tutorial of the level
+    pub step: usize,
+    /// Whether the players have gotten to the current step, so that its prompt is shown
+    pub prompt_shown: bool,
+    controls: PlayerControlMethods,
+}
+
+impl Tutorial {
+    /// Get the buttons of the first player that perform an action, on the given device
+    pub fn buttons(&self, action: TutorialAction, device: InputDevice) -> Vec<InputKind> {
+        let action = match action {
+            TutorialAction::Move => PlayerAction::Move,
+            TutorialAction::Attack => PlayerAction::Attack,
+            TutorialAction::PickUp | TutorialAction::Throw => PlayerAction::Throw,
+        };
+
+        self.controls.buttons(0, device, action)
+    }
+}
+
+/// Start the tutorial of a level when it's loaded, if it has one
+fn start_tutorial(
+    mut commands: Commands,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+    if level.tutorial.is_empty() || !game.features.tutorial_prompts {
+        commands.remove_resource::<Tutorial>();
+        return;
+    }
+
+    let settings = storage
+        .get::<Settings>(Settings::STORAGE_KEY)
+        .unwrap_or_else(|| game.default_settings.clone());
+    commands.insert_resource(Tutorial {
+        step: 0,
+        prompt_shown: false,
+        controls: settings.player_controls,
+    });
+}
+
+fn end_tutorial(mut commands: Commands) {
+    commands.remove_resource::<Tutorial>();
+}
+
+/// Keep the players from walking or being knocked past the gate of the current step
+fn hold_players_at_gate(
+    tutorial: Res<Tutorial>,
+    level: Res<LevelMeta>,
+    mut players: Query<(&Transform, &mut LinearVelocity), With<Player>>,
+) {
+    let Some(step) = level.tutorial.get(tutorial.step) else {
+        return;
+    };
+    let dt = GAMEPLAY_STEP.as_secs_f32();
+
+    for (transform, mut velocity) in &mut players {
+        let new_x = transform.translation.x + velocity.x * dt;
+        if new_x > step.gate_x && velocity.x > 0.0 {
+            velocity.x = 0.0;
+        }
+    }
+}
+
+/// Show the prompt of the current step once the players get to it, and move on to the next step
+/// once its action has been performed
+fn advance_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    level: Res<LevelMeta>,
+    players: Query<(&Transform, &ActionState<PlayerAction>, &Inventory), With<Player>>,
+    throwing_players: Query<(), (With<Player>, Added<Throwing>)>,
+) {
+    let Some(step) = level.tutorial.get(tutorial.step) else {
+        return;
+    };
+
+    if !tutorial.prompt_shown {
+        if players
+            .iter()
+            .any(|(transform, ..)| transform.translation.x >= step.trigger_x)
+        {
+            tutorial.prompt_shown = true;
+        } else {
+            return;
+        }
+    }
+
+    let performed = match step.action {
+        TutorialAction::Move => players
+            .iter()
+            .any(|(_, input, _)| input.pressed(PlayerAction::Move)),
+        TutorialAction::Attack => players
+            .iter()
+            .any(|(_, input, _)| input.just_pressed(PlayerAction::Attack)),
+        TutorialAction::PickUp => players.iter().any(|(_, _, inventory)| inventory.is_some()),
+        TutorialAction::Throw => !throwing_players.is_empty(),
+    };
+
+    if performed {
+        debug!(step = tutorial.step, action = ?step.action, "Tutorial step completed");
+        tutorial.step += 1;
+        tutorial.prompt_shown = false;
+    }
+}
@@ -1,81 +1,236 @@
+use std::time::Duration;
+
 use bevy::{prelude::*, utils::HashMap, window::WindowId};
 use bevy_egui::{egui, EguiContext, EguiPlugin, EguiRenderInputContainer, EguiSettings};
+use bevy_parallax::ParallaxCameraComponent;
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::ActionState;
 
 use crate::{
+    arcade::ArcadeRun,
     assets::{EguiFont, EguiFontDefinitions},
     audio,
     config::ENGINE_CONFIG,
-    input::MenuAction,
-    metadata::GameMeta,
+    dialogue::Dialogue,
+    input::{MenuAction, PlayerAction},
+    menu_stack::{MenuLayer, MenuStack},
+    metadata::{feature_enabled, GameMeta, Settings},
+    platform::Storage,
+    player::Player,
+    survival::SurvivalRun,
+    team_up::TeamUpCinematic,
+    training::TrainingSession,
+    tutorial::Tutorial,
+    versus::VersusMatch,
     GameState,
 };
 
 pub mod hud;
 pub mod widgets;
 
+pub mod attract_mode;
+pub mod debug_console;
 pub mod debug_tools;
+pub mod dialogue_box;
+pub mod frame_data_tuning;
+pub mod hitbox_debug;
+pub mod interaction_prompt;
 pub mod main_menu;
 pub mod pause_menu;
+pub mod performance_overlay;
+pub mod training_panel;
+pub mod tutorial_prompt;
+pub mod versus_results;
 
 pub mod extensions;
 pub use extensions::*;
 
+/// How long the pause button has to be held to pause, when holding to pause is enabled
+const HOLD_TO_PAUSE_DURATION: Duration = Duration::from_millis(500);
+
 pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<WidgetAdjacencies>()
             .add_plugin(EguiPlugin)
+            .add_plugin(attract_mode::AttractModePlugin)
             .add_system(handle_menu_input.run_if_resource_exists::<GameMeta>())
             .add_enter_system(GameState::MainMenu, main_menu::spawn_main_menu_background)
             .add_enter_system(GameState::MainMenu, audio::play_menu_music)
             .add_exit_system(GameState::MainMenu, main_menu::despawn_main_menu_background)
             .add_exit_system(GameState::MainMenu, audio::stop_menu_music)
-            .add_system(unpause.run_in_state(GameState::Paused))
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
                     .with_system(hud::render_hud)
-                    .with_system(pause)
+                    .with_system(pause.run_if_not(attract_mode::demo_playing))
                     .into(),
             )
+            .add_system(
+                hud::render_survival_hud
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<SurvivalRun>(),
+            )
+            .add_system(
+                hud::render_arcade_hud
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<ArcadeRun>(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<VersusMatch>()
+                    .with_system(hud::render_versus_hud)
+                    .with_system(versus_results::versus_results)
+                    .into(),
+            )
+            .add_system(
+                hud::render_team_up_cinematic
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<TeamUpCinematic>(),
+            )
+            .add_system(
+                hud::render_nameplate
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None),
+            )
+            .add_system(
+                hud::render_damage_numbers
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None),
+            )
+            .add_system(
+                dialogue_box::dialogue_box
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<Dialogue>(),
+            )
+            .add_system(
+                tutorial_prompt::tutorial_prompt
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<Tutorial>(),
+            )
+            .add_system(
+                interaction_prompt::interaction_prompt
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if(feature_enabled(|features| features.interaction_prompts)),
+            )
+            .add_system(
+                training_panel::training_panel
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::None)
+                    .run_if_resource_exists::<TrainingSession>(),
+            )
+            // The hitbox debug overlay is shown by the training mode too
+            .init_resource::<hitbox_debug::HitboxDebug>()
+            .add_system_to_stage(CoreStage::Last, hitbox_debug::draw_hitbox_debug)
+            .add_enter_system(GameState::MainMenu, training_panel::hide_training_hitboxes)
             .add_system(update_egui_fonts)
             .add_system(update_ui_scale.run_if_resource_exists::<GameMeta>())
             .add_system_set(
                 ConditionSet::new()
-                    .run_in_state(GameState::Paused)
+                    .run_in_state(GameState::InGame)
+                    .run_in_state(MenuLayer::Paused)
                     .with_system(pause_menu::pause_menu)
+                    .with_system(unpause)
                     .into(),
             )
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(GameState::MainMenu)
+                    .run_in_state(MenuLayer::None)
                     .with_system(main_menu::main_menu_system)
                     .into(),
-            );
+            )
+            // The settings are opened from both the main menu and the pause menu
+            .add_system(main_menu::settings_menu_system.run_in_state(MenuLayer::Settings));
 
         if ENGINE_CONFIG.debug_tools {
-            app.add_system(debug_tools::debug_tools_window)
+            app.init_resource::<frame_data_tuning::FrameDataTuning>()
+                .init_resource::<debug_console::DebugConsole>()
+                .add_system(debug_tools::debug_tools_window)
+                .add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    debug_console::debug_console_window
+                        .before(bevy_egui::EguiSystem::ProcessOutput),
+                )
+                // Runs in the same stage as the check for defeated players, so that the players
+                // despawned when changing the level are only gone once the level is loading
+                .add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    debug_console::run_console_command.after(debug_console::debug_console_window),
+                )
+                .add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    frame_data_tuning::frame_data_tuning_window,
+                )
                 .add_system_to_stage(CoreStage::Last, debug_tools::rapier_debug_render);
         }
+
+        if ENGINE_CONFIG.cheats.any() {
+            app.add_system(hud::render_cheats_watermark);
+        }
+
+        if ENGINE_CONFIG.debug_tools || ENGINE_CONFIG.perf_overlay {
+            app.add_plugin(performance_overlay::PerformanceOverlayPlugin);
+        }
     }
 }
 
-/// Transition game to pause state
-fn pause(mut commands: Commands, input: Query<&ActionState<MenuAction>>) {
-    let input = input.single();
-    if input.just_pressed(MenuAction::Pause) {
-        commands.insert_resource(NextState(GameState::Paused));
+/// Run condition for the states that show a menu, so that menu-only egui systems don't run in the
+/// other states
+pub fn in_menu(
+    state: Res<CurrentState<GameState>>,
+    menu_layer: Res<CurrentState<MenuLayer>>,
+) -> bool {
+    state.0 == GameState::MainMenu || menu_layer.0 != MenuLayer::None
+}
+
+/// Open the pause menu when a player presses their pause button, or has held it for long enough if
+/// [holding to pause](crate::metadata::ButtonControls::hold_to_pause) is enabled
+fn pause(
+    mut menu_stack: ResMut<MenuStack>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let paused = players.iter().any(|input| {
+        if settings.player_controls.pause.hold_to_pause {
+            // Pause once, as the button has been held for long enough
+            let held = input.current_duration(PlayerAction::Pause);
+            input.pressed(PlayerAction::Pause)
+                && held >= HOLD_TO_PAUSE_DURATION
+                && held.saturating_sub(time.delta()) < HOLD_TO_PAUSE_DURATION
+        } else {
+            input.just_pressed(PlayerAction::Pause)
+        }
+    });
+    if paused {
+        menu_stack.push(MenuLayer::Paused);
     }
 }
 
-// Transition game out of paused state
-fn unpause(mut commands: Commands, input: Query<&ActionState<MenuAction>>) {
-    let input = input.single();
-    if input.just_pressed(MenuAction::Pause) {
-        commands.insert_resource(NextState(GameState::InGame));
+/// Close the pause menu when the back button or a pause button is pressed
+fn unpause(
+    mut menu_stack: ResMut<MenuStack>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let menu_input = menu_input.single();
+    if menu_input.just_pressed(MenuAction::Back)
+        || players
+            .iter()
+            .any(|input| input.just_pressed(PlayerAction::Pause))
+    {
+        menu_stack.pop();
     }
 }
 
@@ -151,22 +306,24 @@ impl<'a> WidgetAdjacencyEntry<'a> {
 }
 
 fn handle_menu_input(
-    mut windows: ResMut<Windows>,
     input: Query<&ActionState<MenuAction>>,
     mut egui_inputs: ResMut<EguiRenderInputContainer>,
     adjacencies: Res<WidgetAdjacencies>,
     mut egui_ctx: ResMut<EguiContext>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
 ) {
     let input = input.single();
 
-    // Handle fullscreen toggling
+    // Handle fullscreen toggling, which is saved to the video settings like the window mode picked
+    // in the settings menu, so that the video settings apply it
     if input.just_pressed(MenuAction::ToggleFullscreen) {
-        if let Some(window) = windows.get_primary_mut() {
-            window.set_mode(match window.mode() {
-                WindowMode::BorderlessFullscreen => WindowMode::Windowed,
-                _ => WindowMode::BorderlessFullscreen,
-            });
-        }
+        let mut settings = storage
+            .get::<Settings>(Settings::STORAGE_KEY)
+            .unwrap_or_else(|| game.default_settings.clone());
+        settings.video.window_mode = settings.video.window_mode.toggled();
+        storage.set(Settings::STORAGE_KEY, &settings);
+        storage.save();
     }
 
     let events = &mut egui_inputs.get_mut(&WindowId::primary()).unwrap().0.events;
@@ -229,10 +386,14 @@ fn handle_menu_input(
     }
 }
 
-/// Watches for asset events for [`EguiFont`] assets and updates the corresponding fonts from the
-/// [`GameMeta`], inserting the font data into the egui context.
+/// Keeps the fonts in the egui context up to date with the fonts of the [`GameMeta`].
+///
+/// Watches for asset events for [`EguiFont`] assets, inserting the font data into the egui context
+/// when the fonts finish loading or are hot reloaded, and swaps the fonts of the families that have
+/// [other resolutions](crate::metadata::FontResolutionMeta) when the UI scale changes.
 fn update_egui_fonts(
     mut font_queue: Local<Vec<Handle<EguiFont>>>,
+    mut shown_fonts: Local<HashMap<String, Handle<EguiFont>>>,
     mut egui_ctx: ResMut<EguiContext>,
     egui_font_definitions: Option<ResMut<EguiFontDefinitions>>,
     game: Option<Res<GameMeta>>,
@@ -246,42 +407,56 @@ fn update_egui_fonts(
         }
     }
 
-    // Update queued fonts if the game is ready
-    if let Some((game, mut egui_font_definitions)) = game.zip(egui_font_definitions) {
-        for handle in font_queue.drain(..) {
-            // Get the game font name associated to this handle
-            let name = game
-                .ui_theme
-                .font_handles
-                .iter()
-                .find_map(|(font_name, font_handle)| {
-                    if font_handle == &handle {
-                        Some(font_name.clone())
-                    } else {
-                        None
-                    }
-                });
-
-            // If we were able to find the font handle in our game fonts
-            if let Some(font_name) = name {
-                // Get the font asset
-                if let Some(font) = assets.get(&handle) {
-                    // And insert it into the Egui font definitions
-                    let ctx = egui_ctx.ctx_mut();
-                    egui_font_definitions
-                        .font_data
-                        .insert(font_name.clone(), font.0.clone());
-
-                    egui_font_definitions
-                        .families
-                        .get_mut(&egui::FontFamily::Name(font_name.clone().into()))
-                        .unwrap()
-                        .push(font_name);
-
-                    ctx.set_fonts(egui_font_definitions.get_fonts().clone());
-                }
-            }
+    // Update the fonts if the game is ready
+    let Some((game, mut egui_font_definitions)) = game.zip(egui_font_definitions) else {
+        return;
+    };
+    // The font definitions are replaced with the fonts of the base resolution when the game is
+    // loaded
+    if egui_font_definitions.is_changed() {
+        shown_fonts.clear();
+    }
+
+    let ctx = egui_ctx.ctx_mut();
+    let pixels_per_point = ctx.pixels_per_point();
+    let mut fonts_changed = false;
+
+    for font_name in game.ui_theme.font_handles.keys() {
+        let Some(handle) = game.ui_theme.font_handle(font_name, pixels_per_point) else {
+            continue;
+        };
+        let is_shown = shown_fonts.get(font_name) == Some(handle);
+        if is_shown && !font_queue.contains(handle) {
+            continue;
+        }
+
+        // Get the font asset, which may still be loading
+        let Some(font) = assets.get(handle) else {
+            continue;
+        };
+
+        // And insert it into the Egui font definitions
+        egui_font_definitions
+            .font_data
+            .insert(font_name.clone(), font.0.clone());
+
+        let family = egui_font_definitions
+            .families
+            .entry(egui::FontFamily::Name(font_name.clone().into()))
+            .or_default();
+
+        // The font may already be in the family if it is being hot reloaded or swapped
+        if !family.contains(font_name) {
+            family.push(font_name.clone());
         }
+
+        shown_fonts.insert(font_name.clone(), handle.clone_weak());
+        fonts_changed = true;
+    }
+    font_queue.clear();
+
+    if fonts_changed {
+        ctx.set_fonts(egui_font_definitions.get_fonts().clone());
     }
 }
 
@@ -290,24 +465,30 @@ fn update_egui_fonts(
 fn update_ui_scale(
     mut egui_settings: ResMut<EguiSettings>,
     windows: Res<Windows>,
-    projection: Query<&OrthographicProjection, With<Camera>>,
+    projection: Query<&OrthographicProjection, With<ParallaxCameraComponent>>,
 ) {
     if let Some(window) = windows.get_primary() {
         if let Ok(projection) = projection.get_single() {
-            match projection.scaling_mode {
+            let scale = match projection.scaling_mode {
                 bevy::render::camera::ScalingMode::FixedVertical(height) => {
                     let window_height = window.height();
-                    let scale = window_height / height;
-                    egui_settings.scale_factor = scale as f64;
+                    Some(window_height / height)
                 }
                 bevy::render::camera::ScalingMode::FixedHorizontal(width) => {
                     let window_width = window.width();
-                    let scale = window_width / width;
-                    egui_settings.scale_factor = scale as f64;
+                    Some(window_width / width)
+                }
+                bevy::render::camera::ScalingMode::Auto { .. } => None,
+                bevy::render::camera::ScalingMode::None => None,
+                bevy::render::camera::ScalingMode::WindowSize => None,
+            };
+
+            // Only update the settings when the scale changes, so that they aren't marked as
+            // changed every frame
+            if let Some(scale) = scale.map(|scale| scale as f64) {
+                if egui_settings.scale_factor != scale {
+                    egui_settings.scale_factor = scale;
                 }
-                bevy::render::camera::ScalingMode::Auto { .. } => (),
-                bevy::render::camera::ScalingMode::None => (),
-                bevy::render::camera::ScalingMode::WindowSize => (),
             }
         }
     }
@@ -0,0 +1,223 @@
+//! The attract mode of the main menu.
+//!
+//! After idling on the main menu for [`IDLE_TIME`], the game's
+//! [attract demo](crate::metadata::MainMenuMeta::attract_demo) is played like an arcade machine
+//! would, with the players controlled by the demo's [`InputScript`] and an "insert coin" message
+//! over the level. Pressing any key or button returns to the main menu, and so does the end of the
+//! demo.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    input::{disconnect_player_inputs, InputScript, PlayerAction},
+    localization::LocalizationExt,
+    metadata::{FontStyle, GameMeta, LevelHandle, UIThemeState},
+    player::PlayerIndex,
+    rng::GameRng,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    utils::ResetController,
+    GameState,
+};
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUIExt};
+
+/// How long the main menu has to be idle before the demo is played
+const IDLE_TIME: Duration = Duration::from_secs(30);
+
+/// How long it takes for the "insert coin" message to blink on and off, in seconds
+const BLINK_PERIOD: f32 = 1.2;
+
+pub struct AttractModePlugin;
+
+impl Plugin for AttractModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AttractMode>()
+            .add_enter_system(GameState::MainMenu, finish_demo)
+            .add_system(start_demo_when_idle.run_in_state(GameState::MainMenu))
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .run_if(demo_playing)
+                    .with_system(disconnect_player_inputs)
+                    .with_system(exit_demo)
+                    .with_system(render_attract_overlay)
+                    .into(),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                play_demo.run_in_state(GameState::InGame),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct AttractMode {
+    /// How long the main menu has been idle for
+    idle_time: Duration,
+    demo: Option<DemoPlayback>,
+}
+
+struct DemoPlayback {
+    script: Handle<InputScript>,
+    step: u32,
+    /// The seed of the game's RNG before the demo, which is restored after it
+    seed: u64,
+}
+
+/// Run condition for when the attract mode demo is playing
+pub fn demo_playing(attract_mode: Res<AttractMode>) -> bool {
+    attract_mode.demo.is_some()
+}
+
+/// Whether any key or button has been pressed this frame
+fn any_input_pressed(
+    keys: &Input<KeyCode>,
+    mouse_buttons: &Input<MouseButton>,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> bool {
+    keys.get_just_pressed().next().is_some()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || gamepad_buttons.get_just_pressed().next().is_some()
+}
+
+fn start_demo_when_idle(
+    mut commands: Commands,
+    mut attract_mode: ResMut<AttractMode>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    game: Res<GameMeta>,
+    scripts: Res<Assets<InputScript>>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    if any_input_pressed(&keys, &mouse_buttons, &gamepad_buttons) {
+        attract_mode.idle_time = Duration::ZERO;
+        return;
+    }
+
+    attract_mode.idle_time += time.delta();
+    if attract_mode.idle_time < IDLE_TIME {
+        return;
+    }
+    attract_mode.idle_time = Duration::ZERO;
+
+    let Some(handle) = &game.main_menu.attract_demo_handle else {
+        return;
+    };
+    let Some(script) = scripts.get(handle) else {
+        return;
+    };
+
+    info!("Playing attract mode demo");
+    attract_mode.demo = Some(DemoPlayback {
+        script: handle.clone(),
+        step: 0,
+        seed: rng.seed(),
+    });
+    *rng = GameRng::new(script.seed);
+
+    commands.insert_resource(LevelHandle(script.level_handle.clone()));
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+fn play_demo(
+    mut attract_mode: ResMut<AttractMode>,
+    scripts: Res<Assets<InputScript>>,
+    mut players: Query<(&PlayerIndex, &mut ActionState<PlayerAction>)>,
+) {
+    let Some(demo) = &mut attract_mode.demo else {
+        return;
+    };
+    let Some(script) = scripts.get(&demo.script) else {
+        return;
+    };
+    if demo.step >= script.steps {
+        return;
+    }
+
+    for (player_index, mut action_state) in &mut players {
+        script
+            .input_at(player_index.0, demo.step)
+            .apply(&mut action_state);
+    }
+
+    demo.step += 1;
+}
+
+/// Return to the main menu when any input is pressed, or when the demo is over
+fn exit_demo(
+    mut commands: Commands,
+    attract_mode: Res<AttractMode>,
+    scripts: Res<Assets<InputScript>>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    reset_controller: ResetController,
+) {
+    let Some(demo) = &attract_mode.demo else {
+        return;
+    };
+    let finished = scripts
+        .get(&demo.script)
+        .map_or(true, |script| demo.step >= script.steps);
+
+    if finished || any_input_pressed(&keys, &mouse_buttons, &gamepad_buttons) {
+        reset_controller.reset_world();
+        commands.insert_resource(NextState(GameState::MainMenu));
+    }
+}
+
+/// Restore the RNG after the demo, whether it was exited or the players were defeated
+fn finish_demo(mut attract_mode: ResMut<AttractMode>, mut rng: ResMut<GameRng>) {
+    attract_mode.idle_time = Duration::ZERO;
+
+    if let Some(demo) = attract_mode.demo.take() {
+        *rng = GameRng::new(demo.seed);
+    }
+}
+
+fn render_attract_overlay(
+    mut egui_context: ResMut<EguiContext>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    time: Res<Time>,
+) {
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let heading_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Heading)
+        .expect("Missing 'heading' font style")
+        .colored(ui_theme.panel.font_color);
+    let normal_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .expect("Missing 'normal' font style")
+        .colored(ui_theme.panel.font_color);
+    let blink_on = time.elapsed_seconds() % BLINK_PERIOD < BLINK_PERIOD / 2.0;
+
+    egui::Area::new("attract_mode")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -32.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        // Hide the message instead of leaving it out, so the frame keeps its size
+                        ui.scope(|ui| {
+                            ui.set_visible(blink_on);
+                            ui.themed_label(&heading_font, &localization.get("insert-coin"));
+                        });
+                        ui.themed_label(&normal_font, &localization.get("press-any-button"));
+                    });
+                });
+        });
+}
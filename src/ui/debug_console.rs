@@ -0,0 +1,429 @@
+//! Drop-down console for running debug commands while playing.
+//!
+//! The console, toggled with the backquote key, runs commands that make it quicker to try out
+//! content without having to play up to it:
+//!
+//! - `spawn enemy <name>` spawns an enemy in front of the first player
+//! - `give item <name>` drops an item at the feet of the first player
+//! - `set health <amount>` sets the health of all players
+//! - `goto level <path>` starts the level at the given asset path
+//! - `restart level` starts the current level again, keeping the health and items of the players
+//! - `kill enemies` despawns all of the enemies
+//! - `timescale <scale>` speeds up or slows down the gameplay
+//! - `draw <category> [on|off]` toggles a category of the [hitbox debug](super::hitbox_debug)
+//!   overlay, or all of them with `all`
+//!
+//! Fighters and items are looked up by their name, so only the ones that have been loaded, like the
+//! ones used in the current level, can be spawned.
+
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_fluent::Localization;
+use bevy_mod_js_scripting::ActiveScripts;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    consts,
+    damage::Health,
+    enemy::EnemyBundle,
+    item::ItemBundle,
+    localization::LocalizationExt,
+    metadata::{FighterMeta, FighterSpawnMeta, ItemMeta, ItemSpawnMeta, LevelHandle},
+    player::Player,
+    timestep::TimeScale,
+    ui::hitbox_debug::{HitboxDebug, HitboxDebugCategory},
+    utils::{ResetController, ResetScope},
+    GameState,
+};
+
+/// The number of output lines kept in the console
+const MAX_OUTPUT_LINES: usize = 200;
+
+/// The slowest and fastest time scales that can be set
+const TIME_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.1..=4.0;
+
+/// How far in front of the first player enemies are spawned
+const ENEMY_SPAWN_DISTANCE: f32 = 150.0;
+
+const HELP: &str = "\
+spawn enemy <name>  Spawn an enemy in front of the first player
+give item <name>    Drop an item at the feet of the first player
+set health <amount> Set the health of all players
+goto level <path>   Start the level at the given asset path
+restart level       Start the current level again, keeping the health and items of the players
+kill enemies        Despawn all of the enemies
+timescale <scale>   Set how fast the gameplay runs, from 0.1 to 4
+draw <category> [on|off]
+                    Toggle drawing hitboxes, hurtboxes, grab-ranges, attack-origins, or all
+clear               Clear the console";
+
+/// State of the debug console
+#[derive(Resource, Default)]
+pub struct DebugConsole {
+    pub open: bool,
+    /// The command being typed
+    input: String,
+    output: Vec<String>,
+    /// The commands that have been entered, oldest first
+    history: Vec<String>,
+    /// The position in the history while recalling earlier commands with the arrow keys
+    history_index: Option<usize>,
+    /// The command that was entered this frame, which is run after the console is rendered
+    pending: Option<ConsoleCommand>,
+}
+
+impl DebugConsole {
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        if self.output.len() > MAX_OUTPUT_LINES {
+            self.output.remove(0);
+        }
+    }
+}
+
+/// A command that can be entered into the [`DebugConsole`]
+#[derive(Debug, Clone, PartialEq)]
+enum ConsoleCommand {
+    SpawnEnemy(String),
+    GiveItem(String),
+    SetHealth(i32),
+    GotoLevel(String),
+    RestartLevel,
+    KillEnemies,
+    TimeScale(f32),
+    /// Draw a category of the hitbox debug overlay, or all of them if it's `None`, toggling it if
+    /// it isn't set whether to draw it
+    Draw(Option<HitboxDebugCategory>, Option<bool>),
+    Help,
+    Clear,
+}
+
+impl FromStr for ConsoleCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            ["spawn", "enemy", name @ ..] if !name.is_empty() => {
+                Ok(Self::SpawnEnemy(name.join(" ")))
+            }
+            ["give", "item", name @ ..] if !name.is_empty() => Ok(Self::GiveItem(name.join(" "))),
+            ["set", "health", amount] => amount
+                .parse()
+                .map(Self::SetHealth)
+                .map_err(|_| format!("Invalid health `{amount}`")),
+            ["goto", "level", path] => Ok(Self::GotoLevel(path.to_string())),
+            ["restart", "level"] => Ok(Self::RestartLevel),
+            ["kill", "enemies"] => Ok(Self::KillEnemies),
+            ["timescale", scale] => match scale.parse() {
+                Ok(scale) if TIME_SCALE_RANGE.contains(&scale) => Ok(Self::TimeScale(scale)),
+                _ => Err(format!(
+                    "Invalid time scale `{scale}`, it must be between {} and {}",
+                    TIME_SCALE_RANGE.start(),
+                    TIME_SCALE_RANGE.end()
+                )),
+            },
+            ["draw", category, rest @ ..] if rest.len() <= 1 => {
+                let category = match *category {
+                    "all" => None,
+                    name => Some(HitboxDebugCategory::from_name(name).ok_or_else(|| {
+                        format!(
+                            "Unknown category `{name}`, enter `help` for the list of categories"
+                        )
+                    })?),
+                };
+                let enabled = match rest {
+                    [] => None,
+                    ["on"] => Some(true),
+                    ["off"] => Some(false),
+                    [other] => return Err(format!("Expected `on` or `off`, got `{other}`")),
+                    _ => unreachable!(),
+                };
+                Ok(Self::Draw(category, enabled))
+            }
+            ["help"] => Ok(Self::Help),
+            ["clear"] => Ok(Self::Clear),
+            _ => Err(format!(
+                "Unknown command `{line}`, enter `help` for the list of commands"
+            )),
+        }
+    }
+}
+
+/// Renders the console, and records the commands that are entered into it
+pub fn debug_console_window(
+    mut console: ResMut<DebugConsole>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+    input: Res<Input<KeyCode>>,
+) {
+    let just_opened = input.just_pressed(KeyCode::Grave) && !console.open;
+    if input.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+    }
+    if !console.open {
+        return;
+    }
+
+    let ctx = egui_context.ctx_mut();
+    let console = &mut *console;
+    egui::TopBottomPanel::top("debug_console")
+        .frame(egui::Frame::none().fill(egui::Color32::from_black_alpha(220)))
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(ui.ctx().screen_rect().height() * 0.4)
+                .stick_to_bottom(true)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    for line in &console.output {
+                        ui.monospace(line);
+                    }
+                });
+
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut console.input)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text(localization.get("debug-console-hint"))
+                    .desired_width(f32::INFINITY),
+            );
+            // The key that toggles the console shouldn't be typed into it
+            if response.changed() {
+                console.input.retain(|c| c != '`');
+            }
+            if just_opened {
+                response.request_focus();
+            }
+
+            if response.has_focus() {
+                let earlier = ui.input().key_pressed(egui::Key::ArrowUp);
+                let later = ui.input().key_pressed(egui::Key::ArrowDown);
+                recall_history(console, earlier, later);
+            }
+
+            if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                let line = std::mem::take(&mut console.input);
+                let line = line.trim();
+                if !line.is_empty() {
+                    console.print(format!("> {line}"));
+                    console.history.push(line.to_owned());
+                    console.history_index = None;
+
+                    match line.parse() {
+                        Ok(command) => console.pending = Some(command),
+                        Err(e) => console.print(e),
+                    }
+                }
+
+                response.request_focus();
+            }
+        });
+}
+
+/// Replace the input with an earlier or later command from the history
+fn recall_history(console: &mut DebugConsole, earlier: bool, later: bool) {
+    if console.history.is_empty() {
+        return;
+    }
+
+    let last = console.history.len() - 1;
+    let index = match (console.history_index, earlier, later) {
+        (None, true, _) => Some(last),
+        (Some(index), true, _) => Some(index.saturating_sub(1)),
+        (Some(index), _, true) if index < last => Some(index + 1),
+        (Some(_), _, true) => None,
+        _ => return,
+    };
+
+    console.history_index = index;
+    console.input = index
+        .map(|index| console.history[index].clone())
+        .unwrap_or_default();
+}
+
+/// Runs the command that was entered into the console this frame
+pub fn run_console_command(
+    mut commands: Commands,
+    mut console: ResMut<DebugConsole>,
+    state: Res<CurrentState<GameState>>,
+    asset_server: Res<AssetServer>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut items_assets: ResMut<Assets<ItemMeta>>,
+    mut active_scripts: ResMut<ActiveScripts>,
+    mut time_scale: ResMut<TimeScale>,
+    mut hitbox_debug: ResMut<HitboxDebug>,
+    mut players: Query<(&Transform, &mut Health), (With<Player>, Without<ParallaxCameraComponent>)>,
+    reset_controller: ResetController,
+) {
+    let Some(command) = console.pending.take() else {
+        return;
+    };
+
+    // Spawn locations are relative to the ground
+    let player_location = players
+        .iter()
+        .next()
+        .map(|(transform, _)| transform.translation - Vec3::new(0.0, consts::GROUND_Y, 0.0))
+        .filter(|_| state.0 == GameState::InGame);
+
+    let result = match command {
+        ConsoleCommand::SpawnEnemy(name) => {
+            find_by_name(&fighter_assets, "fighter", &name, |fighter| &fighter.name).and_then(
+                |fighter_handle| {
+                    let location = player_location
+                        .ok_or("There is no player to spawn the enemy in front of")?;
+                    commands.spawn(EnemyBundle::new(&FighterSpawnMeta {
+                        fighter: name.clone(),
+                        fighter_handle,
+                        location: Vec3::new(location.x + ENEMY_SPAWN_DISTANCE, location.y, 0.0),
+                        trip_point_x: f32::MIN,
+                        boss: false,
+                    }));
+                    Ok(format!("Spawned enemy `{name}`"))
+                },
+            )
+        }
+        ConsoleCommand::GiveItem(name) => {
+            find_by_name(&items_assets, "item", &name, |item| &item.name).and_then(|item_handle| {
+                let location = player_location.ok_or("There is no player to give the item to")?;
+                let item_spawn_meta = ItemSpawnMeta {
+                    item: name.clone(),
+                    item_handle,
+                    location: location.truncate().extend(0.0),
+                };
+                let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+                ItemBundle::spawn(
+                    item_commands,
+                    &item_spawn_meta,
+                    &mut items_assets,
+                    &mut active_scripts,
+                );
+                Ok(format!("Dropped item `{name}`"))
+            })
+        }
+        ConsoleCommand::SetHealth(amount) => {
+            for (_, mut health) in &mut players {
+                health.0 = amount;
+            }
+            Ok(format!("Set the health of the players to {amount}"))
+        }
+        ConsoleCommand::GotoLevel(path) => {
+            reset_controller.reset_world();
+            commands.insert_resource(LevelHandle(asset_server.load(&path)));
+            commands.insert_resource(NextState(GameState::LoadingLevel));
+            Ok(format!("Loading level `{path}`"))
+        }
+        ConsoleCommand::RestartLevel => {
+            if state.0 == GameState::InGame {
+                reset_controller.reset(ResetScope::Level);
+                commands.insert_resource(NextState(GameState::LoadingLevel));
+                Ok("Restarting the level".to_owned())
+            } else {
+                Err("There is no level to restart".to_owned())
+            }
+        }
+        ConsoleCommand::KillEnemies => {
+            reset_controller.reset(ResetScope::Enemies);
+            Ok("Despawned the enemies".to_owned())
+        }
+        ConsoleCommand::TimeScale(scale) => {
+            time_scale.scale = scale;
+            Ok(format!("Set the time scale to {scale}"))
+        }
+        ConsoleCommand::Draw(category, enabled) => {
+            let categories = match category {
+                Some(category) => vec![category],
+                None => HitboxDebugCategory::ALL.to_vec(),
+            };
+            // Toggling all of the categories turns them all on unless they already are
+            let enabled = enabled.unwrap_or_else(|| {
+                !categories
+                    .iter()
+                    .all(|category| *hitbox_debug.enabled_mut(*category))
+            });
+
+            for category in &categories {
+                *hitbox_debug.enabled_mut(*category) = enabled;
+            }
+            let names = categories
+                .iter()
+                .map(|category| category.name())
+                .collect::<Vec<_>>();
+            Ok(format!(
+                "Turned {} drawing {}",
+                if enabled { "on" } else { "off" },
+                names.join(", ")
+            ))
+        }
+        ConsoleCommand::Help => Ok(HELP.to_owned()),
+        ConsoleCommand::Clear => {
+            console.output.clear();
+            return;
+        }
+    };
+
+    match result {
+        Ok(line) => console.print(line),
+        Err(e) => console.print(e),
+    }
+}
+
+/// Find a loaded asset by its name, ignoring case
+fn find_by_name<T: bevy::asset::Asset>(
+    assets: &Assets<T>,
+    kind: &str,
+    name: &str,
+    get_name: impl Fn(&T) -> &String,
+) -> Result<Handle<T>, String> {
+    if let Some((id, _)) = assets
+        .iter()
+        .find(|(_, asset)| get_name(asset).eq_ignore_ascii_case(name))
+    {
+        return Ok(assets.get_handle(id));
+    }
+
+    let mut names = assets
+        .iter()
+        .map(|(_, asset)| get_name(asset).as_str())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    Err(format!(
+        "There is no loaded {kind} named `{name}`, the loaded ones are: {}",
+        names.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_console_commands() {
+        assert_eq!(
+            "spawn enemy Sharky Boss".parse(),
+            Ok(ConsoleCommand::SpawnEnemy("Sharky Boss".to_owned()))
+        );
+        assert_eq!(
+            "  set   health 50 ".parse(),
+            Ok(ConsoleCommand::SetHealth(50))
+        );
+        assert_eq!("timescale 0.5".parse(), Ok(ConsoleCommand::TimeScale(0.5)));
+        assert_eq!("restart level".parse(), Ok(ConsoleCommand::RestartLevel));
+        assert!("timescale 10".parse::<ConsoleCommand>().is_err());
+        assert!("set health lots".parse::<ConsoleCommand>().is_err());
+        assert!("spawn enemy".parse::<ConsoleCommand>().is_err());
+        assert_eq!(
+            "draw hurtboxes off".parse(),
+            Ok(ConsoleCommand::Draw(
+                Some(HitboxDebugCategory::Hurtboxes),
+                Some(false)
+            ))
+        );
+        assert_eq!("draw all".parse(), Ok(ConsoleCommand::Draw(None, None)));
+        assert!("draw everything".parse::<ConsoleCommand>().is_err());
+    }
+}
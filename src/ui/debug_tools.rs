@@ -5,6 +5,7 @@ use bevy_inspector_egui::{
     egui::{Color32, Stroke},
     WorldInspectorParams,
 };
+use bevy_parallax::ParallaxCameraComponent;
 use bevy_rapier2d::{
     plugin::RapierContext,
     prelude::{ColliderDebugColor, DebugRenderContext},
@@ -14,7 +15,17 @@ use bevy_rapier2d::{
     },
 };
 
-use crate::{camera::YSort, localization::LocalizationExt, metadata::FighterMeta};
+use crate::{
+    camera::YSort,
+    localization::{LocalizationExt, MissingLocalizationKeys},
+    metadata::FighterMeta,
+    rng::GameRng,
+    timestep::TimeScale,
+    ui::{
+        debug_console::DebugConsole, frame_data_tuning::FrameDataTuning,
+        performance_overlay::PerformanceOverlay,
+    },
+};
 
 /// System that renders the debug tools window which can be toggled by pressing F12
 pub fn debug_tools_window(
@@ -25,6 +36,12 @@ pub fn debug_tools_window(
     mut rapier_debug: ResMut<DebugRenderContext>,
     mut inspector: ResMut<WorldInspectorParams>,
     mut ysort_debug: ResMut<YSortDebug>,
+    missing_localization_keys: Res<MissingLocalizationKeys>,
+    mut frame_data_tuning: ResMut<FrameDataTuning>,
+    mut performance_overlay: ResMut<PerformanceOverlay>,
+    mut debug_console: ResMut<DebugConsole>,
+    mut time_scale: ResMut<TimeScale>,
+    rng: Res<GameRng>,
 ) {
     let ctx = egui_context.ctx_mut();
 
@@ -47,6 +64,27 @@ pub fn debug_tools_window(
         ysort_debug.enabled = !ysort_debug.enabled;
     }
 
+    // Shortcuts for looking at the gameplay frame by frame
+    if input.just_pressed(KeyCode::F4) {
+        time_scale.paused = !time_scale.paused;
+    }
+    if input.just_pressed(KeyCode::F3) {
+        if time_scale.paused {
+            time_scale.step_once();
+        } else {
+            time_scale.paused = true;
+        }
+    }
+    for (key, slow_motion) in [(KeyCode::F2, 0.25), (KeyCode::F1, 0.1)] {
+        if input.just_pressed(key) {
+            time_scale.scale = if time_scale.scale == slow_motion {
+                1.0
+            } else {
+                slow_motion
+            };
+        }
+    }
+
     // Display debug tool window
     egui::Window::new(localization.get("debug-tools"))
         // ID is needed because title comes from localizaition which can change
@@ -70,6 +108,67 @@ pub fn debug_tools_window(
                 &mut ysort_debug.enabled,
                 format!("{} ( F8 )", localization.get("show-ysort-lines")),
             );
+
+            // Show frame data tuning window
+            ui.checkbox(
+                &mut frame_data_tuning.enabled,
+                format!("{} ( F7 )", localization.get("frame-data-tuning")),
+            );
+
+            // Show performance overlay
+            ui.checkbox(
+                &mut performance_overlay.enabled,
+                format!("{} ( F6 )", localization.get("performance-overlay")),
+            );
+
+            // Show the debug console
+            ui.checkbox(
+                &mut debug_console.open,
+                format!("{} ( ` )", localization.get("debug-console")),
+            );
+
+            // Pause and step through the gameplay
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut time_scale.paused,
+                    format!("{} ( F4 )", localization.get("pause-gameplay")),
+                );
+                if ui
+                    .add_enabled(
+                        time_scale.paused,
+                        egui::Button::new(format!("{} ( F3 )", localization.get("step-gameplay"))),
+                    )
+                    .clicked()
+                {
+                    time_scale.step_once();
+                }
+            });
+            ui.label(format!(
+                "{}: {}% ( F2: 25%, F1: 10% )",
+                localization.get("time-scale"),
+                (time_scale.scale * 100.0).round()
+            ));
+
+            // Show the seed, so that the run can be reproduced with `--seed`
+            ui.label(format!("{}: {}", localization.get("rng-seed"), rng.seed()));
+
+            // List the messages that are missing from the active locale
+            let missing_keys = &missing_localization_keys.keys;
+            egui::CollapsingHeader::new(format!(
+                "{} ( {} )",
+                localization.get("missing-localization-keys"),
+                missing_keys.len()
+            ))
+            .id_source("missing_localization_keys")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for key in missing_keys {
+                            ui.label(key);
+                        }
+                    });
+            });
         });
 }
 
@@ -78,7 +177,7 @@ pub fn rapier_debug_render(
     rapier_context: Res<RapierContext>,
     mut egui_context: ResMut<EguiContext>,
     mut rapier_debug: ResMut<DebugRenderContext>,
-    camera: Query<(&Camera, &GlobalTransform)>,
+    camera: Query<(&Camera, &GlobalTransform), With<ParallaxCameraComponent>>,
     custom_colors: Query<&ColliderDebugColor>,
 ) {
     if !rapier_debug.enabled {
@@ -203,7 +302,7 @@ fn draw_ysort_lines(
     ysort_debug: Res<YSortDebug>,
     mut egui_context: ResMut<EguiContext>,
     query: Query<(&YSort, &Handle<FighterMeta>, &Transform)>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<ParallaxCameraComponent>>,
     fighter_assets: Res<Assets<FighterMeta>>,
 ) {
     if !ysort_debug.enabled {
@@ -0,0 +1,92 @@
+//! The box showing the current line of a [dialogue](crate::dialogue), with the portrait and the
+//! name of its speaker.
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{
+    dialogue::Dialogue,
+    localization::LocalizationExt,
+    metadata::{FighterMeta, FontStyle, GameMeta, UIThemeState},
+};
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUIExt};
+
+/// The width of the dialogue box
+const DIALOGUE_BOX_WIDTH: f32 = 600.0;
+
+pub fn dialogue_box(
+    mut egui_context: ResMut<EguiContext>,
+    dialogue: Res<Dialogue>,
+    game: Res<GameMeta>,
+    fighters: Res<Assets<FighterMeta>>,
+    localization: Res<Localization>,
+) {
+    let Some(line) = dialogue.current_line() else {
+        return;
+    };
+    let speaker = fighters.get(&line.speaker_handle);
+
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let font = |style: FontStyle| {
+        ui_theme
+            .font_styles
+            .get(&style)
+            .expect("Missing font style")
+            .colored(ui_theme.panel.font_color)
+    };
+    let name_font = font(FontStyle::Bigger);
+    let text_font = font(FontStyle::Normal);
+
+    let text = localization
+        .get(line.line.as_str())
+        .chars()
+        .take(dialogue.typed_chars())
+        .collect::<String>();
+
+    let border = ui_theme.hud.portrait_frame.border_size;
+    let scale = ui_theme.hud.portrait_frame.scale;
+    let portrait_frame_padding = egui::style::Margin {
+        left: border.left * scale,
+        right: border.right * scale,
+        top: border.top * scale,
+        bottom: border.bottom * scale,
+    };
+    let portrait = speaker.map(|fighter| {
+        let size = fighter.hud.portrait.image_size;
+        (
+            egui_context.add_image(fighter.hud.portrait.image_handle.clone_weak()),
+            egui::vec2(size.x, size.y),
+        )
+    });
+
+    egui::Area::new("dialogue_box")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -20.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(DIALOGUE_BOX_WIDTH);
+
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        if let Some((texture_id, size)) = portrait {
+                            BorderedFrame::new(&ui_theme.hud.portrait_frame)
+                                .padding(portrait_frame_padding)
+                                .show(ui, |ui| {
+                                    ui.image(texture_id, size);
+                                });
+                            ui.add_space(10.0);
+                        }
+
+                        ui.vertical(|ui| {
+                            if let Some(speaker) = speaker {
+                                ui.themed_label(&name_font, &speaker.name);
+                            }
+                            ui.themed_label(&text_font, &text);
+                        });
+                    });
+                });
+        });
+}
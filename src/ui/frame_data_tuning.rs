@@ -0,0 +1,348 @@
+//! Developer tool for tuning the frame data and hitboxes of fighter attacks.
+//!
+//! The tuning window, toggled with F7, allows pausing a fighter's animation, scrubbing through its
+//! frames, and adjusting the frames and hitbox of its attacks, either by editing the values or by
+//! dragging the hitbox rectangle around on screen. Changes are applied to the fighter immediately
+//! and can be saved back into the fighter's YAML file.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_fluent::Localization;
+use bevy_parallax::ParallaxCameraComponent;
+
+use crate::{
+    animation::{Animation, Facing},
+    fighter::AvailableAttacks,
+    localization::LocalizationExt,
+    metadata::FighterMeta,
+};
+
+/// The color of the hitbox rectangle drawn for the selected attack
+const HITBOX_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 80, 20);
+
+/// The size of the handle used to resize the hitbox, in points
+const RESIZE_HANDLE_SIZE: f32 = 8.0;
+
+/// State of the frame data tuning tool
+#[derive(Resource, Default)]
+pub struct FrameDataTuning {
+    pub enabled: bool,
+    /// The fighter that is being tuned
+    fighter: Option<Entity>,
+    /// The index of the attack being tuned in the fighter's [`AvailableAttacks`]
+    attack_index: usize,
+    /// Whether the fighter's animation is paused
+    paused: bool,
+    /// The result of the last save
+    status: Option<Result<String, String>>,
+}
+
+/// Renders the frame data tuning window and the hitbox of the attack being tuned
+pub fn frame_data_tuning_window(
+    mut tuning: ResMut<FrameDataTuning>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+    input: Res<Input<KeyCode>>,
+    mut fighters: Query<(
+        Entity,
+        &Name,
+        &Handle<FighterMeta>,
+        &GlobalTransform,
+        &Facing,
+        &mut Animation,
+        &mut AvailableAttacks,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    camera: Query<(&Camera, &GlobalTransform), With<ParallaxCameraComponent>>,
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))] asset_server: Res<AssetServer>,
+) {
+    if input.just_pressed(KeyCode::F7) {
+        tuning.enabled = !tuning.enabled;
+    }
+    if !tuning.enabled {
+        return;
+    }
+
+    // Deselect the fighter if it has despawned
+    if let Some(entity) = tuning.fighter {
+        if fighters.get(entity).is_err() {
+            tuning.fighter = None;
+            tuning.paused = false;
+        }
+    }
+
+    let ctx = egui_context.ctx_mut();
+    let mut enabled = tuning.enabled;
+    egui::Window::new(localization.get("frame-data-tuning"))
+        .id(egui::Id::new("frame_data_tuning"))
+        .open(&mut enabled)
+        .show(ctx, |ui| {
+            // Fighter selection
+            let selected_name = tuning
+                .fighter
+                .and_then(|entity| fighters.get(entity).ok())
+                .map(|(_, name, ..)| name.to_string())
+                .unwrap_or_default();
+            egui::ComboBox::from_label(localization.get("fighter"))
+                .selected_text(selected_name)
+                .show_ui(ui, |ui| {
+                    for (entity, name, ..) in &fighters {
+                        let selected = tuning.fighter == Some(entity);
+                        if ui
+                            .selectable_label(selected, format!("{name} ( {entity:?} )"))
+                            .clicked()
+                            && !selected
+                        {
+                            tuning.fighter = Some(entity);
+                            tuning.attack_index = 0;
+                            tuning.status = None;
+                        }
+                    }
+                });
+
+            let Some(Ok((_, _, meta_handle, _, _, mut animation, mut available_attacks))) =
+                tuning.fighter.map(|entity| fighters.get_mut(entity))
+            else {
+                return;
+            };
+
+            ui.separator();
+
+            // Animation controls
+            ui.checkbox(&mut tuning.paused, localization.get("pause-animation"));
+            ui.label(format!(
+                "{}: {}",
+                localization.get("animation"),
+                animation.current_animation.as_deref().unwrap_or("-")
+            ));
+            if let Some(frames) = animation.get_current_indices().cloned() {
+                let mut frame = animation.current_frame;
+                let last_frame = frames.end.saturating_sub(frames.start);
+                let response = ui.add_enabled(
+                    tuning.paused,
+                    egui::Slider::new(&mut frame, 0..=last_frame).text(localization.get("frame")),
+                );
+                if response.changed() {
+                    animation.current_frame = frame;
+                    animation.played_once = false;
+                }
+            }
+
+            ui.separator();
+
+            // Attack selection
+            let attack_count = available_attacks.attacks.len();
+            tuning.attack_index = tuning.attack_index.min(attack_count.saturating_sub(1));
+            let attack_index = &mut tuning.attack_index;
+            egui::ComboBox::from_label(localization.get("attack"))
+                .selected_text(
+                    available_attacks
+                        .attacks
+                        .get(*attack_index)
+                        .map(|attack| attack.name.clone())
+                        .unwrap_or_default(),
+                )
+                .show_ui(ui, |ui| {
+                    for (i, attack) in available_attacks.attacks.iter().enumerate() {
+                        ui.selectable_value(attack_index, i, attack.name.as_str());
+                    }
+                });
+            let attack_index = *attack_index;
+
+            let Some(attack) = available_attacks.attacks.get_mut(attack_index) else {
+                return;
+            };
+
+            // Frame data
+            egui::Grid::new("frame_data_tuning_grid")
+                .num_columns(3)
+                .show(ui, |ui| {
+                    let frames = &mut attack.frames;
+                    ui.label(localization.get("startup-frame"));
+                    ui.add(egui::DragValue::new(&mut frames.startup).speed(0.1));
+                    ui.end_row();
+                    ui.label(localization.get("active-frame"));
+                    ui.add(egui::DragValue::new(&mut frames.active).speed(0.1));
+                    ui.end_row();
+                    ui.label(localization.get("recovery-frame"));
+                    ui.add(egui::DragValue::new(&mut frames.recovery).speed(0.1));
+                    ui.end_row();
+
+                    let hitbox = &mut attack.hitbox;
+                    ui.label(localization.get("hitbox-size"));
+                    ui.add(egui::DragValue::new(&mut hitbox.size.x).speed(0.5));
+                    ui.add(egui::DragValue::new(&mut hitbox.size.y).speed(0.5));
+                    ui.end_row();
+                    ui.label(localization.get("hitbox-offset"));
+                    ui.add(egui::DragValue::new(&mut hitbox.offset.x).speed(0.5));
+                    ui.add(egui::DragValue::new(&mut hitbox.offset.y).speed(0.5));
+                    ui.end_row();
+                });
+            attack.hitbox.size = attack.hitbox.size.max(Vec2::ZERO);
+
+            ui.separator();
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button(localization.get("save-to-file")).clicked() {
+                tuning.status = Some(save_attack(&asset_server, meta_handle, attack));
+            }
+            #[cfg(target_arch = "wasm32")]
+            let _ = meta_handle;
+
+            match &tuning.status {
+                Some(Ok(message)) => {
+                    ui.colored_label(egui::Color32::LIGHT_GREEN, message);
+                }
+                Some(Err(message)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, message);
+                }
+                None => (),
+            }
+        });
+    tuning.enabled = enabled;
+
+    let Some(Ok((_, _, meta_handle, transform, facing, mut animation, mut available_attacks))) =
+        tuning.fighter.map(|entity| fighters.get_mut(entity))
+    else {
+        return;
+    };
+
+    // Keep the animation paused, because starting a new animation will un-pause it
+    if tuning.paused {
+        animation.timer.pause();
+    } else if animation.timer.paused() {
+        animation.timer.unpause();
+    }
+
+    // Draw the hitbox of the selected attack and allow dragging it around
+    let (Some(fighter), Ok((camera, camera_transform)), Some(attack)) = (
+        fighter_assets.get(meta_handle),
+        camera.get_single(),
+        available_attacks.attacks.get_mut(tuning.attack_index),
+    ) else {
+        return;
+    };
+
+    // Hitboxes are mirrored when facing left, the same way they are when attacks are spawned
+    let center = transform.translation().truncate()
+        + facing.mirror(attack.hitbox.offset)
+        + Vec2::new(0.0, fighter.collision_offset);
+    let half_size = attack.hitbox.size / 2.0;
+
+    egui::Area::new("frame_data_tuning_hitbox")
+        .fixed_pos(egui::Pos2::ZERO)
+        .order(egui::Order::Background)
+        .show(ctx, |ui| {
+            let screen_size = ui.ctx().input().screen_rect().size();
+            let to_screen = |world: Vec2| -> Option<egui::Pos2> {
+                let ndc = camera.world_to_ndc(camera_transform, world.extend(0.0))?;
+                let half_screen = screen_size / 2.0;
+                Some(egui::pos2(
+                    (ndc.x + 1.0) * half_screen.x,
+                    (1.0 - ndc.y) * half_screen.y,
+                ))
+            };
+
+            let (Some(min), Some(max), Some(unit)) = (
+                to_screen(center + Vec2::new(-half_size.x, half_size.y)),
+                to_screen(center + Vec2::new(half_size.x, -half_size.y)),
+                to_screen(center + Vec2::X),
+            ) else {
+                return;
+            };
+            let Some(origin) = to_screen(center) else {
+                return;
+            };
+            // The number of screen points per world unit
+            let scale = (unit.x - origin.x).max(f32::EPSILON);
+
+            let rect = egui::Rect::from_min_max(min, max);
+            let painter = ui.painter();
+            painter.rect_stroke(rect, 0.0, (1.0, HITBOX_COLOR));
+
+            // Drag the hitbox to change its offset
+            let move_response = ui.interact(
+                rect,
+                egui::Id::new("frame_data_tuning_hitbox_move"),
+                egui::Sense::drag(),
+            );
+            if move_response.dragged() {
+                let delta = move_response.drag_delta() / scale;
+                attack.hitbox.offset += Vec2::new(delta.x * direction, -delta.y);
+            }
+
+            // Drag the corner handle to change its size
+            let handle = egui::Rect::from_center_size(
+                rect.right_bottom(),
+                egui::Vec2::splat(RESIZE_HANDLE_SIZE),
+            );
+            painter.rect_filled(handle, 0.0, HITBOX_COLOR);
+            let resize_response = ui.interact(
+                handle,
+                egui::Id::new("frame_data_tuning_hitbox_resize"),
+                egui::Sense::drag(),
+            );
+            if resize_response.dragged() {
+                let delta = resize_response.drag_delta() / scale;
+                attack.hitbox.size =
+                    (attack.hitbox.size + Vec2::new(delta.x, delta.y) * 2.0).max(Vec2::ZERO);
+            }
+        });
+}
+
+/// Write the frame data and hitbox of an attack back into the fighter's YAML file.
+///
+/// > **Note:** The file is re-serialized, so comments and formatting in it are not preserved.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_attack(
+    asset_server: &AssetServer,
+    meta_handle: &Handle<FighterMeta>,
+    attack: &crate::metadata::AttackMeta,
+) -> Result<String, String> {
+    use serde_yaml::{Mapping, Value};
+
+    let asset_path = asset_server
+        .get_handle_path(meta_handle)
+        .ok_or("Fighter has no asset path")?;
+    let path = crate::config::ENGINE_CONFIG
+        .asset_dir_path()
+        .join(asset_path.path());
+
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let mut fighter: Value = serde_yaml::from_slice(&bytes).map_err(|e| e.to_string())?;
+
+    let attack_value = fighter
+        .get_mut("attacks")
+        .and_then(Value::as_sequence_mut)
+        .and_then(|attacks| {
+            attacks
+                .iter_mut()
+                .find(|x| x.get("name").and_then(Value::as_str) == Some(attack.name.as_str()))
+        })
+        .and_then(Value::as_mapping_mut)
+        .ok_or_else(|| {
+            format!(
+                "Attack `{}` is not defined in {}, it may be inherited from another fighter",
+                attack.name,
+                path.display()
+            )
+        })?;
+
+    let vec2 = |v: Vec2| Value::Sequence(vec![v.x.into(), v.y.into()]);
+
+    let mut frames = Mapping::new();
+    frames.insert("startup".into(), (attack.frames.startup as u64).into());
+    frames.insert("active".into(), (attack.frames.active as u64).into());
+    frames.insert("recovery".into(), (attack.frames.recovery as u64).into());
+    attack_value.insert("frames".into(), Value::Mapping(frames));
+
+    let mut hitbox = Mapping::new();
+    hitbox.insert("size".into(), vec2(attack.hitbox.size));
+    hitbox.insert("offset".into(), vec2(attack.hitbox.offset));
+    attack_value.insert("hitbox".into(), Value::Mapping(hitbox));
+
+    let yaml = serde_yaml::to_string(&fighter).map_err(|e| e.to_string())?;
+    std::fs::write(&path, yaml).map_err(|e| e.to_string())?;
+
+    Ok(format!("Saved `{}` to {}", attack.name, path.display()))
+}
@@ -0,0 +1,195 @@
+//! Debug overlay that draws the shapes that decide how fighters hit each other.
+//!
+//! Unlike the collision shapes drawn by rapier, the shapes are colored by what they are used for,
+//! and every category can be shown on its own with the `draw <category> [on|off]` command of the
+//! [debug console](super::debug_console). The categories are:
+//!
+//! - `hitboxes`, the active colliders of attacks
+//! - `hurtboxes`, the colliders that fighters are hit on
+//! - `grab-ranges`, how close players have to be to items to pick them up, and to enemies to grab
+//!   them
+//! - `attack-origins`, where attacks are anchored, with a line from the fighter that made them
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_parallax::ParallaxCameraComponent;
+use bevy_rapier2d::prelude::Collider;
+
+use crate::{
+    attack::{Attack, Hurtbox},
+    consts,
+    player::Player,
+};
+
+const HITBOX_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 60, 60);
+const HURTBOX_COLOR: egui::Color32 = egui::Color32::from_rgb(60, 200, 255);
+const GRAB_RANGE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 40);
+const ATTACK_ORIGIN_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 120, 255);
+
+/// The size of the cross drawn at attack origins, in points
+const ORIGIN_CROSS_SIZE: f32 = 4.0;
+
+/// A kind of shape drawn by the hitbox debug overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitboxDebugCategory {
+    Hitboxes,
+    Hurtboxes,
+    GrabRanges,
+    AttackOrigins,
+}
+
+impl HitboxDebugCategory {
+    pub const ALL: [Self; 4] = [
+        Self::Hitboxes,
+        Self::Hurtboxes,
+        Self::GrabRanges,
+        Self::AttackOrigins,
+    ];
+
+    /// The name of the category in debug console commands
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hitboxes => "hitboxes",
+            Self::Hurtboxes => "hurtboxes",
+            Self::GrabRanges => "grab-ranges",
+            Self::AttackOrigins => "attack-origins",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == name)
+    }
+}
+
+/// Which categories of shapes the overlay draws
+#[derive(Resource, Default)]
+pub struct HitboxDebug {
+    pub hitboxes: bool,
+    pub hurtboxes: bool,
+    pub grab_ranges: bool,
+    pub attack_origins: bool,
+}
+
+impl HitboxDebug {
+    pub fn enabled_mut(&mut self, category: HitboxDebugCategory) -> &mut bool {
+        match category {
+            HitboxDebugCategory::Hitboxes => &mut self.hitboxes,
+            HitboxDebugCategory::Hurtboxes => &mut self.hurtboxes,
+            HitboxDebugCategory::GrabRanges => &mut self.grab_ranges,
+            HitboxDebugCategory::AttackOrigins => &mut self.attack_origins,
+        }
+    }
+}
+
+/// Renders the enabled categories of the hitbox debug overlay
+pub fn draw_hitbox_debug(
+    hitbox_debug: Res<HitboxDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    camera: Query<(&Camera, &GlobalTransform), With<ParallaxCameraComponent>>,
+    attacks: Query<(&GlobalTransform, Option<&Collider>, Option<&Parent>), With<Attack>>,
+    hurtboxes: Query<(&GlobalTransform, &Collider), With<Hurtbox>>,
+    players: Query<&GlobalTransform, With<Player>>,
+    parents: Query<&GlobalTransform>,
+) {
+    let HitboxDebug {
+        hitboxes: draw_hitboxes,
+        hurtboxes: draw_hurtboxes,
+        grab_ranges: draw_grab_ranges,
+        attack_origins: draw_attack_origins,
+    } = *hitbox_debug;
+    if !(draw_hitboxes || draw_hurtboxes || draw_grab_ranges || draw_attack_origins) {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+
+    egui::Area::new("hitbox_debug")
+        .fixed_pos(egui::Pos2::ZERO)
+        .order(egui::Order::Background)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let screen_size = ui.ctx().input().screen_rect().size();
+            let to_screen = |world: Vec2| -> Option<egui::Pos2> {
+                let ndc = camera.world_to_ndc(camera_transform, world.extend(0.0))?;
+                let half_screen = screen_size / 2.0;
+                Some(egui::pos2(
+                    (ndc.x + 1.0) * half_screen.x,
+                    (1.0 - ndc.y) * half_screen.y,
+                ))
+            };
+            let painter = ui.painter();
+
+            let draw_collider =
+                |transform: &GlobalTransform, collider: &Collider, color: egui::Color32| {
+                    let Some(cuboid) = collider.as_cuboid() else {
+                        return;
+                    };
+                    let center = transform.translation().truncate();
+                    let half_size = cuboid.half_extents();
+                    if let (Some(a), Some(b)) =
+                        (to_screen(center - half_size), to_screen(center + half_size))
+                    {
+                        painter.rect_stroke(egui::Rect::from_two_pos(a, b), 0.0, (1.0, color));
+                    }
+                };
+
+            if draw_hurtboxes {
+                for (transform, collider) in &hurtboxes {
+                    draw_collider(transform, collider, HURTBOX_COLOR);
+                }
+            }
+
+            if draw_hitboxes {
+                for (transform, collider, _) in &attacks {
+                    if let Some(collider) = collider {
+                        draw_collider(transform, collider, HITBOX_COLOR);
+                    }
+                }
+            }
+
+            if draw_grab_ranges {
+                for transform in &players {
+                    let center = transform.translation().truncate();
+                    for radius in [consts::PICK_ITEM_RADIUS, consts::GRAB_FIGHTER_RADIUS] {
+                        let edge = center + Vec2::new(radius, 0.0);
+                        if let (Some(center), Some(edge)) = (to_screen(center), to_screen(edge)) {
+                            painter.circle_stroke(
+                                center,
+                                edge.x - center.x,
+                                (1.0, GRAB_RANGE_COLOR),
+                            );
+                        }
+                    }
+                }
+            }
+
+            if draw_attack_origins {
+                for (transform, _, parent) in &attacks {
+                    let Some(origin) = to_screen(transform.translation().truncate()) else {
+                        continue;
+                    };
+
+                    let stroke = (1.0, ATTACK_ORIGIN_COLOR);
+                    let offset = egui::Vec2::splat(ORIGIN_CROSS_SIZE);
+                    painter.line_segment([origin - offset, origin + offset], stroke);
+                    painter.line_segment(
+                        [
+                            origin + egui::vec2(-offset.x, offset.y),
+                            origin + egui::vec2(offset.x, -offset.y),
+                        ],
+                        stroke,
+                    );
+
+                    let attacker = parent
+                        .and_then(|parent| parents.get(parent.get()).ok())
+                        .and_then(|transform| to_screen(transform.translation().truncate()));
+                    if let Some(attacker) = attacker {
+                        painter.line_segment([attacker, origin], stroke);
+                    }
+                }
+            }
+        });
+}
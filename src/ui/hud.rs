@@ -2,18 +2,65 @@
 
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContext};
+use bevy_fluent::Localization;
+use bevy_parallax::ParallaxCameraComponent;
 
 use crate::{
+    arcade::ArcadeRun,
+    config::ENGINE_CONFIG,
     damage::Health,
+    damage_numbers::DamageNumbers,
     fighter::Inventory,
-    metadata::{FighterMeta, GameMeta},
+    fighter_state::Blocking,
+    localization::LocalizationExt,
+    metadata::{ColorSettings, FighterMeta, FontStyle, GameMeta, UIThemeState},
+    nameplate::Nameplates,
     player::PlayerIndex,
+    status_effect::StatusEffects,
+    survival::SurvivalRun,
+    team_up::TeamUpMeter,
     ui::widgets::{bordered_frame::BorderedFrame, progress_bar::ProgressBar, EguiUIExt},
+    versus::{RoundState, VersusMatch},
     Player, Stats,
 };
 
+/// The information shown in the HUD for a player
+#[derive(Clone)]
+pub struct PlayerInfo {
+    index: usize,
+    name: String,
+    life: f32,
+    portrait_texture_id: egui::TextureId,
+    portrait_size: egui::Vec2,
+    item: Option<ItemInfo>,
+    /// How full the player's team-up meter is, if the game has team-ups
+    team_up_meter: Option<f32>,
+}
+
+#[derive(Clone)]
+pub struct ItemInfo {
+    texture_id: egui::TextureId,
+    size: egui::Vec2,
+}
+
+/// The color of the team-up meters
+const TEAM_UP_METER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 200, 40);
+
+/// The size of the icons of the status effects on the players
+const STATUS_EFFECT_ICON_SIZE: f32 = 8.0;
+
+/// The share of the screen height covered by each of the black bars of the team-up cinematic
+const CINEMATIC_BAR_HEIGHT: f32 = 0.12;
+
+/// Renders the player portraits, life bars, team-up meters, and items, and whether the players are
+/// blocking or have status effects on them.
+///
+/// The player info is only collected again when a player has changed, because the HUD is rendered
+/// every frame but usually stays the same for a long time. Blocks and status effects are short, so
+/// they are looked up every frame instead.
 pub fn render_hud(
     mut egui_context: ResMut<EguiContext>,
+    mut player_infos: Local<Vec<PlayerInfo>>,
     players: Query<
         (
             &PlayerIndex,
@@ -21,55 +68,83 @@ pub fn render_hud(
             &Health,
             &Handle<FighterMeta>,
             &Inventory,
+            &TeamUpMeter,
         ),
         With<Player>,
     >,
+    changed_players: Query<
+        (),
+        (
+            With<Player>,
+            Or<(
+                Changed<Stats>,
+                Changed<Health>,
+                Changed<Handle<FighterMeta>>,
+                Changed<Inventory>,
+                Changed<TeamUpMeter>,
+            )>,
+        ),
+    >,
+    removed_players: RemovedComponents<Player>,
+    blocking_players: Query<(&PlayerIndex, &Blocking), With<Player>>,
+    effect_players: Query<(&PlayerIndex, &StatusEffects), With<Player>>,
+    mut fighter_events: EventReader<AssetEvent<FighterMeta>>,
     game: Res<GameMeta>,
     fighter_assets: Res<Assets<FighterMeta>>,
+    color_settings: Option<Res<ColorSettings>>,
+    localization: Res<Localization>,
 ) {
-    let ui_theme = &game.ui_theme;
-
-    // Helper struct for holding player hud info
-    struct PlayerInfo {
-        name: String,
-        life: f32,
-        portrait_texture_id: egui::TextureId,
-        portrait_size: egui::Vec2,
-        item: Option<ItemInfo>,
-    }
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let lifebar_color = color_settings
+        .and_then(|colors| colors.hud_palette.lifebar_color())
+        .map(|color| {
+            let [r, g, b, _] = color.as_rgba_u8();
+            egui::Color32::from_rgb(r, g, b)
+        });
+
+    // Collect player info if anything has changed since the last frame
+    let fighters_changed = !fighter_events.is_empty();
+    fighter_events.clear();
+    if fighters_changed || !changed_players.is_empty() || removed_players.iter().next().is_some() {
+        let mut players = players.iter().collect::<Vec<_>>();
+        players.sort_by_key(|(player_i, ..)| player_i.0);
 
-    struct ItemInfo {
-        texture_id: egui::TextureId,
-        size: egui::Vec2,
+        *player_infos = players
+            .into_iter()
+            .filter_map(
+                |(player_i, stats, health, fighter_handle, inventory, team_up_meter)| {
+                    fighter_assets.get(fighter_handle).map(|fighter| {
+                        let portrait_size = fighter.hud.portrait.image_size;
+                        PlayerInfo {
+                            index: player_i.0,
+                            name: fighter.name.clone(),
+                            life: **health as f32 / stats.max_health as f32,
+                            portrait_texture_id: egui_context
+                                .add_image(fighter.hud.portrait.image_handle.clone_weak()),
+                            portrait_size: egui::Vec2::new(portrait_size.x, portrait_size.y),
+                            item: inventory.as_ref().map(|item_meta| ItemInfo {
+                                texture_id: egui_context
+                                    .add_image(item_meta.image.image_handle.clone_weak()),
+                                size: egui::Vec2::new(
+                                    item_meta.image.image_size.x,
+                                    item_meta.image.image_size.y,
+                                ),
+                            }),
+                            team_up_meter: game
+                                .team_up
+                                .as_ref()
+                                .map(|team_up| team_up_meter.fraction(team_up)),
+                        }
+                    })
+                },
+            )
+            .collect::<Vec<_>>();
     }
 
-    // Collect player info
-    let mut players = players.iter().collect::<Vec<_>>();
-    players.sort_by_key(|(player_i, _, _, _, _)| player_i.0);
-
-    let player_infos = players
-        .into_iter()
-        .filter_map(|(_, stats, health, fighter_handle, inventory)| {
-            fighter_assets.get(fighter_handle).map(|fighter| {
-                let portrait_size = fighter.hud.portrait.image_size;
-                PlayerInfo {
-                    name: fighter.name.clone(),
-                    life: **health as f32 / stats.max_health as f32,
-                    portrait_texture_id: egui_context
-                        .add_image(fighter.hud.portrait.image_handle.clone_weak()),
-                    portrait_size: egui::Vec2::new(portrait_size.x, portrait_size.y),
-                    item: inventory.as_ref().map(|item_meta| ItemInfo {
-                        texture_id: egui_context
-                            .add_image(item_meta.image.image_handle.clone_weak()),
-                        size: egui::Vec2::new(
-                            item_meta.image.image_size.x,
-                            item_meta.image.image_size.y,
-                        ),
-                    }),
-                }
-            })
-        })
-        .collect::<Vec<_>>();
+    // Don't run an egui pass for an empty HUD
+    if player_infos.is_empty() {
+        return;
+    }
 
     let border = ui_theme.hud.portrait_frame.border_size;
     let scale = ui_theme.hud.portrait_frame.scale;
@@ -85,12 +160,26 @@ pub fn render_hud(
         .show(egui_context.ctx_mut(), |ui| {
             ui.add_space(10.0);
             ui.horizontal(|ui| {
-                for player in player_infos {
+                for player in player_infos.iter() {
                     ui.add_space(20.0);
 
                     ui.vertical(|ui| {
                         ui.allocate_ui(egui::Vec2::new(ui_theme.hud.player_hud_width, 50.), |ui| {
-                            ui.themed_label(&ui_theme.hud.font, &player.name);
+                            ui.horizontal(|ui| {
+                                ui.themed_label(&ui_theme.hud.font, &player.name);
+
+                                let blocking = blocking_players
+                                    .iter()
+                                    .find(|(player_i, _)| player_i.0 == player.index);
+                                if let Some((_, blocking)) = blocking {
+                                    let label = if blocking.parried {
+                                        "parry"
+                                    } else {
+                                        "blocking"
+                                    };
+                                    ui.themed_label(&ui_theme.hud.font, &localization.get(label));
+                                }
+                            });
 
                             ui.horizontal(|ui| {
                                 BorderedFrame::new(&ui_theme.hud.portrait_frame)
@@ -103,10 +192,40 @@ pub fn render_hud(
                                     ui.add_space(5.0);
                                     ProgressBar::new(&ui_theme.hud.lifebar, player.life)
                                         .min_width(ui.available_width())
+                                        .color(lifebar_color)
                                         .show(ui);
 
+                                    if let Some(team_up_meter) = player.team_up_meter {
+                                        ui.add_space(3.0);
+                                        ProgressBar::new(&ui_theme.hud.lifebar, team_up_meter)
+                                            .min_width(ui.available_width())
+                                            .color(Some(TEAM_UP_METER_COLOR))
+                                            .show(ui);
+                                    }
+
+                                    let effects = effect_players
+                                        .iter()
+                                        .find(|(player_i, _)| player_i.0 == player.index);
+                                    if let Some((_, effects)) = effects {
+                                        ui.add_space(3.0);
+                                        ui.horizontal(|ui| {
+                                            for effect in effects.iter() {
+                                                let (rect, _) = ui.allocate_exact_size(
+                                                    egui::Vec2::splat(STATUS_EFFECT_ICON_SIZE),
+                                                    egui::Sense::hover(),
+                                                );
+                                                let [r, g, b, _] = effect.color().as_rgba_u8();
+                                                ui.painter().rect_filled(
+                                                    rect,
+                                                    2.0,
+                                                    egui::Color32::from_rgb(r, g, b),
+                                                );
+                                            }
+                                        });
+                                    }
+
                                     ui.vertical(|ui| {
-                                        if let Some(item) = player.item {
+                                        if let Some(item) = &player.item {
                                             ui.add_space(5.0);
                                             ui.image(item.texture_id, item.size);
                                         }
@@ -119,3 +238,329 @@ pub fn render_hud(
             });
         });
 }
+
+/// Renders the black bars and the banner of the team-up cinematic
+pub fn render_team_up_cinematic(
+    mut egui_context: ResMut<EguiContext>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let heading_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Heading)
+        .expect("Missing 'heading' font style")
+        .colored(ui_theme.panel.font_color);
+
+    let ctx = egui_context.ctx_mut();
+    let screen = ctx.screen_rect();
+    let bar_height = screen.height() * CINEMATIC_BAR_HEIGHT;
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("team_up_cinematic"),
+    ));
+    for bar in [
+        egui::Rect::from_min_size(screen.min, egui::vec2(screen.width(), bar_height)),
+        egui::Rect::from_min_size(
+            egui::pos2(screen.min.x, screen.max.y - bar_height),
+            egui::vec2(screen.width(), bar_height),
+        ),
+    ] {
+        painter.rect_filled(bar, 0.0, egui::Color32::BLACK);
+    }
+
+    egui::Area::new("team_up_banner")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .interactable(false)
+        .show(ctx, |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&heading_font, &localization.get("team-up"));
+                });
+        });
+}
+
+/// Renders the wave and the score of a survival run
+pub fn render_survival_hud(
+    mut egui_context: ResMut<EguiContext>,
+    run: Res<SurvivalRun>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+
+    egui::Area::new("survival_hud")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-20.0, 10.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.vertical(|ui| {
+                ui.themed_label(
+                    &ui_theme.hud.font,
+                    &format!("{} {}", localization.get("wave"), run.wave),
+                );
+                ui.themed_label(
+                    &ui_theme.hud.font,
+                    &format!("{} {}", localization.get("score"), run.score),
+                );
+            });
+        });
+}
+
+/// Renders the score and the lives of an arcade run, and the time left to continue once the players
+/// are out of lives
+pub fn render_arcade_hud(
+    mut egui_context: ResMut<EguiContext>,
+    run: Res<ArcadeRun>,
+    players: Query<&PlayerIndex, With<Player>>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+
+    let mut player_indices = players.iter().map(|index| index.0).collect::<Vec<_>>();
+    player_indices.sort_unstable();
+    egui::Area::new("arcade_hud")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-20.0, 10.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.vertical(|ui| {
+                ui.themed_label(
+                    &ui_theme.hud.font,
+                    &format!("{} {}", localization.get("score"), run.score),
+                );
+                for player in player_indices {
+                    ui.themed_label(
+                        &ui_theme.hud.font,
+                        &format!(
+                            "P{} {} {}",
+                            player + 1,
+                            localization.get("lives"),
+                            run.lives_left(player)
+                        ),
+                    );
+                }
+            });
+        });
+
+    let Some(timer) = &run.continue_timer else {
+        return;
+    };
+    let heading_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Heading)
+        .expect("Missing 'heading' font style")
+        .colored(ui_theme.panel.font_color);
+    let font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .expect("Missing 'normal' font style")
+        .colored(ui_theme.panel.font_color);
+    let time_left = timer.remaining_secs().ceil();
+
+    egui::Area::new("arcade_continue")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(
+                            &heading_font,
+                            &format!("{} {time_left}", localization.get("continue-question")),
+                        );
+                        ui.themed_label(&font, &localization.get("press-confirm-to-continue"));
+                        ui.themed_label(
+                            &font,
+                            &format!(
+                                "{}: {}",
+                                localization.get("continues-left"),
+                                run.continues_left
+                            ),
+                        );
+                    });
+                });
+        });
+}
+
+/// Renders the round, the time left, and the round wins of a versus match, and who won the round
+/// once it's over
+pub fn render_versus_hud(
+    mut egui_context: ResMut<EguiContext>,
+    versus: Res<VersusMatch>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let heading_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Heading)
+        .expect("Missing 'heading' font style")
+        .colored(ui_theme.panel.font_color);
+    let time_left = versus.round_timer.remaining_secs().ceil();
+
+    egui::Area::new("versus_hud")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.themed_label(
+                    &ui_theme.hud.font,
+                    &format!("{} {}", localization.get("round"), versus.round),
+                );
+                ui.themed_label(&ui_theme.hud.font, &time_left.to_string());
+                ui.themed_label(
+                    &ui_theme.hud.font,
+                    &format!("{} - {}", versus.wins[0], versus.wins[1]),
+                );
+            });
+        });
+
+    let RoundState::Over { winner } = versus.state else {
+        return;
+    };
+    let message = match winner {
+        Some(team) => format!(
+            "{} {} {}",
+            localization.get("team"),
+            team + 1,
+            localization.get("wins-the-round")
+        ),
+        None => localization.get("draw"),
+    };
+
+    egui::Area::new("versus_round_over")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&heading_font, &message);
+                });
+        });
+}
+
+/// Renders the numbers floating up from the fighters that are hit
+pub fn render_damage_numbers(
+    mut egui_context: ResMut<EguiContext>,
+    damage_numbers: Res<DamageNumbers>,
+    camera: Query<(&Camera, &GlobalTransform), With<ParallaxCameraComponent>>,
+    game: Res<GameMeta>,
+) {
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let Some(theme) = &ui_theme.hud.damage_numbers else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+
+    egui::Area::new("damage_numbers")
+        .fixed_pos(egui::Pos2::ZERO)
+        .order(egui::Order::Background)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let screen_size = ui.ctx().input().screen_rect().size();
+            let painter = ui.painter();
+
+            for number in damage_numbers.iter() {
+                let (offset, alpha) = number.offset_and_alpha();
+                let Some(ndc) = camera.world_to_ndc(
+                    camera_transform,
+                    (number.position + Vec2::new(0.0, offset)).extend(0.0),
+                ) else {
+                    continue;
+                };
+                let bottom_center = egui::pos2(
+                    (ndc.x + 1.0) * screen_size.x / 2.0,
+                    (1.0 - ndc.y) * screen_size.y / 2.0,
+                );
+
+                let font = theme.font(number.kind);
+                painter.text(
+                    bottom_center,
+                    egui::Align2::CENTER_BOTTOM,
+                    number.text(),
+                    font.font_id(),
+                    egui::Color32::from(font.color).linear_multiply(alpha),
+                );
+            }
+        });
+}
+
+/// Renders the nameplate of the enemy being introduced, with an icon for every level of its threat
+pub fn render_nameplate(
+    mut egui_context: ResMut<EguiContext>,
+    nameplates: Res<Nameplates>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let (Some(nameplate), Some(meta)) = (nameplates.current(), &game.nameplates) else {
+        return;
+    };
+
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let heading_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Heading)
+        .expect("Missing 'heading' font style")
+        .colored(ui_theme.panel.font_color);
+    let font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .expect("Missing 'normal' font style")
+        .colored(ui_theme.panel.font_color);
+    let icon = egui_context.add_image(meta.threat_icon.image_handle.clone_weak());
+    let icon_size = egui::vec2(meta.threat_icon.image_size.x, meta.threat_icon.image_size.y);
+
+    egui::Area::new("nameplate")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        if nameplate.boss {
+                            ui.themed_label(&font, &localization.get("boss"));
+                        }
+                        ui.themed_label(
+                            &heading_font,
+                            &localization.get(nameplate.meta.name.as_str()),
+                        );
+                        ui.horizontal(|ui| {
+                            for _ in 0..nameplate.meta.threat {
+                                ui.image(icon, icon_size);
+                            }
+                        });
+                    });
+                });
+        });
+}
+
+/// Renders a watermark listing the enabled cheats, so that screenshots and recordings made with
+/// them can't be mistaken for normal play
+pub fn render_cheats_watermark(
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+) {
+    let text = format!(
+        "{}: {}",
+        localization.get("cheats-enabled"),
+        ENGINE_CONFIG.cheats.names().join(", ")
+    );
+
+    egui::Area::new("cheats_watermark")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(text)
+                    .strong()
+                    .color(egui::Color32::from_rgb(255, 80, 80)),
+            );
+        });
+}
@@ -0,0 +1,139 @@
+//! The prompts above the [interactables](crate::interaction::Interactable) nearest to the players,
+//! with the buttons that interact with them.
+//!
+//! The prompts are only shown if the `interaction_prompts` feature is enabled.
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use bevy_parallax::ParallaxCameraComponent;
+
+use crate::{
+    fighter::Inventory,
+    input::InputDevices,
+    interaction::{nearest_interactable, Interactable},
+    localization::LocalizationExt,
+    metadata::{ButtonStyle, FontStyle, GameMeta, Settings, UIThemeState},
+    player::{Player, PlayerIndex},
+};
+
+use super::{
+    main_menu::format_input,
+    widgets::{bordered_frame::BorderedFrame, EguiUIExt},
+};
+
+/// How far above the interactables the prompts are shown, in world units
+const PROMPT_OFFSET: f32 = 36.0;
+
+/// The size of the space that a prompt is centered in, in points
+const PROMPT_MAX_SIZE: egui::Vec2 = egui::vec2(300.0, 100.0);
+
+/// Show the prompt of the nearest interactable of every player, with the buttons of the device
+/// that the player used last
+pub fn interaction_prompt(
+    mut egui_context: ResMut<EguiContext>,
+    camera: Query<(&Camera, &GlobalTransform), With<ParallaxCameraComponent>>,
+    players: Query<(&PlayerIndex, &GlobalTransform, &Inventory), With<Player>>,
+    interactables: Query<(Entity, &GlobalTransform, &Interactable)>,
+    input_devices: Res<InputDevices>,
+    game: Res<GameMeta>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+) {
+    let controls = &settings.player_controls;
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+
+    // Players that are next to each other share the prompt of the interactable they are near
+    let mut prompts: Vec<(Entity, Vec2, &Interactable, usize)> = Vec::new();
+    for (player_index, player_transform, inventory) in &players {
+        let nearest = nearest_interactable(
+            player_transform.translation().truncate(),
+            interactables
+                .iter()
+                .filter(|(.., interactable)| interactable.available_to(inventory))
+                .map(|(entity, transform, interactable)| {
+                    (entity, transform.translation().truncate(), interactable)
+                }),
+        );
+        if let Some((entity, interactable)) = nearest {
+            if prompts.iter().all(|(shown, ..)| *shown != entity) {
+                let (_, transform, _) = interactables.get(entity).unwrap();
+                prompts.push((
+                    entity,
+                    transform.translation().truncate(),
+                    interactable,
+                    player_index.0,
+                ));
+            }
+        }
+    }
+    if prompts.is_empty() {
+        return;
+    }
+
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .expect("Missing font style")
+        .colored(ui_theme.panel.font_color);
+    let button_theme = ui_theme
+        .button_styles
+        .get(&ButtonStyle::Small)
+        .expect("Missing button style");
+
+    egui::Area::new("interaction_prompts")
+        .fixed_pos(egui::Pos2::ZERO)
+        .order(egui::Order::Background)
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let screen_size = ui.ctx().input().screen_rect().size();
+
+            for (_, position, interactable, player_idx) in prompts {
+                let Some(ndc) = camera.world_to_ndc(
+                    camera_transform,
+                    (position + Vec2::new(0.0, PROMPT_OFFSET)).extend(0.0),
+                ) else {
+                    continue;
+                };
+                let bottom_center = egui::pos2(
+                    (ndc.x + 1.0) * screen_size.x / 2.0,
+                    (1.0 - ndc.y) * screen_size.y / 2.0,
+                );
+                let rect = egui::Rect::from_min_max(
+                    bottom_center - egui::vec2(PROMPT_MAX_SIZE.x / 2.0, PROMPT_MAX_SIZE.y),
+                    bottom_center + egui::vec2(PROMPT_MAX_SIZE.x / 2.0, 0.0),
+                );
+
+                let device = input_devices.get(player_idx);
+                ui.allocate_ui_at_rect(rect, |ui| {
+                    ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+                        BorderedFrame::new(&ui_theme.panel.border)
+                            .padding(ui_theme.panel.padding.into())
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    for button in
+                                        controls.buttons(player_idx, device, interactable.action)
+                                    {
+                                        BorderedFrame::new(&button_theme.borders.default)
+                                            .padding(button_theme.padding.into())
+                                            .show(ui, |ui| {
+                                                ui.themed_label(
+                                                    &button_theme.font,
+                                                    &format_input(&button),
+                                                );
+                                            });
+                                    }
+                                    ui.themed_label(
+                                        &font,
+                                        &localization.get(interactable.prompt.as_str()),
+                                    );
+                                });
+                            });
+                    });
+                });
+            }
+        });
+}
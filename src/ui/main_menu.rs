@@ -1,18 +1,35 @@
 use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
 use bevy_egui::{egui::style::Margin, *};
 use bevy_fluent::Localization;
+use bevy_kira_audio::{AudioChannel, AudioControl};
 use egui_extras::Column;
 use iyes_loopless::state::NextState;
 use leafwing_input_manager::{
-    axislike::SingleAxis, prelude::ActionState, user_input::InputKind, Actionlike,
+    axislike::SingleAxis,
+    prelude::{ActionState, InputMap},
+    user_input::InputKind,
+    Actionlike,
 };
 
 use crate::{
+    arcade,
+    audio::MusicChannel,
     config::ENGINE_CONFIG,
-    input::MenuAction,
+    crash::LastCrash,
+    gallery::Unlocks,
+    input::{MenuAction, PlayerAction},
     localization::LocalizationExt,
-    metadata::{ButtonStyle, FontStyle, GameMeta, LevelHandle, Settings},
-    platform::Storage,
+    menu_stack::{MenuLayer, MenuStack},
+    metadata::{
+        ButtonStyle, FighterMeta, FontStyle, GalleryMeta, GameMeta, LevelHandle, LevelMeta,
+        Settings, UIThemeMeta, UIThemeState, UnlockCondition,
+    },
+    new_game_plus::{self, CampaignProgress},
+    platform::{self, Storage},
+    player::{Player, PlayerIndex},
+    survival::{self, DailyChallenge, HighScore, HighScoreTable, HighScores, RunResult},
+    training, versus,
+    video::Displays,
     GameState,
 };
 
@@ -62,13 +79,57 @@ pub fn despawn_main_menu_background(
 #[derive(Clone, Copy)]
 pub enum MenuPage {
     Main,
-    Settings { tab: SettingsTab },
+    /// Picking the fighters for the training mode, by their index in the training fighters
+    Training {
+        fighter: usize,
+        dummy: usize,
+    },
+    /// The survival, daily challenge, or arcade high scores, which are also shown after a run
+    HighScores {
+        table: HighScoreTable,
+    },
+    /// Picking between 1v1 and 2v2 for the versus mode
+    Versus,
+    /// Where the report of the crash of the last run was saved, which is shown at startup
+    LastCrash,
+    /// The unlockable entries of the gallery, by their index in the entries of the tab
+    Extras {
+        tab: ExtrasTab,
+        index: usize,
+    },
+}
+
+/// Which entries of the gallery the extras page shows
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExtrasTab {
+    Art,
+    Music,
+    Bios,
+}
+
+impl ExtrasTab {
+    const TABS: &'static [(Self, &'static str)] = &[
+        (Self::Art, "concept-art"),
+        (Self::Music, "music"),
+        (Self::Bios, "enemy-bios"),
+    ];
+
+    /// The number of entries of the tab in the gallery
+    fn entry_count(self, gallery: &GalleryMeta) -> usize {
+        match self {
+            Self::Art => gallery.art.len(),
+            Self::Music => gallery.music.len(),
+            Self::Bios => gallery.bios.len(),
+        }
+    }
 }
 
 /// Which settings tab we are on
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum SettingsTab {
     Controls,
+    Video,
+    Accessibility,
     #[allow(unused)] // Just for now until we get sound settings setup
     Sound,
 }
@@ -88,42 +149,56 @@ impl Default for SettingsTab {
 impl SettingsTab {
     const TABS: &'static [(Self, &'static str)] = &[
         (Self::Controls, "controls"),
+        (Self::Video, "video"),
+        (Self::Accessibility, "accessibility"),
         // For now, hide the sound tab because we don't have it working yet.
         // (Self::Sound, "sound")
     ];
 }
 
-/// Group of parameters needed by the main menu system
+/// Group of parameters needed by the main menu and settings menu systems
 #[derive(SystemParam)]
 pub struct MenuSystemParams<'w, 's> {
     menu_page: Local<'s, MenuPage>,
+    settings_tab: Local<'s, SettingsTab>,
+    /// The settings being edited in the settings menu, while it's open
     modified_settings: Local<'s, Option<Settings>>,
     currently_binding_input_idx: Local<'s, Option<usize>>,
     commands: Commands<'w, 's>,
+    menu_stack: ResMut<'w, MenuStack>,
     game: Res<'w, GameMeta>,
     localization: Res<'w, Localization>,
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
     app_exit: EventWriter<'w, 's, AppExit>,
     storage: ResMut<'w, Storage>,
+    levels: ResMut<'w, Assets<LevelMeta>>,
+    fighters: Res<'w, Assets<FighterMeta>>,
+    run_result: Option<Res<'w, RunResult>>,
+    last_crash: Option<Res<'w, LastCrash>>,
     adjacencies: ResMut<'w, WidgetAdjacencies>,
     control_inputs: ControlInputBindingEvents<'w, 's>,
+    music_channel: Res<'w, AudioChannel<MusicChannel>>,
+    /// The gallery track that is playing over the menu music, by its index in the gallery
+    gallery_track: Local<'s, Option<usize>>,
+    displays: Res<'w, Displays>,
+    players:
+        Query<'w, 's, (&'static PlayerIndex, &'static mut InputMap<PlayerAction>), With<Player>>,
 }
 
-/// Render the main menu UI
-pub fn main_menu_system(mut params: MenuSystemParams, mut egui_context: ResMut<EguiContext>) {
-    let menu_input = params.menu_input.single();
-
-    // Go to previous menu if back button is pressed
-    if menu_input.pressed(MenuAction::Back) {
-        if let MenuPage::Settings { .. } = *params.menu_page {
-            *params.menu_page = MenuPage::Main;
-            egui_context.ctx_mut().clear_focus();
-        }
-    }
+/// Get the UI theme of the main menu
+fn menu_theme(game: &GameMeta) -> &UIThemeMeta {
+    game.ui_theme.for_state(UIThemeState::MainMenu)
+}
 
+/// Show the panel that the pages of the menu are rendered in
+fn menu_panel(
+    params: &mut MenuSystemParams,
+    ctx: &egui::Context,
+    add_contents: impl FnOnce(&mut MenuSystemParams, &mut egui::Ui),
+) {
     egui::CentralPanel::default()
         .frame(egui::Frame::none())
-        .show(egui_context.ctx_mut(), |ui| {
+        .show(ctx, |ui| {
             let screen_rect = ui.max_rect();
 
             // Calculate a margin
@@ -137,38 +212,129 @@ pub fn main_menu_system(mut params: MenuSystemParams, mut egui_context: ResMut<E
             };
 
             // Create menu panel
-            BorderedFrame::new(&params.game.ui_theme.panel.border)
+            BorderedFrame::new(&menu_theme(&params.game).panel.border)
                 .margin(outer_margin)
-                .padding(params.game.ui_theme.panel.padding.into())
+                .padding(menu_theme(&params.game).panel.padding.into())
                 .show(ui, |ui| {
                     // Make sure the frame ocupies the entire rect that we allocated for it.
                     //
                     // Without this it would only take up enough size to fit it's content.
                     ui.set_min_size(ui.available_size());
 
-                    // Render the menu based on the current menu selection
-                    match *params.menu_page {
-                        MenuPage::Main => main_menu_ui(&mut params, ui),
-                        MenuPage::Settings { tab } => settings_menu_ui(&mut params, ui, tab),
-                    }
+                    add_contents(params, ui);
                 });
         });
 }
 
+/// Render the main menu UI
+pub fn main_menu_system(mut params: MenuSystemParams, mut egui_context: ResMut<EguiContext>) {
+    // Show how the survival or arcade run that just ended did
+    if let Some(result) = params
+        .run_result
+        .as_ref()
+        .filter(|result| result.is_added())
+    {
+        *params.menu_page = MenuPage::HighScores {
+            table: result.table,
+        };
+    }
+
+    // Tell the players about the crash of the last run when the game starts
+    if params
+        .last_crash
+        .as_ref()
+        .map_or(false, |last_crash| last_crash.is_added())
+    {
+        *params.menu_page = MenuPage::LastCrash;
+    }
+
+    let menu_input = params.menu_input.single();
+
+    // Go to previous menu if back button is pressed
+    if menu_input.pressed(MenuAction::Back) {
+        if !matches!(*params.menu_page, MenuPage::Main) {
+            *params.menu_page = MenuPage::Main;
+            egui_context.ctx_mut().clear_focus();
+        }
+    }
+
+    // Go back to the menu music when leaving the extras after playing a gallery track
+    if params.gallery_track.is_some() && !matches!(*params.menu_page, MenuPage::Extras { .. }) {
+        *params.gallery_track = None;
+        params.music_channel.stop();
+        params
+            .music_channel
+            .play(params.game.main_menu.music_handle.clone());
+    }
+
+    // The image of the gallery entry has to be added to egui before the menu is rendered
+    let extras_image = match *params.menu_page {
+        MenuPage::Extras { tab, index } => gallery_image(&params, tab, index)
+            .map(|(handle, size)| (egui_context.add_image(handle), egui::vec2(size.x, size.y))),
+        _ => None,
+    };
+
+    menu_panel(&mut params, egui_context.ctx_mut(), |params, ui| {
+        // Render the menu based on the current menu selection
+        match *params.menu_page {
+            MenuPage::Main => main_menu_ui(params, ui),
+            MenuPage::Training { fighter, dummy } => training_menu_ui(params, ui, fighter, dummy),
+            MenuPage::HighScores { table } => high_scores_menu_ui(params, ui, table),
+            MenuPage::Versus => versus_menu_ui(params, ui),
+            MenuPage::LastCrash => last_crash_menu_ui(params, ui),
+            MenuPage::Extras { tab, index } => extras_menu_ui(params, ui, tab, index, extras_image),
+        }
+    });
+}
+
+/// Render the settings menu, over the main menu or the pause menu
+pub fn settings_menu_system(mut params: MenuSystemParams, mut egui_context: ResMut<EguiContext>) {
+    // Edit a copy of the settings, which is only saved with the save button
+    if params.modified_settings.is_none() {
+        *params.modified_settings = Some(
+            params
+                .storage
+                .get(Settings::STORAGE_KEY)
+                .unwrap_or_else(|| params.game.default_settings.clone()),
+        );
+    }
+
+    // Go back to the menu that the settings were opened from if back button is pressed
+    if params.menu_input.single().pressed(MenuAction::Back) {
+        close_settings_menu(&mut params, egui_context.ctx_mut());
+        return;
+    }
+
+    menu_panel(&mut params, egui_context.ctx_mut(), |params, ui| {
+        let tab = *params.settings_tab;
+        settings_menu_ui(params, ui, tab);
+    });
+}
+
+/// Close the settings menu without saving the modified settings
+fn close_settings_menu(params: &mut MenuSystemParams, ctx: &egui::Context) {
+    params.menu_stack.pop();
+    *params.modified_settings = None;
+    *params.settings_tab = default();
+    *params.currently_binding_input_idx = None;
+    ctx.clear_focus();
+}
+
 /// Render the main menu
 fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
     let MenuSystemParams {
         menu_page,
-        modified_settings,
+        menu_stack,
         commands,
         game,
         localization,
         app_exit,
         storage,
+        levels,
         ..
     } = params;
 
-    let ui_theme = &game.ui_theme;
+    let ui_theme = menu_theme(game);
 
     // Create a vertical list of items, centered horizontally
     ui.vertical_centered(|ui| {
@@ -188,8 +354,161 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
         .focus_by_default(ui);
 
         if start_button.clicked() || ENGINE_CONFIG.auto_start {
-            commands.insert_resource(LevelHandle(game.start_level_handle.clone()));
-            commands.insert_resource(NextState(GameState::LoadingLevel));
+            new_game_plus::start_campaign(commands, game);
+        }
+
+        // New Game Plus button, once the campaign has been finished
+        if let Some(new_game_plus) = &game.new_game_plus {
+            let unlocked = storage
+                .get::<CampaignProgress>(CampaignProgress::STORAGE_KEY)
+                .map_or(false, |progress| progress.completed);
+
+            if unlocked
+                && BorderedButton::themed(
+                    ui_theme,
+                    &ButtonStyle::Normal,
+                    &localization.get("new-game-plus"),
+                )
+                .min_size(min_button_size)
+                .show(ui)
+                .clicked()
+            {
+                new_game_plus::start_new_game_plus(commands, levels, game, new_game_plus);
+            }
+        }
+
+        // Endless mode button
+        if let Some(endless_level_handle) = &game.endless_level_handle {
+            if BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &localization.get("endless-mode"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+            {
+                commands.insert_resource(LevelHandle(endless_level_handle.clone()));
+                commands.insert_resource(NextState(GameState::LoadingLevel));
+            }
+        }
+
+        // Tutorial button
+        if let Some(tutorial_level_handle) = &game.tutorial_level_handle {
+            if BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &localization.get("tutorial"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+            {
+                commands.insert_resource(LevelHandle(tutorial_level_handle.clone()));
+                commands.insert_resource(NextState(GameState::LoadingLevel));
+            }
+        }
+
+        // Survival buttons
+        if let Some(survival) = &game.survival {
+            if BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &localization.get("survival"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+            {
+                survival::start_survival(commands, levels, survival);
+            }
+
+            let attempted_today = storage
+                .get::<DailyChallenge>(DailyChallenge::STORAGE_KEY)
+                .unwrap_or_default()
+                .attempted_on(platform::current_day());
+            if attempted_today {
+                ui.themed_label(
+                    ui_theme.font_styles.get(&FontStyle::Normal).unwrap(),
+                    &localization.get("daily-challenge-attempted"),
+                );
+            } else if BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &localization.get("daily-challenge"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+            {
+                survival::start_daily_challenge(commands, levels, survival, storage);
+            }
+        }
+
+        // Arcade button
+        if let Some(arcade) = &game.arcade {
+            if BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("arcade"))
+                .min_size(min_button_size)
+                .show(ui)
+                .clicked()
+            {
+                arcade::start_arcade(commands, game, arcade);
+            }
+        }
+
+        // High scores button, for the modes that have them
+        if let Some(&table) = high_score_tables(game).first() {
+            if BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &localization.get("high-scores"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+            {
+                **menu_page = MenuPage::HighScores { table };
+            }
+        }
+
+        // Versus button
+        if game.versus.is_some()
+            && BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("versus"))
+                .min_size(min_button_size)
+                .show(ui)
+                .clicked()
+        {
+            **menu_page = MenuPage::Versus;
+        }
+
+        // Training button
+        if game.training.is_some()
+            && BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &localization.get("training"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+        {
+            **menu_page = MenuPage::Training {
+                fighter: 0,
+                dummy: 0,
+            };
+        }
+
+        // Extras button
+        if game.gallery.is_some()
+            && BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("extras"))
+                .min_size(min_button_size)
+                .show(ui)
+                .clicked()
+        {
+            **menu_page = MenuPage::Extras {
+                tab: ExtrasTab::Art,
+                index: 0,
+            };
         }
 
         // Settings button
@@ -202,12 +521,8 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
         .show(ui)
         .clicked()
         {
-            **menu_page = MenuPage::Settings { tab: default() };
-            **modified_settings = Some(
-                storage
-                    .get(Settings::STORAGE_KEY)
-                    .unwrap_or_else(|| game.default_settings.clone()),
-            );
+            menu_stack.push(MenuLayer::Settings);
+            ui.ctx().clear_focus();
         }
 
         // Quit button
@@ -226,6 +541,549 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
     });
 }
 
+/// Render the menu for picking the fighters of the training mode
+fn training_menu_ui(
+    params: &mut MenuSystemParams,
+    ui: &mut egui::Ui,
+    fighter: usize,
+    dummy: usize,
+) {
+    let MenuSystemParams {
+        menu_page,
+        commands,
+        game,
+        localization,
+        levels,
+        fighters,
+        ..
+    } = params;
+    let Some(training) = &game.training else {
+        **menu_page = MenuPage::Main;
+        return;
+    };
+
+    let ui_theme = menu_theme(game);
+    let fighter_count = training.fighter_handles.len();
+    let fighter_name = |index: usize| {
+        training
+            .fighter_handles
+            .get(index)
+            .and_then(|handle| fighters.get(handle))
+            .map_or("?", |fighter| fighter.name.as_str())
+            .to_owned()
+    };
+
+    ui.vertical_centered(|ui| {
+        ui.themed_label(
+            ui_theme.font_styles.get(&FontStyle::Heading).unwrap(),
+            &localization.get("training"),
+        );
+        ui.add_space(10.0);
+
+        let min_button_size = egui::vec2(ui.available_width() / 2.0, 0.0);
+
+        // Clicking a fighter picks the next one
+        let fighter_button = BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &format!("{}: {}", localization.get("fighter"), fighter_name(fighter)),
+        )
+        .min_size(min_button_size)
+        .show(ui)
+        .focus_by_default(ui);
+        if fighter_button.clicked() && fighter_count > 0 {
+            **menu_page = MenuPage::Training {
+                fighter: (fighter + 1) % fighter_count,
+                dummy,
+            };
+        }
+
+        if BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &format!("{}: {}", localization.get("dummy"), fighter_name(dummy)),
+        )
+        .min_size(min_button_size)
+        .show(ui)
+        .clicked()
+            && fighter_count > 0
+        {
+            **menu_page = MenuPage::Training {
+                fighter,
+                dummy: (dummy + 1) % fighter_count,
+            };
+        }
+
+        ui.add_space(10.0);
+
+        if BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &localization.get("start-training"),
+        )
+        .min_size(min_button_size)
+        .show(ui)
+        .clicked()
+        {
+            if let (Some(fighter), Some(dummy)) = (
+                training.fighter_handles.get(fighter),
+                training.fighter_handles.get(dummy),
+            ) {
+                training::start_training(commands, levels, training, fighter, dummy);
+            }
+        }
+
+        if BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("back"))
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+        {
+            **menu_page = MenuPage::Main;
+            ui.ctx().clear_focus();
+        }
+    });
+}
+
+/// Render the menu for picking between 1v1 and 2v2 versus
+fn versus_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
+    let MenuSystemParams {
+        menu_page,
+        commands,
+        game,
+        localization,
+        levels,
+        ..
+    } = params;
+    let Some(versus) = &game.versus else {
+        **menu_page = MenuPage::Main;
+        return;
+    };
+
+    let ui_theme = menu_theme(game);
+
+    ui.vertical_centered(|ui| {
+        ui.themed_label(
+            ui_theme.font_styles.get(&FontStyle::Heading).unwrap(),
+            &localization.get("versus"),
+        );
+        ui.add_space(10.0);
+
+        let min_button_size = egui::vec2(ui.available_width() / 2.0, 0.0);
+
+        let one_on_one_button = BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &localization.get("one-vs-one"),
+        )
+        .min_size(min_button_size)
+        .show(ui)
+        .focus_by_default(ui);
+        if one_on_one_button.clicked() {
+            versus::start_versus(commands, levels, versus, 2);
+        }
+
+        if BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &localization.get("two-vs-two"),
+        )
+        .min_size(min_button_size)
+        .show(ui)
+        .clicked()
+        {
+            versus::start_versus(commands, levels, versus, 4);
+        }
+
+        if BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("back"))
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+        {
+            **menu_page = MenuPage::Main;
+            ui.ctx().clear_focus();
+        }
+    });
+}
+
+/// Render where the report of the crash of the last run was saved
+fn last_crash_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
+    let MenuSystemParams {
+        menu_page,
+        game,
+        localization,
+        last_crash,
+        ..
+    } = params;
+    let Some(last_crash) = last_crash else {
+        **menu_page = MenuPage::Main;
+        return;
+    };
+
+    let ui_theme = menu_theme(game);
+    let normal_font = ui_theme.font_styles.get(&FontStyle::Normal).unwrap();
+
+    ui.vertical_centered(|ui| {
+        ui.themed_label(
+            ui_theme.font_styles.get(&FontStyle::Heading).unwrap(),
+            &localization.get("crashed"),
+        );
+        ui.add_space(10.0);
+
+        ui.themed_label(normal_font, &localization.get("crash-report-saved"));
+        ui.themed_label(normal_font, &last_crash.report.display().to_string());
+        ui.add_space(10.0);
+
+        let continue_button = BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &localization.get("continue"),
+        )
+        .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+        .show(ui)
+        .focus_by_default(ui);
+        if continue_button.clicked() {
+            **menu_page = MenuPage::Main;
+            ui.ctx().clear_focus();
+        }
+    });
+}
+
+/// Get the image shown with the gallery entry, with its size: the art itself, or the portrait of
+/// the fighter of a bio
+fn gallery_image(
+    params: &MenuSystemParams,
+    tab: ExtrasTab,
+    index: usize,
+) -> Option<(Handle<Image>, Vec2)> {
+    let gallery = params.game.gallery.as_ref()?;
+    match tab {
+        ExtrasTab::Art => gallery
+            .art
+            .get(index)
+            .map(|art| (art.image.image_handle.clone_weak(), art.image.image_size)),
+        ExtrasTab::Music => None,
+        ExtrasTab::Bios => gallery
+            .bios
+            .get(index)
+            .and_then(|bio| params.fighters.get(&bio.fighter_handle))
+            .map(|fighter| {
+                let portrait = &fighter.hud.portrait;
+                (portrait.image_handle.clone_weak(), portrait.image_size)
+            }),
+    }
+}
+
+/// Get what the players have to do to unlock a gallery entry
+fn unlock_hint(localization: &Localization, condition: UnlockCondition) -> String {
+    match condition {
+        UnlockCondition::Always => String::new(),
+        UnlockCondition::CampaignCompleted => localization.get("unlock-campaign"),
+        UnlockCondition::NewGamePlusCompleted => localization.get("unlock-new-game-plus"),
+        UnlockCondition::SurvivalScore(score) => {
+            format!("{}: {score}", localization.get("unlock-survival-score"))
+        }
+    }
+}
+
+/// Scale the size to fit in `max_size`, keeping its aspect ratio
+fn fit_size(size: egui::Vec2, max_size: egui::Vec2) -> egui::Vec2 {
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return egui::Vec2::ZERO;
+    }
+    size * (max_size.x / size.x).min(max_size.y / size.y).max(0.0)
+}
+
+/// Render the unlockable concept art, music, and enemy bios of the gallery, one entry at a time
+fn extras_menu_ui(
+    params: &mut MenuSystemParams,
+    ui: &mut egui::Ui,
+    tab: ExtrasTab,
+    index: usize,
+    image: Option<(egui::TextureId, egui::Vec2)>,
+) {
+    let MenuSystemParams {
+        menu_page,
+        game,
+        localization,
+        storage,
+        fighters,
+        music_channel,
+        gallery_track,
+        ..
+    } = params;
+    let Some(gallery) = &game.gallery else {
+        **menu_page = MenuPage::Main;
+        return;
+    };
+
+    let ui_theme = menu_theme(game);
+    let normal_font = ui_theme.font_styles.get(&FontStyle::Normal).unwrap();
+    let bigger_font = ui_theme.font_styles.get(&FontStyle::Bigger).unwrap();
+    let unlocks = Unlocks::load(storage);
+    let entry_count = tab.entry_count(gallery);
+    // The gallery may have lost entries when it was hot reloaded
+    let index = index.min(entry_count.saturating_sub(1));
+
+    ui.vertical_centered(|ui| {
+        ui.themed_label(
+            ui_theme.font_styles.get(&FontStyle::Heading).unwrap(),
+            &localization.get("extras"),
+        );
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            for (i, (other_tab, name)) in ExtrasTab::TABS.iter().enumerate() {
+                let mut name = egui::RichText::new(localization.get(*name));
+                if *other_tab == tab {
+                    name = name.underline();
+                }
+
+                let mut button =
+                    BorderedButton::themed(ui_theme, &ButtonStyle::Small, name).show(ui);
+                if i == 0 {
+                    button = button.focus_by_default(ui);
+                }
+                if button.clicked() {
+                    **menu_page = MenuPage::Extras {
+                        tab: *other_tab,
+                        index: 0,
+                    };
+                }
+            }
+        });
+        ui.add_space(10.0);
+
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+            if BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("back"))
+                .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+                .show(ui)
+                .clicked()
+            {
+                **menu_page = MenuPage::Main;
+                ui.ctx().clear_focus();
+            }
+            ui.add_space(10.0);
+
+            // Flip through the entries of the tab
+            if entry_count > 1 {
+                ui.horizontal(|ui| {
+                    if BorderedButton::themed(ui_theme, &ButtonStyle::Small, "<")
+                        .show(ui)
+                        .clicked()
+                    {
+                        **menu_page = MenuPage::Extras {
+                            tab,
+                            index: (index + entry_count - 1) % entry_count,
+                        };
+                    }
+                    ui.themed_label(normal_font, &format!("{} / {entry_count}", index + 1));
+                    if BorderedButton::themed(ui_theme, &ButtonStyle::Small, ">")
+                        .show(ui)
+                        .clicked()
+                    {
+                        **menu_page = MenuPage::Extras {
+                            tab,
+                            index: (index + 1) % entry_count,
+                        };
+                    }
+                });
+                ui.add_space(10.0);
+            }
+
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                if entry_count == 0 {
+                    ui.themed_label(normal_font, &localization.get("no-extras"));
+                    return;
+                }
+
+                let unlock = match tab {
+                    ExtrasTab::Art => gallery.art[index].unlock,
+                    ExtrasTab::Music => gallery.music[index].unlock,
+                    ExtrasTab::Bios => gallery.bios[index].unlock,
+                };
+                if !unlocks.is_unlocked(unlock) {
+                    ui.themed_label(bigger_font, &localization.get("locked"));
+                    ui.themed_label(normal_font, &unlock_hint(localization, unlock));
+                    return;
+                }
+
+                match tab {
+                    ExtrasTab::Art => {
+                        ui.themed_label(
+                            bigger_font,
+                            &localization.get(gallery.art[index].title.as_str()),
+                        );
+                        if let Some((texture, size)) = image {
+                            ui.image(texture, fit_size(size, ui.available_size()));
+                        }
+                    }
+                    ExtrasTab::Music => {
+                        let track = &gallery.music[index];
+                        ui.themed_label(bigger_font, &localization.get(track.title.as_str()));
+                        ui.add_space(10.0);
+
+                        if **gallery_track == Some(index) {
+                            ui.themed_label(normal_font, &localization.get("now-playing"));
+                        } else if BorderedButton::themed(
+                            ui_theme,
+                            &ButtonStyle::Normal,
+                            &localization.get("play"),
+                        )
+                        .show(ui)
+                        .clicked()
+                        {
+                            music_channel.stop();
+                            music_channel.play(track.music_handle.clone()).looped();
+                            **gallery_track = Some(index);
+                        }
+                    }
+                    ExtrasTab::Bios => {
+                        let bio = &gallery.bios[index];
+                        let name = fighters
+                            .get(&bio.fighter_handle)
+                            .map_or("?", |fighter| fighter.name.as_str());
+                        ui.themed_label(bigger_font, name);
+                        if let Some((texture, size)) = image {
+                            let max_size =
+                                egui::vec2(ui.available_width(), ui.available_height() / 2.0);
+                            ui.image(texture, fit_size(size, max_size));
+                        }
+                        ui.add_space(10.0);
+                        ui.themed_label(normal_font, &localization.get(bio.bio.as_str()));
+                    }
+                }
+            });
+        });
+    });
+}
+
+/// Get the high score tables of the modes of the game
+fn high_score_tables(game: &GameMeta) -> Vec<HighScoreTable> {
+    let mut tables = Vec::new();
+    if game.survival.is_some() {
+        tables.extend([HighScoreTable::Survival, HighScoreTable::DailyChallenge]);
+    }
+    if game.arcade.is_some() {
+        tables.push(HighScoreTable::Arcade);
+    }
+    tables
+}
+
+/// Render the survival, daily challenge, or arcade high scores, highlighting the score of the last
+/// run
+fn high_scores_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, table: HighScoreTable) {
+    let MenuSystemParams {
+        menu_page,
+        game,
+        localization,
+        storage,
+        run_result,
+        ..
+    } = params;
+
+    let ui_theme = menu_theme(game);
+    let normal_font = ui_theme.font_styles.get(&FontStyle::Normal).unwrap();
+    let bigger_font = ui_theme.font_styles.get(&FontStyle::Bigger).unwrap();
+    let high_scores = storage
+        .get::<HighScores>(table.storage_key())
+        .unwrap_or_default();
+    // The last run is only shown with the high scores that it was submitted to
+    let run_result = run_result.as_ref().filter(|result| result.table == table);
+    let last_rank = run_result.and_then(|result| result.rank);
+    // Arcade runs are played through the campaign instead of waves, but can be continued
+    let arcade = table == HighScoreTable::Arcade;
+    let count_name = if arcade { "continues" } else { "waves" };
+    let count = |score: &HighScore| {
+        if arcade {
+            score.continues
+        } else {
+            score.waves
+        }
+    };
+
+    ui.vertical_centered(|ui| {
+        ui.themed_label(
+            ui_theme.font_styles.get(&FontStyle::Heading).unwrap(),
+            &localization.get("high-scores"),
+        );
+        ui.add_space(10.0);
+
+        // Switch between the high scores of the modes
+        let tables = high_score_tables(game);
+        if BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Small,
+            &localization.get(table.name()),
+        )
+        .show(ui)
+        .clicked()
+        {
+            let index = tables.iter().position(|other| *other == table).unwrap_or(0);
+            if let Some(&next) = tables.get((index + 1) % tables.len().max(1)) {
+                **menu_page = MenuPage::HighScores { table: next };
+            }
+        }
+        ui.add_space(10.0);
+
+        if high_scores.0.is_empty() {
+            ui.themed_label(normal_font, &localization.get("no-high-scores"));
+        } else {
+            egui::Grid::new("high_scores")
+                .num_columns(4)
+                .spacing(egui::vec2(40.0, 4.0))
+                .show(ui, |ui| {
+                    ui.themed_label(bigger_font, "#");
+                    ui.themed_label(bigger_font, &localization.get("score"));
+                    ui.themed_label(bigger_font, &localization.get(count_name));
+                    ui.end_row();
+
+                    for (rank, high_score) in high_scores.0.iter().enumerate() {
+                        let font = if last_rank == Some(rank) {
+                            bigger_font
+                        } else {
+                            normal_font
+                        };
+                        ui.themed_label(font, &(rank + 1).to_string());
+                        ui.themed_label(font, &high_score.score.to_string());
+                        ui.themed_label(font, &count(high_score).to_string());
+                        if high_score.assisted {
+                            ui.themed_label(font, &localization.get("assisted"));
+                        } else {
+                            ui.label("");
+                        }
+                        ui.end_row();
+                    }
+                });
+        }
+
+        // A run that didn't make it into the high scores is still shown
+        if let Some(result) = run_result.filter(|result| result.rank.is_none()) {
+            ui.add_space(10.0);
+            ui.themed_label(
+                normal_font,
+                &format!(
+                    "{}: {} ( {} {} )",
+                    localization.get("your-score"),
+                    result.score.score,
+                    count(&result.score),
+                    localization.get(count_name),
+                ),
+            );
+        }
+
+        ui.add_space(10.0);
+        let back_button =
+            BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &localization.get("back"))
+                .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+                .show(ui)
+                .focus_by_default(ui);
+        if back_button.clicked() {
+            **menu_page = MenuPage::Main;
+            ui.ctx().clear_focus();
+        }
+    });
+}
+
 /// Render the settings menu
 fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_tab: SettingsTab) {
     // Disable all the buttons if we are currently binding an input
@@ -234,9 +1092,7 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
     ui.vertical_centered(|ui| {
         // Settings Heading
         ui.themed_label(
-            params
-                .game
-                .ui_theme
+            menu_theme(&params.game)
                 .font_styles
                 .get(&FontStyle::Heading)
                 .unwrap(),
@@ -256,7 +1112,7 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
                 }
 
                 let mut button =
-                    BorderedButton::themed(&params.game.ui_theme, &ButtonStyle::Normal, name)
+                    BorderedButton::themed(menu_theme(&params.game), &ButtonStyle::Normal, name)
                         .show(ui);
 
                 // Focus the first tab by default
@@ -266,7 +1122,7 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
 
                 // Change tab when clicked
                 if button.clicked() {
-                    *params.menu_page = MenuPage::Settings { tab: *tab };
+                    *params.settings_tab = *tab;
                 }
 
                 tabs.push(button);
@@ -288,24 +1144,23 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
 
                     // Cancel button
                     let cancel_button = BorderedButton::themed(
-                        &params.game.ui_theme,
+                        menu_theme(&params.game),
                         &ButtonStyle::Normal,
                         &params.localization.get("cancel"),
                     )
                     .min_size(button_min_size)
                     .show(ui);
 
-                    // Go to menu when cancel is clicked
+                    // Go back to the menu that the settings were opened from when cancel is clicked
                     if cancel_button.clicked() {
-                        *params.menu_page = MenuPage::Main;
-                        ui.ctx().clear_focus();
+                        close_settings_menu(params, ui.ctx());
                     }
 
                     ui.add_space(button_spacing);
 
                     // Reset button
                     let reset_button = BorderedButton::themed(
-                        &params.game.ui_theme,
+                        menu_theme(&params.game),
                         &ButtonStyle::Normal,
                         &params.localization.get("reset"),
                     )
@@ -316,7 +1171,7 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
 
                     // Save button
                     let save_button = BorderedButton::themed(
-                        &params.game.ui_theme,
+                        menu_theme(&params.game),
                         &ButtonStyle::Normal,
                         &params.localization.get("save"),
                     )
@@ -333,9 +1188,14 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
                         // Persist to storage
                         params.storage.save();
 
-                        // Go to main menu
-                        *params.menu_page = MenuPage::Main;
-                        ui.ctx().clear_focus();
+                        // Apply the new controls to the players of the level the pause menu was
+                        // opened in
+                        let controls = &params.modified_settings.as_ref().unwrap().player_controls;
+                        for (player_index, mut input_map) in &mut params.players {
+                            *input_map = controls.get_input_map(player_index.0);
+                        }
+
+                        close_settings_menu(params, ui.ctx());
                     }
 
                     [cancel_button, reset_button, save_button]
@@ -362,6 +1222,22 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
                             &bottom_buttons,
                         )
                     }
+                    SettingsTab::Video => video_settings_ui(
+                        params,
+                        ui,
+                        // Reset button clicked
+                        bottom_buttons[1].clicked(),
+                        &tabs,
+                        &bottom_buttons,
+                    ),
+                    SettingsTab::Accessibility => accessibility_settings_ui(
+                        params,
+                        ui,
+                        // Reset button clicked
+                        bottom_buttons[1].clicked(),
+                        &tabs,
+                        &bottom_buttons,
+                    ),
                     SettingsTab::Sound => sound_settings_ui(ui, &params.game),
                 }
             });
@@ -377,7 +1253,7 @@ fn controls_settings_ui(
     settings_tabs: &[egui::Response],
     bottom_buttons: &[egui::Response],
 ) {
-    let ui_theme = &params.game.ui_theme;
+    let ui_theme = menu_theme(&params.game);
 
     // Reset the settings when reset button is clicked
     if should_reset {
@@ -466,6 +1342,38 @@ fn controls_settings_ui(
                 &mut controls.gamepad.throw,
             ],
         ),
+        (
+            &params.localization.get("block"),
+            [
+                &mut controls.block.keyboard1,
+                &mut controls.block.keyboard2,
+                &mut controls.block.gamepad,
+            ],
+        ),
+        (
+            &params.localization.get("dodge"),
+            [
+                &mut controls.dodge.keyboard1,
+                &mut controls.dodge.keyboard2,
+                &mut controls.dodge.gamepad,
+            ],
+        ),
+        (
+            &params.localization.get("special"),
+            [
+                &mut controls.special.keyboard1,
+                &mut controls.special.keyboard2,
+                &mut controls.special.gamepad,
+            ],
+        ),
+        (
+            &params.localization.get("pause"),
+            [
+                &mut controls.pause.keyboard1,
+                &mut controls.pause.keyboard2,
+                &mut controls.pause.gamepad,
+            ],
+        ),
     ];
 
     // Collect input button responses for building adjacency graph
@@ -539,15 +1447,13 @@ fn controls_settings_ui(
                                     .frame(egui::Frame::none())
                                     .title_bar(false)
                                     .show(ui.ctx(), |ui| {
-                                        let font = params
-                                            .game
-                                            .ui_theme
+                                        let font = menu_theme(&params.game)
                                             .font_styles
                                             .get(&FontStyle::Normal)
                                             .unwrap()
-                                            .colored(params.game.ui_theme.panel.font_color);
+                                            .colored(menu_theme(&params.game).panel.font_color);
 
-                                        let border = &params.game.ui_theme.panel.border;
+                                        let border = &menu_theme(&params.game).panel.border;
                                         let m = &border.border_size;
                                         let s = border.scale;
                                         BorderedFrame::new(border)
@@ -611,6 +1517,26 @@ fn controls_settings_ui(
             }
         });
 
+    ui.add_space(label_font.size * 0.5);
+    let hold_to_pause_button = ui
+        .horizontal(|ui| {
+            ui.themed_label(&label_font, &params.localization.get("hold-to-pause"));
+
+            let hold_to_pause = &mut controls.pause.hold_to_pause;
+            let label = params
+                .localization
+                .get(if *hold_to_pause { "on" } else { "off" });
+            let button = BorderedButton::themed(ui_theme, &ButtonStyle::Small, label)
+                .min_size(egui::vec2(label_font.size * 6.0, 0.0))
+                .show(ui);
+            if button.clicked() {
+                *hold_to_pause = !*hold_to_pause;
+            }
+
+            button
+        })
+        .inner;
+
     // Set adjacency for all of the gamepad input buttons
     for row_idx in 0..input_rows.len() {
         if row_idx == 0 {
@@ -633,7 +1559,7 @@ fn controls_settings_ui(
                 }
             }
 
-        // If this is the last row, the input buttons are above the bottom buttons
+        // If this is the last row, the input buttons are above the hold to pause button
         } else if row_idx == input_rows.len() - 1 {
             for i in 0..3 {
                 let button_above = &input_buttons[(row_idx - 1) * 3 + i];
@@ -642,7 +1568,7 @@ fn controls_settings_ui(
                 params
                     .adjacencies
                     .widget(button)
-                    .above(&bottom_buttons[i])
+                    .above(&hold_to_pause_button)
                     .below(button_above);
 
                 // The first bottom button is to the right of the last input button
@@ -664,6 +1590,281 @@ fn controls_settings_ui(
             }
         }
     }
+
+    // The hold to pause button is above the bottom buttons
+    for button in bottom_buttons {
+        params
+            .adjacencies
+            .widget(button)
+            .below(&hold_to_pause_button);
+    }
+    params
+        .adjacencies
+        .widget(&hold_to_pause_button)
+        .above(&bottom_buttons[1]);
+}
+
+/// Render the display, vsync, frame rate limit, dynamic resolution and background settings
+fn video_settings_ui(
+    params: &mut MenuSystemParams,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let ui_theme = menu_theme(&params.game);
+
+    // Reset the settings when reset button is clicked
+    if should_reset {
+        let settings = params.modified_settings.as_mut().unwrap();
+        settings.video = params.game.default_settings.video.clone();
+        settings.background = params.game.default_settings.background;
+    }
+
+    let label_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .unwrap()
+        .colored(ui_theme.panel.font_color);
+    let localization = &params.localization;
+    let displays = &params.displays;
+    let settings = params.modified_settings.as_mut().unwrap();
+    let video = &mut settings.video;
+    let background = &mut settings.background;
+
+    ui.add_space(label_font.size);
+
+    let buttons = egui::Grid::new("video_settings")
+        .num_columns(2)
+        .spacing(egui::vec2(label_font.size, label_font.size * 0.5))
+        .show(ui, |ui| {
+            let setting_button = |ui: &mut egui::Ui, label: &str, value: String| {
+                let label = localization.get(label);
+                ui.themed_label(&label_font, &label);
+                let button = BorderedButton::themed(ui_theme, &ButtonStyle::Small, value.as_str())
+                    .min_size(egui::vec2(label_font.size * 6.0, 0.0))
+                    .description(format!("{label}: {value}"))
+                    .show(ui);
+                ui.end_row();
+                button
+            };
+
+            let window_mode = localization.get(video.window_mode.name());
+            let window_mode_button = setting_button(ui, "window-mode", window_mode);
+            if window_mode_button.clicked() {
+                video.window_mode = video.window_mode.next();
+            }
+
+            let resolution = match video.resolution {
+                Some(resolution) => resolution.to_string(),
+                None => localization.get("auto"),
+            };
+            let resolution_button = setting_button(ui, "resolution", resolution);
+            if resolution_button.clicked() {
+                video.resolution = displays.next_resolution(video.monitor, video.resolution);
+            }
+
+            let monitor = match displays.monitors.get(video.monitor) {
+                Some(monitor) => monitor.name.clone(),
+                None => (video.monitor + 1).to_string(),
+            };
+            let monitor_button = setting_button(ui, "monitor", monitor);
+            if monitor_button.clicked() {
+                video.monitor = (video.monitor + 1) % displays.monitors.len().max(1);
+                // The other monitor may not have the resolution
+                if !displays
+                    .resolutions(video.monitor)
+                    .iter()
+                    .any(|resolution| Some(*resolution) == video.resolution)
+                {
+                    video.resolution = None;
+                }
+            }
+
+            let vsync = localization.get(if video.vsync { "on" } else { "off" });
+            let vsync_button = setting_button(ui, "vsync", vsync);
+            if vsync_button.clicked() {
+                video.vsync = !video.vsync;
+            }
+
+            let frame_rate_limit = match video.frame_rate_limit.fps() {
+                Some(fps) => format!("{fps} {}", localization.get("fps")),
+                None => localization.get("uncapped"),
+            };
+            let frame_rate_button = setting_button(ui, "frame-rate-limit", frame_rate_limit);
+            if frame_rate_button.clicked() {
+                video.frame_rate_limit = video.frame_rate_limit.next();
+            }
+
+            let dynamic_resolution = match video.dynamic_resolution.target_fps() {
+                Some(fps) => format!("{fps} {}", localization.get("fps")),
+                None => localization.get("off"),
+            };
+            let dynamic_resolution_button =
+                setting_button(ui, "dynamic-resolution", dynamic_resolution);
+            if dynamic_resolution_button.clicked() {
+                video.dynamic_resolution = video.dynamic_resolution.next();
+            }
+
+            let toggle_button = |ui: &mut egui::Ui, label: &str, value: &mut bool| {
+                let button = setting_button(
+                    ui,
+                    label,
+                    localization.get(if *value { "on" } else { "off" }),
+                );
+                if button.clicked() {
+                    *value = !*value;
+                }
+                button
+            };
+            let pause_button = toggle_button(
+                ui,
+                "pause-on-focus-loss",
+                &mut background.pause_on_focus_loss,
+            );
+            let mute_button =
+                toggle_button(ui, "mute-in-background", &mut background.mute_in_background);
+            let background_frame_rate_button = toggle_button(
+                ui,
+                "reduce-background-frame-rate",
+                &mut background.reduce_frame_rate,
+            );
+
+            [
+                window_mode_button,
+                resolution_button,
+                monitor_button,
+                vsync_button,
+                frame_rate_button,
+                dynamic_resolution_button,
+                pause_button,
+                mute_button,
+                background_frame_rate_button,
+            ]
+        })
+        .inner;
+
+    // The buttons are in a column between the tabs and the bottom buttons
+    for tab in settings_tabs {
+        params.adjacencies.widget(tab).above(&buttons[0]);
+    }
+    params
+        .adjacencies
+        .widget(&buttons[0])
+        .to_right_of(&settings_tabs[settings_tabs.len() - 1]);
+    for pair in buttons.windows(2) {
+        params.adjacencies.widget(&pair[0]).above(&pair[1]);
+    }
+    params
+        .adjacencies
+        .widget(&buttons[buttons.len() - 1])
+        .above(&bottom_buttons[1])
+        .to_left_of(&bottom_buttons[0]);
+}
+
+/// Render the assist and color settings
+fn accessibility_settings_ui(
+    params: &mut MenuSystemParams,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let ui_theme = menu_theme(&params.game);
+
+    // Reset the settings when reset button is clicked
+    if should_reset {
+        let settings = params.modified_settings.as_mut().unwrap();
+        settings.assist = params.game.default_settings.assist;
+        settings.colors = params.game.default_settings.colors;
+        settings.narration = params.game.default_settings.narration;
+    }
+
+    let label_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .unwrap()
+        .colored(ui_theme.panel.font_color);
+    let localization = &params.localization;
+    let settings = params.modified_settings.as_mut().unwrap();
+    let (assist, colors, narration) = (
+        &mut settings.assist,
+        &mut settings.colors,
+        &mut settings.narration,
+    );
+
+    ui.add_space(label_font.size);
+
+    let buttons = egui::Grid::new("accessibility_settings")
+        .num_columns(2)
+        .spacing(egui::vec2(label_font.size, label_font.size * 0.5))
+        .show(ui, |ui| {
+            let setting_button = |ui: &mut egui::Ui, name: &str, value: &str| {
+                let (name, value) = (localization.get(name), localization.get(value));
+                ui.themed_label(&label_font, &name);
+                let button = BorderedButton::themed(ui_theme, &ButtonStyle::Small, value.as_str())
+                    .min_size(egui::vec2(label_font.size * 6.0, 0.0))
+                    .description(format!("{name}: {value}"))
+                    .show(ui);
+                ui.end_row();
+
+                button
+            };
+            let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+            let mut buttons = Vec::new();
+
+            for (name, enabled) in [
+                ("reduced-damage", &mut assist.reduced_damage),
+                ("slower-game-speed", &mut assist.slower_game_speed),
+                ("auto-block", &mut assist.auto_block),
+                ("extra-lives", &mut assist.extra_lives),
+                ("high-contrast-flashes", &mut colors.high_contrast_flashes),
+                ("menu-narration", &mut narration.enabled),
+            ] {
+                let button = setting_button(ui, name, on_off(*enabled));
+                if button.clicked() {
+                    *enabled = !*enabled;
+                }
+                buttons.push(button);
+            }
+
+            for (name, outline) in [
+                ("player-outline", &mut colors.player_outline),
+                ("enemy-outline", &mut colors.enemy_outline),
+            ] {
+                let button = setting_button(ui, name, outline.name());
+                if button.clicked() {
+                    *outline = outline.next();
+                }
+                buttons.push(button);
+            }
+
+            let button = setting_button(ui, "hud-palette", colors.hud_palette.name());
+            if button.clicked() {
+                colors.hud_palette = colors.hud_palette.next();
+            }
+            buttons.push(button);
+
+            buttons
+        })
+        .inner;
+
+    // The buttons are in a column between the tabs and the bottom buttons
+    for tab in settings_tabs {
+        params.adjacencies.widget(tab).above(&buttons[0]);
+    }
+    params
+        .adjacencies
+        .widget(&buttons[0])
+        .to_right_of(&settings_tabs[settings_tabs.len() - 1]);
+    for pair in buttons.windows(2) {
+        params.adjacencies.widget(&pair[0]).above(&pair[1]);
+    }
+    params
+        .adjacencies
+        .widget(&buttons[buttons.len() - 1])
+        .above(&bottom_buttons[1])
+        .to_left_of(&bottom_buttons[0]);
 }
 
 /// Render the sound settings UI
@@ -673,7 +1874,7 @@ fn sound_settings_ui(_ui: &mut egui::Ui, _game: &GameMeta) {
 }
 
 /// Format an InputKind as a user-facing string
-fn format_input(input: &InputKind) -> String {
+pub fn format_input(input: &InputKind) -> String {
     match input {
         InputKind::SingleAxis(axis) => {
             // If we set the positive low to 1.0, then that means we don't trigger on positive
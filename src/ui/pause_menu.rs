@@ -5,7 +5,8 @@ use iyes_loopless::state::NextState;
 
 use crate::{
     localization::LocalizationExt,
-    metadata::{ButtonStyle, FontStyle, GameMeta},
+    menu_stack::{MenuLayer, MenuStack},
+    metadata::{ButtonStyle, FontStyle, GameMeta, UIThemeState},
     utils::ResetController,
     GameState,
 };
@@ -20,9 +21,10 @@ pub fn pause_menu(
     mut egui_context: ResMut<EguiContext>,
     game: Res<GameMeta>,
     localization: Res<Localization>,
+    mut menu_stack: ResMut<MenuStack>,
     reset_controller: ResetController,
 ) {
-    let ui_theme = &game.ui_theme;
+    let ui_theme = game.ui_theme.for_state(UIThemeState::Paused);
 
     egui::CentralPanel::default()
         .frame(egui::Frame::none())
@@ -66,7 +68,20 @@ pub fn pause_menu(
                         }
 
                         if continue_button.clicked() {
-                            commands.insert_resource(NextState(GameState::InGame));
+                            menu_stack.pop();
+                        }
+
+                        if BorderedButton::themed(
+                            ui_theme,
+                            &ButtonStyle::Normal,
+                            &localization.get("settings"),
+                        )
+                        .min_size(egui::vec2(width, 0.0))
+                        .show(ui)
+                        .clicked()
+                        {
+                            menu_stack.push(MenuLayer::Settings);
+                            ui.ctx().clear_focus();
                         }
 
                         if BorderedButton::themed(
@@ -80,7 +95,8 @@ pub fn pause_menu(
                         {
                             reset_controller.reset_world();
 
-                            // Show the main menu
+                            // Show the main menu, without the menus that were opened over the level
+                            menu_stack.clear();
                             commands.insert_resource(NextState(GameState::MainMenu));
                             ui.ctx().clear_focus();
                         }
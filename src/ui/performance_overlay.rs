@@ -0,0 +1,228 @@
+//! Overlay that shows how well the game is running.
+//!
+//! The overlay, toggled with F6 or shown at startup with `--perf-overlay`, shows the frame rate, a
+//! graph of the recent frame times, the number of entities and archetypes, and how long the
+//! stages of the schedule took in the last frames.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    ecs::{archetype::Archetypes, entity::Entities},
+    prelude::*,
+    utils::Instant,
+};
+use bevy_egui::{egui, EguiContext};
+use bevy_fluent::Localization;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    config::ENGINE_CONFIG,
+    localization::LocalizationExt,
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+};
+
+/// The number of frames shown in the frame time graph
+const FRAME_HISTORY_LEN: usize = 240;
+
+/// The frame time at the top of the graph, in seconds
+const GRAPH_MAX_FRAME_TIME: f32 = 1.0 / 30.0;
+
+/// The target frame time, drawn as a line in the graph
+const TARGET_FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// How much of the new stage timings are mixed into the shown average every frame
+const TIMING_SMOOTHING: f32 = 0.05;
+
+/// The names of the timed stages, in the order they run in
+const TIMED_STAGES: [&str; 6] = [
+    "First",
+    "PreUpdate",
+    "Gameplay",
+    "Update",
+    "PostUpdate",
+    "Last",
+];
+
+pub struct PerformanceOverlayPlugin;
+
+impl Plugin for PerformanceOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PerformanceOverlay {
+            enabled: ENGINE_CONFIG.perf_overlay,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+        })
+        .init_resource::<StageTimings>()
+        .add_system(performance_overlay_window);
+
+        // Time the stages with exclusive systems that run at their very start and end
+        app.add_system_to_stage(CoreStage::First, start_stage_timer::<0>.at_start())
+            .add_system_to_stage(CoreStage::First, end_stage_timer::<0>.at_end())
+            .add_system_to_stage(CoreStage::PreUpdate, start_stage_timer::<1>.at_start())
+            .add_system_to_stage(CoreStage::PreUpdate, end_stage_timer::<1>.at_end())
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                start_stage_timer::<2>.at_start(),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                end_stage_timer::<2>.at_end(),
+            )
+            .add_system_to_stage(CoreStage::Update, start_stage_timer::<3>.at_start())
+            .add_system_to_stage(CoreStage::Update, end_stage_timer::<3>.at_end())
+            .add_system_to_stage(CoreStage::PostUpdate, start_stage_timer::<4>.at_start())
+            .add_system_to_stage(CoreStage::PostUpdate, end_stage_timer::<4>.at_end())
+            .add_system_to_stage(CoreStage::Last, start_stage_timer::<5>.at_start())
+            .add_system_to_stage(CoreStage::Last, end_stage_timer::<5>.at_end());
+    }
+}
+
+/// State of the performance overlay
+#[derive(Resource)]
+pub struct PerformanceOverlay {
+    pub enabled: bool,
+    /// The durations of the last frames, in seconds
+    frame_times: VecDeque<f32>,
+}
+
+/// How long the [`TIMED_STAGES`] take
+#[derive(Resource, Default)]
+struct StageTimings {
+    started: [Option<Instant>; TIMED_STAGES.len()],
+    /// The time spent in the stages so far this frame. The gameplay stage may run several times
+    /// in one frame.
+    current: [Duration; TIMED_STAGES.len()],
+    /// The smoothed time spent in the stages per frame, in seconds
+    average: [f32; TIMED_STAGES.len()],
+}
+
+fn start_stage_timer<const STAGE: usize>(world: &mut World) {
+    let mut timings = world.resource_mut::<StageTimings>();
+
+    // The first stage starts a new frame
+    if STAGE == 0 {
+        let timings = &mut *timings;
+        for (average, current) in timings.average.iter_mut().zip(&mut timings.current) {
+            *average += (current.as_secs_f32() - *average) * TIMING_SMOOTHING;
+            *current = Duration::ZERO;
+        }
+    }
+
+    timings.started[STAGE] = Some(Instant::now());
+}
+
+fn end_stage_timer<const STAGE: usize>(world: &mut World) {
+    let mut timings = world.resource_mut::<StageTimings>();
+    if let Some(started) = timings.started[STAGE].take() {
+        timings.current[STAGE] += started.elapsed();
+    }
+}
+
+/// Records the frame times and renders the overlay
+fn performance_overlay_window(
+    mut overlay: ResMut<PerformanceOverlay>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+    input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    timings: Res<StageTimings>,
+    entities: &Entities,
+    archetypes: &Archetypes,
+) {
+    if input.just_pressed(KeyCode::F6) {
+        overlay.enabled = !overlay.enabled;
+    }
+
+    if overlay.frame_times.len() == FRAME_HISTORY_LEN {
+        overlay.frame_times.pop_front();
+    }
+    overlay.frame_times.push_back(time.delta_seconds());
+
+    if !overlay.enabled {
+        return;
+    }
+
+    let average_frame_time =
+        overlay.frame_times.iter().sum::<f32>() / overlay.frame_times.len() as f32;
+    let max_frame_time = overlay.frame_times.iter().copied().fold(0.0, f32::max);
+
+    let mut enabled = overlay.enabled;
+    egui::Window::new(localization.get("performance-overlay"))
+        .id(egui::Id::new("performance_overlay"))
+        .open(&mut enabled)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{}: {:.0}",
+                localization.get("fps"),
+                1.0 / average_frame_time.max(f32::EPSILON)
+            ));
+            ui.label(format!(
+                "{}: {:.2} ms ( {} {:.2} ms )",
+                localization.get("frame-time"),
+                average_frame_time * 1000.0,
+                localization.get("max"),
+                max_frame_time * 1000.0
+            ));
+
+            frame_time_graph(ui, &overlay.frame_times);
+
+            ui.label(format!(
+                "{}: {}",
+                localization.get("entities"),
+                entities.len()
+            ));
+            ui.label(format!(
+                "{}: {}",
+                localization.get("archetypes"),
+                archetypes.len()
+            ));
+
+            ui.separator();
+            ui.label(localization.get("stage-timings"));
+            egui::Grid::new("stage_timings").show(ui, |ui| {
+                for (name, average) in TIMED_STAGES.iter().zip(&timings.average) {
+                    ui.label(*name);
+                    ui.label(format!("{:.2} ms", average * 1000.0));
+                    ui.end_row();
+                }
+            });
+        });
+    overlay.enabled = enabled;
+}
+
+/// Draws the frame times as bars, with a line at the target frame time
+fn frame_time_graph(ui: &mut egui::Ui, frame_times: &VecDeque<f32>) {
+    let size = egui::vec2(FRAME_HISTORY_LEN as f32, 60.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+
+    let y = |frame_time: f32| {
+        rect.bottom() - (frame_time / GRAPH_MAX_FRAME_TIME).min(1.0) * rect.height()
+    };
+    let bar_width = rect.width() / FRAME_HISTORY_LEN as f32;
+    for (i, frame_time) in frame_times.iter().enumerate() {
+        let x = rect.left() + (i as f32 + 0.5) * bar_width;
+        let color = if *frame_time > TARGET_FRAME_TIME * 1.5 {
+            egui::Color32::LIGHT_RED
+        } else {
+            egui::Color32::LIGHT_GREEN
+        };
+        painter.line_segment(
+            [egui::pos2(x, rect.bottom()), egui::pos2(x, y(*frame_time))],
+            egui::Stroke::new(bar_width, color),
+        );
+    }
+
+    let target_y = y(TARGET_FRAME_TIME);
+    painter.line_segment(
+        [
+            egui::pos2(rect.left(), target_y),
+            egui::pos2(rect.right(), target_y),
+        ],
+        egui::Stroke::new(1.0, egui::Color32::WHITE),
+    );
+}
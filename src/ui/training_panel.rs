@@ -0,0 +1,118 @@
+//! The panel of the [training mode](crate::training), with the dummy settings, the hitbox display,
+//! the readouts of the last hits, and the input history of the player.
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{
+    localization::LocalizationExt,
+    training::{DummyBehavior, InputHistoryEntry, TrainingSession},
+    ui::hitbox_debug::{HitboxDebug, HitboxDebugCategory},
+};
+
+pub fn training_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut session: ResMut<TrainingSession>,
+    mut hitbox_debug: ResMut<HitboxDebug>,
+    localization: Res<Localization>,
+) {
+    egui::Window::new(localization.get("training"))
+        // ID is needed because title comes from localizaition which can change
+        .id(egui::Id::new("training_panel"))
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(localization.get("dummy"));
+            ui.horizontal(|ui| {
+                for behavior in DummyBehavior::ALL {
+                    ui.selectable_value(
+                        &mut session.dummy_behavior,
+                        behavior,
+                        localization.get(behavior.localization_key()),
+                    );
+                }
+            });
+
+            ui.separator();
+            for category in HitboxDebugCategory::ALL {
+                ui.checkbox(
+                    hitbox_debug.enabled_mut(category),
+                    localization.get(category.name()),
+                );
+            }
+
+            ui.separator();
+            let not_yet = || "-".to_owned();
+            egui::Grid::new("training_readouts").show(ui, |ui| {
+                ui.label(localization.get("last-hit-damage"));
+                ui.label(
+                    session
+                        .last_hit_damage
+                        .map_or_else(not_yet, |d| d.to_string()),
+                );
+                ui.end_row();
+
+                ui.label(localization.get("combo"));
+                ui.label(format!(
+                    "{} ( {} )",
+                    session.combo_damage, session.combo_hits
+                ));
+                ui.end_row();
+
+                ui.label(localization.get("frame-advantage"));
+                ui.label(
+                    session
+                        .frame_advantage
+                        .map_or_else(not_yet, |frames| format!("{frames:+}")),
+                );
+                ui.end_row();
+            });
+
+            ui.separator();
+            ui.label(localization.get("input-history"));
+            for entry in &session.input_history {
+                ui.monospace(format_input(entry));
+            }
+
+            ui.separator();
+            if ui
+                .button(format!(
+                    "{} ( Backspace )",
+                    localization.get("reset-positions")
+                ))
+                .clicked()
+            {
+                session.request_reset();
+            }
+        });
+}
+
+/// Format an input of the input history like `6A  12`, with the direction in numpad notation,
+/// the buttons, and the number of steps it was held for
+fn format_input(entry: &InputHistoryEntry) -> String {
+    let mut buttons = String::new();
+    for (pressed, button) in [
+        (entry.attack, 'A'),
+        (entry.throw, 'T'),
+        (entry.shoot, 'S'),
+        (entry.block, 'B'),
+        (entry.dodge, 'D'),
+    ] {
+        if pressed {
+            buttons.push(button);
+        }
+    }
+
+    format!("{}{buttons:<3} {:>4}", entry.direction, entry.steps)
+}
+
+/// Hide the hitboxes shown for training once it's over
+pub fn hide_training_hitboxes(
+    session: Option<Res<TrainingSession>>,
+    mut hitbox_debug: ResMut<HitboxDebug>,
+) {
+    if session.is_some() {
+        *hitbox_debug = default();
+    }
+}
@@ -0,0 +1,67 @@
+//! The prompt of the current step of a [tutorial](crate::tutorial), with the buttons of its
+//! action.
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{
+    input::InputDevices,
+    localization::LocalizationExt,
+    metadata::{ButtonStyle, FontStyle, GameMeta, LevelMeta, UIThemeState},
+    tutorial::Tutorial,
+};
+
+use super::{
+    main_menu::format_input,
+    widgets::{bordered_frame::BorderedFrame, EguiUIExt},
+};
+
+pub fn tutorial_prompt(
+    mut egui_context: ResMut<EguiContext>,
+    tutorial: Res<Tutorial>,
+    input_devices: Res<InputDevices>,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    if !tutorial.prompt_shown {
+        return;
+    }
+    let Some(step) = level.tutorial.get(tutorial.step) else {
+        return;
+    };
+
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let font = ui_theme
+        .font_styles
+        .get(&FontStyle::Bigger)
+        .expect("Missing font style")
+        .colored(ui_theme.panel.font_color);
+    let button_theme = ui_theme
+        .button_styles
+        .get(&ButtonStyle::Normal)
+        .expect("Missing button style");
+
+    egui::Area::new("tutorial_prompt")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -40.0))
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.themed_label(&font, &localization.get(step.prompt.as_str()));
+
+                        // The buttons look like the menu buttons, so that they read as buttons
+                        for button in tutorial.buttons(step.action, input_devices.get(0)) {
+                            BorderedFrame::new(&button_theme.borders.default)
+                                .padding(button_theme.padding.into())
+                                .show(ui, |ui| {
+                                    ui.themed_label(&button_theme.font, &format_input(&button));
+                                });
+                        }
+                    });
+                });
+        });
+}
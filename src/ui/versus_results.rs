@@ -0,0 +1,140 @@
+//! The results screen shown at the end of a [versus match](crate::versus).
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    assist::Assists,
+    localization::LocalizationExt,
+    metadata::{ButtonStyle, FontStyle, GameMeta, UIThemeState},
+    utils::ResetController,
+    versus::{self, RoundState, VersusMatch},
+    GameState,
+};
+
+use super::{
+    widgets::{bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiUIExt},
+    EguiContextExt, EguiResponseExt,
+};
+
+pub fn versus_results(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut versus: ResMut<VersusMatch>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    assists: Option<Res<Assists>>,
+    reset_controller: ResetController,
+) {
+    let RoundState::MatchOver { winner } = versus.state else {
+        return;
+    };
+    let Some(versus_meta) = &game.versus else {
+        return;
+    };
+
+    let ui_theme = game.ui_theme.for_state(UIThemeState::InGame);
+    let font = |style: FontStyle| {
+        ui_theme
+            .font_styles
+            .get(&style)
+            .expect("Missing font style")
+            .colored(ui_theme.panel.font_color)
+    };
+    let heading_font = font(FontStyle::Heading);
+    let bigger_font = font(FontStyle::Bigger);
+    let normal_font = font(FontStyle::Normal);
+
+    let mut rematch = false;
+    let mut main_menu = false;
+
+    // An area instead of a central panel, so that the HUD is still shown behind it
+    egui::Area::new("versus_results")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(400.0);
+
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(
+                            &heading_font,
+                            &format!(
+                                "{} {} {}",
+                                localization.get("team"),
+                                winner + 1,
+                                localization.get("wins-the-match")
+                            ),
+                        );
+                        ui.themed_label(
+                            &bigger_font,
+                            &format!("{} - {}", versus.wins[0], versus.wins[1]),
+                        );
+                        ui.add_space(10.0);
+
+                        egui::Grid::new("versus_results")
+                            .num_columns(4)
+                            .spacing(egui::vec2(30.0, 4.0))
+                            .show(ui, |ui| {
+                                for key in ["player", "team", "damage-dealt", "knockouts"] {
+                                    ui.themed_label(&bigger_font, &localization.get(key));
+                                }
+                                ui.end_row();
+
+                                for player in 0..versus.player_count {
+                                    ui.themed_label(&normal_font, &(player + 1).to_string());
+                                    ui.themed_label(
+                                        &normal_font,
+                                        &(versus::team(player) + 1).to_string(),
+                                    );
+                                    ui.themed_label(
+                                        &normal_font,
+                                        &versus.damage_dealt[player].to_string(),
+                                    );
+                                    ui.themed_label(
+                                        &normal_font,
+                                        &versus.knockouts[player].to_string(),
+                                    );
+                                    ui.end_row();
+                                }
+                            });
+                        if assists.is_some() {
+                            ui.add_space(10.0);
+                            ui.themed_label(&normal_font, &localization.get("played-with-assists"));
+                        }
+                        ui.add_space(10.0);
+
+                        let min_button_size = egui::vec2(ui.available_width() / 2.0, 0.0);
+                        rematch = BorderedButton::themed(
+                            ui_theme,
+                            &ButtonStyle::Normal,
+                            &localization.get("rematch"),
+                        )
+                        .min_size(min_button_size)
+                        .show(ui)
+                        .focus_by_default(ui)
+                        .clicked();
+
+                        main_menu = BorderedButton::themed(
+                            ui_theme,
+                            &ButtonStyle::Normal,
+                            &localization.get("main-menu"),
+                        )
+                        .min_size(min_button_size)
+                        .show(ui)
+                        .clicked();
+                    });
+                });
+        });
+
+    if rematch {
+        versus.rematch(versus_meta);
+        egui_context.ctx_mut().clear_focus();
+    } else if main_menu {
+        reset_controller.reset_world();
+        commands.insert_resource(NextState(GameState::MainMenu));
+    }
+}
@@ -20,6 +20,7 @@ pub struct BorderedButton<'a> {
     on_click_border: Option<&'a BorderImageMeta>,
     margin: egui::style::Margin,
     padding: egui::style::Margin,
+    description: Option<String>,
 }
 
 impl<'a> BorderedButton<'a> {
@@ -36,6 +37,7 @@ impl<'a> BorderedButton<'a> {
             on_click_border: None,
             margin: Default::default(),
             padding: Default::default(),
+            description: None,
         }
     }
 
@@ -128,6 +130,14 @@ impl<'a> BorderedButton<'a> {
         self
     }
 
+    /// Set the text that the button is narrated with, instead of its label, such as the name of
+    /// the setting that the button changes
+    #[must_use = "You must call .show() to render the button"]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
     /// Render the button
     #[must_use = "You must call .show() to render the button"]
     pub fn show(self, ui: &mut Ui) -> egui::Response {
@@ -147,6 +157,7 @@ impl<'a> Widget for BorderedButton<'a> {
             on_click_border,
             margin,
             padding,
+            description,
         }: BorderedButton = self;
 
         let total_extra = padding.sum() + margin.sum();
@@ -158,7 +169,26 @@ impl<'a> Widget for BorderedButton<'a> {
         desired_size = desired_size.at_least(min_size);
 
         let (rect, response) = ui.allocate_at_least(desired_size, sense);
-        response.widget_info(|| WidgetInfo::labeled(WidgetType::Button, text.text()));
+        let description = description.unwrap_or_else(|| text.text().to_owned());
+        response.widget_info(|| WidgetInfo::labeled(WidgetType::Button, &description));
+
+        // Announce the new description of the focused button when it changes, like when the value
+        // of a setting is changed by clicking it
+        if response.has_focus() {
+            let changed = ui
+                .data()
+                .get_temp::<String>(response.id)
+                .map_or(false, |last| last != description);
+            if changed && !response.gained_focus() {
+                ui.ctx()
+                    .output()
+                    .events
+                    .push(egui::output::OutputEvent::ValueChanged(
+                        WidgetInfo::labeled(WidgetType::Button, &description),
+                    ));
+            }
+            ui.data().insert_temp(response.id, description);
+        }
 
         // Focus the button automatically when it is hovered and the mouse is moving
         if response.hovered() && ui.ctx().input().pointer.velocity().length_sq() > 0.0 {
@@ -194,8 +224,9 @@ impl<'a> Widget for BorderedButton<'a> {
             border_rect.max.y = border_rect.max.y.max(border_rect.min.y);
 
             if let Some(border) = border {
-                ui.painter()
-                    .add(BorderedFrame::new(border).paint(border_rect));
+                ui.painter().add(
+                    BorderedFrame::new(border).paint(border_rect, ui.ctx().pixels_per_point()),
+                );
             }
 
             text.paint_with_visuals(ui.painter(), label_pos, visuals);
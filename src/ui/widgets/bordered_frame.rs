@@ -1,6 +1,6 @@
 use bevy_egui::egui;
 
-use crate::metadata::BorderImageMeta;
+use crate::metadata::{select_resolution, BorderImageMeta};
 
 /// A 9-patch style bordered frame.
 ///
@@ -9,6 +9,8 @@ use crate::metadata::BorderImageMeta;
 /// - [`UiBorderImage`]
 pub struct BorderedFrame {
     bg_texture: egui::TextureId,
+    /// The other resolutions of the texture, with their scales
+    bg_texture_resolutions: Vec<(f32, egui::TextureId)>,
     border_scale: f32,
     texture_size: egui::Vec2,
     texture_border_size: egui::style::Margin,
@@ -24,6 +26,11 @@ impl BorderedFrame {
         let s = border_image.image_size;
         Self {
             bg_texture: border_image.egui_texture,
+            bg_texture_resolutions: border_image
+                .resolutions
+                .iter()
+                .map(|resolution| (resolution.scale, resolution.egui_texture))
+                .collect(),
             border_scale: border_image.scale,
             texture_size: egui::Vec2::new(s.x as f32, s.y as f32),
             texture_border_size: border_image.border_size.into(),
@@ -111,12 +118,18 @@ impl BorderedFrame {
         }
     }
 
-    pub fn paint(&self, paint_rect: egui::Rect) -> egui::Shape {
+    /// Paint the frame, using the resolution of the border image that fits a UI with
+    /// `pixels_per_point` pixels on the screen for every point
+    pub fn paint(&self, paint_rect: egui::Rect, pixels_per_point: f32) -> egui::Shape {
         use egui::{Pos2, Rect, Vec2};
         let white = egui::Color32::WHITE;
 
         let mut mesh = egui::Mesh {
-            texture_id: self.bg_texture,
+            texture_id: select_resolution(
+                self.bg_texture,
+                self.bg_texture_resolutions.iter().copied(),
+                pixels_per_point,
+            ),
             ..Default::default()
         };
 
@@ -269,7 +282,7 @@ impl BorderedFramePrepared {
             max: min_rect.max + Vec2::new(m.right, m.bottom),
         };
         if ui.is_rect_visible(paint_rect) {
-            let shape = self.frame.paint(paint_rect);
+            let shape = self.frame.paint(paint_rect, ui.ctx().pixels_per_point());
             ui.painter().set(self.background_shape_idx, shape);
         }
 
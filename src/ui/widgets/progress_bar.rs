@@ -8,6 +8,8 @@ pub struct ProgressBar<'a> {
     pub theme: &'a ProgressBarMeta,
     pub progress: f32,
     pub min_width: f32,
+    /// A color to fill the progress with instead of the progress image
+    pub color: Option<egui::Color32>,
 }
 
 impl<'a> ProgressBar<'a> {
@@ -17,6 +19,7 @@ impl<'a> ProgressBar<'a> {
             theme,
             progress,
             min_width: 0.0,
+            color: None,
         }
     }
 
@@ -26,6 +29,12 @@ impl<'a> ProgressBar<'a> {
         self
     }
 
+    #[must_use = "You must call .show() to render the progress bar"]
+    pub fn color(mut self, color: Option<egui::Color32>) -> Self {
+        self.color = color;
+        self
+    }
+
     pub fn show(self, ui: &mut egui::Ui) -> egui::Response {
         let bg = &self.theme.background_image;
         let size = bg.image_size.as_vec2() * bg.scale;
@@ -34,7 +43,8 @@ impl<'a> ProgressBar<'a> {
 
         let (rect, response) = ui.allocate_at_least(size, egui::Sense::hover());
 
-        let frame = BorderedFrame::new(&self.theme.background_image).paint(rect);
+        let frame = BorderedFrame::new(&self.theme.background_image)
+            .paint(rect, ui.ctx().pixels_per_point());
         ui.painter().add(frame);
 
         let b = bg.border_size;
@@ -44,8 +54,13 @@ impl<'a> ProgressBar<'a> {
             size.y - (b.top + b.bottom) * bg.scale,
         );
         let inner_rect = egui::Rect::from_min_size(inner_rect_min, inner_rect_size);
-        let bar = BorderedFrame::new(&self.theme.progress_image).paint(inner_rect);
-        ui.painter().add(bar);
+        if let Some(color) = self.color {
+            ui.painter().rect_filled(inner_rect, 0.0, color);
+        } else {
+            let bar = BorderedFrame::new(&self.theme.progress_image)
+                .paint(inner_rect, ui.ctx().pixels_per_point());
+            ui.painter().add(bar);
+        }
 
         response
     }
@@ -1,34 +1,105 @@
 use bevy::{
     ecs::system::SystemParam,
-    hierarchy::DespawnRecursiveExt,
-    prelude::{Camera, Commands, Entity, Query, Transform, With, Without},
+    hierarchy::{DespawnRecursiveExt, Parent},
+    prelude::{Camera, Commands, Entity, EventWriter, Query, Transform, With, Without},
 };
+use bevy_parallax::ParallaxCameraComponent;
+
+use crate::{
+    enemy::Enemy, player::Player, post_processing::PostProcessingEntity, streaming::LevelStreaming,
+};
+
+/// How much of the game world is reset by the [`ResetController`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetScope {
+    /// Despawn the enemies, leaving the rest of the level as it is
+    Enemies,
+    /// Despawn the level, but keep the players with their health, items, and meters, so that the
+    /// level can be loaded again for them
+    Level,
+    /// Despawn all of the gameplay entities, including the players
+    World,
+}
+
+impl ResetScope {
+    /// Whether the level is despawned by the reset
+    pub fn resets_level(self) -> bool {
+        matches!(self, Self::Level | Self::World)
+    }
+}
+
+/// Event sent when the game world is reset by the [`ResetController`], so that the systems that
+/// keep track of the world, like the ones that play sounds, can clean up after it
+pub struct ResetEvent {
+    pub scope: ResetScope,
+}
 
 /// System parameter that can be used to reset the game world.
 ///
-/// Currently this just means de-spawning all of the entities other than the camera and resetting
-/// the camera position, but in the future this process might be more involved so we centralize the
-/// logic here so it can be re-used.
+/// Currently this just means de-spawning the entities in the [scope](ResetScope) of the reset and
+/// resetting the camera position, but in the future this process might be more involved so we
+/// centralize the logic here so it can be re-used.
 #[derive(SystemParam)]
 pub struct ResetController<'w, 's> {
     commands: Commands<'w, 's>,
-    camera_transform: Query<'w, 's, &'static mut Transform, With<Camera>>,
-    entities_to_despawn: Query<'w, 's, Entity, Without<Camera>>,
+    reset_events: EventWriter<'w, 's, ResetEvent>,
+    camera_transform: Query<'w, 's, &'static mut Transform, With<ParallaxCameraComponent>>,
+    entities_to_despawn: Query<'w, 's, Entity, (Without<Camera>, Without<PostProcessingEntity>)>,
+    /// The entities at the root of the hierarchy other than the players, whose children like their
+    /// hurtboxes and shadows are kept with them
+    level_entities: Query<
+        'w,
+        's,
+        Entity,
+        (
+            Without<Parent>,
+            Without<Player>,
+            Without<Camera>,
+            Without<PostProcessingEntity>,
+        ),
+    >,
+    enemies: Query<'w, 's, Entity, With<Enemy>>,
 }
 
 impl<'w, 's> ResetController<'w, 's> {
     /// Clean up the game world, despawning all the gameplay entities, but leaving necessary
     /// entities like camera.
-    pub fn reset_world(mut self) {
-        // Clean up all entities other than the camera
-        for entity in self.entities_to_despawn.iter() {
-            self.commands.entity(entity).despawn_recursive();
+    pub fn reset_world(self) {
+        self.reset(ResetScope::World);
+    }
+
+    /// Reset the part of the game world in the given scope
+    pub fn reset(mut self, scope: ResetScope) {
+        match scope {
+            ResetScope::Enemies => {
+                for entity in &self.enemies {
+                    self.commands.entity(entity).despawn_recursive();
+                }
+            }
+            ResetScope::Level => {
+                for entity in &self.level_entities {
+                    self.commands.entity(entity).despawn_recursive();
+                }
+            }
+            ResetScope::World => {
+                // Clean up all entities other than the camera
+                for entity in self.entities_to_despawn.iter() {
+                    self.commands.entity(entity).despawn_recursive();
+                }
+            }
         }
 
-        // Reset camera position
-        if let Some(mut camera_transform) = self.camera_transform.iter_mut().next() {
-            camera_transform.translation.x = 0.0;
-            camera_transform.translation.y = 0.0;
+        if scope.resets_level() {
+            // Release the assets of the streamed level sections
+            self.commands.remove_resource::<LevelStreaming>();
+
+            // Reset camera position
+            if let Some(mut camera_transform) = self.camera_transform.iter_mut().next() {
+                camera_transform.translation.x = 0.0;
+                camera_transform.translation.y = 0.0;
+            }
         }
+
+        self.reset_events.send(ResetEvent { scope });
     }
 }
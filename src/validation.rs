@@ -0,0 +1,1142 @@
+//! Headless validation of the game metadata, run with the `--validate-assets` flag.
+//!
+//! This doesn't start a Bevy app. It walks the asset directory, deserializes every metadata file
+//! with the same types the asset loaders use, and checks that the files they reference exist, so
+//! that content creators can check their changes without having to play through the game.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::default, utils::HashMap};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    assets::{absolutize_fighter_paths, merge_yaml, relative_asset_path, take_yaml_key},
+    enemy_ai::RANGED_ATTACKS,
+    fighter_state::{Dying, HitStun, Idling, Moving},
+    input::InputScript,
+    metadata::{
+        BorderImageMeta, Element, EndlessLevelMeta, FighterMeta, FighterSpritesheetMeta, GameMeta,
+        ItemKind, ItemMeta, LevelMeta, LevelStreamingMeta, StatusEffectKind, StatusEffectMeta,
+    },
+};
+
+/// The animations that the fighter state systems expect every fighter to have.
+const FIGHTER_ANIMATIONS: &[&str] = &[
+    Idling::ANIMATION,
+    Moving::ANIMATION,
+    HitStun::HITSTUN,
+    HitStun::KNOCKED_LEFT,
+    HitStun::KNOCKED_RIGHT,
+    Dying::ANIMATION,
+];
+
+/// The errors and warnings found while validating the game assets.
+#[derive(Default, Debug)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// The number of metadata files that were checked
+    pub checked: usize,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Print the report to the standard output
+    pub fn print(&self) {
+        for warning in &self.warnings {
+            println!("warning: {warning}");
+        }
+        for error in &self.errors {
+            println!("error: {error}");
+        }
+
+        println!(
+            "Checked {} metadata files: {} errors, {} warnings",
+            self.checked,
+            self.errors.len(),
+            self.warnings.len()
+        );
+    }
+
+    fn error(&mut self, path: &Path, message: impl std::fmt::Display) {
+        self.errors.push(format!("{}: {message}", path.display()));
+    }
+
+    fn warning(&mut self, path: &Path, message: impl std::fmt::Display) {
+        self.warnings.push(format!("{}: {message}", path.display()));
+    }
+}
+
+/// Validate all of the metadata files in the given asset directory.
+pub fn validate_assets(asset_dir: &Path) -> ValidationReport {
+    let mut validator = Validator {
+        asset_dir,
+        report: default(),
+        fighters: default(),
+        level_fighters: Vec::new(),
+    };
+
+    let mut files = match collect_files(asset_dir) {
+        Ok(files) => files,
+        Err(e) => {
+            validator
+                .report
+                .error(asset_dir, format!("Could not read asset directory: {e}"));
+            Vec::new()
+        }
+    };
+    files.sort();
+
+    for path in files {
+        let file_name = path.to_string_lossy();
+
+        if file_name.ends_with(".game.yaml") || file_name.ends_with(".game.yml") {
+            validator.validate_game(&path);
+        } else if file_name.ends_with(".level.yaml") || file_name.ends_with(".level.yml") {
+            validator.validate_level(&path);
+        } else if file_name.ends_with(".endless.yaml") || file_name.ends_with(".endless.yml") {
+            validator.validate_endless_level(&path);
+        } else if file_name.ends_with(".fighter.yaml") || file_name.ends_with(".fighter.yml") {
+            validator.validate_fighter(&path);
+        } else if file_name.ends_with(".item.yaml") || file_name.ends_with(".item.yml") {
+            validator.validate_item(&path);
+        } else if file_name.ends_with(".demo.yaml") || file_name.ends_with(".demo.yml") {
+            validator.validate_demo(&path);
+        }
+    }
+
+    // The levels are checked against their fighters once all of the fighters have been read
+    validator.check_anti_air();
+
+    validator.report
+}
+
+/// Recursively collect the paths of all files in the asset directory, relative to the directory.
+pub fn collect_files(asset_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files_in(asset_dir, asset_dir, &mut files)?;
+
+    Ok(files)
+}
+
+fn collect_files_in(asset_dir: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files_in(asset_dir, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(asset_dir) {
+            files.push(relative.to_owned());
+        }
+    }
+
+    Ok(())
+}
+
+struct Validator<'a> {
+    asset_dir: &'a Path,
+    report: ValidationReport,
+    /// The fighters that have been read, by their path
+    fighters: HashMap<PathBuf, FighterSummary>,
+    /// The fighters of the levels that have been read
+    level_fighters: Vec<LevelFighters>,
+}
+
+/// What the checks of the levels need to know about one of their fighters
+struct FighterSummary {
+    flying: bool,
+    /// Whether the fighter has an attack that reaches the flying enemies from the ground
+    anti_air: bool,
+}
+
+/// The paths of the players and the enemies of a level
+struct LevelFighters {
+    level: PathBuf,
+    players: Vec<PathBuf>,
+    enemies: Vec<PathBuf>,
+}
+
+impl<'a> Validator<'a> {
+    /// Read and deserialize a metadata file, reporting an error if it fails.
+    fn load<T: DeserializeOwned>(&mut self, path: &Path) -> Option<T> {
+        self.report.checked += 1;
+
+        let value = self.read_yaml(path)?;
+        match serde_yaml::from_value(value) {
+            Ok(meta) => Some(meta),
+            Err(e) => {
+                self.report.error(path, e);
+                None
+            }
+        }
+    }
+
+    fn read_yaml(&mut self, path: &Path) -> Option<serde_yaml::Value> {
+        let bytes = match fs::read(self.asset_dir.join(path)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.report.error(path, e);
+                return None;
+            }
+        };
+
+        match serde_yaml::from_slice(&bytes) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.report.error(path, e);
+                None
+            }
+        }
+    }
+
+    /// Report an error if the asset at `relative_path`, relative to `self_path`, doesn't exist.
+    fn check_file(&mut self, self_path: &Path, relative_path: &str) {
+        let path = relative_asset_path(self_path, relative_path);
+
+        if !self.asset_dir.join(&path).is_file() {
+            self.report.error(
+                self_path,
+                format!("Referenced file does not exist: {}", path.display()),
+            );
+        }
+    }
+
+    fn check_resolution_scale(&mut self, path: &Path, scale: f32) {
+        if scale <= 0.0 {
+            self.report.error(
+                path,
+                format!("UI asset resolution must have a positive scale, not {scale}"),
+            );
+        }
+    }
+
+    fn validate_game(&mut self, path: &Path) {
+        let Some(game) = self.load::<GameMeta>(path) else {
+            return;
+        };
+
+        self.check_file(path, &game.start_level);
+        if let Some(endless_level) = &game.endless_level {
+            self.check_file(path, endless_level);
+        }
+        if let Some(tutorial_level) = &game.tutorial_level {
+            self.check_file(path, tutorial_level);
+        }
+        if let Some(training) = &game.training {
+            self.check_file(path, &training.level);
+            if training.fighters.is_empty() {
+                self.report
+                    .error(path, "Training mode doesn't have any fighters");
+            }
+            for fighter in &training.fighters {
+                self.check_file(path, fighter);
+            }
+        }
+        if let Some(survival) = &game.survival {
+            self.check_file(path, &survival.level);
+            if survival.enemies.is_empty() {
+                self.report
+                    .error(path, "Survival mode doesn't have any enemies");
+            }
+            for enemy in &survival.enemies {
+                self.check_file(path, enemy);
+            }
+            if survival.first_wave_size == 0 || survival.max_wave_size < survival.first_wave_size {
+                self.report.error(
+                    path,
+                    "Survival mode's first wave must have between one and `max_wave_size` enemies",
+                );
+            }
+        }
+        if let Some(versus) = &game.versus {
+            self.check_file(path, &versus.level);
+            if versus.rounds_to_win == 0 {
+                self.report
+                    .error(path, "Versus mode must take at least one round to win");
+            }
+        }
+        if let Some(gallery) = &game.gallery {
+            for art in &gallery.art {
+                self.check_file(path, &art.image.image);
+            }
+            for track in &gallery.music {
+                self.check_file(path, &track.music);
+            }
+            for bio in &gallery.bios {
+                self.check_file(path, &bio.fighter);
+            }
+        }
+        if let Some(team_up) = &game.team_up {
+            if team_up.meter <= 0.0 {
+                self.report
+                    .warning(path, "The team-up meter is always full");
+            }
+            if team_up.cinematic_time < 0.0 || team_up.press_window < 0.0 {
+                self.report.error(
+                    path,
+                    "The team-up cinematic time and press window can't be negative",
+                );
+            }
+        }
+        if let Some(combo_scaling) = &game.combo_scaling {
+            let scales = [combo_scaling.min_damage_scale, combo_scaling.min_stun_scale];
+            if scales.iter().any(|scale| !(0.0..=1.0).contains(scale)) {
+                self.report
+                    .error(path, "The combo scaling minimums must be between 0 and 1");
+            }
+        }
+        if let Some(combo_breaker) = &game.combo_breaker {
+            match &game.team_up {
+                Some(team_up) if combo_breaker.cost > team_up.meter => self.report.warning(
+                    path,
+                    "The combo breaker costs more than the team-up meter can hold",
+                ),
+                None => self
+                    .report
+                    .warning(path, "The combo breaker needs the team-up meter to be used"),
+                _ => (),
+            }
+            if combo_breaker.duration < 0.0 {
+                self.report
+                    .error(path, "The combo breaker duration can't be negative");
+            }
+        }
+        if let Some(reflection) = &game.reflection {
+            if reflection.window <= 0.0 {
+                self.report.warning(
+                    path,
+                    "The reflection window is empty, so nothing is reflected",
+                );
+            }
+        }
+        if let Some(cinematic) = &game.knockout_cinematic {
+            if cinematic.time_scale <= 0.0 || cinematic.zoom <= 0.0 {
+                self.report.error(
+                    path,
+                    "The knockout cinematic time scale and zoom must be positive",
+                );
+            }
+            if !(0.0..=1.0).contains(&cinematic.desaturation) {
+                self.report.error(
+                    path,
+                    "The knockout cinematic desaturation must be between 0 and 1",
+                );
+            }
+        }
+        if let Some(hit_stop) = &game.hit_stop {
+            if hit_stop.max_duration < hit_stop.duration {
+                self.report.warning(
+                    path,
+                    "The hit-stop max duration is shorter than its duration, so it caps every hit",
+                );
+            }
+            if hit_stop.duration_per_damage < 0.0 {
+                self.report.warning(
+                    path,
+                    "The hit-stop duration per damage shortens stronger hits",
+                );
+            }
+        }
+        if let Some(spirits) = &game.spirits {
+            self.check_file(path, &spirits.image.image);
+            self.check_file(path, &spirits.pickup);
+            if spirits.cooldown < 0.0 || spirits.distract_time < 0.0 {
+                self.report.error(
+                    path,
+                    "The spirit cooldown and distract time can't be negative",
+                );
+            }
+        }
+        if let Some(nameplates) = &game.nameplates {
+            self.check_file(path, &nameplates.threat_icon.image);
+        }
+        if let Some(effects) = &game.effects {
+            let all_effects = [
+                &effects.hit,
+                &effects.heavy_hit,
+                &effects.critical_hit,
+                &effects.landing,
+                &effects.blood,
+            ];
+            for effect in all_effects.into_iter().flatten() {
+                self.validate_spritesheet(path, &effect.spritesheet);
+                if !effect
+                    .spritesheet
+                    .animations
+                    .contains_key(&effect.animation)
+                {
+                    self.report.error(
+                        path,
+                        format!(
+                            "The effect animation `{}` isn't in its spritesheet",
+                            effect.animation
+                        ),
+                    );
+                }
+            }
+            if effects.hit.is_some() && effects.heavy_hit.is_some() && effects.heavy_hit_damage <= 0
+            {
+                self.report.warning(
+                    path,
+                    "Every hit is a heavy hit, so the hit effect is never played",
+                );
+            }
+        }
+        if let Some(new_game_plus) = &game.new_game_plus {
+            for enemy_variant in &new_game_plus.enemy_variants {
+                self.check_file(path, &enemy_variant.fighter);
+                self.check_file(path, &enemy_variant.variant);
+            }
+        }
+        self.check_file(path, &game.main_menu.background_image.image);
+        self.check_file(path, &game.main_menu.music);
+        self.check_file(path, &game.main_menu.play_button_sound);
+        for sound in &game.main_menu.button_sounds {
+            self.check_file(path, sound);
+        }
+        if let Some(attract_demo) = &game.main_menu.attract_demo {
+            self.check_file(path, attract_demo);
+        }
+        for font in game.ui_theme.font_families.values() {
+            self.check_file(path, font);
+        }
+        for (family, resolutions) in &game.ui_theme.font_resolutions {
+            if !game.ui_theme.font_families.contains_key(family) {
+                self.report.error(
+                    path,
+                    format!("Font resolutions are given for unknown font family: {family}"),
+                );
+            }
+            for resolution in resolutions {
+                self.check_file(path, &resolution.font);
+                self.check_resolution_scale(path, resolution.scale);
+            }
+        }
+        for script in &game.scripts {
+            self.check_file(path, script);
+        }
+        for locale in &game.translations.locales {
+            self.check_file(path, locale);
+        }
+
+        let theme = &game.ui_theme;
+        let mut borders: Vec<&BorderImageMeta> = vec![
+            &theme.hud.portrait_frame,
+            &theme.panel.border,
+            &theme.hud.lifebar.background_image,
+            &theme.hud.lifebar.progress_image,
+        ];
+        for button in theme.button_styles.values() {
+            borders.push(&button.borders.default);
+            borders.extend(button.borders.focused.iter());
+            borders.extend(button.borders.clicked.iter());
+        }
+        for theme_override in theme.states.values() {
+            borders.extend(theme_override.border_images());
+        }
+        for border in borders {
+            self.check_file(path, &border.image);
+            for resolution in &border.resolutions {
+                self.check_file(path, &resolution.image);
+                self.check_resolution_scale(path, resolution.scale);
+            }
+        }
+
+        for family in theme.font_styles.values().map(|style| &style.family) {
+            if !theme.font_families.contains_key(family.as_str()) {
+                self.report.error(
+                    path,
+                    format!("Font style uses unknown font family: {}", family.as_str()),
+                );
+            }
+        }
+    }
+
+    fn validate_level(&mut self, path: &Path) {
+        let Some(level) = self.load::<LevelMeta>(path) else {
+            return;
+        };
+
+        if level.players.is_empty() {
+            self.report.error(path, "Level doesn't have any players");
+        }
+
+        for fighter in level.players.iter().chain(&level.enemies) {
+            self.check_file(path, &fighter.fighter);
+        }
+        let wave_enemies = level.waves.iter().flat_map(|wave| &wave.enemies);
+        self.level_fighters.push(LevelFighters {
+            level: path.to_owned(),
+            players: level
+                .players
+                .iter()
+                .map(|player| relative_asset_path(path, &player.fighter))
+                .collect(),
+            enemies: level
+                .enemies
+                .iter()
+                .map(|enemy| &enemy.fighter)
+                .chain(wave_enemies.map(|enemy| &enemy.fighter))
+                .map(|fighter| relative_asset_path(path, fighter))
+                .collect(),
+        });
+        for item in &level.items {
+            self.check_file(path, &item.item);
+        }
+        for layer in &level.parallax_background.layers {
+            self.check_file(path, &layer.path);
+        }
+        self.check_file(path, &level.music);
+
+        if level.stop_points.windows(2).any(|x| x[0] > x[1]) {
+            self.report
+                .warning(path, "Stop points are not sorted from left to right");
+        }
+        self.validate_streaming(path, level.streaming.as_ref());
+
+        for step in &level.tutorial {
+            if step.gate_x < step.trigger_x {
+                self.report.error(
+                    path,
+                    format!(
+                        "Tutorial step `{}` holds the players back before its prompt is shown",
+                        step.prompt
+                    ),
+                );
+            }
+        }
+        if level
+            .tutorial
+            .windows(2)
+            .any(|x| x[0].trigger_x > x[1].trigger_x)
+        {
+            self.report
+                .warning(path, "Tutorial steps are not sorted from left to right");
+        }
+
+        for line in level.dialogues.iter().flat_map(|dialogue| &dialogue.lines) {
+            self.check_file(path, &line.speaker);
+        }
+        if level
+            .dialogues
+            .windows(2)
+            .any(|x| x[0].trigger_x > x[1].trigger_x)
+        {
+            self.report
+                .warning(path, "Dialogues are not sorted from left to right");
+        }
+
+        for wave in &level.waves {
+            if wave.enemies.is_empty() {
+                self.report.warning(
+                    path,
+                    format!("The wave at {} doesn't have any enemies", wave.camera_x),
+                );
+            }
+            for enemy in &wave.enemies {
+                self.check_file(path, &enemy.fighter);
+            }
+        }
+        if level
+            .waves
+            .windows(2)
+            .any(|x| x[0].camera_x > x[1].camera_x)
+        {
+            self.report
+                .warning(path, "Waves are not sorted from left to right");
+        }
+
+        for hazard in &level.hazards {
+            for image in std::iter::once(&hazard.image).chain(&hazard.triggered_image) {
+                self.check_file(path, &image.image);
+            }
+            if hazard.trigger.hits == 0 {
+                self.report
+                    .error(path, "Hazards have to be triggered by at least one hit");
+            }
+            if hazard
+                .trigger
+                .cooldown
+                .map_or(false, |cooldown| cooldown < 0.0)
+            {
+                self.report
+                    .error(path, "The cooldown of a hazard can't be negative");
+            }
+        }
+
+        if let Some(director) = &level.director {
+            if let Some(drop_item) = &director.drop_item {
+                self.check_file(path, drop_item);
+            }
+            let bounds = [
+                director.wave_size_scale,
+                director.attack_tokens,
+                director.drop_chance,
+            ];
+            if bounds
+                .iter()
+                .any(|bounds| bounds.struggling < 0.0 || bounds.doing_well < 0.0)
+            {
+                self.report
+                    .error(path, "The director's bounds can't be negative");
+            }
+            if director.drop_chance.struggling > 1.0 || director.drop_chance.doing_well > 1.0 {
+                self.report
+                    .error(path, "The director's drop chance can't be more than 1");
+            }
+        }
+    }
+
+    /// Warn about the levels with flying enemies that none of the players can reach, which the
+    /// players can't get past
+    fn check_anti_air(&mut self) {
+        for level in std::mem::take(&mut self.level_fighters) {
+            let summaries = |paths: &[PathBuf]| {
+                paths
+                    .iter()
+                    .filter_map(|path| self.fighters.get(path))
+                    .collect::<Vec<_>>()
+            };
+            let has_flying_enemies = summaries(&level.enemies).iter().any(|enemy| enemy.flying);
+            let players_reach_them = summaries(&level.players)
+                .iter()
+                .any(|player| player.anti_air);
+
+            if has_flying_enemies && !players_reach_them {
+                self.report.warning(
+                    &level.level,
+                    "The level has flying enemies, but none of its players has an anti-air attack",
+                );
+            }
+        }
+    }
+
+    fn validate_streaming(&mut self, path: &Path, streaming: Option<&LevelStreamingMeta>) {
+        if let Some(streaming) = streaming {
+            if streaming.section_width <= 0.0 {
+                self.report.error(
+                    path,
+                    "Streamed level sections must have a positive `section_width`",
+                );
+            }
+        }
+    }
+
+    fn validate_demo(&mut self, path: &Path) {
+        let Some(demo) = self.load::<InputScript>(path) else {
+            return;
+        };
+
+        // The level path of input scripts is relative to the asset directory
+        self.check_file(path, &format!("/{}", demo.level.trim_start_matches('/')));
+    }
+
+    fn validate_endless_level(&mut self, path: &Path) {
+        let Some(endless) = self.load::<EndlessLevelMeta>(path) else {
+            return;
+        };
+
+        if endless.players.is_empty() {
+            self.report.error(path, "Level doesn't have any players");
+        }
+        if endless.themes.is_empty() {
+            self.report
+                .error(path, "Endless level doesn't have any themes");
+        }
+
+        for theme in &endless.themes {
+            for layer in &theme.parallax_background.layers {
+                self.check_file(path, &layer.path);
+            }
+            self.check_file(path, &theme.music);
+        }
+
+        let chunks = std::iter::once(&endless.start).chain(&endless.chunks);
+        let fighters = chunks.clone().flat_map(|chunk| &chunk.enemies);
+        for fighter in endless.players.iter().chain(fighters.clone()) {
+            self.check_file(path, &fighter.fighter);
+        }
+        self.level_fighters.push(LevelFighters {
+            level: path.to_owned(),
+            players: endless
+                .players
+                .iter()
+                .map(|player| relative_asset_path(path, &player.fighter))
+                .collect(),
+            enemies: fighters
+                .map(|enemy| relative_asset_path(path, &enemy.fighter))
+                .collect(),
+        });
+        for item in chunks.flat_map(|chunk| &chunk.items) {
+            self.check_file(path, &item.item);
+        }
+        self.validate_streaming(path, endless.streaming.as_ref());
+
+        // Generate a few levels to check that every theme has chunks and that the weights are valid
+        for seed in 0..endless.themes.len() as u64 * 4 {
+            if let Err(e) = endless.generate(seed) {
+                self.report.error(path, e);
+                break;
+            }
+        }
+    }
+
+    fn validate_fighter(&mut self, path: &Path) {
+        let Some(mut value) = self.read_yaml(path) else {
+            self.report.checked += 1;
+            return;
+        };
+
+        // Resolve the fighters this fighter extends, the same way the fighter loader does
+        let mut extended_paths = vec![path.to_owned()];
+        while let Some(extends) = take_yaml_key(&mut value, "extends") {
+            let Some(extends) = extends.as_str() else {
+                self.report
+                    .error(path, "`extends` must be a path to a fighter");
+                self.report.checked += 1;
+                return;
+            };
+            let base_path = relative_asset_path(extended_paths.last().unwrap(), extends);
+
+            if extended_paths.contains(&base_path) {
+                self.report.error(
+                    path,
+                    format!("Fighter extends itself through {base_path:?}"),
+                );
+                self.report.checked += 1;
+                return;
+            }
+
+            let Some(mut base_value) = self.read_yaml(&base_path) else {
+                self.report.checked += 1;
+                return;
+            };
+            absolutize_fighter_paths(&mut base_value, &base_path);
+            merge_yaml(&mut base_value, value);
+
+            value = base_value;
+            extended_paths.push(base_path);
+        }
+
+        self.report.checked += 1;
+        let fighter: FighterMeta = match serde_yaml::from_value(value) {
+            Ok(fighter) => fighter,
+            Err(e) => {
+                self.report.error(path, e);
+                return;
+            }
+        };
+
+        self.fighters.insert(
+            path.to_owned(),
+            FighterSummary {
+                flying: fighter.flying.is_some(),
+                anti_air: fighter.attacks.iter().any(|attack| attack.anti_air),
+            },
+        );
+
+        if fighter.stats.mass.map_or(false, |mass| mass <= 0.0) {
+            self.report
+                .error(path, "Fighter `mass` must be greater than 0");
+        }
+
+        let has_grab = fighter.attacks.iter().any(|attack| attack.name == "grab");
+        if has_grab && fighter.grapple.escape_presses == 0 {
+            self.report.warning(
+                path,
+                "The players break free of the fighter's grab right away",
+            );
+        }
+
+        for (name, socket) in &fighter.sockets {
+            for animation in socket.animations.keys() {
+                if !fighter.spritesheet.animations.contains_key(animation) {
+                    self.report.warning(
+                        path,
+                        format!(
+                            "Socket `{name}` is placed during the missing animation `{animation}`"
+                        ),
+                    );
+                }
+            }
+        }
+
+        if let Some(shadow) = &fighter.shadow {
+            if shadow.size.x <= 0.0 || shadow.size.y <= 0.0 {
+                self.report
+                    .error(path, "Fighter shadow `size` must be greater than 0");
+            }
+        }
+
+        let mut last_phase_health = 1.0;
+        for phase in &fighter.boss_phases {
+            if phase.health <= 0.0 || phase.health > 1.0 {
+                self.report
+                    .error(path, "Boss phase `health` must be between 0 and 1");
+            }
+            if phase.health > last_phase_health {
+                self.report.warning(
+                    path,
+                    "Boss phases should be ordered from the highest `health` to the lowest",
+                );
+            }
+            last_phase_health = phase.health;
+
+            for name in &phase.attacks {
+                if !fighter.attacks.iter().any(|attack| &attack.name == name) {
+                    self.report
+                        .error(path, format!("Boss phase uses the missing attack `{name}`"));
+                }
+            }
+            for clip in phase.animations.keys() {
+                if !fighter.spritesheet.animations.contains_key(clip) {
+                    self.report.warning(
+                        path,
+                        format!("Boss phase replaces the missing animation `{clip}`"),
+                    );
+                }
+            }
+            for minion in &phase.minions {
+                self.check_file(path, &minion.fighter);
+            }
+        }
+
+        self.check_file(path, &fighter.hud.portrait.image);
+        self.validate_spritesheet(path, &fighter.spritesheet);
+        if let Some(attachment) = &fighter.attachment {
+            self.validate_spritesheet(path, attachment);
+        }
+
+        for frame_effects in fighter.audio.effects.values() {
+            for effect in frame_effects.values() {
+                self.check_file(path, effect);
+            }
+        }
+
+        for file in fighter.sounds.iter().flat_map(|sound| &sound.files) {
+            self.check_file(path, file);
+        }
+
+        for attack in &fighter.attacks {
+            if let Some(item) = &attack.item {
+                self.check_file(path, item);
+            }
+            if let Some(projectile) = &attack.projectile {
+                self.check_file(path, &projectile.image.image);
+                if projectile.speed <= 0.0 || projectile.lifetime <= 0.0 {
+                    self.report.warning(
+                        path,
+                        format!(
+                            "The projectile of attack `{}` needs a speed and a lifetime",
+                            attack.name
+                        ),
+                    );
+                }
+            }
+
+            if let Some(ranged) = &attack.ranged {
+                if !RANGED_ATTACKS.contains(&attack.name.as_str()) {
+                    self.report.warning(
+                        path,
+                        format!(
+                            "Attack `{}` is ranged, but only `projectile` and `bomb_throw` attacks \
+                             keep a distance",
+                            attack.name
+                        ),
+                    );
+                }
+                if let Some(telegraph) = &ranged.telegraph {
+                    if !fighter.spritesheet.animations.contains_key(telegraph) {
+                        self.report.error(
+                            path,
+                            format!(
+                                "The telegraph animation `{telegraph}` isn't in the spritesheet"
+                            ),
+                        );
+                    }
+                }
+            }
+
+            let frames = &attack.frames;
+            if frames.startup > frames.active || frames.active > frames.recovery {
+                self.report.warning(
+                    path,
+                    format!(
+                        "Attack `{}` frames should be ordered startup <= active <= recovery",
+                        attack.name
+                    ),
+                );
+            }
+            if attack.name == "chain" && attack.combo.is_empty() {
+                self.report.error(
+                    path,
+                    "Attack `chain` must have at least one step in `combo`",
+                );
+            }
+            for step in &attack.combo {
+                if !fighter.spritesheet.animations.contains_key(&step.animation) {
+                    self.report.warning(
+                        path,
+                        format!(
+                            "Attack `{}` has a combo step with the missing animation `{}`",
+                            attack.name, step.animation
+                        ),
+                    );
+                }
+                if let Some(window) = step.cancel_window {
+                    if window.start > window.end {
+                        self.report.warning(
+                            path,
+                            format!(
+                                "The cancel window of combo step `{}` closes before it opens",
+                                step.animation
+                            ),
+                        );
+                    }
+                }
+            }
+            if let Some(effect) = &attack.status_effect {
+                self.validate_status_effect(path, effect);
+            }
+            let mut launches =
+                std::iter::once(attack.launch).chain(attack.combo.iter().filter_map(|s| s.launch));
+            if launches.any(|launch| launch < 0.0) {
+                self.report.warning(
+                    path,
+                    format!(
+                        "Attack `{}` can't launch fighters lower than the ground",
+                        attack.name
+                    ),
+                );
+            }
+            if let Some(afterimages) = &attack.afterimages {
+                if afterimages.lifetime <= 0.0 {
+                    self.report.warning(
+                        path,
+                        format!(
+                            "The afterimages of attack `{}` fade away as soon as they appear",
+                            attack.name
+                        ),
+                    );
+                }
+            }
+        }
+
+        if fighter.thrown.duration <= 0.0 {
+            self.report
+                .warning(path, "`thrown.duration` should be greater than zero");
+        }
+
+        let block = &fighter.block;
+        if !(0.0..=1.0).contains(&block.damage_reduction) {
+            self.report
+                .error(path, "`block.damage_reduction` must be between 0 and 1");
+        }
+        let block_animation = block.animation.as_str();
+        if !FIGHTER_ANIMATIONS.contains(&block_animation)
+            && !fighter.spritesheet.animations.contains_key(block_animation)
+        {
+            self.report.warning(
+                path,
+                format!("Fighter is missing the `{block_animation}` block animation"),
+            );
+        }
+
+        if fighter.stats.crit_multiplier < 1.0 {
+            self.report.warning(
+                path,
+                "`stats.crit_multiplier` is less than one, so critical hits deal less damage",
+            );
+        }
+
+        let dodge = &fighter.stats.dodge;
+        if dodge.duration <= 0.0 {
+            self.report
+                .error(path, "`stats.dodge.duration` must be greater than zero");
+        }
+        if dodge.cooldown < dodge.duration {
+            self.report.warning(
+                path,
+                "`stats.dodge.cooldown` is shorter than the dodge, so it can be chained forever",
+            );
+        }
+        if !fighter
+            .spritesheet
+            .animations
+            .contains_key(&dodge.animation)
+        {
+            self.report.warning(
+                path,
+                format!(
+                    "Fighter is missing the `{}` dodge animation",
+                    dodge.animation
+                ),
+            );
+        }
+
+        if let Some(knockdown) = &fighter.knockdown {
+            if knockdown.damage <= 0 {
+                self.report
+                    .error(path, "`knockdown.damage` must be more than 0");
+            }
+            if !(0.0..=1.0).contains(&knockdown.down_damage_scale) {
+                self.report.error(
+                    path,
+                    "`knockdown.down_damage_scale` must be between 0 and 1",
+                );
+            }
+            for animation in [&knockdown.animation, &knockdown.rise_animation] {
+                if !fighter.spritesheet.animations.contains_key(animation) {
+                    self.report.warning(
+                        path,
+                        format!("Fighter is missing the `{animation}` knockdown animation"),
+                    );
+                }
+            }
+        }
+
+        for animation in FIGHTER_ANIMATIONS {
+            if !fighter.spritesheet.animations.contains_key(*animation) {
+                self.report.warning(
+                    path,
+                    format!("Fighter is missing the `{animation}` animation"),
+                );
+            }
+        }
+    }
+
+    fn validate_item(&mut self, path: &Path) {
+        let Some(item) = self.load::<ItemMeta>(path) else {
+            return;
+        };
+
+        self.check_file(path, &item.image.image);
+
+        match &item.kind {
+            ItemKind::BreakableBox { item, .. } => self.check_file(path, item),
+            ItemKind::MeleeWeapon {
+                spritesheet, audio, ..
+            }
+            | ItemKind::ProjectileWeapon {
+                spritesheet, audio, ..
+            } => {
+                self.validate_spritesheet(path, spritesheet);
+                for frame_effects in audio.effects.values() {
+                    for effect in frame_effects.values() {
+                        self.check_file(path, effect);
+                    }
+                }
+            }
+            ItemKind::Script { script, .. } => self.check_file(path, script),
+            ItemKind::Bomb { spritesheet, .. } => self.validate_spritesheet(path, spritesheet),
+            ItemKind::Throwable { status_effect, .. } => {
+                if let Some(effect) = status_effect {
+                    self.validate_status_effect(path, effect);
+                }
+            }
+            ItemKind::Coating {
+                element, duration, ..
+            } => {
+                if *duration <= 0.0 {
+                    self.report
+                        .warning(path, "The coating wears off as soon as it's picked up");
+                }
+                if let Element::Fire { burn_time, .. } = element {
+                    if *burn_time < 0.0 {
+                        self.report
+                            .error(path, "The burn time of a coating can't be negative");
+                    }
+                }
+            }
+        }
+
+        if let ItemKind::MeleeWeapon {
+            durability: Some(0),
+            ..
+        } = &item.kind
+        {
+            self.report
+                .warning(path, "`durability` should be greater than zero");
+        }
+    }
+
+    fn validate_status_effect(&mut self, path: &Path, effect: &StatusEffectMeta) {
+        if effect.duration <= 0.0 {
+            self.report
+                .warning(path, "A status effect wears off as soon as it's put on");
+        }
+        match effect.kind {
+            StatusEffectKind::Burn { damage_per_second }
+            | StatusEffectKind::Poison {
+                damage_per_second, ..
+            } if damage_per_second < 0 => {
+                self.report
+                    .error(path, "The damage of a status effect can't be negative");
+            }
+            StatusEffectKind::Poison { slowdown, .. } if !(0.0..=1.0).contains(&slowdown) => {
+                self.report
+                    .error(path, "The slowdown of poison must be between 0 and 1");
+            }
+            _ => (),
+        }
+    }
+
+    fn validate_spritesheet(&mut self, path: &Path, spritesheet: &FighterSpritesheetMeta) {
+        for image in &spritesheet.image {
+            self.check_file(path, image);
+        }
+        if let Some(palette) = &spritesheet.palette {
+            self.check_file(path, palette);
+        }
+
+        let frame_count = spritesheet.columns * spritesheet.rows;
+        for (name, clip) in &spritesheet.animations {
+            if clip.frames.end >= frame_count {
+                self.report.error(
+                    path,
+                    format!(
+                        "Animation `{name}` ends at frame {}, but the spritesheet only has {frame_count} frames",
+                        clip.frames.end
+                    ),
+                );
+            }
+            if let Some(next) = &clip.next {
+                if !spritesheet.animations.contains_key(next) {
+                    self.report.error(
+                        path,
+                        format!("Animation `{name}` is followed by the missing animation `{next}`"),
+                    );
+                }
+            }
+            let last_frame = clip.frames.end.saturating_sub(clip.frames.start);
+            for frame in clip.events.keys().filter(|&&frame| frame > last_frame) {
+                self.report.warning(
+                    path,
+                    format!("Animation `{name}` has tags on frame {frame}, which it never reaches"),
+                );
+            }
+            for (frame, collider) in clip.hitboxes.iter().chain(&clip.hurtboxes) {
+                if *frame > last_frame {
+                    self.report.warning(
+                        path,
+                        format!(
+                            "Animation `{name}` has a box on frame {frame}, which it never reaches"
+                        ),
+                    );
+                }
+                if collider.size.min_element() <= 0.0 {
+                    self.report.error(
+                        path,
+                        format!("Animation `{name}` has an empty box on frame {frame}"),
+                    );
+                }
+            }
+        }
+    }
+}
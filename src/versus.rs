@@ -0,0 +1,345 @@
+//! Local versus mode, where the players fight each other in rounds.
+//!
+//! Versus is started from the main menu as 1v1 or 2v2, and is played in the arena of the game's
+//! [`VersusMeta`], which the players can't leave. There are no enemies, and the attacks of the
+//! players hit each other, teammates included. A round is won by knocking out the other team, or
+//! by having more health left when the round time runs out, and the first team to win
+//! [`rounds_to_win`](VersusMeta::rounds_to_win) rounds wins the match, which ends on the versus
+//! results screen.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    animation::Facing,
+    attack::AttackDamageSystem,
+    combat_events::{HitLanded, Killed},
+    damage::Health,
+    fighter::Stats,
+    metadata::{GameMeta, LevelHandle, LevelMeta, Settings, VersusMeta},
+    movement::{ForceSystems, LinearVelocity, VelocitySystems},
+    platform::Storage,
+    player::{Player, PlayerBundle, PlayerIndex},
+    timestep::{gameplay_stage, GAMEPLAY_STEP, GAMEPLAY_TIMESTEP},
+    GameState,
+};
+
+/// How long the result of a round is shown before the next round starts
+const ROUND_OVER_TIME: Duration = Duration::from_secs(3);
+
+/// The number of teams in a versus match
+pub const TEAM_COUNT: usize = 2;
+
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, end_versus)
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::PRE,
+                ConditionSet::new()
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<VersusMatch>()
+                    .with_system(respawn_players)
+                    .with_system(face_opponents)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::MOVEMENT,
+                ConditionSet::new()
+                    .after(ForceSystems)
+                    .before(VelocitySystems)
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<VersusMatch>()
+                    .with_system(lock_arena)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::COLLISIONS,
+                ConditionSet::new()
+                    .after(AttackDamageSystem)
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<VersusMatch>()
+                    .with_system(record_damage)
+                    .into(),
+            )
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                update_rounds
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<VersusMatch>(),
+            );
+    }
+}
+
+/// Start a versus match in the arena of the game's versus mode, with 2 players for 1v1 or 4 for
+/// 2v2
+pub fn start_versus(
+    commands: &mut Commands,
+    levels: &mut Assets<LevelMeta>,
+    versus: &VersusMeta,
+    player_count: usize,
+) {
+    let Some(level) = levels.get(&versus.level_handle) else {
+        warn!("The versus level hasn't loaded");
+        return;
+    };
+    if level.players.len() < player_count {
+        warn!(player_count, "The versus level doesn't have enough players");
+        return;
+    }
+    let mut level = level.clone();
+
+    level.players.truncate(player_count);
+    level.enemies.clear();
+    level.waves.clear();
+    level.streaming = None;
+    level.stop_points.clear();
+
+    commands.insert_resource(LevelHandle(levels.add(level)));
+    commands.insert_resource(VersusMatch::new(versus, player_count));
+    commands.insert_resource(NextState(GameState::LoadingLevel));
+}
+
+/// Get the team of a player, where the players take turns joining the teams
+pub fn team(player_index: usize) -> usize {
+    player_index % TEAM_COUNT
+}
+
+/// The state of a versus match, which exists while versus is played
+#[derive(Resource)]
+pub struct VersusMatch {
+    pub player_count: usize,
+    /// The number of the current round, starting at 1
+    pub round: u32,
+    pub state: RoundState,
+    /// The time left in the current round
+    pub round_timer: Timer,
+    /// The number of rounds won by each team
+    pub wins: [u32; TEAM_COUNT],
+    rounds_to_win: u32,
+    /// The damage dealt by each player over the match
+    pub damage_dealt: Vec<i32>,
+    /// The number of knockouts of each player over the match
+    pub knockouts: Vec<u32>,
+    /// Whether the players have to be spawned again for a new round
+    respawn_requested: bool,
+}
+
+/// What's happening in the current round
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundState {
+    Fighting,
+    /// The round is over, and was won by a team, or was a draw
+    Over {
+        winner: Option<usize>,
+    },
+    /// The match has been won by a team
+    MatchOver {
+        winner: usize,
+    },
+}
+
+impl VersusMatch {
+    pub fn new(meta: &VersusMeta, player_count: usize) -> Self {
+        Self {
+            player_count,
+            round: 1,
+            state: RoundState::Fighting,
+            round_timer: Timer::from_seconds(meta.round_time, TimerMode::Once),
+            wins: [0; TEAM_COUNT],
+            rounds_to_win: meta.rounds_to_win,
+            damage_dealt: vec![0; player_count],
+            knockouts: vec![0; player_count],
+            respawn_requested: false,
+        }
+    }
+
+    /// Start the match over with the same players
+    pub fn rematch(&mut self, meta: &VersusMeta) {
+        *self = Self {
+            respawn_requested: true,
+            ..Self::new(meta, self.player_count)
+        };
+    }
+}
+
+/// The winner of a round if it's over, given the health left of each team, as a fraction of their
+/// max health. `Some(None)` is a draw.
+fn round_result(team_health: [f32; TEAM_COUNT], time_up: bool) -> Option<Option<usize>> {
+    let [first, second] = team_health;
+    if first <= 0.0 || second <= 0.0 || time_up {
+        Some(match first.total_cmp(&second) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        })
+    } else {
+        None
+    }
+}
+
+fn end_versus(mut commands: Commands) {
+    commands.remove_resource::<VersusMatch>();
+}
+
+/// Spawn the players where the level spawns them, for a new round
+fn respawn_players(
+    mut commands: Commands,
+    mut versus: ResMut<VersusMatch>,
+    players: Query<Entity, With<Player>>,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    if !std::mem::take(&mut versus.respawn_requested) {
+        return;
+    }
+
+    for entity in &players {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let settings = storage.get(Settings::STORAGE_KEY);
+    for (i, player) in level.players.iter().enumerate() {
+        commands.spawn(PlayerBundle::new(player, i, &game, settings.as_ref()));
+    }
+}
+
+/// Turn the players of the second team towards the first one when they are spawned
+fn face_opponents(mut players: Query<(&PlayerIndex, &mut Facing), Added<Player>>) {
+    for (player_index, mut facing) in &mut players {
+        if team(player_index.0) == 1 {
+            *facing = Facing::Left;
+        }
+    }
+}
+
+/// Keep the players from walking or being knocked out of the arena
+fn lock_arena(
+    game: Res<GameMeta>,
+    mut players: Query<(&Transform, &mut LinearVelocity), With<Player>>,
+) {
+    let Some(versus) = &game.versus else {
+        return;
+    };
+    let half_width = versus.arena_width / 2.0;
+    let dt = GAMEPLAY_STEP.as_secs_f32();
+
+    for (transform, mut velocity) in &mut players {
+        let new_x = transform.translation.x + velocity.x * dt;
+        if (new_x < -half_width && velocity.x < 0.0) || (new_x > half_width && velocity.x > 0.0) {
+            velocity.x = 0.0;
+        }
+    }
+}
+
+/// Record the damage and knockouts of each player for the results screen
+fn record_damage(
+    mut versus: ResMut<VersusMatch>,
+    mut damage_events: EventReader<HitLanded>,
+    mut killed_events: EventReader<Killed>,
+    parents: Query<&Parent>,
+    players: Query<&PlayerIndex>,
+) {
+    // Melee attacks are children of the fighter making them
+    let player_count = versus.player_count;
+    let attacking_player = |attack: Entity| {
+        parents
+            .get(attack)
+            .ok()
+            .and_then(|parent| players.get(parent.get()).ok())
+            .map(|player_index| player_index.0)
+            .filter(|&player| player < player_count)
+    };
+
+    for event in damage_events.iter() {
+        if let Some(attacker) = attacking_player(event.damageing_entity) {
+            versus.damage_dealt[attacker] += event.damage;
+        }
+    }
+    for event in killed_events.iter() {
+        if !players.contains(event.killed_entity) {
+            continue;
+        }
+        if let Some(attacker) = attacking_player(event.killing_entity) {
+            versus.knockouts[attacker] += 1;
+        }
+    }
+}
+
+/// Work out when rounds are won, and move on to the next round or the results screen
+fn update_rounds(
+    mut versus: ResMut<VersusMatch>,
+    mut round_over_time: Local<Duration>,
+    players: Query<(&PlayerIndex, &Health, &Stats), With<Player>>,
+    loading_players: Query<(), (With<Player>, Without<Stats>)>,
+) {
+    let state = versus.state;
+    match state {
+        RoundState::Fighting => {
+            // Wait for the players of a new round to be loaded, instead of seeing them all knocked
+            // out
+            if !loading_players.is_empty() {
+                return;
+            }
+            versus.round_timer.tick(GAMEPLAY_STEP);
+
+            let mut team_health = [0.0; TEAM_COUNT];
+            for (player_index, health, stats) in &players {
+                team_health[team(player_index.0)] +=
+                    (**health).max(0) as f32 / stats.max_health as f32;
+            }
+
+            if let Some(winner) = round_result(team_health, versus.round_timer.finished()) {
+                info!(round = versus.round, ?winner, "Versus round over");
+                if let Some(winner) = winner {
+                    versus.wins[winner] += 1;
+                }
+                versus.state = RoundState::Over { winner };
+                *round_over_time = Duration::ZERO;
+            }
+        }
+        RoundState::Over { winner } => {
+            *round_over_time += GAMEPLAY_STEP;
+            if *round_over_time < ROUND_OVER_TIME {
+                return;
+            }
+
+            match winner.filter(|&winner| versus.wins[winner] >= versus.rounds_to_win) {
+                Some(winner) => {
+                    info!(wins = ?versus.wins, "Versus match won by team {}", winner + 1);
+                    versus.state = RoundState::MatchOver { winner };
+                }
+                None => {
+                    versus.round += 1;
+                    versus.round_timer.reset();
+                    versus.state = RoundState::Fighting;
+                    versus.respawn_requested = true;
+                }
+            }
+        }
+        RoundState::MatchOver { .. } => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rounds_are_won_by_knockout_or_health() {
+        assert_eq!(round_result([1.0, 0.5], false), None);
+        assert_eq!(round_result([0.2, 0.0], false), Some(Some(0)));
+        assert_eq!(round_result([0.0, 1.5], false), Some(Some(1)));
+        assert_eq!(round_result([0.0, 0.0], false), Some(None));
+        assert_eq!(round_result([0.4, 0.6], true), Some(Some(1)));
+        assert_eq!(round_result([0.5, 0.5], true), Some(None));
+    }
+}
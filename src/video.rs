@@ -0,0 +1,263 @@
+//! Applying the [`VideoSettings`] to the window.
+//!
+//! The window mode, resolution and monitor settings change the primary window when they are
+//! changed, so that a window that has been resized by hand keeps its size until they change again.
+//! The monitors and their video modes are listed in the [`Displays`] for the settings menu to pick
+//! from.
+//!
+//! The vsync setting changes the present mode of the primary window, and the frame rate limit is
+//! enforced by sleeping at the end of frames that finished early. The frame rate is also limited
+//! while the window is in the background, if the
+//! [`BackgroundSettings`](crate::metadata::BackgroundSettings) reduce it. The browser
+//! decides when to draw frames in the web build, so the frame rate limits aren't applied there.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::Instant,
+    window::{MonitorSelection, WindowId, WindowMode},
+    winit::WinitWindows,
+};
+use iyes_loopless::prelude::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{focus::WindowFocus, metadata::BackgroundSettings};
+use crate::{
+    metadata::{Resolution, Settings, VideoSettings},
+    GameState,
+};
+
+/// The shortest time that a frame may take while the window is in the background, if the
+/// [frame rate is reduced](BackgroundSettings::reduce_frame_rate) then
+#[cfg(not(target_arch = "wasm32"))]
+const BACKGROUND_FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 15);
+
+/// The resolutions that can be picked when the video modes of the monitor aren't known
+const COMMON_RESOLUTIONS: [Resolution; 5] = [
+    Resolution::new(3840, 2160),
+    Resolution::new(2560, 1440),
+    Resolution::new(1920, 1080),
+    Resolution::new(1600, 900),
+    Resolution::new(1280, 720),
+];
+
+pub struct VideoPlugin;
+
+impl Plugin for VideoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameRateLimiter>()
+            .init_resource::<Displays>()
+            // Look for monitors that have been plugged in since the menu was last shown
+            .add_enter_system(GameState::MainMenu, find_displays)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_video_settings.run_if_resource_exists::<Settings>(),
+            );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system_to_stage(CoreStage::Last, limit_frame_rate.at_end());
+    }
+}
+
+/// Keeps track of the frame times to limit the frame rate
+#[derive(Resource)]
+struct FrameRateLimiter {
+    /// The shortest time that a frame may take, if the frame rate is limited
+    frame_duration: Option<Duration>,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    last_frame_end: Instant,
+}
+
+impl Default for FrameRateLimiter {
+    fn default() -> Self {
+        Self {
+            frame_duration: None,
+            last_frame_end: Instant::now(),
+        }
+    }
+}
+
+/// The monitors that the game can be shown on
+#[derive(Resource, Default)]
+pub struct Displays {
+    /// The monitors, in the order of their indices in the [`VideoSettings`]
+    pub monitors: Vec<Monitor>,
+}
+
+impl Displays {
+    /// The resolutions that can be picked for the monitor, from the biggest to the smallest
+    pub fn resolutions(&self, monitor: usize) -> &[Resolution] {
+        self.monitors
+            .get(monitor)
+            .map(|monitor| monitor.resolutions.as_slice())
+            .filter(|resolutions| !resolutions.is_empty())
+            .unwrap_or(&COMMON_RESOLUTIONS)
+    }
+
+    /// The resolution that comes after `resolution` in the settings menu. The resolutions of the
+    /// monitor are cycled through from the biggest, and then back to no resolution.
+    pub fn next_resolution(
+        &self,
+        monitor: usize,
+        resolution: Option<Resolution>,
+    ) -> Option<Resolution> {
+        let resolutions = self.resolutions(monitor);
+        match resolution {
+            None => resolutions.first().copied(),
+            // A resolution that the monitor doesn't have goes back to no resolution too
+            Some(resolution) => {
+                let index = resolutions.iter().position(|other| *other == resolution)?;
+                resolutions.get(index + 1).copied()
+            }
+        }
+    }
+}
+
+pub struct Monitor {
+    pub name: String,
+    /// The resolutions of the video modes of the monitor, from the biggest to the smallest
+    pub resolutions: Vec<Resolution>,
+}
+
+fn find_displays(winit_windows: NonSend<WinitWindows>, mut displays: ResMut<Displays>) {
+    let Some(window) = winit_windows.get_window(WindowId::primary()) else {
+        return;
+    };
+
+    displays.monitors = window
+        .available_monitors()
+        .enumerate()
+        .map(|(index, monitor)| {
+            let mut resolutions = monitor
+                .video_modes()
+                .map(|video_mode| {
+                    let size = video_mode.size();
+                    Resolution::new(size.width, size.height)
+                })
+                .collect::<Vec<_>>();
+            // The video modes of the same size with other refresh rates are only listed once
+            resolutions.sort_unstable_by(|a, b| b.cmp(a));
+            resolutions.dedup();
+
+            Monitor {
+                name: monitor.name().unwrap_or_else(|| (index + 1).to_string()),
+                resolutions,
+            }
+        })
+        .collect();
+}
+
+/// Apply the video settings when they have changed
+fn apply_video_settings(
+    settings: Res<Settings>,
+    mut windows: ResMut<Windows>,
+    mut limiter: ResMut<FrameRateLimiter>,
+    mut applied_display: Local<Option<VideoSettings>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let video = &settings.video;
+
+    if let Some(window) = windows.get_primary_mut() {
+        let present_mode = video.present_mode();
+        if window.present_mode() != present_mode {
+            debug!(?present_mode, "Setting window present mode");
+            window.set_present_mode(present_mode);
+        }
+
+        apply_display_settings(window, video, &mut applied_display);
+    }
+
+    let frame_duration = video.frame_rate_limit.frame_duration();
+    if limiter.frame_duration != frame_duration {
+        debug!(fps = ?video.frame_rate_limit.fps(), "Setting frame rate limit");
+        limiter.frame_duration = frame_duration;
+    }
+}
+
+/// Change the mode, size and monitor of the window, if their settings have changed since they were
+/// last applied
+fn apply_display_settings(
+    window: &mut Window,
+    video: &VideoSettings,
+    applied: &mut Option<VideoSettings>,
+) {
+    let last = applied.replace(video.clone());
+    if last.as_ref().map_or(false, |last| {
+        last.window_mode == video.window_mode
+            && last.resolution == video.resolution
+            && last.monitor == video.monitor
+    }) {
+        return;
+    }
+
+    let mode = video.window_mode.window_mode(video.resolution);
+    debug!(?mode, resolution = ?video.resolution, monitor = video.monitor, "Setting window mode");
+
+    // The window starts out on the first monitor
+    let monitor_changed = last.map_or(0, |last| last.monitor) != video.monitor;
+    if monitor_changed {
+        // The window can only be moved to another monitor while it's windowed
+        window.set_mode(WindowMode::Windowed);
+        window.center_window(MonitorSelection::Index(video.monitor));
+    }
+    if let Some(resolution) = video.resolution {
+        window.set_resolution(resolution.width as f32, resolution.height as f32);
+    }
+    if monitor_changed || window.mode() != mode {
+        window.set_mode(mode);
+    }
+}
+
+/// Sleep until the frame has taken at least as long as the frame rate limit allows
+#[cfg(not(target_arch = "wasm32"))]
+fn limit_frame_rate(world: &mut World) {
+    let in_background = !**world.resource::<WindowFocus>()
+        && world.resource::<BackgroundSettings>().reduce_frame_rate;
+    let mut limiter = world.resource_mut::<FrameRateLimiter>();
+
+    let frame_duration = if in_background {
+        let frame_duration = limiter.frame_duration.unwrap_or_default();
+        Some(frame_duration.max(BACKGROUND_FRAME_DURATION))
+    } else {
+        limiter.frame_duration
+    };
+    if let Some(frame_duration) = frame_duration {
+        let elapsed = limiter.last_frame_end.elapsed();
+        if elapsed < frame_duration {
+            std::thread::sleep(frame_duration - elapsed);
+        }
+    }
+
+    limiter.last_frame_end = Instant::now();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolutions_cycle_back_to_none() {
+        let displays = Displays {
+            monitors: vec![Monitor {
+                name: "Monitor".to_owned(),
+                resolutions: vec![Resolution::new(1920, 1080), Resolution::new(1280, 720)],
+            }],
+        };
+
+        let first = displays.next_resolution(0, None);
+        assert_eq!(first, Some(Resolution::new(1920, 1080)));
+        let second = displays.next_resolution(0, first);
+        assert_eq!(second, Some(Resolution::new(1280, 720)));
+        assert_eq!(displays.next_resolution(0, second), None);
+        assert_eq!(
+            displays.next_resolution(0, Some(Resolution::new(800, 600))),
+            None
+        );
+
+        // Monitors that aren't known have the common resolutions
+        assert_eq!(displays.resolutions(1), COMMON_RESOLUTIONS);
+    }
+}
@@ -0,0 +1,167 @@
+//! Waves of enemies that ambush the players as they move through a level.
+//!
+//! Once the camera gets to one of the [waves](crate::metadata::LevelMeta::waves) of the level, its
+//! enemies come in from just off the edges of the screen, and the camera is held in place until
+//! they have all been defeated. While a wave is fought, the players are held back like at a stop
+//! point, at the distance from the camera that it would start following them at.
+
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    camera::camera_half_width,
+    enemy::{Boss, Enemy, EnemyBundle},
+    metadata::{FighterSpawnMeta, GameMeta, LevelMeta, WaveEnemyMeta, WaveSide},
+    timestep::{gameplay_stage, GAMEPLAY_TIMESTEP},
+    utils::ResetEvent,
+    GameState,
+};
+
+/// How far outside of the screen the enemies of a wave are spawned
+const SPAWN_MARGIN: f32 = 40.0;
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Waves>()
+            .add_fixed_timestep_system(
+                GAMEPLAY_TIMESTEP,
+                gameplay_stage::POST,
+                update_waves.run_in_state(GameState::InGame),
+            )
+            .add_exit_system(GameState::InGame, clear_waves)
+            .add_system(clear_waves_on_reset);
+    }
+}
+
+/// The progress of the players through the waves of the level
+#[derive(Resource, Default, Debug)]
+pub struct Waves {
+    /// The index of the next wave of the level to start
+    next: usize,
+    /// The wave being fought, if the players are in one
+    active: Option<ActiveWave>,
+}
+
+#[derive(Debug)]
+struct ActiveWave {
+    /// Where the camera is held while the wave is fought
+    camera_x: f32,
+    /// The enemies of the wave that haven't been defeated yet
+    enemies: Vec<Entity>,
+}
+
+impl Waves {
+    /// Where the camera is held in place, if a wave is being fought
+    pub fn camera_lock(&self) -> Option<f32> {
+        self.active.as_ref().map(|wave| wave.camera_x)
+    }
+}
+
+/// Start the waves of the level as the camera gets to them, and let the camera move on once they
+/// have been defeated
+fn update_waves(
+    mut commands: Commands,
+    mut waves: ResMut<Waves>,
+    level: Res<LevelMeta>,
+    game: Res<GameMeta>,
+    windows: Res<Windows>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    if level.is_changed() {
+        *waves = default();
+    }
+
+    if let Some(wave) = &mut waves.active {
+        wave.enemies.retain(|&enemy| enemies.contains(enemy));
+        if !wave.enemies.is_empty() {
+            return;
+        }
+        debug!(camera_x = wave.camera_x, "Wave defeated");
+        waves.active = None;
+    }
+
+    let Some(wave) = level.waves.get(waves.next) else {
+        return;
+    };
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let camera_x = camera_transform.translation.x;
+    if camera_x < wave.camera_x {
+        return;
+    }
+    debug!(camera_x = wave.camera_x, "Starting wave");
+
+    let spawn_distance = camera_half_width(&game, windows.primary()) + SPAWN_MARGIN;
+    let enemies = wave
+        .enemies
+        .iter()
+        .map(|enemy| {
+            let mut ec = commands.spawn(EnemyBundle::new(&FighterSpawnMeta {
+                fighter: enemy.fighter.clone(),
+                fighter_handle: enemy.fighter_handle.clone(),
+                location: Vec3::new(spawn_x(enemy, camera_x, spawn_distance), enemy.y, 0.0),
+                // The enemies of the wave go after the players right away
+                trip_point_x: f32::MIN,
+                boss: enemy.boss,
+            }));
+            if enemy.boss {
+                ec.insert(Boss::default());
+            }
+            ec.id()
+        })
+        .collect();
+
+    waves.next += 1;
+    waves.active = Some(ActiveWave { camera_x, enemies });
+}
+
+/// Where an enemy of a wave is spawned, just off the edge of the screen that it comes in from
+fn spawn_x(enemy: &WaveEnemyMeta, camera_x: f32, spawn_distance: f32) -> f32 {
+    match enemy.side {
+        WaveSide::Left => camera_x - spawn_distance,
+        WaveSide::Right => camera_x + spawn_distance,
+    }
+}
+
+fn clear_waves(mut waves: ResMut<Waves>) {
+    *waves = default();
+}
+
+/// Start the waves over when the level is reset, since their enemies are gone
+fn clear_waves_on_reset(mut reset_events: EventReader<ResetEvent>, waves: ResMut<Waves>) {
+    if reset_events.iter().any(|reset| reset.scope.resets_level()) {
+        clear_waves(waves);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enemies_come_in_from_off_the_screen() {
+        let enemy = |side| WaveEnemyMeta {
+            fighter: default(),
+            fighter_handle: default(),
+            side,
+            y: 0.0,
+            boss: false,
+        };
+
+        assert_eq!(spawn_x(&enemy(WaveSide::Left), 500.0, 400.0), 100.0);
+        assert_eq!(spawn_x(&enemy(WaveSide::Right), 500.0, 400.0), 900.0);
+
+        let mut waves = Waves::default();
+        assert_eq!(waves.camera_lock(), None);
+        waves.active = Some(ActiveWave {
+            camera_x: 500.0,
+            enemies: Vec::new(),
+        });
+        assert_eq!(waves.camera_lock(), Some(500.0));
+    }
+}